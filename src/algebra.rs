@@ -0,0 +1,199 @@
+//! Set operations on the links of two saved graphs, such as the links present in one wiki but
+//! absent from another. Nodes are matched up by title, or, for graphs of wikis in different
+//! languages, by a shared ID such as the Wikidata item of each page, read from a CSV file.
+//! Links through redirects are compared, and kept, as links to the page redirected to.
+
+use crate::{
+    graph::{Adjacency as _, Graph, Metadata},
+    workspace, Wiki,
+};
+use anyhow::Context as _;
+use lasso::Rodeo;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Operation {
+    /// Links in either graph
+    Union,
+    /// Links in both graphs, between pages in both
+    Intersect,
+    /// Links of the first graph that the second lacks, between all of the first's pages
+    Subtract,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(value_enum)]
+    operation: Operation,
+
+    /// First graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    left: PathBuf,
+
+    /// Second graph file saved by `parse --graph`, or its name in the workspace
+    #[arg(value_parser = workspace::graph_path)]
+    right: PathBuf,
+
+    /// Where to save the resulting graph
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// CSV with a `title` and an `id` (or `qid`) column giving pages of the first graph an ID to
+    /// match them up by instead of their title; pages it doesn't list are matched by title
+    #[arg(long, value_name = "FILE")]
+    left_ids: Option<PathBuf>,
+
+    /// The same for the second graph
+    #[arg(long, value_name = "FILE")]
+    right_ids: Option<PathBuf>,
+
+    /// Leave the backlinks out of the resulting graph, as with `parse --no-backlinks`
+    #[arg(long)]
+    no_backlinks: bool,
+}
+
+#[derive(Deserialize)]
+struct IdRecord {
+    title: String,
+    #[serde(alias = "qid")]
+    id: String,
+}
+
+/// A graph with the key each of its nodes is matched up by.
+struct Side<'a> {
+    graph: &'a Graph,
+    keys: Vec<String>,
+}
+
+impl<'a> Side<'a> {
+    fn new(graph: &'a Graph, ids: Option<&HashMap<String, String>>) -> Self {
+        let keys = (0..u32::try_from(graph.node_count()).unwrap())
+            .map(|node| {
+                let title = graph.title(node);
+                String::from(
+                    ids.and_then(|ids| ids.get(title))
+                        .map_or(title, String::as_str),
+                )
+            })
+            .collect();
+        Self { graph, keys }
+    }
+
+    /// Nodes that are pages or missing pages rather than redirects.
+    fn nodes(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..u32::try_from(self.graph.node_count()).unwrap())
+            .filter(|&node| self.graph.redirect(node).is_none())
+    }
+
+    fn key(&self, node: u32) -> &str {
+        &self.keys[node as usize]
+    }
+
+    /// Every link as the keys of its ends, with redirects followed.
+    fn links(&self) -> HashSet<(&str, &str)> {
+        self.nodes()
+            .flat_map(|source| {
+                self.graph.links(source).iter().map(move |&target| {
+                    (
+                        self.key(source),
+                        self.key(self.graph.resolve_redirect(target)),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+pub fn run(args: &Args) {
+    let load = |path: &Path| {
+        Graph::load(path)
+            .with_context(|| format!("Failed to load {}", path.display()))
+            .unwrap()
+    };
+    let ids = |path: &Option<PathBuf>| {
+        path.as_deref().map(|path| {
+            read_ids(path)
+                .with_context(|| format!("Failed to read {}", path.display()))
+                .unwrap()
+        })
+    };
+    let (left, right) = (load(&args.left), load(&args.right));
+    let (left_ids, right_ids) = (ids(&args.left_ids), ids(&args.right_ids));
+    let left = Side::new(&left, left_ids.as_ref());
+    let right = Side::new(&right, right_ids.as_ref());
+
+    let (rodeo, wiki) = combine(args.operation, &left, &right);
+    let mut graph = Graph::new(&rodeo, &wiki, Metadata::current(None))
+        .context("Failed to build graph")
+        .unwrap();
+    graph.set_store_backlinks(!args.no_backlinks);
+    graph
+        .save(&args.output)
+        .context("Failed to save graph")
+        .unwrap();
+
+    println!(
+        "{} nodes and {} links",
+        graph.node_count(),
+        graph.edge_count()
+    );
+}
+
+/// The titles of `path` with the ID each is to be matched up by.
+fn read_ids(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let mut ids = HashMap::new();
+    for record in csv::Reader::from_path(path)?.deserialize() {
+        let record: IdRecord = record?;
+        ids.insert(record.title, record.id);
+    }
+    Ok(ids)
+}
+
+/// The graph `operation` makes of `left` and `right`, with the titles of `left` for nodes in
+/// both, and node IDs in the order of `left` and then `right`.
+fn combine(operation: Operation, left: &Side, right: &Side) -> (Rodeo, Wiki) {
+    let right_keys: HashSet<&str> = right.nodes().map(|node| right.key(node)).collect();
+    let left_links = left.links();
+    let right_links = right.links();
+    let links: Vec<(&str, &str)> = match operation {
+        Operation::Union => left_links.union(&right_links).copied().collect(),
+        Operation::Intersect => left_links.intersection(&right_links).copied().collect(),
+        Operation::Subtract => left_links.difference(&right_links).copied().collect(),
+    };
+
+    let mut rodeo = Rodeo::new();
+    let mut wiki = Wiki::default();
+    let mut nodes = HashMap::new();
+    let sides = match operation {
+        Operation::Union => vec![left, right],
+        Operation::Intersect | Operation::Subtract => vec![left],
+    };
+    for side in sides {
+        for node in side.nodes() {
+            let key = side.key(node);
+            if matches!(operation, Operation::Intersect) && !right_keys.contains(key) {
+                continue;
+            }
+            nodes.entry(key).or_insert_with(|| {
+                let spur = rodeo.get_or_intern(side.graph.title(node));
+                if let Some(kind) = side.graph.kind(node) {
+                    wiki.navigation.insert(spur, kind);
+                }
+                spur
+            });
+        }
+    }
+
+    for (source, target) in links {
+        // Links into a loop of redirects lead to no page.
+        let (Some(&source), Some(&target)) = (nodes.get(source), nodes.get(target)) else {
+            continue;
+        };
+        wiki.links.entry(source).or_default().insert(target);
+    }
+    (rodeo, wiki)
+}
@@ -0,0 +1,100 @@
+//! The "anchor dictionary": how often each anchor text links to each target, dump-wide, and the
+//! resulting probability of a target given an anchor. Or, page by page, the anchor texts each page
+//! links to each target with, for surface forms that depend on where they are written.
+
+use crate::{plaintext::strip_markup, profile::Profile, wikilink::links};
+use lasso::{Rodeo, Spur};
+use std::{collections::HashMap, fs::File, path::Path};
+
+#[derive(Default)]
+pub struct AnchorStats {
+    counts: HashMap<String, HashMap<Spur, u64>>,
+}
+
+impl AnchorStats {
+    pub fn add(&mut self, anchor: String, target: Spur) {
+        *self
+            .counts
+            .entry(anchor)
+            .or_default()
+            .entry(target)
+            .or_default() += 1;
+    }
+
+    /// Write `anchor,target,count,probability` rows as CSV, ordered by anchor and then by
+    /// descending count.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn write(&self, path: &Path, rodeo: &Rodeo) -> anyhow::Result<()> {
+        let mut anchors: Vec<&String> = self.counts.keys().collect();
+        anchors.sort_unstable();
+
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["anchor", "target", "count", "probability"])?;
+        for anchor in anchors {
+            let targets = &self.counts[anchor];
+            let total: u64 = targets.values().sum();
+            let mut targets: Vec<(&str, u64)> = targets
+                .iter()
+                .map(|(target, count)| (rodeo.resolve(target), *count))
+                .collect();
+            targets.sort_unstable_by(|(a, m), (b, n)| n.cmp(m).then(a.cmp(b)));
+            for (target, count) in targets {
+                let probability = count as f64 / total as f64;
+                writer.write_record([
+                    anchor.as_str(),
+                    target,
+                    count.to_string().as_str(),
+                    format!("{probability:.6}").as_str(),
+                ])?;
+            }
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// `source,target,anchor,count` rows as CSV, written as pages are parsed.
+pub struct Writer {
+    inner: csv::Writer<File>,
+}
+
+impl Writer {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut inner = csv::Writer::from_path(path)?;
+        inner.write_record(["source", "target", "anchor", "count"])?;
+        Ok(Self { inner })
+    }
+
+    /// Write a row for each distinct target and anchor of the links of the page titled `source`,
+    /// whose banner-stripped wikitext is `text`, in the order first linked, with how many of its
+    /// links they are.
+    pub fn add_page(&mut self, profile: &Profile, source: &str, text: &str) -> anyhow::Result<()> {
+        let links: Vec<_> = links(text)
+            .filter_map(|link| {
+                let target = profile.resolve(source, link.target)?;
+                Some((target, strip_markup(link.anchor)))
+            })
+            .collect();
+        let mut counts: Vec<(&str, &str, u64)> = Vec::new();
+        let mut positions: HashMap<(&str, &str), usize> = HashMap::new();
+        for (target, anchor) in &links {
+            let (target, anchor) = (target.as_ref(), anchor.as_str());
+            let position = *positions.entry((target, anchor)).or_insert_with(|| {
+                counts.push((target, anchor, 0));
+                counts.len() - 1
+            });
+            counts[position].2 += 1;
+        }
+        for (target, anchor, count) in counts {
+            self.inner
+                .write_record([source, target, anchor, count.to_string().as_str()])?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.inner.flush()?;
+        Ok(())
+    }
+}
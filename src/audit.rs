@@ -0,0 +1,73 @@
+//! A record of every title the parser rewrote, and why, for debugging why two nodes were or
+//! weren't merged.
+
+use crate::normalize::Stage;
+use std::{collections::HashMap, path::Path};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Reason {
+    /// A relative subpage link (`/Chapter`, `../Sibling`) made absolute.
+    Subpage,
+    /// The leading colon of `[[:Category:Birds]]`, which links a page instead of categorizing
+    /// or embedding.
+    LeadingColon,
+    /// A redirect page, which points at its target.
+    Redirect,
+    /// A normalization stage that changed the title.
+    Stage(Stage),
+}
+
+impl Reason {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Subpage => "subpage",
+            Self::LeadingColon => "leading_colon",
+            Self::Redirect => "redirect",
+            Self::Stage(stage) => stage.name(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Audit {
+    /// Count and first page seen on, per distinct rewrite.
+    rewrites: HashMap<(String, String, Reason), (u64, String)>,
+}
+
+impl Audit {
+    /// Record that `original`, found on `page`, became `canonical`.
+    pub fn add(&mut self, page: &str, original: &str, canonical: &str, reason: Reason) {
+        self.rewrites
+            .entry((String::from(original), String::from(canonical), reason))
+            .or_insert_with(|| (0, String::from(page)))
+            .0 += 1;
+    }
+
+    /// Add the rewrites recorded in `other`, keeping the first page of those already seen.
+    pub fn merge(&mut self, other: Self) {
+        for (rewrite, (count, page)) in other.rewrites {
+            self.rewrites.entry(rewrite).or_insert((0, page)).0 += count;
+        }
+    }
+
+    /// Write `original,canonical,reason,count,first_page` rows as CSV, ordered by original.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut rewrites: Vec<_> = self.rewrites.iter().collect();
+        rewrites.sort_unstable_by_key(|&(key, _)| key);
+
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["original", "canonical", "reason", "count", "first_page"])?;
+        for ((original, canonical, reason), (count, page)) in rewrites {
+            writer.write_record([
+                original.as_str(),
+                canonical,
+                reason.name(),
+                count.to_string().as_str(),
+                page,
+            ])?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
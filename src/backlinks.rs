@@ -0,0 +1,84 @@
+//! What links here: the pages linking to a page of a saved graph, listed the way MediaWiki's
+//! Special:WhatLinksHere lists them, optionally with the pages linking to its redirects.
+
+use crate::{
+    graph::{mmap::MmapGraph, Adjacency, Graph},
+    path, workspace,
+};
+use anyhow::Context as _;
+use std::path::PathBuf;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Title of the page, found as `path` finds pages; a redirect means the page it redirects to
+    title: String,
+
+    /// Also list the redirects to the page, each followed by the pages linking to it, indented
+    #[arg(long)]
+    through_redirects: bool,
+
+    /// Only print how many pages link to the page, through redirects too with
+    /// `--through-redirects`
+    #[arg(long)]
+    count: bool,
+
+    /// Map the graph file into memory instead of loading it, as with `path --mmap`
+    #[arg(long)]
+    mmap: bool,
+}
+
+pub fn run(args: &Args) {
+    if args.mmap {
+        let graph = MmapGraph::open(&args.graph)
+            .context("Failed to map graph")
+            .unwrap();
+        list(&graph, args);
+    } else {
+        let graph = Graph::load(&args.graph)
+            .context("Failed to load graph")
+            .unwrap();
+        list(&graph, args);
+    }
+}
+
+fn list(graph: &impl Adjacency, args: &Args) {
+    let page = path::find_or_exit(graph, &args.title);
+    let redirects: Vec<u32> = if args.through_redirects {
+        (0..u32::try_from(graph.node_count()).unwrap())
+            .filter(|&node| node != page && graph.resolve_redirect(node) == page)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    // Redirects are listed on their own, rather than as pages linking to the page.
+    let direct: Vec<u32> = graph
+        .backlinks(page)
+        .iter()
+        .copied()
+        .filter(|node| !redirects.contains(node))
+        .collect();
+
+    if args.count {
+        let mut sources: Vec<u32> = direct.clone();
+        for &redirect in &redirects {
+            sources.extend(graph.backlinks(redirect));
+        }
+        sources.sort_unstable();
+        sources.dedup();
+        println!("{}", sources.len());
+        return;
+    }
+    for &source in &direct {
+        println!("{}", graph.title(source));
+    }
+    for &redirect in &redirects {
+        println!("{} (redirect page)", graph.title(redirect));
+        for &source in graph.backlinks(redirect) {
+            println!("\t{}", graph.title(source));
+        }
+    }
+}
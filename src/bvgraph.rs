@@ -0,0 +1,238 @@
+//! `bvgraph`: graph files with their links compressed (see `graph::bv`), for wikis whose graph
+//! doesn't fit in memory as it is, and searches and PageRank that decode the links as they go.
+//! Commands that take a graph file also take a compressed one, decompressing it as they load it.
+
+use crate::{
+    graph::{
+        bv::{BvGraph, Params},
+        Graph,
+    },
+    stats, workspace,
+};
+use anyhow::Context as _;
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Compress the links of a saved graph, leaving out link weights, Wikidata items, and
+    /// stored statistics
+    Compress {
+        /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+        #[arg(value_parser = workspace::graph_path)]
+        graph: PathBuf,
+
+        /// Compressed graph file to write
+        output: PathBuf,
+
+        /// How many of the lists before a page's its list can copy links from, or 0 for none
+        #[arg(long, value_name = "N", default_value_t = Params::default().window)]
+        window: u32,
+
+        /// Longest chain of lists copying from each other, trading compression for the time
+        /// to decode a page's links
+        #[arg(long, value_name = "N", default_value_t = Params::default().max_ref_count)]
+        max_ref_count: u32,
+
+        /// Fewest consecutive pages linked to that are written as a range
+        #[arg(long, value_name = "N", default_value_t = Params::default().min_interval)]
+        min_interval: u32,
+    },
+    /// Write a compressed graph as an ordinary graph file
+    Decompress {
+        /// Compressed graph file written by `bvgraph compress`
+        graph: PathBuf,
+
+        /// Graph file to write
+        output: PathBuf,
+    },
+    /// Count the pages at each distance from a page, following links
+    Bfs {
+        /// Compressed graph file written by `bvgraph compress`
+        graph: PathBuf,
+
+        /// Title of the page to start from, with spaces or underscores between words
+        title: String,
+
+        /// Stop at pages this many links away
+        #[arg(long, value_name = "N")]
+        max_depth: Option<u32>,
+    },
+    /// Print the top pages by PageRank, computed as `pagerank` computes it
+    Pagerank {
+        /// Compressed graph file written by `bvgraph compress`
+        graph: PathBuf,
+
+        /// Number of power iterations
+        #[arg(long, value_name = "N", default_value_t = stats::ITERATIONS)]
+        iterations: u32,
+
+        /// Probability of following a link rather than jumping to a random page
+        #[arg(long, default_value_t = stats::DAMPING)]
+        damping: f64,
+
+        /// Number of top-ranked pages to print
+        #[arg(long, value_name = "K", default_value_t = 10)]
+        top: usize,
+    },
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub fn run(args: &Args) {
+    match &args.command {
+        Command::Compress {
+            graph,
+            output,
+            window,
+            max_ref_count,
+            min_interval,
+        } => {
+            let graph = Graph::load(graph).context("Failed to load graph").unwrap();
+            if graph.is_weighted() || graph.has_wikidata_items() || graph.stats().is_some() {
+                tracing::warn!(
+                    "Leaving out the link weights, Wikidata items, and statistics of the graph"
+                );
+            }
+            let params = Params {
+                window: *window,
+                max_ref_count: *max_ref_count,
+                min_interval: *min_interval,
+            };
+            let compressed = BvGraph::from_graph(&graph, params)
+                .context("Failed to compress graph")
+                .unwrap();
+            compressed
+                .save(output)
+                .with_context(|| format!("Failed to write {}", output.display()))
+                .unwrap();
+            let size = fs::metadata(output)
+                .context("Failed to read size of compressed graph")
+                .unwrap()
+                .len();
+            println!(
+                "{} pages, {} links: {size} bytes, of which links {} bytes ({:.2} bits a link)",
+                compressed.node_count(),
+                compressed.edge_count(),
+                compressed.link_bytes(),
+                compressed.link_bytes() as f64 * 8.0 / compressed.edge_count().max(1) as f64,
+            );
+        }
+        Command::Decompress { graph, output } => {
+            let graph = load(graph).to_graph();
+            graph
+                .save(output)
+                .with_context(|| format!("Failed to write {}", output.display()))
+                .unwrap();
+        }
+        Command::Bfs {
+            graph,
+            title,
+            max_depth,
+        } => bfs(&load(graph), title, *max_depth),
+        Command::Pagerank {
+            graph,
+            iterations,
+            damping,
+            top,
+        } => {
+            let graph = load(graph);
+            let scores = pagerank(&graph, *damping, *iterations);
+            for (node, score) in stats::top_scores(&scores, *top) {
+                println!("{score:.6}\t{}", graph.title(node));
+            }
+        }
+    }
+}
+
+fn load(path: &Path) -> BvGraph {
+    BvGraph::load(path)
+        .context("Failed to load compressed graph")
+        .unwrap()
+}
+
+/// Print how many pages are at each distance from `title`, decoding the links of each page as
+/// it is reached.
+fn bfs(graph: &BvGraph, title: &str, max_depth: Option<u32>) {
+    let title = title.replace('_', " ");
+    let Some(mut start) = graph.id(&title) else {
+        eprintln!("No page titled '{title}'");
+        std::process::exit(1);
+    };
+    if let Some(target) = graph.redirect(start) {
+        tracing::info!("'{title}' redirects to '{}'", graph.title(target));
+        start = target;
+    }
+
+    let mut distances = vec![u32::MAX; graph.node_count()];
+    distances[start as usize] = 0;
+    let mut queue = VecDeque::from([start]);
+    let mut counts = vec![1_u64];
+    let mut links = Vec::new();
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[node as usize] + 1;
+        if max_depth.is_some_and(|max_depth| distance > max_depth) {
+            continue;
+        }
+        graph.links_into(node, &mut links);
+        for &target in &links {
+            if distances[target as usize] == u32::MAX {
+                distances[target as usize] = distance;
+                if counts.len() <= distance as usize {
+                    counts.push(0);
+                }
+                counts[distance as usize] += 1;
+                queue.push_back(target);
+            }
+        }
+    }
+    for (distance, count) in counts.iter().enumerate() {
+        println!("{distance}\t{count}");
+    }
+    println!(
+        "{} of {} pages reached",
+        counts.iter().sum::<u64>(),
+        graph.node_count()
+    );
+}
+
+/// PageRank as `stats::pagerank_scores` computes it, with the scores of pages without links
+/// spread over every page, but pushed along the links of each page in turn, since a compressed
+/// graph has no backlinks to pull them over.
+#[allow(clippy::cast_precision_loss)]
+fn pagerank(graph: &BvGraph, damping: f64, iterations: u32) -> Vec<f64> {
+    let n = graph.node_count();
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut scores = vec![1.0 / n as f64; n];
+    let mut inflow = vec![0.0; n];
+    for _ in 0..iterations {
+        inflow.fill(0.0);
+        let mut dangling = 0.0;
+        graph.for_each_list(|node, links| {
+            let score = scores[node as usize];
+            if links.is_empty() {
+                dangling += score;
+                return;
+            }
+            let share = score / links.len() as f64;
+            for &target in links {
+                inflow[target as usize] += share;
+            }
+        });
+        let base = (1.0 - damping + damping * dangling) / n as f64;
+        for (score, inflow) in scores.iter_mut().zip(&inflow) {
+            *score = base + damping * inflow;
+        }
+    }
+    scores
+}
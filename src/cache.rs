@@ -0,0 +1,412 @@
+//! Caches the parsed graph of a dump, so that reruns which only change exports, filters, or
+//! analysis parameters skip parsing. Reading the dump, normalizing and resolving titles,
+//! assigning IDs, and building the link sets happen in one streaming pass, so the cache holds
+//! that pass's result; everything after it runs from the cache.
+//!
+//! A cache file starts with a line recording everything the parse depended on, and is only
+//! reused if all of it is unchanged.
+
+use crate::{
+    edge_type, guard, link_class, navigation, normalize, profile::Project, remote, script,
+    shard::Shard, variant, ParseArgs, Wiki,
+};
+use anyhow::Context as _;
+use lasso::{Rodeo, Spur};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead as _, BufReader, BufWriter, Write},
+    iter,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// What a parse depends on besides the code.
+#[derive(Serialize, Deserialize, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Inputs {
+    version: String,
+    dump: PathBuf,
+    size: u64,
+    modified_nanos: u128,
+    project: String,
+    namespaces: Vec<i64>,
+    link_rules: Option<String>,
+    no_template_links: bool,
+    siteinfo: bool,
+    script: Option<String>,
+    /// The pages of `--skip-list`, which a parse can add to.
+    skip_list: Option<String>,
+    normalize: Vec<normalize::Stage>,
+    variants: Option<variant::Language>,
+    variant_rules: Option<String>,
+    link_classes: Vec<link_class::LinkClass>,
+    edge_types: Vec<edge_type::EdgeType>,
+    /// Those of `--disambiguation-templates`, or of the wiki's language.
+    disambiguation_templates: Vec<String>,
+    exclude_disambiguation: bool,
+    /// Whether the parse writes a partial result, which leaves disambiguation pages in for
+    /// `merge` to exclude.
+    partial: bool,
+    link_offsets: bool,
+    link_origins: bool,
+    edge_timestamps: bool,
+    edge_weights: bool,
+    max_page_bytes: Option<usize>,
+    oversized: guard::Oversized,
+    max_links: Option<usize>,
+    edge_sample: Option<f64>,
+    skip_pages: Option<u64>,
+    max_pages: Option<u64>,
+    sample: Option<f64>,
+    seed: u64,
+    shard: Option<Shard>,
+}
+
+impl Inputs {
+    pub fn new(dump: &Path, project: Project, args: &ParseArgs) -> anyhow::Result<Self> {
+        let variants = crate::variants(args, dump);
+        let disambiguation_templates = crate::disambiguation_templates(args, dump);
+        // Standard input and URLs have no file to identify them by, and aren't cached.
+        let (dump, size, modified_nanos) = if dump == Path::new("-") || remote::is_url(dump) {
+            (dump.to_path_buf(), 0, 0)
+        } else {
+            let metadata = fs::metadata(dump)?;
+            (
+                fs::canonicalize(dump)?,
+                metadata.len(),
+                metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos(),
+            )
+        };
+        let read = |path: &Option<PathBuf>| {
+            path.as_deref()
+                .map(|path| {
+                    fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read {}", path.display()))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            dump,
+            size,
+            modified_nanos,
+            project: String::from(
+                clap::ValueEnum::to_possible_value(&project)
+                    .context("Unnamed project")?
+                    .get_name(),
+            ),
+            namespaces: args.namespaces.clone(),
+            link_rules: read(&args.link_rules)?,
+            no_template_links: args.no_template_links,
+            siteinfo: args.siteinfo,
+            script: read(&args.script)?,
+            skip_list: args
+                .skip_list
+                .as_deref()
+                .map(|path| fs::read_to_string(path).unwrap_or_default()),
+            normalize: args.normalization(),
+            variants,
+            variant_rules: read(&args.variant_rules)?,
+            link_classes: args.link_classes.clone(),
+            edge_types: args.edge_types.clone(),
+            disambiguation_templates,
+            exclude_disambiguation: args.exclude_disambiguation,
+            partial: args.partial.is_some(),
+            link_offsets: args.link_offsets,
+            link_origins: args.link_origins,
+            edge_timestamps: args.edge_timestamps,
+            edge_weights: args.edge_weights,
+            max_page_bytes: args.max_page_bytes,
+            oversized: args.oversized,
+            max_links: args.max_links,
+            edge_sample: args.edge_sample,
+            skip_pages: args.skip_pages,
+            max_pages: args.max_pages,
+            sample: args.sample,
+            seed: args.seed,
+            shard: args.shard,
+        })
+    }
+
+    /// Whether links to redirects were pointed at the pages they lead to.
+    pub fn resolves_redirects(&self) -> bool {
+        self.normalize.contains(&normalize::Stage::ResolveRedirects)
+    }
+
+    pub fn excludes_disambiguation(&self) -> bool {
+        self.exclude_disambiguation
+    }
+}
+
+/// The cache file for `dump`, or one shard of it, in `dir`.
+pub fn path_for(dir: &Path, dump: &Path, shard: Option<Shard>) -> PathBuf {
+    let name = dump.file_name().unwrap_or(dump.as_os_str());
+    let mut name = name.to_os_string();
+    if let Some(shard) = shard {
+        name.push(format!(".shard-{}", shard.to_string().replace('/', "-of-")));
+    }
+    name.push(".wiki.json");
+    dir.join(name)
+}
+
+/// A `Wiki` with titles replaced by indexes into `titles`.
+#[derive(Serialize, Deserialize)]
+struct Artifact {
+    titles: Vec<String>,
+    links: Vec<(u32, Vec<u32>)>,
+    redirects: Vec<(u32, u32)>,
+    sort_keys: Vec<(u32, String)>,
+    categories: Vec<(String, u32, Option<String>)>,
+    namespaces: Vec<(u32, i64)>,
+    #[serde(default)]
+    page_ids: Vec<(u32, u64)>,
+    #[serde(default)]
+    timestamps: Vec<(u32, String)>,
+    #[serde(default)]
+    navigation: Vec<(u32, navigation::Kind)>,
+    #[serde(default)]
+    edge_types: Vec<(u32, u32, edge_type::EdgeTypes)>,
+    node_attributes: Vec<(u32, script::Attributes)>,
+    edge_attributes: Vec<(u32, u32, script::Attributes)>,
+    #[serde(default)]
+    link_counts: Option<Vec<(u32, u32, u32)>>,
+}
+
+/// Load the graph cached at `path`, interning its titles into `rodeo`, if the cache exists and
+/// was made from the same `inputs`.
+pub fn load(path: &Path, inputs: &Inputs, rodeo: &mut Rodeo) -> anyhow::Result<Option<Wiki>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let mut reader = BufReader::new(file);
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+    if serde_json::from_str::<Inputs>(&header).ok().as_ref() != Some(inputs) {
+        tracing::info!("Cache {} is out of date", path.display());
+        return Ok(None);
+    }
+    read_artifact(reader, rodeo).map(Some)
+}
+
+/// Load a partial result written by `parse --partial`, which has the format of a cache file,
+/// whatever it was parsed from, and the inputs of the parse that wrote it.
+pub fn load_partial(path: &Path, rodeo: &mut Rodeo) -> anyhow::Result<(Inputs, Wiki)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+    let inputs = serde_json::from_str(&header)
+        .context("Not a partial result, or written by another version of wikigraph")?;
+    Ok((inputs, read_artifact(reader, rodeo)?))
+}
+
+/// Write `wiki` as it is saved after the first line of a cache file.
+pub fn write_artifact(writer: impl Write, rodeo: &Rodeo, wiki: &Wiki) -> anyhow::Result<()> {
+    serde_json::to_writer(writer, &Artifact::new(rodeo, wiki))?;
+    Ok(())
+}
+
+/// Read a graph written by `write_artifact`, interning its titles into `rodeo`.
+pub fn read_artifact(reader: impl std::io::Read, rodeo: &mut Rodeo) -> anyhow::Result<Wiki> {
+    let artifact: Artifact = serde_json::from_reader(reader)?;
+
+    let spurs: Vec<Spur> = artifact
+        .titles
+        .iter()
+        .map(|title| rodeo.get_or_intern(title))
+        .collect();
+    let spur = |id: u32| -> anyhow::Result<Spur> {
+        spurs
+            .get(id as usize)
+            .copied()
+            .context("Cache refers to a missing title")
+    };
+
+    let mut wiki = Wiki::default();
+    for (source, targets) in artifact.links {
+        let targets = targets
+            .into_iter()
+            .map(spur)
+            .collect::<anyhow::Result<_>>()?;
+        wiki.links.insert(spur(source)?, targets);
+    }
+    for (page, target) in artifact.redirects {
+        wiki.redirects.insert(spur(page)?, spur(target)?);
+    }
+    for (page, key) in artifact.sort_keys {
+        wiki.sort_keys.insert(spur(page)?, key);
+    }
+    for (category, page, key) in artifact.categories {
+        wiki.add_category(&category, spur(page)?, key);
+    }
+    for (page, namespace) in artifact.namespaces {
+        wiki.namespaces.insert(spur(page)?, namespace);
+    }
+    for (page, id) in artifact.page_ids {
+        wiki.page_ids.insert(spur(page)?, id);
+    }
+    for (page, timestamp) in artifact.timestamps {
+        wiki.timestamps.insert(spur(page)?, timestamp);
+    }
+    for (page, kind) in artifact.navigation {
+        wiki.navigation.insert(spur(page)?, kind);
+    }
+    for (source, target, types) in artifact.edge_types {
+        wiki.edge_types
+            .insert((spur(source)?, spur(target)?), types);
+    }
+    for (page, attributes) in artifact.node_attributes {
+        wiki.node_attributes.insert(spur(page)?, attributes);
+    }
+    for (source, target, attributes) in artifact.edge_attributes {
+        wiki.edge_attributes
+            .insert((spur(source)?, spur(target)?), attributes);
+    }
+    if let Some(link_counts) = artifact.link_counts {
+        let counts = wiki.link_counts.insert(HashMap::new());
+        for (source, target, count) in link_counts {
+            counts.insert((spur(source)?, spur(target)?), count);
+        }
+    }
+    Ok(wiki)
+}
+
+pub fn save(path: &Path, inputs: &Inputs, rodeo: &Rodeo, wiki: &Wiki) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write under a temporary name first, so an interrupted run never leaves a truncated cache.
+    let partial = path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    serde_json::to_writer(&mut writer, inputs)?;
+    writeln!(writer)?;
+    write_artifact(&mut writer, rodeo, wiki)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(partial, path)?;
+    Ok(())
+}
+
+impl Artifact {
+    #[allow(clippy::too_many_lines)]
+    fn new(rodeo: &Rodeo, wiki: &Wiki) -> Self {
+        // Titles in interning order, so that loading them into an empty interner gives every node
+        // the ID it had after parsing.
+        let mut spurs: Vec<Spur> = wiki
+            .links
+            .iter()
+            .flat_map(|(source, targets)| iter::once(source).chain(targets))
+            .chain(
+                wiki.redirects
+                    .iter()
+                    .flat_map(|(page, target)| [page, target]),
+            )
+            .chain(wiki.sort_keys.keys())
+            .chain(wiki.categories.keys().map(|(_, page)| page))
+            .chain(wiki.namespaces.keys())
+            .chain(wiki.page_ids.keys())
+            .chain(wiki.timestamps.keys())
+            .chain(wiki.navigation.keys())
+            .chain(
+                wiki.edge_types
+                    .keys()
+                    .flat_map(|(source, target)| [source, target]),
+            )
+            .chain(wiki.node_attributes.keys())
+            .chain(
+                wiki.edge_attributes
+                    .keys()
+                    .flat_map(|(source, target)| [source, target]),
+            )
+            .copied()
+            .collect();
+        spurs.sort_unstable();
+        spurs.dedup();
+        let ids: HashMap<Spur, u32> = spurs.iter().copied().zip(0..).collect();
+        let id = |spur: &Spur| ids[spur];
+        let titles = spurs
+            .iter()
+            .map(|spur| String::from(rodeo.resolve(spur)))
+            .collect();
+
+        let links = wiki
+            .links
+            .iter()
+            .map(|(source, targets)| (id(source), targets.iter().map(id).collect()))
+            .collect();
+        let redirects = wiki
+            .redirects
+            .iter()
+            .map(|(page, target)| (id(page), id(target)))
+            .collect();
+        let sort_keys = wiki
+            .sort_keys
+            .iter()
+            .map(|(page, key)| (id(page), key.clone()))
+            .collect();
+        let categories = wiki
+            .category_memberships()
+            .map(|(category, page, key)| (String::from(category), id(&page), key.map(String::from)))
+            .collect();
+        let namespaces = wiki
+            .namespaces
+            .iter()
+            .map(|(page, namespace)| (id(page), *namespace))
+            .collect();
+        let page_ids = wiki
+            .page_ids
+            .iter()
+            .map(|(page, page_id)| (id(page), *page_id))
+            .collect();
+        let timestamps = wiki
+            .timestamps
+            .iter()
+            .map(|(page, timestamp)| (id(page), timestamp.clone()))
+            .collect();
+        let navigation = wiki
+            .navigation
+            .iter()
+            .map(|(page, kind)| (id(page), *kind))
+            .collect();
+        let edge_types = wiki
+            .edge_types
+            .iter()
+            .map(|((source, target), types)| (id(source), id(target), *types))
+            .collect();
+        let node_attributes = wiki
+            .node_attributes
+            .iter()
+            .map(|(page, attributes)| (id(page), attributes.clone()))
+            .collect();
+        let edge_attributes = wiki
+            .edge_attributes
+            .iter()
+            .map(|((source, target), attributes)| (id(source), id(target), attributes.clone()))
+            .collect();
+        // Both ends of a counted link are among `links`.
+        let link_counts = wiki.link_counts.as_ref().map(|counts| {
+            counts
+                .iter()
+                .map(|((source, target), count)| (id(source), id(target), *count))
+                .collect()
+        });
+        Self {
+            titles,
+            links,
+            redirects,
+            sort_keys,
+            categories,
+            namespaces,
+            page_ids,
+            timestamps,
+            navigation,
+            edge_types,
+            node_attributes,
+            edge_attributes,
+            link_counts,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Giving up on long traversals. Searches and iterative computations over the graph check a
+//! `Cancel` between levels, iterations, or steps, and stop early with `Cancelled` once its
+//! deadline passes or it is cancelled from elsewhere, so that an accidentally huge query can't
+//! hang the process.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// When to stop: after a timeout, once a flag is set, both, or never.
+#[derive(Clone, Default)]
+pub struct Cancel {
+    deadline: Option<(Instant, Duration)>,
+    flag: Option<Arc<AtomicBool>>,
+}
+
+#[derive(Debug)]
+pub enum Cancelled {
+    TimedOut(Duration),
+    Interrupted,
+}
+
+impl Cancel {
+    /// A `Cancel` that never stops anything.
+    pub fn never() -> Self {
+        Self::default()
+    }
+
+    /// Stop once `timeout` has passed from now, if there is one.
+    pub fn after(timeout: Option<Duration>) -> Self {
+        Self {
+            deadline: timeout.map(|timeout| (Instant::now() + timeout, timeout)),
+            flag: None,
+        }
+    }
+
+    /// Also stop once `flag` is set.
+    pub fn or_when(self, flag: Arc<AtomicBool>) -> Self {
+        Self {
+            flag: Some(flag),
+            ..self
+        }
+    }
+
+    /// Whether to stop now.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if let Some((deadline, timeout)) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(Cancelled::TimedOut(timeout));
+            }
+        }
+        if self
+            .flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+        {
+            return Err(Cancelled::Interrupted);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TimedOut(timeout) => write!(f, "Timed out after {} seconds", timeout.as_secs()),
+            Self::Interrupted => write!(f, "Interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Cancelled {}
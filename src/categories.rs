@@ -0,0 +1,324 @@
+//! Statistics per category of a saved graph, for comparing topic areas: how many pages each
+//! category has, how linked-to and central they are, and how much of their linking stays inside
+//! the category. Categories are the `Category:` nodes of graphs parsed with `--edge-types
+//! wikilink,category`, whose members link to them. Counting the pages of subcategories with
+//! `--depth` also needs the category pages themselves, in namespace 14, to have been parsed.
+//!
+//! `parse --category-graph` saves a graph of the category memberships alone, in which pages link
+//! to their categories and category pages to their parent categories, and on which `categories
+//! CATEGORY` lists everything below a category.
+
+use crate::{
+    cancel::Cancel,
+    edge_type,
+    graph::{Adjacency as _, Graph},
+    stats::{self, PageRank},
+    workspace, Wiki,
+};
+use anyhow::Context as _;
+use lasso::Rodeo;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+const PREFIX: &str = "Category:";
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// List the pages and subcategories in this category, named with or without `Category:`,
+    /// and in its subcategories in turn, as `title,kind,depth,parent` CSV rows, instead of
+    /// writing statistics
+    #[arg(value_name = "CATEGORY", conflicts_with_all = ["category", "min_pages"])]
+    list: Option<String>,
+
+    /// Write the CSV to this file instead of standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Only report this category, named with or without `Category:` (repeatable) [default:
+    /// every category]
+    #[arg(long, value_name = "NAME")]
+    category: Vec<String>,
+
+    /// Levels of subcategories whose pages count towards a category too [default: 0, or every
+    /// level when listing a category]
+    #[arg(long, value_name = "N")]
+    depth: Option<usize>,
+
+    /// Leave out categories with fewer pages than this
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    min_pages: usize,
+
+    /// Give up on computing PageRank after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+/// The aggregates of one category.
+struct Row<'a> {
+    category: &'a str,
+    pages: usize,
+    /// Links to its pages, from anywhere.
+    links_in: u64,
+    mean_pagerank: f64,
+    /// Links from its pages to its pages, and to other pages. Category memberships count as
+    /// neither.
+    internal_links: u64,
+    external_links: u64,
+}
+
+/// Write `category,pages,links_in,mean_pagerank,internal_links,external_links,internal_ratio`
+/// rows as CSV, the categories with the most pages first.
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .context("Failed to create output file")
+                .unwrap(),
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    if let Some(name) = &args.list {
+        let category = find(&graph, name).unwrap();
+        let depth = args.depth.unwrap_or(usize::MAX);
+        list(output, &graph, category, depth)
+            .context("Failed to write the category listing")
+            .unwrap();
+        return;
+    }
+
+    let categories: Vec<u32> = if args.category.is_empty() {
+        (0..u32::try_from(graph.node_count()).unwrap())
+            .filter(|&node| is_category(&graph, node) && graph.redirect(node).is_none())
+            .collect()
+    } else {
+        args.category
+            .iter()
+            .map(|name| find(&graph, name).unwrap())
+            .collect()
+    };
+    if categories.is_empty() {
+        tracing::warn!(
+            "The graph has no categories; they need a graph built with \
+             `parse --edge-types wikilink,category`"
+        );
+    }
+
+    let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+    let computed;
+    let pagerank =
+        if let Some(pagerank) = stats::stored_pagerank(&graph, stats::DAMPING, stats::ITERATIONS) {
+            pagerank
+        } else {
+            computed = PageRank {
+                damping: stats::DAMPING,
+                iterations: stats::ITERATIONS,
+                scores: stats::pagerank_scores(&graph, stats::DAMPING, stats::ITERATIONS, &cancel)
+                    .context("Failed to compute PageRank")
+                    .unwrap(),
+            };
+            &computed
+        };
+
+    let mut rows: Vec<Row> = categories
+        .into_iter()
+        .map(|category| aggregate(&graph, pagerank, category, args.depth.unwrap_or(0)))
+        .filter(|row| row.pages >= args.min_pages)
+        .collect();
+    rows.sort_by(|a, b| b.pages.cmp(&a.pages).then(a.category.cmp(b.category)));
+
+    write(output, &rows)
+        .context("Failed to write category statistics")
+        .unwrap();
+    tracing::info!("Listed {} categories", rows.len());
+}
+
+/// The graph of the category memberships of a parse: each page linking to the `Category:`
+/// nodes of its categories, titled as MediaWiki would. Nodes are in order of title, so that
+/// they are numbered the same on every run.
+pub fn graph(rodeo: &Rodeo, wiki: &Wiki) -> (Rodeo, Wiki) {
+    let mut memberships: Vec<(&str, String)> = wiki
+        .category_memberships()
+        .map(|(category, page, _)| {
+            let name = category.replace('_', " ");
+            (
+                rodeo.resolve(&page),
+                format!("{PREFIX}{}", edge_type::capitalize(name.trim())),
+            )
+        })
+        .collect();
+    memberships.sort_unstable();
+    let mut titles: Vec<&str> = memberships
+        .iter()
+        .flat_map(|(page, category)| [*page, category.as_str()])
+        .collect();
+    titles.sort_unstable();
+
+    let mut categories = Rodeo::new();
+    for title in titles {
+        categories.get_or_intern(title);
+    }
+    let mut graph = Wiki::default();
+    for (page, category) in &memberships {
+        let page = categories.get_or_intern(page);
+        let category = categories.get_or_intern(category);
+        graph.links.entry(page).or_default().insert(category);
+    }
+    (categories, graph)
+}
+
+fn is_category(graph: &Graph, node: u32) -> bool {
+    graph.title(node).starts_with(PREFIX)
+}
+
+/// The node of the category `name`, given with or without `Category:`, and with underscores or
+/// a lowercase first letter as in `[[Category:...]]` tags.
+fn find(graph: &Graph, name: &str) -> anyhow::Result<u32> {
+    let name = name.strip_prefix(PREFIX).unwrap_or(name).replace('_', " ");
+    let title = format!("{PREFIX}{}", edge_type::capitalize(name.trim()));
+    graph
+        .id(&title)
+        .with_context(|| format!("No category '{title}'"))
+}
+
+/// Visit each page and subcategory of `category`, and of its subcategories down to `depth`
+/// levels below it, once, breadth first: with how many levels below `category` it was first
+/// found, 1 for the category's own members, and the category it was found in.
+fn walk(graph: &Graph, category: u32, depth: usize, mut visit: impl FnMut(u32, usize, u32)) {
+    let mut seen = HashSet::from([category]);
+    let mut frontier = vec![category];
+    let mut level = 0;
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for category in frontier {
+            for &node in graph.backlinks(category) {
+                if graph.redirect(node).is_some() || !seen.insert(node) {
+                    continue;
+                }
+                visit(node, level + 1, category);
+                if is_category(graph, node) && level < depth {
+                    next.push(node);
+                }
+            }
+        }
+        frontier = next;
+        level += 1;
+    }
+}
+
+/// The pages of `category` and of its subcategories down to `depth` levels below it.
+fn members(graph: &Graph, category: u32, depth: usize) -> HashSet<u32> {
+    let mut pages = HashSet::new();
+    walk(graph, category, depth, |node, _, _| {
+        if !is_category(graph, node) {
+            pages.insert(node);
+        }
+    });
+    pages
+}
+
+/// Write `title,kind,depth,parent` CSV rows of the pages and subcategories below `category`,
+/// nearest first, where `kind` is `page` or `category` and `parent` is the category the row
+/// was first found in.
+fn list(output: Box<dyn Write>, graph: &Graph, category: u32, depth: usize) -> anyhow::Result<()> {
+    let mut rows = Vec::new();
+    walk(graph, category, depth, |node, level, parent| {
+        rows.push((level, graph.title(node), node, parent));
+    });
+    rows.sort_unstable();
+
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(["title", "kind", "depth", "parent"])?;
+    for &(level, title, node, parent) in &rows {
+        let kind = if is_category(graph, node) {
+            "category"
+        } else {
+            "page"
+        };
+        writer.write_record([title, kind, &level.to_string(), graph.title(parent)])?;
+    }
+    writer.flush()?;
+    let subcategories = rows
+        .iter()
+        .filter(|&&(_, _, node, _)| is_category(graph, node))
+        .count();
+    tracing::info!(
+        "Listed {} pages and {subcategories} subcategories of {}",
+        rows.len() - subcategories,
+        graph.title(category)
+    );
+    Ok(())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn aggregate<'a>(graph: &'a Graph, pagerank: &PageRank, category: u32, depth: usize) -> Row<'a> {
+    let pages = members(graph, category, depth);
+    let mut row = Row {
+        category: &graph.title(category)[PREFIX.len()..],
+        pages: pages.len(),
+        links_in: 0,
+        mean_pagerank: 0.0,
+        internal_links: 0,
+        external_links: 0,
+    };
+    for &page in &pages {
+        row.links_in += u64::from(graph.in_degree(page));
+        row.mean_pagerank += pagerank.scores[page as usize];
+        for &target in graph.links(page) {
+            if is_category(graph, target) {
+                continue;
+            }
+            if pages.contains(&graph.resolve_redirect(target)) {
+                row.internal_links += 1;
+            } else {
+                row.external_links += 1;
+            }
+        }
+    }
+    row.mean_pagerank /= pages.len().max(1) as f64;
+    row
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn write(output: Box<dyn Write>, rows: &[Row]) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record([
+        "category",
+        "pages",
+        "links_in",
+        "mean_pagerank",
+        "internal_links",
+        "external_links",
+        "internal_ratio",
+    ])?;
+    for row in rows {
+        let links = row.internal_links + row.external_links;
+        let ratio = if links == 0 {
+            String::new()
+        } else {
+            format!("{:.4}", row.internal_links as f64 / links as f64)
+        };
+        writer.write_record([
+            row.category,
+            &row.pages.to_string(),
+            &row.links_in.to_string(),
+            &row.mean_pagerank.to_string(),
+            &row.internal_links.to_string(),
+            &row.external_links.to_string(),
+            &ratio,
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
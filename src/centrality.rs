@@ -0,0 +1,288 @@
+//! Betweenness and closeness centrality, estimated from breadth-first searches from a sample of
+//! pages, since searching from every page of a wiki would take years. Each search counts the
+//! shortest paths from its page as Brandes' algorithm does, and how much of them runs through
+//! every other page; scaled up by the share of pages sampled, the sums estimate betweenness
+//! without bias, as Brandes and Pich showed, and the distances found estimate closeness.
+//!
+//! Closeness is harmonic, the mean of `1 / distance` from the other pages, so pages some pages
+//! can't reach still have a score. Links resolve through redirects when parsed, so redirects
+//! are left out of paths, and have no score.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::{Adjacency as _, Direction, Graph},
+    sample::Rng,
+    stats, workspace,
+};
+use anyhow::Context as _;
+use std::{
+    panic,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Pages to search from; the estimates are exact with as many as there are pages
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1000,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    samples: u32,
+
+    /// Which links paths follow
+    #[arg(long, value_enum, default_value_t)]
+    direction: Direction,
+
+    /// Number of pages to print by each measure
+    #[arg(long, value_name = "K", default_value_t = 20)]
+    top: usize,
+
+    /// Also write the title, betweenness, and closeness of every page to this CSV file, by
+    /// descending betweenness
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Number of threads searching [default: the number of CPUs]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// Seed for picking the pages to search from
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Give up after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+/// Estimated scores of every node, in `[0, 1]`: betweenness as the share of the shortest paths
+/// between other pages that pass through a page, and closeness as the mean of `1 / distance`
+/// to it from the other pages.
+struct Centrality {
+    betweenness: Vec<f64>,
+    closeness: Vec<f64>,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let threads = args.threads.map_or_else(
+        || thread::available_parallelism().map_or(1, usize::from),
+        usize::from,
+    );
+    let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+    let centrality = estimate(
+        &graph,
+        args.samples,
+        args.direction,
+        args.seed,
+        threads,
+        &cancel,
+    )
+    .context("Failed to estimate centrality")
+    .unwrap();
+
+    for (heading, scores) in [
+        ("Betweenness", &centrality.betweenness),
+        ("Closeness", &centrality.closeness),
+    ] {
+        println!("{heading}:");
+        for (node, score) in stats::top_scores(scores, args.top) {
+            println!("{score:.6}\t{}", graph.title(node));
+        }
+    }
+    if let Some(path) = &args.output {
+        export(&graph, &centrality, path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+            .unwrap();
+    }
+}
+
+/// Estimate the centrality of every node of `graph` from searches in `direction` from `samples`
+/// pages picked with `seed`, on `threads` threads. Checks `cancel` before each search.
+#[allow(clippy::cast_precision_loss)]
+fn estimate(
+    graph: &Graph,
+    samples: u32,
+    direction: Direction,
+    seed: u64,
+    threads: usize,
+    cancel: &Cancel,
+) -> Result<Centrality, Cancelled> {
+    let n = graph.node_count();
+    let redirects: Vec<bool> = (0..u32::try_from(n).unwrap())
+        .map(|node| graph.redirect(node).is_some())
+        .collect();
+    let mut pages: Vec<u32> = (0..u32::try_from(n).unwrap())
+        .filter(|&node| !redirects[node as usize])
+        .collect();
+    let mut rng = Rng::seeded(seed, &["centrality"]);
+    for i in (1..pages.len()).rev() {
+        pages.swap(i, rng.below(i + 1));
+    }
+    let population = pages.len();
+    pages.truncate(samples as usize);
+    tracing::info!(
+        "Searching from {} of {population} pages on {threads} threads",
+        pages.len()
+    );
+
+    let chunk = pages.len().div_ceil(threads.max(1)).max(1);
+    let redirects = &redirects;
+    let sums = thread::scope(|scope| {
+        let searching: Vec<_> = pages
+            .chunks(chunk)
+            .map(|sources| {
+                scope.spawn(move || {
+                    let mut search = Search::new(n);
+                    for &source in sources {
+                        cancel.check()?;
+                        search.accumulate(graph, redirects, source, direction);
+                    }
+                    Ok((search.betweenness, search.closeness))
+                })
+            })
+            .collect();
+        searching
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+            })
+            .collect::<Result<Vec<_>, Cancelled>>()
+    })?;
+
+    let mut centrality = Centrality {
+        betweenness: vec![0.0; n],
+        closeness: vec![0.0; n],
+    };
+    for (betweenness, closeness) in sums {
+        for (total, sum) in centrality.betweenness.iter_mut().zip(betweenness) {
+            *total += sum;
+        }
+        for (total, sum) in centrality.closeness.iter_mut().zip(closeness) {
+            *total += sum;
+        }
+    }
+    // Each search stands for `population / sampled` of them, over the ordered pairs of other
+    // pages for betweenness and the other pages for closeness.
+    let scale = population as f64 / pages.len().max(1) as f64;
+    let others = population.saturating_sub(1).max(1) as f64;
+    let pairs = (others * population.saturating_sub(2) as f64).max(1.0);
+    for score in &mut centrality.betweenness {
+        *score *= scale / pairs;
+    }
+    for score in &mut centrality.closeness {
+        *score *= scale / others;
+    }
+    Ok(centrality)
+}
+
+/// One thread's sums over its searches, with the state of a search kept between them so that
+/// each one only resets the nodes it reached.
+struct Search {
+    betweenness: Vec<f64>,
+    closeness: Vec<f64>,
+    /// Distance of each node from the source, or `u32::MAX` if not reached.
+    distances: Vec<u32>,
+    /// Number of shortest paths from the source to each node.
+    paths: Vec<f64>,
+    /// Share of the shortest paths from the source to nodes further on through each node.
+    dependencies: Vec<f64>,
+    /// Nodes reached, by distance.
+    order: Vec<u32>,
+}
+
+impl Search {
+    fn new(n: usize) -> Self {
+        Self {
+            betweenness: vec![0.0; n],
+            closeness: vec![0.0; n],
+            distances: vec![u32::MAX; n],
+            paths: vec![0.0; n],
+            dependencies: vec![0.0; n],
+            order: Vec::new(),
+        }
+    }
+
+    /// Search from `source` in `direction`, adding to the sums of every node it reaches except
+    /// the `redirects`.
+    fn accumulate(&mut self, graph: &Graph, redirects: &[bool], source: u32, direction: Direction) {
+        self.distances[source as usize] = 0;
+        self.paths[source as usize] = 1.0;
+        self.order.push(source);
+        let mut next = 0;
+        while let Some(&node) = self.order.get(next) {
+            next += 1;
+            let distance = self.distances[node as usize] + 1;
+            for neighbour in graph.neighbours(node, direction) {
+                let index = neighbour as usize;
+                if redirects[index] {
+                    continue;
+                }
+                if self.distances[index] == u32::MAX {
+                    self.distances[index] = distance;
+                    self.order.push(neighbour);
+                }
+                if self.distances[index] == distance {
+                    self.paths[index] += self.paths[node as usize];
+                }
+            }
+        }
+
+        // From the furthest nodes back, each node's predecessors on shortest paths share its
+        // paths, and those of the nodes beyond it, in proportion to their own.
+        for &node in self.order.iter().rev() {
+            let index = node as usize;
+            let distance = self.distances[index];
+            if node == source {
+                continue;
+            }
+            let share = (1.0 + self.dependencies[index]) / self.paths[index];
+            for predecessor in graph.neighbours(node, direction.reverse()) {
+                let predecessor = predecessor as usize;
+                if self.distances[predecessor] != u32::MAX
+                    && self.distances[predecessor] + 1 == distance
+                {
+                    self.dependencies[predecessor] += self.paths[predecessor] * share;
+                }
+            }
+            self.betweenness[index] += self.dependencies[index];
+            self.closeness[index] += 1.0 / f64::from(distance);
+        }
+
+        for node in self.order.drain(..) {
+            let index = node as usize;
+            self.distances[index] = u32::MAX;
+            self.paths[index] = 0.0;
+            self.dependencies[index] = 0.0;
+        }
+    }
+}
+
+fn export(graph: &Graph, centrality: &Centrality, path: &Path) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["title", "betweenness", "closeness"])?;
+    for (node, betweenness) in stats::top_scores(&centrality.betweenness, graph.node_count()) {
+        if graph.redirect(node).is_some() {
+            continue;
+        }
+        writer.write_record([
+            graph.title(node),
+            format!("{betweenness:.9}").as_str(),
+            format!("{:.9}", centrality.closeness[node as usize]).as_str(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
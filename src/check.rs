@@ -0,0 +1,135 @@
+//! Check a dump the way a parse reads it, without building anything, so that a damaged or cut
+//! short download shows up in the time it takes to decompress rather than hours into a parse.
+
+use crate::progress::Progress;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use wikigraph::{
+    dump::{self, read_xml_counted, Pages},
+    remote,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Dump file, `-` for standard input, or a URL as for `parse`, compressed or not
+    input: PathBuf,
+
+    /// Don't show progress
+    #[arg(long)]
+    no_progress: bool,
+}
+
+/// What reading the dump found.
+#[derive(Default)]
+struct Scan {
+    /// Pages of each namespace, counting the newest revision of history dumps.
+    namespaces: BTreeMap<i64, u64>,
+    redirects: u64,
+    /// Pages whose newest revision has no text.
+    empty: u64,
+    malformed: u64,
+    textless: u64,
+    closed: bool,
+    /// Why reading stopped before the end, if it did.
+    error: Option<anyhow::Error>,
+}
+
+/// Print the pages of each namespace and every problem found, exiting with status 1 if there
+/// are any.
+pub fn run(args: &Args) {
+    let scan = {
+        let progress = Progress::start(!args.no_progress);
+        if let Ok(metadata) = fs::metadata(&args.input) {
+            progress.set_total(metadata.len());
+        }
+        scan(args, &progress)
+    };
+
+    let pages: u64 = scan.namespaces.values().sum();
+    for (namespace, count) in &scan.namespaces {
+        println!("Namespace {namespace}: {count} pages");
+    }
+    println!(
+        "{pages} pages, of which {} redirects and {} without text",
+        scan.redirects, scan.empty
+    );
+
+    let mut problems = Vec::new();
+    if let Some(error) = &scan.error {
+        problems.push(format!("Failed to read on after {pages} pages: {error:#}"));
+    } else if !scan.closed {
+        problems.push(String::from(
+            "The dump ends without a </mediawiki>, so it may have been cut short",
+        ));
+    }
+    if scan.malformed > 0 {
+        problems.push(format!(
+            "{} malformed pages, which a parse skips, or fails on with --strict",
+            scan.malformed
+        ));
+    }
+    if scan.textless > 0 {
+        problems.push(format!(
+            "{} pages without a <text> element, which a parse leaves out",
+            scan.textless
+        ));
+    }
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if !problems.is_empty() {
+        std::process::exit(1);
+    }
+    println!("OK");
+}
+
+fn scan(args: &Args, progress: &Progress) -> Scan {
+    let mut scan = Scan::default();
+    let xml = match read_xml_counted(
+        &args.input,
+        None,
+        progress.bytes_read(),
+        &remote::Options::default(),
+    ) {
+        Ok(xml) => xml,
+        Err(error) => {
+            scan.error = Some(error);
+            return scan;
+        }
+    };
+    let malformed = Arc::new(AtomicU64::new(0));
+    let mut pages = Pages::new(xml)
+        .lenient(Arc::clone(&malformed))
+        .latest_revisions();
+    loop {
+        match pages.next_page() {
+            Ok(Some(page)) => {
+                progress.page();
+                *scan.namespaces.entry(page.namespace).or_default() += 1;
+                if page.redirect.is_some() || dump::redirect_target(&page.text).is_some() {
+                    scan.redirects += 1;
+                }
+                if page.text.is_empty() {
+                    scan.empty += 1;
+                }
+            }
+            Ok(None) => break,
+            Err(error) => {
+                scan.error = Some(error);
+                break;
+            }
+        }
+    }
+    scan.malformed = malformed.load(Ordering::Relaxed);
+    scan.textless = pages.textless();
+    scan.closed = pages.closed();
+    scan
+}
@@ -0,0 +1,92 @@
+//! Checkpoints of a long parse, so that one stopped hours in can continue with `--resume`
+//! instead of starting over. Pages are extracted on several threads at once and in no particular
+//! order, so the dump is read in runs of `--checkpoint-interval`, and a checkpoint is saved once
+//! every page of a run has been added to the graph: the graph so far, and where the next run
+//! starts reading.
+//!
+//! Like a cache file, a checkpoint starts with a line recording everything the parse depended
+//! on, which must be unchanged to resume, then a line with the position in the dump, and then
+//! the graph.
+
+use crate::{cache, Wiki};
+use anyhow::Context as _;
+use lasso::Rodeo;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufRead as _, BufReader, BufWriter, Write as _},
+    path::Path,
+};
+
+/// How far into the dump a parse has got.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Position {
+    /// Where the first stream not read yet starts, if the dump is indexed. Without an index,
+    /// the `read` pages are read again and skipped.
+    pub offset: Option<u64>,
+    /// Pages in `--namespace` read.
+    pub read: u64,
+    /// Of those, how many were parsed, the rest being left out by `--skip-pages` or `--sample`.
+    pub kept: u64,
+}
+
+/// Save the graph `wiki` of a parse of `inputs` that has read the dump up to `position` to
+/// `path`, replacing the checkpoint before only once this one is complete.
+pub fn save(
+    path: &Path,
+    inputs: &cache::Inputs,
+    position: Position,
+    rodeo: &Rodeo,
+    wiki: &Wiki,
+) -> anyhow::Result<()> {
+    let partial = path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    serde_json::to_writer(&mut writer, inputs)?;
+    writeln!(writer)?;
+    serde_json::to_writer(&mut writer, &position)?;
+    writeln!(writer)?;
+    cache::write_artifact(&mut writer, rodeo, wiki)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(partial, path)?;
+    Ok(())
+}
+
+/// The position and graph saved at `path`, interning its titles into `rodeo`, or `None` if there
+/// is no checkpoint there.
+///
+/// # Errors
+///
+/// If the checkpoint can't be read, or was made by a parse of anything but `inputs`.
+pub fn load(
+    path: &Path,
+    inputs: &cache::Inputs,
+    rodeo: &mut Rodeo,
+) -> anyhow::Result<Option<(Position, Wiki)>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    anyhow::ensure!(
+        serde_json::from_str::<cache::Inputs>(&line).ok().as_ref() == Some(inputs),
+        "The checkpoint is of another dump, or of a parse with other options"
+    );
+    line.clear();
+    reader.read_line(&mut line)?;
+    let position = serde_json::from_str(&line).context("Failed to read the position")?;
+    Ok(Some((position, cache::read_artifact(reader, rodeo)?)))
+}
+
+/// Remove the checkpoint at `path` of a parse that has finished, if it saved one.
+pub fn remove(path: &Path) {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            tracing::warn!("Failed to remove checkpoint {}: {error}", path.display());
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,114 @@
+//! Checking downloaded dumps against the checksums Wikimedia publishes beside them, in
+//! `<wiki>-<date>-sha1sums.txt` and `<wiki>-<date>-md5sums.txt`, so that a truncated or corrupt
+//! file is caught before it is parsed rather than hours into the parse.
+
+use anyhow::Context as _;
+use md5::Md5;
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha1,
+    Md5,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha1 => "SHA-1",
+            Self::Md5 => "MD5",
+        }
+    }
+
+    /// The algorithm of the digests in the checksums file `name`, going by the names Wikimedia
+    /// gives them, or else by the length of `digest`.
+    pub fn detect(name: &str, digest: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name.contains("sha1") {
+            Some(Self::Sha1)
+        } else if name.contains("md5") {
+            Some(Self::Md5)
+        } else {
+            match digest.len() {
+                40 => Some(Self::Sha1),
+                32 => Some(Self::Md5),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// The digests of the checksums file at `path` by file name, and their algorithm.
+pub fn read(path: &Path) -> anyhow::Result<(Algorithm, HashMap<String, String>)> {
+    let sums = parse(&std::fs::read_to_string(path)?)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let sample = sums.values().next().map_or("", String::as_str);
+    let algorithm = Algorithm::detect(&name, sample).with_context(|| {
+        format!(
+            "Can't tell whether {} has SHA-1 or MD5 digests",
+            path.display()
+        )
+    })?;
+    Ok((algorithm, sums))
+}
+
+/// The digests listed in `text` by file name: lines of a hex digest, whitespace, and a name, as
+/// `sha1sum` writes them.
+pub fn parse(text: &str) -> anyhow::Result<HashMap<String, String>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (digest, name) = line
+                .split_once(char::is_whitespace)
+                .with_context(|| format!("Invalid checksum line '{line}'"))?;
+            // `sha1sum --binary` marks names with a `*`.
+            let name = name.trim_start().trim_start_matches('*');
+            Ok((String::from(name), digest.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+/// The hex digest of everything `reader` reads.
+pub fn digest(algorithm: Algorithm, mut reader: impl Read) -> io::Result<String> {
+    fn hash<D: Digest>(mut hasher: D, reader: &mut impl Read) -> io::Result<String> {
+        let mut buffer = vec![0; 1 << 20];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            }))
+    }
+    match algorithm {
+        Algorithm::Sha1 => hash(Sha1::new(), &mut reader),
+        Algorithm::Md5 => hash(Md5::new(), &mut reader),
+    }
+}
+
+/// Check the file at `path` against the digest `expected`.
+pub fn verify(path: &Path, algorithm: Algorithm, expected: &str) -> anyhow::Result<()> {
+    let actual = digest(algorithm, File::open(path)?)?;
+    anyhow::ensure!(
+        actual.eq_ignore_ascii_case(expected),
+        "{} doesn't match its published {} checksum: expected {expected}, got {actual}; the \
+         file is truncated or corrupt",
+        path.display(),
+        algorithm.name()
+    );
+    Ok(())
+}
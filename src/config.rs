@@ -0,0 +1,95 @@
+//! `parse --config`: options of `parse` kept in a TOML file, so that a long command line can be
+//! checked in beside the project that needs it and run again the same way. Keys are the names
+//! of the options without their dashes, and options given on the command line win:
+//!
+//! ```toml
+//! project = "wikipedia"
+//! namespace = [0, 14]
+//! link-classes = ["article", "category"]
+//! no-template-links = true
+//! link-rules = "rules.toml"
+//! disambiguation-templates = ["Disambig", "Dab"]
+//! ```
+//!
+//! Relative paths are relative to the config file. The options are read by adding them to the
+//! command line, ahead of the ones given there, so they are checked as those are.
+
+use crate::Args;
+use anyhow::Context as _;
+use clap::{parser::ValueSource, ArgAction, CommandFactory as _};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// `command_line` with the options of its `--config` file added, if it is a `parse` with one.
+/// Command lines that don't parse are left for parsing them to report.
+pub fn expand(command_line: Vec<OsString>) -> anyhow::Result<Vec<OsString>> {
+    let command = Args::command();
+    let Ok(matches) = command.clone().try_get_matches_from(&command_line) else {
+        return Ok(command_line);
+    };
+    let Some(("parse", given)) = matches.subcommand() else {
+        return Ok(command_line);
+    };
+    let Some(path) = given.get_one::<PathBuf>("config") else {
+        return Ok(command_line);
+    };
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config {}", path.display()))?;
+    let table: toml::Table =
+        toml::from_str(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let parse = command
+        .find_subcommand("parse")
+        .context("`parse` is a subcommand")?;
+    let mut options = Vec::new();
+    for (key, value) in &table {
+        let name = key.replace('_', "-");
+        let arg = parse
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name.as_str()) && name != "config")
+            .with_context(|| format!("`{key}` in {} isn't an option of `parse`", path.display()))?;
+        if given.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let is_path = arg.get_value_names().is_some_and(|names| {
+            names
+                .iter()
+                .any(|name| ["FILE", "DIR"].contains(&name.as_str()))
+        });
+        let values = match value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                toml::Value::Boolean(set) if matches!(arg.get_action(), ArgAction::SetTrue) => {
+                    if *set {
+                        options.push(OsString::from(format!("--{name}")));
+                    }
+                    continue;
+                }
+                toml::Value::String(value) if is_path => dir.join(value).into_os_string(),
+                toml::Value::String(value) => OsString::from(value),
+                toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                    OsString::from(value.to_string())
+                }
+                _ => anyhow::bail!(
+                    "`{key}` in {} should be a string, number, boolean, or array of them",
+                    path.display()
+                ),
+            };
+            let mut option = OsString::from(format!("--{name}="));
+            option.push(value);
+            options.push(option);
+        }
+    }
+
+    // Ahead of the dump, so that they don't end up after a `--`.
+    let mut command_line = command_line;
+    command_line.splice(2..2, options);
+    Ok(command_line)
+}
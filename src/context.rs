@@ -0,0 +1,88 @@
+//! The sentence surrounding each link, as (source, target, anchor, context) records for training
+//! entity-linking and relation-extraction models.
+
+use crate::{plaintext::strip_markup, wikilink::Link};
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+#[derive(Serialize)]
+struct Record<'a> {
+    source: &'a str,
+    target: &'a str,
+    anchor: &'a str,
+    context: &'a str,
+}
+
+pub struct Writer {
+    inner: BufWriter<File>,
+    window: usize,
+}
+
+impl Writer {
+    /// Create a JSON lines writer whose contexts extend at most `window` bytes on either side
+    /// of the link.
+    pub fn create(path: &Path, window: usize) -> anyhow::Result<Self> {
+        Ok(Self {
+            inner: BufWriter::new(File::create(path)?),
+            window,
+        })
+    }
+
+    pub fn write(
+        &mut self,
+        source: &str,
+        target: &str,
+        text: &str,
+        link: &Link,
+    ) -> anyhow::Result<()> {
+        let context = strip_markup(sentence(text, link, self.window));
+        let anchor = strip_markup(link.anchor);
+        let record = Record {
+            source,
+            target,
+            anchor: &anchor,
+            context: &context,
+        };
+        serde_json::to_writer(&mut self.inner, &record)?;
+        self.inner.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+/// The sentence of `text` containing `link`, cut off at `window` bytes on either side.
+pub fn sentence<'a>(text: &'a str, link: &Link, window: usize) -> &'a str {
+    let mut start = link.range.start.saturating_sub(window);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let mut end = (link.range.end + window).min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let before = &text[start..link.range.start];
+    let start = before
+        .rfind('\n')
+        .into_iter()
+        .chain(before.rfind(". ").map(|i| i + 1))
+        .chain(before.rfind("! ").map(|i| i + 1))
+        .chain(before.rfind("? ").map(|i| i + 1))
+        .max()
+        .map_or(start, |i| start + i + 1);
+
+    let after = &text[link.range.end..end];
+    let end = after
+        .find(['.', '!', '?', '\n'])
+        .map_or(end, |i| link.range.end + i + 1);
+
+    text[start..end].trim()
+}
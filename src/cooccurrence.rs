@@ -0,0 +1,91 @@
+//! An undirected graph connecting link targets that appear in the same sentence or paragraph,
+//! which tends to relate pages more tightly than page-level adjacency.
+
+use lasso::{Rodeo, Spur};
+use std::{collections::HashMap, ops::Range, path::Path};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Scope {
+    Sentence,
+    Paragraph,
+}
+
+pub struct Cooccurrence {
+    scope: Scope,
+    /// Co-occurrence counts, keyed by target pairs with the smaller key first.
+    weights: HashMap<(Spur, Spur), u64>,
+}
+
+impl Cooccurrence {
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            weights: HashMap::new(),
+        }
+    }
+
+    /// Count every pair of distinct targets linked within the same segment of `text`.
+    pub fn add_page(&mut self, text: &str, links: &[(Range<usize>, Spur)]) {
+        let boundaries = boundaries(text, self.scope);
+
+        let mut segments: HashMap<usize, Vec<Spur>> = HashMap::new();
+        for (range, target) in links {
+            let segment = boundaries.partition_point(|&boundary| boundary <= range.start);
+            segments.entry(segment).or_default().push(*target);
+        }
+
+        for mut targets in segments.into_values() {
+            targets.sort_unstable();
+            targets.dedup();
+            for (i, &a) in targets.iter().enumerate() {
+                for &b in &targets[i + 1..] {
+                    *self.weights.entry((a, b)).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    /// Write the graph as an undirected Gephi edge list, with titles as node IDs.
+    pub fn write(&self, path: &Path, rodeo: &Rodeo) -> anyhow::Result<()> {
+        let mut edges: Vec<(&str, &str, u64)> = self
+            .weights
+            .iter()
+            .map(|((a, b), weight)| {
+                let (a, b) = (rodeo.resolve(a), rodeo.resolve(b));
+                if a <= b {
+                    (a, b, *weight)
+                } else {
+                    (b, a, *weight)
+                }
+            })
+            .collect();
+        edges.sort_unstable();
+
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["Source", "Target", "Weight", "Type"])?;
+        for (a, b, weight) in edges {
+            writer.write_record([a, b, weight.to_string().as_str(), "Undirected"])?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Byte offsets where a new segment starts.
+fn boundaries(text: &str, scope: Scope) -> Vec<usize> {
+    match scope {
+        Scope::Paragraph => text.match_indices("\n\n").map(|(i, _)| i + 2).collect(),
+        Scope::Sentence => {
+            let bytes = text.as_bytes();
+            (0..bytes.len())
+                .filter(|&i| match bytes[i] {
+                    b'\n' => true,
+                    b'.' | b'!' | b'?' => bytes.get(i + 1).is_none_or(u8::is_ascii_whitespace),
+                    _ => false,
+                })
+                .map(|i| i + 1)
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Change reports between two builds of the same wiki: of two dumps, with `parse --diff-from`,
+//! or of two saved graphs, with `diff`, which needs neither dump. Saved graphs don't tell pages
+//! from the titles only linked to, so there every node counts as a page.
+
+use crate::{graph::Graph, workspace, Wiki};
+use anyhow::Context as _;
+use lasso::{Rodeo, Spur};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The older graph: a graph file saved by `parse --graph`, or its name in the workspace (see
+    /// `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    old: PathBuf,
+
+    /// The newer graph, given the same way
+    #[arg(value_parser = workspace::graph_path)]
+    new: PathBuf,
+
+    /// Write the changes to this file instead of standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Minimum Jaccard similarity of link sets for a page that became a redirect to count as
+    /// moved to its target, as with `parse --rename-similarity`
+    #[arg(long, value_name = "S", default_value_t = 0.5)]
+    rename_similarity: f64,
+}
+
+pub fn run(args: &Args) {
+    let mut rodeo = Rodeo::new();
+    let [old, new] = [&args.old, &args.new].map(|path| {
+        let graph = Graph::load(path)
+            .with_context(|| format!("Failed to load {}", path.display()))
+            .unwrap();
+        let mut wiki = graph.to_wiki(&mut rodeo);
+        for node in 0..u32::try_from(graph.node_count()).unwrap() {
+            let page = rodeo.get(graph.title(node)).unwrap();
+            wiki.links.entry(page).or_default();
+        }
+        wiki
+    });
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .context("Failed to create output file")
+                .unwrap(),
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let summary = write(output, &rodeo, &old, &new, args.rename_similarity)
+        .context("Failed to write changes")
+        .unwrap();
+    // Standard output has the changes unless they went to a file.
+    if args.output.is_some() {
+        println!("{summary}");
+    } else {
+        tracing::info!("{summary}");
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+enum Change<'a> {
+    Added {
+        title: &'a str,
+    },
+    Removed {
+        title: &'a str,
+    },
+    Renamed {
+        from: &'a str,
+        to: &'a str,
+        similarity: f64,
+    },
+    LinkAdded {
+        source: &'a str,
+        target: &'a str,
+    },
+    LinkRemoved {
+        source: &'a str,
+        target: &'a str,
+    },
+}
+
+#[derive(Default)]
+pub struct Summary {
+    pub added: usize,
+    pub removed: usize,
+    pub renamed: usize,
+    pub links_added: usize,
+    pub links_removed: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} pages added, {} removed, {} renamed; {} links added, {} removed",
+            self.added, self.removed, self.renamed, self.links_added, self.links_removed
+        )
+    }
+}
+
+/// Write one JSON line per change from `old` to `new` to `output`.
+///
+/// A page counts as renamed (moved) rather than removed-and-added when its old title became a
+/// redirect to a title that didn't exist before, and the link sets of the old and new page have a
+/// Jaccard similarity of at least `min_similarity`.
+pub fn write(
+    output: impl Write,
+    rodeo: &Rodeo,
+    old: &Wiki,
+    new: &Wiki,
+    min_similarity: f64,
+) -> anyhow::Result<Summary> {
+    let empty = HashSet::new();
+
+    // Pairs of (old title, new title) for pages present in both dumps, including moves.
+    let mut pairs: Vec<(Spur, Spur)> = Vec::new();
+    let mut renames: HashMap<Spur, (Spur, f64)> = HashMap::new();
+
+    for (&from, old_links) in &old.links {
+        if old.redirects.contains_key(&from) {
+            continue;
+        }
+        let Some(&to) = new.redirects.get(&from) else {
+            continue;
+        };
+        if old.links.contains_key(&to) {
+            continue;
+        }
+        let Some(new_links) = new.links.get(&to) else {
+            continue;
+        };
+        let similarity = jaccard(old_links, new_links);
+        if similarity >= min_similarity {
+            renames.insert(from, (to, similarity));
+        }
+    }
+
+    let rename_targets: HashSet<Spur> = renames.values().map(|(to, _)| *to).collect();
+
+    let mut changes = Vec::new();
+    let mut summary = Summary::default();
+
+    for &title in sorted(rodeo, new.links.keys()) {
+        if !old.links.contains_key(&title) && !rename_targets.contains(&title) {
+            changes.push(Change::Added {
+                title: rodeo.resolve(&title),
+            });
+            summary.added += 1;
+        }
+    }
+
+    for &title in sorted(rodeo, old.links.keys()) {
+        if let Some(&(to, similarity)) = renames.get(&title) {
+            changes.push(Change::Renamed {
+                from: rodeo.resolve(&title),
+                to: rodeo.resolve(&to),
+                similarity,
+            });
+            summary.renamed += 1;
+            pairs.push((title, to));
+        } else if new.links.contains_key(&title) {
+            pairs.push((title, title));
+        } else {
+            changes.push(Change::Removed {
+                title: rodeo.resolve(&title),
+            });
+            summary.removed += 1;
+        }
+    }
+
+    for (from, to) in pairs {
+        let old_links = old.links.get(&from).unwrap_or(&empty);
+        let new_links = new.links.get(&to).unwrap_or(&empty);
+        let source = rodeo.resolve(&to);
+        for &target in sorted(rodeo, new_links.difference(old_links)) {
+            changes.push(Change::LinkAdded {
+                source,
+                target: rodeo.resolve(&target),
+            });
+            summary.links_added += 1;
+        }
+        for &target in sorted(rodeo, old_links.difference(new_links)) {
+            changes.push(Change::LinkRemoved {
+                source,
+                target: rodeo.resolve(&target),
+            });
+            summary.links_removed += 1;
+        }
+    }
+
+    let mut writer = BufWriter::new(output);
+    for change in &changes {
+        serde_json::to_writer(&mut writer, change)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    Ok(summary)
+}
+
+fn sorted<'a>(rodeo: &Rodeo, keys: impl Iterator<Item = &'a Spur>) -> Vec<&'a Spur> {
+    let mut keys: Vec<&Spur> = keys.collect();
+    keys.sort_unstable_by_key(|key| rodeo.resolve(key));
+    keys
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn jaccard(a: &HashSet<Spur>, b: &HashSet<Spur>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
@@ -0,0 +1,996 @@
+//! Reading pages out of MediaWiki XML dumps, compressed or not, whole or one shard at a time,
+//! and for multistream dumps with an index, many streams at once.
+
+use crate::{remote, shard, wikilink::links};
+use anyhow::Context as _;
+use quick_xml::{events::Event, name::QName};
+use std::{
+    fs::File,
+    io::{self, BufRead as _, BufReader, Read as _, Seek as _, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// The XML of a dump, decompressed.
+pub type Xml = quick_xml::Reader<Box<dyn io::BufRead + Send>>;
+
+/// How a dump is compressed, told apart by its first bytes, or failing that by its file name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compression {
+    None,
+    Bzip2,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression format of the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read.
+    pub fn detect(path: &Path) -> anyhow::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        Ok(Self::sniff(file.fill_buf()?).unwrap_or_else(|| {
+            Self::from_name(&path.file_name().unwrap_or_default().to_string_lossy())
+        }))
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "plain XML",
+            Self::Bzip2 => "bzip2",
+            Self::Gzip => "gzip",
+            Self::Xz => "xz",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// The compression format starting `bytes`, if it is recognizable. Uncompressed XML starts
+    /// with `<`, after any byte order mark and whitespace.
+    fn sniff(bytes: &[u8]) -> Option<Self> {
+        let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        if bytes.starts_with(b"BZh") {
+            Some(Self::Bzip2)
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Self::Xz)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd])
+            // A skippable frame, which `pzstd` and seekable zstd files start with.
+            || matches!(bytes, [0x50..=0x5f, 0x2a, 0x4d, 0x18, ..])
+        {
+            Some(Self::Zstd)
+        } else if text.trim_ascii_start().starts_with(b"<") {
+            Some(Self::None)
+        } else {
+            None
+        }
+    }
+
+    /// The compression format a file name suggests.
+    fn from_name(name: &str) -> Self {
+        let extension = Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str());
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("bz2") => Self::Bzip2,
+            Some("gz") => Self::Gzip,
+            Some("xz") => Self::Xz,
+            Some("zst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Open the dump at `path`, or standard input if it is `-`, decompressing it as its first bytes
+/// say. Every format may hold several concatenated streams, as multistream dumps do. With
+/// `shard`, only that shard of a multistream bzip2 dump is read.
+///
+/// # Errors
+///
+/// If the dump can't be read, or is sharded without being a bzip2 file.
+pub fn read_xml(path: &Path, shard: Option<shard::Shard>) -> anyhow::Result<Xml> {
+    read_xml_counted(path, shard, &Arc::default(), &remote::Options::default())
+}
+
+/// `read_xml`, adding the number of bytes read from the file, before decompression, to
+/// `bytes_read` as it goes. `path` may also be a URL, downloaded as `remote` says.
+///
+/// # Errors
+///
+/// If the dump can't be read, or is sharded without being a bzip2 file.
+pub fn read_xml_counted(
+    path: &Path,
+    shard: Option<shard::Shard>,
+    bytes_read: &Arc<AtomicU64>,
+    remote: &remote::Options,
+) -> anyhow::Result<Xml> {
+    if remote::is_url(path) {
+        anyhow::ensure!(shard.is_none(), "Dumps read from URLs can't be sharded");
+        let url = path.to_str().context("URL is not valid UTF-8")?;
+        tracing::debug!("Downloading '{url}'");
+        let download = Counted::new(remote::open(url, remote)?, bytes_read);
+        return decode(BufReader::new(download), remote::file_name(url));
+    }
+    if path == Path::new("-") {
+        anyhow::ensure!(shard.is_none(), "Standard input can't be sharded");
+        tracing::debug!("Reading standard input");
+        let stdin = Counted::new(io::stdin(), bytes_read);
+        return decode(BufReader::new(stdin), "");
+    }
+    if !path.is_file() {
+        anyhow::bail!("Path is not a file");
+    }
+
+    let file_name = path
+        .file_name()
+        .context("Could not get file name from path")?
+        .to_str()
+        .context("File name is not valid UTF-8")?;
+
+    let mut file = BufReader::new(Counted::new(File::open(path)?, bytes_read));
+    if let Some(shard) = shard {
+        anyhow::ensure!(
+            Compression::sniff(file.fill_buf()?) == Some(Compression::Bzip2),
+            "Only multistream bzip2 dumps can be sharded"
+        );
+        let range = shard.range(path)?;
+        tracing::info!(
+            "Reading shard {shard} of '{}', bytes {}..{}",
+            path.display(),
+            range.start,
+            range.end
+        );
+        let mut file = file.into_inner().inner;
+        file.seek(SeekFrom::Start(range.start))?;
+        let file = Counted::new(file.take(range.end - range.start), bytes_read);
+        let bzip2_decoder = bzip2::read::MultiBzDecoder::new(file);
+        let buf_reader: Box<dyn io::BufRead + Send> = Box::new(BufReader::new(bzip2_decoder));
+        let mut xml_reader = quick_xml::Reader::from_reader(buf_reader);
+        // Shards hold runs of pages without the enclosing `<mediawiki>` element.
+        xml_reader.check_end_names(false);
+        Ok(xml_reader)
+    } else {
+        tracing::debug!("Reading '{}'", path.display());
+        decode(file, file_name)
+    }
+}
+
+/// A reader adding how many bytes it reads to a shared count.
+struct Counted<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R> Counted<R> {
+    fn new(inner: R, count: &Arc<AtomicU64>) -> Self {
+        Self {
+            inner,
+            count: Arc::clone(count),
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// The XML reader for `input`, decompressed as its first bytes say, or as `name` suggests if
+/// they don't.
+fn decode(input: impl io::BufRead + Send + 'static, name: &str) -> anyhow::Result<Xml> {
+    Ok(quick_xml::Reader::from_reader(decompress(input, name)?))
+}
+
+/// Open the file at `path`, or standard input if it is `-`, decompressed as its first bytes
+/// say, or as its name suggests if they don't. This is for the other files published beside
+/// the XML dumps, such as the SQL dumps of single tables.
+///
+/// # Errors
+///
+/// If the file can't be read.
+pub fn open_decompressed(path: &Path) -> anyhow::Result<Box<dyn io::BufRead + Send>> {
+    if path == Path::new("-") {
+        return decompress(BufReader::new(io::stdin()), "");
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    decompress(BufReader::new(File::open(path)?), &name)
+}
+
+/// `input`, decompressed as its first bytes say, or as `name` suggests if they don't.
+fn decompress(
+    mut input: impl io::BufRead + Send + 'static,
+    name: &str,
+) -> anyhow::Result<Box<dyn io::BufRead + Send>> {
+    let compression =
+        Compression::sniff(input.fill_buf()?).unwrap_or_else(|| Compression::from_name(name));
+    tracing::debug!("Decompressing as {compression:?}");
+    Ok(match compression {
+        Compression::None => Box::new(input),
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(input))),
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(input))),
+        Compression::Xz => Box::new(BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(
+            input,
+        ))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(input)?)),
+    })
+}
+
+/// The `-index.txt.bz2` file that Wikimedia publishes beside a multistream dump, if it is there.
+#[must_use]
+pub fn index_path(dump: &Path) -> Option<PathBuf> {
+    let name = dump.file_name()?.to_str()?;
+    let stem = name.strip_suffix("multistream.xml.bz2")?;
+    let index = dump.with_file_name(format!("{stem}multistream-index.txt.bz2"));
+    index.is_file().then_some(index)
+}
+
+/// The byte ranges of the bzip2 streams of the multistream dump at `path`, with its index at
+/// `index`: the header stream up to the first offset listed, then one stream per offset. Index
+/// lines are `offset:page ID:title`, one per page, and either bzip2-compressed or plain text.
+/// With `shard`, only the streams of that shard.
+///
+/// # Errors
+///
+/// If either file can't be read, or the index has lines that aren't sorted index lines.
+pub fn stream_ranges(
+    path: &Path,
+    index: &Path,
+    shard: Option<shard::Shard>,
+) -> anyhow::Result<Vec<Range<u64>>> {
+    let len = File::open(path)?.metadata()?.len();
+
+    let mut starts = vec![0];
+    for line in index_lines(index)?.lines() {
+        let line = line?;
+        let offset = index_offset(&line)?;
+        anyhow::ensure!(
+            offset <= len,
+            "Index offset {offset} is past the end of the dump"
+        );
+        let last = starts[starts.len() - 1];
+        if offset != last {
+            anyhow::ensure!(offset > last, "Index offsets are out of order at '{line}'");
+            starts.push(offset);
+        }
+    }
+    let shard = shard.map(|shard| shard.range(path)).transpose()?;
+    let ranges = starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&len]))
+        .map(|(&start, &end)| start..end)
+        .filter(|range| !range.is_empty())
+        .filter(|range| {
+            shard
+                .as_ref()
+                .is_none_or(|shard| shard.contains(&range.start))
+        })
+        .collect();
+    Ok(ranges)
+}
+
+/// The number of pages listed in the multistream dump index at `index`, or with `shard`, in that
+/// shard of the dump at `path`.
+///
+/// # Errors
+///
+/// If either file can't be read, or the index has lines that aren't index lines.
+pub fn index_pages(
+    path: &Path,
+    index: &Path,
+    shard: Option<shard::Shard>,
+) -> anyhow::Result<usize> {
+    let shard = shard.map(|shard| shard.range(path)).transpose()?;
+    let mut pages = 0;
+    for line in index_lines(index)?.lines() {
+        let offset = index_offset(&line?)?;
+        if shard.as_ref().is_none_or(|shard| shard.contains(&offset)) {
+            pages += 1;
+        }
+    }
+    Ok(pages)
+}
+
+/// A line of a multistream dump index: the offset in the dump of the bzip2 stream holding the
+/// page, and the page's ID and title.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub offset: u64,
+    pub id: u64,
+    pub title: String,
+}
+
+/// The entries of the multistream dump index at `index`, in order, read as they are needed.
+/// The index is enough to list every page's ID and title, without reading the dump.
+///
+/// # Errors
+///
+/// If the index can't be opened; then, for each line, if it can't be read or isn't an index
+/// line.
+pub fn index_entries(
+    index: &Path,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<IndexEntry>>> {
+    Ok(index_lines(index)?.lines().map(|line| {
+        let line = line?;
+        let offset = index_offset(&line)?;
+        // Titles can contain colons, page IDs can't. Titles are escaped as in the XML.
+        let (id, title) = line
+            .split_once(':')
+            .and_then(|(_, rest)| rest.split_once(':'))
+            .with_context(|| format!("Invalid index line '{line}'"))?;
+        Ok(IndexEntry {
+            offset,
+            id: id
+                .parse()
+                .with_context(|| format!("Invalid page ID in index line '{line}'"))?,
+            title: quick_xml::escape::unescape(title)
+                .map_or_else(|_| String::from(title), std::borrow::Cow::into_owned),
+        })
+    }))
+}
+
+/// The page of the multistream dump at `path` whose entry in the index at `index` is the first
+/// that `find` picks, read from the one bzip2 stream holding it rather than the whole dump, or
+/// `None` if `find` picks no entry. Only the index up to the next stream is read.
+///
+/// # Errors
+///
+/// If either file can't be read, the index has lines that aren't index lines, or the stream
+/// doesn't hold the page the index says it does.
+pub fn indexed_page(
+    path: &Path,
+    index: &Path,
+    find: impl Fn(&IndexEntry) -> bool,
+) -> anyhow::Result<Option<Page>> {
+    let mut entries = index_entries(index)?;
+    let Some(entry) = entries
+        .find(|entry| entry.as_ref().is_err() || entry.as_ref().is_ok_and(&find))
+        .transpose()?
+    else {
+        return Ok(None);
+    };
+    // The stream ends where the next one starts.
+    let end = entries
+        .map(|next| next.map(|next| next.offset))
+        .find(|next| next.as_ref().map_or(true, |&next| next != entry.offset))
+        .transpose()?;
+    let end = match end {
+        Some(end) => end,
+        None => File::open(path)?.metadata()?.len(),
+    };
+    let page = stream_pages(path, entry.offset..end, |pages| pages, &Arc::default())?
+        .into_iter()
+        // Pages without an `<id>` can only be told apart by title.
+        .find(|page| {
+            page.id
+                .map_or(page.title == entry.title, |id| id == entry.id)
+        })
+        .with_context(|| {
+            format!(
+                "The stream at byte {} has no page {}, which the index lists there",
+                entry.offset, entry.id
+            )
+        })?;
+    Ok(Some(page))
+}
+
+/// The lines of the index at `index`, decompressed if it is a `.bz2` file.
+fn index_lines(index: &Path) -> anyhow::Result<Box<dyn io::BufRead>> {
+    let file = File::open(index)?;
+    Ok(if index.extension().is_some_and(|ext| ext == "bz2") {
+        Box::new(BufReader::new(bzip2::read::MultiBzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    })
+}
+
+/// The stream offset of an `offset:page ID:title` index line.
+fn index_offset(line: &str) -> anyhow::Result<u64> {
+    let (offset, _) = line
+        .split_once(':')
+        .with_context(|| format!("Invalid index line '{line}'"))?;
+    offset
+        .parse()
+        .with_context(|| format!("Invalid offset in index line '{line}'"))
+}
+
+/// Send every page of the streams at `ranges` of the dump at `path` to `tx`, in dump order,
+/// decompressing and parsing up to `threads` streams at a time, each with the pages `configure`
+/// makes of it. A stream's pages are only sent once the streams before it have been, so node IDs
+/// don't depend on which thread is faster. The compressed bytes read are added to `bytes_read`.
+///
+/// # Errors
+///
+/// If a stream can't be read or parsed, or `tx` is disconnected.
+pub fn read_streams(
+    path: &Path,
+    ranges: Vec<Range<u64>>,
+    configure: impl Fn(Pages) -> Pages + Clone + Send + 'static,
+    threads: usize,
+    tx: &flume::Sender<Page>,
+    bytes_read: &Arc<AtomicU64>,
+) -> anyhow::Result<()> {
+    type Pending = flume::Receiver<anyhow::Result<Vec<Page>>>;
+    let (jobs_tx, jobs_rx) = flume::unbounded();
+    // Bounding the streams in flight bounds how many parsed pages wait for an earlier stream.
+    let (order_tx, order_rx) = flume::bounded::<Pending>(threads * 2);
+
+    for _ in 0..threads {
+        let jobs_rx: flume::Receiver<(Range<u64>, flume::Sender<_>)> = jobs_rx.clone();
+        let path = path.to_path_buf();
+        let configure = configure.clone();
+        let bytes_read = Arc::clone(bytes_read);
+        thread::spawn(move || {
+            for (range, result_tx) in jobs_rx {
+                let pages = stream_pages(&path, range, &configure, &bytes_read);
+                // The reader stops listening after an error, so a failed send is fine.
+                let _ = result_tx.send(pages);
+            }
+        });
+    }
+    thread::spawn(move || {
+        for range in ranges {
+            let (result_tx, result_rx) = flume::bounded(1);
+            if jobs_tx.send((range, result_tx)).is_err() || order_tx.send(result_rx).is_err() {
+                return;
+            }
+        }
+    });
+
+    for pending in order_rx {
+        for page in pending.recv()?? {
+            tx.send(page)?;
+        }
+    }
+    Ok(())
+}
+
+/// The pages `configure` makes of the bzip2 stream at `range` of the dump at `path`.
+fn stream_pages(
+    path: &Path,
+    range: Range<u64>,
+    configure: impl Fn(Pages) -> Pages,
+    bytes_read: &Arc<AtomicU64>,
+) -> anyhow::Result<Vec<Page>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(range.start))?;
+    let file = Counted::new(file.take(range.end - range.start), bytes_read);
+    let bzip2_decoder = bzip2::read::MultiBzDecoder::new(file);
+    let buf_reader: Box<dyn io::BufRead + Send> = Box::new(BufReader::new(bzip2_decoder));
+    let mut xml_reader = quick_xml::Reader::from_reader(buf_reader);
+    // Streams hold runs of pages without the enclosing `<mediawiki>` element.
+    xml_reader.check_end_names(false);
+
+    let mut pages = configure(Pages::new(xml_reader));
+    let mut stream = Vec::new();
+    while let Some(page) = pages
+        .next_page()
+        .with_context(|| format!("Failed to read stream at byte {}", range.start))?
+    {
+        stream.push(page);
+    }
+    Ok(stream)
+}
+
+/// One revision of a page. Current-revision dumps yield one per page; full-history dumps yield
+/// every revision of a page in turn.
+#[derive(Debug)]
+pub struct Page {
+    /// Page ID from the page's `<id>`, which stays the same across renames.
+    pub id: Option<u64>,
+    pub title: String,
+    /// Namespace number from `<ns>`: 0 for articles, 14 for categories, and so on.
+    pub namespace: i64,
+    pub timestamp: Option<String>,
+    pub redirect: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug)]
+enum State {
+    Limbo1,
+    TitleStarted,
+    Title {
+        title: String,
+    },
+    Limbo2 {
+        title: String,
+        timestamp: Option<String>,
+    },
+    NamespaceStarted {
+        title: String,
+    },
+    IdStarted {
+        title: String,
+        timestamp: Option<String>,
+    },
+    TimestampStarted {
+        title: String,
+    },
+    Timestamp {
+        title: String,
+        timestamp: String,
+    },
+    TextStarted {
+        title: String,
+        timestamp: Option<String>,
+    },
+    /// The text as read, validated and unescaped once its end tag is.
+    Text {
+        title: String,
+        timestamp: Option<String>,
+        text: Vec<u8>,
+    },
+}
+
+/// The pages of a dump, one item per revision for history dumps. After an error, there are no
+/// more items.
+pub struct DumpReader {
+    pages: Option<Pages>,
+}
+
+impl DumpReader {
+    /// Open the dump at `path`, or standard input if it is `-`, compressed or not.
+    ///
+    /// # Errors
+    ///
+    /// If the dump can't be read.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            pages: Some(Pages::new(read_xml(path, None)?)),
+        })
+    }
+}
+
+impl Iterator for DumpReader {
+    type Item = anyhow::Result<Page>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page = self.pages.as_mut()?.next_page().transpose();
+        if !matches!(page, Some(Ok(_))) {
+            self.pages = None;
+        }
+        page
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+pub struct Pages {
+    xml: Xml,
+    state: State,
+    /// Target of the `<redirect title="..."/>` element of the current page, which applies to
+    /// all of its revisions.
+    redirect: Option<String>,
+    /// Namespace of the current page.
+    namespace: i64,
+    /// ID of the current page: the first `<id>` in it, before those of revisions and
+    /// contributors.
+    id: Option<u64>,
+    /// Whether to skip over `<text>` elements instead of reading them.
+    skip_text: bool,
+    /// The namespaces to read pages of, if not all of them.
+    namespaces: Option<Vec<i64>>,
+    /// Where to count the malformed pages skipped over, if they are skipped rather than ending
+    /// the read.
+    malformed: Option<Arc<AtomicU64>>,
+    /// Whether the `</page>` of the current page is still to come.
+    in_page: bool,
+    /// Title of the current page, for reporting it if it turns out malformed.
+    title: String,
+    /// Whether the XML itself failed to read, which no skipping gets past.
+    unreadable: bool,
+    /// Whether to yield only the newest revision of each page.
+    latest: bool,
+    /// Pages started so far, which tells the revisions of one page from those of the next.
+    page_number: u64,
+    /// The newest revision so far of the page being read, with its page number, when only the
+    /// newest revisions are yielded.
+    newest: Option<(u64, Page)>,
+    /// Older revisions passed over for a newer one of the same page.
+    superseded: u64,
+    /// Whether the current page has had a `<text>` element, even an empty or skipped one.
+    has_text: bool,
+    /// Pages that ended without a `<text>` element, and so yielded nothing.
+    textless: u64,
+    /// Whether the `</mediawiki>` closing the dump has been read.
+    closed: bool,
+}
+
+impl Pages {
+    #[must_use]
+    pub fn new(xml: Xml) -> Self {
+        Self {
+            xml,
+            state: State::Limbo1,
+            redirect: None,
+            namespace: 0,
+            id: None,
+            skip_text: false,
+            namespaces: None,
+            malformed: None,
+            in_page: false,
+            title: String::new(),
+            unreadable: false,
+            latest: false,
+            page_number: 0,
+            newest: None,
+            superseded: 0,
+            has_text: false,
+            textless: 0,
+            closed: false,
+        }
+    }
+
+    /// These pages with empty text, skipping over the wikitext without decoding it, for when
+    /// only the titles and other metadata are needed.
+    #[must_use]
+    pub fn without_text(self) -> Self {
+        Self {
+            skip_text: true,
+            ..self
+        }
+    }
+
+    /// Only the pages in `namespaces`. The text of other pages is skipped over without
+    /// decoding it, which for a dump of every namespace saves most of the time spent on talk
+    /// and user pages.
+    #[must_use]
+    pub fn in_namespaces(self, namespaces: Vec<i64>) -> Self {
+        Self {
+            namespaces: Some(namespaces),
+            ..self
+        }
+    }
+
+    /// These pages minus malformed ones: pages with unexpected elements, or an invalid title,
+    /// namespace, ID, or text, are logged, counted in `malformed`, and skipped up to their
+    /// `</page>`, rather than ending the read. XML that can't be read at all still does.
+    #[must_use]
+    pub fn lenient(self, malformed: Arc<AtomicU64>) -> Self {
+        Self {
+            malformed: Some(malformed),
+            ..self
+        }
+    }
+
+    /// Only the newest revision of each page, by timestamp, so that a full-history dump reads
+    /// like a dump of current revisions. Revisions without text, such as deleted ones, don't
+    /// count.
+    #[must_use]
+    pub fn latest_revisions(self) -> Self {
+        Self {
+            latest: true,
+            ..self
+        }
+    }
+
+    /// The pages read so far that had no `<text>` element, which are left out.
+    #[must_use]
+    pub fn textless(&self) -> u64 {
+        self.textless
+    }
+
+    /// Whether the dump has been read up to its closing `</mediawiki>`, as a dump that wasn't
+    /// cut short is once `next_page` returns `None`. Streams of multistream dumps have none.
+    #[must_use]
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Whether the current page is in one of the namespaces to read.
+    fn accepted(&self) -> bool {
+        self.namespaces
+            .as_ref()
+            .is_none_or(|namespaces| namespaces.contains(&self.namespace))
+    }
+
+    /// The next page, or revision of history dumps unless reading the `latest_revisions`, or
+    /// `None` at the end of the dump.
+    ///
+    /// # Errors
+    ///
+    /// If the XML is malformed or isn't a MediaWiki dump, unless these pages are `lenient` and
+    /// only a page is.
+    pub fn next_page(&mut self) -> anyhow::Result<Option<Page>> {
+        if !self.latest {
+            return self.next_revision();
+        }
+        // A page's newest revision is only known once the next page has started.
+        loop {
+            let Some(revision) = self.next_revision()? else {
+                return Ok(self.newest.take().map(|(_, page)| page));
+            };
+            match self.newest.take() {
+                Some((page_number, page)) if page_number != self.page_number => {
+                    self.newest = Some((self.page_number, revision));
+                    return Ok(Some(page));
+                }
+                Some((page_number, page)) => {
+                    self.superseded += 1;
+                    if self.superseded == 1 {
+                        tracing::info!(
+                            "Reading a full-history dump, keeping the newest revision of each page"
+                        );
+                    }
+                    // Of revisions with the same timestamp, or none, the last in the dump wins.
+                    let newer = if revision.timestamp >= page.timestamp {
+                        revision
+                    } else {
+                        page
+                    };
+                    self.newest = Some((page_number, newer));
+                }
+                None => self.newest = Some((self.page_number, revision)),
+            }
+        }
+    }
+
+    /// The next page or revision, skipping malformed pages if `lenient`.
+    fn next_revision(&mut self) -> anyhow::Result<Option<Page>> {
+        loop {
+            let error = match self.read_page() {
+                Err(error) if !self.unreadable => error,
+                result => return result,
+            };
+            let Some(malformed) = &self.malformed else {
+                return Err(error);
+            };
+            malformed.fetch_add(1, Ordering::Relaxed);
+            if self.title.is_empty() {
+                tracing::warn!("Skipping malformed page: {error:#}");
+            } else {
+                tracing::warn!("Skipping malformed page '{}': {error:#}", self.title);
+            }
+            self.resync()?;
+        }
+    }
+
+    /// Read on past the `</page>` of the current page, if not there already, so that the next
+    /// read starts at a page of its own.
+    fn resync(&mut self) -> anyhow::Result<()> {
+        self.state = State::Limbo1;
+        let mut buffer = Vec::new();
+        while self.in_page {
+            let event = self
+                .xml
+                .read_event_into(&mut buffer)
+                .context("Failed to read XML event")?;
+            match event {
+                Event::End(data) if data.name().into_inner() == b"page" => self.in_page = false,
+                Event::Eof => break,
+                _ => {}
+            }
+            buffer.clear();
+        }
+        Ok(())
+    }
+
+    // One arm per state transition; splitting the match up would hide the state machine.
+    #[allow(clippy::too_many_lines)]
+    fn read_page(&mut self) -> anyhow::Result<Option<Page>> {
+        let mut buffer = Vec::new();
+
+        loop {
+            let event = match self.xml.read_event_into(&mut buffer) {
+                Ok(event) => event,
+                Err(error) => {
+                    self.unreadable = true;
+                    return Err(error).context("Failed to read XML event");
+                }
+            };
+
+            let state = std::mem::replace(&mut self.state, State::Limbo1);
+
+            self.state = match (state, event) {
+                (State::Limbo1, Event::Eof) => {
+                    return Ok(None);
+                }
+                (State::Limbo1, Event::Start(data)) if data.name().into_inner() == b"title" => {
+                    self.redirect = None;
+                    self.namespace = 0;
+                    self.id = None;
+                    self.in_page = true;
+                    self.page_number += 1;
+                    self.has_text = false;
+                    self.title.clear();
+                    State::TitleStarted
+                }
+                (State::Limbo1, Event::End(data)) if data.name().into_inner() == b"mediawiki" => {
+                    self.closed = true;
+                    State::Limbo1
+                }
+                (limbo1 @ State::Limbo1, _) => limbo1,
+                (State::TitleStarted, Event::Text(data)) => {
+                    let title = unescape(&data)?;
+                    self.title.clone_from(&title);
+                    State::Title { title }
+                }
+                (State::Title { title }, Event::End(data))
+                    if data.name().into_inner() == b"title" =>
+                {
+                    State::Limbo2 {
+                        title,
+                        timestamp: None,
+                    }
+                }
+                (State::Limbo2 { title, .. }, Event::Start(data))
+                    if data.name().into_inner() == b"ns" =>
+                {
+                    State::NamespaceStarted { title }
+                }
+                (State::NamespaceStarted { title }, Event::Text(data)) => {
+                    self.namespace = data
+                        .unescape()?
+                        .trim()
+                        .parse()
+                        .context("Invalid namespace number")?;
+                    State::Limbo2 {
+                        title,
+                        timestamp: None,
+                    }
+                }
+                (State::Limbo2 { title, timestamp }, Event::Start(data))
+                    if data.name().into_inner() == b"id" && self.id.is_none() =>
+                {
+                    State::IdStarted { title, timestamp }
+                }
+                (State::IdStarted { title, timestamp }, Event::Text(data)) => {
+                    self.id = Some(data.unescape()?.trim().parse().context("Invalid page ID")?);
+                    State::Limbo2 { title, timestamp }
+                }
+                (State::Limbo2 { title, .. }, Event::Start(data))
+                    if data.name().into_inner() == b"text" && !self.accepted() =>
+                {
+                    self.has_text = true;
+                    let mut skipped = Vec::new();
+                    self.xml
+                        .read_to_end_into(QName(b"text"), &mut skipped)
+                        .context("Failed to skip page text")?;
+                    State::Limbo2 {
+                        title,
+                        timestamp: None,
+                    }
+                }
+                (State::Limbo2 { title, timestamp }, Event::Start(data))
+                    if data.name().into_inner() == b"text" && self.skip_text =>
+                {
+                    self.has_text = true;
+                    let mut skipped = Vec::new();
+                    self.xml
+                        .read_to_end_into(QName(b"text"), &mut skipped)
+                        .context("Failed to skip page text")?;
+                    self.state = State::Limbo2 {
+                        title: title.clone(),
+                        timestamp: None,
+                    };
+                    return Ok(Some(self.page(title, timestamp, String::new())));
+                }
+                (State::Limbo2 { title, .. }, Event::Start(data))
+                    if data.name().into_inner() == b"timestamp" =>
+                {
+                    State::TimestampStarted { title }
+                }
+                (State::Limbo2 { title, timestamp }, Event::Start(data))
+                    if data.name().into_inner() == b"text" =>
+                {
+                    self.has_text = true;
+                    State::TextStarted { title, timestamp }
+                }
+                // The text of deleted revisions, which there is none of.
+                (limbo2 @ State::Limbo2 { .. }, Event::Empty(data))
+                    if data.name().into_inner() == b"text" =>
+                {
+                    self.has_text = true;
+                    limbo2
+                }
+                (limbo2 @ State::Limbo2 { .. }, Event::Empty(data))
+                    if data.name().into_inner() == b"redirect" =>
+                {
+                    if let Some(attribute) = data.try_get_attribute("title")? {
+                        self.redirect = Some(attribute.unescape_value()?.into_owned());
+                    }
+                    limbo2
+                }
+                (State::Limbo2 { .. }, Event::End(data)) if data.name().into_inner() == b"page" => {
+                    self.in_page = false;
+                    if !self.has_text {
+                        self.textless += 1;
+                        tracing::warn!("Skipping '{}', which has no <text>", self.title);
+                    }
+                    State::Limbo1
+                }
+                (limbo2 @ State::Limbo2 { .. }, _) => limbo2,
+                (State::TimestampStarted { title }, Event::Text(data)) => {
+                    let timestamp = unescape(&data)?;
+                    State::Timestamp { title, timestamp }
+                }
+                (State::Timestamp { title, timestamp }, Event::End(data))
+                    if data.name().into_inner() == b"timestamp" =>
+                {
+                    State::Limbo2 {
+                        title,
+                        timestamp: Some(timestamp),
+                    }
+                }
+                (State::TextStarted { title, timestamp }, Event::Text(data)) => State::Text {
+                    title,
+                    timestamp,
+                    text: data.into_inner().into_owned(),
+                },
+                (
+                    State::Text {
+                        title,
+                        timestamp,
+                        text,
+                    },
+                    Event::End(data),
+                ) if data.name().into_inner() == b"text" => {
+                    let text =
+                        unescape(&text).with_context(|| format!("Invalid text of '{title}'"))?;
+                    // Stay inside the page: history dumps have more revisions to come.
+                    self.state = State::Limbo2 {
+                        title: title.clone(),
+                        timestamp: None,
+                    };
+                    return Ok(Some(self.page(title, timestamp, text)));
+                }
+                (state, event) => {
+                    if matches!(&event, Event::End(data) if data.name().into_inner() == b"page") {
+                        self.in_page = false;
+                    }
+                    anyhow::bail!(
+                        "Unexpected event in current state\nstate: {state:?}\nevent: {event:?}"
+                    );
+                }
+            };
+
+            buffer.clear();
+        }
+    }
+
+    fn page(&self, title: String, timestamp: Option<String>, text: String) -> Page {
+        Page {
+            id: self.id,
+            title,
+            namespace: self.namespace,
+            timestamp,
+            redirect: self.redirect.clone(),
+            text,
+        }
+    }
+}
+
+/// `bytes` as UTF-8 with XML entities replaced. quick-xml's own unescaping validates with
+/// `std::str::from_utf8`, which simdutf8 beats several times over on the megabytes of wikitext
+/// of the longest pages.
+fn unescape(bytes: &[u8]) -> anyhow::Result<String> {
+    let text = simdutf8::basic::from_utf8(bytes)
+        .map_err(|_| anyhow::anyhow!("Text is not valid UTF-8"))?;
+    Ok(quick_xml::escape::unescape(text)?.into_owned())
+}
+
+/// The link target of a `#REDIRECT [[Target]]` at the start of `text`, for dumps or revisions
+/// without a `<redirect>` element. Only the English magic word is recognized; dumps of other
+/// languages have the element.
+#[must_use]
+pub fn redirect_target(text: &str) -> Option<&str> {
+    let rest = text.trim_start().strip_prefix('#')?;
+    let word = rest.get(..8)?;
+    if !word.eq_ignore_ascii_case("redirect") {
+        return None;
+    }
+    let rest = rest[8..].trim_start().trim_start_matches(':').trim_start();
+    let link = links(rest).next().filter(|link| link.range.start == 0)?;
+    Some(link.target)
+}
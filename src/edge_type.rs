@@ -0,0 +1,122 @@
+//! Typed edge layers. Besides wikilinks, a build can link pages to the categories, templates,
+//! external URLs, and files they use, all in one graph. Each edge records which layers it
+//! belongs to, and exports list it once per layer, with its type.
+
+use crate::{dump::Page, page_json, profile::Profile, siteinfo, sort, template, wikilink::links};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeType {
+    /// `[[Target]]` links, and links from link templates and `--link-rules`
+    Wikilink,
+    /// Category memberships, to `Category:Name` nodes
+    Category,
+    /// Transclusions, to `Template:Name` nodes
+    Template,
+    /// External links, to nodes titled by their URL
+    External,
+    /// Embedded files and images, to `File:Name` nodes
+    File,
+}
+
+impl EdgeType {
+    /// Every type, in the order of their numbers in the NumPy and PyTorch Geometric exports.
+    pub const ALL: [Self; 5] = [
+        Self::Wikilink,
+        Self::Category,
+        Self::Template,
+        Self::External,
+        Self::File,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wikilink => "wikilink",
+            Self::Category => "category",
+            Self::Template => "template",
+            Self::External => "external",
+            Self::File => "file",
+        }
+    }
+
+    /// Position in `ALL`.
+    pub fn number(self) -> u32 {
+        self as u32
+    }
+
+    /// Targets of this layer's edges from `page`, whose banner-stripped wikitext is `text`.
+    /// Wikilinks are resolved by the caller, so they have none here. Categories and files are
+    /// titled with the namespace names of `profile`.
+    pub fn targets(self, profile: &Profile, page: &Page, text: &str) -> Vec<String> {
+        match self {
+            Self::Wikilink => Vec::new(),
+            Self::Category => {
+                let namespace = profile.namespace_name(siteinfo::CATEGORY, "Category");
+                sort::categories(&page.text, |prefix| profile.is_category(prefix))
+                    .map(|(name, _)| format!("{namespace}:{}", capitalize(name)))
+                    .collect()
+            }
+            Self::Template => template::templates(&page.text)
+                .map(|template| template.name)
+                .filter(|&name| !template::is_magic_word(name))
+                .map(template_title)
+                .collect(),
+            Self::External => page_json::external_links(&page.text)
+                .into_iter()
+                .map(|link| String::from(link.url))
+                .collect(),
+            Self::File => {
+                let namespace = profile.namespace_name(siteinfo::FILE, "File");
+                links(text)
+                    .filter_map(|link| {
+                        let (prefix, name) = link.target.split_once(':')?;
+                        let prefix = prefix.trim().trim_start_matches(':');
+                        profile
+                            .is_file(prefix)
+                            .then(|| format!("{namespace}:{}", capitalize(name.trim())))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A set of edge types, as bits numbered like `EdgeType::ALL`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct EdgeTypes(u8);
+
+impl EdgeTypes {
+    pub fn insert(&mut self, edge_type: EdgeType) {
+        self.0 |= 1 << edge_type.number();
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = EdgeType> {
+        EdgeType::ALL
+            .into_iter()
+            .filter(move |edge_type| self.0 & (1 << edge_type.number()) != 0)
+    }
+}
+
+/// The page a template invocation transcludes: `{{name}}` is `Template:Name`, while names with a
+/// namespace, and `{{:Article}}`, are titles already.
+pub fn template_title(name: &str) -> String {
+    let name = name.replace('_', " ");
+    match name.strip_prefix(':') {
+        Some(article) => capitalize(article.trim()),
+        None if name.contains(':') => capitalize(&name),
+        None => format!("Template:{}", capitalize(&name)),
+    }
+}
+
+/// MediaWiki titles start with an uppercase letter.
+pub fn capitalize(title: &str) -> String {
+    let mut chars = title.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
@@ -0,0 +1,239 @@
+//! Exports of the link graph for other tools. Most are written by `parse` from the graph it
+//! builds, and the formats for GUI tools by `export` from a saved graph, which is easier to cut
+//! down to a size they can open first.
+
+use crate::{
+    graph::{Direction, Graph},
+    layout, path,
+    script::Attributes,
+    sort, subgraph, workspace, Wiki,
+};
+use anyhow::Context as _;
+use lasso::{Key as _, Rodeo, Spur};
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap, HashSet},
+    io::Write,
+    panic,
+    path::PathBuf,
+    thread,
+};
+
+pub mod condensed;
+pub mod dot;
+pub mod edge_list;
+pub mod gephi;
+pub mod gexf;
+pub mod graphml;
+pub mod graphology;
+pub mod neo4j;
+pub mod npy;
+pub mod partitions;
+pub mod pyg;
+pub mod sort_index;
+
+/// Positions of the nodes of `graph` from a force-directed layout of its links.
+fn lay_out(graph: &Graph, iterations: usize) -> Vec<(f64, f64)> {
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    let edges: Vec<(usize, usize)> = nodes
+        .flat_map(|source| {
+            graph
+                .links(source)
+                .iter()
+                .map(move |&target| (source as usize, target as usize))
+        })
+        .collect();
+    layout::force_atlas2(graph.node_count(), &edges, iterations)
+}
+
+/// Nodes above which a DOT export is more than Graphviz lays out in reasonable time.
+const DOT_NODES: usize = 2000;
+
+/// CSV records serialized at a time by each thread of `write_chunked`.
+const CHUNK_RECORDS: usize = 1 << 16;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// GraphML, for yEd, Gephi, Cytoscape, and NetworkX
+    Graphml,
+    /// GEXF 1.3, for Gephi
+    Gexf,
+    /// Graphviz's DOT language, for drawing neighbourhoods of a few pages
+    Dot,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    #[arg(long, value_enum)]
+    format: Format,
+
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// Only export the pages within `--hops` links of this page (repeatable), since GUI tools
+    /// can't open the graph of a whole wiki
+    #[arg(long, visible_alias = "center", value_name = "TITLE")]
+    around: Vec<String>,
+
+    /// Number of links to follow from `--around`
+    #[arg(
+        long,
+        visible_alias = "radius",
+        value_name = "N",
+        default_value_t = 1,
+        requires = "around"
+    )]
+    hops: usize,
+
+    /// Which links to follow from `--around`
+    #[arg(long, value_enum, default_value_t, requires = "around")]
+    direction: Direction,
+
+    /// Compute a force-directed layout for GEXF exports with at most this many nodes, giving
+    /// each node a position Gephi opens it at
+    #[arg(long, value_name = "N", default_value_t = 2000)]
+    layout_max_nodes: usize,
+
+    /// Number of force-directed layout iterations
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    layout_iterations: usize,
+}
+
+pub fn run(args: &Args) {
+    let mut graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let mut centers: Vec<u32> = args
+        .around
+        .iter()
+        .map(|title| path::find_or_exit(&graph, title))
+        .collect();
+    if !args.around.is_empty() {
+        let keep = subgraph::around(&graph, &args.around, args.hops, args.direction);
+        graph = graph.subgraph(&keep);
+        // Subgraphs number their nodes anew, in the same order.
+        centers = centers
+            .iter()
+            .map(|&center| {
+                u32::try_from(keep[..center as usize].iter().filter(|&&kept| kept).count()).unwrap()
+            })
+            .collect();
+    }
+
+    match args.format {
+        Format::Graphml => graphml::write(&args.output, &graph),
+        Format::Gexf => {
+            let layout = (graph.node_count() <= args.layout_max_nodes)
+                .then(|| lay_out(&graph, args.layout_iterations));
+            gexf::write(&args.output, &graph, layout.as_deref())
+        }
+        Format::Dot => {
+            if graph.node_count() > DOT_NODES {
+                tracing::warn!(
+                    "Graphviz takes long to lay out {} nodes; `--around` cuts the graph down",
+                    graph.node_count()
+                );
+            }
+            dot::write(&args.output, &graph, &centers)
+        }
+    }
+    .with_context(|| format!("Failed to write {}", args.output.display()))
+    .unwrap();
+    println!(
+        "Exported {} nodes and {} links",
+        graph.node_count(),
+        graph.edge_count()
+    );
+}
+
+/// Count incoming links for every interned title, indexed by `Key::into_usize`.
+pub fn in_degrees(rodeo: &Rodeo, links: &HashMap<Spur, HashSet<Spur>>) -> Vec<usize> {
+    let mut in_degrees = vec![0; rodeo.len()];
+    for links in links.values() {
+        for link in links {
+            in_degrees[link.into_usize()] += 1;
+        }
+    }
+    in_degrees
+}
+
+/// All interned titles with their sort keys, in MediaWiki listing order.
+fn sorted_nodes<'a>(rodeo: &'a Rodeo, wiki: &'a Wiki) -> Vec<(Spur, &'a str, &'a str)> {
+    let mut nodes: Vec<_> = rodeo
+        .iter()
+        .map(|(key, title)| (key, title, wiki.sort_key(rodeo, key)))
+        .collect();
+    nodes.sort_by_cached_key(|(_, title, sort_key)| (sort::collation(sort_key), *title));
+    nodes
+}
+
+/// Every attribute name used in `attributes`, sorted, for use as extra columns.
+fn attribute_columns<'a>(attributes: impl Iterator<Item = &'a Attributes>) -> Vec<&'a str> {
+    let columns: BTreeSet<&str> = attributes
+        .flat_map(|attributes| attributes.keys().map(String::as_str))
+        .collect();
+    columns.into_iter().collect()
+}
+
+/// Values of the `columns` attributes as CSV fields: strings as they are, anything else as JSON,
+/// and missing attributes as empty fields.
+fn attribute_fields<'a>(attributes: Option<&'a Attributes>, columns: &[&str]) -> Vec<Cow<'a, str>> {
+    columns
+        .iter()
+        .map(
+            |&column| match attributes.and_then(|attributes| attributes.get(column)) {
+                None => Cow::Borrowed(""),
+                Some(serde_json::Value::String(value)) => Cow::Borrowed(value.as_str()),
+                Some(value) => Cow::Owned(value.to_string()),
+            },
+        )
+        .collect()
+}
+
+/// Write a CSV record for each of `items` to `out` with `record`, serializing chunks of
+/// `CHUNK_RECORDS` on up to `threads` threads at once and writing them in order, so the file is
+/// the one a single thread would write. `record` is given a writer made by `builder`.
+pub fn write_chunked<T: Sync>(
+    out: &mut impl Write,
+    builder: &csv::WriterBuilder,
+    items: &[T],
+    threads: usize,
+    record: impl Fn(&mut csv::Writer<&mut Vec<u8>>, &T) -> csv::Result<()> + Sync,
+) -> anyhow::Result<()> {
+    let chunks: Vec<&[T]> = items.chunks(CHUNK_RECORDS).collect();
+    for batch in chunks.chunks(threads.max(1)) {
+        let serialized = thread::scope(|scope| {
+            let serializing: Vec<_> = batch
+                .iter()
+                .map(|chunk| {
+                    scope.spawn(|| {
+                        let mut buffer = Vec::new();
+                        let mut writer = builder.from_writer(&mut buffer);
+                        for item in *chunk {
+                            record(&mut writer, item)?;
+                        }
+                        writer.flush()?;
+                        drop(writer);
+                        anyhow::Ok(buffer)
+                    })
+                })
+                .collect();
+            serializing
+                .into_iter()
+                .map(|thread| {
+                    thread
+                        .join()
+                        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?;
+        for buffer in serialized {
+            out.write_all(&buffer)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crate::{template::template_eq, Wiki};
+use lasso::{Rodeo, Spur};
+use std::{collections::HashMap, fs, path::Path};
+
+/// Write a Gephi export in which the members of each of `categories` are collapsed into one node
+/// named `Category:Name`, with link counts between nodes as edge weights. Pages in several of the
+/// categories join the first one listed. Other pages stay as they are, unless `members_only`,
+/// which drops them and their links for a purely topic-level map. Links among the members of a
+/// category become a self-loop on its node.
+pub fn write(
+    dir: &Path,
+    rodeo: &Rodeo,
+    wiki: &Wiki,
+    categories: &[String],
+    members_only: bool,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let names: Vec<String> = categories
+        .iter()
+        .map(|category| format!("Category:{category}"))
+        .collect();
+
+    let mut groups: HashMap<Spur, usize> = HashMap::new();
+    for (category, page, _) in wiki.category_memberships() {
+        let Some(group) = categories
+            .iter()
+            .position(|chosen| template_eq(chosen, category))
+        else {
+            continue;
+        };
+        groups
+            .entry(page)
+            .and_modify(|current| *current = (*current).min(group))
+            .or_insert(group);
+    }
+
+    let node = |page: &Spur| match groups.get(page) {
+        Some(&group) => Some(names[group].as_str()),
+        None if members_only => None,
+        None => Some(rodeo.resolve(page)),
+    };
+
+    let mut members: HashMap<&str, usize> = HashMap::new();
+    for (page, _) in rodeo.iter() {
+        if let Some(node) = node(&page) {
+            *members.entry(node).or_default() += 1;
+        }
+    }
+
+    let mut weights: HashMap<(&str, &str), u64> = HashMap::new();
+    for (source, links) in &wiki.links {
+        let Some(source) = node(source) else {
+            continue;
+        };
+        for target in links {
+            if let Some(target) = node(target) {
+                *weights.entry((source, target)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut members: Vec<_> = members.into_iter().collect();
+    members.sort_unstable();
+    let mut nodes = csv::Writer::from_path(dir.join("nodes.csv"))?;
+    nodes.write_record(["Id", "Label", "members"])?;
+    for (node, count) in members {
+        nodes.write_record([node, node, count.to_string().as_str()])?;
+    }
+    nodes.flush()?;
+
+    let mut weights: Vec<_> = weights.into_iter().collect();
+    weights.sort_unstable();
+    let mut edges = csv::Writer::from_path(dir.join("edges.csv"))?;
+    edges.write_record(["Source", "Target", "Weight", "Type"])?;
+    for ((source, target), weight) in weights {
+        edges.write_record([source, target, weight.to_string().as_str(), "Directed"])?;
+    }
+    edges.flush()?;
+
+    Ok(())
+}
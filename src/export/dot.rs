@@ -0,0 +1,48 @@
+use crate::graph::Graph;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+/// Write `graph` in Graphviz's DOT language: one node per page labelled with its title, one
+/// directed edge per link, and the graph's metadata as JSON in a comment. The nodes of
+/// `centers` are drawn bold, so the pages an ego network was cut around stand out.
+pub fn write(path: &Path, graph: &Graph, centers: &[u32]) -> anyhow::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let provenance = serde_json::to_string(graph.metadata())?;
+    writeln!(out, "// {provenance}")?;
+    writeln!(out, "digraph wikigraph {{")?;
+    writeln!(out, "  node [shape=box];")?;
+    let nodes = 0..u32::try_from(graph.node_count())?;
+    for node in nodes.clone() {
+        let style = if centers.contains(&node) {
+            ", style=bold"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            r#"  {node} [label="{}"{style}];"#,
+            escape(graph.title(node))
+        )?;
+    }
+    for source in nodes {
+        for &target in graph.links(source) {
+            writeln!(out, "  {source} -> {target};")?;
+        }
+    }
+    writeln!(out, "}}")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// `title` as the inside of a DOT string, in which only quotes and backslashes are special.
+fn escape(title: &str) -> Cow<'_, str> {
+    if title.contains(['"', '\\']) {
+        Cow::Owned(title.replace('\\', r"\\").replace('"', r#"\""#))
+    } else {
+        Cow::Borrowed(title)
+    }
+}
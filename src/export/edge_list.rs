@@ -0,0 +1,166 @@
+use crate::{edge_type::EdgeType, sample, Wiki};
+use lasso::Rodeo;
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// Tab-separated, as read by `networkx.read_edgelist(path, delimiter="\t")`
+    Tsv,
+    /// Comma-separated, with titles quoted where needed
+    Csv,
+}
+
+impl Format {
+    pub fn delimiter(self) -> u8 {
+        match self {
+            Self::Tsv => b'\t',
+            Self::Csv => b',',
+        }
+    }
+}
+
+/// Write one `source, target` line per link, by title and without a header, plus the link's
+/// weight for weighted graphs, as `networkx.read_weighted_edgelist` reads it, and then the edge
+/// type for typed edges. Lines are serialized on up to `threads` threads.
+///
+/// With `shards`, the lines are split over that many files named by `shard_path` instead, each
+/// starting with a header naming its columns, and the links of a page all go in the same one.
+pub fn write(
+    path: &Path,
+    rodeo: &Rodeo,
+    wiki: &Wiki,
+    format: Format,
+    shards: Option<u32>,
+    threads: usize,
+) -> anyhow::Result<()> {
+    let builder = builder(format);
+    let typed = wiki.is_typed();
+    let edges: Vec<_> = wiki.typed_edges().collect();
+    let record = |writer: &mut csv::Writer<&mut Vec<u8>>,
+                  &(source, target, edge_type): &(_, _, Option<EdgeType>)| {
+        let weight = wiki.weight(source, target).map(|weight| weight.to_string());
+        writer.write_record(
+            [rodeo.resolve(&source), rodeo.resolve(&target)]
+                .into_iter()
+                .chain(weight.as_deref())
+                .chain(typed.then(|| edge_type.map_or("", EdgeType::name))),
+        )
+    };
+    let Some(count) = shards else {
+        let mut out = BufWriter::new(File::create(path)?);
+        super::write_chunked(&mut out, &builder, &edges, threads, record)?;
+        out.flush()?;
+        return Ok(());
+    };
+
+    let mut sharded = vec![Vec::new(); count as usize];
+    for edge in edges {
+        sharded[shard(rodeo.resolve(&edge.0), count) as usize].push(edge);
+    }
+    let header = header(wiki.link_counts.is_some(), typed);
+    for (index, edges) in (0..count).zip(&sharded) {
+        let mut out = BufWriter::new(File::create(shard_path(path, index, count))?);
+        let mut writer = builder.from_writer(&mut out);
+        writer.write_record(&header)?;
+        writer.flush()?;
+        drop(writer);
+        super::write_chunked(&mut out, &builder, edges, threads, record)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// The file of shard `index` of `count` of the edge list at `path`, as in
+/// `links-00003-of-00016.tsv` for `links.tsv`.
+pub fn shard_path(path: &Path, index: u32, count: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}-{index:05}-of-{count:05}");
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// The shard of `count` that the links of page `source` go in. It goes by a hash of the title
+/// rather than the page's ID in the graph, which depends on the order pages were read in, so
+/// that a page has the same shard in any parse with the same number of them.
+pub fn shard(source: &str, count: u32) -> u32 {
+    // The remainder is less than `count`.
+    #[allow(clippy::cast_possible_truncation)]
+    let shard = (sample::hash(0, &[source]) % u64::from(count)) as u32;
+    shard
+}
+
+fn header(weighted: bool, typed: bool) -> Vec<&'static str> {
+    ["source", "target"]
+        .into_iter()
+        .chain(weighted.then_some("weight"))
+        .chain(typed.then_some("type"))
+        .collect()
+}
+
+/// An edge list written a page at a time, as `write` writes one for unweighted, untyped links,
+/// for links too many to hold in memory at once.
+pub struct Writer {
+    /// One writer, or one for each shard.
+    shards: Vec<csv::Writer<File>>,
+}
+
+impl Writer {
+    pub fn create(path: &Path, format: Format, shards: Option<u32>) -> anyhow::Result<Self> {
+        let builder = builder(format);
+        let Some(count) = shards else {
+            return Ok(Self {
+                shards: vec![builder.from_path(path)?],
+            });
+        };
+        let shards = (0..count)
+            .map(|index| {
+                let mut writer = builder.from_path(shard_path(path, index, count))?;
+                writer.write_record(header(false, false))?;
+                anyhow::Ok(writer)
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { shards })
+    }
+
+    /// Write the links of `source` to each of `targets`.
+    pub fn add_links<'a>(
+        &mut self,
+        source: &str,
+        targets: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<()> {
+        let count = u32::try_from(self.shards.len())?;
+        let writer = &mut self.shards[shard(source, count) as usize];
+        for target in targets {
+            writer.write_record([source, target])?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        for mut writer in self.shards {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn builder(format: Format) -> csv::WriterBuilder {
+    let mut builder = csv::WriterBuilder::new();
+    builder
+        .delimiter(format.delimiter())
+        .has_headers(false)
+        // Titles can't contain tabs or newlines, so TSV needs no quoting.
+        .quote_style(match format {
+            Format::Tsv => csv::QuoteStyle::Never,
+            Format::Csv => csv::QuoteStyle::Necessary,
+        })
+        .flexible(true);
+    builder
+}
@@ -0,0 +1,128 @@
+use crate::{edge_type::EdgeType, navigation::Kind, wikidata, Wiki};
+use lasso::{Key as _, Rodeo};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+/// Write `nodes.csv` and `edges.csv` into `dir`, using the column names Gephi's spreadsheet
+/// importer recognizes without any manual mapping. Nodes are listed in sort key order, with the
+/// page ID, Wikidata item, namespace, redirect target, and latest revision timestamp of those the
+/// dump has, whether each is a disambiguation page, and whether it is `missing` from the dump, only
+/// linked to. Typed edges get an `edge_type` column, with one row per layer. Edges weigh 1 unless
+/// the links are weighted. Rows are serialized on up to `threads` threads.
+#[allow(clippy::too_many_lines)]
+pub fn write(dir: &Path, rodeo: &Rodeo, wiki: &Wiki, threads: usize) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let in_degrees = super::in_degrees(rodeo, &wiki.links);
+    let builder = csv::WriterBuilder::new();
+
+    let node_columns = super::attribute_columns(wiki.node_attributes.values());
+    let mut nodes = BufWriter::new(File::create(dir.join("nodes.csv"))?);
+    let mut header = builder.from_writer(&mut nodes);
+    header.write_record(
+        [
+            "Id",
+            "Label",
+            "sort_key",
+            "in_degree",
+            "out_degree",
+            "page_id",
+            "wikidata",
+            "namespace",
+            "redirect",
+            "timestamp",
+            "disambiguation",
+            "missing",
+        ]
+        .iter()
+        .chain(&node_columns),
+    )?;
+    header.flush()?;
+    drop(header);
+    super::write_chunked(
+        &mut nodes,
+        &builder,
+        &super::sorted_nodes(rodeo, wiki),
+        threads,
+        |writer, &(key, title, sort_key)| {
+            let in_degree = in_degrees[key.into_usize()];
+            let out_degree = wiki.links.get(&key).map_or(0, HashSet::len);
+            let attributes = super::attribute_fields(wiki.node_attributes.get(&key), &node_columns);
+            let page_id = wiki.page_ids.get(&key).map(u64::to_string);
+            let item = wiki
+                .wikidata_items
+                .get(&key)
+                .map(|&item| wikidata::name(item));
+            let namespace = wiki.namespaces.get(&key).map(i64::to_string);
+            let disambiguation = wiki.navigation.get(&key) == Some(&Kind::Disambiguation);
+            writer.write_record(
+                [
+                    key.into_usize().to_string().as_str(),
+                    title,
+                    sort_key,
+                    in_degree.to_string().as_str(),
+                    out_degree.to_string().as_str(),
+                    page_id.as_deref().unwrap_or_default(),
+                    item.as_deref().unwrap_or_default(),
+                    namespace.as_deref().unwrap_or_default(),
+                    wiki.redirects
+                        .get(&key)
+                        .map_or("", |target| rodeo.resolve(target)),
+                    wiki.timestamps.get(&key).map_or("", String::as_str),
+                    if disambiguation { "true" } else { "false" },
+                    if wiki.links.contains_key(&key) {
+                        "false"
+                    } else {
+                        "true"
+                    },
+                ]
+                .iter()
+                .copied()
+                .chain(attributes.iter().map(AsRef::as_ref)),
+            )
+        },
+    )?;
+    nodes.flush()?;
+
+    let edge_columns = super::attribute_columns(wiki.edge_attributes.values());
+    let mut edges = BufWriter::new(File::create(dir.join("edges.csv"))?);
+    let typed = wiki.is_typed();
+    let mut header = builder.from_writer(&mut edges);
+    header.write_record(
+        ["Source", "Target", "Weight", "Type"]
+            .iter()
+            .chain(typed.then_some(&"edge_type"))
+            .chain(&edge_columns),
+    )?;
+    header.flush()?;
+    drop(header);
+    super::write_chunked(
+        &mut edges,
+        &builder,
+        &wiki.typed_edges().collect::<Vec<_>>(),
+        threads,
+        |writer, &(source, target, edge_type)| {
+            let attributes =
+                super::attribute_fields(wiki.edge_attributes.get(&(source, target)), &edge_columns);
+            let weight = wiki.weight(source, target).unwrap_or(1);
+            writer.write_record(
+                [
+                    source.into_usize().to_string().as_str(),
+                    target.into_usize().to_string().as_str(),
+                    weight.to_string().as_str(),
+                    "Directed",
+                ]
+                .into_iter()
+                .chain(typed.then(|| edge_type.map_or("", EdgeType::name)))
+                .chain(attributes.iter().map(AsRef::as_ref)),
+            )
+        },
+    )?;
+    edges.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::{graph::Graph, wikidata};
+use quick_xml::escape::escape;
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+/// Write `graph` as GEXF 1.3, Gephi's own format: one node per page labelled with its title and
+/// carrying its degrees and Wikidata item, one directed edge per link, weighted if the links are,
+/// and the graph's metadata as JSON in the description. Nodes get a `viz:position` from
+/// `layout`, if given.
+pub fn write(path: &Path, graph: &Graph, layout: Option<&[(f64, f64)]>) -> anyhow::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<gexf xmlns="http://gexf.net/1.3" xmlns:viz="http://gexf.net/1.3/viz" version="1.3">"#
+    )?;
+    writeln!(out, "  <meta>")?;
+    writeln!(
+        out,
+        "    <creator>wikigraph {}</creator>",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    let provenance = serde_json::to_string(graph.metadata())?;
+    writeln!(
+        out,
+        "    <description>{}</description>",
+        escape(&provenance)
+    )?;
+    writeln!(out, "  </meta>")?;
+    writeln!(out, r#"  <graph defaultedgetype="directed">"#)?;
+    writeln!(out, r#"    <attributes class="node">"#)?;
+    writeln!(
+        out,
+        r#"      <attribute id="in_degree" title="in_degree" type="integer"/>"#
+    )?;
+    writeln!(
+        out,
+        r#"      <attribute id="out_degree" title="out_degree" type="integer"/>"#
+    )?;
+    if graph.has_wikidata_items() {
+        writeln!(
+            out,
+            r#"      <attribute id="wikidata" title="wikidata" type="string"/>"#
+        )?;
+    }
+    writeln!(out, "    </attributes>")?;
+    writeln!(out, "    <nodes>")?;
+    let nodes = 0..u32::try_from(graph.node_count())?;
+    for node in nodes.clone() {
+        let item = graph.wikidata_item(node).map_or_else(String::new, |item| {
+            format!(
+                r#"<attvalue for="wikidata" value="{}"/>"#,
+                wikidata::name(item)
+            )
+        });
+        let position = layout.map_or_else(String::new, |layout| {
+            let (x, y) = layout[node as usize];
+            format!(r#"<viz:position x="{x}" y="{y}" z="0.0"/>"#)
+        });
+        writeln!(
+            out,
+            r#"      <node id="{node}" label="{}"><attvalues><attvalue for="in_degree" value="{}"/><attvalue for="out_degree" value="{}"/>{item}</attvalues>{position}</node>"#,
+            escape(graph.title(node)),
+            graph.in_degree(node),
+            graph.out_degree(node)
+        )?;
+    }
+    writeln!(out, "    </nodes>")?;
+    writeln!(out, "    <edges>")?;
+    let mut id = 0_u64;
+    for source in nodes {
+        let weights = graph.link_weights(source);
+        for (edge, &target) in graph.links(source).iter().enumerate() {
+            let weight = weights.map_or_else(String::new, |weights| {
+                format!(r#" weight="{}""#, weights[edge])
+            });
+            writeln!(
+                out,
+                r#"      <edge id="{id}" source="{source}" target="{target}"{weight}/>"#
+            )?;
+            id += 1;
+        }
+    }
+    writeln!(out, "    </edges>")?;
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</gexf>")?;
+    out.flush()?;
+    Ok(())
+}
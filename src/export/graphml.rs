@@ -0,0 +1,83 @@
+use crate::{graph::Graph, wikidata};
+use quick_xml::escape::escape;
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+/// Write `graph` as GraphML, which yEd, Gephi, Cytoscape, and NetworkX read: one node per page
+/// labelled with its title and carrying its degrees and Wikidata item, one directed edge per link
+/// with its weight if the links are weighted, and the graph's metadata as JSON in a `provenance`
+/// attribute of the graph.
+pub fn write(path: &Path, graph: &Graph) -> anyhow::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    writeln!(
+        out,
+        r#"  <key id="provenance" for="graph" attr.name="provenance" attr.type="string"/>"#
+    )?;
+    writeln!(
+        out,
+        r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+    )?;
+    for degree in ["in_degree", "out_degree"] {
+        writeln!(
+            out,
+            r#"  <key id="{degree}" for="node" attr.name="{degree}" attr.type="int"/>"#
+        )?;
+    }
+    if graph.has_wikidata_items() {
+        writeln!(
+            out,
+            r#"  <key id="wikidata" for="node" attr.name="wikidata" attr.type="string"/>"#
+        )?;
+    }
+    if graph.is_weighted() {
+        writeln!(
+            out,
+            r#"  <key id="weight" for="edge" attr.name="weight" attr.type="int"/>"#
+        )?;
+    }
+    writeln!(out, r#"  <graph id="G" edgedefault="directed">"#)?;
+    let provenance = serde_json::to_string(graph.metadata())?;
+    writeln!(
+        out,
+        r#"    <data key="provenance">{}</data>"#,
+        escape(&provenance)
+    )?;
+    let nodes = 0..u32::try_from(graph.node_count())?;
+    for node in nodes.clone() {
+        let item = graph.wikidata_item(node).map_or_else(String::new, |item| {
+            format!(r#"<data key="wikidata">{}</data>"#, wikidata::name(item))
+        });
+        writeln!(
+            out,
+            r#"    <node id="n{node}"><data key="label">{}</data><data key="in_degree">{}</data><data key="out_degree">{}</data>{item}</node>"#,
+            escape(graph.title(node)),
+            graph.in_degree(node),
+            graph.out_degree(node)
+        )?;
+    }
+    for source in nodes {
+        let weights = graph.link_weights(source);
+        for (edge, &target) in graph.links(source).iter().enumerate() {
+            match weights {
+                Some(weights) => writeln!(
+                    out,
+                    r#"    <edge source="n{source}" target="n{target}"><data key="weight">{}</data></edge>"#,
+                    weights[edge]
+                )?,
+                None => writeln!(out, r#"    <edge source="n{source}" target="n{target}"/>"#)?,
+            }
+        }
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    out.flush()?;
+    Ok(())
+}
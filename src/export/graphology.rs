@@ -0,0 +1,163 @@
+use crate::{edge_type::EdgeType, graph::Metadata, script::Attributes, wikidata, Wiki};
+use lasso::{Key as _, Rodeo};
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+#[derive(Serialize)]
+struct Graph<'a> {
+    attributes: GraphAttributes<'a>,
+    options: Options,
+    nodes: Vec<Node<'a>>,
+    edges: Vec<Edge<'a>>,
+}
+
+#[derive(Serialize)]
+struct GraphAttributes<'a> {
+    provenance: &'a Metadata,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Options {
+    r#type: &'static str,
+    multi: bool,
+    allow_self_loops: bool,
+}
+
+#[derive(Serialize)]
+struct Node<'a> {
+    key: String,
+    attributes: NodeAttributes<'a>,
+}
+
+#[derive(Serialize)]
+struct NodeAttributes<'a> {
+    label: &'a str,
+    sort_key: &'a str,
+    in_degree: usize,
+    out_degree: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_id: Option<u64>,
+    /// The page's Wikidata item, as in `Q42`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wikidata: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<i64>,
+    /// Title of the page a redirect leads to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redirect: Option<&'a str>,
+    /// Of the page's latest revision.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<f64>,
+    /// Attributes set by `--script`.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    extra: Option<&'a Attributes>,
+}
+
+#[derive(Serialize)]
+struct Edge<'a> {
+    source: String,
+    target: String,
+    #[serde(skip_serializing_if = "EdgeAttributes::is_empty")]
+    attributes: EdgeAttributes<'a>,
+}
+
+#[derive(Serialize)]
+struct EdgeAttributes<'a> {
+    /// Not `type`, which sigma.js reads as the program to render the edge with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edge_type: Option<&'static str>,
+    /// Read by sigma.js as the edge's thickness, once mapped to `size`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<u32>,
+    /// Attributes set by `--script`.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    extra: Option<&'a Attributes>,
+}
+
+impl EdgeAttributes<'_> {
+    fn is_empty(&self) -> bool {
+        self.edge_type.is_none() && self.weight.is_none() && self.extra.is_none()
+    }
+}
+
+/// Write the graph in graphology's serialization format, which sigma.js can load with
+/// `Graph.from(json)`. Nodes have the page ID, namespace, redirect target, and latest revision
+/// timestamp the dump gives them, and positions from `layout` as `x`/`y` attributes. Typed
+/// edges make it a multigraph, with one edge per layer and its type as `edge_type`. Weighted
+/// links have a `weight`. The graph's own attributes hold `provenance`.
+pub fn write(
+    path: &Path,
+    rodeo: &Rodeo,
+    wiki: &Wiki,
+    layout: Option<&[(f64, f64)]>,
+    provenance: &Metadata,
+) -> anyhow::Result<()> {
+    let in_degrees = super::in_degrees(rodeo, &wiki.links);
+
+    let nodes = super::sorted_nodes(rodeo, wiki)
+        .into_iter()
+        .map(|(key, title, sort_key)| {
+            let position = layout.map(|layout| layout[key.into_usize()]);
+            Node {
+                key: key.into_usize().to_string(),
+                attributes: NodeAttributes {
+                    label: title,
+                    sort_key,
+                    in_degree: in_degrees[key.into_usize()],
+                    out_degree: wiki.links.get(&key).map_or(0, HashSet::len),
+                    page_id: wiki.page_ids.get(&key).copied(),
+                    wikidata: wiki
+                        .wikidata_items
+                        .get(&key)
+                        .map(|&item| wikidata::name(item)),
+                    namespace: wiki.namespaces.get(&key).copied(),
+                    redirect: wiki.redirects.get(&key).map(|target| rodeo.resolve(target)),
+                    timestamp: wiki.timestamps.get(&key).map(String::as_str),
+                    x: position.map(|(x, _)| x),
+                    y: position.map(|(_, y)| y),
+                    extra: wiki.node_attributes.get(&key),
+                },
+            }
+        })
+        .collect();
+
+    let edges = wiki
+        .typed_edges()
+        .map(|(source, target, edge_type)| Edge {
+            source: source.into_usize().to_string(),
+            target: target.into_usize().to_string(),
+            attributes: EdgeAttributes {
+                edge_type: edge_type.map(EdgeType::name),
+                weight: wiki.weight(source, target),
+                extra: wiki.edge_attributes.get(&(source, target)),
+            },
+        })
+        .collect();
+
+    let graph = Graph {
+        attributes: GraphAttributes { provenance },
+        options: Options {
+            r#type: "directed",
+            multi: wiki.is_typed(),
+            allow_self_loops: true,
+        },
+        nodes,
+        edges,
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &graph)?;
+    writer.flush()?;
+
+    Ok(())
+}
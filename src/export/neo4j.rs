@@ -0,0 +1,83 @@
+use crate::{navigation::Kind, Wiki};
+use lasso::{Key as _, Rodeo};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+/// Write `nodes.csv` and `rels.csv` into `dir` with the headers `neo4j-admin database import`
+/// reads, to be loaded with `--nodes=nodes.csv --relationships=rels.csv`. Every node is a `Page`,
+/// redirects are a `Redirect` too, disambiguation pages a `Disambiguation`, and titles the dump
+/// has no page for a `Missing`. A redirect's link to its target is a `REDIRECTS_TO` relationship,
+/// typed edges have their type in upper case, and other links are `LINKS_TO`, with a `weight` if
+/// the links are weighted. Rows are serialized on up to `threads` threads.
+pub fn write(dir: &Path, rodeo: &Rodeo, wiki: &Wiki, threads: usize) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    let builder = csv::WriterBuilder::new();
+
+    let mut nodes = BufWriter::new(File::create(dir.join("nodes.csv"))?);
+    let mut header = builder.from_writer(&mut nodes);
+    header.write_record([":ID", "title", ":LABEL"])?;
+    header.flush()?;
+    drop(header);
+    super::write_chunked(
+        &mut nodes,
+        &builder,
+        &rodeo.iter().collect::<Vec<_>>(),
+        threads,
+        |writer, &(key, title)| {
+            let label = if wiki.redirects.contains_key(&key) {
+                "Page;Redirect"
+            } else if wiki.navigation.get(&key) == Some(&Kind::Disambiguation) {
+                "Page;Disambiguation"
+            } else if !wiki.links.contains_key(&key) {
+                "Page;Missing"
+            } else {
+                "Page"
+            };
+            writer.write_record([key.into_usize().to_string().as_str(), title, label])
+        },
+    )?;
+    nodes.flush()?;
+
+    let weighted = wiki.link_counts.is_some();
+    let mut rels = BufWriter::new(File::create(dir.join("rels.csv"))?);
+    let mut header = builder.from_writer(&mut rels);
+    header.write_record(
+        [":START_ID", ":END_ID", ":TYPE"]
+            .into_iter()
+            .chain(weighted.then_some("weight:int")),
+    )?;
+    header.flush()?;
+    drop(header);
+    super::write_chunked(
+        &mut rels,
+        &builder,
+        &wiki.typed_edges().collect::<Vec<_>>(),
+        threads,
+        |writer, &(source, target, edge_type)| {
+            let kind = if wiki.redirects.get(&source) == Some(&target) {
+                String::from("REDIRECTS_TO")
+            } else {
+                edge_type.map_or_else(
+                    || String::from("LINKS_TO"),
+                    |edge_type| edge_type.name().to_uppercase(),
+                )
+            };
+            let weight = wiki.weight(source, target).map(|weight| weight.to_string());
+            writer.write_record(
+                [
+                    source.into_usize().to_string().as_str(),
+                    target.into_usize().to_string().as_str(),
+                    kind.as_str(),
+                ]
+                .into_iter()
+                .chain(weight.as_deref()),
+            )
+        },
+    )?;
+    rels.flush()?;
+
+    Ok(())
+}
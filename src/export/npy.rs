@@ -0,0 +1,148 @@
+use crate::{edge_type::EdgeType, graph::Metadata, Wiki};
+use anyhow::Context as _;
+use lasso::{Key as _, Rodeo, Spur};
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// A scalar type that can be stored in an `.npy` array.
+pub trait Element: Copy {
+    /// NumPy `descr` string, e.g. `<u4` for little-endian `uint32`.
+    const DESCR: &'static str;
+
+    fn write_le(self, writer: &mut impl Write) -> io::Result<()>;
+}
+
+impl Element for u32 {
+    const DESCR: &'static str = "<u4";
+
+    fn write_le(self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+impl Element for i64 {
+    const DESCR: &'static str = "<i8";
+
+    fn write_le(self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+impl Element for f32 {
+    const DESCR: &'static str = "<f4";
+
+    fn write_le(self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+/// Write a C-order array in NumPy's `.npy` v1.0 format. `data` must yield exactly as many
+/// elements as `shape` describes.
+pub fn write_array<T: Element>(
+    path: &Path,
+    shape: &[usize],
+    data: impl IntoIterator<Item = T>,
+) -> anyhow::Result<()> {
+    let shape = match shape {
+        [n] => format!("({n},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}",
+        T::DESCR
+    );
+    // Magic (6) + version (2) + header length (2) + header + newline must be 64-byte aligned.
+    let padding = 63 - (10 + header.len()) % 64;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+    let header_len = u16::try_from(header.len()).context("NumPy header is too long")?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&header_len.to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for element in data {
+        element.write_le(&mut writer)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Index<'a> {
+    num_nodes: usize,
+    num_edges: usize,
+    titles: Vec<&'a str>,
+    /// Names of the numbers in `edge_types.npy`, if the edges are typed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edge_types: Option<Vec<&'static str>>,
+    provenance: &'a Metadata,
+}
+
+/// Write the graph as sparse COO arrays (`sources.npy`, `targets.npy`) plus an `index.json`
+/// mapping node IDs to titles and recording `provenance`, so it loads with `np.load` and
+/// `scipy.sparse.coo_array`. Typed edges appear once per layer, with the layer's number in
+/// `edge_types.npy`, or the largest `uint32` for edges without a type.
+pub fn write(dir: &Path, rodeo: &Rodeo, wiki: &Wiki, provenance: &Metadata) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let edges: Vec<(u32, u32, Option<EdgeType>)> = wiki
+        .typed_edges()
+        .map(|(source, target, edge_type)| Ok((id(source)?, id(target)?, edge_type)))
+        .collect::<anyhow::Result<_>>()?;
+
+    write_array(
+        &dir.join("sources.npy"),
+        &[edges.len()],
+        edges.iter().map(|(source, _, _)| *source),
+    )?;
+    write_array(
+        &dir.join("targets.npy"),
+        &[edges.len()],
+        edges.iter().map(|(_, target, _)| *target),
+    )?;
+    let typed = wiki.is_typed();
+    if typed {
+        write_array(
+            &dir.join("edge_types.npy"),
+            &[edges.len()],
+            edges
+                .iter()
+                .map(|(_, _, edge_type)| edge_type.map_or(u32::MAX, EdgeType::number)),
+        )?;
+    }
+
+    let index = Index {
+        num_nodes: rodeo.len(),
+        num_edges: edges.len(),
+        titles: rodeo.strings().collect(),
+        edge_types: typed.then(|| {
+            EdgeType::ALL
+                .iter()
+                .map(|edge_type| edge_type.name())
+                .collect()
+        }),
+        provenance,
+    };
+    let mut writer = BufWriter::new(File::create(dir.join("index.json"))?);
+    serde_json::to_writer(&mut writer, &index)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn id(key: Spur) -> anyhow::Result<u32> {
+    u32::try_from(key.into_usize()).context("Node ID does not fit in uint32")
+}
@@ -0,0 +1,116 @@
+use crate::{edge_type::EdgeType, Wiki};
+use anyhow::Context as _;
+use lasso::{Key as _, Rodeo, Spur};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::{self, File},
+    path::Path,
+};
+
+/// Namespace numbers every MediaWiki site shares, with the names used for partition files.
+/// Talk namespaces are the next odd number.
+const NAMESPACES: &[(i64, &str, &[&str])] = &[
+    (0, "article", &[]),
+    (2, "user", &["User"]),
+    (4, "project", &["Project", "WP"]),
+    (6, "file", &["File", "Image"]),
+    (8, "mediawiki", &["MediaWiki"]),
+    (10, "template", &["Template"]),
+    (12, "help", &["Help"]),
+    (14, "category", &["Category"]),
+    (100, "portal", &["Portal"]),
+    (118, "draft", &["Draft"]),
+    (828, "module", &["Module"]),
+];
+
+/// Write the links into one `<source>-<target>.csv` file per pair of namespaces in `dir`, like
+/// `article-category.csv`, with `source,target` node IDs (plus `edge_type` for typed edges),
+/// and a `nodes.csv` of `id,title,ns` shared by all of them. Pages take their namespace from
+/// the dump; link targets missing from it are placed by their title's prefix.
+pub fn write(dir: &Path, rodeo: &Rodeo, wiki: &Wiki) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let namespace = |node: Spur| {
+        wiki.namespaces
+            .get(&node)
+            .copied()
+            .unwrap_or_else(|| namespace_of(rodeo.resolve(&node)))
+    };
+
+    let mut nodes = csv::Writer::from_path(dir.join("nodes.csv"))?;
+    nodes.write_record(["id", "title", "ns"])?;
+    for (key, title) in rodeo.iter() {
+        nodes.write_record([
+            key.into_usize().to_string().as_str(),
+            title,
+            namespace(key).to_string().as_str(),
+        ])?;
+    }
+    nodes.flush()?;
+
+    let typed = wiki.is_typed();
+    let mut partitions: HashMap<(i64, i64), csv::Writer<File>> = HashMap::new();
+    for (source, target, edge_type) in wiki.typed_edges() {
+        let pair = (namespace(source), namespace(target));
+        let writer = match partitions.entry(pair) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = dir.join(format!("{}-{}.csv", name(pair.0), name(pair.1)));
+                let mut writer = csv::Writer::from_path(&path)
+                    .with_context(|| format!("Failed to create {}", path.display()))?;
+                writer.write_record(
+                    ["source", "target"]
+                        .into_iter()
+                        .chain(typed.then_some("edge_type")),
+                )?;
+                entry.insert(writer)
+            }
+        };
+        writer.write_record(
+            [
+                source.into_usize().to_string().as_str(),
+                target.into_usize().to_string().as_str(),
+            ]
+            .into_iter()
+            .chain(typed.then(|| edge_type.map_or("", EdgeType::name))),
+        )?;
+    }
+    for writer in partitions.values_mut() {
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// The namespace a title is in, going by its prefix.
+fn namespace_of(title: &str) -> i64 {
+    let Some((prefix, _)) = title.split_once(':') else {
+        return 0;
+    };
+    let prefix = prefix.trim().replace('_', " ");
+    let (prefix, talk) = match prefix.strip_suffix(" talk") {
+        Some(subject) => (subject, 1),
+        None if prefix.eq_ignore_ascii_case("talk") => return 1,
+        None => (prefix.as_str(), 0),
+    };
+    NAMESPACES
+        .iter()
+        .find(|(_, _, prefixes)| {
+            prefixes
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(prefix))
+        })
+        .map_or(0, |&(number, _, _)| number + talk)
+}
+
+/// The file name part for namespace `number`: its name, `_talk` for a talk namespace, or
+/// `ns<number>` for namespaces without a common name.
+fn name(number: i64) -> String {
+    let subject = number - number.rem_euclid(2);
+    match NAMESPACES.iter().find(|&&(n, _, _)| n == subject) {
+        Some((_, "article", _)) if number == 1 => String::from("talk"),
+        Some((_, name, _)) if number == subject => String::from(*name),
+        Some((_, name, _)) => format!("{name}_talk"),
+        None => format!("ns{number}"),
+    }
+}
@@ -0,0 +1,139 @@
+use super::npy::write_array;
+use crate::{edge_type::EdgeType, graph::Metadata, Wiki};
+use lasso::{Key as _, Rodeo};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+/// Features every node gets, before any numeric attributes from `--script`.
+const FEATURES: &[&str] = &["in_degree", "out_degree", "is_redirect", "exists", "ns"];
+
+#[derive(Serialize)]
+struct Meta<'a> {
+    num_nodes: usize,
+    num_edges: usize,
+    /// Column names of `x.npy`.
+    features: Vec<&'a str>,
+    /// Names of the numbers in `edge_type.npy`, if the edges are typed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edge_types: Option<Vec<&'static str>>,
+    provenance: &'a Metadata,
+}
+
+/// Write the tensors of a PyTorch Geometric `Data` object: `edge_index.npy` (int64, 2 × edges),
+/// `x.npy` (float32, nodes × features), `meta.json` naming the feature columns and recording
+/// `provenance`, and `nodes.csv`
+/// mapping node IDs to titles. Loads with
+/// `Data(x=torch.from_numpy(np.load("x.npy")), edge_index=torch.from_numpy(np.load("edge_index.npy")))`.
+/// Features that don't apply to a node, like the namespace of a page missing from the dump, are
+/// NaN. Typed edges appear once per layer, with the layer's number (or -1 for none) in
+/// `edge_type.npy`, as relational models like `RGCNConv` expect.
+// Features are float32, as PyTorch expects; large counts losing precision doesn't matter.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+pub fn write(dir: &Path, rodeo: &Rodeo, wiki: &Wiki, provenance: &Metadata) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let edges: Vec<(i64, i64, Option<EdgeType>)> = wiki
+        .typed_edges()
+        .map(|(source, target, edge_type)| {
+            Ok((
+                id(source.into_usize())?,
+                id(target.into_usize())?,
+                edge_type,
+            ))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    write_array(
+        &dir.join("edge_index.npy"),
+        &[2, edges.len()],
+        edges
+            .iter()
+            .map(|(source, _, _)| *source)
+            .chain(edges.iter().map(|(_, target, _)| *target)),
+    )?;
+    let typed = wiki.is_typed();
+    if typed {
+        write_array(
+            &dir.join("edge_type.npy"),
+            &[edges.len()],
+            edges.iter().map(|(_, _, edge_type)| {
+                edge_type.map_or(-1_i64, |edge_type| edge_type.number().into())
+            }),
+        )?;
+    }
+
+    // Script attributes that are numbers or booleans somewhere become extra features, unless
+    // they clash with a built-in one.
+    let extra: BTreeSet<&str> = wiki
+        .node_attributes
+        .values()
+        .flat_map(|attributes| attributes.iter())
+        .filter(|(_, value)| value.is_number() || value.is_boolean())
+        .map(|(name, _)| name.as_str())
+        .filter(|name| !FEATURES.contains(name))
+        .collect();
+    let features: Vec<&str> = FEATURES.iter().copied().chain(extra).collect();
+
+    let in_degrees = super::in_degrees(rodeo, &wiki.links);
+    let mut x = Vec::with_capacity(rodeo.len() * features.len());
+    for (key, _) in rodeo.iter() {
+        x.push(in_degrees[key.into_usize()] as f32);
+        x.push(wiki.links.get(&key).map_or(0, HashSet::len) as f32);
+        x.push(flag(wiki.redirects.contains_key(&key)));
+        x.push(flag(wiki.links.contains_key(&key)));
+        x.push(wiki.namespaces.get(&key).map_or(f32::NAN, |&ns| ns as f32));
+        for name in &features[FEATURES.len()..] {
+            let value = wiki
+                .node_attributes
+                .get(&key)
+                .and_then(|attributes| attributes.get(*name));
+            x.push(match value {
+                Some(serde_json::Value::Bool(value)) => flag(*value),
+                Some(value) => value.as_f64().map_or(f32::NAN, |value| value as f32),
+                None => f32::NAN,
+            });
+        }
+    }
+    write_array(&dir.join("x.npy"), &[rodeo.len(), features.len()], x)?;
+
+    let meta = Meta {
+        num_nodes: rodeo.len(),
+        num_edges: edges.len(),
+        features,
+        edge_types: typed.then(|| {
+            EdgeType::ALL
+                .iter()
+                .map(|edge_type| edge_type.name())
+                .collect()
+        }),
+        provenance,
+    };
+    let mut writer = BufWriter::new(File::create(dir.join("meta.json"))?);
+    serde_json::to_writer(&mut writer, &meta)?;
+    writer.flush()?;
+
+    let mut nodes = csv::Writer::from_path(dir.join("nodes.csv"))?;
+    nodes.write_record(["id", "title"])?;
+    for (key, title) in rodeo.iter() {
+        nodes.write_record([key.into_usize().to_string().as_str(), title])?;
+    }
+    nodes.flush()?;
+
+    Ok(())
+}
+
+fn id(key: usize) -> anyhow::Result<i64> {
+    Ok(i64::try_from(key)?)
+}
+
+const fn flag(value: bool) -> f32 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
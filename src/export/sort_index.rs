@@ -0,0 +1,38 @@
+use crate::{sort, Wiki};
+use lasso::Rodeo;
+use std::path::Path;
+
+/// Write `title,sort_key` for every page, ordered by collated sort key.
+pub fn write(path: &Path, rodeo: &Rodeo, wiki: &Wiki) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["title", "sort_key"])?;
+    for (_, title, sort_key) in super::sorted_nodes(rodeo, wiki) {
+        writer.write_record([title, sort_key])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write `category,sort_key,title` for every category membership, grouped by category and
+/// ordered within each one the way its category page lists members: by the membership's own
+/// sort key, falling back to the page's DEFAULTSORT key, falling back to its title.
+pub fn write_categories(path: &Path, rodeo: &Rodeo, wiki: &Wiki) -> anyhow::Result<()> {
+    let mut members: Vec<(&str, &str, &str)> = wiki
+        .category_memberships()
+        .map(|(category, page, key)| {
+            let sort_key = key.unwrap_or_else(|| wiki.sort_key(rodeo, page));
+            (category, sort_key, rodeo.resolve(&page))
+        })
+        .collect();
+    members.sort_by_cached_key(|(category, sort_key, title)| {
+        (*category, sort::collation(sort_key), *title)
+    });
+
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["category", "sort_key", "title"])?;
+    for (category, sort_key, title) in members {
+        writer.write_record([category, sort_key, title])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
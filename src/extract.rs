@@ -0,0 +1,117 @@
+//! One page of a multistream dump, read from the bzip2 stream its index says holds it, so that
+//! the wikitext or links of a problem article can be looked at in a second rather than after a
+//! parse of the whole dump.
+
+use crate::{config, progress::Progress, Command, Extractor, ParseArgs};
+use anyhow::Context as _;
+use clap::Parser as _;
+use std::{collections::HashSet, ffi::OsString, path::PathBuf};
+use wikigraph::dump::{self, Page};
+
+#[derive(clap::Args)]
+#[command(group(clap::ArgGroup::new("page").required(true).args(["title", "id"])))]
+pub struct Args {
+    /// Multistream dump, such as `enwiki-20240601-pages-articles-multistream.xml.bz2`
+    dump: PathBuf,
+
+    /// Index of the dump [default: the `-index.txt.bz2` file beside it]
+    #[arg(long, value_name = "FILE")]
+    index: Option<PathBuf>,
+
+    /// Title of the page, with spaces or underscores between words
+    #[arg(long)]
+    title: Option<String>,
+
+    /// ID of the page
+    #[arg(long)]
+    id: Option<u64>,
+
+    /// Print the targets of the page's links, one per line in the order first found, as `parse`
+    /// finds and normalizes them, instead of its wikitext
+    #[arg(long)]
+    links: bool,
+
+    /// Options of `parse` to find the links with, as in `wikigraph extract dump.xml.bz2 --title
+    /// Rust --links -- --no-template-links`
+    #[arg(last = true, value_name = "PARSE_ARGS", requires = "links")]
+    parse: Vec<OsString>,
+}
+
+pub fn run(args: &Args) {
+    let index = args.index.clone().unwrap_or_else(|| {
+        dump::index_path(&args.dump)
+            .with_context(|| format!("No index beside {}, so give one", args.dump.display()))
+            .unwrap()
+    });
+    let title = args.title.as_ref().map(|title| title.replace('_', " "));
+    let page = dump::indexed_page(&args.dump, &index, |entry| {
+        title
+            .as_ref()
+            .map_or(Some(entry.id) == args.id, |title| entry.title == *title)
+    })
+    .context("Failed to read the page")
+    .unwrap();
+    let Some(page) = page else {
+        match (&title, args.id) {
+            (Some(title), _) => eprintln!("The index lists no page titled '{title}'"),
+            (None, Some(id)) => eprintln!("The index lists no page with ID {id}"),
+            (None, None) => unreachable!("A title or ID is required"),
+        }
+        std::process::exit(1);
+    };
+    tracing::info!(
+        "'{}' (ID {}, namespace {})",
+        page.title,
+        page.id
+            .map_or_else(|| String::from("none"), |id| id.to_string()),
+        page.namespace
+    );
+
+    if args.links {
+        for target in links(args, page) {
+            println!("{target}");
+        }
+    } else {
+        println!("{}", page.text);
+    }
+}
+
+/// The distinct link targets of `page`, found as a parse with the `PARSE_ARGS` of `args` would.
+fn links(args: &Args, page: Page) -> Vec<String> {
+    let command = ["wikigraph", "parse"]
+        .into_iter()
+        .map(OsString::from)
+        .chain([args.dump.as_os_str().to_owned()])
+        .chain(args.parse.iter().cloned())
+        .collect();
+    let command = config::expand(command)
+        .context("Failed to read config")
+        .unwrap();
+    let Command::Parse(mut parse) = crate::Args::try_parse_from(command)
+        .unwrap_or_else(|error| error.exit())
+        .command
+    else {
+        unreachable!("The command line is a `parse`");
+    };
+    // The page was asked for, whatever namespaces a parse would keep.
+    parse.namespaces.push(page.namespace);
+    let parse: &ParseArgs = &parse;
+    let profile = crate::link_profile(parse, &args.dump);
+    let progress = Progress::start(false);
+    let extractor = Extractor::new(parse, &profile, &args.dump, &progress);
+    let title = page.title.clone();
+    let Some(extracted) = extractor.extract(page) else {
+        tracing::warn!("A parse would leave out '{title}'");
+        return Vec::new();
+    };
+    if let Some(redirect) = &extracted.page.redirect {
+        tracing::info!("'{title}' redirects to '{redirect}'");
+    }
+    let mut seen = HashSet::new();
+    extracted
+        .targets
+        .into_iter()
+        .filter(|target| seen.insert(target.clone()))
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,196 @@
+//! Downloading a wiki's multistream dump and its index from dumps.wikimedia.org, or a mirror
+//! laid out the same way, resuming downloads cut short and checking them against the published
+//! checksums, so that a parse can start from a dump known to be whole.
+
+use crate::checksum::{self, Algorithm};
+use anyhow::Context as _;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    io::{self, Read as _},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use wikigraph::remote;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Database name of the wiki, such as `enwiki` or `dewiktionary`
+    #[arg(long)]
+    wiki: String,
+
+    /// Date of the dump, as `YYYYMMDD`, or `latest` for the most recent one
+    #[arg(long, default_value = "latest")]
+    date: String,
+
+    /// Directory to download into, where an earlier download is resumed or reused
+    #[arg(long, short, value_name = "DIR", default_value = ".")]
+    output: PathBuf,
+
+    /// Base URL of the dumps
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "https://dumps.wikimedia.org"
+    )]
+    mirror: String,
+
+    /// Times in a row to retry a failed request or read before giving up
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    retries: u32,
+
+    /// Most bytes to download a second
+    #[arg(long, value_name = "BYTES")]
+    max_bandwidth: Option<u64>,
+
+    /// Parse the dump once downloaded, with the arguments after `--`, as in `wikigraph fetch
+    /// --wiki enwiki --parse -- --graph enwiki.graph`
+    #[arg(long)]
+    parse: bool,
+
+    #[arg(last = true, value_name = "PARSE_ARGS", requires = "parse")]
+    then: Vec<OsString>,
+}
+
+/// Download the dump and its index, and return the command line of the `parse` to run on the
+/// dump if there is to be one.
+pub fn run(args: &Args) -> Option<Vec<OsString>> {
+    let options = remote::Options {
+        retries: args.retries,
+        max_bandwidth: args.max_bandwidth,
+        spool: Some(args.output.clone()),
+    };
+    let mirror = args.mirror.trim_end_matches('/');
+    let date = if args.date == "latest" {
+        latest_date(mirror, &args.wiki, &options)
+            .context("Failed to find the latest dump")
+            .unwrap()
+    } else {
+        args.date.clone()
+    };
+    let base = format!("{mirror}/{}/{date}", args.wiki);
+    let (algorithm, sums) = published_sums(&base, &args.wiki, &date, &options)
+        .context("Failed to read the published checksums")
+        .unwrap();
+
+    let prefix = format!("{}-{date}-pages-articles-multistream", args.wiki);
+    let mut dump = None;
+    for name in [
+        format!("{prefix}.xml.bz2"),
+        format!("{prefix}-index.txt.bz2"),
+    ] {
+        let expected = sums
+            .get(&name)
+            .with_context(|| format!("No checksum is published for {name}; is the dump finished?"))
+            .unwrap();
+        let path = download(
+            &format!("{base}/{name}"),
+            &args.output,
+            (algorithm, expected),
+            &options,
+        )
+        .with_context(|| format!("Failed to download {name}"))
+        .unwrap();
+        dump.get_or_insert(path);
+    }
+    let dump = dump.unwrap();
+    println!("{}", dump.display());
+
+    args.parse.then(|| {
+        ["wikigraph", "parse"]
+            .into_iter()
+            .map(OsString::from)
+            .chain([dump.into_os_string()])
+            .chain(args.then.iter().cloned())
+            .collect()
+    })
+}
+
+/// The date of the latest finished dump of `wiki`, from the RSS feed published beside it.
+fn latest_date(mirror: &str, wiki: &str, options: &remote::Options) -> anyhow::Result<String> {
+    let url =
+        format!("{mirror}/{wiki}/latest/{wiki}-latest-pages-articles-multistream.xml.bz2-rss.xml");
+    let feed = read_text(&url, options)?;
+    let link = regex::Regex::new(&format!(r"/{}/(\d{{8}})", regex::escape(wiki)))?;
+    let date = link
+        .captures(&feed)
+        .with_context(|| format!("No dump date in {url}"))?[1]
+        .to_owned();
+    tracing::info!("The latest dump of {wiki} is from {date}");
+    Ok(date)
+}
+
+/// The SHA-1 checksums published for the dump at `base`, or the MD5 ones if there are none.
+fn published_sums(
+    base: &str,
+    wiki: &str,
+    date: &str,
+    options: &remote::Options,
+) -> anyhow::Result<(Algorithm, HashMap<String, String>)> {
+    let mut failure = None;
+    for (algorithm, name) in [(Algorithm::Sha1, "sha1sums"), (Algorithm::Md5, "md5sums")] {
+        match read_text(&format!("{base}/{wiki}-{date}-{name}.txt"), options) {
+            Ok(text) => return Ok((algorithm, checksum::parse(&text)?)),
+            Err(error) => failure = Some(error),
+        }
+    }
+    Err(failure.unwrap())
+}
+
+/// The small text file at `url`, never spooled.
+fn read_text(url: &str, options: &remote::Options) -> anyhow::Result<String> {
+    let options = remote::Options {
+        spool: None,
+        ..options.clone()
+    };
+    let mut text = String::new();
+    remote::open(url, &options)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Download `url` into `dir`, unless a copy matching the `expected` checksum is already there,
+/// and return the path of the file.
+fn download(
+    url: &str,
+    dir: &Path,
+    (algorithm, expected): (Algorithm, &str),
+    options: &remote::Options,
+) -> anyhow::Result<PathBuf> {
+    let path = dir.join(remote::file_name(url));
+    if path.is_file() {
+        match checksum::verify(&path, algorithm, expected) {
+            Ok(()) => {
+                tracing::info!("{} is already downloaded", path.display());
+                return Ok(path);
+            }
+            Err(error) => {
+                tracing::warn!("Downloading again: {error:#}");
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+
+    tracing::info!("Downloading {url}");
+    let bar = ProgressBar::new_spinner().with_style(ProgressStyle::with_template(
+        "{spinner} {bytes} downloaded, {bytes_per_sec}",
+    )?);
+    bar.enable_steady_tick(Duration::from_millis(250));
+    io::copy(
+        &mut bar.wrap_read(remote::open(url, options)?),
+        &mut io::sink(),
+    )?;
+    bar.finish_and_clear();
+
+    tracing::info!(
+        "Checking {} against its {} checksum",
+        path.display(),
+        algorithm.name()
+    );
+    if let Err(error) = checksum::verify(&path, algorithm, expected) {
+        fs::remove_file(&path)?;
+        return Err(error);
+    }
+    Ok(path)
+}
@@ -0,0 +1,576 @@
+//! A small expression language for choosing nodes and edges, like
+//! `ns == 0 && !is_redirect && out_degree < 500`.
+//!
+//! Expressions combine attribute names, numbers, `"strings"`, `true`, and `false` with `==`, `!=`,
+//! `<`, `<=`, `>`, `>=`, `&&`, `||`, `!`, and parentheses. Missing attributes are `null`, which
+//! only equals `null`. Comparisons between different types are false (and `!=` true). In
+//! conditions, `null`, `false`, `0`, and `""` count as false.
+
+use crate::{navigation::Kind, wikidata, Wiki};
+use lasso::{Key as _, Rodeo, Spur};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+#[derive(Clone, Debug)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(Cow<'a, str>),
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Literal(Value<'static>),
+    Attribute(String),
+    Not(Box<Self>),
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Compare(Box<Self>, Comparison, Box<Self>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    String(String),
+    Ident(String),
+    Not,
+    And,
+    Or,
+    Comparison(&'static str),
+    Minus,
+    Open,
+    Close,
+}
+
+impl Filter {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            anyhow::bail!("Unexpected {token:?} in filter");
+        }
+        Ok(Self { expr })
+    }
+
+    /// Evaluate the filter, looking attributes up with `attribute`.
+    pub fn matches<'a>(&self, attribute: &dyn Fn(&str) -> Value<'a>) -> bool {
+        eval(&self.expr, attribute).is_truthy()
+    }
+}
+
+impl<'a> Value<'a> {
+    pub fn from_json(value: &'a serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Self::Null,
+            serde_json::Value::Bool(value) => Self::Bool(*value),
+            serde_json::Value::Number(value) => value.as_f64().map_or(Self::Null, Self::Number),
+            serde_json::Value::String(value) => Self::String(Cow::Borrowed(value)),
+            // Arrays and objects have no operators; they only count as present.
+            _ => Self::Bool(true),
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Null => false,
+            Self::Bool(value) => *value,
+            Self::Number(value) => *value != 0.0,
+            Self::String(value) => !value.is_empty(),
+        }
+    }
+
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Null, Self::Null) => Some(Ordering::Equal),
+            (Self::Bool(a), Self::Bool(b)) => Some(a.cmp(b)),
+            (Self::Number(a), Self::Number(b)) => a.partial_cmp(b),
+            (Self::String(a), Self::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+impl From<usize> for Value<'_> {
+    #[allow(clippy::cast_precision_loss)]
+    fn from(value: usize) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<u32> for Value<'_> {
+    fn from(value: u32) -> Self {
+        Self::Number(f64::from(value))
+    }
+}
+
+impl From<u64> for Value<'_> {
+    #[allow(clippy::cast_precision_loss)]
+    fn from(value: u64) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<i64> for Value<'_> {
+    #[allow(clippy::cast_precision_loss)]
+    fn from(value: i64) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+fn eval<'a>(expr: &Expr, attribute: &dyn Fn(&str) -> Value<'a>) -> Value<'a> {
+    match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Attribute(name) => attribute(name),
+        Expr::Not(expr) => Value::Bool(!eval(expr, attribute).is_truthy()),
+        Expr::And(a, b) => {
+            Value::Bool(eval(a, attribute).is_truthy() && eval(b, attribute).is_truthy())
+        }
+        Expr::Or(a, b) => {
+            Value::Bool(eval(a, attribute).is_truthy() || eval(b, attribute).is_truthy())
+        }
+        Expr::Compare(a, comparison, b) => {
+            let ordering = eval(a, attribute).compare(&eval(b, attribute));
+            Value::Bool(match comparison {
+                Comparison::Eq => ordering == Some(Ordering::Equal),
+                Comparison::Ne => ordering != Some(Ordering::Equal),
+                Comparison::Lt => ordering == Some(Ordering::Less),
+                Comparison::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                Comparison::Gt => ordering == Some(Ordering::Greater),
+                Comparison::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            })
+        }
+    }
+}
+
+fn tokenize(source: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let rest = &source[start..];
+        let operator = [
+            "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")", "-",
+        ]
+        .into_iter()
+        .find(|operator| rest.starts_with(operator));
+        if let Some(operator) = operator {
+            tokens.push(match operator {
+                "&&" => Token::And,
+                "||" => Token::Or,
+                "!" => Token::Not,
+                "(" => Token::Open,
+                ")" => Token::Close,
+                "-" => Token::Minus,
+                comparison => Token::Comparison(comparison),
+            });
+            for _ in 0..operator.len() {
+                chars.next();
+            }
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    None => anyhow::bail!("Unterminated string in filter"),
+                    Some((_, '\\')) => {
+                        if let Some((_, escaped)) = chars.next() {
+                            string.push(escaped);
+                        }
+                    }
+                    Some((_, quote)) if quote == c => break,
+                    Some((_, other)) => string.push(other),
+                }
+            }
+            tokens.push(Token::String(string));
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = rest[..len]
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid number '{}' in filter", &rest[..len]))?;
+            tokens.push(Token::Number(number));
+            for _ in 0..len {
+                chars.next();
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_' && c != '.')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(String::from(&rest[..len])));
+            for _ in rest[..len].chars() {
+                chars.next();
+            }
+        } else {
+            anyhow::bail!("Unexpected '{c}' in filter");
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> anyhow::Result<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> anyhow::Result<Expr> {
+        let left = self.primary()?;
+        let Some(Token::Comparison(operator)) = self.peek() else {
+            return Ok(left);
+        };
+        let comparison = match *operator {
+            "==" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            _ => Comparison::Ge,
+        };
+        self.next();
+        let right = self.primary()?;
+        Ok(Expr::Compare(Box::new(left), comparison, Box::new(right)))
+    }
+
+    fn primary(&mut self) -> anyhow::Result<Expr> {
+        Ok(match self.next() {
+            Some(Token::Number(number)) => Expr::Literal(Value::Number(*number)),
+            Some(Token::Minus) => match self.next() {
+                Some(Token::Number(number)) => Expr::Literal(Value::Number(-number)),
+                _ => anyhow::bail!("Expected a number after '-' in filter"),
+            },
+            Some(Token::String(string)) => Expr::Literal(Value::String(Cow::Owned(string.clone()))),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                _ => Expr::Attribute(ident.clone()),
+            },
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                anyhow::ensure!(self.next() == Some(&Token::Close), "Expected ')' in filter");
+                expr
+            }
+            Some(token) => anyhow::bail!("Unexpected {token:?} in filter"),
+            None => anyhow::bail!("Unexpected end of filter"),
+        })
+    }
+}
+
+/// A node's attributes in the parsed wiki: `title`, `ns`, `page_id`, `wikidata` (its item, as in
+/// `Q42`), `timestamp` (of its latest revision), `is_redirect`, `exists` (whether the dump has a
+/// page for it), `is_portal`, `is_disambiguation`, `is_navigation` (a portal, a disambiguation
+/// page, or a navigation-heavy page), `in_degree`, `out_degree`, `sort_key`, and any set by
+/// `--script`.
+fn node_attribute<'a>(
+    rodeo: &'a Rodeo,
+    wiki: &'a Wiki,
+    in_degrees: &[usize],
+    node: Spur,
+    name: &str,
+) -> Value<'a> {
+    match name {
+        "title" => Value::String(Cow::Borrowed(rodeo.resolve(&node))),
+        "ns" => wiki
+            .namespaces
+            .get(&node)
+            .map_or(Value::Null, |&ns| Value::from(ns)),
+        "page_id" => wiki
+            .page_ids
+            .get(&node)
+            .map_or(Value::Null, |&id| Value::from(id)),
+        "wikidata" => wiki.wikidata_items.get(&node).map_or(Value::Null, |&item| {
+            Value::String(Cow::Owned(wikidata::name(item)))
+        }),
+        "timestamp" => wiki.timestamps.get(&node).map_or(Value::Null, |timestamp| {
+            Value::String(Cow::Borrowed(timestamp))
+        }),
+        "is_redirect" => Value::Bool(wiki.redirects.contains_key(&node)),
+        "exists" => Value::Bool(wiki.links.contains_key(&node)),
+        "is_portal" => Value::Bool(wiki.navigation.get(&node) == Some(&Kind::Portal)),
+        "is_disambiguation" => {
+            Value::Bool(wiki.navigation.get(&node) == Some(&Kind::Disambiguation))
+        }
+        "is_navigation" => Value::Bool(wiki.navigation.contains_key(&node)),
+        "in_degree" => Value::from(in_degrees[node.into_usize()]),
+        "out_degree" => Value::from(wiki.links.get(&node).map_or(0, HashSet::len)),
+        "sort_key" => Value::String(Cow::Borrowed(wiki.sort_key(rodeo, node))),
+        _ => wiki
+            .node_attributes
+            .get(&node)
+            .and_then(|attributes| attributes.get(name))
+            .map_or(Value::Null, Value::from_json),
+    }
+}
+
+/// The part of `wiki` passing the filters, re-interned so that node IDs stay dense. Nodes are
+/// checked against `nodes`; links between kept nodes against `edges`, which sees the link's
+/// attributes from `--script` and its ends' attributes as `source.*` and `target.*`. Attributes
+/// are computed on the unfiltered graph.
+#[allow(clippy::too_many_lines)]
+pub fn apply(
+    rodeo: &Rodeo,
+    wiki: &Wiki,
+    nodes: Option<&Filter>,
+    edges: Option<&Filter>,
+) -> (Rodeo, Wiki) {
+    let in_degrees = crate::export::in_degrees(rodeo, &wiki.links);
+
+    let mut filtered_rodeo = Rodeo::new();
+    let mut ids = HashMap::new();
+    for (node, title) in rodeo.iter() {
+        let keep = nodes.is_none_or(|filter| {
+            filter.matches(&|name| node_attribute(rodeo, wiki, &in_degrees, node, name))
+        });
+        if keep {
+            ids.insert(node, filtered_rodeo.get_or_intern(title));
+        }
+    }
+
+    let keep_edge = |source: Spur, target: Spur| {
+        edges.is_none_or(|filter| {
+            filter.matches(&|name| {
+                if let Some(name) = name.strip_prefix("source.") {
+                    node_attribute(rodeo, wiki, &in_degrees, source, name)
+                } else if let Some(name) = name.strip_prefix("target.") {
+                    node_attribute(rodeo, wiki, &in_degrees, target, name)
+                } else {
+                    wiki.edge_attributes
+                        .get(&(source, target))
+                        .and_then(|attributes| attributes.get(name))
+                        .map_or(Value::Null, Value::from_json)
+                }
+            })
+        })
+    };
+
+    let id = |node: &Spur| ids.get(node).copied();
+    let filtered = Wiki {
+        links: wiki
+            .links
+            .iter()
+            .filter_map(|(source, links)| {
+                let links = links
+                    .iter()
+                    .filter(|&&target| keep_edge(*source, target))
+                    .filter_map(id)
+                    .collect();
+                Some((id(source)?, links))
+            })
+            .collect(),
+        redirects: wiki
+            .redirects
+            .iter()
+            .filter_map(|(from, to)| Some((id(from)?, id(to)?)))
+            .collect(),
+        sort_keys: wiki
+            .sort_keys
+            .iter()
+            .filter_map(|(node, key)| Some((id(node)?, key.clone())))
+            .collect(),
+        categories: wiki
+            .categories
+            .iter()
+            .filter_map(|(&(category, node), key)| Some(((category, id(&node)?), key.clone())))
+            .collect(),
+        category_names: wiki.category_names.clone(),
+        namespaces: wiki
+            .namespaces
+            .iter()
+            .filter_map(|(node, &ns)| Some((id(node)?, ns)))
+            .collect(),
+        page_ids: wiki
+            .page_ids
+            .iter()
+            .filter_map(|(node, &page_id)| Some((id(node)?, page_id)))
+            .collect(),
+        wikidata_items: wiki
+            .wikidata_items
+            .iter()
+            .filter_map(|(node, &item)| Some((id(node)?, item)))
+            .collect(),
+        timestamps: wiki
+            .timestamps
+            .iter()
+            .filter_map(|(node, timestamp)| Some((id(node)?, timestamp.clone())))
+            .collect(),
+        navigation: wiki
+            .navigation
+            .iter()
+            .filter_map(|(node, &kind)| Some((id(node)?, kind)))
+            .collect(),
+        edge_types: wiki
+            .edge_types
+            .iter()
+            .filter_map(|((source, target), &types)| Some(((id(source)?, id(target)?), types)))
+            .collect(),
+        node_attributes: wiki
+            .node_attributes
+            .iter()
+            .filter_map(|(node, attributes)| Some((id(node)?, attributes.clone())))
+            .collect(),
+        edge_attributes: wiki
+            .edge_attributes
+            .iter()
+            .filter_map(|((source, target), attributes)| {
+                Some(((id(source)?, id(target)?), attributes.clone()))
+            })
+            .collect(),
+        link_counts: wiki.link_counts.as_ref().map(|counts| {
+            counts
+                .iter()
+                .filter_map(|((source, target), &count)| Some(((id(source)?, id(target)?), count)))
+                .collect()
+        }),
+        spill: None,
+    };
+    (filtered_rodeo, filtered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(source: &str) -> bool {
+        let attributes = serde_json::json!({
+            "ns": 0,
+            "title": "Cat",
+            "is_redirect": false,
+            "out_degree": 12,
+            "score": -1.5,
+        });
+        let filter = Filter::parse(source).unwrap_or_else(|error| panic!("{source:?}: {error}"));
+        filter.matches(&|name| attributes.get(name).map_or(Value::Null, Value::from_json))
+    }
+
+    #[test]
+    fn precedence() {
+        let cases = [
+            // `&&` binds tighter than `||`, and both group to the left.
+            ("true || false && false", true),
+            ("(true || false) && false", false),
+            ("false && false || true", true),
+            ("false && (false || true)", false),
+            // `!` binds tighter than `&&`, and comparisons tighter than `!`.
+            ("!false && false", false),
+            ("!(false && false)", true),
+            ("!ns == 1", true),
+            ("!!true", true),
+            ("ns == 0 && !is_redirect && out_degree < 500", true),
+            ("ns == 1 || title == 'Cat' && out_degree >= 12", true),
+            ("(ns == 1 || title == 'Cat') && out_degree > 12", false),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(matches(source), expected, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn values() {
+        let cases = [
+            ("score == -1.5", true),
+            ("score < 0", true),
+            ("title == \"Cat\"", true),
+            ("title < 'Dog'", true),
+            ("'it\\'s' == \"it's\"", true),
+            ("missing == null", true),
+            ("missing", false),
+            ("!missing", true),
+            ("ns", false),
+            ("title", true),
+            // Different types are never equal or ordered.
+            ("ns == '0'", false),
+            ("ns != '0'", true),
+            ("ns < '1'", false),
+            ("is_redirect == 0", false),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(matches(source), expected, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("", "Unexpected end of filter"),
+            ("ns ==", "Unexpected end of filter"),
+            ("ns == 0 &&", "Unexpected end of filter"),
+            ("ns == 0 ns", "Unexpected Ident(\"ns\") in filter"),
+            ("ns == 0 == 0", "Unexpected Comparison(\"==\") in filter"),
+            ("(ns == 0", "Expected ')' in filter"),
+            ("ns == 0)", "Unexpected Close in filter"),
+            ("&& ns", "Unexpected And in filter"),
+            ("ns == -x", "Expected a number after '-' in filter"),
+            ("title == 'Cat", "Unterminated string in filter"),
+            ("ns == 1.2.3", "Invalid number '1.2.3' in filter"),
+            ("ns = 0", "Unexpected '=' in filter"),
+            ("ns & 0", "Unexpected '&' in filter"),
+        ];
+        for (source, expected) in cases {
+            let error = Filter::parse(source).expect_err(source);
+            assert_eq!(error.to_string(), expected, "{source:?}");
+        }
+    }
+}
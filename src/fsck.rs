@@ -0,0 +1,75 @@
+//! Check a saved graph for corruption, so that a damaged file fails loudly instead of answering
+//! queries wrongly.
+
+use crate::{
+    graph::{Codec, Parts},
+    workspace,
+};
+use anyhow::Context as _;
+use std::path::PathBuf;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+}
+
+/// Print every problem found, exiting with status 1 if there are any.
+pub fn run(args: &Args) {
+    let parts = Parts::read(&args.graph)
+        .context("Failed to read graph")
+        .unwrap();
+
+    let metadata = &parts.metadata;
+    let unknown = || String::from("unknown");
+    println!("Format version {}", parts.version);
+    if parts.compressed {
+        println!("Compressed with zstd");
+    }
+    if parts.codec != Codec::None {
+        println!("Sections stored in {} blocks", parts.codec.name());
+    }
+    if parts.version >= 8 && !parts.has_backlinks {
+        println!("Backlinks not stored");
+    }
+    println!(
+        "Built by wikigraph {}",
+        metadata.wikigraph.clone().unwrap_or_else(unknown)
+    );
+    println!("Dump: {}", metadata.dump.clone().unwrap_or_else(unknown));
+    if let Some(date) = &metadata.dump_date {
+        println!("Dump date: {date}");
+    }
+    println!(
+        "Created: {}",
+        metadata.created.map_or_else(unknown, |created| format!(
+            "{created} (seconds since the Unix epoch)"
+        ))
+    );
+    if !metadata.command.is_empty() {
+        println!("Command: {}", metadata.command.join(" "));
+    }
+    if let Some(options) = &metadata.options {
+        println!("Options hash: {options}");
+    }
+
+    let mut problems = parts.problems();
+    match parts.checksum_matches {
+        Some(true) => {}
+        Some(false) => problems.insert(0, String::from("Checksum mismatch")),
+        None => println!("No checksum in this format version; only checking structure"),
+    }
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if !problems.is_empty() {
+        std::process::exit(1);
+    }
+    println!(
+        "OK: {} nodes and {} links",
+        parts.node_count(),
+        parts.edge_count()
+    );
+}
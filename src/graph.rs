@@ -0,0 +1,1668 @@
+//! The frozen link graph, in compressed sparse row form, and its on-disk format.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    navigation::Kind,
+    provenance,
+    stats::Stats,
+    title_search, Wiki,
+};
+use anyhow::Context as _;
+use lasso::{Key as _, Rodeo};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    env,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use wikigraph::graph_file::{
+    read_backlinks_flag, read_header, valid_blocks, ALIGN, MAGIC, VERSION, ZSTD_LEVEL,
+};
+pub use wikigraph::graph_file::{read_u32, read_u64, Codec};
+
+pub mod bv;
+pub mod mmap;
+
+/// The start of SQLite databases, which `parse --sqlite` writes and commands read like graph
+/// files.
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+/// Bytes of a section compressed in blocks that a block holds at least, before compression. A
+/// block ends where the list of a node does, so a node with more has a block to itself.
+const BLOCK_BYTES: u64 = 1 << 16;
+
+/// Where a graph came from. Fields are optional so graphs migrated from older versions, which
+/// didn't record them, can say so.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Version of wikigraph that built the graph.
+    pub wikigraph: Option<String>,
+    /// File name of the dump it was parsed from.
+    pub dump: Option<String>,
+    /// Date of the dump, from its file name, as `YYYY-MM-DD`.
+    pub dump_date: Option<String>,
+    /// When it was built, in seconds since the Unix epoch.
+    pub created: Option<u64>,
+    /// Command line that built it.
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// `provenance::options_hash` of the parse that built it.
+    pub options: Option<String>,
+}
+
+impl Metadata {
+    /// Metadata for a graph built by this run from `dump`.
+    pub fn current(dump: Option<&Path>) -> Self {
+        let dump = dump
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned());
+        Self {
+            wikigraph: Some(String::from(env!("CARGO_PKG_VERSION"))),
+            dump_date: dump.as_deref().and_then(provenance::dump_date),
+            dump,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|age| age.as_secs()),
+            command: env::args().collect(),
+            options: None,
+        }
+    }
+
+    /// The metadata of the graph file at `path`, reading no further into it.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let (_, version, compressed, _) = read_header(&mut file)?;
+        if compressed {
+            Self::read_from(&mut zstd::Decoder::with_buffer(file)?, version)
+        } else {
+            Self::read_from(&mut file, version)
+        }
+    }
+
+    /// The metadata that follows the header of a file of format `version`, which is empty
+    /// before version 3.
+    fn read_from(reader: &mut impl Read, version: u32) -> anyhow::Result<Self> {
+        if version < 3 {
+            return Ok(Self::default());
+        }
+        let len = read_u32(reader)?;
+        let mut metadata = Vec::new();
+        reader.take(u64::from(len)).read_to_end(&mut metadata)?;
+        anyhow::ensure!(metadata.len() == len as usize, "Unexpected end of file");
+        serde_json::from_slice(&metadata).context("Invalid metadata")
+    }
+}
+
+/// Which links a traversal follows from each node.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Outgoing links, the way a reader clicks through
+    #[default]
+    Out,
+    /// Incoming links, from each page to the pages linking to it
+    In,
+    /// Both, ignoring which way links point
+    Both,
+}
+
+impl Direction {
+    /// The direction that retraces a traversal in this one.
+    pub fn reverse(self) -> Self {
+        match self {
+            Self::Out => Self::In,
+            Self::In => Self::Out,
+            Self::Both => Self::Both,
+        }
+    }
+}
+
+/// Node IDs are the interner's keys, so they match the IDs used by every export.
+pub struct Graph {
+    titles: Vec<String>,
+    /// `targets[offsets[n]..offsets[n + 1]]` are the outgoing links of node `n`, sorted.
+    offsets: Vec<u64>,
+    targets: Vec<u32>,
+    /// How many times each link of `targets` occurs in its page, if built with
+    /// `parse --edge-weights`.
+    weights: Option<Vec<u32>>,
+    /// The number of the Wikidata item of each node, 0 for none, if built with
+    /// `parse --page-props`.
+    wikidata: Option<Vec<u32>>,
+    /// Which nodes are portals, disambiguation pages, or navigation-heavy pages.
+    kinds: Vec<Option<Kind>>,
+    /// The target of each redirect page.
+    redirects: HashMap<u32, u32>,
+    /// Title lookup is derived when loading rather than read from the file.
+    ids: HashMap<String, u32>,
+    /// Node IDs in order of folded title (see `title_search::fold`), as read from the file, or
+    /// derived when first needed for files from before it was stored.
+    by_folded_title: OnceLock<Vec<u32>>,
+    /// `sources[back_offsets[n]..back_offsets[n + 1]]` are the pages linking to node `n`, sorted,
+    /// as `(back_offsets, sources)`. Derived when first needed, since they take as much memory
+    /// as the links and many commands only follow links forwards.
+    backlinks: OnceLock<(Vec<u64>, Vec<u32>)>,
+    /// Whether `save` stores the backlinks, which `mmap::MmapGraph` needs. Loaded graphs keep
+    /// the setting of their file.
+    store_backlinks: bool,
+    metadata: Metadata,
+    /// Statistics stored by `stats`, if they describe this graph.
+    stats: Option<Stats>,
+    /// Whether `save` zstd-compresses the file. Loaded graphs keep the compression of theirs.
+    compressed: bool,
+    /// How `save` stores the largest sections, unless it compresses the whole file. Loaded
+    /// graphs keep the codec of theirs.
+    codec: Codec,
+}
+
+impl Graph {
+    pub fn new(rodeo: &Rodeo, wiki: &Wiki, metadata: Metadata) -> anyhow::Result<Self> {
+        // Each link with its weight, 0 for unweighted graphs.
+        let mut adjacency: Vec<Vec<(u32, u32)>> = vec![Vec::new(); rodeo.len()];
+        for (&source, links) in &wiki.links {
+            let targets = &mut adjacency[source.into_usize()];
+            for &target in links {
+                let weight = wiki.weight(source, target).unwrap_or(0);
+                targets.push((
+                    u32::try_from(target.into_usize()).context("Too many nodes")?,
+                    weight,
+                ));
+            }
+            targets.sort_unstable();
+        }
+
+        let mut offsets = Vec::with_capacity(rodeo.len() + 1);
+        let mut targets = Vec::new();
+        let mut weights = Vec::new();
+        offsets.push(0);
+        for links in adjacency {
+            for (target, weight) in links {
+                targets.push(target);
+                weights.push(weight);
+            }
+            offsets.push(targets.len() as u64);
+        }
+
+        let mut graph = Self::from_csr(rodeo, wiki, offsets, targets, metadata)?;
+        graph.weights = wiki.link_counts.is_some().then_some(weights);
+        Ok(graph)
+    }
+
+    /// The graph of the pages and redirects of `wiki` with links already in compressed sparse
+    /// row form, `targets[offsets[n]..offsets[n + 1]]` being the sorted links of node `n`, as
+    /// `parse --memory-limit` builds them instead of keeping `wiki.links`.
+    pub fn from_csr(
+        rodeo: &Rodeo,
+        wiki: &Wiki,
+        offsets: Vec<u64>,
+        targets: Vec<u32>,
+        metadata: Metadata,
+    ) -> anyhow::Result<Self> {
+        let titles: Vec<String> = rodeo.strings().map(String::from).collect();
+        anyhow::ensure!(
+            offsets.len() == titles.len() + 1,
+            "Links of {} nodes for {} titles",
+            offsets.len().saturating_sub(1),
+            titles.len()
+        );
+        let mut kinds = vec![None; titles.len()];
+        for (page, &kind) in &wiki.navigation {
+            kinds[page.into_usize()] = Some(kind);
+        }
+        let mut wikidata = vec![0; titles.len()];
+        for (page, &item) in &wiki.wikidata_items {
+            wikidata[page.into_usize()] = item;
+        }
+
+        let redirects = wiki
+            .redirects
+            .iter()
+            .map(|(page, target)| {
+                let id = |node: &lasso::Spur| u32::try_from(node.into_usize());
+                Ok((id(page)?, id(target)?))
+            })
+            .collect::<Result<_, std::num::TryFromIntError>>()
+            .context("Too many nodes")?;
+
+        let mut graph = Self::from_parts(titles, offsets, targets, kinds, redirects, metadata);
+        graph.wikidata = (!wiki.wikidata_items.is_empty()).then_some(wikidata);
+        Ok(graph)
+    }
+
+    fn from_parts(
+        titles: Vec<String>,
+        offsets: Vec<u64>,
+        targets: Vec<u32>,
+        kinds: Vec<Option<Kind>>,
+        redirects: HashMap<u32, u32>,
+        metadata: Metadata,
+    ) -> Self {
+        let ids = titles.iter().cloned().zip(0..).collect();
+        Self {
+            titles,
+            offsets,
+            targets,
+            weights: None,
+            wikidata: None,
+            kinds,
+            redirects,
+            ids,
+            by_folded_title: OnceLock::new(),
+            backlinks: OnceLock::new(),
+            store_backlinks: true,
+            metadata,
+            stats: None,
+            compressed: false,
+            codec: Codec::None,
+        }
+    }
+
+    /// Whether `path` starts like a graph file, compressed or not, or an SQLite database.
+    pub fn is_graph_file(path: &Path) -> anyhow::Result<bool> {
+        Ok(Self::is_sqlite(path)? || starts_with(path, MAGIC)? || starts_with(path, bv::MAGIC)?)
+    }
+
+    /// Whether `path` starts like an SQLite database.
+    pub fn is_sqlite(path: &Path) -> anyhow::Result<bool> {
+        starts_with(path, SQLITE_MAGIC)
+    }
+
+    /// Load a graph, failing if it is corrupt.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if Self::is_sqlite(path)? {
+            return Self::load_sqlite(path);
+        }
+        if starts_with(path, bv::MAGIC)? {
+            return Ok(bv::BvGraph::load(path)?.to_graph());
+        }
+        let parts = Parts::read(path)?;
+        anyhow::ensure!(
+            parts.checksum_matches != Some(false),
+            "Checksum mismatch; the graph file is corrupt"
+        );
+        if let Some(problem) = parts.problems().first() {
+            anyhow::bail!("Corrupt graph file: {problem}");
+        }
+        let mut graph = Self::from_parts(
+            parts.titles,
+            parts.offsets,
+            parts.targets,
+            parts.kinds,
+            parts.redirects.into_iter().collect(),
+            parts.metadata,
+        );
+        graph.compressed = parts.compressed;
+        graph.codec = parts.codec;
+        graph.weights = parts.weights;
+        graph.wikidata = parts.wikidata;
+        if parts.version >= 12 {
+            graph.by_folded_title = OnceLock::from(parts.by_folded_title);
+        }
+        // Files from before backlinks were stored are saved with them, like new graphs.
+        graph.store_backlinks = parts.has_backlinks || parts.version < 8;
+        if let Some(stats) = parts.stats {
+            if stats.fingerprint == graph.fingerprint() {
+                graph.stats = Some(stats);
+            } else {
+                tracing::warn!("Ignoring statistics stored for a different graph");
+            }
+        }
+        Ok(graph)
+    }
+
+    /// The graph in an SQLite database written by `parse --sqlite`.
+    #[cfg(feature = "sqlite")]
+    fn load_sqlite(path: &Path) -> anyhow::Result<Self> {
+        let (rodeo, wiki, metadata) = crate::sink::sqlite::read(path)?;
+        Self::new(&rodeo, &wiki, metadata)
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    fn load_sqlite(_path: &Path) -> anyhow::Result<Self> {
+        anyhow::bail!("Reading SQLite databases needs wikigraph built with `--features sqlite`")
+    }
+
+    /// A CRC-32 of the titles, links, and link weights, identifying the graph that statistics
+    /// describe.
+    pub fn fingerprint(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        for title in &self.titles {
+            hasher.update(&(title.len() as u64).to_le_bytes());
+            hasher.update(title.as_bytes());
+        }
+        for offset in &self.offsets {
+            hasher.update(&offset.to_le_bytes());
+        }
+        for target in &self.targets {
+            hasher.update(&target.to_le_bytes());
+        }
+        for weight in self.weights.iter().flatten() {
+            hasher.update(&weight.to_le_bytes());
+        }
+        hasher.finalize()
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    pub fn set_compressed(&mut self, compressed: bool) {
+        self.compressed = compressed;
+    }
+
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+
+    pub fn set_store_backlinks(&mut self, store_backlinks: bool) {
+        self.store_backlinks = store_backlinks;
+    }
+
+    /// Save the graph the way `other` is saved: compressed or not, with the same codec, and
+    /// with backlinks if it has them.
+    pub fn set_storage_of(&mut self, other: &Self) {
+        self.compressed = other.compressed;
+        self.codec = other.codec;
+        self.store_backlinks = other.store_backlinks;
+    }
+
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.stats = Some(stats);
+    }
+
+    pub fn id(&self, title: &str) -> Option<u32> {
+        self.ids.get(title).copied()
+    }
+
+    pub fn title(&self, id: u32) -> &str {
+        &self.titles[id as usize]
+    }
+
+    pub fn by_folded_title(&self) -> &[u32] {
+        self.by_folded_title.get_or_init(|| {
+            title_search::folded_order(self.titles.len(), |id| &self.titles[id as usize])
+        })
+    }
+
+    /// Whether the node is a portal, a disambiguation page, or a navigation-heavy page.
+    pub fn kind(&self, id: u32) -> Option<Kind> {
+        self.kinds[id as usize]
+    }
+
+    /// The page `id` redirects to, if it is a redirect.
+    pub fn redirect(&self, id: u32) -> Option<u32> {
+        self.redirects.get(&id).copied()
+    }
+
+    pub fn links(&self, id: u32) -> &[u32] {
+        let start = usize::try_from(self.offsets[id as usize]).unwrap();
+        let end = usize::try_from(self.offsets[id as usize + 1]).unwrap();
+        &self.targets[start..end]
+    }
+
+    /// How many times `id` links to each of `links(id)`, if the graph's links are weighted.
+    pub fn link_weights(&self, id: u32) -> Option<&[u32]> {
+        let start = usize::try_from(self.offsets[id as usize]).unwrap();
+        let end = usize::try_from(self.offsets[id as usize + 1]).unwrap();
+        self.weights.as_deref().map(|weights| &weights[start..end])
+    }
+
+    pub fn is_weighted(&self) -> bool {
+        self.weights.is_some()
+    }
+
+    /// The number of the Wikidata item of `id`, if the graph was built with them and it has one.
+    pub fn wikidata_item(&self, id: u32) -> Option<u32> {
+        let item = self.wikidata.as_ref()?[id as usize];
+        (item != 0).then_some(item)
+    }
+
+    pub fn has_wikidata_items(&self) -> bool {
+        self.wikidata.is_some()
+    }
+
+    pub fn backlinks(&self, id: u32) -> &[u32] {
+        let (back_offsets, sources) = self.backlink_lists();
+        let start = usize::try_from(back_offsets[id as usize]).unwrap();
+        let end = usize::try_from(back_offsets[id as usize + 1]).unwrap();
+        &sources[start..end]
+    }
+
+    fn backlink_lists(&self) -> &(Vec<u64>, Vec<u32>) {
+        self.backlinks
+            .get_or_init(|| backlinks(self.titles.len(), &self.offsets, &self.targets))
+    }
+
+    pub fn in_degree(&self, id: u32) -> u32 {
+        // Each other node links to this one at most once, and node IDs fit in a `u32`.
+        u32::try_from(self.backlinks(id).len()).unwrap()
+    }
+
+    pub fn out_degree(&self, id: u32) -> u32 {
+        // A node links to each other node at most once, and node IDs fit in a `u32`.
+        u32::try_from(self.links(id).len()).unwrap()
+    }
+
+    /// Every node reachable from `start` by following at most `hops` links in `direction`, only
+    /// passing through nodes for which `keep` holds. Checks `cancel` before each level.
+    pub fn within(
+        &self,
+        start: u32,
+        hops: usize,
+        direction: Direction,
+        keep: impl Fn(u32) -> bool,
+        cancel: &Cancel,
+    ) -> Result<HashSet<u32>, Cancelled> {
+        let mut seen = HashSet::from([start]);
+        let mut frontier = VecDeque::from([(start, 0)]);
+        let mut level = 0;
+        while let Some((node, distance)) = frontier.pop_front() {
+            if distance == hops {
+                continue;
+            }
+            if distance == level {
+                cancel.check()?;
+                level += 1;
+            }
+            for target in self.neighbours(node, direction) {
+                if keep(target) && seen.insert(target) {
+                    frontier.push_back((target, distance + 1));
+                }
+            }
+        }
+        Ok(seen)
+    }
+
+    /// How many links in `direction` each node is from the nearest of `starts`, for every node
+    /// reachable from them. Only enters nodes for which `enter` holds, and only follows links
+    /// onward from nodes for which `pass` holds. Checks `cancel` before each level.
+    pub fn distances(
+        &self,
+        starts: &[u32],
+        direction: Direction,
+        enter: impl Fn(u32) -> bool,
+        pass: impl Fn(u32) -> bool,
+        cancel: &Cancel,
+    ) -> Result<HashMap<u32, usize>, Cancelled> {
+        let mut distances: HashMap<u32, usize> = starts.iter().map(|&start| (start, 0)).collect();
+        let mut frontier: VecDeque<u32> = starts.iter().copied().collect();
+        let mut level = 0;
+        while let Some(node) = frontier.pop_front() {
+            if !pass(node) {
+                continue;
+            }
+            let distance = distances[&node];
+            if distance == level {
+                cancel.check()?;
+                level += 1;
+            }
+            for target in self.neighbours(node, direction) {
+                if !enter(target) {
+                    continue;
+                }
+                if let Entry::Vacant(entry) = distances.entry(target) {
+                    entry.insert(distance + 1);
+                    frontier.push_back(target);
+                }
+            }
+        }
+        Ok(distances)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// The links, link weights, page kinds, Wikidata items, and redirects of the graph, with
+    /// titles interned into `rodeo`. Interning into an empty `rodeo` keeps the node IDs.
+    pub fn to_wiki(&self, rodeo: &mut Rodeo) -> Wiki {
+        let mut wiki = Wiki::default();
+        let nodes: Vec<_> = self
+            .titles
+            .iter()
+            .map(|title| rodeo.get_or_intern(title))
+            .collect();
+        for (node, &source) in (0..).zip(&nodes) {
+            let links: HashSet<_> = self
+                .links(node)
+                .iter()
+                .map(|&target| nodes[target as usize])
+                .collect();
+            if !links.is_empty() {
+                wiki.links.insert(source, links);
+            }
+            if let Some(weights) = self.link_weights(node) {
+                let counts = wiki.link_counts.get_or_insert_default();
+                for (&target, &weight) in self.links(node).iter().zip(weights) {
+                    if weight > 1 {
+                        counts.insert((source, nodes[target as usize]), weight);
+                    }
+                }
+            }
+            if let Some(kind) = self.kind(node) {
+                wiki.navigation.insert(source, kind);
+            }
+            if let Some(item) = self.wikidata_item(node) {
+                wiki.wikidata_items.insert(source, item);
+            }
+        }
+        for (&page, &target) in &self.redirects {
+            wiki.redirects
+                .insert(nodes[page as usize], nodes[target as usize]);
+        }
+        wiki
+    }
+
+    /// The graph induced by the nodes for which `keep[node]` holds, with node IDs renumbered in
+    /// their original order. It keeps the dump name, but is otherwise built by this run.
+    pub fn subgraph(&self, keep: &[bool]) -> Self {
+        let mut ids = vec![None; self.titles.len()];
+        let mut titles = Vec::new();
+        let mut kinds = Vec::new();
+        let mut wikidata = self.wikidata.as_ref().map(|_| Vec::new());
+        for (node, title) in self.titles.iter().enumerate() {
+            if keep[node] {
+                ids[node] = Some(u32::try_from(titles.len()).unwrap());
+                titles.push(title.clone());
+                kinds.push(self.kinds[node]);
+                if let (Some(wikidata), Some(from)) = (&mut wikidata, &self.wikidata) {
+                    wikidata.push(from[node]);
+                }
+            }
+        }
+
+        let mut offsets = vec![0];
+        let mut targets = Vec::new();
+        let mut weights = self.weights.as_ref().map(|_| Vec::new());
+        for node in (0..self.titles.len()).filter(|&node| keep[node]) {
+            let node = u32::try_from(node).unwrap();
+            // Renumbering preserves order, so the links stay sorted.
+            for (edge, &target) in self.links(node).iter().enumerate() {
+                if let Some(target) = ids[target as usize] {
+                    targets.push(target);
+                    if let (Some(weights), Some(from)) = (&mut weights, self.link_weights(node)) {
+                        weights.push(from[edge]);
+                    }
+                }
+            }
+            offsets.push(targets.len() as u64);
+        }
+
+        let redirects = self
+            .redirects
+            .iter()
+            .filter_map(|(&page, &target)| Some((ids[page as usize]?, ids[target as usize]?)))
+            .collect();
+
+        let metadata = Metadata {
+            dump: self.metadata.dump.clone(),
+            dump_date: self.metadata.dump_date.clone(),
+            ..Metadata::current(None)
+        };
+        let mut graph = Self::from_parts(titles, offsets, targets, kinds, redirects, metadata);
+        graph.weights = weights;
+        graph.wikidata = wikidata;
+        graph.compressed = self.compressed;
+        graph.codec = self.codec;
+        graph.store_backlinks = self.store_backlinks;
+        graph
+    }
+
+    /// Write the graph as: magic, version, a compression byte (0 for none, 1 for zstd, and 2 or 3
+    /// for the title bytes, links, backlinks, and weights in LZ4 or zstd blocks, as `write_stored`
+    /// writes them), and then, compressed as a whole if so: length-prefixed JSON metadata; node,
+    /// edge, title byte, and redirect counts, and 1 if the backlinks are stored or else 0; the
+    /// offset of each title in the title bytes, and the title bytes; CSR offsets and targets of the
+    /// links, and of the backlinks if stored; node IDs sorted by title, and by folded title (see
+    /// `title_search::fold`); (page, target) redirect pairs sorted by page; a kind byte per node
+    /// (see `Kind::to_byte`); a byte saying whether link weights follow, and a weight per link in
+    /// the order of the targets; a byte saying whether Wikidata items follow, and the number of the
+    /// item of each node, 0 for none; a byte saying whether statistics follow, and the statistics;
+    /// and a CRC-32 of everything before it, uncompressed. All integers are little-endian. Where
+    /// each section starts follows from the counts, and arrays of offsets are padded to start at a
+    /// multiple of 8 bytes, so that uncompressed files can be mapped into memory instead of loaded
+    /// (see `mmap::MmapGraph`). The file is replaced only once it is complete.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let partial = path.with_extension("partial");
+        let mut file = BufWriter::new(File::create(&partial)?);
+        let mut header = Vec::from(*MAGIC);
+        header.extend(VERSION.to_le_bytes());
+        header.push(if self.compressed {
+            1
+        } else {
+            self.codec.to_byte()
+        });
+        file.write_all(&header)?;
+        if self.compressed {
+            let encoder = self.write_body(&header, zstd::Encoder::new(file, ZSTD_LEVEL)?)?;
+            encoder.finish()?.flush()?;
+        } else {
+            self.write_body(&header, file)?.flush()?;
+        }
+        std::fs::rename(partial, path)?;
+        Ok(())
+    }
+
+    /// Write everything after the `header`, checksum included, returning the writer.
+    fn write_body<W: Write>(&self, header: &[u8], writer: W) -> anyhow::Result<W> {
+        let codec = if self.compressed {
+            Codec::None
+        } else {
+            self.codec
+        };
+        let mut writer = Checksummed::new(writer);
+        writer.hasher.update(header);
+        writer.position = header.len() as u64;
+        let metadata = serde_json::to_vec(&self.metadata)?;
+        writer.write_all(&u32::try_from(metadata.len())?.to_le_bytes())?;
+        writer.write_all(&metadata)?;
+
+        let mut title_offsets = Vec::with_capacity(self.titles.len() + 1);
+        title_offsets.push(0);
+        for title in &self.titles {
+            title_offsets.push(title_offsets.last().unwrap() + title.len() as u64);
+        }
+        let mut by_title: Vec<u32> = (0..u32::try_from(self.titles.len())?).collect();
+        by_title.sort_unstable_by(|&a, &b| self.title(a).cmp(self.title(b)));
+        let mut redirects: Vec<(u32, u32)> = self
+            .redirects
+            .iter()
+            .map(|(&page, &target)| (page, target))
+            .collect();
+        redirects.sort_unstable();
+
+        let counts = [
+            self.titles.len() as u64,
+            self.targets.len() as u64,
+            *title_offsets.last().unwrap(),
+            redirects.len() as u64,
+            u64::from(self.store_backlinks),
+        ];
+        write_all_le(&mut writer, &counts, u64::to_le_bytes)?;
+        writer.pad()?;
+        write_all_le(&mut writer, &title_offsets, u64::to_le_bytes)?;
+        if codec == Codec::None {
+            for title in &self.titles {
+                writer.write_all(title.as_bytes())?;
+            }
+        } else {
+            let title_bytes = self.titles.concat();
+            write_stored(
+                &mut writer,
+                codec,
+                title_bytes.as_bytes(),
+                &title_offsets,
+                |byte| [byte],
+            )?;
+        }
+        writer.pad()?;
+        write_all_le(&mut writer, &self.offsets, u64::to_le_bytes)?;
+        write_stored(
+            &mut writer,
+            codec,
+            &self.targets,
+            &self.offsets,
+            u32::to_le_bytes,
+        )?;
+        if self.store_backlinks {
+            let (back_offsets, sources) = self.backlink_lists();
+            writer.pad()?;
+            write_all_le(&mut writer, back_offsets, u64::to_le_bytes)?;
+            write_stored(&mut writer, codec, sources, back_offsets, u32::to_le_bytes)?;
+        }
+        write_all_le(&mut writer, &by_title, u32::to_le_bytes)?;
+        write_all_le(&mut writer, self.by_folded_title(), u32::to_le_bytes)?;
+        let redirects: Vec<u32> = redirects
+            .into_iter()
+            .flat_map(|(page, target)| [page, target])
+            .collect();
+        write_all_le(&mut writer, &redirects, u32::to_le_bytes)?;
+        let kinds: Vec<u8> = self.kinds.iter().map(|&kind| Kind::to_byte(kind)).collect();
+        writer.write_all(&kinds)?;
+        match &self.weights {
+            Some(weights) => {
+                writer.write_all(&[1])?;
+                write_stored(&mut writer, codec, weights, &self.offsets, u32::to_le_bytes)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        match &self.wikidata {
+            Some(wikidata) => {
+                writer.write_all(&[1])?;
+                write_all_le(&mut writer, wikidata, u32::to_le_bytes)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        match &self.stats {
+            Some(stats) => {
+                writer.write_all(&[1])?;
+                stats.write(&mut writer)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        let checksum = writer.hasher.clone().finalize();
+        writer.inner.write_all(&checksum.to_le_bytes())?;
+        Ok(writer.inner)
+    }
+}
+
+/// Read access to the nodes and links of a graph, whether loaded into memory as a `Graph` or
+/// mapped as an `mmap::MmapGraph`, and the searches that only need that.
+pub trait Adjacency {
+    fn node_count(&self) -> usize;
+
+    fn id(&self, title: &str) -> Option<u32>;
+
+    fn title(&self, id: u32) -> &str;
+
+    /// Node IDs in order of folded title (see `title_search::fold`).
+    fn by_folded_title(&self) -> &[u32];
+
+    /// Whether the node is a portal, a disambiguation page, or a navigation-heavy page.
+    fn kind(&self, id: u32) -> Option<Kind>;
+
+    /// The page `id` redirects to, if it is a redirect.
+    fn redirect(&self, id: u32) -> Option<u32>;
+
+    /// The nodes `id` links to, sorted.
+    fn links(&self, id: u32) -> &[u32];
+
+    /// The nodes linking to `id`, sorted.
+    fn backlinks(&self, id: u32) -> &[u32];
+
+    /// The page a reader asking for `id` ends up on, following redirects, or `id` itself if it
+    /// isn't a redirect or leads into a loop of them.
+    fn resolve_redirect(&self, id: u32) -> u32 {
+        let mut seen = HashSet::new();
+        let mut node = id;
+        while let Some(target) = self.redirect(node) {
+            if !seen.insert(node) {
+                return id;
+            }
+            node = target;
+        }
+        node
+    }
+
+    /// The nodes one link away from `id` in `direction`. With `Direction::Both`, pages linking
+    /// each other appear twice.
+    fn neighbours(&self, id: u32, direction: Direction) -> impl Iterator<Item = u32> + '_ {
+        let (links, backlinks): (&[u32], &[u32]) = match direction {
+            Direction::Out => (self.links(id), &[]),
+            Direction::In => (&[], self.backlinks(id)),
+            Direction::Both => (self.links(id), self.backlinks(id)),
+        };
+        links.iter().chain(backlinks).copied()
+    }
+
+    /// A shortest chain of links from `from` to `to` in `direction`, including both ends, only
+    /// passing through nodes for which `keep` holds. Searches from both ends at once, always
+    /// growing the smaller frontier by a whole level, so it visits far fewer nodes than
+    /// searching from one end. Checks `cancel` before each level.
+    fn shortest_path(
+        &self,
+        from: u32,
+        to: u32,
+        direction: Direction,
+        keep: impl Fn(u32) -> bool,
+        cancel: &Cancel,
+    ) -> Result<Option<Vec<u32>>, Cancelled> {
+        let enter = |node: u32| keep(node) || node == from || node == to;
+        self.shortest_path_by(
+            from,
+            to,
+            direction,
+            |node, target| enter(node) && enter(target),
+            cancel,
+        )
+    }
+
+    /// A shortest chain of links from `from` to `to` in `direction`, as `shortest_path` finds,
+    /// only following the links from a node to a neighbour in `direction` for which `follow`
+    /// holds, so that single links can be left out as well as whole nodes.
+    fn shortest_path_by(
+        &self,
+        from: u32,
+        to: u32,
+        direction: Direction,
+        follow: impl Fn(u32, u32) -> bool,
+        cancel: &Cancel,
+    ) -> Result<Option<Vec<u32>>, Cancelled> {
+        if from == to {
+            return Ok(Some(vec![from]));
+        }
+        // The neighbour each reached node was reached from, on the side of `from` and of `to`.
+        let mut forward = HashMap::from([(from, from)]);
+        let mut backward = HashMap::from([(to, to)]);
+        let mut forward_frontier = vec![from];
+        let mut backward_frontier = vec![to];
+        while !forward_frontier.is_empty() && !backward_frontier.is_empty() {
+            cancel.check()?;
+            let forward_side = forward_frontier.len() <= backward_frontier.len();
+            let (parents, others, frontier, direction) = if forward_side {
+                (&mut forward, &backward, &mut forward_frontier, direction)
+            } else {
+                (
+                    &mut backward,
+                    &forward,
+                    &mut backward_frontier,
+                    direction.reverse(),
+                )
+            };
+            let mut next = Vec::new();
+            let mut meeting = None;
+            for &node in frontier.iter() {
+                for target in self.neighbours(node, direction) {
+                    let followed = if forward_side {
+                        follow(node, target)
+                    } else {
+                        follow(target, node)
+                    };
+                    if !followed {
+                        continue;
+                    }
+                    if let Entry::Vacant(entry) = parents.entry(target) {
+                        entry.insert(node);
+                        next.push(target);
+                    }
+                    if meeting.is_none() && others.contains_key(&target) {
+                        meeting = Some(target);
+                    }
+                }
+            }
+            // Both frontiers are at their levels' ends, so any meeting found in a full level
+            // gives a shortest path.
+            if let Some(meeting) = meeting {
+                return Ok(Some(join(&forward, &backward, meeting)));
+            }
+            *frontier = next;
+        }
+        Ok(None)
+    }
+
+    /// Every shortest chain of links from `from` to `to` in `direction`, up to `limit` of them,
+    /// searching as `shortest_path` does but keeping all the neighbours each node is reached
+    /// from in a level. Empty if there is no chain.
+    fn shortest_paths(
+        &self,
+        from: u32,
+        to: u32,
+        direction: Direction,
+        keep: impl Fn(u32) -> bool,
+        limit: usize,
+        cancel: &Cancel,
+    ) -> Result<Vec<Vec<u32>>, Cancelled> {
+        if from == to {
+            return Ok(vec![vec![from]]);
+        }
+        let enter = |node: u32| keep(node) || node == from || node == to;
+        // The level of each reached node and the neighbours it was reached from, on each side.
+        let mut forward = HashMap::from([(from, (0, Vec::new()))]);
+        let mut backward = HashMap::from([(to, (0, Vec::new()))]);
+        let mut forward_frontier = vec![from];
+        let mut backward_frontier = vec![to];
+        while !forward_frontier.is_empty() && !backward_frontier.is_empty() {
+            cancel.check()?;
+            let forward_side = forward_frontier.len() <= backward_frontier.len();
+            let (parents, others, frontier, direction) = if forward_side {
+                (&mut forward, &backward, &mut forward_frontier, direction)
+            } else {
+                (
+                    &mut backward,
+                    &forward,
+                    &mut backward_frontier,
+                    direction.reverse(),
+                )
+            };
+            let mut next = Vec::new();
+            let mut meetings = Vec::new();
+            for &node in frontier.iter() {
+                let level = parents[&node].0 + 1;
+                for target in self.neighbours(node, direction) {
+                    if !enter(target) {
+                        continue;
+                    }
+                    let (reached, sources) = parents.entry(target).or_insert_with(|| {
+                        next.push(target);
+                        if others.contains_key(&target) {
+                            meetings.push(target);
+                        }
+                        (level, Vec::new())
+                    });
+                    // A node's neighbours come one after another, so a neighbour listed twice,
+                    // with `Direction::Both`, is only kept once.
+                    if *reached == level && sources.last() != Some(&node) {
+                        sources.push(node);
+                    }
+                }
+            }
+            // No level so far met the other side, so every meeting is as near it as the other
+            // frontier, and every shortest chain runs through exactly one of them.
+            if !meetings.is_empty() {
+                let mut paths = Vec::new();
+                'meetings: for meeting in meetings {
+                    let tails = chains(&backward, meeting, limit);
+                    for head in chains(&forward, meeting, limit) {
+                        for tail in &tails {
+                            if paths.len() == limit {
+                                break 'meetings;
+                            }
+                            let mut path = head.clone();
+                            path.extend(tail.iter().rev().skip(1));
+                            paths.push(path);
+                        }
+                    }
+                }
+                return Ok(paths);
+            }
+            *frontier = next;
+        }
+        Ok(Vec::new())
+    }
+}
+
+impl Adjacency for Graph {
+    fn node_count(&self) -> usize {
+        self.node_count()
+    }
+
+    fn id(&self, title: &str) -> Option<u32> {
+        self.id(title)
+    }
+
+    fn title(&self, id: u32) -> &str {
+        self.title(id)
+    }
+
+    fn by_folded_title(&self) -> &[u32] {
+        self.by_folded_title()
+    }
+
+    fn kind(&self, id: u32) -> Option<Kind> {
+        self.kind(id)
+    }
+
+    fn redirect(&self, id: u32) -> Option<u32> {
+        self.redirect(id)
+    }
+
+    fn links(&self, id: u32) -> &[u32] {
+        self.links(id)
+    }
+
+    fn backlinks(&self, id: u32) -> &[u32] {
+        self.backlinks(id)
+    }
+}
+
+/// The stored fields of a graph file, before any validation besides the header.
+#[derive(Default)]
+pub struct Parts {
+    pub version: u32,
+    pub compressed: bool,
+    pub codec: Codec,
+    titles: Vec<String>,
+    offsets: Vec<u64>,
+    targets: Vec<u32>,
+    /// Link weights, which versions before 10 don't have.
+    weights: Option<Vec<u32>>,
+    /// Wikidata items, which versions before 13 don't have.
+    wikidata: Option<Vec<u32>>,
+    /// Whether the file stores backlinks, as files since version 8 do unless saved without.
+    pub has_backlinks: bool,
+    /// The stored backlinks and title order, which versions before 8 don't have, and which
+    /// loading derives again rather than trusting.
+    back_offsets: Vec<u64>,
+    sources: Vec<u32>,
+    by_title: Vec<u32>,
+    /// The order of the folded titles, which versions before 12 don't have.
+    by_folded_title: Vec<u32>,
+    kinds: Vec<Option<Kind>>,
+    /// (page, target) pairs.
+    redirects: Vec<(u32, u32)>,
+    pub metadata: Metadata,
+    pub stats: Option<Stats>,
+    /// Whether the stored checksum matched, if the version has one.
+    pub checksum_matches: Option<bool>,
+}
+
+impl Parts {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let (header, version, compressed, codec) = read_header(&mut file)?;
+        let body: Box<dyn Read> = if compressed {
+            Box::new(zstd::Decoder::with_buffer(file)?)
+        } else {
+            Box::new(file)
+        };
+        let mut reader = Checksummed::new(body);
+        reader.hasher.update(&header);
+        reader.position = header.len() as u64;
+
+        let metadata = Metadata::read_from(&mut reader, version)?;
+
+        let parts = if version >= 8 {
+            Self::read_sections(&mut reader, version, codec)?
+        } else {
+            Self::read_sequential(&mut reader, version)?
+        };
+
+        let stats = if version >= 4 {
+            let mut present = [0];
+            reader
+                .read_exact(&mut present)
+                .context("Unexpected end of file")?;
+            match present {
+                [0] => None,
+                [1] => Some(
+                    Stats::read(&mut reader, parts.titles.len()).context("Invalid statistics")?,
+                ),
+                _ => anyhow::bail!("Invalid statistics marker"),
+            }
+        } else {
+            None
+        };
+
+        let checksum_matches = if version == 1 {
+            None
+        } else {
+            let computed = reader.hasher.clone().finalize();
+            let stored = read_u32(&mut reader.inner).context("Missing checksum")?;
+            Some(computed == stored)
+        };
+        let mut rest = [0];
+        anyhow::ensure!(
+            reader.inner.read(&mut rest)? == 0,
+            "Trailing data after the graph"
+        );
+
+        Ok(Self {
+            version,
+            compressed,
+            codec,
+            metadata,
+            stats,
+            checksum_matches,
+            ..parts
+        })
+    }
+
+    /// The titles, links, kinds, and redirects of versions 8 and later, as `Graph::save` writes
+    /// them.
+    fn read_sections<R: Read>(
+        reader: &mut Checksummed<R>,
+        version: u32,
+        codec: Codec,
+    ) -> anyhow::Result<Self> {
+        let counts = read_all_le(reader, 4, u64::from_le_bytes)?;
+        let &[node_count, edge_count, title_len, redirect_count] = &counts[..] else {
+            unreachable!()
+        };
+        let has_backlinks = version < 9 || read_backlinks_flag(reader)?;
+        reader.skip_padding()?;
+        let title_offsets = read_all_le(reader, node_count + 1, u64::from_le_bytes)?;
+        let title_bytes = read_stored(reader, codec, title_len, |[byte]: [u8; 1]| byte)?;
+        let titles = title_offsets
+            .windows(2)
+            .map(|range| {
+                let bytes = usize::try_from(range[0])
+                    .ok()
+                    .zip(usize::try_from(range[1]).ok())
+                    .and_then(|(start, end)| title_bytes.get(start..end))
+                    .context("Title offsets are out of bounds")?;
+                String::from_utf8(bytes.to_vec()).context("Title is not valid UTF-8")
+            })
+            .collect::<anyhow::Result<_>>()?;
+        reader.skip_padding()?;
+        let offsets = read_all_le(reader, node_count + 1, u64::from_le_bytes)?;
+        let targets = read_stored(reader, codec, edge_count, u32::from_le_bytes)?;
+        let (back_offsets, sources) = if has_backlinks {
+            reader.skip_padding()?;
+            (
+                read_all_le(reader, node_count + 1, u64::from_le_bytes)?,
+                read_stored(reader, codec, edge_count, u32::from_le_bytes)?,
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let by_title = read_all_le(reader, node_count, u32::from_le_bytes)?;
+        let by_folded_title = if version >= 12 {
+            read_all_le(reader, node_count, u32::from_le_bytes)?
+        } else {
+            Vec::new()
+        };
+        let redirects = read_all_le(reader, redirect_count * 2, u32::from_le_bytes)?
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        let kinds = read_kinds(reader, node_count)?;
+        let weights = if version >= 10 {
+            let mut present = [0];
+            reader
+                .read_exact(&mut present)
+                .context("Unexpected end of file")?;
+            match present {
+                [0] => None,
+                [1] => Some(read_stored(reader, codec, edge_count, u32::from_le_bytes)?),
+                _ => anyhow::bail!("Invalid link weights marker"),
+            }
+        } else {
+            None
+        };
+        let wikidata = if version >= 13 {
+            let mut present = [0];
+            reader
+                .read_exact(&mut present)
+                .context("Unexpected end of file")?;
+            match present {
+                [0] => None,
+                [1] => Some(read_all_le(reader, node_count, u32::from_le_bytes)?),
+                _ => anyhow::bail!("Invalid Wikidata items marker"),
+            }
+        } else {
+            None
+        };
+        Ok(Self {
+            titles,
+            offsets,
+            targets,
+            weights,
+            wikidata,
+            has_backlinks,
+            back_offsets,
+            sources,
+            by_title,
+            by_folded_title,
+            kinds,
+            redirects,
+            ..Self::default()
+        })
+    }
+
+    /// The titles, links, kinds, and redirects of versions before 8, which store those that
+    /// they have one after the other.
+    fn read_sequential(reader: &mut impl Read, version: u32) -> anyhow::Result<Self> {
+        let node_count = usize::try_from(read_u64(reader)?)?;
+        let edge_count = usize::try_from(read_u64(reader)?)?;
+
+        // Counts come from the file, so don't trust them for preallocation.
+        let mut titles = Vec::new();
+        for _ in 0..node_count {
+            let len = read_u32(reader)?;
+            let mut title = Vec::new();
+            reader.take(u64::from(len)).read_to_end(&mut title)?;
+            anyhow::ensure!(title.len() == len as usize, "Unexpected end of file");
+            titles.push(String::from_utf8(title).context("Title is not valid UTF-8")?);
+        }
+        let offsets = read_all_le(reader, node_count as u64 + 1, u64::from_le_bytes)?;
+        let targets = read_all_le(reader, edge_count as u64, u32::from_le_bytes)?;
+        let kinds = if version >= 5 {
+            read_kinds(reader, node_count as u64)?
+        } else {
+            vec![None; node_count]
+        };
+        let redirects = if version >= 6 {
+            let count = read_u64(reader)?;
+            (0..count)
+                .map(|_| Ok((read_u32(reader)?, read_u32(reader)?)))
+                .collect::<anyhow::Result<_>>()?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            titles,
+            offsets,
+            targets,
+            kinds,
+            redirects,
+            ..Self::default()
+        })
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Everything wrong with the structure: offsets that don't delimit the targets, links to
+    /// nodes that don't exist, unsorted or duplicate links, zero link weights, duplicate titles,
+    /// and redirects that are unsorted, repeated, or between nodes that don't exist. Only the
+    /// first few problems of each kind are listed.
+    pub fn problems(&self) -> Vec<String> {
+        const LIMIT: usize = 10;
+        let mut problems = Vec::new();
+        let node_count = self.titles.len();
+        let edge_count = self.targets.len() as u64;
+
+        if self.offsets.first() != Some(&0) {
+            problems.push(String::from("First offset is not 0"));
+        }
+        if self.offsets.last() != Some(&edge_count) {
+            problems.push(format!("Last offset is not the link count {edge_count}"));
+        }
+        let mut bad_offsets = 0;
+        let mut bad_lists = 0;
+        for (node, range) in self.offsets.windows(2).enumerate() {
+            let (start, end) = (range[0], range[1]);
+            if start > end || end > edge_count {
+                if bad_offsets < LIMIT {
+                    problems.push(format!(
+                        "Offsets of node {node} are out of bounds: {start}..{end}"
+                    ));
+                }
+                bad_offsets += 1;
+                continue;
+            }
+            // In bounds, so they fit in a usize.
+            let links =
+                &self.targets[usize::try_from(start).unwrap()..usize::try_from(end).unwrap()];
+            if !links.windows(2).all(|pair| pair[0] < pair[1]) {
+                if bad_lists < LIMIT {
+                    problems.push(format!(
+                        "Links of node {node} are not sorted or have duplicates"
+                    ));
+                }
+                bad_lists += 1;
+            }
+        }
+
+        let dangling: Vec<_> = self
+            .targets
+            .iter()
+            .enumerate()
+            .filter(|&(_, &target)| target as usize >= node_count)
+            .collect();
+        for &(edge, target) in dangling.iter().take(LIMIT) {
+            problems.push(format!(
+                "Link {edge} targets node {target}, but there are only {node_count} nodes"
+            ));
+        }
+
+        let weightless = self
+            .weights
+            .iter()
+            .flatten()
+            .filter(|&&weight| weight == 0)
+            .count();
+        if weightless > 0 {
+            problems.push(format!("{weightless} links weigh 0"));
+        }
+
+        let mut seen = HashSet::new();
+        let duplicates: Vec<_> = self
+            .titles
+            .iter()
+            .filter(|title| !seen.insert(title.as_str()))
+            .collect();
+        for title in duplicates.iter().take(LIMIT) {
+            problems.push(format!("Title '{title}' belongs to several nodes"));
+        }
+
+        if !self.redirects.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            problems.push(String::from("Redirects are not sorted or have duplicates"));
+        }
+        let bad_redirects: Vec<_> = self
+            .redirects
+            .iter()
+            .filter(|&&(page, target)| page as usize >= node_count || target as usize >= node_count)
+            .collect();
+        for (page, target) in bad_redirects.iter().take(LIMIT) {
+            problems.push(format!(
+                "Redirect from node {page} to node {target}, but there are only {node_count} nodes"
+            ));
+        }
+
+        for (count, kind) in [
+            (bad_offsets, "bad offsets"),
+            (bad_lists, "unsorted link lists"),
+            (dangling.len(), "dangling links"),
+            (duplicates.len(), "duplicate titles"),
+            (bad_redirects.len(), "bad redirects"),
+        ] {
+            if count > LIMIT {
+                problems.push(format!("... {} more {kind}", count - LIMIT));
+            }
+        }
+        if problems.is_empty() && self.version >= 8 {
+            problems.extend(self.index_problems());
+        }
+        problems
+    }
+
+    /// What is wrong with the backlinks and title order stored for mapping, given that the
+    /// links and titles are sound.
+    fn index_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.has_backlinks {
+            let (back_offsets, sources) =
+                backlinks(self.titles.len(), &self.offsets, &self.targets);
+            if back_offsets != self.back_offsets || sources != self.sources {
+                problems.push(String::from("Stored backlinks don't match the links"));
+            }
+        }
+        let mut by_title: Vec<usize> = self.by_title.iter().map(|&node| node as usize).collect();
+        let sorted = by_title
+            .windows(2)
+            .all(|pair| self.titles.get(pair[0]) < self.titles.get(pair[1]));
+        by_title.sort_unstable();
+        if !sorted || !by_title.iter().copied().eq(0..self.titles.len()) {
+            problems.push(String::from("Stored title order is not the titles sorted"));
+        }
+        if self.version >= 12 {
+            let mut by_folded_title: Vec<usize> = self
+                .by_folded_title
+                .iter()
+                .map(|&node| node as usize)
+                .collect();
+            let folded = |node: usize| self.titles.get(node).map(|title| title_search::fold(title));
+            let sorted = by_folded_title.windows(2).all(|pair| {
+                folded(pair[0])
+                    .into_iter()
+                    .flatten()
+                    .le(folded(pair[1]).into_iter().flatten())
+            });
+            by_folded_title.sort_unstable();
+            if !sorted || !by_folded_title.iter().copied().eq(0..self.titles.len()) {
+                problems.push(String::from(
+                    "Stored folded title order is not the folded titles sorted",
+                ));
+            }
+        }
+        problems
+    }
+}
+
+/// The CSR offsets and sources of the backlinks of the links given by `offsets` and `targets`,
+/// which must be valid.
+fn backlinks(node_count: usize, offsets: &[u64], targets: &[u32]) -> (Vec<u64>, Vec<u32>) {
+    let mut back_offsets = vec![0; node_count + 1];
+    for &target in targets {
+        back_offsets[target as usize + 1] += 1;
+    }
+    for n in 0..node_count {
+        back_offsets[n + 1] += back_offsets[n];
+    }
+    // Visiting sources in order fills every backlink list already sorted.
+    let mut next = back_offsets.clone();
+    let mut sources = vec![0; targets.len()];
+    for source in 0..node_count {
+        let start = usize::try_from(offsets[source]).unwrap();
+        let end = usize::try_from(offsets[source + 1]).unwrap();
+        for &target in &targets[start..end] {
+            let slot = &mut next[target as usize];
+            sources[usize::try_from(*slot).unwrap()] = u32::try_from(source).unwrap();
+            *slot += 1;
+        }
+    }
+    (back_offsets, sources)
+}
+
+/// Computes a CRC-32 of everything written or read through it, and counts the bytes, from the
+/// start of the file, so that sections can be padded.
+struct Checksummed<T> {
+    inner: T,
+    hasher: crc32fast::Hasher,
+    position: u64,
+}
+
+impl<T> Checksummed<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+            position: 0,
+        }
+    }
+
+    /// How many bytes of padding bring the position to a multiple of `ALIGN`.
+    fn padding(&self) -> u64 {
+        self.position.next_multiple_of(ALIGN) - self.position
+    }
+}
+
+impl<R: Read> Checksummed<R> {
+    fn skip_padding(&mut self) -> anyhow::Result<()> {
+        let padding = self.padding();
+        let mut bytes = Vec::new();
+        self.take(padding).read_to_end(&mut bytes)?;
+        anyhow::ensure!(bytes.len() as u64 == padding, "Unexpected end of file");
+        Ok(())
+    }
+}
+
+impl<W: Write> Checksummed<W> {
+    fn pad(&mut self) -> std::io::Result<()> {
+        let padding = vec![0; usize::try_from(self.padding()).unwrap()];
+        self.write_all(&padding)
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Whether the file at `path` starts with `magic`.
+fn starts_with(path: &Path, magic: &[u8]) -> anyhow::Result<bool> {
+    let mut start = vec![0; magic.len()];
+    let mut file = File::open(path)?;
+    let matches = match file.read_exact(&mut start) {
+        Ok(()) => start == magic,
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => false,
+        Err(error) => return Err(error.into()),
+    };
+    Ok(matches)
+}
+
+/// A kind byte for each of `node_count` nodes.
+fn read_kinds(reader: &mut impl Read, node_count: u64) -> anyhow::Result<Vec<Option<Kind>>> {
+    let mut bytes = Vec::new();
+    reader.take(node_count).read_to_end(&mut bytes)?;
+    anyhow::ensure!(bytes.len() as u64 == node_count, "Unexpected end of file");
+    bytes.into_iter().map(Kind::from_byte).collect()
+}
+
+/// Write `values` in bulk rather than a few bytes at a time, since the CSR arrays of a large
+/// graph have hundreds of millions of them.
+fn write_all_le<T: Copy, const N: usize>(
+    writer: &mut impl Write,
+    values: &[T],
+    to_le_bytes: impl Fn(T) -> [u8; N],
+) -> std::io::Result<()> {
+    let mut buffer = Vec::with_capacity(1 << 16);
+    for chunk in values.chunks((1 << 16) / N) {
+        buffer.clear();
+        buffer.extend(chunk.iter().flat_map(|&value| to_le_bytes(value)));
+        writer.write_all(&buffer)?;
+    }
+    Ok(())
+}
+
+/// Write `values`, whose lists for each node start at `offsets`, as `write_all_le` does for
+/// `Codec::None`, or else in blocks of whole lists: padding, the number of blocks, the index of
+/// the first value of each block and then the number of values, the position of each block in
+/// the data and then the length of the data, the blocks compressed one by one, and padding.
+fn write_stored<T: Copy, const N: usize, W: Write>(
+    writer: &mut Checksummed<W>,
+    codec: Codec,
+    values: &[T],
+    offsets: &[u64],
+    to_le_bytes: impl Fn(T) -> [u8; N],
+) -> std::io::Result<()> {
+    if codec == Codec::None {
+        return write_all_le(writer, values, to_le_bytes);
+    }
+    let starts = block_starts(offsets, N as u64);
+    let blocks = starts
+        .windows(2)
+        .map(|range| {
+            let range = usize::try_from(range[0]).unwrap()..usize::try_from(range[1]).unwrap();
+            let raw: Vec<u8> = values[range]
+                .iter()
+                .flat_map(|&value| to_le_bytes(value))
+                .collect();
+            codec.compress(&raw)
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let mut positions = vec![0];
+    for block in &blocks {
+        positions.push(positions.last().unwrap() + block.len() as u64);
+    }
+    writer.pad()?;
+    writer.write_all(&(blocks.len() as u64).to_le_bytes())?;
+    write_all_le(writer, &starts, u64::to_le_bytes)?;
+    write_all_le(writer, &positions, u64::to_le_bytes)?;
+    for block in &blocks {
+        writer.write_all(block)?;
+    }
+    writer.pad()
+}
+
+/// Where the blocks of values of `size` bytes start, splitting them only between the lists
+/// starting at `offsets`, followed by the number of values.
+fn block_starts(offsets: &[u64], size: u64) -> Vec<u64> {
+    let mut starts = vec![0];
+    for &end in offsets.iter().skip(1) {
+        if (end - starts.last().unwrap()) * size >= BLOCK_BYTES {
+            starts.push(end);
+        }
+    }
+    let count = offsets.last().copied().unwrap_or(0);
+    if *starts.last().unwrap() != count {
+        starts.push(count);
+    }
+    starts
+}
+
+/// Read `count` values written by `write_stored`.
+fn read_stored<T, const N: usize, R: Read>(
+    reader: &mut Checksummed<R>,
+    codec: Codec,
+    count: u64,
+    from_le_bytes: impl Fn([u8; N]) -> T,
+) -> anyhow::Result<Vec<T>> {
+    if codec == Codec::None {
+        return read_all_le(reader, count, from_le_bytes);
+    }
+    reader.skip_padding()?;
+    let blocks = read_u64(reader)?;
+    let entries = blocks.checked_add(1).context("Invalid block count")?;
+    let starts = read_all_le(reader, entries, u64::from_le_bytes)?;
+    let positions = read_all_le(reader, entries, u64::from_le_bytes)?;
+    anyhow::ensure!(
+        valid_blocks(&starts, &positions, count),
+        "Invalid block index"
+    );
+    let len = *positions.last().unwrap();
+    let mut data = Vec::new();
+    reader.take(len).read_to_end(&mut data)?;
+    anyhow::ensure!(data.len() as u64 == len, "Unexpected end of file");
+
+    let mut values = Vec::new();
+    for (range, stored) in starts.windows(2).zip(positions.windows(2)) {
+        let stored = usize::try_from(stored[0])?..usize::try_from(stored[1])?;
+        let raw = codec.decompress(
+            &data[stored],
+            usize::try_from((range[1] - range[0]) * N as u64)?,
+        )?;
+        values.extend(
+            raw.chunks_exact(N)
+                .map(|chunk| from_le_bytes(chunk.try_into().unwrap())),
+        );
+    }
+    reader.skip_padding()?;
+    Ok(values)
+}
+
+/// Read `count` values written by `write_all_le`.
+fn read_all_le<T, const N: usize>(
+    reader: &mut impl Read,
+    count: u64,
+    from_le_bytes: impl Fn([u8; N]) -> T,
+) -> anyhow::Result<Vec<T>> {
+    let len = count
+        .checked_mul(N as u64)
+        .context("Unexpected end of file")?;
+    // The count comes from the file, so don't trust it for preallocation.
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    anyhow::ensure!(bytes.len() as u64 == len, "Unexpected end of file");
+    Ok(bytes
+        .chunks_exact(N)
+        .map(|chunk| from_le_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+/// Up to `limit` of the chains from the root of `parents` to `end`, following the neighbours
+/// each node was reached from, which `parents` keeps with the node's level.
+fn chains(parents: &HashMap<u32, (u32, Vec<u32>)>, end: u32, limit: usize) -> Vec<Vec<u32>> {
+    let mut chains = Vec::new();
+    // The chain from `end` back so far, and the next of its last node's neighbours to try.
+    let mut stack = vec![(end, 0)];
+    while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+        if chains.len() == limit {
+            break;
+        }
+        let from = &parents[&node].1;
+        if from.is_empty() {
+            chains.push(stack.iter().rev().map(|&(node, _)| node).collect());
+            stack.pop();
+        } else if let Some(&parent) = from.get(*next) {
+            *next += 1;
+            stack.push((parent, 0));
+        } else {
+            stack.pop();
+        }
+    }
+    chains
+}
+
+/// The path through `meeting`, from the root of `forward` to the root of `backward`, whose
+/// entries point from each node toward their roots.
+fn join(forward: &HashMap<u32, u32>, backward: &HashMap<u32, u32>, meeting: u32) -> Vec<u32> {
+    let mut path = vec![meeting];
+    let mut node = meeting;
+    while forward[&node] != node {
+        node = forward[&node];
+        path.push(node);
+    }
+    path.reverse();
+    let mut node = meeting;
+    while backward[&node] != node {
+        node = backward[&node];
+        path.push(node);
+    }
+    path
+}
@@ -0,0 +1,911 @@
+//! Graph files with their links compressed as Boldi and Vigna's BV format compresses those of
+//! the web, for graphs too large to load as they are. The list of a
+//! node is written as a bit stream of instantaneous codes: its length; which of the previous
+//! `window` lists it copies links from, and which runs of that list it copies; runs of
+//! consecutive targets as intervals; and the rest as gaps between targets. Links between pages
+//! near each other in node order make the gaps small and the lists similar, so a wiki's links
+//! take a byte or so each rather than four.
+//!
+//! The lists are decoded as they are read, so a search or PageRank over a compressed graph only
+//! needs memory for the bit stream, the titles, and its own state. Loading one as a `Graph`
+//! decodes it whole. Link weights, Wikidata items, and stored statistics aren't kept.
+
+use super::{read_kinds, read_u32, read_u64, write_all_le, Checksummed, Graph, Metadata};
+use crate::navigation::Kind;
+use anyhow::Context as _;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write as _},
+    path::Path,
+};
+
+pub const MAGIC: &[u8; 8] = b"WIKIGRBV";
+const VERSION: u32 = 1;
+/// Nodes between the absolute bit positions kept when loading; those of the nodes between are
+/// kept relative to them, in half the memory.
+const ANCHOR_EVERY: usize = 64;
+/// The `k` of the ζ code of the gaps between targets, which suits their power-law sizes.
+const ZETA_K: u32 = 3;
+
+/// How lists are compressed: Boldi and Vigna's defaults compress a wiki's links about as well as
+/// larger windows, which take longer to compress.
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    /// How many of the lists before a node's its list can copy links from, or 0 for none.
+    pub window: u32,
+    /// Longest chain of lists copying from lists copying from others; longer chains compress
+    /// better, but take longer to decode a list of.
+    pub max_ref_count: u32,
+    /// Fewest consecutive targets written as an interval rather than as gaps.
+    pub min_interval: u32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            window: 7,
+            max_ref_count: 3,
+            min_interval: 4,
+        }
+    }
+}
+
+pub struct BvGraph {
+    titles: Vec<String>,
+    kinds: Vec<Option<Kind>>,
+    redirects: HashMap<u32, u32>,
+    metadata: Metadata,
+    params: Params,
+    edge_count: u64,
+    /// The lists, one after another, most significant bit first in each word.
+    bits: Vec<u64>,
+    bit_len: u64,
+    /// Where the list of every `ANCHOR_EVERY`th node starts in `bits`, and of each node, from
+    /// the last of those.
+    anchors: Vec<u64>,
+    relative: Vec<u32>,
+}
+
+impl BvGraph {
+    /// `graph` with its links compressed as `params` says.
+    pub fn from_graph(graph: &Graph, params: Params) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            params.min_interval >= 2,
+            "Intervals need at least 2 targets"
+        );
+        let node_count = graph.node_count();
+        let mut writer = BitWriter::default();
+        let mut scratch = BitWriter::default();
+        let mut lengths = Vec::with_capacity(node_count);
+        // How many lists each node's list copies from in a chain.
+        let mut ref_counts = vec![0; node_count];
+        for node in 0..u32::try_from(node_count)? {
+            let list = graph.links(node);
+            let mut best = (u64::MAX, 0);
+            for reference in 0..=params.window.min(node) {
+                let source = (node - reference) as usize;
+                if reference > 0 && ref_counts[source] >= params.max_ref_count {
+                    continue;
+                }
+                scratch.clear();
+                let referenced = graph.links(node - reference);
+                encode(&mut scratch, node, list, reference, referenced, params);
+                best = best.min((scratch.len, reference));
+            }
+            let reference = best.1;
+            if reference > 0 {
+                ref_counts[node as usize] = ref_counts[(node - reference) as usize] + 1;
+            }
+            let start = writer.len;
+            let referenced = graph.links(node - reference);
+            encode(&mut writer, node, list, reference, referenced, params);
+            lengths.push(writer.len - start);
+        }
+
+        let (anchors, relative) = positions(&lengths)?;
+        Ok(Self {
+            titles: graph.titles.clone(),
+            kinds: graph.kinds.clone(),
+            redirects: graph.redirects.clone(),
+            metadata: graph.metadata.clone(),
+            params,
+            edge_count: graph.edge_count() as u64,
+            bit_len: writer.len,
+            bits: writer.words,
+            anchors,
+            relative,
+        })
+    }
+
+    /// The graph with its lists decoded.
+    pub fn to_graph(&self) -> Graph {
+        let mut offsets = Vec::with_capacity(self.titles.len() + 1);
+        offsets.push(0);
+        let mut targets = Vec::with_capacity(usize::try_from(self.edge_count).unwrap_or(0));
+        self.for_each_list(|_, list| {
+            targets.extend_from_slice(list);
+            offsets.push(targets.len() as u64);
+        });
+        Graph::from_parts(
+            self.titles.clone(),
+            offsets,
+            targets,
+            self.kinds.clone(),
+            self.redirects.clone(),
+            self.metadata.clone(),
+        )
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn edge_count(&self) -> u64 {
+        self.edge_count
+    }
+
+    /// Bytes the lists take.
+    pub fn link_bytes(&self) -> u64 {
+        self.bit_len.div_ceil(8)
+    }
+
+    pub fn title(&self, id: u32) -> &str {
+        &self.titles[id as usize]
+    }
+
+    /// The node titled `title`, found by going through the titles, since a map of them would
+    /// take as much memory as the lists.
+    pub fn id(&self, title: &str) -> Option<u32> {
+        let node = self.titles.iter().position(|other| other == title)?;
+        u32::try_from(node).ok()
+    }
+
+    pub fn redirect(&self, id: u32) -> Option<u32> {
+        self.redirects.get(&id).copied()
+    }
+
+    /// The sorted list of nodes `id` links to, decoded into `list`.
+    pub fn links_into(&self, id: u32, list: &mut Vec<u32>) {
+        list.clear();
+        let mut reader = self.reader(id);
+        let degree = reader.gamma_u32() as usize;
+        if degree == 0 {
+            return;
+        }
+        let reference = if self.params.window > 0 {
+            reader.gamma_u32()
+        } else {
+            0
+        };
+        let mut referenced = Vec::new();
+        if reference > 0 {
+            self.links_into(id - reference, &mut referenced);
+        }
+        decode(
+            &mut reader,
+            id,
+            degree,
+            &referenced,
+            reference,
+            self.params,
+            list,
+        );
+    }
+
+    /// Call `f` with each node and its sorted list, in order. Lists are decoded one after
+    /// another, keeping those a later one can copy from, so nothing is decoded twice.
+    pub fn for_each_list(&self, mut f: impl FnMut(u32, &[u32])) {
+        let window = self.params.window as usize + 1;
+        let mut recent: Vec<Vec<u32>> = vec![Vec::new(); window];
+        let mut reader = BitReader {
+            words: &self.bits,
+            position: 0,
+        };
+        for node in 0..self.titles.len() {
+            let id = u32::try_from(node).unwrap();
+            let mut list = std::mem::take(&mut recent[node % window]);
+            list.clear();
+            let degree = reader.gamma_u32() as usize;
+            if degree > 0 {
+                let reference = if self.params.window > 0 {
+                    reader.gamma_u32()
+                } else {
+                    0
+                };
+                let referenced = &recent[(node + window - reference as usize) % window];
+                let referenced = if reference > 0 { &referenced[..] } else { &[] };
+                decode(
+                    &mut reader,
+                    id,
+                    degree,
+                    referenced,
+                    reference,
+                    self.params,
+                    &mut list,
+                );
+            }
+            f(id, &list);
+            recent[node % window] = list;
+        }
+    }
+
+    fn reader(&self, id: u32) -> BitReader<'_> {
+        let node = id as usize;
+        BitReader {
+            words: &self.bits,
+            position: self.anchors[node / ANCHOR_EVERY] + u64::from(self.relative[node]),
+        }
+    }
+
+    /// Write the graph as: magic, version, length-prefixed JSON metadata; node and link
+    /// counts, and the window, longest reference chain, and shortest interval of the lists;
+    /// the length of a zstd frame of the titles, each prefixed by its length in bytes, and the
+    /// frame; a kind byte per node; the number of redirects and (page, target) pairs sorted by
+    /// page; the length in bits and the words of a stream of the γ-coded length in bits of the
+    /// list of each node, and of the stream of the lists; and a CRC-32 of everything before
+    /// it. All integers are little-endian. The file is replaced only once it is complete.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let partial = path.with_extension("partial");
+        let mut writer = Checksummed::new(BufWriter::new(File::create(&partial)?));
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let metadata = serde_json::to_vec(&self.metadata)?;
+        writer.write_all(&u32::try_from(metadata.len())?.to_le_bytes())?;
+        writer.write_all(&metadata)?;
+        write_all_le(
+            &mut writer,
+            &[self.titles.len() as u64, self.edge_count],
+            u64::to_le_bytes,
+        )?;
+        let Params {
+            window,
+            max_ref_count,
+            min_interval,
+        } = self.params;
+        write_all_le(
+            &mut writer,
+            &[window, max_ref_count, min_interval],
+            u32::to_le_bytes,
+        )?;
+
+        let mut titles = Vec::new();
+        for title in &self.titles {
+            titles.extend(u32::try_from(title.len())?.to_le_bytes());
+            titles.extend(title.as_bytes());
+        }
+        let titles = zstd::bulk::compress(&titles, super::ZSTD_LEVEL)?;
+        writer.write_all(&(titles.len() as u64).to_le_bytes())?;
+        writer.write_all(&titles)?;
+        let kinds: Vec<u8> = self.kinds.iter().map(|&kind| Kind::to_byte(kind)).collect();
+        writer.write_all(&kinds)?;
+        let mut redirects: Vec<(u32, u32)> = self.redirects.iter().map(|(&a, &b)| (a, b)).collect();
+        redirects.sort_unstable();
+        writer.write_all(&(redirects.len() as u64).to_le_bytes())?;
+        for (page, target) in redirects {
+            writer.write_all(&page.to_le_bytes())?;
+            writer.write_all(&target.to_le_bytes())?;
+        }
+
+        let mut lengths = BitWriter::default();
+        for node in 0..self.titles.len() {
+            lengths.gamma(self.list_end(node) - self.list_start(node));
+        }
+        for stream in [&lengths, &BitWriter::of(&self.bits, self.bit_len)] {
+            writer.write_all(&stream.len.to_le_bytes())?;
+            write_all_le(&mut writer, &stream.words, u64::to_le_bytes)?;
+        }
+        let checksum = writer.hasher.clone().finalize();
+        writer.inner.write_all(&checksum.to_le_bytes())?;
+        writer.inner.flush()?;
+        drop(writer);
+        fs::rename(partial, path)?;
+        Ok(())
+    }
+
+    /// Load a graph saved by `save`, failing if it is corrupt.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut reader = Checksummed::new(BufReader::new(File::open(path)?));
+        let mut magic = [0; 8];
+        reader
+            .read_exact(&mut magic)
+            .context("Not a compressed graph file")?;
+        anyhow::ensure!(&magic == MAGIC, "Not a compressed graph file");
+        let version = read_u32(&mut reader)?;
+        anyhow::ensure!(
+            version == VERSION,
+            "Unsupported compressed graph format version {version}"
+        );
+        let metadata_len = read_u32(&mut reader)?;
+        let mut metadata = Vec::new();
+        (&mut reader)
+            .take(u64::from(metadata_len))
+            .read_to_end(&mut metadata)?;
+        let metadata: Metadata = serde_json::from_slice(&metadata).context("Invalid metadata")?;
+        let node_count = read_u64(&mut reader)?;
+        let edge_count = read_u64(&mut reader)?;
+        let params = Params {
+            window: read_u32(&mut reader)?,
+            max_ref_count: read_u32(&mut reader)?,
+            min_interval: read_u32(&mut reader)?,
+        };
+
+        let titles_len = read_u64(&mut reader)?;
+        let mut titles = Vec::new();
+        (&mut reader).take(titles_len).read_to_end(&mut titles)?;
+        anyhow::ensure!(titles.len() as u64 == titles_len, "Unexpected end of file");
+        let titles = read_titles(&zstd::stream::decode_all(&titles[..])?, node_count)?;
+        let kinds = read_kinds(&mut reader, node_count)?;
+        let redirect_count = read_u64(&mut reader)?;
+        let mut redirects = HashMap::new();
+        for _ in 0..redirect_count {
+            let (page, target) = (read_u32(&mut reader)?, read_u32(&mut reader)?);
+            anyhow::ensure!(
+                u64::from(page.max(target)) < node_count,
+                "Redirect out of range"
+            );
+            redirects.insert(page, target);
+        }
+
+        let lengths = read_stream(&mut reader)?;
+        let (bits, bit_len) = read_stream(&mut reader)?;
+        let computed = reader.hasher.clone().finalize();
+        let stored = read_u32(&mut reader.inner).context("Missing checksum")?;
+        anyhow::ensure!(
+            computed == stored,
+            "Checksum mismatch; the graph file is corrupt"
+        );
+
+        let mut reader = BitReader {
+            words: &lengths.0,
+            position: 0,
+        };
+        let mut list_lengths = Vec::with_capacity(usize::try_from(node_count)?);
+        for _ in 0..node_count {
+            anyhow::ensure!(reader.position < lengths.1, "Missing list lengths");
+            list_lengths.push(reader.gamma());
+        }
+        anyhow::ensure!(
+            list_lengths.iter().sum::<u64>() == bit_len,
+            "List lengths don't add up to the lists"
+        );
+        let (anchors, relative) = positions(&list_lengths)?;
+        Ok(Self {
+            titles,
+            kinds,
+            redirects,
+            metadata,
+            params,
+            edge_count,
+            bits,
+            bit_len,
+            anchors,
+            relative,
+        })
+    }
+
+    fn list_start(&self, node: usize) -> u64 {
+        self.anchors[node / ANCHOR_EVERY] + u64::from(self.relative[node])
+    }
+
+    fn list_end(&self, node: usize) -> u64 {
+        if node + 1 < self.titles.len() {
+            self.list_start(node + 1)
+        } else {
+            self.bit_len
+        }
+    }
+}
+
+/// Write the list of `node`, copying from `referenced`, the list of `reference` nodes before,
+/// unless `reference` is 0.
+fn encode(
+    writer: &mut BitWriter,
+    node: u32,
+    list: &[u32],
+    reference: u32,
+    referenced: &[u32],
+    params: Params,
+) {
+    writer.gamma(list.len() as u64);
+    if list.is_empty() {
+        return;
+    }
+    if params.window > 0 {
+        writer.gamma(u64::from(reference));
+    }
+    let mut extra = Vec::with_capacity(list.len());
+    if reference > 0 {
+        // Runs of the referenced list, alternately copied and not, starting with a copied one
+        // that may be empty. The last is left out, since its length follows from the others.
+        let mut blocks = Vec::new();
+        let (mut copying, mut run) = (true, 0);
+        for target in referenced {
+            if list.binary_search(target).is_ok() == copying {
+                run += 1;
+            } else {
+                blocks.push(run);
+                (copying, run) = (!copying, 1);
+            }
+        }
+        writer.gamma(blocks.len() as u64);
+        for (i, &block) in blocks.iter().enumerate() {
+            writer.gamma(if i == 0 { block } else { block - 1 });
+        }
+        extra.extend(
+            list.iter()
+                .filter(|target| referenced.binary_search(target).is_err()),
+        );
+    } else {
+        extra.extend_from_slice(list);
+    }
+
+    let mut intervals = Vec::new();
+    let mut residuals = Vec::new();
+    let mut i = 0;
+    while i < extra.len() {
+        let mut end = i + 1;
+        while end < extra.len() && extra[end] == extra[end - 1] + 1 {
+            end += 1;
+        }
+        if end - i >= params.min_interval as usize {
+            intervals.push((extra[i], (end - i) as u64));
+        } else {
+            residuals.extend_from_slice(&extra[i..end]);
+        }
+        i = end;
+    }
+    writer.gamma(intervals.len() as u64);
+    let mut previous_end = None;
+    for &(left, len) in &intervals {
+        match previous_end {
+            None => writer.gamma(to_natural(i64::from(left) - i64::from(node))),
+            Some(end) => writer.gamma(u64::from(left) - end - 1),
+        }
+        writer.gamma(len - u64::from(params.min_interval));
+        previous_end = Some(u64::from(left) + len);
+    }
+    let mut previous = None;
+    for &target in &residuals {
+        match previous {
+            None => writer.zeta(to_natural(i64::from(target) - i64::from(node))),
+            Some(previous) => writer.zeta(u64::from(target - previous - 1)),
+        }
+        previous = Some(target);
+    }
+}
+
+/// Read the rest of the list of `node`, of `degree` targets, after its reference, into `list`.
+fn decode(
+    reader: &mut BitReader,
+    node: u32,
+    degree: usize,
+    referenced: &[u32],
+    reference: u32,
+    params: Params,
+    list: &mut Vec<u32>,
+) {
+    let mut copied = Vec::new();
+    if reference > 0 {
+        let count = reader.gamma_u32() as usize;
+        let mut position = 0;
+        for i in 0..count {
+            let block = reader.gamma_u32() as usize + usize::from(i > 0);
+            if i % 2 == 0 {
+                copied.extend_from_slice(&referenced[position..position + block]);
+            }
+            position += block;
+        }
+        if count.is_multiple_of(2) {
+            copied.extend_from_slice(&referenced[position..]);
+        }
+    }
+    let mut intervals = Vec::new();
+    let interval_count = reader.gamma();
+    let mut previous_end = None;
+    for _ in 0..interval_count {
+        let left = match previous_end {
+            None => u32::try_from(i64::from(node) + from_natural(reader.gamma())).unwrap(),
+            Some(end) => end + 1 + reader.gamma_u32(),
+        };
+        let len = reader.gamma_u32() + params.min_interval;
+        intervals.extend(left..left + len);
+        previous_end = Some(left + len);
+    }
+    let residual_count = degree - copied.len() - intervals.len();
+    let mut residuals = Vec::with_capacity(residual_count);
+    for _ in 0..residual_count {
+        let target = match residuals.last() {
+            None => u32::try_from(i64::from(node) + from_natural(reader.zeta())).unwrap(),
+            Some(&previous) => previous + 1 + reader.zeta_u32(),
+        };
+        residuals.push(target);
+    }
+    list.extend(copied);
+    list.extend(intervals);
+    list.extend(residuals);
+    list.sort_unstable();
+}
+
+/// `value` as a natural number: 0, -1, 1, -2, 2, ... as 0, 1, 2, 3, 4, ...
+fn to_natural(value: i64) -> u64 {
+    if value >= 0 {
+        value.unsigned_abs() * 2
+    } else {
+        value.unsigned_abs() * 2 - 1
+    }
+}
+
+fn from_natural(value: u64) -> i64 {
+    let half = i64::try_from(value / 2).unwrap();
+    if value.is_multiple_of(2) {
+        half
+    } else {
+        -half - 1
+    }
+}
+
+/// The start of the list of every `ANCHOR_EVERY`th node, and of each from the last of those,
+/// for lists `lengths` bits long.
+fn positions(lengths: &[u64]) -> anyhow::Result<(Vec<u64>, Vec<u32>)> {
+    let mut anchors = Vec::with_capacity(lengths.len() / ANCHOR_EVERY + 1);
+    let mut relative = Vec::with_capacity(lengths.len());
+    let mut position = 0;
+    for (node, &length) in lengths.iter().enumerate() {
+        if node % ANCHOR_EVERY == 0 {
+            anchors.push(position);
+        }
+        let anchor = anchors[node / ANCHOR_EVERY];
+        relative.push(u32::try_from(position - anchor).context("A list is too long")?);
+        position += length;
+    }
+    Ok((anchors, relative))
+}
+
+/// `node_count` titles, each prefixed by its length.
+fn read_titles(mut bytes: &[u8], node_count: u64) -> anyhow::Result<Vec<String>> {
+    let mut titles = Vec::new();
+    for _ in 0..node_count {
+        let len = read_u32(&mut bytes)? as usize;
+        anyhow::ensure!(len <= bytes.len(), "Unexpected end of titles");
+        let (title, rest) = bytes.split_at(len);
+        titles.push(String::from_utf8(title.to_vec()).context("Title is not valid UTF-8")?);
+        bytes = rest;
+    }
+    Ok(titles)
+}
+
+/// A bit stream: its length in bits, and its words.
+fn read_stream(reader: &mut impl Read) -> anyhow::Result<(Vec<u64>, u64)> {
+    let len = read_u64(reader)?;
+    let words = super::read_all_le(reader, len.div_ceil(64), u64::from_le_bytes)?;
+    Ok((words, len))
+}
+
+#[derive(Default)]
+struct BitWriter {
+    words: Vec<u64>,
+    len: u64,
+}
+
+impl BitWriter {
+    /// A copy of a stream of `len` bits in `words`.
+    fn of(words: &[u64], len: u64) -> Self {
+        Self {
+            words: words.to_vec(),
+            len,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.words.clear();
+        self.len = 0;
+    }
+
+    /// Write the low `count` bits of `value`, most significant first.
+    fn bits(&mut self, value: u64, count: u32) {
+        for bit in (0..count).rev() {
+            if self.len.is_multiple_of(64) {
+                self.words.push(0);
+            }
+            if value >> bit & 1 == 1 {
+                *self.words.last_mut().unwrap() |= 1 << (63 - self.len % 64);
+            }
+            self.len += 1;
+        }
+    }
+
+    fn unary(&mut self, value: u64) {
+        for _ in 0..value {
+            self.bits(0, 1);
+        }
+        self.bits(1, 1);
+    }
+
+    /// Elias γ code of `value + 1`.
+    fn gamma(&mut self, value: u64) {
+        let value = value + 1;
+        let width = value.ilog2();
+        self.unary(u64::from(width));
+        self.bits(value, width);
+    }
+
+    /// ζ code of `value + 1` with `ZETA_K`.
+    fn zeta(&mut self, value: u64) {
+        let value = value + 1;
+        let h = value.ilog2() / ZETA_K;
+        self.unary(u64::from(h));
+        let left = 1 << (h * ZETA_K);
+        self.minimal_binary(value - left, (1 << ((h + 1) * ZETA_K)) - left);
+    }
+
+    /// `value` of the range `0..size` in as few bits as the range needs.
+    fn minimal_binary(&mut self, value: u64, size: u64) {
+        if size <= 1 {
+            return;
+        }
+        let width = 64 - (size - 1).leading_zeros();
+        let short = (1 << width) - size;
+        if value < short {
+            self.bits(value, width - 1);
+        } else {
+            self.bits(value + short, width);
+        }
+    }
+}
+
+struct BitReader<'a> {
+    words: &'a [u64],
+    position: u64,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> u64 {
+        let word = self
+            .words
+            .get(usize::try_from(self.position / 64).unwrap_or(usize::MAX))
+            .copied()
+            .unwrap_or(0);
+        let bit = word >> (63 - self.position % 64) & 1;
+        self.position += 1;
+        bit
+    }
+
+    fn bits(&mut self, count: u32) -> u64 {
+        (0..count).fold(0, |value, _| value << 1 | self.bit())
+    }
+
+    fn unary(&mut self) -> u64 {
+        let mut value = 0;
+        while self.bit() == 0 {
+            value += 1;
+        }
+        value
+    }
+
+    fn gamma(&mut self) -> u64 {
+        let width = u32::try_from(self.unary()).unwrap();
+        ((1 << width) | self.bits(width)) - 1
+    }
+
+    /// A γ-coded count or gap of a list, which fits in a `u32` as node IDs do.
+    fn gamma_u32(&mut self) -> u32 {
+        u32::try_from(self.gamma()).unwrap_or(u32::MAX)
+    }
+
+    fn zeta_u32(&mut self) -> u32 {
+        u32::try_from(self.zeta()).unwrap_or(u32::MAX)
+    }
+
+    fn zeta(&mut self) -> u64 {
+        let h = u32::try_from(self.unary()).unwrap();
+        let left = 1 << (h * ZETA_K);
+        left + self.minimal_binary((1 << ((h + 1) * ZETA_K)) - left) - 1
+    }
+
+    fn minimal_binary(&mut self, size: u64) -> u64 {
+        if size <= 1 {
+            return 0;
+        }
+        let width = 64 - (size - 1).leading_zeros();
+        let short = (1 << width) - size;
+        let value = self.bits(width - 1);
+        if value < short {
+            value
+        } else {
+            (value << 1 | self.bit()) - short
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A graph whose node `i` links to `lists[i]`, titled `i`, and whose node 0 redirects to 1.
+    fn graph(lists: &[Vec<u32>]) -> Graph {
+        let mut offsets = vec![0];
+        let mut targets = Vec::new();
+        for list in lists {
+            targets.extend_from_slice(list);
+            offsets.push(targets.len() as u64);
+        }
+        Graph::from_parts(
+            (0..lists.len()).map(|node| node.to_string()).collect(),
+            offsets,
+            targets,
+            vec![None; lists.len()],
+            (lists.len() > 1).then_some((0, 1)).into_iter().collect(),
+            Metadata::default(),
+        )
+    }
+
+    /// Compress `lists` with `params`, checking that every way of decoding them gives them back.
+    fn round_trip(lists: &[Vec<u32>], params: Params) -> BvGraph {
+        let bv = BvGraph::from_graph(&graph(lists), params).unwrap();
+        let mut list = Vec::new();
+        for (node, expected) in (0..).zip(lists) {
+            bv.links_into(node, &mut list);
+            assert_eq!(&list, expected, "list of node {node} with {params:?}");
+        }
+        let mut decoded = Vec::new();
+        bv.for_each_list(|_, list| decoded.push(list.to_vec()));
+        assert_eq!(decoded, lists, "lists in order with {params:?}");
+        let graph = bv.to_graph();
+        for (node, expected) in (0..).zip(lists) {
+            assert_eq!(graph.links(node), &expected[..]);
+        }
+        assert_eq!(
+            bv.edge_count(),
+            lists.iter().map(Vec::len).sum::<usize>() as u64
+        );
+        bv
+    }
+
+    fn all_params() -> Vec<Params> {
+        let mut all = Vec::new();
+        for window in [0, 1, 7] {
+            for max_ref_count in [1, 3] {
+                for min_interval in [2, 4] {
+                    all.push(Params {
+                        window,
+                        max_ref_count,
+                        min_interval,
+                    });
+                }
+            }
+        }
+        all
+    }
+
+    /// Sorted, distinct lists for `count` nodes, like a wiki's: similar to the lists just
+    /// before, with runs of consecutive targets, and targets both before and after the node.
+    fn lists(count: u32) -> Vec<Vec<u32>> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = |below: u32| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            u32::try_from(state % u64::from(below)).unwrap()
+        };
+        let mut lists: Vec<Vec<u32>> = Vec::new();
+        for node in 0..count {
+            let mut list: Vec<u32> = match lists.last() {
+                Some(last) if random(2) == 0 => {
+                    last.iter().copied().filter(|_| random(4) > 0).collect()
+                }
+                _ => Vec::new(),
+            };
+            for _ in 0..random(6) {
+                list.push(random(count));
+            }
+            if random(3) == 0 {
+                let start = random(count);
+                list.extend(start..(start + random(10)).min(count));
+            }
+            if random(5) == 0 {
+                list.push(node);
+            }
+            list.sort_unstable();
+            list.dedup();
+            lists.push(list);
+        }
+        lists
+    }
+
+    #[test]
+    fn empty_lists() {
+        for params in all_params() {
+            round_trip(&[], params);
+            round_trip(&[vec![], vec![], vec![]], params);
+            round_trip(&[vec![], vec![0, 1], vec![], vec![1]], params);
+        }
+    }
+
+    #[test]
+    fn intervals() {
+        let lists = [
+            vec![1, 2, 3, 4, 5, 6, 7, 8],
+            vec![0, 2, 3, 5, 6, 7, 8, 20],
+            (10..30).chain([40]).collect(),
+            vec![0, 1, 3, 4, 6, 7, 9, 10],
+        ];
+        let lists: Vec<Vec<u32>> = lists
+            .into_iter()
+            .chain((4..41).map(|_| Vec::new()))
+            .collect();
+        for params in all_params() {
+            round_trip(&lists, params);
+        }
+    }
+
+    #[test]
+    fn windows() {
+        let shared: Vec<u32> = vec![3, 9, 14, 22, 37, 51, 60, 77, 80, 95];
+        let lists: Vec<Vec<u32>> = (0..100)
+            .map(|node: u32| {
+                let mut list = shared.clone();
+                list.retain(|&target| target % 7 != node % 7);
+                list.push(node);
+                list.sort_unstable();
+                list.dedup();
+                list
+            })
+            .collect();
+        let params = |window| Params {
+            window,
+            ..Params::default()
+        };
+        let copying = round_trip(&lists, params(7));
+        let plain = round_trip(&lists, params(0));
+        assert!(copying.link_bytes() < plain.link_bytes());
+    }
+
+    #[test]
+    fn many_nodes() {
+        // Past several anchors, with targets far before and after their sources.
+        let lists = lists(1000);
+        for params in all_params() {
+            round_trip(&lists, params);
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let lists = lists(300);
+        let bv = round_trip(&lists, Params::default());
+        let path = std::env::temp_dir().join(format!("wikigraph-bv-{}.graph", std::process::id()));
+        bv.save(&path).unwrap();
+        let loaded = BvGraph::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut decoded = Vec::new();
+        loaded.for_each_list(|_, list| decoded.push(list.to_vec()));
+        assert_eq!(decoded, lists);
+        assert_eq!(loaded.title(299), "299");
+        assert_eq!(loaded.redirect(0), Some(1));
+    }
+
+    #[test]
+    fn codes() {
+        let values: Vec<u64> = (0..1000).chain([1 << 20, u64::from(u32::MAX)]).collect();
+        let mut writer = BitWriter::default();
+        for &value in &values {
+            writer.gamma(value);
+            writer.zeta(value);
+            writer.minimal_binary(value % 5, 5);
+        }
+        let mut reader = BitReader {
+            words: &writer.words,
+            position: 0,
+        };
+        for &value in &values {
+            assert_eq!(reader.gamma(), value);
+            assert_eq!(reader.zeta(), value);
+            assert_eq!(reader.minimal_binary(5), value % 5);
+        }
+        assert_eq!(reader.position, writer.len);
+        for value in [0, 1, -1, 2, -2, 1000, -1000] {
+            assert_eq!(from_natural(to_natural(value)), value);
+        }
+    }
+}
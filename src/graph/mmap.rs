@@ -0,0 +1,58 @@
+//! Mapped graph files (see `wikigraph::graph_file::mmap`) as graphs commands can search.
+
+use super::Adjacency;
+use crate::{navigation::Kind, title_search};
+use std::{path::Path, sync::OnceLock};
+use wikigraph::graph_file::mmap;
+
+pub struct MmapGraph {
+    graph: mmap::MmapGraph,
+    /// The order of folded titles of files before version 12, derived when first needed.
+    derived_folded_order: OnceLock<Vec<u32>>,
+}
+
+impl MmapGraph {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            graph: mmap::MmapGraph::open(path)?,
+            derived_folded_order: OnceLock::new(),
+        })
+    }
+}
+
+impl Adjacency for MmapGraph {
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    fn id(&self, title: &str) -> Option<u32> {
+        self.graph.id(title)
+    }
+
+    fn by_folded_title(&self) -> &[u32] {
+        self.graph.by_folded_title().unwrap_or_else(|| {
+            self.derived_folded_order
+                .get_or_init(|| title_search::folded_order(self.node_count(), |id| self.title(id)))
+        })
+    }
+
+    fn title(&self, id: u32) -> &str {
+        self.graph.title(id)
+    }
+
+    fn kind(&self, id: u32) -> Option<Kind> {
+        Kind::from_byte(self.graph.kind_byte(id)).ok().flatten()
+    }
+
+    fn redirect(&self, id: u32) -> Option<u32> {
+        self.graph.redirect(id)
+    }
+
+    fn links(&self, id: u32) -> &[u32] {
+        self.graph.links(id)
+    }
+
+    fn backlinks(&self, id: u32) -> &[u32] {
+        self.graph.backlinks(id)
+    }
+}
@@ -0,0 +1,166 @@
+//! The on-disk format of graph files saved by `parse --graph`, as far as reading them in place
+//! needs it, so that other programs can use saved graphs (see `mmap::MmapGraph`).
+
+use anyhow::Context as _;
+use std::io::Read;
+
+pub mod mmap;
+
+/// The start of graph files.
+pub const MAGIC: &[u8; 8] = b"WIKIGRPH";
+/// Version 2 added the trailing checksum, version 3 the metadata, version 4 the optional
+/// statistics, version 5 the page kinds, version 6 the redirects, version 7 the compression byte,
+/// version 8 the layout that can be mapped into memory, version 9 made storing the backlinks
+/// optional, version 10 added the optional link weights, version 11 the sections compressed in
+/// blocks, version 12 the order of the folded titles, and version 13 the optional Wikidata items.
+/// Older files are migrated when loaded: they get empty metadata, no statistics, only ordinary
+/// pages, and no redirects, and version 1 files go unverified.
+pub const VERSION: u32 = 13;
+/// Alignment of the sections of offsets, in bytes.
+pub const ALIGN: u64 = 8;
+/// zstd level of compressed graph files, which favours saving quickly over saving a few more
+/// bytes, since loading takes as long either way.
+pub const ZSTD_LEVEL: i32 = 3;
+
+/// How the links, backlinks, link weights, and title bytes of a graph file are stored. Unlike
+/// compressing the whole file, compressing them in blocks keeps the file mappable, a block
+/// being decompressed when a query first reads from it.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum Codec {
+    /// Uncompressed, which is the quickest to map and read
+    #[default]
+    None,
+    /// LZ4, which decompresses about as fast as the disk reads
+    Lz4,
+    /// zstd, which makes the sections smaller than LZ4 but decompresses more slowly
+    Zstd,
+}
+
+impl Codec {
+    /// The compression byte of a file with its sections stored this way.
+    #[must_use]
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Lz4 => 2,
+            Self::Zstd => 3,
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Lz4 => "LZ4",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// # Errors
+    ///
+    /// If zstd fails to compress `raw`.
+    pub fn compress(self, raw: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(raw.to_vec()),
+            Self::Lz4 => Ok(lz4_flex::compress(raw)),
+            Self::Zstd => zstd::bulk::compress(raw, ZSTD_LEVEL),
+        }
+    }
+
+    /// The `len` bytes that `stored` is a block of.
+    ///
+    /// # Errors
+    ///
+    /// If `stored` isn't a block of `len` bytes.
+    pub fn decompress(self, stored: &[u8], len: usize) -> anyhow::Result<Vec<u8>> {
+        let raw = match self {
+            Self::None => stored.to_vec(),
+            Self::Lz4 => lz4_flex::decompress(stored, len).context("Corrupt LZ4 block")?,
+            Self::Zstd => zstd::bulk::decompress(stored, len).context("Corrupt zstd block")?,
+        };
+        anyhow::ensure!(raw.len() == len, "Block decompresses to the wrong length");
+        Ok(raw)
+    }
+}
+
+/// The count after the other four of version 9 and later, saying whether backlinks are stored.
+///
+/// # Errors
+///
+/// If `reader` fails or ends, or the count is neither 0 nor 1.
+pub fn read_backlinks_flag(reader: &mut impl Read) -> anyhow::Result<bool> {
+    match read_u64(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => anyhow::bail!("Invalid backlinks flag"),
+    }
+}
+
+/// The header of a graph file, as its bytes, its format version, whether the rest of the file
+/// is compressed, and how its largest sections are stored.
+///
+/// # Errors
+///
+/// If `file` fails, or isn't a graph file of a version this one reads.
+pub fn read_header(file: &mut impl Read) -> anyhow::Result<(Vec<u8>, u32, bool, Codec)> {
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)
+        .context("Not a wikigraph graph file")?;
+    anyhow::ensure!(&magic == MAGIC, "Not a wikigraph graph file");
+    let version = read_u32(file)?;
+    anyhow::ensure!(
+        (1..=VERSION).contains(&version),
+        "Unsupported graph format version {version}"
+    );
+    let mut header = Vec::from(magic);
+    header.extend(version.to_le_bytes());
+    if version < 7 {
+        return Ok((header, version, false, Codec::None));
+    }
+    let mut compression = [0];
+    file.read_exact(&mut compression)
+        .context("Unexpected end of file")?;
+    header.extend(compression);
+    let (compressed, codec) = match compression {
+        [0] => (false, Codec::None),
+        [1] => (true, Codec::None),
+        [2] if version >= 11 => (false, Codec::Lz4),
+        [3] if version >= 11 => (false, Codec::Zstd),
+        _ => anyhow::bail!("Unknown graph compression {}", compression[0]),
+    };
+    Ok((header, version, compressed, codec))
+}
+
+/// Whether `starts` and `positions` are the index of blocks of `count` values.
+#[must_use]
+pub fn valid_blocks(starts: &[u64], positions: &[u64], count: u64) -> bool {
+    let ascending = |values: &[u64]| values.windows(2).all(|pair| pair[0] <= pair[1]);
+    starts.len() == positions.len()
+        && starts.first() == Some(&0)
+        && positions.first() == Some(&0)
+        && starts.last() == Some(&count)
+        && ascending(starts)
+        && ascending(positions)
+}
+
+/// # Errors
+///
+/// If `reader` fails or ends first.
+pub fn read_u32(reader: &mut impl Read) -> anyhow::Result<u32> {
+    let mut bytes = [0; 4];
+    reader
+        .read_exact(&mut bytes)
+        .context("Unexpected end of file")?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// # Errors
+///
+/// If `reader` fails or ends first.
+pub fn read_u64(reader: &mut impl Read) -> anyhow::Result<u64> {
+    let mut bytes = [0; 8];
+    reader
+        .read_exact(&mut bytes)
+        .context("Unexpected end of file")?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,317 @@
+//! Graph files mapped into memory rather than loaded. Opening one only reads its header, and
+//! a query then touches only the pages of the file for the nodes it visits, so a single
+//! search on a very large graph starts at once and needs no more memory than the searching.
+//! Sections stored in compressed blocks are decompressed a block at a time as queries first
+//! read from them, and kept decompressed for later queries.
+
+use super::{read_backlinks_flag, read_header, valid_blocks, Codec, ALIGN};
+use anyhow::Context as _;
+use bytemuck::Pod;
+use memmap2::Mmap;
+use std::{fs::File, ops::Range, path::Path, sync::OnceLock};
+
+/// A graph file of version 8 or later, not compressed as a whole, with its backlinks stored,
+/// read in place. Unlike loading it with `wikigraph`, opening one verifies neither the checksum
+/// nor the structure, which would mean reading the whole file; `wikigraph fsck` does.
+pub struct MmapGraph {
+    map: Mmap,
+    node_count: usize,
+    /// Byte ranges of the sections of the file.
+    title_offsets: Range<usize>,
+    title_bytes: Stored<u8>,
+    offsets: Range<usize>,
+    targets: Stored<u32>,
+    back_offsets: Range<usize>,
+    sources: Stored<u32>,
+    by_title: Range<usize>,
+    /// Absent from files before version 12.
+    by_folded_title: Option<Range<usize>>,
+    redirects: Range<usize>,
+    kinds: Range<usize>,
+}
+
+/// A section of values, either as they are or in compressed blocks (see `write_stored`).
+enum Stored<T> {
+    Plain(Range<usize>),
+    Blocks {
+        codec: Codec,
+        /// Byte ranges of the index of the first value of each block, of the position of each
+        /// block in the data, and of the data.
+        starts: Range<usize>,
+        positions: Range<usize>,
+        data: Range<usize>,
+        decompressed: Vec<OnceLock<Vec<T>>>,
+    },
+}
+
+impl MmapGraph {
+    /// # Errors
+    ///
+    /// If the file can't be mapped, or isn't a graph file that can be (see `MmapGraph`).
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: The map is only read, and graph files are replaced by renaming a complete new
+        // file over them rather than written in place, so it doesn't change while mapped.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let mut header = &map[..];
+        let (header, version, compressed, codec) = read_header(&mut header)?;
+        anyhow::ensure!(
+            version >= 8,
+            "Graph format version {version} can't be mapped; save it again with `merge`, \
+             e.g. `wikigraph merge {} --output NEW`",
+            path.display()
+        );
+        anyhow::ensure!(
+            !compressed,
+            "Graph files compressed as a whole can't be mapped; save it again with \
+             `--graph-codec` instead, e.g. `wikigraph merge {} --graph-codec lz4 --output NEW`",
+            path.display()
+        );
+        anyhow::ensure!(
+            cfg!(target_endian = "little"),
+            "Graph files can only be mapped on little-endian machines"
+        );
+
+        let mut position = header.len();
+        let metadata_len = u32::from_le_bytes(bytes(&map, position)?);
+        position += 4 + metadata_len as usize;
+        let mut counts = [0; 4];
+        for count in &mut counts {
+            *count = usize::try_from(u64::from_le_bytes(bytes(&map, position)?))?;
+            position += 8;
+        }
+        let [node_count, edge_count, title_len, redirect_count] = counts;
+        if version >= 9 {
+            let flag = map
+                .get(position..position + 8)
+                .context("Unexpected end of file")?;
+            anyhow::ensure!(
+                read_backlinks_flag(&mut &flag[..])?,
+                "{} has no backlinks stored to map; save it again with `merge`, e.g. \
+                 `wikigraph merge {} --output NEW`",
+                path.display(),
+                path.display()
+            );
+            position += 8;
+        }
+
+        let offsets_len = node_count.checked_add(1).and_then(|n| n.checked_mul(8));
+        let position = &mut position;
+        let graph = Self {
+            node_count,
+            title_offsets: section(&map, position, offsets_len, ALIGN)?,
+            title_bytes: stored(&map, position, title_len, codec)?,
+            offsets: section(&map, position, offsets_len, ALIGN)?,
+            targets: stored(&map, position, edge_count, codec)?,
+            back_offsets: section(&map, position, offsets_len, ALIGN)?,
+            sources: stored(&map, position, edge_count, codec)?,
+            by_title: section(&map, position, node_count.checked_mul(4), 4)?,
+            by_folded_title: if version >= 12 {
+                Some(section(&map, position, node_count.checked_mul(4), 4)?)
+            } else {
+                None
+            },
+            redirects: section(&map, position, redirect_count.checked_mul(8), 4)?,
+            kinds: section(&map, position, Some(node_count), 1)?,
+            map,
+        };
+        anyhow::ensure!(
+            graph.u64s(&graph.offsets).last() == Some(&(edge_count as u64)),
+            "Corrupt graph file: last offset is not the link count {edge_count}"
+        );
+        Ok(graph)
+    }
+
+    fn u64s(&self, range: &Range<usize>) -> &[u64] {
+        // Sections of offsets are aligned to 8 bytes, and maps to pages.
+        bytemuck::cast_slice(&self.map[range.clone()])
+    }
+
+    fn u32s(&self, range: &Range<usize>) -> &[u32] {
+        bytemuck::cast_slice(&self.map[range.clone()])
+    }
+
+    /// The slice of `values` between the offsets of `id` and the next node.
+    fn list<'a, T: Pod>(
+        &'a self,
+        offsets: &Range<usize>,
+        values: &'a Stored<T>,
+        id: u32,
+    ) -> &'a [T] {
+        let offsets = self.u64s(offsets);
+        let start = usize::try_from(offsets[id as usize]).unwrap();
+        let end = usize::try_from(offsets[id as usize + 1]).unwrap();
+        self.values(values, start..end)
+    }
+
+    /// The values at `range` of a section, decompressing the block they are in if need be.
+    fn values<'a, T: Pod>(&'a self, stored: &'a Stored<T>, range: Range<usize>) -> &'a [T] {
+        let (codec, starts, positions, data, decompressed) = match stored {
+            Stored::Plain(section) => {
+                return &bytemuck::cast_slice(&self.map[section.clone()])[range];
+            }
+            Stored::Blocks {
+                codec,
+                starts,
+                positions,
+                data,
+                decompressed,
+            } => (codec, starts, positions, data, decompressed),
+        };
+        if range.is_empty() {
+            return &[];
+        }
+        let starts = self.u64s(starts);
+        let block = starts.partition_point(|&start| start <= range.start as u64) - 1;
+        let values = decompressed[block].get_or_init(|| {
+            let positions = self.u64s(positions);
+            let stored = usize::try_from(positions[block]).unwrap()
+                ..usize::try_from(positions[block + 1]).unwrap();
+            let len = usize::try_from(starts[block + 1] - starts[block]).unwrap();
+            let raw = codec
+                .decompress(&self.map[data.clone()][stored], len * size_of::<T>())
+                .expect("Corrupt graph file");
+            let mut values = vec![T::zeroed(); len];
+            bytemuck::cast_slice_mut(&mut values).copy_from_slice(&raw);
+            values
+        });
+        let first = usize::try_from(starts[block]).unwrap();
+        values
+            .get(range.start - first..range.end - first)
+            .expect("Corrupt graph file: a list spans blocks")
+    }
+}
+
+impl MmapGraph {
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// The node titled exactly `title`.
+    #[must_use]
+    pub fn id(&self, title: &str) -> Option<u32> {
+        let by_title = self.u32s(&self.by_title);
+        by_title
+            .binary_search_by(|&node| self.title(node).cmp(title))
+            .ok()
+            .map(|index| by_title[index])
+    }
+
+    /// Node IDs in order of their titles without accents, in lower case, and with spaces for
+    /// underscores, as stored by version 12 and later.
+    #[must_use]
+    pub fn by_folded_title(&self) -> Option<&[u32]> {
+        self.by_folded_title
+            .as_ref()
+            .map(|section| self.u32s(section))
+    }
+
+    /// # Panics
+    ///
+    /// If the title isn't valid UTF-8, which only a corrupt file has.
+    #[must_use]
+    pub fn title(&self, id: u32) -> &str {
+        let offsets = self.u64s(&self.title_offsets);
+        let start = usize::try_from(offsets[id as usize]).unwrap();
+        let end = usize::try_from(offsets[id as usize + 1]).unwrap();
+        std::str::from_utf8(self.values(&self.title_bytes, start..end))
+            .expect("Title is not valid UTF-8")
+    }
+
+    /// Whether the node is an ordinary page (0), a portal (1), a navigation-heavy page like a
+    /// list (2), or a disambiguation page (3).
+    #[must_use]
+    pub fn kind_byte(&self, id: u32) -> u8 {
+        self.map[self.kinds.start + id as usize]
+    }
+
+    /// The page `id` redirects to, if it is a redirect.
+    #[must_use]
+    pub fn redirect(&self, id: u32) -> Option<u32> {
+        let pairs: &[[u32; 2]] = bytemuck::cast_slice(&self.map[self.redirects.clone()]);
+        pairs
+            .binary_search_by_key(&id, |&[page, _]| page)
+            .ok()
+            .map(|index| pairs[index][1])
+    }
+
+    /// The nodes `id` links to, sorted.
+    #[must_use]
+    pub fn links(&self, id: u32) -> &[u32] {
+        self.list(&self.offsets, &self.targets, id)
+    }
+
+    /// The nodes linking to `id`, sorted.
+    #[must_use]
+    pub fn backlinks(&self, id: u32) -> &[u32] {
+        self.list(&self.back_offsets, &self.sources, id)
+    }
+}
+
+/// The byte range of the section of `len` bytes at `position`, aligned to `align`, moving
+/// `position` past it.
+fn section(
+    map: &[u8],
+    position: &mut usize,
+    len: Option<usize>,
+    align: u64,
+) -> anyhow::Result<Range<usize>> {
+    let start = position.next_multiple_of(usize::try_from(align).unwrap());
+    let end = len
+        .and_then(|len| start.checked_add(len))
+        .filter(|&end| end <= map.len())
+        .context("Unexpected end of file")?;
+    *position = end;
+    Ok(start..end)
+}
+
+/// The section of `count` values of `T` at `position`, stored with `codec`, moving
+/// `position` past it.
+fn stored<T>(
+    map: &[u8],
+    position: &mut usize,
+    count: usize,
+    codec: Codec,
+) -> anyhow::Result<Stored<T>> {
+    if codec == Codec::None {
+        let len = count.checked_mul(size_of::<T>());
+        return Ok(Stored::Plain(section(
+            map,
+            position,
+            len,
+            size_of::<T>() as u64,
+        )?));
+    }
+    let blocks = usize::try_from(u64::from_le_bytes(bytes(
+        map,
+        position.next_multiple_of(8),
+    )?))?;
+    section(map, position, Some(8), ALIGN)?;
+    let index_len = blocks.checked_add(1).and_then(|n| n.checked_mul(8));
+    let starts = section(map, position, index_len, ALIGN)?;
+    let positions = section(map, position, index_len, ALIGN)?;
+    let u64s = |range: &Range<usize>| -> &[u64] { bytemuck::cast_slice(&map[range.clone()]) };
+    anyhow::ensure!(
+        valid_blocks(u64s(&starts), u64s(&positions), count as u64),
+        "Corrupt graph file: invalid block index"
+    );
+    let data_len = usize::try_from(*u64s(&positions).last().unwrap())?;
+    let data = section(map, position, Some(data_len), 1)?;
+    *position = position.next_multiple_of(8);
+    Ok(Stored::Blocks {
+        codec,
+        starts,
+        positions,
+        data,
+        decompressed: (0..blocks).map(|_| OnceLock::new()).collect(),
+    })
+}
+
+/// The `N` bytes of `map` at `position`.
+fn bytes<const N: usize>(map: &[u8], position: usize) -> anyhow::Result<[u8; N]> {
+    map.get(position..position + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .context("Unexpected end of file")
+}
@@ -0,0 +1,34 @@
+//! Limits on single pages. A few pages hold tens of megabytes of wikitext or thousands of links,
+//! and without limits they take a large share of a parse's memory and time.
+
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashSet};
+
+/// What to do with a page over `--max-page-bytes`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Oversized {
+    /// Leave the page out of the graph
+    Skip,
+    /// Keep only the page's first bytes, up to the limit
+    Truncate,
+}
+
+/// Cut `text` down to at most `max` bytes, at a character boundary.
+pub fn truncate(text: &mut String, max: usize) {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+}
+
+/// Keep the first `max` distinct targets of `targets`, in order, returning how many distinct
+/// targets there were.
+pub fn cap_links(targets: &mut Vec<Cow<str>>, max: usize) -> usize {
+    let mut seen = HashSet::new();
+    targets.retain(|target| seen.insert(target.clone()));
+    let distinct = targets.len();
+    targets.truncate(max);
+    distinct
+}
@@ -0,0 +1,171 @@
+//! Estimated neighbourhood sizes by HyperBall: how many pages are within 1, 2, … clicks of
+//! every page, with one HyperLogLog counter per page. Exact counts would take a breadth-first
+//! search from every page, but a counter of a page's neighbourhood one click further is simply
+//! the union of its neighbours' counters, so each click is one pass over the links. Small graphs
+//! (see `small`) are quick to search from every page, though, so theirs are counted exactly.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::{Adjacency as _, Direction, Graph},
+};
+use serde::{Deserialize, Serialize};
+
+/// Default HyperLogLog precision.
+pub const PRECISION: u8 = 8;
+
+/// What the estimates are of: pages within `hops` links in `direction`, from counters of
+/// `2^precision` registers, which are off by about `104 / sqrt(2^precision)` percent, or
+/// counted by breadth-first searches instead if `exact`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Params {
+    pub hops: u32,
+    pub precision: u8,
+    pub direction: Direction,
+    #[serde(default)]
+    pub exact: bool,
+}
+
+pub struct Neighbourhoods {
+    pub params: Params,
+    /// `sizes[node * hops + (t - 1)]` is about how many other pages are within `t` links of
+    /// `node`.
+    pub sizes: Vec<f32>,
+}
+
+impl Neighbourhoods {
+    /// Estimate the neighbourhoods of every node of `graph`. Checks `cancel` before each hop.
+    pub fn compute(graph: &Graph, params: Params, cancel: &Cancel) -> Result<Self, Cancelled> {
+        if params.exact {
+            return Self::count(graph, params, cancel);
+        }
+        let n = graph.node_count();
+        let hops = params.hops as usize;
+        let registers = 1 << params.precision;
+        let nodes = 0..u32::try_from(n).unwrap();
+
+        let mut counters = vec![0_u8; n * registers];
+        for node in nodes.clone() {
+            let (register, rank) = hash(node, params.precision);
+            counters[node as usize * registers + register] = rank;
+        }
+        let mut next = counters.clone();
+        let mut sizes = vec![0.0; n * hops];
+        for hop in 0..hops {
+            cancel.check()?;
+            for node in nodes.clone() {
+                let counter = &mut next[node as usize * registers..][..registers];
+                for neighbour in graph.neighbours(node, params.direction) {
+                    let other = &counters[neighbour as usize * registers..][..registers];
+                    for (register, &rank) in counter.iter_mut().zip(other) {
+                        *register = (*register).max(rank);
+                    }
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                let size = (estimate(counter) - 1.0).max(0.0) as f32;
+                sizes[node as usize * hops + hop] = size;
+            }
+            counters.copy_from_slice(&next);
+        }
+        Ok(Self { params, sizes })
+    }
+
+    /// Count the neighbourhoods of every node of `graph` by a breadth-first search from each
+    /// one, as far as `params.hops`. Checks `cancel` before each search.
+    #[allow(clippy::cast_precision_loss)]
+    fn count(graph: &Graph, params: Params, cancel: &Cancel) -> Result<Self, Cancelled> {
+        let n = graph.node_count();
+        let hops = params.hops as usize;
+        let mut sizes = vec![0.0; n * hops];
+        let mut distances = vec![u32::MAX; n];
+        let mut reached = Vec::new();
+        for source in 0..u32::try_from(n).unwrap() {
+            cancel.check()?;
+            let mut within = vec![0_u64; hops + 1];
+            distances[source as usize] = 0;
+            reached.push(source);
+            let mut next = 0;
+            while let Some(&node) = reached.get(next) {
+                next += 1;
+                let distance = distances[node as usize] + 1;
+                if distance > params.hops {
+                    continue;
+                }
+                for neighbour in graph.neighbours(node, params.direction) {
+                    if distances[neighbour as usize] == u32::MAX {
+                        distances[neighbour as usize] = distance;
+                        within[distance as usize] += 1;
+                        reached.push(neighbour);
+                    }
+                }
+            }
+            for &node in &reached {
+                distances[node as usize] = u32::MAX;
+            }
+            reached.clear();
+            let mut total = 0;
+            for hop in 0..hops {
+                total += within[hop + 1];
+                sizes[source as usize * hops + hop] = total as f32;
+            }
+        }
+        Ok(Self { params, sizes })
+    }
+
+    /// About how many other pages are within `hops` links of `node`, if that is at most as
+    /// far as estimated.
+    pub fn size(&self, node: u32, hops: u32) -> Option<f64> {
+        let estimated = self.params.hops as usize;
+        let hops = hops as usize;
+        if hops == 0 {
+            return Some(0.0);
+        }
+        (hops <= estimated).then(|| f64::from(self.sizes[node as usize * estimated + hops - 1]))
+    }
+
+    /// Harmonic centrality counting pages up to the hops estimated: the sum over other pages of
+    /// one over their distance, which is high for pages few links away from many others.
+    pub fn harmonic(&self, node: u32) -> f64 {
+        let mut previous = 0.0;
+        (1..=self.params.hops)
+            .map(|hops| {
+                let size = self.size(node, hops).unwrap();
+                let new = (size - previous).max(0.0);
+                previous = previous.max(size);
+                new / f64::from(hops)
+            })
+            .sum()
+    }
+}
+
+/// The register of `node`'s counter that counts it, and the rank it sets there.
+fn hash(node: u32, precision: u8) -> (usize, u8) {
+    // SplitMix64, so that consecutive IDs are spread over registers.
+    let mut hash = u64::from(node).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+    let register = usize::try_from(hash >> (64 - precision)).unwrap();
+    let rest = hash << precision;
+    let rank = rest.leading_zeros().min(64 - u32::from(precision)) + 1;
+    (register, u8::try_from(rank).unwrap())
+}
+
+/// The cardinality estimated by a HyperLogLog counter, with the small-range correction.
+#[allow(clippy::cast_precision_loss, clippy::naive_bytecount)]
+fn estimate(counter: &[u8]) -> f64 {
+    let m = counter.len() as f64;
+    let alpha = match counter.len() {
+        16 => 0.673,
+        32 => 0.697,
+        64 => 0.709,
+        _ => 0.7213 / (1.0 + 1.079 / m),
+    };
+    let sum: f64 = counter.iter().map(|&rank| (-f64::from(rank)).exp2()).sum();
+    let raw = alpha * m * m / sum;
+    let zeros = counter.iter().filter(|&&rank| rank == 0).count();
+    if raw <= 2.5 * m && zeros > 0 {
+        m * (m / zeros as f64).ln()
+    } else {
+        raw
+    }
+}
@@ -0,0 +1,169 @@
+//! Saved graphs built from an existing edge list instead of a dump, so that link datasets made
+//! elsewhere can be queried, served, and exported like a parse. Only the links are known (and,
+//! with `--nodes`, the redirects), so the graph has no text index and no categories.
+
+use crate::{
+    export::edge_list::Format,
+    graph::{Codec, Graph, Metadata},
+    Wiki,
+};
+use anyhow::Context as _;
+use lasso::{Rodeo, Spur};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Edge list of one `source, target` link per line without a header, such as `parse
+    /// --output` writes, or `-` for standard input. Further fields are ignored, and titles are
+    /// taken as they are, without `parse --normalize`.
+    edges: PathBuf,
+
+    /// Where to save the graph
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// Field separator of the edge list
+    #[arg(long, value_enum, default_value_t = Format::Tsv)]
+    format: Format,
+
+    /// CSV of the pages, with a header naming an `id` and a `title` (or `label`) column and
+    /// optionally a `redirect` column, such as `inventory` writes. The edge list then gives
+    /// pages by ID, and pages without links are kept. Of rows with the same ID, the last wins.
+    #[arg(long, value_name = "FILE")]
+    nodes: Option<PathBuf>,
+
+    /// zstd-compress the graph file, which makes it a few times smaller
+    #[arg(long)]
+    compress_graph: bool,
+
+    /// Store the titles, links, and backlinks of the graph file in compressed blocks, as with
+    /// `parse --graph-codec`
+    #[arg(
+        long,
+        value_name = "CODEC",
+        value_enum,
+        default_value_t,
+        conflicts_with = "compress_graph"
+    )]
+    graph_codec: Codec,
+
+    /// Leave the backlinks out of the graph file, as with `parse --no-backlinks`
+    #[arg(long)]
+    no_backlinks: bool,
+}
+
+pub fn run(args: &Args) {
+    let mut rodeo = Rodeo::new();
+    let mut wiki = Wiki::default();
+    let ids = args.nodes.as_deref().map(|path| {
+        read_nodes(path, &mut rodeo, &mut wiki)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .unwrap()
+    });
+    read_edges(args, ids.as_ref(), &mut rodeo, &mut wiki)
+        .context("Failed to read edge list")
+        .unwrap();
+
+    let mut graph = Graph::new(&rodeo, &wiki, Metadata::current(None))
+        .context("Failed to build graph")
+        .unwrap();
+    graph.set_compressed(args.compress_graph);
+    graph.set_codec(args.graph_codec);
+    graph.set_store_backlinks(!args.no_backlinks);
+    graph
+        .save(&args.output)
+        .context("Failed to save graph")
+        .unwrap();
+
+    println!(
+        "{} nodes and {} links",
+        graph.node_count(),
+        graph.edge_count()
+    );
+}
+
+/// Intern the pages listed at `path` in order, recording their redirects, and return the node
+/// of each ID.
+fn read_nodes(
+    path: &Path,
+    rodeo: &mut Rodeo,
+    wiki: &mut Wiki,
+) -> anyhow::Result<HashMap<String, Spur>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name))
+    };
+    let id = column("id").context("No `id` column")?;
+    let title = column("title")
+        .or_else(|| column("label"))
+        .context("No `title` column")?;
+    let redirect = column("redirect");
+
+    let mut ids = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |index| record.get(index).unwrap_or_default();
+        let line = record.position().map_or(0, csv::Position::line);
+        anyhow::ensure!(!field(title).is_empty(), "Empty title on line {line}");
+        let page = rodeo.get_or_intern(field(title));
+        ids.insert(String::from(field(id)), page);
+        match redirect.map(field).filter(|target| !target.is_empty()) {
+            Some(target) => {
+                let target = rodeo.get_or_intern(target);
+                wiki.redirects.insert(page, target);
+            }
+            None => {
+                wiki.redirects.remove(&page);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Add the links of the edge list to `wiki`, looking pages up in `ids` if given, or else
+/// interning them as titles.
+fn read_edges(
+    args: &Args,
+    ids: Option<&HashMap<String, Spur>>,
+    rodeo: &mut Rodeo,
+    wiki: &mut Wiki,
+) -> anyhow::Result<()> {
+    let input: Box<dyn Read> = if args.edges == Path::new("-") {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(&args.edges)?)
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(args.format.delimiter())
+        .has_headers(false)
+        .flexible(true)
+        // Titles can't contain tabs or newlines, so TSV has no quoting.
+        .quoting(matches!(args.format, Format::Csv))
+        .from_reader(input);
+
+    let mut record = csv::StringRecord::new();
+    while reader.read_record(&mut record)? {
+        let line = record.position().map_or(0, csv::Position::line);
+        let (Some(source), Some(target)) = (record.get(0), record.get(1)) else {
+            anyhow::bail!("Fewer than two fields on line {line}");
+        };
+        let mut node = |field: &str| match ids {
+            Some(ids) => ids
+                .get(field)
+                .copied()
+                .with_context(|| format!("Unknown page ID {field} on line {line}")),
+            None => Ok(rodeo.get_or_intern(field)),
+        };
+        let (source, target) = (node(source)?, node(target)?);
+        wiki.links.entry(source).or_default().insert(target);
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! The page inventory of a multistream dump from its index alone: every page's ID and title, and
+//! how many pages each bzip2 stream holds. The index is a small fraction of the dump, so this
+//! takes seconds where even `inventory` reads the whole dump.
+
+use crate::dump::{self, IndexEntry};
+use anyhow::Context as _;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Multistream dump index, such as `enwiki-20240601-pages-articles-multistream-index.txt.bz2`,
+    /// or the multistream dump beside it
+    input: PathBuf,
+
+    /// Write the CSV to this file instead of standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Also write every stream's offset in the dump and number of pages as CSV to this file
+    #[arg(long, value_name = "FILE")]
+    streams: Option<PathBuf>,
+}
+
+/// Write `id,title,stream` rows as CSV, one per page, where `stream` is the offset of the
+/// page's bzip2 stream in the dump.
+pub fn run(args: &Args) {
+    let index = if args
+        .input
+        .to_string_lossy()
+        .ends_with("multistream.xml.bz2")
+    {
+        dump::index_path(&args.input)
+            .with_context(|| format!("No index beside {}", args.input.display()))
+            .unwrap()
+    } else {
+        args.input.clone()
+    };
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .context("Failed to create output file")
+                .unwrap(),
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let streams = write(&index, output)
+        .context("Failed to write page inventory")
+        .unwrap();
+    let pages: usize = streams.iter().map(|&(_, pages)| pages).sum();
+    tracing::info!("Listed {pages} pages in {} streams", streams.len());
+
+    if let Some(path) = &args.streams {
+        write_streams(path, &streams)
+            .with_context(|| format!("Failed to write {}", path.display()))
+            .unwrap();
+    }
+}
+
+/// Write the pages of `index` to `output`, returning each stream's offset and page count.
+fn write(index: &Path, output: Box<dyn Write>) -> anyhow::Result<Vec<(u64, usize)>> {
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(["id", "title", "stream"])?;
+    let mut streams: Vec<(u64, usize)> = Vec::new();
+    for entry in dump::index_entries(index)? {
+        let IndexEntry { offset, id, title } = entry?;
+        match streams.last_mut() {
+            Some((last, pages)) if *last == offset => *pages += 1,
+            _ => streams.push((offset, 1)),
+        }
+        writer.write_record([id.to_string(), title, offset.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(streams)
+}
+
+fn write_streams(path: &Path, streams: &[(u64, usize)]) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["offset", "pages"])?;
+    for &(offset, pages) in streams {
+        writer.write_record([offset.to_string(), pages.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! The page inventory of a dump: every page's ID, title, namespace, redirect target, and
+//! revision timestamp, read while skipping over the wikitext, which is most of a dump's bytes.
+
+use crate::dump::{read_xml, Pages};
+use anyhow::Context as _;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Dump file, or `-` for standard input, compressed or not
+    input: PathBuf,
+
+    /// Write the CSV to this file instead of standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Only list pages of this namespace number (repeatable) [default: all namespaces]
+    #[arg(long = "namespace", value_name = "NS")]
+    namespaces: Vec<i64>,
+}
+
+/// Write `id,title,namespace,redirect,timestamp` rows as CSV, one per page, or per revision of
+/// history dumps. Only redirects with a `<redirect>` element are listed, since the text that
+/// could say otherwise isn't read.
+pub fn run(args: &Args) {
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .context("Failed to create output file")
+                .unwrap(),
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let count = write(args, output)
+        .context("Failed to write page inventory")
+        .unwrap();
+    tracing::info!("Listed {count} pages");
+}
+
+fn write(args: &Args, output: Box<dyn Write>) -> anyhow::Result<usize> {
+    let xml = read_xml(&args.input, None).context("Failed to read XML file")?;
+    let mut pages = Pages::new(xml).without_text();
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(["id", "title", "namespace", "redirect", "timestamp"])?;
+    let mut count = 0;
+    while let Some(page) = pages.next_page()? {
+        if !args.namespaces.is_empty() && !args.namespaces.contains(&page.namespace) {
+            continue;
+        }
+        writer.write_record([
+            page.id.map(|id| id.to_string()).unwrap_or_default(),
+            page.title,
+            page.namespace.to_string(),
+            page.redirect.unwrap_or_default(),
+            page.timestamp.unwrap_or_default(),
+        ])?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
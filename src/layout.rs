@@ -0,0 +1,108 @@
+//! A ForceAtlas2-style force-directed layout, so small exports can carry node positions and
+//! render immediately in Gephi or sigma.js.
+//!
+//! This is the plain O(n²) variant without Barnes-Hut approximation, which is fine for the
+//! graph sizes anybody would want to look at.
+
+const REPULSION: f64 = 2.0;
+const GRAVITY: f64 = 1.0;
+const JITTER_TOLERANCE: f64 = 1.0;
+const MAX_SPEED_RATIO: f64 = 10.0;
+
+/// Compute `(x, y)` coordinates for nodes `0..node_count` connected by `edges`.
+///
+/// Positions start on a deterministic spiral, so the same graph always produces the same layout.
+#[allow(clippy::cast_precision_loss)]
+pub fn force_atlas2(
+    node_count: usize,
+    edges: &[(usize, usize)],
+    iterations: usize,
+) -> Vec<(f64, f64)> {
+    let golden_angle = std::f64::consts::PI * (3.0 - 5.0_f64.sqrt());
+    let mut positions: Vec<(f64, f64)> = (0..node_count)
+        .map(|i| {
+            let radius = 10.0 * (i as f64).sqrt();
+            let angle = i as f64 * golden_angle;
+            (radius * angle.cos(), radius * angle.sin())
+        })
+        .collect();
+
+    // ForceAtlas2 uses "degree + 1" as node mass, ignoring edge direction.
+    let mut masses = vec![1.0; node_count];
+    for &(source, target) in edges {
+        masses[source] += 1.0;
+        masses[target] += 1.0;
+    }
+
+    let mut previous_forces = vec![(0.0, 0.0); node_count];
+    let mut speed = 1.0;
+
+    for _ in 0..iterations {
+        let mut forces = vec![(0.0, 0.0); node_count];
+
+        for i in 0..node_count {
+            for j in (i + 1)..node_count {
+                let dx = positions[i].0 - positions[j].0;
+                let dy = positions[i].1 - positions[j].1;
+                let distance_squared = dx * dx + dy * dy;
+                if distance_squared > 0.0 {
+                    let factor = REPULSION * masses[i] * masses[j] / distance_squared;
+                    forces[i].0 += dx * factor;
+                    forces[i].1 += dy * factor;
+                    forces[j].0 -= dx * factor;
+                    forces[j].1 -= dy * factor;
+                }
+            }
+
+            let (x, y) = positions[i];
+            let distance = x.hypot(y);
+            if distance > 0.0 {
+                let factor = GRAVITY * masses[i] / distance;
+                forces[i].0 -= x * factor;
+                forces[i].1 -= y * factor;
+            }
+        }
+
+        for &(source, target) in edges {
+            let dx = positions[source].0 - positions[target].0;
+            let dy = positions[source].1 - positions[target].1;
+            forces[source].0 -= dx;
+            forces[source].1 -= dy;
+            forces[target].0 += dx;
+            forces[target].1 += dy;
+        }
+
+        // Adaptive speed: nodes whose force keeps flipping direction ("swinging") slow down,
+        // while the global speed follows how much useful movement ("traction") there is.
+        let mut swings = vec![0.0; node_count];
+        let mut global_swing = 0.0;
+        let mut global_traction = 0.0;
+        for i in 0..node_count {
+            let (fx, fy) = forces[i];
+            let (px, py) = previous_forces[i];
+            swings[i] = (fx - px).hypot(fy - py);
+            global_swing += masses[i] * swings[i];
+            global_traction += masses[i] * (fx + px).hypot(fy + py) / 2.0;
+        }
+        if global_swing > 0.0 {
+            let target_speed = JITTER_TOLERANCE * global_traction / global_swing;
+            speed += (target_speed - speed).min(speed * 0.5);
+        }
+
+        for i in 0..node_count {
+            let (fx, fy) = forces[i];
+            let force = fx.hypot(fy);
+            if force == 0.0 {
+                continue;
+            }
+            let node_speed =
+                (speed / (1.0 + speed * swings[i].sqrt())).min(MAX_SPEED_RATIO / force);
+            positions[i].0 += fx * node_speed;
+            positions[i].1 += fy * node_speed;
+        }
+
+        previous_forces = forces;
+    }
+
+    positions
+}
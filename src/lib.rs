@@ -0,0 +1,22 @@
+//! Reading MediaWiki XML dumps, and the graph files the command-line tool saves, the parts of
+//! wikigraph that other programs can reuse without running it:
+//!
+//! ```no_run
+//! use wikigraph::DumpReader;
+//!
+//! for page in DumpReader::open("enwiki-latest-pages-articles.xml.bz2".as_ref())? {
+//!     let page = page?;
+//!     println!("{} ({} bytes)", page.title, page.text.len());
+//! }
+//! # anyhow::Ok(())
+//! ```
+
+pub mod dump;
+pub mod graph_file;
+#[cfg(feature = "petgraph")]
+pub mod petgraph;
+pub mod remote;
+pub mod shard;
+pub mod wikilink;
+
+pub use dump::{DumpReader, Page};
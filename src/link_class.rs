@@ -0,0 +1,101 @@
+//! What a `[[...]]` link points at, told from its prefix. Only links to articles are links
+//! between pages of the graph by default; the rest embed files, put the page in a category,
+//! link another namespace, or leave the wiki altogether.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkClass {
+    /// Links to pages of the main namespace, like `[[Cat]]` or `[[Star Wars: Episode IV]]`
+    Article,
+    /// Links to pages of other namespaces of the same wiki, like `[[Help:Contents]]`,
+    /// `[[:Category:Birds]]`, or `[[:File:Foo.jpg]]`
+    Namespace,
+    /// Embedded files, like `[[File:Foo.jpg|thumb]]`
+    File,
+    /// Category memberships, like `[[Category:Birds]]`
+    Category,
+    /// Links to other wikis, like `[[wikt:cat]]` or `[[:de:Katze]]`
+    Interwiki,
+    /// Links to the same page in another language, like `[[fr:Chat]]`, shown in the sidebar
+    Language,
+}
+
+impl LinkClass {
+    /// Whether links of this class point outside the wiki.
+    pub fn leaves_wiki(self) -> bool {
+        matches!(self, Self::Interwiki | Self::Language)
+    }
+}
+
+/// Interwiki prefixes of the Wikimedia projects, besides language codes.
+const INTERWIKI_PREFIXES: &[&str] = &[
+    "w",
+    "wikipedia",
+    "wikt",
+    "wiktionary",
+    "b",
+    "wikibooks",
+    "q",
+    "wikiquote",
+    "s",
+    "wikisource",
+    "n",
+    "wikinews",
+    "v",
+    "wikiversity",
+    "voy",
+    "wikivoyage",
+    "species",
+    "wikispecies",
+    "c",
+    "commons",
+    "m",
+    "meta",
+    "mw",
+    "mediawikiwiki",
+    "d",
+    "wikidata",
+    "f",
+    "wikifunctions",
+    "foundation",
+    "wmf",
+    "incubator",
+    "outreach",
+    "phab",
+    "phabricator",
+    "wikitech",
+];
+
+/// Language codes of the Wikipedias, which as prefixes make language links.
+const LANGUAGES: &str = "\
+    aa ab ace ady af ak als alt am ami an ang anp ar arc ary arz as ast atj av avk awa ay az \
+    azb ba ban bar bat-smg bcl bdr be be-tarask be-x-old bew bg bh bi bjn blk bm bn bo bpy br \
+    bs btm bug bxr ca cbk-zam cdo ce ceb ch cho chr chy ckb co cr crh cs csb cu cv cy da dag \
+    de dga din diq dsb dtp dty dv dz ee el eml en eo es et eu ext fa fat ff fi fiu-vro fj fo \
+    fon fr frp frr fur fy ga gag gan gcr gd gl glk gn gom gor got gpe gsw gu guc gur guw gv ha \
+    hak haw he hi hif ho hr hsb ht hu hy hyw hz ia iba id ie ig igl ii ik ilo inh io is it iu \
+    ja jam jbo jv ka kaa kab kbd kbp kcg kg kge ki kj kk kl km kn knc ko koi kr krc ks ksh ku \
+    kus kv kw ky la lad lb lbe lez lfn lg li lij lld lmo ln lo lrc lt ltg lv mad mai map-bms \
+    mdf mg mh mhr mi min mk ml mn mni mnw mos mr mrj ms mt mus mwl my myv mzn na nah nap nb \
+    nds nds-nl ne new ng nia nl nn no nov nqo nr nrm nso nup nv ny oc olo om or os pa pag pam \
+    pap pcd pcm pdc pfl pi pih pl pms pnb pnt ps pt pwn qu rki rm rmy rn ro roa-rup roa-tara \
+    rsk ru rue rup rw sa sah sat sc scn sco sd se sg sgs sh shi shn shy si simple sk skr sl sm \
+    smn sn so sq sr srn ss st stq su sv sw syl szl szy ta tay tcy tdd te tet tg th ti tig tk \
+    tl tly tn to tpi tr trv ts tt tum tw ty tyv udm ug uk ur uz ve vec vep vi vls vo vro wa \
+    war wo wuu xal xh xmf yi yo yue za zea zgh zh zh-classical zh-min-nan zh-yue zu";
+
+/// Whether `prefix` is an interwiki prefix of a Wikimedia project.
+pub fn is_interwiki(prefix: &str) -> bool {
+    INTERWIKI_PREFIXES
+        .iter()
+        .any(|interwiki| interwiki.eq_ignore_ascii_case(prefix))
+}
+
+/// Whether `prefix` is the language code of a Wikipedia.
+pub fn is_language(prefix: &str) -> bool {
+    LANGUAGES
+        .split_whitespace()
+        .any(|language| language.eq_ignore_ascii_case(prefix))
+}
@@ -1,16 +1,107 @@
 use anyhow::Context as _;
 use clap::Parser as _;
-use lasso::{Rodeo, Spur};
-use once_cell::sync::Lazy;
-use quick_xml::events::Event;
-use regex::Regex;
+use dump::{read_xml_counted, Page, Pages};
+use lasso::{Key as _, Rodeo, Spur};
+use progress::Progress;
 use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::BufReader,
-    path::PathBuf,
+    borrow::Cow,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    env, fs, io,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     thread,
+    time::{Duration, Instant},
 };
+use wikigraph::{dump, remote, shard, wikilink};
+use wikilink::links;
+
+mod algebra;
+mod anchors;
+mod audit;
+mod backlinks;
+mod bvgraph;
+mod cache;
+mod cancel;
+mod categories;
+mod centrality;
+mod check;
+mod checkpoint;
+mod checksum;
+mod config;
+mod context;
+mod cooccurrence;
+mod diff;
+mod edge_type;
+mod export;
+mod extract;
+mod fetch;
+mod filter;
+mod fsck;
+mod graph;
+mod guard;
+mod hyperball;
+mod import;
+mod index;
+mod inventory;
+mod layout;
+mod link_class;
+mod merge;
+mod metrics;
+mod navigation;
+mod normalize;
+mod origin;
+mod page_json;
+mod page_profile;
+mod page_stream;
+mod pagerank;
+mod path;
+mod path_eval;
+mod plaintext;
+mod plan;
+mod poster;
+mod profile;
+mod progress;
+mod provenance;
+mod prune;
+mod query;
+mod red_link;
+mod redirect_report;
+mod related;
+mod repl;
+mod report;
+mod rules;
+mod run_report;
+mod sample;
+mod script;
+mod serve;
+mod sink;
+mod siteinfo;
+mod skip_list;
+mod small;
+mod snapshot;
+mod sort;
+mod spill;
+mod sql_dump;
+mod stats;
+mod stream;
+mod subgraph;
+mod template;
+mod template_usage;
+mod text_index;
+mod title_list;
+mod title_search;
+mod top;
+mod update;
+mod variant;
+mod walks;
+mod weights;
+mod wikidata;
+mod workspace;
 
 // QUESTIONS TO ANSWER:
 //
@@ -20,159 +111,3024 @@ use std::{
 
 #[derive(clap::Parser)]
 struct Args {
-    /// Wikipedia dump file (multistream `*.xml.bz2`)
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Parse a dump into a link graph and write the requested outputs
+    Parse(Box<ParseArgs>),
+    /// Save the union, intersection, or difference of the links of two saved graphs, matching
+    /// pages up by title or by an ID such as their Wikidata item
+    Algebra(algebra::Args),
+    /// List the pages linking to a page of a saved graph
+    Backlinks(backlinks::Args),
+    /// Compress the links of a saved graph so that a large wiki's fits in memory, convert it
+    /// back, or search it or rank its pages without decompressing it
+    Bvgraph(bvgraph::Args),
+    /// Write statistics per category of a saved graph as CSV: pages, links in, mean PageRank,
+    /// and how many of their links stay inside the category; or list what is in a category
+    Categories(categories::Args),
+    /// Estimate the betweenness and closeness centrality of the pages of a saved graph from
+    /// shortest paths from a sample of them, and print the top pages by each
+    Centrality(centrality::Args),
+    /// Read a dump through to the end without building anything, counting its pages by
+    /// namespace and reporting anything a parse would skip or fail on
+    Check(check::Args),
+    /// Report the pages and links added and removed between two saved graphs of a wiki, as JSON
+    /// lines
+    Diff(diff::Args),
+    /// Write a saved graph, or the part of it around some pages, as GraphML, GEXF, or DOT
+    Export(export::Args),
+    /// Print the wikitext or links of one page of a multistream dump, read from the one stream
+    /// its index says holds it
+    Extract(extract::Args),
+    /// Download a wiki's multistream dump and its index from Wikimedia, resuming cut-short
+    /// downloads and checking them against the published checksums, and optionally parse it
+    Fetch(fetch::Args),
+    /// Check a saved graph for corruption
+    Fsck(fsck::Args),
+    /// List, name, and forget the graphs of the workspace, which commands taking a graph file
+    /// also take by name
+    Graphs(workspace::Args),
+    /// Build a saved graph from an edge list made elsewhere, without a dump
+    Import(import::Args),
+    /// Build a saved graph from the `page` and `pagelinks` SQL dumps of a wiki, which is much
+    /// faster than parsing its wikitext
+    ImportSql(sql_dump::Args),
+    /// List every page's ID and title from the index of a multistream dump, without reading
+    /// the dump, and count the pages of each stream
+    Index(index::Args),
+    /// List every page's ID, title, namespace, redirect target, and revision timestamp as CSV,
+    /// much faster than a parse since the wikitext is skipped
+    Inventory(inventory::Args),
+    /// Combine saved graphs or the partial results of sharded parses into one graph
+    Merge(merge::Args),
+    /// Print the pages of a saved graph with the highest PageRank, or export every score
+    Pagerank(pagerank::Args),
+    /// Print the shortest chain of links from one page to another
+    Path(path::Args),
+    /// Compare the lengths of shortest paths between random pairs of pages with estimates
+    /// through landmarks and distance sketches
+    PathEval(path_eval::Args),
+    /// Export the most central pages of a saved graph and the paths joining them, laid out for
+    /// visualization
+    Poster(poster::Args),
+    /// Write a Markdown or HTML profile of one page of a saved graph: its degrees and PageRank
+    /// against every other page's, its most central neighbours and community, and its distance
+    /// to landmark pages
+    Profile(page_profile::Args),
+    /// Remove nodes outside degree bounds, in dropped namespaces, or with titles matching a
+    /// pattern from a saved graph
+    #[command(visible_alias = "filter")]
+    Prune(prune::Args),
+    /// Rank the pages most related to a page of a saved graph by the random walks from it that
+    /// pass through them, as a personalized PageRank does
+    Related(related::Args),
+    /// Explore a saved graph at an interactive prompt, with titles completed by Tab
+    Repl(repl::Args),
+    /// Write a Markdown or HTML report of how a wiki changed between two saved graphs: growth,
+    /// the biggest movers in links in and PageRank, new hubs, and the largest new components
+    Report(report::Args),
+    /// Draw a subgraph of a given size from a saved graph by forest fire, random walk, or
+    /// snowball sampling
+    Sample(sample::Args),
+    /// Answer queries against a saved graph, given as JSON
+    Query(query::Args),
+    /// Find pages by title, whatever its case, accents, or underscores, and allowing for typos
+    Search(title_search::Args),
+    /// Search the full-text index saved next to a graph
+    SearchText(SearchTextArgs),
+    /// Serve queries against a saved graph over HTTP
+    Serve(serve::Args),
+    /// List, or clear entries of, the pages that failed in parses with `--skip-list`
+    SkipList(skip_list::Args),
+    /// Print degree distributions, components, and PageRank of a saved graph, storing them in it
+    Stats(stats::Args),
+    /// Save the part of a saved graph around some pages, or of listed pages, as a graph
+    Subgraph(subgraph::Args),
+    /// List the pages of a saved graph that rank highest by degree, PageRank, or HITS
+    Top(top::Args),
+    /// Apply the pages added and changed in incremental dumps to a saved graph, instead of
+    /// parsing the whole wiki again
+    Update(update::Args),
+    /// Write node2vec random walks over a saved graph, biased by where they came from, as a
+    /// corpus for training node embeddings
+    Walks(walks::Args),
+}
+
+#[derive(clap::Args)]
+struct SearchTextArgs {
+    /// Graph file saved by `parse --graph ... --text-index`, or its name in the workspace
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Tantivy query, e.g. `borrow checker` or `title:rust`
+    query: String,
+
+    /// Maximum number of results
+    #[arg(long, default_value_t = 10)]
+    limit: usize,
+
+    /// Only return pages within `--hops` links of this page
+    #[arg(long, value_name = "TITLE")]
+    near: Option<String>,
+
+    /// Number of links to follow from `--near`
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "near")]
+    hops: usize,
+
+    /// Which links to follow from `--near`
+    #[arg(long, value_enum, default_value_t, requires = "near")]
+    direction: graph::Direction,
+}
+
+// The bools are independent command-line switches.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(clap::Args)]
+struct ParseArgs {
+    /// Wikipedia dump file, `-` for standard input, or with the `http` feature an `http://`,
+    /// `https://`, or public `s3://` URL; plain XML or compressed with bzip2, gzip, xz or zstd,
+    /// told apart by the first bytes
     input: PathBuf,
+
+    /// TOML file of options of `parse`, keyed by their names, like `namespace = [0, 14]` or
+    /// `no-template-links = true`; options on the command line override it, and relative paths
+    /// in it are relative to it
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Wikimedia project the dump comes from, which decides namespaces and link conventions
+    /// [default: guessed from the file name]
+    #[arg(long, value_enum)]
+    project: Option<profile::Project>,
+
+    /// Language of the wiki, a code like `de` or `ja`, which decides the default
+    /// `--disambiguation-templates` [default: guessed from the file name, e.g. `de` for
+    /// `dewiki`, else English]
+    #[arg(long, value_name = "CODE")]
+    lang: Option<String>,
+
+    /// Read the namespace names and title case of the wiki from the `<siteinfo>` at the head of
+    /// the dump, so that the links of a wiki in another language, like `[[Kategorie:...]]` or
+    /// `[[Datei:...]]`, are classified as they are in English; without it, only the English
+    /// names are known. Titles of the category and file layers of `--edge-types` take the
+    /// local names too
+    #[arg(long)]
+    siteinfo: bool,
+
+    /// TOML file of extra link extraction rules: regex patterns, link templates, and target
+    /// prefixes to ignore
+    #[arg(long, value_name = "FILE")]
+    link_rules: Option<PathBuf>,
+
+    /// Don't take links from the arguments of the project's link-producing templates, such as
+    /// `{{main|...}}` and `{{see also|...}}` on Wikipedia; templates of `--link-rules` still
+    /// produce links
+    #[arg(long)]
+    no_template_links: bool,
+
+    /// Language whose script variants are the same titles, so that a link spelling its target
+    /// in traditional characters or Latin letters links to a page titled in simplified ones or
+    /// Cyrillic [default: guessed from the file name, e.g. `zh` for `zhwiki`]
+    #[arg(long, value_enum, conflicts_with = "no_variants")]
+    variants: Option<variant::Language>,
+
+    /// Take titles in different scripts as different pages, even on `zhwiki` and `srwiki`
+    #[arg(long)]
+    no_variants: bool,
+
+    /// Conversion table to add to the built-in one of `--variants`, in OpenCC's format, e.g.
+    /// OpenCC's `TSCharacters.txt` for every traditional character
+    #[arg(long, value_name = "FILE")]
+    variant_rules: Option<PathBuf>,
+
+    /// Namespace number of the pages to parse, e.g. 0 for articles and 14 for categories
+    /// (repeatable); pages of other namespaces are left out of the graph
+    #[arg(long = "namespace", value_name = "NS", default_values_t = [0])]
+    namespaces: Vec<i64>,
+
+    /// Rhai script defining `fn page(title, ns, text, links)`, run on every page to veto it or
+    /// attach node and edge attributes for the Gephi and graphology exports
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    /// Normalization stages applied to link targets, as a comma-separated list in the order to
+    /// apply them [default: all, in the order listed]
+    #[arg(long, value_name = "STAGES", value_enum, value_delimiter = ',')]
+    normalize: Vec<normalize::Stage>,
+
+    /// Normalization stages to leave out, as a comma-separated list
+    #[arg(long, value_name = "STAGES", value_enum, value_delimiter = ',')]
+    no_normalize: Vec<normalize::Stage>,
+
+    /// Classes of `[[...]]` link targets to keep as wikilinks, as a comma-separated list.
+    /// Category memberships can get a layer of their own with `--edge-types category` instead
+    #[arg(
+        long,
+        value_name = "CLASSES",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "article"
+    )]
+    link_classes: Vec<link_class::LinkClass>,
+
+    /// Edge layers to build, as a comma-separated list of types; when given, exports list each
+    /// edge once per layer with its type [default: wikilink]
+    #[arg(long, value_name = "TYPES", value_enum, value_delimiter = ',')]
+    edge_types: Vec<edge_type::EdgeType>,
+
+    /// Templates marking disambiguation pages, as a comma-separated list; pages invoking any of
+    /// them are tagged as disambiguation pages in the saved graph and the Gephi and Neo4j node
+    /// tables [default: those of the `--lang` wiki, e.g. `Disambiguation,Disambig,Dab,...` in
+    /// English and `Begriffsklärung` in German]
+    #[arg(long, value_name = "TEMPLATES", value_delimiter = ',')]
+    disambiguation_templates: Vec<String>,
+
+    /// Drop the links of disambiguation pages, the links to them, and redirects to them, so
+    /// that they are left isolated instead of being hubs for paths and PageRank
+    #[arg(long)]
+    exclude_disambiguation: bool,
+
+    /// Record where each wikilink appears in the source page's wikitext as edge attributes:
+    /// `offset` and `char_offset` of its first occurrence, the byte `offsets` of all of them, and
+    /// their number as `occurrences`
+    #[arg(long)]
+    link_offsets: bool,
+
+    /// Record how each link was found as an `origins` edge attribute: written in the prose
+    /// (`wikitext`), in a template's arguments or by a link template (`template:Name`), in an
+    /// infobox field (`infobox:field`), or by a `--link-rules` pattern (`pattern`)
+    #[arg(long)]
+    link_origins: bool,
+
+    /// Stamp each link with the time of the source page's revision as a `timestamp` edge
+    /// attribute, such as `2024-06-01T12:00:00Z`, so that `--edge-filter 'timestamp >=
+    /// "2024-01-01"'` keeps the links of recently edited pages
+    #[arg(long)]
+    edge_timestamps: bool,
+
+    /// Weigh each link by how many times the source page links to the target, for weighted
+    /// PageRank of the saved graph and as the weights of the edge list, Gephi, graphology,
+    /// GraphML, and GEXF exports
+    #[arg(long)]
+    edge_weights: bool,
+
+    /// Leave out, or with `--oversized truncate` cut down, pages with more wikitext than this
+    /// many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_page_bytes: Option<usize>,
+
+    /// What to do with pages over `--max-page-bytes`
+    #[arg(long, value_enum, default_value_t = guard::Oversized::Skip, requires = "max_page_bytes")]
+    oversized: guard::Oversized,
+
+    /// Leave out the pages listed in this file, and add pages whose links or `--script` fail
+    /// to it rather than stopping (see `skip-list`)
+    #[arg(long, value_name = "FILE", env = "WIKIGRAPH_SKIP_LIST")]
+    skip_list: Option<PathBuf>,
+
+    /// Stop at the first page with unexpected XML or an invalid title, namespace, ID, or text,
+    /// rather than logging it and skipping to the next page
+    #[arg(long)]
+    strict: bool,
+
+    /// Keep only the first this many distinct link targets of each page
+    #[arg(long, value_name = "N")]
+    max_links: Option<usize>,
+
+    /// Keep each link with this probability (0 < P <= 1), for quick approximate analyses
+    #[arg(long, value_name = "P", value_parser = parse_probability)]
+    edge_sample: Option<f64>,
+
+    /// Leave out the first this many pages in `--namespace`, in dump order
+    #[arg(long, value_name = "N")]
+    skip_pages: Option<u64>,
+
+    /// Stop reading the dump after this many pages in `--namespace`, counted after
+    /// `--skip-pages` and `--sample`, for a small graph to try changes on quickly
+    #[arg(long, value_name = "N")]
+    max_pages: Option<u64>,
+
+    /// Keep each page in `--namespace` with this probability (0 < P <= 1), with all its links;
+    /// the pages left out are still link targets, so the graph keeps them as nodes
+    #[arg(long, value_name = "P", value_parser = parse_probability)]
+    sample: Option<f64>,
+
+    /// Seed for sampling; the same seed selects the same pages and edges on every run
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Keep the parsed graph of each dump in this directory, and reuse it instead of parsing
+    /// again while the dump and the options that affect parsing are unchanged. Outputs written
+    /// while parsing, such as `--link-contexts` or `--snapshots`, still need a parse.
+    #[arg(long, value_name = "DIR")]
+    cache: Option<PathBuf>,
+
+    /// Save the graph parsed so far and how far into the dump it got to this file every
+    /// `--checkpoint-interval`, so that a parse that stops can continue with `--resume`; the
+    /// file is removed once the parse finishes
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["format", "snapshots"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Seconds between checkpoints
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 600,
+        requires = "checkpoint"
+    )]
+    checkpoint_interval: u64,
+
+    /// Continue from the `--checkpoint` file if there is one, which must have been saved by a
+    /// parse of the same dump with the same options; outputs written while parsing, such as
+    /// `--link-contexts`, only cover the pages parsed after it
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// Index of the multistream dump, listing where each bzip2 stream starts, so that streams
+    /// can be decompressed and parsed in parallel [default: the `-index.txt.bz2` file beside
+    /// the dump, if there is one]
+    #[arg(long, value_name = "FILE")]
+    index: Option<PathBuf>,
+
+    /// Checksums file published with the dump, such as `enwiki-20240101-sha1sums.txt` or the
+    /// `md5sums` one, to check the dump, and its index if listed, against before parsing
+    #[arg(long, value_name = "FILE")]
+    verify: Option<PathBuf>,
+
+    /// Number of streams of an indexed multistream dump to decompress at once [default: 1 for
+    /// small dumps (see `--demo`), else the number of CPUs]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// Number of threads finding the links of parsed pages, in whatever order they finish, so
+    /// node IDs differ between parses unless this is 1 [default: 1 for small dumps (see
+    /// `--demo`), else the number of CPUs]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    extract_threads: Option<u16>,
+
+    /// Number of threads serializing the rows of each of the edge list and Gephi exports, which
+    /// are written in order all the same [default: 1 for small dumps (see `--demo`), else the
+    /// number of CPUs]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    export_threads: Option<u16>,
+
+    /// Parse on one thread throughout unless told otherwise, so that node IDs and every output
+    /// are the same on every run, as dumps of at most 4 MiB are parsed anyway
+    #[arg(long)]
+    demo: bool,
+
+    /// Number of parsed pages that may wait for the graph builder before the dump readers
+    /// pause, which bounds the memory their text takes when reading outpaces building
+    #[arg(
+        long,
+        value_name = "PAGES",
+        default_value_t = 1024,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    channel_capacity: u32,
+
+    /// Times in a row to retry a failed request or read of a dump given by URL, resuming where
+    /// it stopped, before giving up
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    retries: u32,
+
+    /// Most bytes a second to download a dump given by URL at
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "BYTES")]
+    max_bandwidth: Option<u64>,
+
+    /// Keep a dump given by URL in this directory as it downloads, so that a parse cut short
+    /// resumes the download where it stopped and later parses read the copy
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "DIR")]
+    spool: Option<PathBuf>,
+
+    /// Don't show how much of the dump has been read, which is otherwise drawn as a progress
+    /// bar on a terminal and logged every 30 seconds elsewhere
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Serve metrics of the parse for Prometheus at `/metrics` on this address, such as
+    /// `127.0.0.1:9184`, while the dump is read: bytes and pages read, malformed pages skipped,
+    /// read pages waiting to be parsed, and memory in use
+    #[arg(long, value_name = "ADDRESS")]
+    metrics_listen: Option<String>,
+
+    /// Log the same metrics every this many seconds while the dump is read, as fields of one
+    /// event with the rates over the last interval, so that a stalled parse shows in the log
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    metrics_interval: Option<u64>,
+
+    /// Only parse the bzip2 streams starting in the K-th of N equal byte ranges of a
+    /// multistream dump, so that N independent workers can split the dump between them
+    #[arg(long, value_name = "K/N", conflicts_with = "diff_from")]
+    shard: Option<shard::Shard>,
+
+    /// Write the parsed graph as a partial result to this file, for `merge` to combine with the
+    /// other shards
+    #[arg(long, value_name = "FILE")]
+    partial: Option<PathBuf>,
+
+    /// Treat the input as a full-history dump and write one Gephi snapshot per interval into
+    /// subdirectories of this directory
+    #[arg(long, value_name = "DIR")]
+    snapshots: Option<PathBuf>,
+
+    /// Length of the period covered by each snapshot
+    #[arg(long, value_enum, default_value_t = snapshot::Interval::Month)]
+    snapshot_interval: snapshot::Interval,
+
+    /// Compare against an older dump of the same wiki, reporting added, removed, and renamed
+    /// pages and changed links
+    #[arg(long, value_name = "OLD_DUMP", requires = "diff_report")]
+    diff_from: Option<PathBuf>,
+
+    /// Write the change report as JSON lines to this file
+    #[arg(long, value_name = "FILE", requires = "diff_from")]
+    diff_report: Option<PathBuf>,
+
+    /// Minimum Jaccard similarity of link sets for a page that became a redirect to count as
+    /// moved to its target
+    #[arg(long, value_name = "S", default_value_t = 0.5)]
+    rename_similarity: f64,
+
+    /// `page_props` table dump, such as `enwiki-20240601-page_props.sql.gz`, giving each page
+    /// the Wikidata item it is linked to, by page ID, in the graph file and the exports
+    #[arg(long, value_name = "FILE")]
+    page_props: Option<PathBuf>,
+
+    /// Save the link graph to this file, for later queries
+    #[arg(long, value_name = "FILE")]
+    graph: Option<PathBuf>,
+
+    /// zstd-compress the `--graph` file, which makes it a few times smaller
+    #[arg(long, requires = "graph")]
+    compress_graph: bool,
+
+    /// Store the titles, links, and backlinks of the `--graph` file in compressed blocks, which
+    /// makes it smaller while keeping it mappable by `path --mmap`, unlike `--compress-graph`
+    #[arg(
+        long,
+        value_name = "CODEC",
+        value_enum,
+        default_value_t,
+        requires = "graph",
+        conflicts_with = "compress_graph"
+    )]
+    graph_codec: graph::Codec,
+
+    /// Leave the backlinks out of the `--graph` file, which makes it about a third smaller.
+    /// Commands derive them when they need them, but `path --mmap` can't map the file.
+    #[arg(long, requires = "graph")]
+    no_backlinks: bool,
+
+    /// Keep a sorted list of every page title in this file, updated as the parser meets them, so
+    /// that `search --titles` can find pages while a long parse is still running
+    #[arg(long, value_name = "FILE")]
+    titles: Option<PathBuf>,
+
+    /// Also build a full-text index of titles and plain text next to the saved graph
+    #[arg(long, requires = "graph", conflicts_with = "snapshots")]
+    text_index: bool,
+
+    /// Write every link with its anchor text and surrounding sentence as JSON lines to this file
+    #[arg(long, value_name = "FILE")]
+    link_contexts: Option<PathBuf>,
+
+    /// Maximum number of bytes of context on either side of a link
+    #[arg(long, value_name = "BYTES", default_value_t = 300)]
+    context_window: usize,
+
+    /// Write every page and link as a length-delimited Protocol Buffers stream (see
+    /// `src/page_stream.proto`) to this file or named pipe
+    #[arg(long, value_name = "FILE")]
+    page_stream: Option<PathBuf>,
+
+    /// Write every page with its metadata, categories, templates, links with anchor text,
+    /// external links, and infobox fields as JSON lines to this file
+    #[arg(long, value_name = "FILE")]
+    page_json: Option<PathBuf>,
+
+    /// Write how often each anchor text links to each target, with the probability of the target
+    /// given the anchor, as CSV to this file
+    #[arg(long, value_name = "FILE")]
+    anchor_stats: Option<PathBuf>,
+
+    /// Write the anchor texts each page links to each target with, as `source,target,anchor,
+    /// count` CSV rows to this file, for surface-form dictionaries that keep the page a form is
+    /// used on. There is a row for nearly every link, so the file is often larger than the graph
+    #[arg(long, value_name = "FILE")]
+    anchor_text: Option<PathBuf>,
+
+    /// Write every link target and title the parser rewrote, with what it became and why, as
+    /// CSV to this file
+    #[arg(long, value_name = "FILE")]
+    normalization_audit: Option<PathBuf>,
+
+    /// Write redirects whose target isn't a page of the dump (`broken`) and redirects no page
+    /// links to (`orphaned`) as CSV to this file
+    #[arg(long, value_name = "FILE")]
+    redirect_report: Option<PathBuf>,
+
+    /// Write a summary of the parse as JSON to this file: pages read, parsed, left out, and
+    /// malformed, redirects, links, edges, pages by namespace, wall time, and peak memory. The
+    /// dump is parsed even if `--cache` has it, so that there are pages to count
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// What to do with red links, whose target isn't a page of the dump
+    #[arg(long, value_enum, default_value_t)]
+    red_links: red_link::Policy,
+
+    /// Write the red links dropped by `--red-links report` as `source,target` CSV to this file
+    #[arg(long, value_name = "FILE", required_if_eq("red_links", "report"))]
+    red_link_report: Option<PathBuf>,
+
+    /// Write an undirected graph of link targets appearing in the same sentence or paragraph,
+    /// weighted by how often they do, as a Gephi edge list to this file
+    #[arg(long, value_name = "FILE")]
+    cooccurrence: Option<PathBuf>,
+
+    /// Text segment within which links count as co-occurring
+    #[arg(long, value_enum, default_value_t = cooccurrence::Scope::Sentence)]
+    cooccurrence_scope: cooccurrence::Scope,
+
+    /// Write the bipartite graph of pages and the templates they invoke, and its projections,
+    /// as Gephi CSV files to this directory
+    #[arg(long, value_name = "DIR")]
+    template_usage: Option<PathBuf>,
+
+    /// One-mode projections of the page–template graph to write
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "pages,templates",
+        requires = "template_usage"
+    )]
+    template_projections: Vec<template_usage::Projection>,
+
+    /// Fewest shared templates or pages for a pair to be linked in a projection
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        requires = "template_usage"
+    )]
+    template_min_weight: u64,
+
+    /// Leave templates used on more than this many pages out of the page projection
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1000,
+        requires = "template_usage"
+    )]
+    template_max_uses: usize,
+
+    /// Only export nodes matching this expression, e.g. `ns == 0 && !is_redirect`; see
+    /// `filter.rs` for the attributes and operators
+    #[arg(long, value_name = "EXPR", value_parser = filter::Filter::parse)]
+    node_filter: Option<filter::Filter>,
+
+    /// Only export links matching this expression, e.g. `target.in_degree > 1`
+    #[arg(long, value_name = "EXPR", value_parser = filter::Filter::parse)]
+    edge_filter: Option<filter::Filter>,
+
+    /// Write every page with its sort key as CSV to this file, in MediaWiki listing order
+    #[arg(long, value_name = "FILE")]
+    sort_index: Option<PathBuf>,
+
+    /// Write every category membership with its sort key as CSV to this file, ordered like
+    /// MediaWiki category pages
+    #[arg(long, value_name = "FILE")]
+    category_index: Option<PathBuf>,
+
+    /// Save the category memberships as a graph of their own to this file, in which pages link
+    /// to their categories and category pages, if parsed, to their parent categories; see
+    /// `categories`
+    #[arg(long, value_name = "FILE")]
+    category_graph: Option<PathBuf>,
+
+    /// Write the link graph as an edge list of `source` and `target` titles to this file, or
+    /// with `--format`, each page as it is parsed, into a directory for Parquet [default with
+    /// `--format jsonl` or `ntriples`: standard output]
+    #[arg(long, short, value_name = "FILE", required_if_eq("format", "parquet"))]
+    output: Option<PathBuf>,
+
+    /// Stream each page to `--output` as soon as it is parsed, instead of building a graph,
+    /// so that dumps of any size fit in memory; logs go to standard error with no `--output`
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = [
+            "graph", "category_graph", "partial", "cache", "snapshots", "diff_report",
+            "sort_index", "category_index", "output_format", "shards", "gephi", "neo4j",
+            "condensed", "graphology", "npy", "pyg", "namespace_partitions", "node_filter",
+            "edge_filter",
+        ]
+    )]
+    format: Option<stream::Format>,
+
+    /// Hold at most this many bytes of links in memory while parsing, such as `4G` or `512M`,
+    /// spilling the rest to sorted files that are merged into the `--graph` and the `--output`
+    /// edge list at the end, for dumps whose links don't fit in memory otherwise. Links have
+    /// no types or weights, and other outputs of the graph can't be written
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = spill::parse_size,
+        conflicts_with_all = [
+            "format", "edge_types", "edge_weights", "edge_timestamps", "link_offsets",
+            "link_origins", "snapshots", "cache", "checkpoint", "partial", "diff_from",
+            "category_graph", "sort_index", "category_index", "gephi", "neo4j", "condensed",
+            "graphology", "npy", "pyg", "namespace_partitions", "node_filter", "edge_filter",
+        ]
+    )]
+    memory_limit: Option<u64>,
+
+    /// Directory for the files of `--memory-limit` [default: the system's temporary directory]
+    #[arg(long, value_name = "DIR", requires = "memory_limit")]
+    spill_dir: Option<PathBuf>,
+
+    /// Field separator of the `--output` edge list
+    #[arg(long, value_enum, default_value_t = export::edge_list::Format::Tsv)]
+    output_format: export::edge_list::Format,
+
+    /// Split the `--output` edge list over this many files, `links-00000-of-00016.tsv` and so
+    /// on for `links.tsv`, each with a header, putting the links of each page in the one picked
+    /// by a hash of its title, so that they can be read in parallel and every parse picks the
+    /// same one for a page
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "output",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    shards: Option<u32>,
+
+    /// Write the link graph as Gephi `nodes.csv` and `edges.csv` into this directory
+    #[arg(long, value_name = "DIR")]
+    gephi: Option<PathBuf>,
+
+    /// Write the link graph as `nodes.csv` and `rels.csv` for `neo4j-admin database import`
+    /// into this directory
+    #[arg(long, value_name = "DIR")]
+    neo4j: Option<PathBuf>,
+
+    /// Write a Gephi export to this directory in which the members of each `--condense-category`
+    /// are collapsed into a single node, with link counts as edge weights
+    #[arg(long, value_name = "DIR", requires = "condense_category")]
+    condensed: Option<PathBuf>,
+
+    /// Category to collapse into a super-node for `--condensed` (repeatable)
+    #[arg(long, value_name = "NAME")]
+    condense_category: Vec<String>,
+
+    /// Drop pages outside the condensed categories from `--condensed`
+    #[arg(long, requires = "condensed")]
+    condense_members_only: bool,
+
+    /// Write the link graph as graphology JSON (loadable by sigma.js) to this file
+    #[arg(long, value_name = "FILE")]
+    graphology: Option<PathBuf>,
+
+    /// Write the link graph as NumPy COO arrays plus an `index.json` into this directory
+    #[arg(long, value_name = "DIR")]
+    npy: Option<PathBuf>,
+
+    /// Write the link graph as PyTorch Geometric `edge_index` and node feature arrays into this
+    /// directory
+    #[arg(long, value_name = "DIR")]
+    pyg: Option<PathBuf>,
+
+    /// Write the links into one CSV file per pair of source and target namespaces, like
+    /// `article-category.csv`, in this directory
+    #[arg(long, value_name = "DIR")]
+    namespace_partitions: Option<PathBuf>,
+
+    #[cfg(feature = "postgres")]
+    #[command(flatten)]
+    postgres: sink::postgres::Args,
+
+    #[cfg(feature = "clickhouse")]
+    #[command(flatten)]
+    clickhouse: sink::clickhouse::Args,
+
+    #[cfg(feature = "kafka")]
+    #[command(flatten)]
+    kafka: sink::kafka::Args,
+
+    #[cfg(feature = "sqlite")]
+    #[command(flatten)]
+    sqlite: sink::sqlite::Args,
+
+    /// Print what the parse would read, do, and write, with the number of pages from the dump
+    /// index and an estimate of the memory needed, then exit without parsing
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Compute a force-directed layout for exports with at most this many nodes
+    #[arg(long, value_name = "N", default_value_t = 2000)]
+    layout_max_nodes: usize,
+
+    /// Number of force-directed layout iterations
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    layout_iterations: usize,
+}
+
+impl ParseArgs {
+    /// The normalization stages to apply, in order.
+    fn normalization(&self) -> Vec<normalize::Stage> {
+        let stages = if self.normalize.is_empty() {
+            &normalize::Stage::ALL[..]
+        } else {
+            &self.normalize
+        };
+        stages
+            .iter()
+            .copied()
+            .filter(|stage| !self.no_normalize.contains(stage))
+            .collect()
+    }
+}
+
+/// Links and redirects of one dump, with titles interned into a shared `Rodeo`.
+#[derive(Default)]
+struct Wiki {
+    links: HashMap<Spur, HashSet<Spur>>,
+    redirects: HashMap<Spur, Spur>,
+    /// Keys from `{{DEFAULTSORT:...}}`, for pages that set one.
+    sort_keys: HashMap<Spur, String>,
+    /// Category memberships as (category name, page), with the explicit sort key from
+    /// `[[Category:Name|Key]]` if there is one. Category names are interned into
+    /// `category_names` rather than the shared `Rodeo`, so that they don't show up as nodes.
+    categories: HashMap<(Spur, Spur), Option<String>>,
+    category_names: Rodeo,
+    /// Namespace numbers of the pages in the dump.
+    namespaces: HashMap<Spur, i64>,
+    /// Page IDs from the dump, for pages that have one.
+    page_ids: HashMap<Spur, u64>,
+    /// Numbers of the Wikidata items of pages, from `--page-props`, for pages that have one.
+    wikidata_items: HashMap<Spur, u32>,
+    /// Timestamp of the latest revision of each page that has one.
+    timestamps: HashMap<Spur, String>,
+    /// Portals and navigation-heavy pages.
+    navigation: HashMap<Spur, navigation::Kind>,
+    /// Layers of each edge, when built with `--edge-types`; edges without an entry have no type.
+    edge_types: HashMap<(Spur, Spur), edge_type::EdgeTypes>,
+    /// Attributes set by `--script`.
+    node_attributes: HashMap<Spur, script::Attributes>,
+    edge_attributes: HashMap<(Spur, Spur), script::Attributes>,
+    /// How many times the source of each link links to its target, when built with
+    /// `--edge-weights`; links without an entry occur once.
+    link_counts: Option<HashMap<(Spur, Spur), u32>>,
+    /// Links spilled to disk with `--memory-limit`, which leaves `links` empty.
+    spill: Option<spill::Spill>,
+}
+
+/// Optional outputs that are produced while parsing, rather than from the finished graph.
+#[derive(Default)]
+struct Collectors {
+    contexts: Option<context::Writer>,
+    cooccurrence: Option<cooccurrence::Cooccurrence>,
+    template_usage: Option<template_usage::TemplateUsage>,
+    anchors: Option<anchors::AnchorStats>,
+    anchor_text: Option<anchors::Writer>,
+    audit: Option<audit::Audit>,
+    redirect_report: Option<redirect_report::RedirectReport>,
+    text_index: Option<text_index::Writer>,
+    titles: Option<title_list::Writer>,
+    page_stream: Option<page_stream::Writer>,
+    page_json: Option<page_json::Writer>,
+    stream: Option<stream::Writer>,
+    #[cfg(feature = "kafka")]
+    kafka: Option<sink::kafka::Producer>,
+    /// Taken out by `parse` before the others are finished, to be written last.
+    report: Option<run_report::Report>,
+}
+
+impl Collectors {
+    fn new(args: &ParseArgs) -> Self {
+        Self {
+            contexts: args.link_contexts.as_ref().map(|path| {
+                context::Writer::create(path, args.context_window)
+                    .context("Failed to create link context file")
+                    .unwrap()
+            }),
+            cooccurrence: args
+                .cooccurrence
+                .is_some()
+                .then(|| cooccurrence::Cooccurrence::new(args.cooccurrence_scope)),
+            template_usage: args
+                .template_usage
+                .is_some()
+                .then(template_usage::TemplateUsage::new),
+            anchors: args
+                .anchor_stats
+                .is_some()
+                .then(anchors::AnchorStats::default),
+            anchor_text: args.anchor_text.as_ref().map(|path| {
+                anchors::Writer::create(path)
+                    .context("Failed to create anchor text file")
+                    .unwrap()
+            }),
+            audit: args
+                .normalization_audit
+                .is_some()
+                .then(audit::Audit::default),
+            redirect_report: args
+                .redirect_report
+                .is_some()
+                .then(redirect_report::RedirectReport::default),
+            text_index: args
+                .graph
+                .as_ref()
+                .filter(|_| args.text_index)
+                .map(|graph| {
+                    text_index::Writer::create(&text_index::path_for(graph))
+                        .context("Failed to create text index")
+                        .unwrap()
+                }),
+            titles: args.titles.as_ref().map(|path| {
+                title_list::Writer::create(path)
+                    .context("Failed to create title list")
+                    .unwrap()
+            }),
+            page_stream: args.page_stream.as_ref().map(|path| {
+                page_stream::Writer::create(path)
+                    .context("Failed to create page stream")
+                    .unwrap()
+            }),
+            page_json: args.page_json.as_ref().map(|path| {
+                page_json::Writer::create(path)
+                    .context("Failed to create page JSON file")
+                    .unwrap()
+            }),
+            stream: args.format.map(|format| {
+                stream::Writer::create(format, args)
+                    .context("Failed to create the stream of pages")
+                    .unwrap()
+            }),
+            #[cfg(feature = "kafka")]
+            kafka: sink::kafka::Producer::connect(&args.kafka)
+                .context("Failed to connect to Kafka")
+                .unwrap(),
+            report: args.report.is_some().then(run_report::Report::default),
+        }
+    }
+
+    /// Whether no collector is enabled, so the pages themselves aren't needed.
+    fn is_empty(&self) -> bool {
+        let Self {
+            contexts,
+            cooccurrence,
+            template_usage,
+            anchors,
+            anchor_text,
+            audit,
+            redirect_report,
+            text_index,
+            titles,
+            page_stream,
+            page_json,
+            stream,
+            #[cfg(feature = "kafka")]
+            kafka,
+            report,
+        } = self;
+        #[cfg(feature = "kafka")]
+        let kafka = kafka.is_none();
+        #[cfg(not(feature = "kafka"))]
+        let kafka = true;
+        contexts.is_none()
+            && cooccurrence.is_none()
+            && template_usage.is_none()
+            && anchors.is_none()
+            && anchor_text.is_none()
+            && audit.is_none()
+            && redirect_report.is_none()
+            && text_index.is_none()
+            && titles.is_none()
+            && page_stream.is_none()
+            && page_json.is_none()
+            && stream.is_none()
+            && kafka
+            && report.is_none()
+    }
+
+    /// Feed one parsed page, whose banner-stripped wikitext is `text` and whose resolved link
+    /// targets are `targets`, to every collector.
+    #[allow(clippy::too_many_lines)]
+    fn add_page(
+        &mut self,
+        profile: &profile::Profile,
+        rodeo: &mut Rodeo,
+        page: &Page,
+        title: Spur,
+        text: &str,
+        targets: &[Cow<str>],
+    ) {
+        if let Some(contexts) = &mut self.contexts {
+            for link in links(text) {
+                if let Some(target) = profile.resolve(&page.title, link.target) {
+                    contexts
+                        .write(&page.title, &target, text, &link)
+                        .context("Failed to write link context")
+                        .unwrap();
+                }
+            }
+        }
+        if let Some(cooccurrence) = &mut self.cooccurrence {
+            let targets: Vec<_> = links(text)
+                .filter_map(|link| {
+                    let target = profile.resolve(&page.title, link.target)?;
+                    Some((link.range, rodeo.get_or_intern(target)))
+                })
+                .collect();
+            cooccurrence.add_page(text, &targets);
+        }
+        if let Some(report) = &mut self.report {
+            report.add_page(page, targets);
+        }
+        if let Some(template_usage) = &mut self.template_usage {
+            template_usage.add_page(title, &page.text);
+        }
+        if let Some(redirect_report) = &mut self.redirect_report {
+            let redirect = page.redirect.as_deref().map(|r| rodeo.get_or_intern(r));
+            let targets: Vec<Spur> = targets.iter().map(|l| rodeo.get_or_intern(l)).collect();
+            redirect_report.add_page(title, redirect, &targets);
+        }
+        if let Some(titles) = &mut self.titles {
+            titles
+                .add_page(title, &page.title)
+                .context("Failed to write title list")
+                .unwrap();
+        }
+        if let Some(text_index) = &mut self.text_index {
+            text_index
+                .add(title, &page.title, &page.text)
+                .context("Failed to index page text")
+                .unwrap();
+        }
+        if let Some(anchors) = &mut self.anchors {
+            for link in links(text) {
+                if let Some(target) = profile.resolve(&page.title, link.target) {
+                    anchors.add(
+                        plaintext::strip_markup(link.anchor),
+                        rodeo.get_or_intern(target),
+                    );
+                }
+            }
+        }
+        if let Some(anchor_text) = &mut self.anchor_text {
+            anchor_text
+                .add_page(profile, &page.title, text)
+                .context("Failed to write anchor text")
+                .unwrap();
+        }
+        if let Some(page_stream) = &mut self.page_stream {
+            page_stream
+                .add_page(
+                    &page.title,
+                    page.namespace,
+                    page.redirect.as_deref(),
+                    targets,
+                )
+                .context("Failed to write page stream")
+                .unwrap();
+        }
+        if let Some(page_json) = &mut self.page_json {
+            page_json
+                .add_page(profile, page, text)
+                .context("Failed to write page JSON")
+                .unwrap();
+        }
+        if let Some(stream) = &mut self.stream {
+            stream
+                .add_page(
+                    rodeo,
+                    &page.title,
+                    title,
+                    page.namespace,
+                    page.redirect.as_deref(),
+                    targets,
+                )
+                .context("Failed to write the stream of pages")
+                .unwrap();
+        }
+        #[cfg(feature = "kafka")]
+        if let Some(kafka) = &mut self.kafka {
+            let targets: Vec<&str> = targets.iter().map(AsRef::as_ref).collect();
+            kafka
+                .add_page(
+                    &page.title,
+                    page.namespace,
+                    page.redirect.as_deref(),
+                    &targets,
+                )
+                .context("Failed to publish to Kafka")
+                .unwrap();
+        }
+    }
+
+    /// Write out everything collected during the parse.
+    fn finish(self, args: &ParseArgs, rodeo: &Rodeo) {
+        #[cfg(feature = "kafka")]
+        if let Some(kafka) = self.kafka {
+            kafka
+                .finish()
+                .context("Failed to publish to Kafka")
+                .unwrap();
+        }
+
+        if let Some(page_stream) = self.page_stream {
+            page_stream
+                .finish()
+                .context("Failed to write page stream")
+                .unwrap();
+        }
+        if let Some(page_json) = self.page_json {
+            page_json
+                .finish()
+                .context("Failed to write page JSON")
+                .unwrap();
+        }
+        if let Some(anchor_text) = self.anchor_text {
+            anchor_text
+                .finish()
+                .context("Failed to write anchor text")
+                .unwrap();
+        }
+        if let Some(stream) = self.stream {
+            let pages = stream
+                .finish(rodeo)
+                .context("Failed to write the stream of pages")
+                .unwrap();
+            tracing::info!("Streamed {pages} pages");
+        }
+        if let Some(titles) = self.titles {
+            titles
+                .finish()
+                .context("Failed to write title list")
+                .unwrap();
+        }
+        if let Some(text_index) = self.text_index {
+            text_index
+                .finish()
+                .context("Failed to write text index")
+                .unwrap();
+        }
+
+        if let Some(contexts) = self.contexts {
+            contexts
+                .finish()
+                .context("Failed to write link contexts")
+                .unwrap();
+        }
+
+        if let (Some(path), Some(cooccurrence)) = (&args.cooccurrence, &self.cooccurrence) {
+            cooccurrence
+                .write(path, rodeo)
+                .context("Failed to write co-occurrence graph")
+                .unwrap();
+        }
+        if let (Some(dir), Some(template_usage)) = (&args.template_usage, &self.template_usage) {
+            template_usage
+                .write(
+                    dir,
+                    rodeo,
+                    &args.template_projections,
+                    args.template_min_weight,
+                    args.template_max_uses,
+                )
+                .context("Failed to write template usage graph")
+                .unwrap();
+        }
+
+        if let (Some(path), Some(anchors)) = (&args.anchor_stats, &self.anchors) {
+            anchors
+                .write(path, rodeo)
+                .context("Failed to write anchor statistics")
+                .unwrap();
+        }
+        if let (Some(path), Some(audit)) = (&args.normalization_audit, &self.audit) {
+            audit
+                .write(path)
+                .context("Failed to write normalization audit")
+                .unwrap();
+        }
+        if let (Some(path), Some(redirect_report)) = (&args.redirect_report, &self.redirect_report)
+        {
+            let summary = redirect_report
+                .write(path, rodeo)
+                .context("Failed to write redirect report")
+                .unwrap();
+            println!(
+                "{} broken redirects, {} orphaned redirects",
+                summary.broken, summary.orphaned
+            );
+        }
+    }
+}
+
+impl Wiki {
+    /// Record the attributes a script set on `page` and on its `links`. Attributes for targets
+    /// the page doesn't link to are ignored.
+    fn add_script_output(
+        &mut self,
+        rodeo: &Rodeo,
+        page: Spur,
+        links: &HashSet<Spur>,
+        output: script::Output,
+    ) {
+        if !output.node.is_empty() {
+            self.node_attributes.insert(page, output.node);
+        }
+        for (target, attributes) in output.edges {
+            if let Some(target) = rodeo.get(target).filter(|target| links.contains(target)) {
+                self.edge_attributes.insert((page, target), attributes);
+            }
+        }
+    }
+
+    /// Record the edges of `page` in each of `layers`, whose wikilinks resolve to `targets`, and
+    /// return all of their targets.
+    fn add_typed_links(
+        &mut self,
+        profile: &profile::Profile,
+        rodeo: &mut Rodeo,
+        layers: &[edge_type::EdgeType],
+        page: &Page,
+        text: &str,
+        targets: &[Cow<str>],
+    ) -> HashSet<Spur> {
+        let title = rodeo.get_or_intern(&page.title);
+        let mut links = HashSet::new();
+        for &layer in layers {
+            let layer_targets: Vec<Spur> = if layer == edge_type::EdgeType::Wikilink {
+                targets.iter().map(|l| rodeo.get_or_intern(l)).collect()
+            } else {
+                layer
+                    .targets(profile, page, text)
+                    .iter()
+                    .map(|l| rodeo.get_or_intern(l))
+                    .collect()
+            };
+            for target in layer_targets {
+                self.edge_types
+                    .entry((title, target))
+                    .or_default()
+                    .insert(layer);
+                links.insert(target);
+            }
+        }
+        links
+    }
+
+    /// Record the positions of the wikilinks of `page` to each of `targets` as edge attributes,
+    /// alongside any set by `--script`.
+    fn add_link_offsets(
+        &mut self,
+        profile: &profile::Profile,
+        rodeo: &Rodeo,
+        title: Spur,
+        page: &Page,
+        targets: &HashSet<Spur>,
+    ) {
+        let mut positions: HashMap<Spur, Vec<(usize, usize)>> = HashMap::new();
+        // Characters before `counted`, which only moves forward since links come in order.
+        let (mut counted, mut chars) = (0, 0);
+        for link in links(&page.text) {
+            let Some(target) = profile
+                .resolve(&page.title, link.target)
+                .and_then(|target| rodeo.get(target))
+                .filter(|target| targets.contains(target))
+            else {
+                continue;
+            };
+            chars += page.text[counted..link.range.start].chars().count();
+            counted = link.range.start;
+            positions
+                .entry(target)
+                .or_default()
+                .push((link.range.start, chars));
+        }
+        for (target, positions) in positions {
+            let attributes = self.edge_attributes.entry((title, target)).or_default();
+            let (offset, char_offset) = positions[0];
+            attributes.insert(String::from("offset"), offset.into());
+            attributes.insert(String::from("char_offset"), char_offset.into());
+            attributes.insert(
+                String::from("offsets"),
+                positions
+                    .iter()
+                    .map(|&(offset, _)| serde_json::Value::from(offset))
+                    .collect(),
+            );
+            attributes.insert(String::from("occurrences"), positions.len().into());
+        }
+    }
+
+    /// Record the timestamp of the revision `page` of `title` as a `timestamp` edge attribute
+    /// of its links to each of `targets`, alongside any set by `--script`. Of a history dump's
+    /// revisions, the last read wins.
+    fn add_edge_timestamps(&mut self, title: Spur, page: &Page, targets: &HashSet<Spur>) {
+        let Some(timestamp) = &page.timestamp else {
+            return;
+        };
+        for &target in targets {
+            self.edge_attributes
+                .entry((title, target))
+                .or_default()
+                .insert(String::from("timestamp"), timestamp.as_str().into());
+        }
+    }
+
+    /// Record how many times each of `targets`, the link targets of page `title`, occurs,
+    /// where it is more than once. Of a history dump's revisions, the last read wins.
+    fn add_link_counts(&mut self, rodeo: &Rodeo, title: Spur, targets: &[Cow<str>]) {
+        let mut counts: HashMap<Spur, u32> = HashMap::new();
+        for target in targets {
+            if let Some(target) = rodeo.get(target) {
+                *counts.entry(target).or_default() += 1;
+            }
+        }
+        let link_counts = self.link_counts.get_or_insert_default();
+        for (target, count) in counts {
+            if count > 1 {
+                link_counts.insert((title, target), count);
+            } else {
+                link_counts.remove(&(title, target));
+            }
+        }
+    }
+
+    /// The weight of the link from `source` to `target`, if the links are weighted.
+    fn weight(&self, source: Spur, target: Spur) -> Option<u32> {
+        self.link_counts
+            .as_ref()
+            .map(|counts| counts.get(&(source, target)).copied().unwrap_or(1))
+    }
+
+    /// Record `origins`, how the links of page `title` were found, as an `origins` edge
+    /// attribute of its links to each of `targets`, alongside any set by `--script`.
+    fn add_link_origins(
+        &mut self,
+        profile: &profile::Profile,
+        rodeo: &Rodeo,
+        title: Spur,
+        origins: Vec<(&str, String)>,
+        targets: &HashSet<Spur>,
+    ) {
+        let page = rodeo.resolve(&title);
+        let mut by_target: HashMap<Spur, BTreeSet<String>> = HashMap::new();
+        for (target, origin) in origins {
+            let Some(target) = profile
+                .resolve(page, target)
+                .and_then(|target| rodeo.get(target))
+                .filter(|target| targets.contains(target))
+            else {
+                continue;
+            };
+            by_target.entry(target).or_default().insert(origin);
+        }
+        for (target, origins) in by_target {
+            self.edge_attributes
+                .entry((title, target))
+                .or_default()
+                .insert(String::from("origins"), origins.into_iter().collect());
+        }
+    }
+
+    /// Point links at the pages they lead to, through titles in other scripts and redirects, as
+    /// far as `profile` says to.
+    fn resolve_targets(&mut self, rodeo: &Rodeo, profile: &profile::Profile) {
+        if let Some(variants) = profile.variants() {
+            self.merge_variants(rodeo, variants);
+        }
+        if profile
+            .stages()
+            .contains(&normalize::Stage::ResolveRedirects)
+        {
+            self.resolve_redirects();
+        }
+    }
+
+    /// Point links to redirects at the pages they redirect to, following chains of redirects.
+    /// Links into redirect loops are left alone. Edge types and attributes move with the link,
+    /// keeping those of a link the page already had to the target, and weights add up.
+    fn resolve_redirects(&mut self) {
+        let mut moved = Vec::new();
+        for (&source, targets) in &self.links {
+            for &target in targets {
+                if let Some(resolved) = self
+                    .resolve_redirect(target)
+                    .filter(|&resolved| resolved != target)
+                {
+                    moved.push((source, target, resolved));
+                }
+            }
+        }
+        self.retarget(moved);
+    }
+
+    /// The page `target` leads to through chains of redirects, or `None` if they loop.
+    fn resolve_redirect(&self, mut target: Spur) -> Option<Spur> {
+        let mut seen = HashSet::new();
+        while let Some(&next) = self.redirects.get(&target) {
+            if !seen.insert(target) {
+                return None;
+            }
+            target = next;
+        }
+        Some(target)
+    }
+
+    /// Point links to titles that aren't pages at the page whose title `variants` converts the
+    /// same way, if there is one, and so redirects too. Of pages whose titles convert the same
+    /// way, the first by title wins. Runs before `resolve_redirects`, which then follows
+    /// redirects spelled in another script.
+    fn merge_variants(&mut self, rodeo: &Rodeo, variants: &variant::Rules) {
+        let pages = self.pages_by_variant(rodeo, variants);
+        let page = |target| self.variant_page(rodeo, variants, &pages, target);
+
+        let mut moved = Vec::new();
+        for (&source, targets) in &self.links {
+            for &target in targets {
+                if let Some(page) = page(target) {
+                    moved.push((source, target, page));
+                }
+            }
+        }
+        let redirects: Vec<_> = self
+            .redirects
+            .iter()
+            .filter_map(|(&redirect, &target)| Some((redirect, page(target)?)))
+            .collect();
+        tracing::info!(
+            "Pointed {} links and {} redirects at pages titled in another script",
+            moved.len(),
+            redirects.len()
+        );
+        self.redirects.extend(redirects);
+        self.retarget(moved);
+    }
+
+    /// The pages by how `variants` converts their titles. Of pages whose titles convert the
+    /// same way, the first by title wins.
+    fn pages_by_variant<'a>(
+        &self,
+        rodeo: &'a Rodeo,
+        variants: &variant::Rules,
+    ) -> HashMap<Cow<'a, str>, Spur> {
+        let mut pages: HashMap<Cow<str>, Spur> = HashMap::new();
+        for &page in self.namespaces.keys() {
+            let title = rodeo.resolve(&page);
+            pages
+                .entry(variants.key(title))
+                .and_modify(|other| {
+                    if title < rodeo.resolve(other) {
+                        *other = page;
+                    }
+                })
+                .or_insert(page);
+        }
+        pages
+    }
+
+    /// The page of `pages_by_variant` that links to `target` lead to, if it isn't a page.
+    fn variant_page(
+        &self,
+        rodeo: &Rodeo,
+        variants: &variant::Rules,
+        pages: &HashMap<Cow<str>, Spur>,
+        target: Spur,
+    ) -> Option<Spur> {
+        if self.namespaces.contains_key(&target) {
+            return None;
+        }
+        pages.get(&variants.key(rodeo.resolve(&target))).copied()
+    }
+
+    /// Where links to each title lead, by title ID, as `resolve_targets` points them once it
+    /// has run, or `spill::LEFT_OUT` for those to disambiguation pages if `exclude_disambiguation`
+    /// would drop them. For links spilled with `--memory-limit`, which are only resolved when
+    /// merged.
+    fn link_targets(
+        &self,
+        rodeo: &Rodeo,
+        profile: &profile::Profile,
+        exclude_disambiguation: bool,
+    ) -> Vec<u32> {
+        let variants = profile
+            .variants()
+            .map(|variants| (variants, self.pages_by_variant(rodeo, variants)));
+        let redirects = profile
+            .stages()
+            .contains(&normalize::Stage::ResolveRedirects);
+        rodeo
+            .iter()
+            .map(|(title, _)| {
+                let mut target = variants
+                    .as_ref()
+                    .and_then(|(variants, pages)| self.variant_page(rodeo, variants, pages, title))
+                    .unwrap_or(title);
+                if redirects {
+                    target = self.resolve_redirect(target).unwrap_or(target);
+                }
+                if exclude_disambiguation
+                    && self.navigation.get(&target) == Some(&navigation::Kind::Disambiguation)
+                {
+                    spill::LEFT_OUT
+                } else {
+                    u32::try_from(target.into_usize()).unwrap()
+                }
+            })
+            .collect()
+    }
+
+    /// Leave disambiguation pages isolated, without their links, the links to them, or the
+    /// redirects to them. They are still nodes, tagged as disambiguation pages.
+    fn exclude_disambiguation(&mut self) {
+        let excluded: HashSet<Spur> = self
+            .navigation
+            .iter()
+            .filter(|&(_, &kind)| kind == navigation::Kind::Disambiguation)
+            .map(|(&page, _)| page)
+            .collect();
+        let is_excluded = |&(source, target): &(Spur, Spur)| {
+            excluded.contains(&source) || excluded.contains(&target)
+        };
+        for (source, targets) in &mut self.links {
+            if excluded.contains(source) {
+                targets.clear();
+            } else {
+                targets.retain(|target| !excluded.contains(target));
+            }
+        }
+        self.redirects
+            .retain(|_, target| !excluded.contains(target));
+        self.edge_types.retain(|link, _| !is_excluded(link));
+        self.edge_attributes.retain(|link, _| !is_excluded(link));
+        if let Some(counts) = &mut self.link_counts {
+            counts.retain(|link, _| !is_excluded(link));
+        }
+        tracing::info!("Excluded {} disambiguation pages", excluded.len());
+    }
+
+    /// Replace each link (source, target) of `moved` with (source, resolved). Weights of links
+    /// that end up at the same page add up.
+    fn retarget(&mut self, moved: Vec<(Spur, Spur, Spur)>) {
+        for (source, target, resolved) in moved {
+            let links = self.links.get_mut(&source).unwrap();
+            links.remove(&target);
+            let merged = !links.insert(resolved);
+            if let Some(counts) = &mut self.link_counts {
+                let mut count = counts.remove(&(source, target)).unwrap_or(1);
+                if merged {
+                    count += counts.get(&(source, resolved)).copied().unwrap_or(1);
+                }
+                if count > 1 {
+                    counts.insert((source, resolved), count);
+                }
+            }
+            if let Some(types) = self.edge_types.remove(&(source, target)) {
+                let merged = self.edge_types.entry((source, resolved)).or_default();
+                *merged = merged.union(types);
+            }
+            if let Some(attributes) = self.edge_attributes.remove(&(source, target)) {
+                self.edge_attributes
+                    .entry((source, resolved))
+                    .or_insert(attributes);
+            }
+        }
+    }
+
+    /// Every edge once per layer it belongs to, or once without a type if it has none.
+    fn typed_edges(&self) -> impl Iterator<Item = (Spur, Spur, Option<edge_type::EdgeType>)> + '_ {
+        self.links.iter().flat_map(move |(&source, links)| {
+            links.iter().flat_map(move |&target| {
+                let types: Vec<_> = match self.edge_types.get(&(source, target)) {
+                    Some(types) => types.iter().map(Some).collect(),
+                    None => vec![None],
+                };
+                types
+                    .into_iter()
+                    .map(move |edge_type| (source, target, edge_type))
+            })
+        })
+    }
+
+    /// Whether edges have types, so exports need a column for them.
+    fn is_typed(&self) -> bool {
+        !self.edge_types.is_empty()
+    }
+
+    /// Record the namespace, kind, sort key, and categories of `page`, whose banner-stripped
+    /// wikitext is `text` and which links to `link_count` distinct targets. Pages invoking any of
+    /// `disambiguation_templates` are disambiguation pages, and category links are told apart by
+    /// the namespace names of `profile`.
+    fn add_page_properties(
+        &mut self,
+        profile: &profile::Profile,
+        title: Spur,
+        page: &Page,
+        text: &str,
+        link_count: usize,
+        disambiguation_templates: &[String],
+    ) {
+        self.namespaces.insert(title, page.namespace);
+        if let Some(id) = page.id {
+            self.page_ids.insert(title, id);
+        }
+        // ISO 8601 timestamps in UTC sort as strings do.
+        if let Some(timestamp) = &page.timestamp {
+            if self
+                .timestamps
+                .get(&title)
+                .is_none_or(|latest| latest < timestamp)
+            {
+                self.timestamps.insert(title, timestamp.clone());
+            }
+        }
+        // Later revisions of a history dump replace the kind of earlier ones.
+        match navigation::Kind::detect(
+            &page.title,
+            page.namespace,
+            text,
+            link_count,
+            disambiguation_templates,
+        ) {
+            Some(kind) => self.navigation.insert(title, kind),
+            None => self.navigation.remove(&title),
+        };
+        if let Some(key) = sort::default_sort_key(&page.text) {
+            self.sort_keys.insert(title, String::from(key));
+        }
+        for (category, key) in sort::categories(&page.text, |prefix| profile.is_category(prefix)) {
+            self.add_category(category, title, key.map(String::from));
+        }
+    }
+
+    /// Record that `page` is in `category`, sorted there by `key`.
+    fn add_category(&mut self, category: &str, page: Spur, key: Option<String>) {
+        let category = self.category_names.get_or_intern(category);
+        self.categories.insert((category, page), key);
+    }
+
+    /// The category memberships as (category name, page, explicit sort key).
+    fn category_memberships(&self) -> impl Iterator<Item = (&str, Spur, Option<&str>)> {
+        self.categories.iter().map(|((category, page), key)| {
+            (self.category_names.resolve(category), *page, key.as_deref())
+        })
+    }
+
+    /// The key MediaWiki would sort `page` by: its DEFAULTSORT key, or else its title.
+    fn sort_key<'a>(&'a self, rodeo: &'a Rodeo, page: Spur) -> &'a str {
+        self.sort_keys
+            .get(&page)
+            .map_or_else(|| rodeo.resolve(&page), String::as_str)
+    }
 }
 
 fn main() {
-    tracing_subscriber::fmt::init();
+    let args = Args::parse_from(
+        config::expand(env::args_os().collect())
+            .context("Failed to read config")
+            .unwrap(),
+    );
+    // Pages streamed to standard output would be mixed up with the logs.
+    let logs_to_stderr = matches!(
+        &args.command,
+        Command::Parse(parse) if parse.format.is_some() && parse.output.is_none()
+    );
+    // Tantivy logs every commit and merge at info level.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info,tantivy=warn")),
+        )
+        .with_writer(move || -> Box<dyn io::Write> {
+            if logs_to_stderr {
+                Box::new(io::stderr())
+            } else {
+                Box::new(io::stdout())
+            }
+        })
+        .init();
+
+    match args.command {
+        Command::Parse(args) => parse(&args),
+        Command::Algebra(args) => algebra::run(&args),
+        Command::Backlinks(args) => backlinks::run(&args),
+        Command::Bvgraph(args) => bvgraph::run(&args),
+        Command::Categories(args) => categories::run(&args),
+        Command::Centrality(args) => centrality::run(&args),
+        Command::Check(args) => check::run(&args),
+        Command::Diff(args) => diff::run(&args),
+        Command::Export(args) => export::run(&args),
+        Command::Extract(args) => extract::run(&args),
+        Command::Fetch(args) => {
+            if let Some(command_line) = fetch::run(&args) {
+                let command_line = config::expand(command_line)
+                    .context("Failed to read config")
+                    .unwrap();
+                let Command::Parse(args) = Args::parse_from(command_line).command else {
+                    unreachable!("`fetch` runs `parse`");
+                };
+                parse(&args);
+            }
+        }
+        Command::Fsck(args) => fsck::run(&args),
+        Command::Graphs(args) => workspace::run(&args),
+        Command::Import(args) => import::run(&args),
+        Command::ImportSql(args) => sql_dump::run(&args),
+        Command::Index(args) => index::run(&args),
+        Command::Inventory(args) => inventory::run(&args),
+        Command::Merge(args) => merge::run(&args),
+        Command::Pagerank(args) => pagerank::run(&args),
+        Command::Path(args) => path::run(&args),
+        Command::PathEval(args) => path_eval::run(&args),
+        Command::Poster(args) => poster::run(&args),
+        Command::Profile(args) => page_profile::run(&args),
+        Command::Prune(args) => prune::run(&args),
+        Command::Related(args) => related::run(&args),
+        Command::Repl(args) => repl::run(&args),
+        Command::Report(args) => report::run(&args),
+        Command::Sample(args) => sample::run(&args),
+        Command::Query(args) => query::run(&args),
+        Command::Search(args) => title_search::run(&args),
+        Command::SearchText(args) => search_text(&args),
+        Command::Serve(args) => serve::run(&args),
+        Command::SkipList(args) => skip_list::run(&args),
+        Command::Stats(args) => stats::run(&args),
+        Command::Subgraph(args) => subgraph::run(&args),
+        Command::Top(args) => top::run(&args),
+        Command::Update(args) => update::run(&args),
+        Command::Walks(args) => walks::run(&args),
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn parse(args: &ParseArgs) {
+    let started = Instant::now();
+    red_link::check(args);
+    if args.dry_run {
+        plan::print(args).context("Failed to plan parse").unwrap();
+        return;
+    }
+    if let Some(sums) = &args.verify {
+        verify(args, sums)
+            .context("Failed to verify the dump")
+            .unwrap();
+    }
+    if small::dump(&args.input, args.demo) {
+        tracing::info!("Small dump: parsing on one thread unless told otherwise");
+    }
 
-    let args = Args::parse();
+    let mut rodeo = Rodeo::new();
 
-    let (tx, rx) = flume::unbounded();
+    let mut history = args
+        .snapshots
+        .is_some()
+        .then(|| snapshot::History::new(args.snapshot_interval));
 
-    thread::spawn(move || {
-        let mut xml = read_xml(&args.input)
-            .context("Failed to read XML file")
+    let mut collectors = Collectors::new(args);
+
+    let mut wiki = build_cached(
+        &args.input,
+        args,
+        &mut rodeo,
+        history.as_mut(),
+        &mut collectors,
+    );
+
+    let report = collectors.report.take();
+    collectors.finish(args, &rodeo);
+
+    let inputs = cache::Inputs::new(&args.input, project(args, &args.input), args)
+        .context("Failed to read parse inputs")
+        .unwrap();
+    let metadata = parse_metadata(args, &inputs);
+
+    if args.format.is_some() {
+        // There is no graph to save or export, and standard output may be the stream.
+        write_provenance(args, &metadata);
+        write_report(args, report, started, None, &metadata);
+        return;
+    }
+
+    if let Some(path) = &args.page_props {
+        wikidata::apply(path, &mut wiki)
+            .with_context(|| format!("Failed to read {}", path.display()))
             .unwrap();
+    }
 
-        while let Some(page) = read_page(&mut xml).context("Failed to read page").unwrap() {
-            tx.send(page).unwrap();
+    if let Some(spill) = wiki.spill.take() {
+        println!("{} pages", wiki.namespaces.len());
+        let edges = spill::write(args, &rodeo, &wiki, spill, metadata.clone())
+            .context("Failed to write spilled links")
+            .unwrap();
+        write_provenance(args, &metadata);
+        write_report(args, report, started, Some(edges), &metadata);
+        return;
+    }
+
+    if let Some(path) = &args.partial {
+        cache::save(path, &inputs, &rodeo, &wiki)
+            .context("Failed to save partial result")
+            .unwrap();
+        if args.exclude_disambiguation {
+            wiki.exclude_disambiguation();
         }
+    }
+
+    let (mut rodeo, wiki) =
+        red_link::apply(args.red_links, args.red_link_report.as_deref(), rodeo, wiki)
+            .context("Failed to handle red links")
+            .unwrap();
+    println!("{} pages", wiki.links.len());
+    let edges = wiki
+        .links
+        .values()
+        .map(|targets| targets.len() as u64)
+        .sum();
+
+    if let Some(path) = &args.graph {
+        graph::Graph::new(&rodeo, &wiki, metadata.clone())
+            .and_then(|graph| save_graph(args, graph, path))
+            .context("Failed to save graph")
+            .unwrap();
+    }
+
+    if let Some(path) = &args.category_graph {
+        save_category_graph(args, path, &rodeo, &wiki, &metadata);
+    }
+
+    if let (Some(dir), Some(history)) = (&args.snapshots, &history) {
+        history
+            .write(
+                dir,
+                &rodeo,
+                args.normalization()
+                    .contains(&normalize::Stage::ResolveRedirects),
+                export_threads(args),
+            )
+            .context("Failed to write snapshots")
+            .unwrap();
+    }
+
+    if let (Some(old), Some(report)) = (&args.diff_from, &args.diff_report) {
+        let old = build_cached(old, args, &mut rodeo, None, &mut Collectors::default());
+        let summary = fs::File::create(report)
+            .map_err(anyhow::Error::from)
+            .and_then(|report| diff::write(report, &rodeo, &old, &wiki, args.rename_similarity))
+            .context("Failed to write diff report")
+            .unwrap();
+        println!("{summary}");
+    }
+
+    // Filters only apply to exports; the saved graph keeps the node IDs of the text index.
+    let filtered = (args.node_filter.is_some() || args.edge_filter.is_some()).then(|| {
+        filter::apply(
+            &rodeo,
+            &wiki,
+            args.node_filter.as_ref(),
+            args.edge_filter.as_ref(),
+        )
     });
+    let (rodeo, wiki) = filtered
+        .as_ref()
+        .map_or((&rodeo, &wiki), |(rodeo, wiki)| (rodeo, wiki));
+    export(args, rodeo, wiki, &metadata);
+    write_provenance(args, &metadata);
 
-    let mut rodeo = Rodeo::new();
+    if let Some(file) = &args.checkpoint {
+        checkpoint::remove(file);
+    }
+    write_report(args, report, started, Some(edges), &metadata);
+}
 
-    let mut wiki: HashMap<Spur, HashSet<Spur>> = HashMap::new();
+/// Write the `--report` of a parse started at `started`, whose graph has `edges` links.
+fn write_report(
+    args: &ParseArgs,
+    report: Option<run_report::Report>,
+    started: Instant,
+    edges: Option<u64>,
+    metadata: &graph::Metadata,
+) {
+    if let (Some(path), Some(report)) = (&args.report, report) {
+        report
+            .write(path, started, edges, metadata)
+            .context("Failed to write report")
+            .unwrap();
+    }
+}
 
-    while let Ok(page) = rx.recv() {
-        let title = rodeo.get_or_intern(page.title);
-        let links = links(&page.text).map(|l| rodeo.get_or_intern(l)).collect();
-        if let Some(v) = wiki.get_mut(&title) {
-            v.extend(links);
-        } else {
-            wiki.insert(title, links);
-        }
+/// Save `graph` to `path`, stored as `--compress-graph`, `--graph-codec`, and `--no-backlinks`
+/// say.
+fn save_graph(args: &ParseArgs, mut graph: graph::Graph, path: &Path) -> anyhow::Result<()> {
+    graph.set_compressed(args.compress_graph);
+    graph.set_codec(args.graph_codec);
+    graph.set_store_backlinks(!args.no_backlinks);
+    graph.save(path)
+}
+
+/// Save the graph of the category memberships of `wiki` to `path`.
+fn save_category_graph(
+    args: &ParseArgs,
+    path: &Path,
+    rodeo: &Rodeo,
+    wiki: &Wiki,
+    metadata: &graph::Metadata,
+) {
+    if !args.namespaces.contains(&14) {
+        tracing::warn!(
+            "Category pages are parsed only with `--namespace 14`, so the category graph has no \
+             parent categories"
+        );
     }
+    let (titles, memberships) = categories::graph(rodeo, wiki);
+    let graph = graph::Graph::new(&titles, &memberships, metadata.clone())
+        .and_then(|graph| graph.save(path).map(|()| graph))
+        .context("Failed to save category graph")
+        .unwrap();
+    println!(
+        "{} category memberships of {} pages and categories",
+        graph.edge_count(),
+        graph.node_count()
+    );
+}
 
-    println!("{} pages", wiki.len());
+/// The provenance of the outputs of a parse of `inputs`.
+fn parse_metadata(args: &ParseArgs, inputs: &cache::Inputs) -> graph::Metadata {
+    graph::Metadata {
+        options: Some(
+            provenance::options_hash(inputs, args)
+                .context("Failed to hash parse options")
+                .unwrap(),
+        ),
+        ..graph::Metadata::current(Some(&args.input))
+    }
 }
 
-enum Xml {
-    Raw(quick_xml::Reader<BufReader<File>>),
-    Bzip2(quick_xml::Reader<BufReader<bzip2::read::BzDecoder<File>>>),
-    MultistreamBzip2(quick_xml::Reader<BufReader<bzip2::read::MultiBzDecoder<File>>>),
+/// Write `metadata` beside every file and directory output without room for it inside. The graph
+/// file and the graphology, NumPy, PyTorch Geometric, PostgreSQL, ClickHouse, and SQLite exports
+/// record it themselves.
+fn write_provenance(args: &ParseArgs, metadata: &graph::Metadata) {
+    let outputs = [
+        &args.snapshots,
+        &args.titles,
+        &args.diff_report,
+        &args.link_contexts,
+        &args.page_stream,
+        &args.page_json,
+        &args.anchor_stats,
+        &args.anchor_text,
+        &args.normalization_audit,
+        &args.redirect_report,
+        &args.cooccurrence,
+        &args.template_usage,
+        &args.sort_index,
+        &args.category_index,
+        &args.output,
+        &args.gephi,
+        &args.neo4j,
+        &args.condensed,
+        &args.namespace_partitions,
+    ];
+    // A sharded edge list has one for each shard.
+    let shards = args
+        .output
+        .as_deref()
+        .zip(args.shards)
+        .map(|(path, count)| {
+            (0..count)
+                .map(|index| export::edge_list::shard_path(path, index, count))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let outputs = outputs
+        .into_iter()
+        .flatten()
+        .filter(|path| args.shards.is_none() || Some(*path) != args.output.as_ref())
+        .chain(&shards);
+    for path in outputs {
+        provenance::write_sidecar(path, metadata)
+            .with_context(|| format!("Failed to write provenance of {}", path.display()))
+            .unwrap();
+    }
 }
 
-fn read_xml(path: &PathBuf) -> anyhow::Result<Xml> {
-    if !path.is_file() {
-        anyhow::bail!("Path is not a file");
+/// Threads to serialize the rows of an export on: `--export-threads`, or else one for a small
+/// dump and one per CPU otherwise.
+fn export_threads(args: &ParseArgs) -> usize {
+    small::threads(args.export_threads, small::dump(&args.input, args.demo))
+}
+
+/// Node positions for the graphology export, unless the graph has more than
+/// `--layout-max-nodes` nodes to lay out.
+fn graphology_layout(args: &ParseArgs, rodeo: &Rodeo, wiki: &Wiki) -> Option<Vec<(f64, f64)>> {
+    (rodeo.len() <= args.layout_max_nodes).then(|| {
+        let edges: Vec<(usize, usize)> = wiki
+            .links
+            .iter()
+            .flat_map(|(source, links)| {
+                links
+                    .iter()
+                    .map(|target| (source.into_usize(), target.into_usize()))
+            })
+            .collect();
+        layout::force_atlas2(rodeo.len(), &edges, args.layout_iterations)
+    })
+}
+
+/// Write the exports computed from the finished graph, recording `metadata` in those with
+/// room for it. The file exports are written side by side, with the edge list, Gephi, and Neo4j
+/// rows also serialized on up to `--export-threads` threads each.
+#[allow(clippy::too_many_lines)]
+fn export(args: &ParseArgs, rodeo: &Rodeo, wiki: &Wiki, metadata: &graph::Metadata) {
+    let threads = export_threads(args);
+    thread::scope(|scope| {
+        if let Some(path) = &args.sort_index {
+            scope.spawn(|| {
+                export::sort_index::write(path, rodeo, wiki)
+                    .context("Failed to write sort index")
+                    .unwrap();
+            });
+        }
+
+        if let Some(path) = &args.category_index {
+            scope.spawn(|| {
+                export::sort_index::write_categories(path, rodeo, wiki)
+                    .context("Failed to write category index")
+                    .unwrap();
+            });
+        }
+
+        if let Some(path) = &args.output {
+            scope.spawn(|| {
+                export::edge_list::write(
+                    path,
+                    rodeo,
+                    wiki,
+                    args.output_format,
+                    args.shards,
+                    threads,
+                )
+                .context("Failed to write edge list")
+                .unwrap();
+            });
+        }
+
+        if let Some(dir) = &args.gephi {
+            scope.spawn(|| {
+                export::gephi::write(dir, rodeo, wiki, threads)
+                    .context("Failed to write Gephi export")
+                    .unwrap();
+            });
+        }
+
+        if let Some(dir) = &args.neo4j {
+            scope.spawn(|| {
+                export::neo4j::write(dir, rodeo, wiki, threads)
+                    .context("Failed to write Neo4j export")
+                    .unwrap();
+            });
+        }
+
+        if let Some(dir) = &args.condensed {
+            scope.spawn(|| {
+                export::condensed::write(
+                    dir,
+                    rodeo,
+                    wiki,
+                    &args.condense_category,
+                    args.condense_members_only,
+                )
+                .context("Failed to write condensed export")
+                .unwrap();
+            });
+        }
+
+        if let Some(path) = &args.graphology {
+            scope.spawn(|| {
+                let layout = graphology_layout(args, rodeo, wiki);
+                export::graphology::write(path, rodeo, wiki, layout.as_deref(), metadata)
+                    .context("Failed to write graphology export")
+                    .unwrap();
+            });
+        }
+
+        if let Some(dir) = &args.npy {
+            scope.spawn(|| {
+                export::npy::write(dir, rodeo, wiki, metadata)
+                    .context("Failed to write NumPy export")
+                    .unwrap();
+            });
+        }
+
+        if let Some(dir) = &args.pyg {
+            scope.spawn(|| {
+                export::pyg::write(dir, rodeo, wiki, metadata)
+                    .context("Failed to write PyTorch Geometric export")
+                    .unwrap();
+            });
+        }
+
+        if let Some(dir) = &args.namespace_partitions {
+            scope.spawn(|| {
+                export::partitions::write(dir, rodeo, wiki)
+                    .context("Failed to write namespace partitions")
+                    .unwrap();
+            });
+        }
+    });
+
+    #[cfg(feature = "postgres")]
+    args.postgres
+        .write(rodeo, wiki, metadata)
+        .context("Failed to write to PostgreSQL")
+        .unwrap();
+
+    #[cfg(feature = "clickhouse")]
+    args.clickhouse
+        .write(rodeo, wiki, metadata)
+        .context("Failed to write to ClickHouse")
+        .unwrap();
+
+    #[cfg(feature = "sqlite")]
+    args.sqlite
+        .write(rodeo, wiki, metadata)
+        .context("Failed to write SQLite database")
+        .unwrap();
+}
+
+fn search_text(args: &SearchTextArgs) {
+    let graph = graph::Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let searcher = text_index::Searcher::open(&text_index::path_for(&args.graph))
+        .context("Failed to open text index")
+        .unwrap();
+
+    let within = args.near.as_ref().map(|title| {
+        let start = graph
+            .id(title)
+            .with_context(|| format!("No page titled '{title}' in the graph"))
+            .unwrap();
+        graph
+            .within(
+                start,
+                args.hops,
+                args.direction,
+                |_| true,
+                &cancel::Cancel::never(),
+            )
+            .unwrap()
+    });
+
+    let hits = searcher
+        .search(&args.query, within.as_ref(), args.limit)
+        .context("Failed to search text index")
+        .unwrap();
+    let (hits, stale): (Vec<_>, Vec<_>) = hits.into_iter().partition(|hit| hit.is_in(&graph));
+    if !stale.is_empty() {
+        tracing::warn!(
+            "Leaving out {} hits for pages the graph doesn't have under their node IDs; the text \
+             index is older than the graph, so save them again together",
+            stale.len()
+        );
+    }
+    for hit in hits {
+        println!(
+            "{:.3}\t{}\tin={} out={}",
+            hit.score,
+            hit.title,
+            graph.in_degree(hit.node),
+            graph.out_degree(hit.node)
+        );
     }
+}
 
-    let file_name = path
-        .file_name()
-        .context("Could not get file name from path")?
-        .to_str()
-        .context("File name is not valid UTF-8")?;
+/// Check the input, and its index if `sums` lists it, against the checksums in `sums`.
+fn verify(args: &ParseArgs, sums: &Path) -> anyhow::Result<()> {
+    let input = &args.input;
+    anyhow::ensure!(
+        input != Path::new("-") && !remote::is_url(input),
+        "`--verify` needs the dump as a local file"
+    );
+    let (algorithm, digests) =
+        checksum::read(sums).with_context(|| format!("Failed to read {}", sums.display()))?;
+    let listed = |path: &Path| {
+        let name = path.file_name()?.to_str()?;
+        digests.get(name)
+    };
+    let expected = listed(input).with_context(|| {
+        format!(
+            "{} lists no checksum for {}; is it the checksums file of this dump?",
+            sums.display(),
+            input.display()
+        )
+    })?;
+    let index = args.index.clone().or_else(|| dump::index_path(input));
+    let index = index.and_then(|index| Some((listed(&index)?, index)));
+    for (expected, path) in [(expected, input.clone())].into_iter().chain(index) {
+        tracing::info!(
+            "Checking {} against its {} checksum",
+            path.display(),
+            algorithm.name()
+        );
+        checksum::verify(&path, algorithm, expected)?;
+    }
+    Ok(())
+}
 
-    let mut file_name = String::from(file_name);
-    file_name.make_ascii_lowercase();
+/// `build`, reusing or refreshing the `--cache` entry for the dump when one is configured. A
+/// cached graph is only used when no history or collector needs the pages.
+fn build_cached(
+    path: &Path,
+    args: &ParseArgs,
+    rodeo: &mut Rodeo,
+    history: Option<&mut snapshot::History>,
+    collectors: &mut Collectors,
+) -> Wiki {
+    let Some(dir) = &args.cache else {
+        return build(path, args, rodeo, history, collectors);
+    };
+    if path == Path::new("-") || remote::is_url(path) {
+        tracing::warn!("Not caching a parse of standard input or a URL");
+        return build(path, args, rodeo, history, collectors);
+    }
+    let inputs = cache::Inputs::new(path, project(args, path), args)
+        .context("Failed to read parse inputs")
+        .unwrap();
+    let cached = cache::path_for(dir, path, args.shard);
 
-    if file_name.ends_with("multistream.xml.bz2") {
-        tracing::debug!("Reading '{}' as multistream bzip2 XML", path.display());
-        let file = File::open(path)?;
-        let bzip2_decoder = bzip2::read::MultiBzDecoder::new(file);
-        let buf_reader = BufReader::new(bzip2_decoder);
-        let xml_reader = quick_xml::Reader::from_reader(buf_reader);
-        Ok(Xml::MultistreamBzip2(xml_reader))
-    } else if file_name.ends_with(".xml.bz2") {
-        tracing::debug!("Reading '{}' as bzip2 XML", path.display());
-        let file = File::open(path)?;
-        let bzip2_decoder = bzip2::read::BzDecoder::new(file);
-        let buf_reader = BufReader::new(bzip2_decoder);
-        let xml_reader = quick_xml::Reader::from_reader(buf_reader);
-        Ok(Xml::Bzip2(xml_reader))
-    } else {
-        tracing::debug!("Reading '{}' as raw XML", path.display());
-        let xml_reader = quick_xml::Reader::from_file(path)?;
-        Ok(Xml::Raw(xml_reader))
+    if history.is_none() && collectors.is_empty() {
+        match cache::load(&cached, &inputs, rodeo) {
+            Ok(Some(wiki)) => {
+                tracing::info!("Loaded parsed graph from {}", cached.display());
+                return wiki;
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::warn!("Ignoring unreadable cache {}: {error:#}", cached.display());
+            }
+        }
     }
+
+    let wiki = build(path, args, rodeo, history, collectors);
+    cache::save(&cached, &inputs, rodeo, &wiki)
+        .context("Failed to save cache")
+        .unwrap();
+    wiki
+}
+
+/// Parse the dump at `path` into a link graph. With `history`, every revision is recorded there
+/// and the graph reflects each page's newest revision. Pages without a `<redirect>` element
+/// are still redirects if their text starts with `#REDIRECT [[Target]]`. At most
+/// `--channel-capacity` read pages wait to be parsed. Parsing the input with `--checkpoint`
+/// saves a checkpoint every `--checkpoint-interval`, and starts from the last one with
+/// `--resume`.
+fn build(
+    path: &Path,
+    args: &ParseArgs,
+    rodeo: &mut Rodeo,
+    mut history: Option<&mut snapshot::History>,
+    collectors: &mut Collectors,
+) -> Wiki {
+    let progress = Progress::start(!args.no_progress);
+    let _metrics = metrics::Metrics::start(
+        &progress,
+        args.metrics_listen.as_deref(),
+        args.metrics_interval,
+    )
+    .context("Failed to start reporting metrics")
+    .unwrap();
+    let malformed = Arc::clone(progress.malformed());
+    let profile = &link_profile(args, path);
+
+    let extractor = Extractor::new(args, profile, path, &progress);
+
+    // Only the input is checkpointed, not the older dump of `--diff-from`.
+    let checkpoint_file = args.checkpoint.as_deref().filter(|_| path == args.input);
+    let inputs = checkpoint_file.map(|_| {
+        cache::Inputs::new(path, project(args, path), args)
+            .context("Failed to read parse inputs")
+            .unwrap()
+    });
+    let (position, mut wiki) = match (checkpoint_file, &inputs) {
+        (Some(file), Some(inputs)) if args.resume => {
+            resume(file, inputs, rodeo, collectors).unwrap_or_default()
+        }
+        _ => Default::default(),
+    };
+    if let Some(limit) = args.memory_limit {
+        let dir = args.spill_dir.clone().unwrap_or_else(env::temp_dir);
+        wiki.spill = Some(
+            spill::Spill::create(&dir, limit)
+                .context("Failed to start spilling links")
+                .unwrap(),
+        );
+    }
+
+    let interval = Duration::from_secs(args.checkpoint_interval);
+    let mut reading = Some(Reading::new(path, args, &progress, &malformed, position));
+    while let Some(current) = reading.take() {
+        let until = checkpoint_file.map(|_| Instant::now() + interval);
+        let (tx, rx) = flume::bounded(args.channel_capacity as usize);
+        thread::scope(|scope| {
+            let reader = scope.spawn(move || current.read(&tx, until));
+            for extracted in extract_pages(scope, &extractor, &rx) {
+                let extracted = extracted.unwrap_or_else(|payload| panic::resume_unwind(payload));
+                progress.built(rx.len());
+                add_extracted(
+                    &extractor,
+                    rodeo,
+                    &mut wiki,
+                    history.as_deref_mut(),
+                    collectors,
+                    extracted,
+                );
+            }
+            reading = reader
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload));
+        });
+        if let (Some(reading), Some(file), Some(inputs)) = (&reading, checkpoint_file, &inputs) {
+            checkpoint::save(file, inputs, reading.position, rodeo, &wiki)
+                .context("Failed to save checkpoint")
+                .unwrap();
+            tracing::info!(
+                "Saved a checkpoint to {} after {} pages",
+                file.display(),
+                reading.position.read
+            );
+        }
+    }
+
+    if let Some(history) = history {
+        // Pages that were redirects in older revisions only don't redirect any more.
+        (wiki.links, wiki.redirects) = history.latest();
+    }
+    wiki.resolve_targets(rodeo, profile);
+    if let Some(mut spill) = wiki.spill.take() {
+        spill.set_targets(wiki.link_targets(rodeo, profile, args.exclude_disambiguation));
+        wiki.spill = Some(spill);
+    }
+    // A partial result keeps them for `merge`, which also knows the links to them from other
+    // shards.
+    if args.exclude_disambiguation && args.partial.is_none() {
+        wiki.exclude_disambiguation();
+    }
+    if let Some(skip_list) = &extractor.skip_list {
+        skip_list.report();
+    }
+    let malformed = malformed.load(Ordering::Relaxed);
+    if malformed > 0 {
+        tracing::warn!("Skipped {malformed} malformed pages of {}", path.display());
+    }
+    if let Some(report) = &mut collectors.report {
+        report.add_reading(progress.pages(), malformed);
+    }
+
+    wiki
 }
 
-#[derive(Debug)]
-struct Page {
-    title: String,
-    text: String,
+/// The position and graph of the checkpoint at `file` for a parse of `inputs`, if there is one.
+fn resume(
+    file: &Path,
+    inputs: &cache::Inputs,
+    rodeo: &mut Rodeo,
+    collectors: &Collectors,
+) -> Option<(checkpoint::Position, Wiki)> {
+    let Some((position, wiki)) = checkpoint::load(file, inputs, rodeo)
+        .with_context(|| format!("Failed to load checkpoint {}", file.display()))
+        .unwrap()
+    else {
+        tracing::info!(
+            "No checkpoint at {}, so parsing from the start",
+            file.display()
+        );
+        return None;
+    };
+    tracing::info!(
+        "Resuming from {} after {} pages",
+        file.display(),
+        position.read
+    );
+    if !collectors.is_empty() {
+        tracing::warn!("Outputs written while parsing only cover the pages after the checkpoint");
+    }
+    Some((position, wiki))
 }
 
-fn read_page(xml: &mut Xml) -> anyhow::Result<Option<Page>> {
-    #[derive(Debug)]
-    enum State {
-        Limbo1,
-        TitleStarted,
-        Title { title: String },
-        Limbo2 { title: String },
-        TextStarted { title: String },
-        Text { title: String, text: String },
+/// Add the links and properties of a parsed page to `wiki`, or with `history`, record its
+/// revision there.
+fn add_extracted(
+    extractor: &Extractor,
+    rodeo: &mut Rodeo,
+    wiki: &mut Wiki,
+    history: Option<&mut snapshot::History>,
+    collectors: &mut Collectors,
+    extracted: Extracted,
+) {
+    let Extracted {
+        page,
+        stripped,
+        targets,
+        output,
+        audit,
+    } = extracted;
+    let (args, profile) = (extractor.args, extractor.profile);
+    let text = stripped.as_deref().unwrap_or(&page.text);
+    if let (Some(all), Some(audit)) = (&mut collectors.audit, audit) {
+        all.merge(audit);
+    }
+    let title = rodeo.get_or_intern(&page.title);
+    collectors.add_page(profile, rodeo, &page, title, text, &targets);
+    if args.format.is_some() {
+        // The page has gone out with its links, which aren't kept for a graph.
+        return;
+    }
+    let links = if args.edge_types.is_empty() {
+        targets.iter().map(|l| rodeo.get_or_intern(l)).collect()
+    } else {
+        wiki.add_typed_links(profile, rodeo, &args.edge_types, &page, text, &targets)
+    };
+    if let Some(output) = output {
+        wiki.add_script_output(rodeo, title, &links, output);
+    }
+    if args.link_offsets {
+        wiki.add_link_offsets(profile, rodeo, title, &page, &links);
+    }
+    if args.link_origins {
+        let origins = origin::origins(profile, &extractor.rules, text);
+        wiki.add_link_origins(profile, rodeo, title, origins, &links);
+    }
+    if args.edge_timestamps {
+        wiki.add_edge_timestamps(title, &page, &links);
+    }
+    if args.edge_weights {
+        wiki.add_link_counts(rodeo, title, &targets);
     }
+    wiki.add_page_properties(
+        profile,
+        title,
+        &page,
+        text,
+        links.len(),
+        &extractor.disambiguation_templates,
+    );
+    if let Some(redirect) = &page.redirect {
+        if let Some(audit) = &mut collectors.audit {
+            audit.add(&page.title, &page.title, redirect, audit::Reason::Redirect);
+        }
+        wiki.redirects.insert(title, rodeo.get_or_intern(redirect));
+    }
+    if let Some(history) = history {
+        let redirect = page
+            .redirect
+            .as_deref()
+            .map(|redirect| rodeo.get_or_intern(redirect));
+        let Some(timestamp) = &page.timestamp else {
+            tracing::warn!("Skipping revision of '{}' without timestamp", page.title);
+            return;
+        };
+        history
+            .record(title, timestamp, links, redirect)
+            .context("Failed to record revision")
+            .unwrap();
+    } else if let Some(spill) = &mut wiki.spill {
+        spill
+            .add(title, links)
+            .context("Failed to spill links")
+            .unwrap();
+    } else if let Some(v) = wiki.links.get_mut(&title) {
+        v.extend(links);
+    } else {
+        wiki.links.insert(title, links);
+    }
+}
 
-    let mut buffer = Vec::new();
-    let mut state = State::Limbo1;
+/// Streams of an indexed dump read at once between checkpoints, which can only be made between
+/// streams.
+const CHECKPOINT_STREAMS: usize = 64;
 
-    loop {
-        let event = (match xml {
-            Xml::Raw(xml) => xml.read_event_into(&mut buffer),
-            Xml::Bzip2(xml) => xml.read_event_into(&mut buffer),
-            Xml::MultistreamBzip2(xml) => xml.read_event_into(&mut buffer),
-        })
-        .context("Failed to read XML event")?;
+/// Reading the pages in `--namespace` of the dump at `path`, or of one shard of it, on other
+/// threads: one per stream up to `--threads` when the dump is indexed, or else one for the
+/// whole file. Unless `--strict`, malformed pages are skipped and counted in `malformed`. Of
+/// full-history dumps, only the newest revision of each page is read, unless every revision is
+/// needed for `--snapshots`. Only the pages selected by `--skip-pages`, `--max-pages`, and
+/// `--sample` are sent on, and reading stops at `--max-pages`.
+///
+/// The dump is read in runs, each on a thread of its own that hands this back if it stopped
+/// before the end, so that no page is being parsed while a checkpoint is saved.
+struct Reading {
+    input: PathBuf,
+    shard: Option<shard::Shard>,
+    configure: Arc<dyn Fn(Pages) -> Pages + Send + Sync>,
+    threads: usize,
+    bytes_read: Arc<AtomicU64>,
+    remote: remote::Options,
+    selection: Option<Selection>,
+    /// The streams not read yet, if the dump is indexed.
+    ranges: Option<VecDeque<Range<u64>>>,
+    /// The pages of a dump read without an index, once it is opened.
+    pages: Option<Pages>,
+    position: checkpoint::Position,
+}
 
-        state = match (state, event) {
-            (State::Limbo1, Event::Eof) => {
-                return Ok(None);
+/// Why pages stopped being sent on.
+enum Stopped {
+    /// `--max-pages` were.
+    Done,
+    /// A checkpoint is due.
+    Due,
+    /// There were no more.
+    Exhausted,
+}
+
+impl Reading {
+    /// Start reading at `position`, setting the total and counting the bytes read of `progress`.
+    fn new(
+        path: &Path,
+        args: &ParseArgs,
+        progress: &Progress,
+        malformed: &Arc<AtomicU64>,
+        position: checkpoint::Position,
+    ) -> Self {
+        let shard = args.shard;
+        let namespaces = args.namespaces.clone();
+        let malformed = (!args.strict).then(|| Arc::clone(malformed));
+        let latest = args.snapshots.is_none();
+        let configure = move |pages: Pages| {
+            let mut pages = pages.in_namespaces(namespaces.clone());
+            if let Some(malformed) = &malformed {
+                pages = pages.lenient(Arc::clone(malformed));
             }
-            (State::Limbo1, Event::Start(data)) if data.name().into_inner() == b"title" => {
-                State::TitleStarted
+            if latest {
+                pages = pages.latest_revisions();
             }
-            (limbo1 @ State::Limbo1, _) => limbo1,
-            (State::TitleStarted, Event::Text(data)) => {
-                let title = data.unescape()?.into_owned();
-                State::Title { title }
+            pages
+        };
+        // `--index` describes the input, not the older dump of `--diff-from`.
+        let index = (path == args.input)
+            .then(|| args.index.clone())
+            .flatten()
+            .or_else(|| dump::index_path(path));
+        let ranges = index.map(|index| {
+            tracing::info!("Reading streams listed in '{}'", index.display());
+            let mut ranges = dump::stream_ranges(path, &index, shard)
+                .context("Failed to read dump index")
+                .unwrap();
+            if let Some(offset) = position.offset {
+                ranges.retain(|range| range.start >= offset);
             }
-            (State::Title { title }, Event::End(data)) if data.name().into_inner() == b"title" => {
-                State::Limbo2 { title }
+            VecDeque::from(ranges)
+        });
+        let total = match (&ranges, shard) {
+            (Some(ranges), _) => Some(ranges.iter().map(|range| range.end - range.start).sum()),
+            (None, Some(shard)) => shard.range(path).ok().map(|range| range.end - range.start),
+            (None, None) => fs::metadata(path).ok().map(|metadata| metadata.len()),
+        };
+        if let Some(total) = total {
+            progress.set_total(total);
+        }
+        Self {
+            input: path.to_path_buf(),
+            shard,
+            configure: Arc::new(configure),
+            threads: small::threads(args.threads, small::dump(&args.input, args.demo)),
+            bytes_read: Arc::clone(progress.bytes_read()),
+            remote: remote_options(args),
+            selection: Selection::new(args),
+            ranges,
+            pages: None,
+            position,
+        }
+    }
+
+    /// Send pages to `tx` until the end of the dump, or if a checkpoint is due at `until`, until
+    /// then, and return the reading left if there is any.
+    fn read(mut self, tx: &flume::Sender<Page>, until: Option<Instant>) -> Option<Self> {
+        if let Some(mut ranges) = self.ranges.take() {
+            if self.selection.is_none() && until.is_none() {
+                let configure = Arc::clone(&self.configure);
+                dump::read_streams(
+                    &self.input,
+                    ranges.into(),
+                    move |pages| configure(pages),
+                    self.threads,
+                    tx,
+                    &self.bytes_read,
+                )
+                .context("Failed to read dump streams")
+                .unwrap();
+                return None;
             }
-            (State::Limbo2 { title }, Event::Start(data))
-                if data.name().into_inner() == b"text" =>
-            {
-                State::TextStarted { title }
+            while !ranges.is_empty() {
+                let count = if until.is_some() {
+                    ranges.len().min(CHECKPOINT_STREAMS)
+                } else {
+                    ranges.len()
+                };
+                let batch = ranges.drain(..count).collect();
+                if let Stopped::Done = self.read_streams(batch, tx) {
+                    return None;
+                }
+                if until.is_some_and(|until| Instant::now() >= until) {
+                    if let Some(next) = ranges.front() {
+                        self.position.offset = Some(next.start);
+                        self.ranges = Some(ranges);
+                        return Some(self);
+                    }
+                }
             }
-            (limbo2 @ State::Limbo2 { .. }, _) => limbo2,
-            (State::TextStarted { title }, Event::Text(data)) => {
-                let text = data.unescape()?.into_owned();
-                State::Text { title, text }
+            return None;
+        }
+
+        let mut pages = self.pages.take().unwrap_or_else(|| self.open());
+        let pages_read =
+            std::iter::from_fn(|| pages.next_page().context("Failed to read page").unwrap());
+        match self.forward(pages_read, tx, until) {
+            Stopped::Done | Stopped::Exhausted => None,
+            Stopped::Due => {
+                self.pages = Some(pages);
+                Some(self)
             }
-            (State::Text { title, text }, Event::End(data))
-                if data.name().into_inner() == b"text" =>
+        }
+    }
+
+    /// Send the pages of the streams at `ranges` to `tx`, decompressed and parsed in parallel.
+    fn read_streams(&mut self, ranges: Vec<Range<u64>>, tx: &flume::Sender<Page>) -> Stopped {
+        let (input, threads) = (self.input.clone(), self.threads);
+        let bytes_read = Arc::clone(&self.bytes_read);
+        let configure = Arc::clone(&self.configure);
+        let configure = move |pages| configure(pages);
+        // The streams' pages come in dump order, so they are counted as from one reader.
+        let (streams_tx, streams_rx) = flume::bounded(tx.capacity().unwrap_or(1));
+        let streams = thread::spawn(move || {
+            dump::read_streams(&input, ranges, configure, threads, &streams_tx, &bytes_read)
+        });
+        let stopped = self.forward(streams_rx.into_iter(), tx, None);
+        // At `--max-pages`, dropping the receiver stops the streams, failing their send.
+        let result = streams.join().unwrap();
+        if !matches!(stopped, Stopped::Done) {
+            result.context("Failed to read dump streams").unwrap();
+        }
+        stopped
+    }
+
+    /// The pages of the dump read without an index, with those read before the checkpoint it
+    /// started from skipped.
+    fn open(&self) -> Pages {
+        let xml = read_xml_counted(&self.input, self.shard, &self.bytes_read, &self.remote)
+            .context("Failed to read XML file")
+            .unwrap();
+        let mut pages = (self.configure)(Pages::new(xml));
+        if self.position.read > 0 {
+            tracing::info!(
+                "Skipping the {} pages read before the checkpoint",
+                self.position.read
+            );
+            for _ in 0..self.position.read {
+                if pages
+                    .next_page()
+                    .context("Failed to read page")
+                    .unwrap()
+                    .is_none()
+                {
+                    break;
+                }
+            }
+        }
+        pages
+    }
+
+    /// Send the selected `pages` to `tx`, stopping at `--max-pages`, at the end, or at the first
+    /// page sent after `until`.
+    fn forward(
+        &mut self,
+        pages: impl Iterator<Item = Page>,
+        tx: &flume::Sender<Page>,
+        until: Option<Instant>,
+    ) -> Stopped {
+        let done = |selection: &Option<Selection>, kept| {
+            selection
+                .as_ref()
+                .is_some_and(|selection| selection.done(kept))
+        };
+        if done(&self.selection, self.position.kept) {
+            return Stopped::Done;
+        }
+        for page in pages {
+            let index = self.position.read;
+            self.position.read += 1;
+            if !self
+                .selection
+                .as_ref()
+                .is_none_or(|selection| selection.keeps(index, &page))
             {
-                return Ok(Some(Page { title, text }));
+                continue;
             }
-            (state, event) => {
-                anyhow::bail!(
-                    "Unexpected event in current state\nstate: {state:?}\nevent: {event:?}"
-                );
+            self.position.kept += 1;
+            tx.send(page).unwrap();
+            if done(&self.selection, self.position.kept) {
+                return Stopped::Done;
             }
-        };
+            if until.is_some_and(|until| Instant::now() >= until) {
+                return Stopped::Due;
+            }
+        }
+        Stopped::Exhausted
+    }
+}
+
+/// Which pages of the dump to parse, with `--skip-pages`, `--max-pages`, or `--sample`.
+struct Selection {
+    skip: u64,
+    max: Option<u64>,
+    sample: Option<f64>,
+    seed: u64,
+}
+
+impl Selection {
+    fn new(args: &ParseArgs) -> Option<Self> {
+        (args.skip_pages.is_some() || args.max_pages.is_some() || args.sample.is_some()).then(
+            || Self {
+                skip: args.skip_pages.unwrap_or(0),
+                max: args.max_pages,
+                sample: args.sample,
+                seed: args.seed,
+            },
+        )
+    }
 
-        buffer.clear();
+    /// Whether to parse `page`, the one at `index` of the pages read.
+    fn keeps(&self, index: u64, page: &Page) -> bool {
+        index >= self.skip
+            && self
+                .sample
+                .is_none_or(|p| sample::keep_page(self.seed, p, &page.title))
+    }
+
+    /// Whether `kept` pages are all `--max-pages` wants.
+    fn done(&self, kept: u64) -> bool {
+        self.max.is_some_and(|max| kept >= max)
     }
 }
 
-fn links(haystack: &str) -> impl Iterator<Item = &str> {
-    static REGEX: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r"(?:\[\[)([^\[\]]+?)(?:\|[^\[\]]*)?(?:\]\])").unwrap());
+/// How to download the dump if it is given by URL.
+#[cfg(feature = "http")]
+fn remote_options(args: &ParseArgs) -> remote::Options {
+    remote::Options {
+        retries: args.retries,
+        max_bandwidth: args.max_bandwidth,
+        spool: args.spool.clone(),
+    }
+}
 
-    REGEX
-        .captures_iter(haystack)
-        .map(|capture| capture.get(1).unwrap().as_str())
+#[cfg(not(feature = "http"))]
+fn remote_options(_args: &ParseArgs) -> remote::Options {
+    remote::Options::default()
+}
+
+/// A parsed page as the extraction threads hand it to the graph builder.
+struct Extracted {
+    page: Page,
+    /// Its banner-stripped wikitext, if stripping banners changed it.
+    stripped: Option<String>,
+    targets: Vec<Cow<'static, str>>,
+    output: Option<script::Output>,
+    /// The rewrites of its link targets, with `--normalization-audit`.
+    audit: Option<audit::Audit>,
+}
+
+/// What the extraction threads share to find the links of pages.
+struct Extractor<'a> {
+    args: &'a ParseArgs,
+    profile: &'a profile::Profile,
+    rules: rules::Rules,
+    script: Option<script::Hook>,
+    skip_list: Option<skip_list::SkipList>,
+    disambiguation_templates: Vec<String>,
+    progress: &'a Progress,
+}
+
+impl<'a> Extractor<'a> {
+    /// Find links as `args` say for the dump at `path`, with the names of `profile`.
+    fn new(
+        args: &'a ParseArgs,
+        profile: &'a profile::Profile,
+        path: &Path,
+        progress: &'a Progress,
+    ) -> Self {
+        Self {
+            args,
+            profile,
+            rules: link_rules(args),
+            script: script_hook(args),
+            skip_list: args.skip_list.as_deref().map(|file| {
+                skip_list::SkipList::load(file, path)
+                    .with_context(|| format!("Failed to read {}", file.display()))
+                    .unwrap()
+            }),
+            disambiguation_templates: disambiguation_templates(args, path),
+            progress,
+        }
+    }
+
+    /// `page` with its redirect detected and its links found, or `None` if it is left out:
+    /// outside `--namespace`, skip-listed, too large, dropped by the script, or failing with a
+    /// skip list.
+    fn extract(&self, mut page: Page) -> Option<Extracted> {
+        let args = self.args;
+        self.progress.page();
+        if !args.namespaces.contains(&page.namespace)
+            || self
+                .skip_list
+                .as_ref()
+                .is_some_and(|list| list.skips(&page.title))
+        {
+            return None;
+        }
+        if page.redirect.is_none() {
+            page.redirect = dump::redirect_target(&page.text)
+                .and_then(|target| self.profile.resolve(&page.title, target))
+                .map(Cow::into_owned);
+        }
+        if !within_size_limit(args, &mut page) {
+            return None;
+        }
+        let mut audit = args
+            .normalization_audit
+            .is_some()
+            .then(audit::Audit::default);
+        let examined = skip_list::attempt(self.skip_list.as_ref(), &page.title, || {
+            let text = self.profile.strip_banners(&page.text);
+            let script = self.script.as_ref();
+            let audit = audit.as_mut();
+            let examined = examine(args, self.profile, &self.rules, script, &page, &text, audit)?;
+            let Some((targets, output)) = examined else {
+                return Ok(None);
+            };
+            // The targets borrow from the text, which goes on with the page.
+            let targets: Vec<_> = targets
+                .into_iter()
+                .map(|target| Cow::Owned(target.into_owned()))
+                .collect();
+            let stripped = match text {
+                Cow::Owned(text) => Some(text),
+                Cow::Borrowed(_) => None,
+            };
+            Ok(Some((stripped, targets, output)))
+        });
+        let (stripped, targets, output) = examined??;
+        Some(Extracted {
+            page,
+            stripped,
+            targets,
+            output,
+            audit,
+        })
+    }
+}
+
+/// Find the links of the pages from `pages` on `--extract-threads` threads of `scope`, passing
+/// them on in whatever order they finish. A panic on one of them is passed on too, for the
+/// builder to resume, so that the parse ends at once rather than once the other threads are
+/// through the dump.
+fn extract_pages<'scope>(
+    scope: &'scope thread::Scope<'scope, '_>,
+    extractor: &'scope Extractor,
+    pages: &flume::Receiver<Page>,
+) -> flume::Receiver<thread::Result<Extracted>> {
+    let args = extractor.args;
+    let (tx, rx) = flume::bounded(args.channel_capacity as usize);
+    let threads = small::threads(args.extract_threads, small::dump(&args.input, args.demo));
+    for _ in 0..threads {
+        let (pages, tx) = (pages.clone(), tx.clone());
+        scope.spawn(move || {
+            for page in pages {
+                let extracted = panic::catch_unwind(AssertUnwindSafe(|| extractor.extract(page)));
+                let Some(extracted) = extracted.transpose() else {
+                    continue;
+                };
+                // The builder only stops receiving when it panics itself.
+                if tx.send(extracted).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+    rx
+}
+
+/// A page's link targets, and the attributes `--script` gives it.
+type Examined<'a> = (Vec<Cow<'a, str>>, Option<script::Output>);
+
+/// The link targets of `page`, whose banner-stripped wikitext is `text`, and the attributes
+/// `script` gives it, or `None` if the script drops the page.
+fn examine<'a>(
+    args: &ParseArgs,
+    profile: &profile::Profile,
+    rules: &rules::Rules,
+    script: Option<&script::Hook>,
+    page: &Page,
+    text: &'a str,
+    audit: Option<&mut audit::Audit>,
+) -> anyhow::Result<Option<Examined<'a>>> {
+    let targets = link_targets(args, profile, rules, page, text, audit);
+    let Some(script) = script else {
+        return Ok(Some((targets, None)));
+    };
+    let names: Vec<&str> = targets.iter().map(AsRef::as_ref).collect();
+    let output = script
+        .page(&page.title, page.namespace, text, &names)
+        .with_context(|| format!("Script failed on '{}'", page.title))?;
+    Ok(output.map(|output| (targets, Some(output))))
+}
+
+/// The resolved targets of the links and link rules found in `text`, the banner-stripped
+/// wikitext of `page`, minus ignored and sampled-out ones and those past `--max-links`. Rewrites
+/// are recorded in `audit`.
+fn link_targets<'a>(
+    args: &ParseArgs,
+    profile: &profile::Profile,
+    rules: &rules::Rules,
+    page: &Page,
+    text: &'a str,
+    mut audit: Option<&mut audit::Audit>,
+) -> Vec<Cow<'a, str>> {
+    let mut targets: Vec<_> = links(text)
+        .map(|link| link.target)
+        .chain(profile.template_links(text))
+        .chain(rules.links(text))
+        .filter_map(|l| {
+            profile.resolve_traced(&page.title, l, |from, to, reason| {
+                if let Some(audit) = audit.as_deref_mut() {
+                    audit.add(&page.title, from, to, reason);
+                }
+            })
+        })
+        .filter(|l| !rules.ignores(l))
+        .filter(|l| {
+            args.edge_sample
+                .is_none_or(|p| sample::keep_edge(args.seed, p, &page.title, l))
+        })
+        .collect();
+    if let Some(max) = args.max_links {
+        let distinct = guard::cap_links(&mut targets, max);
+        if distinct > max {
+            tracing::warn!(
+                "Keeping {max} of the {distinct} link targets of '{}'",
+                page.title
+            );
+        }
+    }
+    targets
+}
+
+/// The rules of `--link-rules`, or none.
+fn link_rules(args: &ParseArgs) -> rules::Rules {
+    args.link_rules
+        .as_deref()
+        .map_or_else(rules::Rules::default, |path| {
+            rules::Rules::load(path)
+                .context("Failed to load link rules")
+                .unwrap()
+        })
+}
+
+fn script_hook(args: &ParseArgs) -> Option<script::Hook> {
+    args.script.as_deref().map(|path| {
+        script::Hook::load(path)
+            .context("Failed to load script")
+            .unwrap()
+    })
+}
+
+/// The language of `--variants`, or the one guessed from the name of `dump`.
+fn variants(args: &ParseArgs, dump: &Path) -> Option<variant::Language> {
+    if args.no_variants {
+        return None;
+    }
+    args.variants.or_else(|| variant::Language::detect(dump))
+}
+
+/// The language of `--lang`, or the one guessed from the name of `dump`, like `de` for
+/// `dewiki-20240601-pages-articles.xml.bz2`.
+fn language(args: &ParseArgs, dump: &Path) -> Option<String> {
+    args.lang.clone().or_else(|| {
+        let name = dump.file_name()?.to_string_lossy().to_ascii_lowercase();
+        let (code, _) = name.split_once("wiki")?;
+        (!code.is_empty() && code.chars().all(|c| c.is_ascii_lowercase() || c == '_'))
+            .then(|| code.replace('_', "-"))
+    })
+}
+
+/// The templates of `--disambiguation-templates`, or those of the wiki's language.
+fn disambiguation_templates(args: &ParseArgs, dump: &Path) -> Vec<String> {
+    if !args.disambiguation_templates.is_empty() {
+        return args.disambiguation_templates.clone();
+    }
+    let language = language(args, dump).unwrap_or_default();
+    navigation::disambiguation_templates(&language)
+        .iter()
+        .map(|&template| String::from(template))
+        .collect()
+}
+
+/// The siteinfo of `dump` with `--siteinfo`. Standard input can't be read twice, so its pages
+/// are read with the English names.
+fn siteinfo(args: &ParseArgs, dump: &Path) -> Option<siteinfo::Siteinfo> {
+    if !args.siteinfo {
+        return None;
+    }
+    if dump == Path::new("-") {
+        tracing::warn!(
+            "Can't read the siteinfo of standard input ahead of its pages; using the English \
+             namespace names"
+        );
+        return None;
+    }
+    let siteinfo = siteinfo::Siteinfo::read(dump, &remote_options(args))
+        .context("Failed to read siteinfo")
+        .unwrap();
+    tracing::debug!("{} namespaces in the siteinfo", siteinfo.len());
+    Some(siteinfo)
+}
+
+/// The conversion table of the language of `variants`, with `--variant-rules` added.
+fn variant_rules(args: &ParseArgs, dump: &Path) -> Option<variant::Rules> {
+    let language = variants(args, dump);
+    if language.is_none() && (args.no_variants || args.variant_rules.is_none()) {
+        return None;
+    }
+    let mut rules = language.map(variant::Language::rules).unwrap_or_default();
+    if let Some(path) = &args.variant_rules {
+        rules
+            .load(path)
+            .context("Failed to load variant rules")
+            .unwrap();
+    }
+    Some(rules)
+}
+
+/// Apply `--max-page-bytes` to `page`, returning whether to keep it.
+fn within_size_limit(args: &ParseArgs, page: &mut Page) -> bool {
+    let Some(max) = args.max_page_bytes.filter(|&max| page.text.len() > max) else {
+        return true;
+    };
+    let size = page.text.len();
+    match args.oversized {
+        guard::Oversized::Skip => {
+            tracing::warn!("Skipping '{}', with {size} bytes of wikitext", page.title);
+            false
+        }
+        guard::Oversized::Truncate => {
+            tracing::warn!(
+                "Truncating '{}' from {size} to {max} bytes of wikitext",
+                page.title
+            );
+            guard::truncate(&mut page.text, max);
+            true
+        }
+    }
+}
+
+fn project(args: &ParseArgs, dump: &Path) -> profile::Project {
+    args.project
+        .unwrap_or_else(|| profile::Project::detect(dump))
+}
+
+/// How the links of the pages in `dump` are found and normalized, as `args` say.
+fn link_profile(args: &ParseArgs, dump: &Path) -> profile::Profile {
+    let profile = project(args, dump)
+        .profile()
+        .with_stages(args.normalization())
+        .with_link_classes(args.link_classes.clone())
+        .with_variants(variant_rules(args, dump))
+        .with_siteinfo(siteinfo(args, dump));
+    if args.no_template_links {
+        profile.without_template_links()
+    } else {
+        profile
+    }
+}
+
+fn parse_probability(s: &str) -> Result<f64, String> {
+    let p: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if p > 0.0 && p <= 1.0 {
+        Ok(p)
+    } else {
+        Err(String::from("must be greater than 0 and at most 1"))
+    }
 }
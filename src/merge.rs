@@ -0,0 +1,112 @@
+//! Combine saved graphs (`parse --graph`) and the partial results of sharded parses (`parse
+//! --shard K/N --partial FILE`) into one saved graph. Nodes are matched up by title, so the
+//! result is the union of the inputs, whether they are the shards of one dump or separate wikis.
+//! Links to redirects are resolved again over the union, since a link of one shard can lead to
+//! a redirect of another, and so are disambiguation pages excluded if the shards were parsed
+//! with `--exclude-disambiguation`.
+
+use crate::{
+    cache,
+    graph::{Codec, Graph, Metadata},
+    workspace, Wiki,
+};
+use anyhow::Context as _;
+use lasso::Rodeo;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Saved graphs, by file or by name in the workspace, or partial results, in any mix; node
+    /// IDs follow the order given, so list shards in order for reproducible IDs
+    #[arg(required = true, value_name = "INPUT", value_parser = workspace::graph_path)]
+    inputs: Vec<PathBuf>,
+
+    /// Where to save the combined graph
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// Store the titles, links, and backlinks of the combined graph in compressed blocks, as
+    /// with `parse --graph-codec`
+    #[arg(long, value_name = "CODEC", value_enum, default_value_t)]
+    graph_codec: Codec,
+
+    /// Leave the backlinks out of the combined graph, as with `parse --no-backlinks`
+    #[arg(long)]
+    no_backlinks: bool,
+}
+
+pub fn run(args: &Args) {
+    let mut rodeo = Rodeo::new();
+    let mut wiki = Wiki::default();
+    let (mut resolve_redirects, mut exclude_disambiguation) = (false, false);
+    for path in &args.inputs {
+        let inputs = add(&mut rodeo, &mut wiki, path)
+            .with_context(|| format!("Failed to merge {}", path.display()))
+            .unwrap();
+        if let Some(inputs) = inputs {
+            resolve_redirects |= inputs.resolves_redirects();
+            exclude_disambiguation |= inputs.excludes_disambiguation();
+        }
+    }
+    if resolve_redirects {
+        wiki.resolve_redirects();
+    }
+    if exclude_disambiguation {
+        wiki.exclude_disambiguation();
+    }
+
+    let mut graph = Graph::new(&rodeo, &wiki, Metadata::current(None))
+        .context("Failed to build graph")
+        .unwrap();
+    graph.set_codec(args.graph_codec);
+    graph.set_store_backlinks(!args.no_backlinks);
+    graph
+        .save(&args.output)
+        .context("Failed to save graph")
+        .unwrap();
+
+    println!(
+        "{} nodes and {} links",
+        graph.node_count(),
+        graph.edge_count()
+    );
+}
+
+/// Add the graph or partial result at `path` to `wiki`, giving the inputs of the parse that
+/// wrote it if it is a partial result.
+fn add(rodeo: &mut Rodeo, wiki: &mut Wiki, path: &Path) -> anyhow::Result<Option<cache::Inputs>> {
+    let (inputs, partial) = if Graph::is_graph_file(path)? {
+        (None, Graph::load(path)?.to_wiki(rodeo))
+    } else {
+        let (inputs, partial) = cache::load_partial(path, rodeo)?;
+        (Some(inputs), partial)
+    };
+
+    for (category, page, key) in partial.category_memberships() {
+        wiki.add_category(category, page, key.map(String::from));
+    }
+    for (page, links) in partial.links {
+        wiki.links.entry(page).or_default().extend(links);
+    }
+    wiki.redirects.extend(partial.redirects);
+    wiki.sort_keys.extend(partial.sort_keys);
+    wiki.namespaces.extend(partial.namespaces);
+    wiki.page_ids.extend(partial.page_ids);
+    for (page, timestamp) in partial.timestamps {
+        let latest = wiki.timestamps.entry(page).or_default();
+        if *latest < timestamp {
+            *latest = timestamp;
+        }
+    }
+    wiki.navigation.extend(partial.navigation);
+    for (edge, types) in partial.edge_types {
+        let merged = wiki.edge_types.entry(edge).or_default();
+        *merged = merged.union(types);
+    }
+    wiki.node_attributes.extend(partial.node_attributes);
+    wiki.edge_attributes.extend(partial.edge_attributes);
+    if let Some(counts) = partial.link_counts {
+        wiki.link_counts.get_or_insert_default().extend(counts);
+    }
+    Ok(inputs)
+}
@@ -0,0 +1,198 @@
+//! `parse --metrics-listen` and `--metrics-interval`: the counters of a parse as it reads the
+//! dump, for watching parses run as scheduled jobs and alerting on one that stalls. They are
+//! served in the Prometheus text format, where `rate()` of the totals gives pages and bytes a
+//! second over any window, or logged now and then as the fields of one event.
+
+use crate::{
+    progress::{Handle, Progress, Snapshot},
+    run_report,
+};
+use std::{
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Reports the counters of a `Progress` until dropped.
+pub struct Metrics {
+    done: Arc<AtomicBool>,
+    server: Option<Arc<Server>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl Metrics {
+    /// Serve the counters of `progress` on `listen` and log them every `interval` seconds,
+    /// either of which may be left out.
+    pub fn start(
+        progress: &Progress,
+        listen: Option<&str>,
+        interval: Option<u64>,
+    ) -> anyhow::Result<Self> {
+        let started = Instant::now();
+        let done = Arc::new(AtomicBool::new(false));
+        let mut threads = Vec::new();
+        let server = listen
+            .map(|listen| {
+                let server =
+                    Arc::new(Server::http(listen).map_err(|error| {
+                        anyhow::anyhow!("Failed to listen on {listen}: {error}")
+                    })?);
+                tracing::info!("Serving metrics on http://{listen}/metrics");
+                let counters = progress.handle();
+                let serving = Arc::clone(&server);
+                threads.push(thread::spawn(move || {
+                    for request in serving.incoming_requests() {
+                        respond(request, &counters, started);
+                    }
+                }));
+                anyhow::Ok(server)
+            })
+            .transpose()?;
+        if let Some(interval) = interval {
+            let counters = progress.handle();
+            let done = Arc::clone(&done);
+            threads.push(thread::spawn(move || {
+                log(&counters, &done, Duration::from_secs(interval));
+            }));
+        }
+        Ok(Self {
+            done,
+            server,
+            threads,
+        })
+    }
+}
+
+impl Drop for Metrics {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(server) = &self.server {
+            server.unblock();
+        }
+        for thread in self.threads.drain(..) {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+fn respond(request: Request, counters: &Handle, started: Instant) {
+    let response = if *request.method() == Method::Get && request.url() == "/metrics" {
+        Response::from_string(exposition(&counters.snapshot(), started.elapsed()))
+            .with_header(Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap())
+    } else {
+        Response::from_string("Not found\n").with_status_code(404)
+    };
+    if let Err(error) = request.respond(response) {
+        tracing::warn!("Failed to send metrics: {error}");
+    }
+}
+
+/// The counters in the Prometheus text format, `elapsed` into reading the dump.
+fn exposition(counters: &Snapshot, elapsed: Duration) -> String {
+    let metrics = [
+        (
+            "wikigraph_parse_bytes_read_total",
+            "counter",
+            "Bytes of the dump read, as stored, compressed or not",
+            Some(counters.bytes_read),
+        ),
+        (
+            "wikigraph_parse_bytes",
+            "gauge",
+            "Bytes of the dump to read in all, or 0 while unknown",
+            Some(counters.total_bytes),
+        ),
+        (
+            "wikigraph_parse_pages_read_total",
+            "counter",
+            "Pages read from the dump",
+            Some(counters.pages),
+        ),
+        (
+            "wikigraph_parse_pages_built_total",
+            "counter",
+            "Pages added to the graph, leaving out those outside the namespaces or skipped",
+            Some(counters.built),
+        ),
+        (
+            "wikigraph_parse_malformed_pages_total",
+            "counter",
+            "Malformed pages skipped",
+            Some(counters.malformed),
+        ),
+        (
+            "wikigraph_parse_backlog_pages",
+            "gauge",
+            "Read pages waiting to be parsed, which stays at --channel-capacity while parsing is \
+             the bottleneck",
+            Some(counters.backlog),
+        ),
+        (
+            "wikigraph_parse_resident_memory_bytes",
+            "gauge",
+            "Resident memory of the parse",
+            run_report::rss(),
+        ),
+        (
+            "wikigraph_parse_peak_resident_memory_bytes",
+            "gauge",
+            "Most resident memory of the parse so far",
+            run_report::peak_rss(),
+        ),
+    ];
+    let mut text = String::new();
+    for (name, kind, help, value) in metrics {
+        if let Some(value) = value {
+            let _ = writeln!(
+                text,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+            );
+        }
+    }
+    let _ = writeln!(
+        text,
+        "# HELP wikigraph_parse_seconds Seconds since the dump started being read\n\
+         # TYPE wikigraph_parse_seconds gauge\n\
+         wikigraph_parse_seconds {}",
+        elapsed.as_secs_f64()
+    );
+    text
+}
+
+/// Log the counters every `interval` until `done`.
+fn log(counters: &Handle, done: &AtomicBool, interval: Duration) {
+    let mut last = counters.snapshot();
+    let mut last_time = Instant::now();
+    loop {
+        thread::park_timeout(interval);
+        if done.load(Ordering::Relaxed) {
+            return;
+        }
+        let now = counters.snapshot();
+        let millis = last_time.elapsed().as_millis().max(1);
+        let rate = |count: u64, last: u64| {
+            u64::try_from(u128::from(count.saturating_sub(last)) * 1000 / millis)
+                .unwrap_or(u64::MAX)
+        };
+        tracing::info!(
+            bytes_read = now.bytes_read,
+            total_bytes = now.total_bytes,
+            bytes_per_second = rate(now.bytes_read, last.bytes_read),
+            pages_read = now.pages,
+            pages_per_second = rate(now.pages, last.pages),
+            pages_built = now.built,
+            malformed = now.malformed,
+            backlog = now.backlog,
+            resident_bytes = run_report::rss().unwrap_or(0),
+            "Parse metrics"
+        );
+        last = now;
+        last_time = Instant::now();
+    }
+}
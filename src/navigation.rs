@@ -0,0 +1,128 @@
+//! Pages that exist to be browsed rather than read: portals, disambiguation pages, and
+//! navigation-heavy pages like lists, indexes, and outlines. Their huge out-degree turns shortest
+//! paths into meaningless two-hop routes through them, so they are tagged for queries to avoid.
+
+use crate::{plaintext, template};
+use serde::{Deserialize, Serialize};
+
+/// Wikipedia's Portal namespace number, shared by most wikis that have portals.
+const PORTAL_NAMESPACE: i64 = 100;
+/// Fewest distinct links for a page to count as navigation-heavy.
+const MIN_LINKS: usize = 100;
+/// Most words of prose per link for a page to count as navigation-heavy. Articles have dozens;
+/// bulleted lists of links have a handful.
+const MAX_WORDS_PER_LINK: usize = 5;
+
+/// The templates marking disambiguation pages on the Wikipedia in `language`, with their most
+/// used redirects. Wikis not listed get the English ones, which some of them import.
+pub fn disambiguation_templates(language: &str) -> &'static [&'static str] {
+    match language {
+        "de" => &["Begriffsklärung"],
+        "es" => &["Desambiguación", "Desambig", "Des"],
+        "fr" => &[
+            "Homonymie",
+            "Patronymie",
+            "Toponymie",
+            "Bandeau standard pour page d'homonymie",
+        ],
+        "it" => &["Disambigua", "Disambig"],
+        "ja" => &[
+            "Aimai",
+            "曖昧さ回避",
+            "人名の曖昧さ回避",
+            "地名の曖昧さ回避",
+        ],
+        "nl" => &["Dp", "Dpintro", "Dp-naam"],
+        "pl" => &["Ujednoznacznienie", "Disambig"],
+        "pt" => &["Desambiguação", "Desambig", "Dambig"],
+        "ru" => &["Неоднозначность", "Многозначность", "Disambig"],
+        "sv" => &["Förgrening", "Grensida", "Gren"],
+        "uk" => &["Неоднозначність", "Disambig"],
+        "zh" => &["Disambig", "消歧义", "消歧義", "Dab"],
+        _ => &[
+            "Disambiguation",
+            "Disambig",
+            "Dab",
+            "Disamb",
+            "Dbig",
+            "Hndis",
+            "Geodis",
+            "Numberdis",
+            "Letter-NumberCombDisambig",
+            "Mathdab",
+            "Hospitaldis",
+            "Roaddis",
+            "Schooldis",
+        ],
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Portal,
+    /// Lists the pages a title could mean, with a `{{disambiguation}}`-family template.
+    Disambiguation,
+    /// Mostly links, with little prose between them.
+    Navigation,
+}
+
+impl Kind {
+    /// Detect the kind of a parsed page with `links` distinct link targets, which is a
+    /// disambiguation page if it invokes any of `disambiguation_templates`.
+    pub fn detect(
+        title: &str,
+        namespace: i64,
+        text: &str,
+        links: usize,
+        disambiguation_templates: &[String],
+    ) -> Option<Self> {
+        let in_portal_namespace = title
+            .split_once(':')
+            .is_some_and(|(prefix, _)| prefix.trim().eq_ignore_ascii_case("portal"));
+        if namespace == PORTAL_NAMESPACE || in_portal_namespace {
+            return Some(Self::Portal);
+        }
+        // Only the names of templates are needed, not where each one ends.
+        let disambiguation = text.match_indices("{{").any(|(start, _)| {
+            let name = text[start + 2..]
+                .split(['|', '}', '\n'])
+                .next()
+                .unwrap_or_default();
+            disambiguation_templates
+                .iter()
+                .any(|template| template::template_eq(template, name))
+        });
+        if disambiguation {
+            return Some(Self::Disambiguation);
+        }
+        // Only convert the text of pages with many links, since that is the slow part.
+        if links >= MIN_LINKS {
+            let words = plaintext::article(text).split_whitespace().count();
+            if words <= links * MAX_WORDS_PER_LINK {
+                return Some(Self::Navigation);
+            }
+        }
+        None
+    }
+
+    /// The byte stored for each node in graph files: 0 for ordinary pages.
+    pub fn to_byte(kind: Option<Self>) -> u8 {
+        match kind {
+            None => 0,
+            Some(Self::Portal) => 1,
+            Some(Self::Navigation) => 2,
+            Some(Self::Disambiguation) => 3,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> anyhow::Result<Option<Self>> {
+        Ok(match byte {
+            0 => None,
+            1 => Some(Self::Portal),
+            2 => Some(Self::Navigation),
+            3 => Some(Self::Disambiguation),
+            _ => anyhow::bail!("Invalid page kind {byte}"),
+        })
+    }
+}
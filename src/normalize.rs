@@ -0,0 +1,157 @@
+//! The steps that turn a link target as written into the title of the node it becomes. Which
+//! targets count as the same page depends on the question — section links may or may not be
+//! links to the whole article, and links to redirects may or may not be links to their
+//! targets — so the steps can be reordered and turned off with `parse --normalize`.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc, UnicodeNormalization as _};
+
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Decode HTML entities, so `AT&amp;T` is `AT&T`
+    DecodeEntities,
+    /// Read underscores as spaces, and trim and collapse whitespace, so `Foo_bar` and
+    /// ` Foo  bar ` are `Foo bar`
+    Whitespace,
+    /// Drop the `#Section` part, so section links are links to the page; a bare `[[#Section]]`
+    /// links the page to itself
+    StripAnchor,
+    /// Uppercase the first letter, as MediaWiki does, except on wikis with case-sensitive titles
+    CaseFold,
+    /// Apply Unicode normalization form C, so composed and decomposed accents match
+    Nfc,
+    /// Replace links to redirects with links to their targets. This needs every redirect in
+    /// the dump (or shard), so it always runs last, once parsing is done.
+    ResolveRedirects,
+}
+
+impl Stage {
+    /// Every stage, in the default order.
+    pub const ALL: [Self; 6] = [
+        Self::DecodeEntities,
+        Self::Whitespace,
+        Self::StripAnchor,
+        Self::CaseFold,
+        Self::Nfc,
+        Self::ResolveRedirects,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DecodeEntities => "decode_entities",
+            Self::Whitespace => "whitespace",
+            Self::StripAnchor => "strip_anchor",
+            Self::CaseFold => "case_fold",
+            Self::Nfc => "nfc",
+            Self::ResolveRedirects => "resolve_redirects",
+        }
+    }
+
+    /// Apply this stage to `target`, a link found on page `title`. `ResolveRedirects` is
+    /// applied to the whole graph by `Wiki::resolve_redirects` instead, so it does nothing here.
+    pub fn apply<'a>(
+        self,
+        title: &str,
+        target: Cow<'a, str>,
+        case_sensitive: bool,
+    ) -> Cow<'a, str> {
+        match self {
+            Self::DecodeEntities if target.contains('&') => Cow::Owned(decode_entities(&target)),
+            Self::Whitespace if needs_spacing(&target) => Cow::Owned(spaced(&target)),
+            Self::StripAnchor => match target.find('#') {
+                Some(0) => Cow::Owned(String::from(title)),
+                Some(end) => {
+                    let page = target[..end].trim_end();
+                    Cow::Owned(String::from(page))
+                }
+                None => target,
+            },
+            Self::CaseFold if !case_sensitive => match target.chars().next() {
+                Some(first) if first.is_lowercase() => {
+                    let rest = &target[first.len_utf8()..];
+                    Cow::Owned(first.to_uppercase().chain(rest.chars()).collect())
+                }
+                _ => target,
+            },
+            Self::Nfc if !is_nfc(&target) => Cow::Owned(target.nfc().collect()),
+            _ => target,
+        }
+    }
+}
+
+/// Whether `target` has an underscore, or whitespace that isn't a single space between words.
+fn needs_spacing(target: &str) -> bool {
+    let mut previous_space = true;
+    for character in target.chars() {
+        if character == '_' || (character.is_whitespace() && (previous_space || character != ' ')) {
+            return true;
+        }
+        previous_space = character == ' ';
+    }
+    previous_space && !target.is_empty()
+}
+
+/// `target` with underscores as spaces and its words separated by single spaces.
+fn spaced(target: &str) -> String {
+    let words: Vec<&str> = target
+        .split(|character: char| character == '_' || character.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.join(" ")
+}
+
+/// Decode named entities for the characters that turn up in titles, and numeric ones. Unknown
+/// or malformed entities are left as they are.
+fn decode_entities(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let character = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                // Titles treat non-breaking spaces as spaces.
+                "nbsp" => ' ',
+                "ndash" => '–',
+                "mdash" => '—',
+                entity => {
+                    let number = entity.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((character, end + 1))
+        });
+        if let Some((character, len)) = decoded {
+            output.push(character);
+            rest = &rest[len..];
+        } else {
+            output.push('&');
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+    output
+}
@@ -0,0 +1,50 @@
+//! Where each link of a page comes from. Most are `[[...]]` links written in the page, but some
+//! are written as template arguments, or as infobox fields, and others only exist because a
+//! link template, or a `--link-rules` pattern, turns plain text into a link. Analyses that want
+//! only the links an editor wrote in the prose can tell them apart by their `origins`.
+
+use crate::{profile::Profile, rules::Rules, template, wikilink::links};
+
+/// The raw link targets of `text` with how each was found: `wikitext` for links in the prose,
+/// `template:Name` for links in the arguments of a template or made by a link template,
+/// `infobox:field` for links in an infobox field, and `pattern` for `--link-rules` patterns.
+pub fn origins<'a>(profile: &Profile, rules: &Rules, text: &'a str) -> Vec<(&'a str, String)> {
+    let templates: Vec<_> = template::templates(text)
+        .filter(|template| !template::is_magic_word(template.name))
+        .collect();
+    let mut origins = Vec::new();
+
+    for link in links(text) {
+        // Templates come in order of their opening braces, so the innermost is the last.
+        let enclosing = templates
+            .iter()
+            .rfind(|template| template.range.contains(&link.range.start));
+        let origin = match enclosing {
+            None => String::from("wikitext"),
+            Some(template) if template::is_infobox(template.name) => {
+                match template.key_at(text, link.range.start) {
+                    Some(key) => format!("infobox:{key}"),
+                    None => format!("template:{}", template.name),
+                }
+            }
+            Some(template) => format!("template:{}", template.name),
+        };
+        origins.push((link.target, origin));
+    }
+
+    for template in &templates {
+        let targets = profile
+            .link_template_targets(template)
+            .into_iter()
+            .chain(rules.template_targets(template));
+        origins.extend(targets.map(|target| (target, format!("template:{}", template.name))));
+    }
+
+    origins.extend(
+        rules
+            .pattern_links(text)
+            .into_iter()
+            .map(|target| (target, String::from("pattern"))),
+    );
+    origins
+}
@@ -0,0 +1,164 @@
+//! One JSON object per page with everything the parser can pull out of it, so a dump can be
+//! turned into JSON lines for other tools without building a graph at all.
+
+use crate::{
+    dump::Page, plaintext::strip_markup, profile::Profile, sort, template, wikilink::links,
+};
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write as _},
+    ops::Range,
+    path::Path,
+    sync::LazyLock,
+};
+
+#[derive(Serialize)]
+struct Record<'a> {
+    title: &'a str,
+    namespace: i64,
+    timestamp: Option<&'a str>,
+    redirect: Option<&'a str>,
+    sort_key: Option<&'a str>,
+    categories: Vec<Category<'a>>,
+    /// Names of every template invoked, nested ones included, in order of appearance. Magic
+    /// words and parser functions like `{{DEFAULTSORT:...}}` and `{{#if:...}}` aren't templates.
+    templates: Vec<&'a str>,
+    links: Vec<Link>,
+    external_links: Vec<ExternalLink<'a>>,
+    infoboxes: Vec<Infobox<'a>>,
+}
+
+#[derive(Serialize)]
+struct Category<'a> {
+    name: &'a str,
+    sort_key: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Link {
+    target: String,
+    anchor: String,
+}
+
+#[derive(Serialize)]
+pub struct ExternalLink<'a> {
+    pub url: &'a str,
+    /// The text after the URL in `[url label]`, if any.
+    pub label: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Infobox<'a> {
+    name: &'a str,
+    /// Named arguments, with their values as wikitext.
+    fields: BTreeMap<&'a str, &'a str>,
+}
+
+pub struct Writer {
+    inner: BufWriter<File>,
+}
+
+impl Writer {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            inner: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Write the record of `page`, whose banner-stripped wikitext is `text`.
+    pub fn add_page(&mut self, profile: &Profile, page: &Page, text: &str) -> anyhow::Result<()> {
+        let templates: Vec<_> = template::templates(&page.text).collect();
+        let record = Record {
+            title: &page.title,
+            namespace: page.namespace,
+            timestamp: page.timestamp.as_deref(),
+            redirect: page.redirect.as_deref(),
+            sort_key: sort::default_sort_key(&page.text),
+            categories: sort::categories(&page.text, |prefix| profile.is_category(prefix))
+                .map(|(name, sort_key)| Category { name, sort_key })
+                .collect(),
+            templates: templates
+                .iter()
+                .map(|template| template.name)
+                .filter(|&name| !template::is_magic_word(name))
+                .collect(),
+            links: links(text)
+                .filter_map(|link| {
+                    Some(Link {
+                        target: profile.resolve(&page.title, link.target)?.into_owned(),
+                        anchor: strip_markup(link.anchor),
+                    })
+                })
+                .collect(),
+            external_links: external_links(&page.text),
+            infoboxes: templates
+                .iter()
+                .filter(|template| template::is_infobox(template.name))
+                .map(|template| Infobox {
+                    name: template.name,
+                    fields: template
+                        .args
+                        .iter()
+                        .filter_map(|arg| {
+                            let (key, value) = arg.split_once('=')?;
+                            let key = key.trim();
+                            (!key.is_empty() && !key.contains(['[', '{']))
+                                .then_some((key, value.trim()))
+                        })
+                        .collect(),
+                })
+                .collect(),
+        };
+        serde_json::to_writer(&mut self.inner, &record)?;
+        self.inner.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+/// Bracketed `[url label]` links, then bare URLs outside of them, in order of appearance.
+pub fn external_links(text: &str) -> Vec<ExternalLink<'_>> {
+    static BRACKETED: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\[((?:https?:|ftp:)?//[^\s\[\]<>]+)(?:\s+([^\[\]]*))?\]").unwrap()
+    });
+    static BARE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"(?:https?|ftp)://[^\s\[\]<>{}|"]+"#).unwrap());
+
+    let mut found: Vec<(Range<usize>, ExternalLink)> = BRACKETED
+        .captures_iter(text)
+        .map(|capture| {
+            let link = ExternalLink {
+                url: capture.get(1).unwrap().as_str(),
+                label: capture
+                    .get(2)
+                    .map(|label| strip_markup(label.as_str()))
+                    .filter(|label| !label.is_empty()),
+            };
+            (capture.get(0).unwrap().range(), link)
+        })
+        .collect();
+    let bare: Vec<_> = BARE
+        .find_iter(text)
+        .filter(|url| !found.iter().any(|(range, _)| range.contains(&url.start())))
+        .map(|url| {
+            // Trailing punctuation usually ends the sentence rather than the URL.
+            let link = ExternalLink {
+                url: url
+                    .as_str()
+                    .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'']),
+                label: None,
+            };
+            (url.range(), link)
+        })
+        .collect();
+    found.extend(bare);
+    found.sort_by_key(|(range, _)| range.start);
+    found.into_iter().map(|(_, link)| link).collect()
+}
@@ -0,0 +1,276 @@
+//! A one-page summary of where a page sits in the graph, as Markdown or HTML to paste into an
+//! issue or a wiki discussion: its degrees and PageRank against every other page's, the most
+//! central pages linking to it and linked from it, its community, and how far it is from a few
+//! landmark pages.
+
+use crate::{
+    cancel::Cancel,
+    graph::{Direction, Graph},
+    path,
+    report::{self, Format, Section},
+    stats::{self, PageRank},
+    workspace,
+};
+use anyhow::Context as _;
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+/// Rounds of label propagation, past which few nodes still move.
+const COMMUNITY_ROUNDS: u32 = 20;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Title of the page to profile
+    title: String,
+
+    /// Where to write the profile [default: standard output]
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// [default: HTML if `--output` ends in `.html`, else Markdown]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Number of pages listed in each table
+    #[arg(long, value_name = "K", default_value_t = 10)]
+    top: usize,
+
+    /// Page to give the distance to and from (repeatable) [default: the pages with the
+    /// highest PageRank]
+    #[arg(long, value_name = "TITLE")]
+    landmark: Vec<String>,
+
+    /// Give up on computing PageRank, communities, and distances after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let page = path::find_or_exit(&graph, &args.title);
+    let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+    let pagerank = stats::stored_pagerank(&graph, stats::DAMPING, stats::ITERATIONS).map_or_else(
+        || {
+            stats::pagerank_scores(&graph, stats::DAMPING, stats::ITERATIONS, &cancel)
+                .context("Failed to compute PageRank")
+                .unwrap()
+        },
+        |pagerank: &PageRank| pagerank.scores.clone(),
+    );
+    let communities = stats::communities(&graph, COMMUNITY_ROUNDS, &cancel)
+        .context("Failed to find communities")
+        .unwrap();
+    let profile = Profile {
+        graph: &graph,
+        page,
+        pagerank,
+        communities,
+    };
+
+    let landmarks: Vec<u32> = if args.landmark.is_empty() {
+        profile.central(profile.pages().filter(|&node| node != page), 5)
+    } else {
+        args.landmark
+            .iter()
+            .map(|title| path::find_or_exit(&graph, title))
+            .collect()
+    };
+    let sections = [
+        profile.overview(),
+        profile.neighbours(
+            "Most central pages linking to it",
+            graph.backlinks(page),
+            args.top,
+        ),
+        profile.neighbours(
+            "Most central pages it links to",
+            graph.links(page),
+            args.top,
+        ),
+        profile.community(args.top),
+        profile.distances(&landmarks, &cancel),
+    ];
+    let title = format!(
+        "{} in {}",
+        graph.title(page),
+        report::name(&graph, &args.graph)
+    );
+    report::write(args.output.as_deref(), args.format, &title, &sections);
+}
+
+struct Profile<'a> {
+    graph: &'a Graph,
+    page: u32,
+    pagerank: Vec<f64>,
+    communities: Vec<u32>,
+}
+
+impl Profile<'_> {
+    /// Nodes that are pages rather than redirects.
+    fn pages(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..u32::try_from(self.graph.node_count()).unwrap())
+            .filter(|&node| self.graph.redirect(node).is_none())
+    }
+
+    /// The PageRank of `node` over that of the average node.
+    #[allow(clippy::cast_precision_loss)]
+    fn relative(&self, node: u32) -> f64 {
+        self.pagerank[node as usize] * self.graph.node_count() as f64
+    }
+
+    /// The `k` of `nodes` with the highest PageRank, highest first.
+    fn central(&self, nodes: impl IntoIterator<Item = u32>, k: usize) -> Vec<u32> {
+        let mut nodes: Vec<u32> = nodes.into_iter().collect();
+        nodes.sort_by(|&a, &b| self.pagerank[b as usize].total_cmp(&self.pagerank[a as usize]));
+        nodes.truncate(k);
+        nodes
+    }
+
+    /// The percentage of pages for which `measure` is lower than for the profiled page.
+    #[allow(clippy::cast_precision_loss)]
+    fn percentile<T: PartialOrd>(&self, measure: impl Fn(u32) -> T) -> String {
+        let own = measure(self.page);
+        let (mut lower, mut pages) = (0_usize, 0_usize);
+        for node in self.pages() {
+            pages += 1;
+            if measure(node) < own {
+                lower += 1;
+            }
+        }
+        format!("{:.1}", lower as f64 / pages.max(1) as f64 * 100.0)
+    }
+
+    fn overview(&self) -> Section {
+        let graph = self.graph;
+        let page = self.page;
+        let redirects = graph
+            .backlinks(page)
+            .iter()
+            .filter(|&&source| graph.redirect(source) == Some(page))
+            .count();
+        let community = self.communities[page as usize];
+        let community_size = self
+            .pages()
+            .filter(|&node| self.communities[node as usize] == community)
+            .count();
+        let rows = [
+            ("Links in", graph.in_degree(page).to_string()),
+            (
+                "Links in, percentile",
+                self.percentile(|node| graph.in_degree(node)),
+            ),
+            ("Links out", graph.out_degree(page).to_string()),
+            (
+                "Links out, percentile",
+                self.percentile(|node| graph.out_degree(node)),
+            ),
+            ("Redirects to it", redirects.to_string()),
+            ("PageRank", format!("{:.2}", self.relative(page))),
+            (
+                "PageRank, percentile",
+                self.percentile(|node| self.pagerank[node as usize]),
+            ),
+            ("Pages in its community", community_size.to_string()),
+        ];
+        Section {
+            heading: "Overview",
+            summary: format!(
+                "PageRank (damping {}, {} iterations) is relative to the average page. \
+                 Percentiles are the share of pages with less.",
+                stats::DAMPING,
+                stats::ITERATIONS
+            ),
+            header: &["", "Value"],
+            rows: rows
+                .into_iter()
+                .map(|(measure, value)| vec![String::from(measure), value])
+                .collect(),
+        }
+    }
+
+    fn neighbours(&self, heading: &'static str, nodes: &[u32], top: usize) -> Section {
+        let pages = nodes
+            .iter()
+            .copied()
+            .filter(|&node| self.graph.redirect(node).is_none());
+        Section {
+            heading,
+            summary: format!("{} pages, by PageRank.", pages.clone().count()),
+            header: &["Page", "PageRank", "Links in"],
+            rows: self.table(self.central(pages, top)),
+        }
+    }
+
+    fn community(&self, top: usize) -> Section {
+        let community = self.communities[self.page as usize];
+        let members: Vec<u32> = self
+            .pages()
+            .filter(|&node| self.communities[node as usize] == community && node != self.page)
+            .collect();
+        Section {
+            heading: "Community",
+            summary: format!(
+                "The most central of the {} other pages in its community, found by label \
+                 propagation over links in both directions.",
+                members.len()
+            ),
+            header: &["Page", "PageRank", "Links in"],
+            rows: self.table(self.central(members, top)),
+        }
+    }
+
+    /// Rows of title, PageRank, and links in for each of `nodes`.
+    fn table(&self, nodes: Vec<u32>) -> Vec<Vec<String>> {
+        nodes
+            .into_iter()
+            .map(|node| {
+                vec![
+                    String::from(self.graph.title(node)),
+                    format!("{:.2}", self.relative(node)),
+                    self.graph.in_degree(node).to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn distances(&self, landmarks: &[u32], cancel: &Cancel) -> Section {
+        let from_page = self.reach(Direction::Out, cancel);
+        let to_page = self.reach(Direction::In, cancel);
+        let distance = |distances: &HashMap<u32, usize>, node: u32| {
+            distances
+                .get(&node)
+                .map_or_else(|| String::from("-"), ToString::to_string)
+        };
+        Section {
+            heading: "Distances to landmarks",
+            summary: String::from(
+                "The fewest links to click from the page to each landmark, and back; \
+                 \"-\" where there is no way.",
+            ),
+            header: &["Landmark", "From the page", "To the page"],
+            rows: landmarks
+                .iter()
+                .map(|&landmark| {
+                    vec![
+                        String::from(self.graph.title(landmark)),
+                        distance(&from_page, landmark),
+                        distance(&to_page, landmark),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// How many links in `direction` every page reachable from the profiled one is away.
+    fn reach(&self, direction: Direction, cancel: &Cancel) -> HashMap<u32, usize> {
+        self.graph
+            .distances(&[self.page], direction, |_| true, |_| true, cancel)
+            .context("Failed to measure distances")
+            .unwrap()
+    }
+}
@@ -0,0 +1,85 @@
+//! PageRank of every page of a saved graph, on its own: the top pages, and optionally every
+//! score as CSV for analysis elsewhere. Scores that `stats --pagerank` stored in the graph file
+//! with the same parameters are reused rather than computed again.
+
+use crate::{
+    cancel::Cancel,
+    graph::Graph,
+    stats::{self, PageRank},
+    workspace,
+};
+use anyhow::Context as _;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Number of power iterations
+    #[arg(long, value_name = "N", default_value_t = stats::ITERATIONS)]
+    iterations: u32,
+
+    /// Probability of following a link rather than jumping to a random page
+    #[arg(long, default_value_t = stats::DAMPING)]
+    damping: f64,
+
+    /// Number of top-ranked pages to print
+    #[arg(long, value_name = "K", default_value_t = 10)]
+    top: usize,
+
+    /// Also write the rank, title, and score of every page to this CSV file, best first
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Give up after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+
+    let computed;
+    let pagerank =
+        if let Some(pagerank) = stats::stored_pagerank(&graph, args.damping, args.iterations) {
+            tracing::info!("Using PageRank stored in the graph file");
+            pagerank
+        } else {
+            let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+            let scores = stats::pagerank_scores(&graph, args.damping, args.iterations, &cancel)
+                .context("Failed to compute PageRank")
+                .unwrap();
+            computed = PageRank {
+                damping: args.damping,
+                iterations: args.iterations,
+                scores,
+            };
+            &computed
+        };
+
+    for (node, score) in stats::top(pagerank, args.top) {
+        println!("{score:.6}\t{}", graph.title(node));
+    }
+    if let Some(path) = &args.output {
+        export(&graph, pagerank, path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+            .unwrap();
+    }
+}
+
+fn export(graph: &Graph, pagerank: &PageRank, path: &Path) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["rank", "title", "score"])?;
+    for (rank, (node, score)) in (1_u32..).zip(stats::top(pagerank, graph.node_count())) {
+        writer.write_record([&rank.to_string(), graph.title(node), &score.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,243 @@
+//! The shortest-path game: the fewest links a reader needs to click to get from one article to
+//! another, and the other ways there, for players comparing routes.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::{mmap::MmapGraph, Adjacency, Direction, Graph},
+    navigation::Kind,
+    title_search, workspace,
+};
+use anyhow::Context as _;
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(clap::Args)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Title of the page to start from; underscores and the case of the first letter don't
+    /// matter, and other case differences don't either if only one page matches
+    from: String,
+
+    /// Title of the page to reach
+    to: String,
+
+    /// Which links to follow
+    #[arg(long, value_enum, default_value_t)]
+    direction: Direction,
+
+    /// Don't pass through portals, disambiguation pages, and navigation-heavy pages like lists
+    #[arg(long)]
+    avoid_navigation: bool,
+
+    /// Don't pass through disambiguation pages, which link to every page sharing a name
+    #[arg(long)]
+    avoid_disambiguation: bool,
+
+    /// Don't pass through this page, such as a hub like `United States` that makes every path
+    /// short; may be given more than once
+    #[arg(long, value_name = "TITLE")]
+    forbid: Vec<String>,
+
+    /// Print every shortest path, not just one
+    #[arg(long, conflicts_with = "paths")]
+    all: bool,
+
+    /// Most paths `--all` prints
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 100,
+        requires = "all",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    max_paths: u32,
+
+    /// Print the K shortest paths that don't visit a page twice, shortest first, going on to
+    /// longer ones once the shortest run out
+    #[arg(long, short = 'k', value_name = "K", value_parser = clap::value_parser!(u32).range(1..))]
+    paths: Option<u32>,
+
+    /// Give up after this many seconds of searching
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Map the graph file into memory instead of loading it, which is much faster for one
+    /// search on a large graph; needs a graph file saved without `--compress-graph`
+    #[arg(long)]
+    mmap: bool,
+}
+
+pub fn run(args: &Args) {
+    if args.mmap {
+        let graph = MmapGraph::open(&args.graph)
+            .context("Failed to map graph")
+            .unwrap();
+        search(&graph, args);
+    } else {
+        let graph = Graph::load(&args.graph)
+            .context("Failed to load graph")
+            .unwrap();
+        search(&graph, args);
+    }
+}
+
+/// Print the chains of titles, one per line, exiting with status 1 if there are none or a title
+/// isn't a page.
+fn search(graph: &impl Adjacency, args: &Args) {
+    let from = find_or_exit(graph, &args.from);
+    let to = find_or_exit(graph, &args.to);
+    let forbidden: HashSet<u32> = args
+        .forbid
+        .iter()
+        .map(|title| find_or_exit(graph, title))
+        .collect();
+    for &end in [from, to].iter().filter(|end| forbidden.contains(end)) {
+        tracing::warn!("Not forbidding '{}', an end of the path", graph.title(end));
+    }
+
+    let keep = |node| {
+        !forbidden.contains(&node)
+            && match graph.kind(node) {
+                Some(Kind::Disambiguation) => !args.avoid_navigation && !args.avoid_disambiguation,
+                Some(_) => !args.avoid_navigation,
+                None => true,
+            }
+    };
+    let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+    let paths = if let Some(k) = args.paths {
+        k_shortest(graph, from, to, args.direction, keep, k as usize, &cancel)
+    } else if args.all {
+        let limit = args.max_paths as usize;
+        graph.shortest_paths(from, to, args.direction, keep, limit, &cancel)
+    } else {
+        graph
+            .shortest_path(from, to, args.direction, keep, &cancel)
+            .map(|path| path.into_iter().collect())
+    }
+    .context("Failed to find a path")
+    .unwrap();
+    let (Some(shortest), Some(longest)) = (paths.first(), paths.last()) else {
+        println!(
+            "No path from '{}' to '{}'",
+            graph.title(from),
+            graph.title(to)
+        );
+        std::process::exit(1);
+    };
+    for path in &paths {
+        let titles: Vec<&str> = path.iter().map(|&node| graph.title(node)).collect();
+        println!("{}", titles.join(" → "));
+    }
+    let (shortest, longest) = (shortest.len() - 1, longest.len() - 1);
+    let links = if longest == 1 { "link" } else { "links" };
+    match paths.len() {
+        1 => println!("{shortest} {links}"),
+        n if shortest == longest => println!("{n} paths of {shortest} {links}"),
+        n => println!("{n} paths of {shortest} to {longest} links"),
+    }
+}
+
+/// Up to `k` of the chains of links from `from` to `to` in `direction` that visit no node twice,
+/// shortest first, passing through nodes for which `keep` holds, by Yen's algorithm: each chain
+/// after the first leaves one of those before it somewhere, by a link none of them take from
+/// there, and goes on the shortest way that doesn't go back through the nodes before.
+fn k_shortest(
+    graph: &impl Adjacency,
+    from: u32,
+    to: u32,
+    direction: Direction,
+    keep: impl Fn(u32) -> bool,
+    k: usize,
+    cancel: &Cancel,
+) -> Result<Vec<Vec<u32>>, Cancelled> {
+    let Some(first) = graph.shortest_path(from, to, direction, &keep, cancel)? else {
+        return Ok(Vec::new());
+    };
+    let mut paths = vec![first];
+    // Chains found by leaving the ones before, by length and then node IDs, so that they are
+    // tried in the same order every time.
+    let mut candidates = BTreeSet::new();
+    while paths.len() < k {
+        let last = &paths[paths.len() - 1];
+        for (i, &spur) in last.iter().enumerate().take(last.len() - 1) {
+            let root = &last[..i];
+            let taken: HashSet<u32> = paths
+                .iter()
+                .filter(|path| path.len() > i + 1 && path[..=i] == last[..=i])
+                .map(|path| path[i + 1])
+                .collect();
+            let enter = |node| node == spur || node == to || (keep(node) && !root.contains(&node));
+            let follow = |node, target| {
+                enter(node) && enter(target) && !(node == spur && taken.contains(&target))
+            };
+            if let Some(rest) = graph.shortest_path_by(spur, to, direction, follow, cancel)? {
+                let path: Vec<u32> = root.iter().copied().chain(rest).collect();
+                candidates.insert((path.len(), path));
+            }
+        }
+        let Some((_, next)) = candidates.pop_first() else {
+            break;
+        };
+        paths.push(next);
+    }
+    Ok(paths)
+}
+
+/// The page a player typing `title` means, following redirects (see `lookup`).
+pub fn find(graph: &impl Adjacency, title: &str) -> anyhow::Result<u32> {
+    lookup(graph, title).map(|id| graph.resolve_redirect(id))
+}
+
+/// The node titled `title` as someone typing it means it, which may be a redirect: the exact
+/// title, else the title as MediaWiki would normalize it, else the only title equal to it
+/// ignoring case.
+pub fn lookup(graph: &impl Adjacency, title: &str) -> anyhow::Result<u32> {
+    let normalized = normalize(title);
+    if let Some(id) = graph.id(title).or_else(|| graph.id(&normalized)) {
+        return Ok(id);
+    }
+    let lowercase = normalized.to_lowercase();
+    let matches: Vec<u32> = (0..u32::try_from(graph.node_count())?)
+        .filter(|&node| graph.title(node).to_lowercase() == lowercase)
+        .collect();
+    let id = match matches[..] {
+        [id] => id,
+        [] => anyhow::bail!(
+            "No page titled '{title}'{}",
+            title_search::did_you_mean(graph, title)
+        ),
+        _ => {
+            let titles: Vec<&str> = matches.iter().map(|&node| graph.title(node)).collect();
+            anyhow::bail!("'{title}' could be any of: {}", titles.join(", "));
+        }
+    };
+    Ok(id)
+}
+
+/// The page `find` finds for `title`, or else, for commands taking titles on the command line,
+/// its error and any titles like `title` printed and an exit with status 1.
+pub fn find_or_exit(graph: &impl Adjacency, title: &str) -> u32 {
+    find(graph, title).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        std::process::exit(1);
+    })
+}
+
+/// Spaces for underscores, runs of spaces collapsed, and the first letter in uppercase.
+fn normalize(title: &str) -> String {
+    let spaced = title.replace('_', " ");
+    let words: Vec<&str> = spaced.split_whitespace().collect();
+    let title = words.join(" ");
+    let mut chars = title.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,408 @@
+//! How far off estimated numbers of clicks between pages are on a real graph: samples pairs of
+//! pages, finds the exact shortest path between each, and sets beside it the estimates of two
+//! approximate methods.
+//!
+//! - Landmarks: breadth-first searches to and from a few landmark pages, the best-linked by
+//!   default, give the length of the shortest path between two pages through any landmark,
+//!   which is never shorter than the real one.
+//! - Distance sketches (Das Sarma et al.): seed sets of 1, 2, 4, … random pages, with each page
+//!   knowing its nearest seed of each set in either direction. Pages sharing a nearest seed
+//!   are estimated to be as far apart as the path through it, and pages sharing none are left
+//!   unestimated.
+
+use crate::{
+    cancel::Cancel,
+    graph::{Adjacency as _, Direction, Graph},
+    sample::Rng,
+    workspace,
+};
+use anyhow::Context as _;
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Write the CSV of error statistics to this file instead of standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Also write every sampled pair with its exact and estimated distances to this CSV file
+    #[arg(long, value_name = "FILE")]
+    pair_output: Option<PathBuf>,
+
+    /// Pairs of pages to sample
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pairs: usize,
+
+    /// Number of landmarks
+    #[arg(long, value_name = "K", default_value_t = 16)]
+    landmarks: usize,
+
+    /// How to pick landmarks
+    #[arg(long, value_enum, default_value_t = LandmarkChoice::Degree)]
+    landmark_choice: LandmarkChoice,
+
+    /// Seed sets of each size for distance sketches
+    #[arg(long, value_name = "K", default_value_t = 2)]
+    sketch_repetitions: usize,
+
+    /// Which links paths follow
+    #[arg(long, value_enum, default_value_t)]
+    direction: Direction,
+
+    /// Seed for sampling pairs, random landmarks, and seed sets
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum LandmarkChoice {
+    /// The pages with the most links in and out
+    Degree,
+    /// Pages picked at random
+    Random,
+}
+
+/// Distances to or from each endpoint of the sampled pairs, by its position in `Endpoints`.
+type Distances = Vec<Option<u32>>;
+
+/// The nearest of a set of pages, and how far it is, to or from each endpoint.
+type Nearest = Vec<Option<(u32, u32)>>;
+
+/// The pages at either end of the sampled pairs, numbered so that estimates only need keeping
+/// for them.
+struct Endpoints {
+    slots: HashMap<u32, usize>,
+}
+
+impl Endpoints {
+    fn new(pairs: &[(u32, u32)]) -> Self {
+        let mut slots = HashMap::new();
+        for &(from, to) in pairs {
+            for node in [from, to] {
+                let next = slots.len();
+                slots.entry(node).or_insert(next);
+            }
+        }
+        Self { slots }
+    }
+
+    fn slot(&self, node: u32) -> usize {
+        self.slots[&node]
+    }
+
+    /// What `nearest` found for each endpoint.
+    fn pick<T: Copy>(&self, all: &[Option<T>]) -> Vec<Option<T>> {
+        let mut picked = vec![None; self.slots.len()];
+        for (&node, &slot) in &self.slots {
+            picked[slot] = all[node as usize];
+        }
+        picked
+    }
+}
+
+/// The estimates of one method for every sampled pair, and how long it took.
+struct Estimates {
+    method: &'static str,
+    distances: Vec<Option<u32>>,
+    seconds: f64,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let pages: Vec<u32> = (0..u32::try_from(graph.node_count()).unwrap())
+        .filter(|&node| graph.redirect(node).is_none())
+        .collect();
+    assert!(
+        pages.len() >= 2,
+        "The graph needs two pages to sample pairs of"
+    );
+
+    let mut rng = Rng::seeded(args.seed, &["pairs"]);
+    let pairs: Vec<(u32, u32)> = (0..args.pairs)
+        .map(|_| loop {
+            let from = pages[rng.below(pages.len())];
+            let to = pages[rng.below(pages.len())];
+            if from != to {
+                break (from, to);
+            }
+        })
+        .collect();
+    let endpoints = Endpoints::new(&pairs);
+
+    let estimates = [
+        exact(&graph, &pairs, args.direction),
+        landmarks(&graph, &pages, &pairs, &endpoints, args),
+        sketches(&graph, &pages, &pairs, &endpoints, args),
+    ];
+
+    if let Some(path) = &args.pair_output {
+        write_pairs(path, &graph, &pairs, &estimates)
+            .with_context(|| format!("Failed to write {}", path.display()))
+            .unwrap();
+    }
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .context("Failed to create output file")
+                .unwrap(),
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    write_summary(output, &estimates)
+        .context("Failed to write error statistics")
+        .unwrap();
+}
+
+fn exact(graph: &Graph, pairs: &[(u32, u32)], direction: Direction) -> Estimates {
+    let start = Instant::now();
+    let cancel = Cancel::never();
+    let distances = pairs
+        .iter()
+        .map(|&(from, to)| {
+            let path = graph
+                .shortest_path(from, to, direction, |_| true, &cancel)
+                .unwrap();
+            path.map(|path| u32::try_from(path.len() - 1).unwrap())
+        })
+        .collect();
+    tracing::info!("Found the shortest path of {} pairs", pairs.len());
+    Estimates {
+        method: "exact",
+        distances,
+        seconds: start.elapsed().as_secs_f64(),
+    }
+}
+
+fn landmarks(
+    graph: &Graph,
+    pages: &[u32],
+    pairs: &[(u32, u32)],
+    endpoints: &Endpoints,
+    args: &Args,
+) -> Estimates {
+    let start = Instant::now();
+    let landmarks: Vec<u32> = match args.landmark_choice {
+        LandmarkChoice::Degree => {
+            let mut pages = pages.to_vec();
+            pages.sort_unstable_by_key(|&node| {
+                (
+                    std::cmp::Reverse(graph.in_degree(node) + graph.out_degree(node)),
+                    node,
+                )
+            });
+            pages.truncate(args.landmarks);
+            pages
+        }
+        LandmarkChoice::Random => {
+            let mut rng = Rng::seeded(args.seed, &["landmarks"]);
+            (0..args.landmarks)
+                .map(|_| pages[rng.below(pages.len())])
+                .collect()
+        }
+    };
+
+    // The distance from each endpoint to each landmark, and from each landmark to each endpoint.
+    let tables: Vec<(Distances, Distances)> = landmarks
+        .iter()
+        .map(|&landmark| {
+            let distance = |direction| {
+                let found = nearest(graph, &[landmark], direction);
+                endpoints
+                    .pick(&found)
+                    .into_iter()
+                    .map(|found| found.map(|(_, distance)| distance))
+                    .collect()
+            };
+            (distance(args.direction.reverse()), distance(args.direction))
+        })
+        .collect();
+    tracing::info!("Searched from {} landmarks", landmarks.len());
+
+    let distances = pairs
+        .iter()
+        .map(|&(from, to)| {
+            let (from, to) = (endpoints.slot(from), endpoints.slot(to));
+            tables
+                .iter()
+                .filter_map(|(to_landmark, from_landmark)| {
+                    Some(to_landmark[from]? + from_landmark[to]?)
+                })
+                .min()
+        })
+        .collect();
+    Estimates {
+        method: "landmarks",
+        distances,
+        seconds: start.elapsed().as_secs_f64(),
+    }
+}
+
+fn sketches(
+    graph: &Graph,
+    pages: &[u32],
+    pairs: &[(u32, u32)],
+    endpoints: &Endpoints,
+    args: &Args,
+) -> Estimates {
+    let start = Instant::now();
+    let mut rng = Rng::seeded(args.seed, &["sketches"]);
+    // The nearest seed of each set, and how far it is, from each endpoint and to each endpoint.
+    let mut tables: Vec<(Nearest, Nearest)> = Vec::new();
+    let mut size = 1;
+    while size <= pages.len() {
+        for _ in 0..args.sketch_repetitions {
+            let seeds: Vec<u32> = (0..size).map(|_| pages[rng.below(pages.len())]).collect();
+            tables.push((
+                endpoints.pick(&nearest(graph, &seeds, args.direction.reverse())),
+                endpoints.pick(&nearest(graph, &seeds, args.direction)),
+            ));
+        }
+        size *= 2;
+    }
+    tracing::info!("Built {} seed sets", tables.len());
+
+    let distances = pairs
+        .iter()
+        .map(|&(from, to)| {
+            let (from, to) = (endpoints.slot(from), endpoints.slot(to));
+            tables
+                .iter()
+                .filter_map(|(to_seed, from_seed)| {
+                    let ((seed, before), (other, after)) = (to_seed[from]?, from_seed[to]?);
+                    (seed == other).then_some(before + after)
+                })
+                .min()
+        })
+        .collect();
+    Estimates {
+        method: "sketches",
+        distances,
+        seconds: start.elapsed().as_secs_f64(),
+    }
+}
+
+/// For every node reachable from `sources` in `direction`, the nearest of them, and how many
+/// links away it is. Ties go to whichever the search reaches first.
+fn nearest(graph: &Graph, sources: &[u32], direction: Direction) -> Vec<Option<(u32, u32)>> {
+    let mut found = vec![None; graph.node_count()];
+    let mut frontier = VecDeque::new();
+    for &source in sources {
+        if found[source as usize].is_none() {
+            found[source as usize] = Some((source, 0));
+            frontier.push_back(source);
+        }
+    }
+    while let Some(node) = frontier.pop_front() {
+        let (source, distance) = found[node as usize].unwrap();
+        for neighbour in graph.neighbours(node, direction) {
+            if found[neighbour as usize].is_none() {
+                found[neighbour as usize] = Some((source, distance + 1));
+                frontier.push_back(neighbour);
+            }
+        }
+    }
+    found
+}
+
+/// Write `method,pairs,reachable,estimated,exact,mean_error,mean_relative_error,p90_error,
+/// max_error,seconds` rows as CSV, one per method. Errors are over the reachable pairs with an
+/// estimate, and are never negative, since every estimate is the length of some path.
+#[allow(clippy::cast_precision_loss)]
+fn write_summary(output: Box<dyn Write>, estimates: &[Estimates]) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record([
+        "method",
+        "pairs",
+        "reachable",
+        "estimated",
+        "exact",
+        "mean_error",
+        "mean_relative_error",
+        "p90_error",
+        "max_error",
+        "seconds",
+    ])?;
+    let truth = &estimates[0].distances;
+    for estimate in estimates {
+        let mut errors: Vec<(u32, u32)> = truth
+            .iter()
+            .zip(&estimate.distances)
+            .filter_map(|(&truth, &estimate)| Some((estimate? - truth?, truth?)))
+            .collect();
+        errors.sort_unstable();
+        let count = errors.len().max(1) as f64;
+        let mean = errors
+            .iter()
+            .map(|&(error, _)| f64::from(error))
+            .sum::<f64>()
+            / count;
+        let relative = errors
+            .iter()
+            .map(|&(error, truth)| f64::from(error) / f64::from(truth.max(1)))
+            .sum::<f64>()
+            / count;
+        let quantile = |q: f64| {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let index = ((errors.len() as f64 - 1.0) * q).round() as usize;
+            errors
+                .get(index)
+                .map_or_else(String::new, |&(error, _)| error.to_string())
+        };
+        writer.write_record([
+            estimate.method,
+            &truth.len().to_string(),
+            &truth.iter().flatten().count().to_string(),
+            &errors.len().to_string(),
+            &errors
+                .iter()
+                .filter(|&&(error, _)| error == 0)
+                .count()
+                .to_string(),
+            &format!("{mean:.4}"),
+            &format!("{relative:.4}"),
+            &quantile(0.9),
+            &quantile(1.0),
+            &format!("{:.3}", estimate.seconds),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write `from,to` and a column of distances per method as CSV, empty where there is none.
+fn write_pairs(
+    path: &std::path::Path,
+    graph: &Graph,
+    pairs: &[(u32, u32)],
+    estimates: &[Estimates],
+) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(
+        ["from", "to"]
+            .into_iter()
+            .chain(estimates.iter().map(|estimate| estimate.method)),
+    )?;
+    for (i, &(from, to)) in pairs.iter().enumerate() {
+        writer.write_record(
+            [graph.title(from).to_owned(), graph.title(to).to_owned()]
+                .into_iter()
+                .chain(estimates.iter().map(|estimate| {
+                    estimate.distances[i].map_or_else(String::new, |distance| distance.to_string())
+                })),
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Link graphs as [petgraph](https://docs.rs/petgraph) graphs, for its algorithms, such as A*,
+//! topological sorts, and minimum cuts, with no loader of their own. Nodes are weighted by
+//! their titles, borrowed from the graph file or links the graph is built from:
+//!
+//! ```no_run
+//! use wikigraph::{graph_file::mmap::MmapGraph, petgraph};
+//!
+//! let file = MmapGraph::open("simplewiki.graph".as_ref())?;
+//! let graph = petgraph::from_graph(&file);
+//! println!("{} nodes, {} links", graph.node_count(), graph.edge_count());
+//! # anyhow::Ok(())
+//! ```
+
+use crate::graph_file::mmap::MmapGraph;
+use ::petgraph::{
+    csr::Csr,
+    graph::{DiGraph, NodeIndex},
+    visit::EdgeRef as _,
+};
+use std::collections::{HashMap, HashSet};
+
+/// The graph of `links`, as (source, target) title pairs, with a node for each distinct title
+/// and an edge for each distinct pair, in the order first seen. Titles are compared as they are.
+pub fn from_links<'a>(links: impl IntoIterator<Item = (&'a str, &'a str)>) -> DiGraph<&'a str, ()> {
+    let mut builder = Builder::default();
+    for (source, target) in links {
+        builder.add_link(source, target);
+    }
+    builder.graph
+}
+
+/// The graph of a file saved by `parse --graph`, as the command line searches it: a node for
+/// each of its nodes, with the same index, and an edge for each link. Redirects are nodes too,
+/// linking to their targets, but other links skip them, having been resolved when the graph was
+/// built; `MmapGraph::redirect` says where each leads.
+///
+/// # Panics
+///
+/// If the file has more nodes than `u32` can count, which only a corrupt file has.
+#[must_use]
+pub fn from_graph(graph: &MmapGraph) -> DiGraph<&str, ()> {
+    let node_count = u32::try_from(graph.node_count()).unwrap();
+    let mut petgraph = DiGraph::with_capacity(graph.node_count(), 0);
+    for node in 0..node_count {
+        petgraph.add_node(graph.title(node));
+    }
+    for node in 0..node_count {
+        for &target in graph.links(node) {
+            petgraph.add_edge(
+                NodeIndex::new(node as usize),
+                NodeIndex::new(target as usize),
+                (),
+            );
+        }
+    }
+    petgraph
+}
+
+/// `graph` in compressed sparse row form, which takes less memory and is quicker to traverse,
+/// for algorithms that don't change the graph. Nodes keep their indices.
+#[must_use]
+pub fn to_csr<'a>(graph: &DiGraph<&'a str, ()>) -> Csr<&'a str, ()> {
+    let mut edges: Vec<(u32, u32)> = graph
+        .edge_references()
+        .map(|edge| (index(edge.source()), index(edge.target())))
+        .collect();
+    edges.sort_unstable();
+    let Ok(mut csr) = Csr::from_sorted_edges(&edges) else {
+        unreachable!("The edges are sorted");
+    };
+    // Nodes after the last one with an edge are left out.
+    while csr.node_count() < graph.node_count() {
+        csr.add_node("");
+    }
+    for node in graph.node_indices() {
+        csr[index(node)] = graph[node];
+    }
+    csr
+}
+
+#[derive(Default)]
+struct Builder<'a> {
+    graph: DiGraph<&'a str, ()>,
+    nodes: HashMap<&'a str, NodeIndex>,
+    edges: HashSet<(NodeIndex, NodeIndex)>,
+}
+
+impl<'a> Builder<'a> {
+    fn node(&mut self, title: &'a str) -> NodeIndex {
+        *self
+            .nodes
+            .entry(title)
+            .or_insert_with(|| self.graph.add_node(title))
+    }
+
+    fn add_link(&mut self, source: &'a str, target: &'a str) {
+        let (source, target) = (self.node(source), self.node(target));
+        if self.edges.insert((source, target)) {
+            self.graph.add_edge(source, target, ());
+        }
+    }
+}
+
+fn index(node: NodeIndex) -> u32 {
+    u32::try_from(node.index()).unwrap()
+}
@@ -0,0 +1,49 @@
+//! Crude wikitext-to-text conversion, good enough for snippets and full-text indexing.
+
+use crate::template::template_len;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Strip inline markup from a snippet: links become their anchor text, bold and italic quotes
+/// disappear, and whitespace is collapsed.
+pub fn strip_markup(snippet: &str) -> String {
+    static LINK: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\[\[(?:[^\[\]|]*\|)?([^\[\]]*)\]\]").unwrap());
+
+    let snippet = LINK.replace_all(snippet, "$1");
+    let snippet = snippet.replace("'''", "").replace("''", "");
+    snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The readable prose of a whole article: templates, tables, references, comments, HTML tags,
+/// headings markup, and category/file links are dropped before stripping inline markup.
+pub fn article(text: &str) -> String {
+    static NOISE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(concat!(
+            r"(?s)<!--.*?-->",
+            r"|<ref[^>]*/>",
+            r"|<ref[^>]*>.*?</ref>",
+            r"|\{\|.*?\|\}",
+            r"|\[\[\s*(?i:file|image|category)\s*:[^\[\]]*(?:\[\[[^\[\]]*\]\][^\[\]]*)*\]\]",
+            r"|<[^>]+>",
+            r"|={2,}",
+        ))
+        .unwrap()
+    });
+
+    let text = strip_templates(text);
+    let text = NOISE.replace_all(&text, " ");
+    strip_markup(&text)
+}
+
+fn strip_templates(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let len = template_len(&rest[start..]).unwrap_or(rest.len() - start);
+        rest = &rest[start + len..];
+    }
+    output.push_str(rest);
+    output
+}
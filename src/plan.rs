@@ -0,0 +1,311 @@
+//! `parse --dry-run`: what a parse would read, do, and write, worked out from the options and a
+//! look at the input instead of a parse, so that a mistake shows up before a job of several
+//! hours starts rather than after it ends.
+
+use crate::{dump, export, project, red_link, remote, rules, script, siteinfo, ParseArgs};
+use anyhow::Context as _;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+/// A rough figure for the memory each page takes in the graph: its interned title, its entry in
+/// the link map, and a few dozen four-byte link targets.
+const BYTES_PER_PAGE: u64 = 400;
+
+/// Check the options, probe the input, and print the plan.
+#[allow(clippy::too_many_lines)]
+pub fn print(args: &ParseArgs) -> anyhow::Result<()> {
+    if let Some(path) = &args.link_rules {
+        rules::Rules::load(path).context("Failed to load link rules")?;
+    }
+    if let Some(path) = &args.script {
+        script::Hook::load(path).context("Failed to load script")?;
+    }
+
+    let pages = probe(&args.input, args)?;
+    if let Some(old) = &args.diff_from {
+        println!("Compared with:");
+        probe(old, args)?;
+    }
+
+    let stages: Vec<&str> = args
+        .normalization()
+        .iter()
+        .map(|stage| stage.name())
+        .collect();
+    let namespaces: Vec<String> = args.namespaces.iter().map(ToString::to_string).collect();
+    println!("Namespaces: {}", namespaces.join(", "));
+    println!("Normalization: {}", none_if_empty(&stages.join(", ")));
+    let classes: Vec<String> = args.link_classes.iter().map(value_name).collect();
+    println!("Link classes: {}", none_if_empty(&classes.join(", ")));
+    if !args.edge_types.is_empty() {
+        let edge_types: Vec<&str> = args
+            .edge_types
+            .iter()
+            .map(|edge_type| edge_type.name())
+            .collect();
+        println!("Edge types: {}", edge_types.join(", "));
+    }
+    println!(
+        "Disambiguation pages: {}, by {}",
+        if args.exclude_disambiguation {
+            "excluded"
+        } else {
+            "tagged"
+        },
+        none_if_empty(&crate::disambiguation_templates(args, &args.input).join(", "))
+    );
+    match (args.red_links, &args.red_link_report) {
+        (red_link::Policy::Report, Some(path)) => {
+            println!("Red links: dropped, listed in {}", path.display());
+        }
+        (policy, _) => println!("Red links: {}", value_name(&policy)),
+    }
+    print_limits(args);
+    if let Some(dir) = &args.cache {
+        println!(
+            "Cache: {}, reused if the dump and parse options are unchanged",
+            dir.display()
+        );
+    }
+    if let Some(file) = &args.checkpoint {
+        println!(
+            "Checkpoint: {} every {}s{}",
+            file.display(),
+            args.checkpoint_interval,
+            if args.resume {
+                ", resumed from if it exists"
+            } else {
+                ""
+            }
+        );
+    }
+
+    let outputs = outputs(args);
+    if let (Some(format), None) = (&args.format, &args.output) {
+        println!(
+            "Outputs: {} to standard output, written while parsing",
+            value_name(format)
+        );
+    } else if outputs.is_empty() {
+        println!("Outputs: none");
+    } else {
+        println!("Outputs:");
+        for (flag, path, when) in outputs {
+            println!("  --{flag} {} ({when})", path.display());
+            if let Some(count) = args.shards.filter(|_| flag == "output") {
+                println!(
+                    "    in {count} shards, {} to {}",
+                    export::edge_list::shard_path(path, 0, count).display(),
+                    export::edge_list::shard_path(path, count - 1, count).display()
+                );
+            }
+        }
+    }
+
+    if let Some(pages) = pages.filter(|_| args.format.is_none()) {
+        // The graph of `--diff-from` is held at the same time.
+        let graphs = if args.diff_from.is_some() { 2 } else { 1 };
+        let bytes = u64::try_from(pages)? * BYTES_PER_PAGE * graphs;
+        println!("Estimated memory for the graph: {}", size(bytes));
+    }
+    if let Some(limit) = args.memory_limit {
+        let dir = args.spill_dir.clone().unwrap_or_else(env::temp_dir);
+        println!(
+            "Links: at most {} in memory, the rest spilled to {}",
+            size(limit),
+            dir.display()
+        );
+    }
+    if args.snapshots.is_some() {
+        println!(
+            "Snapshots keep every revision's links, so need more memory the longer the history"
+        );
+    }
+    Ok(())
+}
+
+/// Print which pages and links of the dump are left out or cut down.
+fn print_limits(args: &ParseArgs) {
+    if args.no_template_links {
+        println!("Links from templates: only those of the link rules");
+    }
+    if let Some(max) = args.max_page_bytes {
+        println!("Pages over {max} bytes: {}", value_name(&args.oversized));
+    }
+    if let Some(max) = args.max_links {
+        println!("Links per page: at most {max}");
+    }
+    if let Some(p) = args.edge_sample {
+        println!("Links kept: {:.1}%, seed {}", p * 100.0, args.seed);
+    }
+    if let Some(skip) = args.skip_pages {
+        println!("Pages skipped: the first {skip}");
+    }
+    if let Some(p) = args.sample {
+        println!("Pages kept: {:.1}%, seed {}", p * 100.0, args.seed);
+    }
+    if let Some(max) = args.max_pages {
+        println!("Pages read: at most {max}");
+    }
+}
+
+/// Print what the dump at `path` is and how it would be read, returning how many pages its
+/// index lists, if it has one.
+fn probe(path: &Path, args: &ParseArgs) -> anyhow::Result<Option<usize>> {
+    if path == Path::new("-") {
+        println!("Input: standard input, not probed");
+        return Ok(None);
+    }
+    if remote::is_url(path) {
+        println!("Input: {}, downloaded, not probed", path.display());
+        return Ok(None);
+    }
+    let len = fs::metadata(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .len();
+    let compression = dump::Compression::detect(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    println!(
+        "Input: {} ({}, {})",
+        path.display(),
+        size(len),
+        compression.name()
+    );
+    println!("Project: {}", value_name(&project(args, path)));
+    if let Some(language) = crate::language(args, path) {
+        println!("Language: {language}");
+    }
+    if args.siteinfo {
+        let siteinfo = siteinfo::Siteinfo::read(path, &remote::Options::default())
+            .context("Failed to read siteinfo")?;
+        println!(
+            "Namespace names: {} from the siteinfo, and the English ones; titles {}",
+            siteinfo.len(),
+            if siteinfo.case_sensitive {
+                "case-sensitive"
+            } else {
+                "capitalized"
+            }
+        );
+    }
+    if let Some(language) = crate::variants(args, path) {
+        println!("Variants: {}", value_name(&language));
+    }
+    if let Some(shard) = args.shard {
+        println!("Shard: {shard}");
+    }
+
+    let index = (path == args.input)
+        .then(|| args.index.clone())
+        .flatten()
+        .or_else(|| dump::index_path(path));
+    let Some(index) = index else {
+        println!("Pages: unknown without an index; read on one thread");
+        return Ok(None);
+    };
+    let pages = dump::index_pages(path, &index, args.shard).context("Failed to read dump index")?;
+    let threads = args.threads.map_or_else(
+        || thread::available_parallelism().map_or(1, usize::from),
+        usize::from,
+    );
+    println!(
+        "Pages: {pages} in all namespaces, listed in {}; read on {threads} {}",
+        index.display(),
+        if threads == 1 { "thread" } else { "threads" }
+    );
+    Ok(Some(pages))
+}
+
+/// Every output the options ask for: its flag, its path, and when it is written.
+fn outputs(args: &ParseArgs) -> Vec<(&'static str, &Path, &'static str)> {
+    static NONE: Option<PathBuf> = None;
+    // With `--format`, `--output` is the stream of pages.
+    let (streamed, edge_list) = if args.format.is_some() {
+        (&args.output, &NONE)
+    } else {
+        (&NONE, &args.output)
+    };
+    let while_parsing = [
+        ("output", streamed),
+        ("titles", &args.titles),
+        ("link-contexts", &args.link_contexts),
+        ("page-stream", &args.page_stream),
+        ("page-json", &args.page_json),
+        ("anchor-text", &args.anchor_text),
+        ("snapshots", &args.snapshots),
+    ];
+    let collected = [
+        ("anchor-stats", &args.anchor_stats),
+        ("normalization-audit", &args.normalization_audit),
+        ("redirect-report", &args.redirect_report),
+        ("cooccurrence", &args.cooccurrence),
+        ("template-usage", &args.template_usage),
+    ];
+    let after_parsing = [
+        ("graph", &args.graph),
+        ("partial", &args.partial),
+        ("diff-report", &args.diff_report),
+        ("sort-index", &args.sort_index),
+        ("category-index", &args.category_index),
+        ("category-graph", &args.category_graph),
+        ("output", edge_list),
+        ("gephi", &args.gephi),
+        ("neo4j", &args.neo4j),
+        ("condensed", &args.condensed),
+        ("graphology", &args.graphology),
+        ("npy", &args.npy),
+        ("pyg", &args.pyg),
+        ("namespace-partitions", &args.namespace_partitions),
+        ("report", &args.report),
+    ];
+    let when = [
+        (&while_parsing[..], "written while parsing"),
+        (
+            &collected[..],
+            "collected in memory while parsing, written after",
+        ),
+        (&after_parsing[..], "written from the finished graph"),
+    ];
+    when.into_iter()
+        .flat_map(|(outputs, when)| {
+            outputs
+                .iter()
+                .filter_map(move |(flag, path)| Some((*flag, path.as_deref()?, when)))
+        })
+        .collect()
+}
+
+/// The name of `value` on the command line.
+fn value_name(value: &impl clap::ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map_or_else(String::new, |value| String::from(value.get_name()))
+}
+
+fn none_if_empty(list: &str) -> &str {
+    if list.is_empty() {
+        "none"
+    } else {
+        list
+    }
+}
+
+/// `bytes` in the largest binary unit that keeps the number at least 1.
+fn size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KiB", "MiB", "GiB", "TiB"];
+    #[allow(clippy::cast_precision_loss)]
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} bytes")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
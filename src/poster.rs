@@ -0,0 +1,225 @@
+//! A "poster map" of a saved graph in one command: the most central pages, the shortest paths
+//! that join them up, a force-directed layout, and a visualization export sized by centrality.
+
+use crate::{
+    cancel::Cancel,
+    export,
+    graph::{Adjacency as _, Direction, Graph},
+    layout, provenance, stats, workspace,
+};
+use anyhow::Context as _;
+use lasso::{Key as _, Rodeo};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Where to write the export: a JSON file for graphology, a directory for Gephi
+    #[arg(long, short, value_name = "PATH")]
+    output: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = Format::Graphology)]
+    format: Format,
+
+    /// Number of most central pages to keep
+    #[arg(long, value_name = "K", default_value_t = 100)]
+    top: usize,
+
+    /// How to rank pages. PageRank is read from the graph file if `stats --pagerank` stored it,
+    /// and computed with its defaults otherwise.
+    #[arg(long, value_enum, default_value_t = Centrality::Pagerank)]
+    centrality: Centrality,
+
+    /// Join each top page that links to no other top page to its nearest one through paths of
+    /// up to this many links, in either direction; 0 keeps only the top pages
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    max_hops: usize,
+
+    /// Number of force-directed layout iterations
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    layout_iterations: usize,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    /// graphology JSON for sigma.js, with node positions and sizes
+    Graphology,
+    /// Gephi CSV node and edge lists, with node positions and sizes as columns
+    Gephi,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Centrality {
+    Pagerank,
+    /// Number of incoming links
+    InDegree,
+    /// Number of incoming and outgoing links
+    Degree,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+
+    let scores = scores(&graph, args.centrality);
+    let top = top(&scores, args.top);
+    let mut keep = vec![false; graph.node_count()];
+    for &node in &top {
+        keep[node as usize] = true;
+    }
+    let hubs = keep.clone();
+    for &node in &top {
+        if let Some(path) = path_to_hub(&graph, &hubs, node, args.max_hops) {
+            for node in path {
+                keep[node as usize] = true;
+            }
+        }
+    }
+
+    let subgraph = graph.subgraph(&keep);
+    let mut rodeo = Rodeo::new();
+    let mut wiki = subgraph.to_wiki(&mut rodeo);
+    let edges: Vec<(usize, usize)> = wiki
+        .links
+        .iter()
+        .flat_map(|(source, links)| {
+            links
+                .iter()
+                .map(|target| (source.into_usize(), target.into_usize()))
+        })
+        .collect();
+    let positions = layout::force_atlas2(rodeo.len(), &edges, args.layout_iterations);
+
+    // The subgraph numbers the kept nodes in their original order, as does `rodeo`.
+    let originals = keep
+        .iter()
+        .enumerate()
+        .filter(|&(_, &kept)| kept)
+        .map(|(node, _)| node);
+    let max_score = top.first().map_or(0.0, |&node| scores[node as usize]);
+    for ((key, _), original) in rodeo.iter().zip(originals) {
+        let score: f64 = scores[original];
+        let mut attributes = serde_json::Map::new();
+        attributes.insert(String::from("centrality"), score.into());
+        // sigma.js draws nodes with this radius in pixels; path nodes get the smallest.
+        let size = if max_score > 0.0 {
+            2.0 + 18.0 * (score / max_score).sqrt()
+        } else {
+            2.0
+        };
+        attributes.insert(String::from("size"), size.into());
+        attributes.insert(String::from("hub"), hubs[original].into());
+        if let Format::Gephi = args.format {
+            // The graphology export has its own `x`/`y` attributes.
+            let (x, y) = positions[key.into_usize()];
+            attributes.insert(String::from("x"), x.into());
+            attributes.insert(String::from("y"), y.into());
+        }
+        wiki.node_attributes.insert(key, attributes);
+    }
+
+    match args.format {
+        Format::Graphology => {
+            export::graphology::write(
+                &args.output,
+                &rodeo,
+                &wiki,
+                Some(&positions),
+                graph.metadata(),
+            )
+            .context("Failed to write graphology export")
+            .unwrap();
+        }
+        Format::Gephi => {
+            export::gephi::write(&args.output, &rodeo, &wiki, 1)
+                .and_then(|()| provenance::write_sidecar(&args.output, graph.metadata()))
+                .context("Failed to write Gephi export")
+                .unwrap();
+        }
+    }
+
+    println!(
+        "{} top pages and {} on paths between them, with {} links",
+        top.len(),
+        subgraph.node_count() - top.len(),
+        subgraph.edge_count()
+    );
+}
+
+/// Centrality of every node.
+fn scores(graph: &Graph, centrality: Centrality) -> Vec<f64> {
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    match centrality {
+        Centrality::Pagerank => {
+            if let Some(pagerank) = graph.stats().and_then(|stats| stats.pagerank.as_ref()) {
+                pagerank.scores.clone()
+            } else {
+                tracing::info!("Computing PageRank");
+                stats::pagerank_scores(graph, stats::DAMPING, stats::ITERATIONS, &Cancel::never())
+                    .unwrap()
+            }
+        }
+        Centrality::InDegree => nodes.map(|node| f64::from(graph.in_degree(node))).collect(),
+        Centrality::Degree => nodes
+            .map(|node| f64::from(graph.in_degree(node) + graph.out_degree(node)))
+            .collect(),
+    }
+}
+
+/// The `k` highest-scoring nodes, highest first, ties broken by node ID.
+fn top(scores: &[f64], k: usize) -> Vec<u32> {
+    let by_score = |a: &u32, b: &u32| {
+        scores[*b as usize]
+            .total_cmp(&scores[*a as usize])
+            .then(a.cmp(b))
+    };
+    let mut nodes: Vec<u32> = (0..u32::try_from(scores.len()).unwrap()).collect();
+    if k < nodes.len() {
+        nodes.select_nth_unstable_by(k, by_score);
+        nodes.truncate(k);
+    }
+    nodes.sort_unstable_by(by_score);
+    nodes
+}
+
+/// The nodes between `start` and the nearest other hub, by a breadth-first search of up to
+/// `max_hops` links in either direction, or `None` if it is adjacent to one already or there
+/// is none that close.
+fn path_to_hub(graph: &Graph, hubs: &[bool], start: u32, max_hops: usize) -> Option<Vec<u32>> {
+    let is_other_hub = |node: u32| node != start && hubs[node as usize];
+    if max_hops == 0 || graph.neighbours(start, Direction::Both).any(is_other_hub) {
+        return None;
+    }
+
+    let mut parents = HashMap::from([(start, start)]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((node, hops)) = queue.pop_front() {
+        if hops == max_hops {
+            continue;
+        }
+        for neighbour in graph.neighbours(node, Direction::Both) {
+            if parents.contains_key(&neighbour) {
+                continue;
+            }
+            parents.insert(neighbour, node);
+            if is_other_hub(neighbour) {
+                let mut path = Vec::new();
+                let mut node = node;
+                while node != start {
+                    path.push(node);
+                    node = parents[&node];
+                }
+                return Some(path);
+            }
+            queue.push_back((neighbour, hops + 1));
+        }
+    }
+    None
+}
@@ -0,0 +1,506 @@
+//! Per-project conventions for Wikimedia sister projects, so their dumps don't fill the graph
+//! with nodes for project namespaces or relative subpage links.
+
+use crate::{
+    audit::Reason,
+    link_class::{self, LinkClass},
+    normalize::Stage,
+    siteinfo::{self, Siteinfo},
+    template::{self, template_eq, template_len},
+    variant,
+};
+use std::{borrow::Cow, path::Path, sync::Arc};
+
+/// Namespaces present on every MediaWiki site, none of which hold content.
+const COMMON_NAMESPACES: &[&str] = &[
+    "Media",
+    "Special",
+    "Talk",
+    "User",
+    "User talk",
+    "File",
+    "File talk",
+    "Image",
+    "Image talk",
+    "MediaWiki",
+    "MediaWiki talk",
+    "Template",
+    "Template talk",
+    "Help",
+    "Help talk",
+    "Category",
+    "Category talk",
+    "Module",
+    "Module talk",
+    "TimedText",
+    "TimedText talk",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Project {
+    Wikipedia,
+    Wikivoyage,
+    Wikibooks,
+    Wikisource,
+    Wiktionary,
+}
+
+impl Project {
+    /// Guess the project from a dump file name like `enwikivoyage-20240601-pages-articles.xml.bz2`.
+    pub fn detect(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        [
+            Self::Wikivoyage,
+            Self::Wikibooks,
+            Self::Wikisource,
+            Self::Wiktionary,
+        ]
+        .into_iter()
+        .find(|project| name.contains(project.profile().name))
+        .unwrap_or(Self::Wikipedia)
+    }
+
+    pub fn profile(self) -> &'static Profile {
+        match self {
+            Self::Wikipedia => &WIKIPEDIA,
+            Self::Wikivoyage => &WIKIVOYAGE,
+            Self::Wikibooks => &WIKIBOOKS,
+            Self::Wikisource => &WIKISOURCE,
+            Self::Wiktionary => &WIKTIONARY,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Profile {
+    /// Lowercase project name, as it appears in database and dump file names.
+    name: &'static str,
+    /// Project-specific namespaces that don't hold content, in addition to the common ones and
+    /// the project namespace itself.
+    namespaces: &'static [&'static str],
+    /// Whether `[[/Chapter]]` and `[[../]]` links are relative to the current page.
+    subpages: bool,
+    /// Templates rendering banners, headers, or navigation boxes, whose links are page chrome
+    /// rather than content.
+    banner_templates: &'static [&'static str],
+    /// Templates whose arguments are links, as (template name, 1-based index of the first
+    /// positional argument holding a link target, whether all following arguments do too).
+    template_links: &'static [(&'static str, usize, bool)],
+    /// Whether titles may start with a lowercase letter, so `Stage::CaseFold` leaves them be.
+    case_sensitive: bool,
+    /// Normalization stages applied to link targets, in order.
+    stages: Cow<'static, [Stage]>,
+    /// Classes of link targets kept as links.
+    classes: Cow<'static, [LinkClass]>,
+    /// How titles convert between scripts, on wikis that show pages in several.
+    variants: Option<Arc<variant::Rules>>,
+    /// The dump's own namespace names, recognized along with the English ones.
+    site: Option<Arc<Siteinfo>>,
+}
+
+/// Wikipedia's hatnotes link to related articles through templates: `{{main|Foo}}`,
+/// `{{see also|Bar|Baz}}`, `{{for|other uses|Qux}}`. `{{sortname|First|Last|Article}}` only
+/// counts when it names the article; without one it links to "First Last", which is no argument.
+static WIKIPEDIA: Profile = Profile {
+    name: "wikipedia",
+    namespaces: &["Portal", "Portal talk", "Draft", "Draft talk", "WP"],
+    subpages: false,
+    banner_templates: &[],
+    template_links: &[
+        ("Main", 1, true),
+        ("Main article", 1, true),
+        ("Main list", 1, false),
+        ("See also", 1, true),
+        ("Further", 1, true),
+        ("Further information", 1, true),
+        ("Details", 1, false),
+        ("Broader", 1, true),
+        ("Distinguish", 1, true),
+        ("For", 2, true),
+        ("Excerpt", 1, false),
+        ("Annotated link", 1, false),
+        ("Section link", 1, false),
+        ("Sortname", 3, false),
+    ],
+    case_sensitive: false,
+    stages: Cow::Borrowed(&Stage::ALL),
+    classes: Cow::Borrowed(&[LinkClass::Article]),
+    variants: None,
+    site: None,
+};
+
+static WIKIVOYAGE: Profile = Profile {
+    name: "wikivoyage",
+    namespaces: &["WV"],
+    subpages: false,
+    banner_templates: &["Pagebanner", "Quickbar"],
+    template_links: &[],
+    case_sensitive: false,
+    stages: Cow::Borrowed(&Stage::ALL),
+    classes: Cow::Borrowed(&[LinkClass::Article]),
+    variants: None,
+    site: None,
+};
+
+static WIKIBOOKS: Profile = Profile {
+    name: "wikibooks",
+    namespaces: &[
+        "WB",
+        "Transwiki",
+        "Transwiki talk",
+        "Subject",
+        "Subject talk",
+    ],
+    subpages: true,
+    banner_templates: &["BookCat"],
+    template_links: &[],
+    case_sensitive: false,
+    stages: Cow::Borrowed(&Stage::ALL),
+    classes: Cow::Borrowed(&[LinkClass::Article]),
+    variants: None,
+    site: None,
+};
+
+static WIKISOURCE: Profile = Profile {
+    name: "wikisource",
+    namespaces: &[
+        "WS",
+        "Portal",
+        "Portal talk",
+        "Author talk",
+        "Page",
+        "Page talk",
+        "Index",
+        "Index talk",
+        "Translation talk",
+    ],
+    subpages: true,
+    banner_templates: &["Header", "Author"],
+    template_links: &[],
+    case_sensitive: false,
+    stages: Cow::Borrowed(&Stage::ALL),
+    classes: Cow::Borrowed(&[LinkClass::Article]),
+    variants: None,
+    site: None,
+};
+
+/// Wiktionary entries link to each other almost entirely through templates: `{{l|en|word}}`
+/// and `{{m|la|verbum}}` for links and mentions, `{{der|en|la|verbum}}` for etymologies,
+/// `{{syn|en|a|b}}` for semantic relations.
+static WIKTIONARY: Profile = Profile {
+    name: "wiktionary",
+    namespaces: &[
+        "WT",
+        "Citations",
+        "Citations talk",
+        "Index",
+        "Index talk",
+        "Rhymes",
+        "Rhymes talk",
+        "Transwiki",
+        "Transwiki talk",
+    ],
+    subpages: false,
+    banner_templates: &[],
+    template_links: &[
+        ("l", 2, false),
+        ("link", 2, false),
+        ("ll", 2, false),
+        ("l-self", 2, false),
+        ("m", 2, false),
+        ("mention", 2, false),
+        ("cog", 2, false),
+        ("cognate", 2, false),
+        ("der", 3, false),
+        ("derived", 3, false),
+        ("inh", 3, false),
+        ("inherited", 3, false),
+        ("bor", 3, false),
+        ("borrowed", 3, false),
+        ("syn", 2, true),
+        ("synonyms", 2, true),
+        ("ant", 2, true),
+        ("antonyms", 2, true),
+        ("hyper", 2, true),
+        ("hypo", 2, true),
+        ("col", 2, true),
+        ("col3", 2, true),
+        ("der3", 2, true),
+    ],
+    case_sensitive: true,
+    stages: Cow::Borrowed(&Stage::ALL),
+    classes: Cow::Borrowed(&[LinkClass::Article]),
+    variants: None,
+    site: None,
+};
+
+impl Profile {
+    /// This profile with `stages` as its normalization stages.
+    pub fn with_stages(&self, stages: Vec<Stage>) -> Self {
+        Self {
+            stages: Cow::Owned(stages),
+            ..self.clone()
+        }
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// This profile keeping links of `classes`.
+    pub fn with_link_classes(&self, classes: Vec<LinkClass>) -> Self {
+        Self {
+            classes: Cow::Owned(classes),
+            ..self.clone()
+        }
+    }
+
+    /// This profile taking no links from templates.
+    pub fn without_template_links(&self) -> Self {
+        Self {
+            template_links: &[],
+            ..self.clone()
+        }
+    }
+
+    /// This profile comparing titles as converted by `variants`.
+    pub fn with_variants(&self, variants: Option<variant::Rules>) -> Self {
+        Self {
+            variants: variants.map(Arc::new),
+            ..self.clone()
+        }
+    }
+
+    pub fn variants(&self) -> Option<&variant::Rules> {
+        self.variants.as_deref()
+    }
+
+    /// This profile naming namespaces, and capitalizing titles, as `site` says.
+    pub fn with_siteinfo(&self, site: Option<Siteinfo>) -> Self {
+        Self {
+            case_sensitive: site
+                .as_ref()
+                .map_or(self.case_sensitive, |site| site.case_sensitive),
+            site: site.map(Arc::new),
+            ..self.clone()
+        }
+    }
+
+    /// Whether `prefix` names the category namespace.
+    pub fn is_category(&self, prefix: &str) -> bool {
+        namespace_eq(prefix, "Category") || self.namespace(prefix) == Some(siteinfo::CATEGORY)
+    }
+
+    /// Whether `prefix` names the file namespace, which embeds the file it links to.
+    pub fn is_file(&self, prefix: &str) -> bool {
+        namespace_eq(prefix, "File")
+            || namespace_eq(prefix, "Image")
+            || self.namespace(prefix) == Some(siteinfo::FILE)
+    }
+
+    /// The name of the namespace `key`, in the dump's language if known, else `english`.
+    pub fn namespace_name<'a>(&'a self, key: i64, english: &'a str) -> &'a str {
+        self.site
+            .as_ref()
+            .and_then(|site| site.name(key))
+            .unwrap_or(english)
+    }
+
+    /// The key of the namespace named `prefix` in the dump's siteinfo.
+    fn namespace(&self, prefix: &str) -> Option<i64> {
+        self.site.as_ref().and_then(|site| site.namespace(prefix))
+    }
+
+    /// What the raw link target `target` points at.
+    pub fn classify(&self, target: &str) -> LinkClass {
+        let colon = target.trim_start().starts_with(':');
+        let Some((prefix, _)) = target.split_once(':') else {
+            return LinkClass::Article;
+        };
+        let prefix = prefix.trim().trim_start_matches(':').trim();
+        if colon && prefix.is_empty() {
+            // `[[:Category:Birds]]` and `[[:Cat]]`, going by what follows the colon.
+            return match self.classify(target.trim_start()[1..].trim_start()) {
+                LinkClass::Category | LinkClass::File => LinkClass::Namespace,
+                LinkClass::Language => LinkClass::Interwiki,
+                class => class,
+            };
+        }
+        let key = self.namespace(prefix);
+        if self.is_category(prefix) {
+            LinkClass::Category
+        } else if matches!(key, Some(siteinfo::FILE | siteinfo::MEDIA))
+            || ["File", "Image", "Media"]
+                .iter()
+                .any(|namespace| namespace_eq(namespace, prefix))
+        {
+            LinkClass::File
+        } else if key.is_some_and(siteinfo::is_meta) || self.is_meta_namespace(prefix) {
+            LinkClass::Namespace
+        } else if link_class::is_interwiki(prefix) {
+            LinkClass::Interwiki
+        } else if link_class::is_language(prefix) {
+            LinkClass::Language
+        } else {
+            LinkClass::Article
+        }
+    }
+
+    /// Turn a raw link target found on page `title` into the title it refers to, or `None` if it
+    /// isn't of a kept class.
+    pub fn resolve<'a>(&self, title: &str, target: &'a str) -> Option<Cow<'a, str>> {
+        self.resolve_traced(title, target, |_, _, _| {})
+    }
+
+    /// Like `resolve`, calling `trace` with the target before and after each step that changed
+    /// it, and why.
+    pub fn resolve_traced<'a>(
+        &self,
+        title: &str,
+        target: &'a str,
+        mut trace: impl FnMut(&str, &str, Reason),
+    ) -> Option<Cow<'a, str>> {
+        let class = self.classify(target);
+        if !self.classes.contains(&class) {
+            return None;
+        }
+
+        let mut resolved = Cow::Borrowed(target);
+        if let Some(rest) = target.trim_start().strip_prefix(':') {
+            trace(target, rest, Reason::LeadingColon);
+            resolved = Cow::Borrowed(rest);
+        }
+        if self.subpages && (target.starts_with('/') || target.starts_with("../")) {
+            let subpage = resolve_subpage(title, target)?;
+            trace(target, &subpage, Reason::Subpage);
+            resolved = Cow::Owned(subpage);
+        }
+
+        for &stage in self.stages.iter() {
+            let result = if stage == Stage::CaseFold && class.leaves_wiki() {
+                // The case of the title is up to the other wiki, while prefixes are lowercase.
+                lowercase_prefix(resolved.clone())
+            } else {
+                stage.apply(title, resolved.clone(), self.case_sensitive)
+            };
+            if result != resolved {
+                trace(&resolved, &result, Reason::Stage(stage));
+            }
+            resolved = result;
+        }
+
+        Some(resolved)
+    }
+
+    fn is_meta_namespace(&self, prefix: &str) -> bool {
+        // Project namespaces are named after the project ("Wikibooks", "Wikibooks talk").
+        let project = prefix
+            .strip_suffix(" talk")
+            .or_else(|| prefix.strip_suffix("_talk"))
+            .unwrap_or(prefix);
+        project.eq_ignore_ascii_case(self.name)
+            || COMMON_NAMESPACES
+                .iter()
+                .chain(self.namespaces)
+                .any(|namespace| namespace_eq(namespace, prefix))
+    }
+
+    /// Link targets found in the arguments of link-producing templates.
+    pub fn template_links<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        if self.template_links.is_empty() {
+            return Vec::new();
+        }
+        template::templates(text)
+            .flat_map(|template| self.link_template_targets(&template))
+            .collect()
+    }
+
+    /// Link targets among the arguments of `template`, if it is a link-producing template.
+    pub fn link_template_targets<'a>(&self, template: &template::Template<'a>) -> Vec<&'a str> {
+        self.template_links
+            .iter()
+            .find(|(name, _, _)| template.is(name))
+            .map(|&(_, first, repeated)| template.link_args(first, repeated).collect())
+            .unwrap_or_default()
+    }
+
+    /// Remove banner template invocations (including nested templates) from `text`.
+    pub fn strip_banners<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.banner_templates.is_empty() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut output = String::new();
+        let mut rest = text;
+        let mut stripped = false;
+
+        while let Some(start) = rest.find("{{") {
+            let name = rest[start + 2..]
+                .split(['|', '}', '\n'])
+                .next()
+                .unwrap_or_default()
+                .trim();
+            if !self
+                .banner_templates
+                .iter()
+                .any(|banner| template_eq(banner, name))
+            {
+                output.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                continue;
+            }
+            output.push_str(&rest[..start]);
+            let len = template_len(&rest[start..]).unwrap_or(rest.len() - start);
+            rest = &rest[start + len..];
+            stripped = true;
+        }
+
+        if stripped {
+            output.push_str(rest);
+            Cow::Owned(output)
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
+
+/// Resolve a relative subpage link (`/Child`, `../Sibling`, `../`) against `title`.
+fn resolve_subpage(title: &str, target: &str) -> Option<String> {
+    let mut base: Vec<&str> = title.split('/').collect();
+    let mut rest = target;
+    while let Some(stripped) = rest.strip_prefix("../") {
+        base.pop();
+        rest = stripped;
+    }
+    if base.is_empty() {
+        return None;
+    }
+    let rest = rest.trim_start_matches('/').trim_end_matches('/');
+    let mut resolved = base.join("/");
+    if !rest.is_empty() {
+        resolved.push('/');
+        resolved.push_str(rest);
+    }
+    Some(resolved)
+}
+
+/// `target` with the prefix before its first colon in lowercase.
+fn lowercase_prefix(target: Cow<str>) -> Cow<str> {
+    match target.split_once(':') {
+        Some((prefix, rest)) if prefix.chars().any(char::is_uppercase) => {
+            Cow::Owned(format!("{}:{rest}", prefix.to_lowercase()))
+        }
+        _ => target,
+    }
+}
+
+/// Namespace names are case-insensitive, and underscores are interchangeable with spaces.
+fn namespace_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes().zip(b.bytes()).all(|(a, b)| {
+            a.eq_ignore_ascii_case(&b) || matches!((a, b), (b' ', b'_') | (b'_', b' '))
+        })
+}
@@ -0,0 +1,196 @@
+//! Feedback during long parses: how much of the dump has been read, how many pages parsed, how
+//! fast, and how long the rest will take. Progress is measured in compressed bytes read from
+//! the dump rather than in pages, whose total isn't known until the end. On a terminal it is a
+//! progress bar; otherwise, such as when logging to a file, a line is logged now and then.
+
+use indicatif::{HumanBytes, HumanCount, HumanDuration, ProgressBar, ProgressStyle};
+use std::{
+    io::{self, IsTerminal as _},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the bar is redrawn.
+const DRAW_INTERVAL: Duration = Duration::from_millis(250);
+/// How often progress is logged when standard error isn't a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Counters shared with the threads reading the dump, and the thread reporting them.
+#[derive(Default)]
+struct Counters {
+    bytes_read: Arc<AtomicU64>,
+    /// Bytes to read in all, or 0 while unknown, as for standard input.
+    total_bytes: AtomicU64,
+    pages: AtomicU64,
+    malformed: Arc<AtomicU64>,
+    /// Pages added to the graph, and how many read pages were waiting to be parsed then.
+    built: AtomicU64,
+    backlog: AtomicU64,
+    done: AtomicBool,
+}
+
+/// Reports the progress of a parse on another thread until dropped.
+pub struct Progress {
+    counters: Arc<Counters>,
+    reporter: Option<thread::JoinHandle<()>>,
+}
+
+/// The counters of a `Progress`, for reporting them elsewhere, such as in `metrics`.
+#[derive(Clone)]
+pub struct Handle(Arc<Counters>);
+
+/// The counters at some moment.
+#[derive(Clone, Copy, Default)]
+pub struct Snapshot {
+    pub bytes_read: u64,
+    /// Bytes to read in all, or 0 while unknown.
+    pub total_bytes: u64,
+    pub pages: u64,
+    pub malformed: u64,
+    pub built: u64,
+    pub backlog: u64,
+}
+
+impl Progress {
+    /// Start reporting, unless `enabled` is false, in which case the counters are kept but
+    /// nothing is shown.
+    pub fn start(enabled: bool) -> Self {
+        let counters = Arc::new(Counters::default());
+        let reporter = enabled.then(|| {
+            let counters = Arc::clone(&counters);
+            if io::stderr().is_terminal() {
+                thread::spawn(move || draw(&counters))
+            } else {
+                thread::spawn(move || log(&counters))
+            }
+        });
+        Self { counters, reporter }
+    }
+
+    /// The count that dump readers add the bytes they read to.
+    pub fn bytes_read(&self) -> &Arc<AtomicU64> {
+        &self.counters.bytes_read
+    }
+
+    /// Set how many bytes the dump readers will read, once known.
+    pub fn set_total(&self, bytes: u64) {
+        self.counters.total_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Count a parsed page.
+    pub fn page(&self) {
+        self.counters.pages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The pages counted so far.
+    pub fn pages(&self) -> u64 {
+        self.counters.pages.load(Ordering::Relaxed)
+    }
+
+    /// The count that dump readers add the malformed pages they skip to.
+    pub fn malformed(&self) -> &Arc<AtomicU64> {
+        &self.counters.malformed
+    }
+
+    /// Count a page added to the graph, when `backlog` read pages are waiting to be parsed.
+    pub fn built(&self, backlog: usize) {
+        self.counters.built.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .backlog
+            .store(backlog as u64, Ordering::Relaxed);
+    }
+
+    pub fn handle(&self) -> Handle {
+        Handle(Arc::clone(&self.counters))
+    }
+}
+
+impl Handle {
+    pub fn snapshot(&self) -> Snapshot {
+        let counters = &self.0;
+        Snapshot {
+            bytes_read: counters.bytes_read.load(Ordering::Relaxed),
+            total_bytes: counters.total_bytes.load(Ordering::Relaxed),
+            pages: counters.pages.load(Ordering::Relaxed),
+            malformed: counters.malformed.load(Ordering::Relaxed),
+            built: counters.built.load(Ordering::Relaxed),
+            backlog: counters.backlog.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.counters.done.store(true, Ordering::Relaxed);
+        if let Some(reporter) = self.reporter.take() {
+            reporter.thread().unpark();
+            let _ = reporter.join();
+        }
+    }
+}
+
+fn draw(counters: &Counters) {
+    let bar = ProgressBar::new(0);
+    let known = ProgressStyle::with_template(
+        "{wide_bar} {bytes}/{total_bytes} ({percent}%) {msg}, ETA {eta}",
+    )
+    .unwrap();
+    let unknown = ProgressStyle::with_template("{spinner} {bytes} read, {msg}").unwrap();
+    let start = Instant::now();
+    while !counters.done.load(Ordering::Relaxed) {
+        let total = counters.total_bytes.load(Ordering::Relaxed);
+        if total == 0 {
+            bar.set_style(unknown.clone());
+        } else {
+            bar.set_style(known.clone());
+            bar.set_length(total);
+        }
+        bar.set_position(counters.bytes_read.load(Ordering::Relaxed));
+        bar.set_message(pages(counters, start.elapsed()));
+        thread::park_timeout(DRAW_INTERVAL);
+    }
+    bar.finish_and_clear();
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn log(counters: &Counters) {
+    let start = Instant::now();
+    loop {
+        thread::park_timeout(LOG_INTERVAL);
+        if counters.done.load(Ordering::Relaxed) {
+            return;
+        }
+        let elapsed = start.elapsed();
+        let read = counters.bytes_read.load(Ordering::Relaxed);
+        let total = counters.total_bytes.load(Ordering::Relaxed);
+        let pages = pages(counters, elapsed);
+        if total == 0 || read == 0 {
+            tracing::info!("Read {}, {pages}", HumanBytes(read));
+            continue;
+        }
+        let fraction = read as f64 / total as f64;
+        let remaining = elapsed.mul_f64((1.0 - fraction).max(0.0) / fraction);
+        tracing::info!(
+            "Read {} of {} ({:.0}%), {pages}, ETA {}",
+            HumanBytes(read),
+            HumanBytes(total),
+            fraction * 100.0,
+            HumanDuration(remaining)
+        );
+    }
+}
+
+/// The pages parsed so far and how many a second, `elapsed` into the parse.
+fn pages(counters: &Counters, elapsed: Duration) -> String {
+    let pages = counters.pages.load(Ordering::Relaxed);
+    let rate = u128::from(pages) * 1000 / elapsed.as_millis().max(1);
+    format!(
+        "{} pages, {} pages/s",
+        HumanCount(pages),
+        HumanCount(u64::try_from(rate).unwrap_or(u64::MAX))
+    )
+}
@@ -0,0 +1,57 @@
+//! Provenance records for exports: which dump and options produced them, written into formats
+//! that have room for it and beside the others, so datasets derived from different dumps or
+//! settings can be told apart once they leave the machine that made them.
+
+use crate::{cache, graph::Metadata, ParseArgs};
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::{Path, PathBuf},
+};
+
+/// The date in a dump file name like `enwiki-20240601-pages-articles.xml.bz2`, as `2024-06-01`.
+pub fn dump_date(name: &str) -> Option<String> {
+    name.split(['-', '.', '_'])
+        .find(|part| {
+            part.len() == 8
+                && part.bytes().all(|byte| byte.is_ascii_digit())
+                && (part.starts_with("19") || part.starts_with("20"))
+        })
+        .map(|date| format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]))
+}
+
+/// CRC-32, as hex, of the options that decide a parse's output: the parse inputs besides the
+/// dump itself, and the export filters.
+pub fn options_hash(inputs: &cache::Inputs, args: &ParseArgs) -> anyhow::Result<String> {
+    let mut options = serde_json::to_value(inputs)?;
+    if let Some(options) = options.as_object_mut() {
+        for dump in ["dump", "size", "modified_nanos"] {
+            options.remove(dump);
+        }
+    }
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(options.to_string().as_bytes());
+    hasher.update(format!("{:?} {:?}", args.node_filter, args.edge_filter).as_bytes());
+    Ok(format!("{:08x}", hasher.finalize()))
+}
+
+/// The sidecar for the export at `path`: `provenance.json` inside a directory, or
+/// `<file>.provenance.json` beside a file.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join("provenance.json")
+    } else {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".provenance.json");
+        PathBuf::from(name)
+    }
+}
+
+/// Write `metadata` as pretty JSON to the sidecar of the export at `path`.
+pub fn write_sidecar(path: &Path, metadata: &Metadata) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(File::create(sidecar_path(path))?);
+    serde_json::to_writer_pretty(&mut writer, metadata)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
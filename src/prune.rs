@@ -0,0 +1,131 @@
+//! Cut a saved graph down to the nodes within degree bounds, for visualization and for faster
+//! analyses on the well-connected core, leaving out namespaces and titles that are noise to an
+//! analysis, such as categories, lists, and dates.
+//!
+//! Graph files don't record the namespace of each page, so it is read from the start of its
+//! title, by the canonical English name of the namespace (see `siteinfo::english_key`), as
+//! English wikis and `import-sql` graphs name them.
+
+use crate::{graph::Graph, siteinfo, workspace};
+use anyhow::Context as _;
+use regex::Regex;
+use std::path::PathBuf;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Where to save the pruned graph
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// Remove nodes with fewer incoming links than this
+    #[arg(long, visible_alias = "min-in-degree", value_name = "N")]
+    min_in: Option<u32>,
+
+    /// Remove nodes with more incoming links than this
+    #[arg(long, visible_alias = "max-in-degree", value_name = "N")]
+    max_in: Option<u32>,
+
+    /// Remove nodes with fewer outgoing links than this
+    #[arg(long, visible_alias = "min-out-degree", value_name = "N")]
+    min_out: Option<u32>,
+
+    /// Remove nodes with more outgoing links than this
+    #[arg(long, visible_alias = "max-out-degree", value_name = "N")]
+    max_out: Option<u32>,
+
+    /// Remove the pages of this namespace number (repeatable), e.g. 14 for categories; 0 removes
+    /// the pages whose title starts with no namespace name
+    #[arg(long = "drop-ns", value_name = "NS")]
+    drop_namespaces: Vec<i64>,
+
+    /// Remove the pages whose title matches this regular expression (repeatable), e.g.
+    /// `^List of ` or `^\d{4}$` for years
+    #[arg(long = "drop-titles-matching", value_name = "REGEX")]
+    drop_titles: Vec<Regex>,
+
+    /// Keep pruning until every remaining node is within bounds, counting only links between
+    /// remaining nodes (with `--min-in` and `--min-out`, this finds the graph's core)
+    #[arg(long)]
+    recursive: bool,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+
+    let keep = prune(&graph, args);
+    let pruned = graph.subgraph(&keep);
+    pruned
+        .save(&args.output)
+        .context("Failed to save pruned graph")
+        .unwrap();
+
+    println!(
+        "Kept {} of {} nodes and {} of {} links",
+        pruned.node_count(),
+        graph.node_count(),
+        pruned.edge_count(),
+        graph.edge_count()
+    );
+}
+
+fn prune(graph: &Graph, args: &Args) -> Vec<bool> {
+    let in_bounds = |degree: u32, min: Option<u32>, max: Option<u32>| {
+        min.is_none_or(|min| degree >= min) && max.is_none_or(|max| degree <= max)
+    };
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+
+    let dropped: Vec<bool> = nodes
+        .clone()
+        .map(|node| is_dropped(graph.title(node), args))
+        .collect();
+
+    let mut keep = vec![true; graph.node_count()];
+    loop {
+        // Degrees among the nodes still kept; on the first pass, simply the degrees.
+        let mut in_degrees = vec![0; graph.node_count()];
+        let mut out_degrees = vec![0; graph.node_count()];
+        for source in nodes.clone().filter(|&node| keep[node as usize]) {
+            for &target in graph.links(source) {
+                if keep[target as usize] {
+                    out_degrees[source as usize] += 1;
+                    in_degrees[target as usize] += 1;
+                }
+            }
+        }
+
+        let mut removed = false;
+        for node in nodes.clone().map(|node| node as usize) {
+            if keep[node]
+                && (dropped[node]
+                    || !(in_bounds(in_degrees[node], args.min_in, args.max_in)
+                        && in_bounds(out_degrees[node], args.min_out, args.max_out)))
+            {
+                keep[node] = false;
+                removed = true;
+            }
+        }
+
+        if !args.recursive || !removed {
+            return keep;
+        }
+    }
+}
+
+/// Whether the page titled `title` is in a namespace or matches a pattern that `args` drops.
+fn is_dropped(title: &str, args: &Args) -> bool {
+    let namespace = title
+        .split_once(':')
+        .and_then(|(prefix, _)| siteinfo::english_key(prefix))
+        .unwrap_or(0);
+    args.drop_namespaces.contains(&namespace)
+        || args
+            .drop_titles
+            .iter()
+            .any(|pattern| pattern.is_match(title))
+}
@@ -0,0 +1,728 @@
+//! Scripted queries against a saved graph, as JSON objects in and JSON objects out. With
+//! `--stdin`, one loaded graph answers any number of queries, one per line.
+
+use crate::{
+    cancel::Cancel,
+    filter::{Filter, Value},
+    graph::{Adjacency as _, Direction, Graph},
+    hyperball::Neighbourhoods,
+    navigation::Kind,
+    path,
+    weights::Weights,
+    wikidata, workspace,
+};
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufRead as _, BufReader, BufWriter, Write as _},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Query as JSON, e.g. `{"op":"path","from":"Rust","to":"C++"}`
+    #[arg(required_unless_present = "stdin", conflicts_with = "stdin")]
+    query: Option<String>,
+
+    /// Read queries from stdin as JSON lines, writing one result line per query. Ctrl-C
+    /// interrupts the query running, and pressing it twice in a row quits.
+    #[arg(long)]
+    stdin: bool,
+
+    /// Give up on each query after this many seconds, answering with an error instead
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Only answer with, and traverse through, pages matching this expression over `title`,
+    /// `in_degree`, `out_degree`, `wikidata` (as in `Q42`), `is_portal`, `is_disambiguation`, and
+    /// `is_navigation` (portals, disambiguation pages, and navigation-heavy pages like lists), e.g.
+    /// `out_degree < 500`
+    #[arg(long, value_name = "EXPR", value_parser = Filter::parse)]
+    filter: Option<Filter>,
+
+    /// Which links paths and neighbourhoods follow
+    #[arg(long, value_enum, default_value_t)]
+    direction: Direction,
+
+    /// Link contexts written by `parse --link-contexts`, for path queries with `"explain":true`
+    /// to show the anchor text and sentence of each hop
+    #[arg(long, value_name = "FILE")]
+    contexts: Option<PathBuf>,
+
+    /// Edge weights for `widest_path` and `heaviest_path` queries, as a CSV file with `Source`
+    /// and `Target` titles, `Weight`, and `Type` columns, like the one written by
+    /// `parse --cooccurrence`
+    #[arg(long, value_name = "FILE")]
+    weights: Option<PathBuf>,
+
+    /// Answer about redirect pages themselves, instead of the pages they redirect to. Either
+    /// way, graphs saved before redirects were stored have none to resolve.
+    #[arg(long)]
+    no_resolve: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Query {
+    /// Shortest chain of links between two pages, optionally not passing through portals and
+    /// navigation-heavy pages, and optionally with where each link occurs.
+    Path {
+        from: String,
+        to: String,
+        #[serde(default)]
+        avoid_navigation: bool,
+        #[serde(default)]
+        explain: bool,
+    },
+    Links {
+        title: String,
+    },
+    Backlinks {
+        title: String,
+    },
+    Degree {
+        title: String,
+    },
+    /// The Wikidata item of a page, for graphs built with `parse --page-props`.
+    Wikidata {
+        title: String,
+    },
+    /// The page of a Wikidata item, as in `{"op":"item","wikidata":"Q42"}`.
+    Item {
+        wikidata: String,
+    },
+    /// Pages at most `hops` links away.
+    Within {
+        title: String,
+        hops: usize,
+    },
+    /// Path whose weakest edge, by `--weights`, is strongest.
+    WidestPath {
+        from: String,
+        to: String,
+    },
+    /// Path of at most `max_hops` edges with the greatest total weight, by `--weights`.
+    HeaviestPath {
+        from: String,
+        to: String,
+        #[serde(default = "default_max_hops")]
+        max_hops: usize,
+    },
+    /// Every page and link on a shortest path from any of the `from` pages to any of the `to`
+    /// pages, showing how two topic areas are connected.
+    Connect {
+        from: PageSet,
+        to: PageSet,
+    },
+    /// Highest-ranked pages, by the PageRank stored by `stats --pagerank` or the harmonic
+    /// centrality of the neighbourhoods stored by `stats --neighbourhoods`.
+    Rank {
+        #[serde(default = "default_rank_limit")]
+        limit: usize,
+        #[serde(default)]
+        by: Measure,
+    },
+    /// About how many pages are `hops` links away, from the neighbourhoods stored by `stats
+    /// --neighbourhoods`, which follow the links they were estimated along rather than
+    /// `--direction`, and count pages the filter excludes.
+    Reach {
+        title: String,
+        hops: u32,
+    },
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Measure {
+    #[default]
+    Pagerank,
+    Harmonic,
+}
+
+/// Pages given by title, or as the pages in a category, for graphs built with category edges.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PageSet {
+    Titles(Vec<String>),
+    Category { category: String },
+}
+
+fn default_rank_limit() -> usize {
+    10
+}
+
+fn default_max_hops() -> usize {
+    3
+}
+
+/// An answer, and which of the titles asked about were redirects resolved to their targets.
+#[derive(Serialize)]
+struct Response<'a> {
+    #[serde(flatten)]
+    answer: Answer<'a>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    resolved: Vec<Resolution<'a>>,
+}
+
+/// A title asked about that was a redirect, and the title of the page it leads to.
+#[derive(Serialize)]
+pub struct Resolution<'a> {
+    pub asked: String,
+    pub resolved: &'a str,
+}
+
+/// How a query finds the pages it asks about, and when it gives up.
+struct Lookup<'a> {
+    graph: &'a Graph,
+    resolve: bool,
+    cancel: Cancel,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Answer<'a> {
+    /// `null` if there is no path.
+    Path {
+        path: Option<Vec<&'a str>>,
+    },
+    ExplainedPath {
+        path: Option<Vec<&'a str>>,
+        hops: Option<Vec<Hop<'a>>>,
+    },
+    /// The bottleneck or total weight of the path, `null` if there is none.
+    WeightedPath {
+        path: Option<Vec<&'a str>>,
+        weight: Option<f64>,
+    },
+    Titles {
+        titles: Vec<&'a str>,
+    },
+    Degree {
+        in_degree: u32,
+        out_degree: u32,
+    },
+    /// `null` if the page has no item.
+    Wikidata {
+        wikidata: Option<String>,
+    },
+    /// `null` if no page has the item.
+    Page {
+        title: Option<&'a str>,
+    },
+    Ranking {
+        ranking: Vec<Ranked<'a>>,
+    },
+    Estimate {
+        pages: f64,
+    },
+    /// `distance` is the length of the shortest paths, `null` with no nodes if there are none.
+    Subgraph {
+        distance: Option<usize>,
+        nodes: Vec<&'a str>,
+        links: Vec<[&'a str; 2]>,
+    },
+    Error {
+        error: String,
+    },
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+
+    let keep = |node: u32| {
+        args.filter.as_ref().is_none_or(|filter| {
+            filter.matches(&|name| match name {
+                "title" => Value::String(graph.title(node).into()),
+                "in_degree" => Value::from(graph.in_degree(node)),
+                "out_degree" => Value::from(graph.out_degree(node)),
+                "wikidata" => graph.wikidata_item(node).map_or(Value::Null, |item| {
+                    Value::String(wikidata::name(item).into())
+                }),
+                "is_portal" => Value::Bool(graph.kind(node) == Some(Kind::Portal)),
+                "is_disambiguation" => Value::Bool(graph.kind(node) == Some(Kind::Disambiguation)),
+                "is_navigation" => Value::Bool(graph.kind(node).is_some()),
+                _ => Value::Null,
+            })
+        })
+    };
+
+    let contexts = args.contexts.as_deref().map(|path| {
+        load_contexts(path, &graph)
+            .context("Failed to load link contexts")
+            .unwrap()
+    });
+    let weights = args.weights.as_deref().map(|path| {
+        Weights::load(path, &graph)
+            .context("Failed to load edge weights")
+            .unwrap()
+    });
+    let timeout = args.timeout.map(Duration::from_secs);
+    let lookup = |cancel| Lookup {
+        graph: &graph,
+        resolve: !args.no_resolve,
+        cancel,
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    if let Some(query) = &args.query {
+        write_answer(
+            &mut out,
+            &answer(
+                &lookup(Cancel::after(timeout)),
+                contexts.as_ref(),
+                weights.as_ref(),
+                args.direction,
+                &keep,
+                query,
+            ),
+        )
+        .unwrap();
+    } else {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        {
+            use signal_hook::{consts::SIGINT, flag};
+            // The first Ctrl-C sets the flag, and a second one before a query clears it quits.
+            flag::register_conditional_shutdown(SIGINT, 130, Arc::clone(&interrupted))
+                .and_then(|_| flag::register(SIGINT, Arc::clone(&interrupted)))
+                .context("Failed to listen for Ctrl-C")
+                .unwrap();
+        }
+        for line in io::stdin().lock().lines() {
+            let line = line.context("Failed to read query").unwrap();
+            if line.trim().is_empty() {
+                continue;
+            }
+            interrupted.store(false, Ordering::Relaxed);
+            let cancel = Cancel::after(timeout).or_when(Arc::clone(&interrupted));
+            write_answer(
+                &mut out,
+                &answer(
+                    &lookup(cancel),
+                    contexts.as_ref(),
+                    weights.as_ref(),
+                    args.direction,
+                    &keep,
+                    &line,
+                ),
+            )
+            .unwrap();
+            // Whoever is sending queries may be waiting for this answer before the next.
+            out.flush().unwrap();
+        }
+    }
+    out.flush().unwrap();
+}
+
+fn write_answer(out: &mut impl io::Write, answer: &Response) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut *out, answer)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// One link of an explained path, with its first anchor text and sentence, if the link
+/// contexts have them. Links from templates have none.
+#[derive(Serialize)]
+struct Hop<'a> {
+    source: &'a str,
+    target: &'a str,
+    anchor: Option<&'a str>,
+    context: Option<&'a str>,
+}
+
+/// The first anchor text and sentence of each link, by source and target node.
+type Contexts = HashMap<(u32, u32), (String, String)>;
+
+#[derive(Deserialize)]
+struct ContextRecord {
+    source: String,
+    target: String,
+    anchor: String,
+    context: String,
+}
+
+fn explain_path<'a>(graph: &'a Graph, contexts: &'a Contexts, path: &[u32]) -> Vec<Hop<'a>> {
+    path.windows(2)
+        .map(|hop| {
+            let context = contexts.get(&(hop[0], hop[1]));
+            Hop {
+                source: graph.title(hop[0]),
+                target: graph.title(hop[1]),
+                anchor: context.map(|(anchor, _)| anchor.as_str()),
+                context: context.map(|(_, context)| context.as_str()),
+            }
+        })
+        .collect()
+}
+
+/// Read the link contexts of the links in `graph`.
+fn load_contexts(path: &Path, graph: &Graph) -> anyhow::Result<Contexts> {
+    let mut contexts = HashMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let record: ContextRecord = serde_json::from_str(&line?)?;
+        if let (Some(source), Some(target)) = (graph.id(&record.source), graph.id(&record.target)) {
+            contexts
+                .entry((source, target))
+                .or_insert((record.anchor, record.context));
+        }
+    }
+    Ok(contexts)
+}
+
+#[derive(Serialize)]
+struct Ranked<'a> {
+    title: &'a str,
+    score: f64,
+}
+
+impl<'a> Lookup<'a> {
+    /// The node `title` means, as `path::lookup` finds it, or the page it redirects to when
+    /// resolving, noting the resolution in `resolved`.
+    fn id(&self, title: &str, resolved: &mut Vec<Resolution<'a>>) -> anyhow::Result<u32> {
+        let id = path::lookup(self.graph, title)?;
+        if !self.resolve {
+            return Ok(id);
+        }
+        let target = self.graph.resolve_redirect(id);
+        if target != id {
+            resolved.push(Resolution {
+                asked: String::from(title),
+                resolved: self.graph.title(target),
+            });
+        }
+        Ok(target)
+    }
+}
+
+/// Answers to bad queries are errors too, so that every input line gets exactly one output line.
+fn answer<'a>(
+    lookup: &Lookup<'a>,
+    contexts: Option<&'a Contexts>,
+    weights: Option<&Weights>,
+    direction: Direction,
+    keep: &dyn Fn(u32) -> bool,
+    query: &str,
+) -> Response<'a> {
+    let mut resolved = Vec::new();
+    let answer = match serde_json::from_str(query)
+        .map_err(anyhow::Error::from)
+        .and_then(|query| {
+            run_query(
+                lookup,
+                &mut resolved,
+                contexts,
+                weights,
+                direction,
+                keep,
+                &query,
+            )
+        }) {
+        Ok(answer) => answer,
+        Err(error) => Answer::Error {
+            error: error.to_string(),
+        },
+    };
+    Response { answer, resolved }
+}
+
+#[allow(clippy::too_many_lines)]
+fn run_query<'a>(
+    lookup: &Lookup<'a>,
+    resolved: &mut Vec<Resolution<'a>>,
+    contexts: Option<&'a Contexts>,
+    weights: Option<&Weights>,
+    direction: Direction,
+    keep: &dyn Fn(u32) -> bool,
+    query: &Query,
+) -> anyhow::Result<Answer<'a>> {
+    let graph = lookup.graph;
+    let cancel = &lookup.cancel;
+    let mut id = |title: &str| lookup.id(title, resolved);
+    // Paths are printed whole, since their search already kept to the filter apart from the
+    // ends; lists of pages leave out those it doesn't match.
+    let titles = |ids: &[u32]| ids.iter().map(|&node| graph.title(node)).collect();
+    let kept = |ids: &[u32]| {
+        ids.iter()
+            .filter(|&&node| keep(node))
+            .map(|&node| graph.title(node))
+            .collect()
+    };
+    Ok(match query {
+        Query::Path {
+            from,
+            to,
+            avoid_navigation,
+            explain,
+        } => {
+            let path = graph.shortest_path(
+                id(from)?,
+                id(to)?,
+                direction,
+                |node| keep(node) && !(*avoid_navigation && graph.kind(node).is_some()),
+                cancel,
+            )?;
+            if *explain {
+                let contexts = contexts.context(
+                    "No link contexts loaded; pass `--contexts` with the file written by \
+                     `parse --link-contexts`",
+                )?;
+                Answer::ExplainedPath {
+                    hops: path
+                        .as_ref()
+                        .map(|path| explain_path(graph, contexts, path)),
+                    path: path.map(|path| titles(&path)),
+                }
+            } else {
+                Answer::Path {
+                    path: path.map(|path| titles(&path)),
+                }
+            }
+        }
+        Query::WidestPath { from, to } | Query::HeaviestPath { from, to, .. } => {
+            let weights = weights.context(
+                "No edge weights loaded; pass `--weights` with a weighted edge list such as the \
+                 one written by `parse --cooccurrence`",
+            )?;
+            let (from, to) = (id(from)?, id(to)?);
+            let found = match query {
+                Query::HeaviestPath { max_hops, .. } => {
+                    weights.heaviest_path(from, to, direction, *max_hops, keep, cancel)?
+                }
+                _ => weights.widest_path(from, to, direction, keep, cancel)?,
+            };
+            let (path, weight) = found.unzip();
+            Answer::WeightedPath {
+                path: path.map(|path| titles(&path)),
+                weight,
+            }
+        }
+        Query::Links { title } => Answer::Titles {
+            titles: kept(graph.links(id(title)?)),
+        },
+        Query::Backlinks { title } => Answer::Titles {
+            titles: kept(graph.backlinks(id(title)?)),
+        },
+        Query::Degree { title } => {
+            let id = id(title)?;
+            Answer::Degree {
+                in_degree: graph.in_degree(id),
+                out_degree: graph.out_degree(id),
+            }
+        }
+        Query::Wikidata { title } => Answer::Wikidata {
+            wikidata: graph.wikidata_item(id(title)?).map(wikidata::name),
+        },
+        Query::Item { wikidata } => {
+            anyhow::ensure!(
+                graph.has_wikidata_items(),
+                "The graph has no Wikidata items; build it with `parse --page-props`"
+            );
+            let item = wikidata::parse(wikidata)?;
+            Answer::Page {
+                title: (0..u32::try_from(graph.node_count())?)
+                    .find(|&node| graph.wikidata_item(node) == Some(item) && keep(node))
+                    .map(|node| graph.title(node)),
+            }
+        }
+        Query::Within { title, hops } => {
+            let mut nodes: Vec<u32> = graph
+                .within(id(title)?, *hops, direction, keep, cancel)?
+                .into_iter()
+                .collect();
+            nodes.sort_unstable();
+            Answer::Titles {
+                titles: kept(&nodes),
+            }
+        }
+        Query::Connect { from, to } => {
+            let from = members(graph, &mut id, from)?;
+            let to = members(graph, &mut id, to)?;
+            connect(graph, &from, &to, direction, keep, cancel)?
+        }
+        Query::Rank { limit, by } => ranking(graph, keep, *limit, *by)?,
+        Query::Reach { title, hops } => {
+            let neighbourhoods = neighbourhoods(graph)?;
+            Answer::Estimate {
+                pages: neighbourhoods.size(id(title)?, *hops).with_context(|| {
+                    format!(
+                        "Neighbourhoods are only estimated up to {} hops",
+                        neighbourhoods.params.hops
+                    )
+                })?,
+            }
+        }
+    })
+}
+
+fn neighbourhoods(graph: &Graph) -> anyhow::Result<&Neighbourhoods> {
+    graph
+        .stats()
+        .and_then(|stats| stats.neighbourhoods.as_ref())
+        .context("No neighbourhoods stored in the graph; run `stats --neighbourhoods` first")
+}
+
+/// The `limit` highest-ranked pages by `measure` for which `keep` holds, by decreasing score.
+fn ranking<'a>(
+    graph: &'a Graph,
+    keep: &dyn Fn(u32) -> bool,
+    limit: usize,
+    measure: Measure,
+) -> anyhow::Result<Answer<'a>> {
+    let harmonic: Vec<f64>;
+    let scores = match measure {
+        Measure::Pagerank => {
+            &graph
+                .stats()
+                .and_then(|stats| stats.pagerank.as_ref())
+                .context("No PageRank stored in the graph; run `stats --pagerank` first")?
+                .scores
+        }
+        Measure::Harmonic => {
+            let neighbourhoods = neighbourhoods(graph)?;
+            harmonic = (0..u32::try_from(graph.node_count())?)
+                .map(|node| neighbourhoods.harmonic(node))
+                .collect();
+            &harmonic
+        }
+    };
+    let mut nodes: Vec<u32> = (0..u32::try_from(scores.len())?)
+        .filter(|&node| keep(node))
+        .collect();
+    let by_score = |a: &u32, b: &u32| {
+        scores[*b as usize]
+            .total_cmp(&scores[*a as usize])
+            .then(a.cmp(b))
+    };
+    if limit < nodes.len() {
+        nodes.select_nth_unstable_by(limit, by_score);
+        nodes.truncate(limit);
+    }
+    nodes.sort_unstable_by(by_score);
+    Ok(Answer::Ranking {
+        ranking: nodes
+            .into_iter()
+            .map(|node| Ranked {
+                title: graph.title(node),
+                score: scores[node as usize],
+            })
+            .collect(),
+    })
+}
+
+/// The nodes of `set`, looking titles up with `id`.
+fn members(
+    graph: &Graph,
+    id: &mut impl FnMut(&str) -> anyhow::Result<u32>,
+    set: &PageSet,
+) -> anyhow::Result<Vec<u32>> {
+    match set {
+        PageSet::Titles(titles) => titles.iter().map(|title| id(title)).collect(),
+        PageSet::Category { category } => {
+            let node = graph.id(&format!("Category:{category}")).with_context(|| {
+                format!(
+                    "No category '{category}'; category membership needs a graph built with \
+                     `parse --edge-types wikilink,category`"
+                )
+            })?;
+            Ok(graph.backlinks(node).to_vec())
+        }
+    }
+}
+
+/// The union of all shortest paths from `from` to `to` in `direction`, passing only through
+/// nodes for which `keep` holds. A node is on one of them exactly when its distances from
+/// `from` and to `to` add up to the shortest distance.
+fn connect<'a>(
+    graph: &'a Graph,
+    from: &[u32],
+    to: &[u32],
+    direction: Direction,
+    keep: &dyn Fn(u32) -> bool,
+    cancel: &Cancel,
+) -> anyhow::Result<Answer<'a>> {
+    let sources: HashSet<u32> = from.iter().copied().collect();
+    let targets: HashSet<u32> = to.iter().copied().collect();
+    // Paths end at the first page of the other set they reach.
+    let forward = graph.distances(
+        from,
+        direction,
+        |node| keep(node) || targets.contains(&node),
+        |node| !targets.contains(&node),
+        cancel,
+    )?;
+    let backward = graph.distances(
+        to,
+        direction.reverse(),
+        |node| keep(node) || sources.contains(&node),
+        |node| !sources.contains(&node),
+        cancel,
+    )?;
+    let Some(distance) = to
+        .iter()
+        .filter_map(|node| forward.get(node))
+        .min()
+        .copied()
+    else {
+        return Ok(Answer::Subgraph {
+            distance: None,
+            nodes: Vec::new(),
+            links: Vec::new(),
+        });
+    };
+
+    let on_path = |node: u32| {
+        forward
+            .get(&node)
+            .zip(backward.get(&node))
+            .is_some_and(|(a, b)| a + b == distance)
+    };
+    let mut nodes: Vec<u32> = forward
+        .keys()
+        .copied()
+        .filter(|&node| on_path(node))
+        .collect();
+    nodes.sort_unstable();
+
+    // A link is on a path when it takes one step closer to `to` between nodes on paths.
+    let step = |a: u32, b: u32| on_path(b) && forward[&a] + 1 == forward[&b];
+    let mut links = Vec::new();
+    for &node in &nodes {
+        if direction != Direction::In {
+            for &target in graph.links(node) {
+                if step(node, target) {
+                    links.push((node, target));
+                }
+            }
+        }
+        if direction != Direction::Out {
+            for &source in graph.backlinks(node) {
+                if step(node, source) {
+                    links.push((source, node));
+                }
+            }
+        }
+    }
+    links.sort_unstable();
+    links.dedup();
+
+    Ok(Answer::Subgraph {
+        distance: Some(distance),
+        nodes: nodes.iter().map(|&node| graph.title(node)).collect(),
+        links: links
+            .into_iter()
+            .map(|(source, target)| [graph.title(source), graph.title(target)])
+            .collect(),
+    })
+}
@@ -0,0 +1,105 @@
+//! Red links: links to titles the dump has no page for, which MediaWiki shows in red. They are
+//! a large share of the links of every dump, and left alone each one becomes a node that looks
+//! like any article. Pages of other shards, or left out by `--sample` or `--max-pages`, count as
+//! missing too.
+
+use crate::{filter::Filter, ParseArgs, Wiki};
+use clap::CommandFactory as _;
+use lasso::{Rodeo, Spur};
+use std::{collections::HashSet, path::Path};
+
+/// What to do with links to titles that aren't pages of the dump.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum Policy {
+    /// Keep them as stub nodes, marked `missing` in the Gephi and Neo4j node tables
+    #[default]
+    Keep,
+    /// Drop them, so that only pages of the dump are nodes
+    Drop,
+    /// Drop them, and list them in `--red-link-report`
+    Report,
+}
+
+/// Exit with a usage error if `--red-links` would drop nodes that outputs of the parse depend
+/// on: the `--text-index`, which refers to nodes by ID, and a `--partial`, whose red links may be
+/// pages of another shard. Links spilled by `--memory-limit` aren't in memory to drop.
+pub fn check(args: &ParseArgs) {
+    if args.red_links == Policy::Keep {
+        return;
+    }
+    let conflict = if args.text_index {
+        "--text-index"
+    } else if args.partial.is_some() {
+        "--partial"
+    } else if args.memory_limit.is_some() {
+        "--memory-limit"
+    } else {
+        return;
+    };
+    crate::Args::command()
+        .error(
+            clap::error::ErrorKind::ArgumentConflict,
+            format!("`--red-links` other than `keep` can't be used with `{conflict}`"),
+        )
+        .exit();
+}
+
+/// Apply `policy` to the parsed `wiki`, writing the red links to `report` with
+/// `Policy::Report`. Dropping them re-interns the titles, so that node IDs stay dense.
+pub fn apply(
+    policy: Policy,
+    report: Option<&Path>,
+    rodeo: Rodeo,
+    wiki: Wiki,
+) -> anyhow::Result<(Rodeo, Wiki)> {
+    let links = red_links(&wiki);
+    let missing: HashSet<Spur> = links.iter().map(|&(_, target)| target).collect();
+    tracing::info!(
+        "{} links to {} titles without a page",
+        links.len(),
+        missing.len()
+    );
+    if let (Policy::Report, Some(path)) = (policy, report) {
+        write_report(path, &rodeo, &links)?;
+    }
+    if policy == Policy::Keep {
+        return Ok((rodeo, wiki));
+    }
+    let pages = Filter::parse("exists")?;
+    Ok(crate::filter::apply(&rodeo, &wiki, Some(&pages), None))
+}
+
+/// Every link, and redirect, whose target isn't a page, as (source, target).
+fn red_links(wiki: &Wiki) -> Vec<(Spur, Spur)> {
+    let links = wiki
+        .links
+        .iter()
+        .flat_map(|(&source, targets)| targets.iter().map(move |&target| (source, target)));
+    let redirects = wiki
+        .redirects
+        .iter()
+        .map(|(&redirect, &target)| (redirect, target));
+    links
+        .chain(redirects)
+        .filter(|(_, target)| !wiki.links.contains_key(target))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Write `source,target` rows as CSV, ordered by target and then by source, so that the links to
+/// each missing page are together.
+fn write_report(path: &Path, rodeo: &Rodeo, links: &[(Spur, Spur)]) -> anyhow::Result<()> {
+    let mut rows: Vec<(&str, &str)> = links
+        .iter()
+        .map(|(source, target)| (rodeo.resolve(target), rodeo.resolve(source)))
+        .collect();
+    rows.sort_unstable();
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["source", "target"])?;
+    for (target, source) in rows {
+        writer.write_record([source, target])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
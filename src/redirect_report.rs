@@ -0,0 +1,68 @@
+//! Redirects that need an editor's attention: broken ones, whose target isn't a page of the
+//! dump, and orphaned ones, which no page links to. Links are counted as they are written, before
+//! `resolve_redirects` points them past the redirect.
+
+use lasso::{Rodeo, Spur};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+#[derive(Default)]
+pub struct RedirectReport {
+    pages: HashSet<Spur>,
+    redirects: HashMap<Spur, Spur>,
+    linked: HashSet<Spur>,
+}
+
+/// Counts of the problems written by `RedirectReport::write`.
+pub struct Summary {
+    pub broken: usize,
+    pub orphaned: usize,
+}
+
+impl RedirectReport {
+    /// Record page `title`, which redirects to `redirect` if it is a redirect and links to
+    /// `targets`. Later revisions of a history dump replace the redirect of earlier ones.
+    pub fn add_page(&mut self, title: Spur, redirect: Option<Spur>, targets: &[Spur]) {
+        self.pages.insert(title);
+        match redirect {
+            Some(redirect) => self.redirects.insert(title, redirect),
+            None => self.redirects.remove(&title),
+        };
+        self.linked.extend(targets);
+    }
+
+    /// Write `redirect,target,problem` rows as CSV, where the problem is `broken` or `orphaned`
+    /// and a redirect can have both, ordered by problem and then by redirect. Pages of other
+    /// shards count as missing.
+    pub fn write(&self, path: &Path, rodeo: &Rodeo) -> anyhow::Result<Summary> {
+        let mut rows: Vec<(&str, &str, &str)> = Vec::new();
+        for (redirect, target) in &self.redirects {
+            let (redirect_title, target_title) = (rodeo.resolve(redirect), rodeo.resolve(target));
+            if !self.pages.contains(target) {
+                rows.push(("broken", redirect_title, target_title));
+            }
+            if !self.linked.contains(redirect) {
+                rows.push(("orphaned", redirect_title, target_title));
+            }
+        }
+        rows.sort_unstable();
+
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["redirect", "target", "problem"])?;
+        for &(problem, redirect, target) in &rows {
+            writer.write_record([redirect, target, problem])?;
+        }
+        writer.flush()?;
+
+        let broken = rows
+            .iter()
+            .filter(|(problem, ..)| *problem == "broken")
+            .count();
+        Ok(Summary {
+            broken,
+            orphaned: rows.len() - broken,
+        })
+    }
+}
@@ -0,0 +1,159 @@
+//! Related articles by random walks with restart from a page, a Monte Carlo personalized
+//! PageRank: pages that many short walks from the page pass through are close to it by many
+//! routes, not just by one link, so they rank above the hubs that everything links to.
+
+use crate::{
+    graph::{mmap::MmapGraph, Adjacency, Direction, Graph},
+    path,
+    sample::Rng,
+    workspace,
+};
+use anyhow::Context as _;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Title of the page to find related pages of, found as `path` finds pages
+    title: String,
+
+    /// Number of walks from the page
+    #[arg(long, value_name = "N", default_value_t = 10_000)]
+    walks: u32,
+
+    /// Most links each walk follows
+    #[arg(long, value_name = "STEPS", default_value_t = 5)]
+    length: u32,
+
+    /// Probability of a walk ending before each step, as restarting from the page does in
+    /// personalized PageRank
+    #[arg(long, value_name = "P", default_value_t = 0.15, value_parser = parse_restart)]
+    restart: f64,
+
+    /// Which links walks follow
+    #[arg(long, value_enum, default_value_t)]
+    direction: Direction,
+
+    /// Number of related pages to print
+    #[arg(long, value_name = "K", default_value_t = 20)]
+    top: usize,
+
+    /// Leave out the pages the page already links to, to recommend links it is missing
+    #[arg(long)]
+    unlinked: bool,
+
+    /// End walks at portals, disambiguation pages, and navigation-heavy pages like lists
+    /// instead of passing through them, so that pages sharing a name with the page or a list
+    /// with it don't rank high
+    #[arg(long)]
+    avoid_navigation: bool,
+
+    /// Seed for the walks; the same seed ranks the same pages of the same graph
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Map the graph file into memory instead of loading it, as with `path --mmap`
+    #[arg(long)]
+    mmap: bool,
+}
+
+pub fn run(args: &Args) {
+    if args.mmap {
+        let graph = MmapGraph::open(&args.graph)
+            .context("Failed to map graph")
+            .unwrap();
+        print(&graph, args);
+    } else {
+        let graph = Graph::load(&args.graph)
+            .context("Failed to load graph")
+            .unwrap();
+        print(&graph, args);
+    }
+}
+
+fn parse_restart(s: &str) -> Result<f64, String> {
+    let p: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..1.0).contains(&p) {
+        Ok(p)
+    } else {
+        Err(String::from("must be at least 0 and less than 1"))
+    }
+}
+
+/// Print the share of walk steps that reached each of the top pages, most first.
+#[allow(clippy::cast_precision_loss)]
+fn print(graph: &impl Adjacency, args: &Args) {
+    let page = path::find_or_exit(graph, &args.title);
+    let visits = walk(graph, page, args);
+    let total: u64 = visits.values().sum();
+    let linked: HashSet<u32> = if args.unlinked {
+        graph
+            .links(page)
+            .iter()
+            .map(|&target| graph.resolve_redirect(target))
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let mut ranked: Vec<(u32, u64)> = visits
+        .into_iter()
+        .filter(|(node, _)| *node != page && !linked.contains(node))
+        .collect();
+    ranked.sort_unstable_by(|(a, m), (b, n)| n.cmp(m).then(a.cmp(b)));
+    if ranked.is_empty() {
+        tracing::warn!("No walk from '{}' got anywhere", graph.title(page));
+    }
+    for (node, count) in ranked.into_iter().take(args.top) {
+        println!("{:.6}\t{}", count as f64 / total as f64, graph.title(node));
+    }
+}
+
+/// How many steps of the walks from `page` reached each page, redirects counting as the pages
+/// they redirect to.
+fn walk(graph: &impl Adjacency, page: u32, args: &Args) -> HashMap<u32, u64> {
+    let mut rng = Rng::seeded(args.seed, &[graph.title(page)]);
+    let mut visits: HashMap<u32, u64> = HashMap::new();
+    for _ in 0..args.walks {
+        let mut node = page;
+        for _ in 0..args.length {
+            if rng.unit() < args.restart {
+                break;
+            }
+            let Some(next) = step(graph, node, args.direction, &mut rng) else {
+                break;
+            };
+            node = graph.resolve_redirect(next);
+            if args.avoid_navigation && graph.kind(node).is_some() {
+                break;
+            }
+            *visits.entry(node).or_default() += 1;
+        }
+    }
+    visits
+}
+
+/// A random one of the nodes one link away from `node` in `direction`, if there are any.
+fn step(graph: &impl Adjacency, node: u32, direction: Direction, rng: &mut Rng) -> Option<u32> {
+    let (links, backlinks): (&[u32], &[u32]) = match direction {
+        Direction::Out => (graph.links(node), &[]),
+        Direction::In => (&[], graph.backlinks(node)),
+        Direction::Both => (graph.links(node), graph.backlinks(node)),
+    };
+    let count = links.len() + backlinks.len();
+    if count == 0 {
+        return None;
+    }
+    let index = rng.below(count);
+    Some(
+        links
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| backlinks[index - links.len()]),
+    )
+}
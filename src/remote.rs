@@ -0,0 +1,341 @@
+//! Dumps read straight from a URL, over HTTP(S) or from a public S3 bucket, in a way that
+//! survives a flaky connection: a failed request or read is retried after a pause with a request
+//! for the rest of the file, so a parse hours in carries on where it was. Downloads can be
+//! throttled, and with a spool directory, kept on disk as they arrive, so that a parse ended
+//! anyway resumes the download where it stopped and later parses read the local copy.
+
+use std::path::{Path, PathBuf};
+
+/// How to read dumps from URLs.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Times in a row to retry a failed request or read before giving up.
+    pub retries: u32,
+    /// Most bytes to download a second, if limited.
+    pub max_bandwidth: Option<u64>,
+    /// Directory to keep downloads in as they arrive, if any.
+    pub spool: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            retries: 5,
+            max_bandwidth: None,
+            spool: None,
+        }
+    }
+}
+
+/// Whether `path` is the URL of a dump rather than a file: `http://`, `https://`, or
+/// `s3://bucket/key`.
+#[must_use]
+pub fn is_url(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| {
+        ["http://", "https://", "s3://"]
+            .iter()
+            .any(|scheme| path.starts_with(scheme))
+    })
+}
+
+/// The name of the file at `url`: its last path segment.
+#[must_use]
+pub fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(feature = "http")]
+pub use download::open;
+
+/// The dump at `url`, downloaded as it is read.
+///
+/// # Errors
+///
+/// Always, since this build can't make HTTP requests.
+#[cfg(not(feature = "http"))]
+pub fn open(
+    _url: &str,
+    _options: &Options,
+) -> anyhow::Result<Box<dyn std::io::Read + Send + 'static>> {
+    anyhow::bail!("Reading dumps from URLs needs wikigraph built with `--features http`")
+}
+
+#[cfg(feature = "http")]
+mod download {
+    use super::{file_name, Options};
+    use anyhow::Context as _;
+    use std::{
+        ffi::OsString,
+        fs::{self, File, OpenOptions},
+        io::{self, Read, Write as _},
+        path::PathBuf,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Pause before the first retry, doubled for each further one in a row.
+    const FIRST_PAUSE: Duration = Duration::from_secs(1);
+    const LONGEST_PAUSE: Duration = Duration::from_mins(1);
+    /// Longest wait for a connection, or for the headers of a response.
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// The dump at `url`, downloaded as it is read. With a spool directory, a copy completed by
+    /// an earlier run is read instead, and one left partial is read before downloading the
+    /// rest.
+    ///
+    /// # Errors
+    ///
+    /// If the spool directory can't be written to.
+    pub fn open(url: &str, options: &Options) -> anyhow::Result<Box<dyn Read + Send + 'static>> {
+        let mut download = Download {
+            url: http_url(url),
+            agent: ureq::Agent::config_builder()
+                .user_agent(format!("wikigraph/{}", env!("CARGO_PKG_VERSION")))
+                .timeout_connect(Some(TIMEOUT))
+                .timeout_recv_response(Some(TIMEOUT))
+                .build()
+                .into(),
+            retries: options.retries,
+            failures: 0,
+            body: None,
+            offset: 0,
+            length: None,
+            spool: None,
+            throttle: options.max_bandwidth.map(|limit| Throttle {
+                limit,
+                start: Instant::now(),
+                bytes: 0,
+            }),
+        };
+        let Some(dir) = &options.spool else {
+            return Ok(Box::new(download));
+        };
+
+        let done = dir.join(file_name(&download.url));
+        if done.is_file() {
+            tracing::info!(
+                "Reading the download of {url} spooled to {}",
+                done.display()
+            );
+            return Ok(Box::new(File::open(&done)?));
+        }
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let mut partial = OsString::from(done.as_os_str());
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&partial)
+            .with_context(|| format!("Failed to open {}", partial.display()))?;
+        let spooled = file.metadata()?.len();
+        if spooled > 0 {
+            tracing::info!(
+                "Resuming the download of {url} at byte {spooled} of {}",
+                partial.display()
+            );
+        }
+        download.offset = spooled;
+        download.spool = Some(Spool {
+            file,
+            partial: partial.clone(),
+            done,
+        });
+        Ok(Box::new(
+            File::open(&partial)?.take(spooled).chain(download),
+        ))
+    }
+
+    /// The HTTP(S) URL of `url`, which for `s3://bucket/key` is the object's virtual-hosted
+    /// address. Requests aren't signed, so only public objects can be read.
+    fn http_url(url: &str) -> String {
+        match url.strip_prefix("s3://") {
+            Some(object) => {
+                let (bucket, key) = object.split_once('/').unwrap_or((object, ""));
+                format!("https://{bucket}.s3.amazonaws.com/{key}")
+            }
+            None => String::from(url),
+        }
+    }
+
+    /// A download that retries failed requests and reads, resuming at the byte it had got to.
+    struct Download {
+        url: String,
+        agent: ureq::Agent,
+        retries: u32,
+        /// Failures since the last successful read.
+        failures: u32,
+        /// The response being read, unless the last request or read failed.
+        body: Option<ureq::BodyReader<'static>>,
+        /// Bytes of the file read so far.
+        offset: u64,
+        /// Length of the file, once a response has said.
+        length: Option<u64>,
+        spool: Option<Spool>,
+        throttle: Option<Throttle>,
+    }
+
+    /// The file a download is kept in as it arrives, renamed once complete.
+    struct Spool {
+        file: File,
+        partial: PathBuf,
+        done: PathBuf,
+    }
+
+    /// Paces reads to stay under `limit` bytes a second on average.
+    struct Throttle {
+        limit: u64,
+        start: Instant,
+        bytes: u64,
+    }
+
+    impl Throttle {
+        fn take(&mut self, bytes: usize) {
+            self.bytes += bytes as u64;
+            let due = u128::from(self.bytes) * 1_000_000_000 / u128::from(self.limit);
+            let due = Duration::from_nanos(u64::try_from(due).unwrap_or(u64::MAX));
+            if let Some(early) = due.checked_sub(self.start.elapsed()) {
+                thread::sleep(early);
+            }
+        }
+    }
+
+    /// Why a request or read failed, and whether trying again could help.
+    struct Failure {
+        error: io::Error,
+        transient: bool,
+    }
+
+    impl Read for Download {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            loop {
+                let failure = match self.try_read(buf) {
+                    Ok(read) => {
+                        self.failures = 0;
+                        return Ok(read);
+                    }
+                    Err(failure) => failure,
+                };
+                self.body = None;
+                self.failures += 1;
+                if !failure.transient || self.failures > self.retries {
+                    return Err(failure.error);
+                }
+                let pause = FIRST_PAUSE
+                    .saturating_mul(1 << (self.failures - 1).min(16))
+                    .min(LONGEST_PAUSE);
+                tracing::warn!(
+                    "Retrying {} from byte {} in {}s ({} of {}): {}",
+                    self.url,
+                    self.offset,
+                    pause.as_secs(),
+                    self.failures,
+                    self.retries,
+                    failure.error
+                );
+                thread::sleep(pause);
+            }
+        }
+    }
+
+    impl Download {
+        fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, Failure> {
+            if self.length.is_some_and(|length| self.offset >= length) {
+                self.finish()?;
+                return Ok(0);
+            }
+            let body = if let Some(body) = &mut self.body {
+                body
+            } else {
+                let Some(body) = self.request()? else {
+                    self.finish()?;
+                    return Ok(0);
+                };
+                self.body.insert(body)
+            };
+            let read = body.read(buf).map_err(|error| Failure {
+                error,
+                transient: true,
+            })?;
+            if read == 0 {
+                if self.length.is_some_and(|length| self.offset < length) {
+                    return Err(Failure {
+                        error: io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "The connection closed early",
+                        ),
+                        transient: true,
+                    });
+                }
+                self.finish()?;
+                return Ok(0);
+            }
+            if let Some(spool) = &mut self.spool {
+                spool.file.write_all(&buf[..read]).map_err(permanent)?;
+            }
+            if let Some(throttle) = &mut self.throttle {
+                throttle.take(read);
+            }
+            self.offset += read as u64;
+            Ok(read)
+        }
+
+        /// Ask for the file from `offset` on, or `None` if there is nothing past it.
+        fn request(&mut self) -> Result<Option<ureq::BodyReader<'static>>, Failure> {
+            let mut request = self.agent.get(&self.url);
+            if self.offset > 0 {
+                request = request.header("Range", format!("bytes={}-", self.offset));
+            }
+            let response = match request.call() {
+                Ok(response) => response,
+                // A spool holding the whole file, but not yet renamed.
+                Err(ureq::Error::StatusCode(416)) if self.offset > 0 => return Ok(None),
+                Err(error) => {
+                    let transient = match &error {
+                        ureq::Error::StatusCode(status) => {
+                            *status >= 500 || *status == 408 || *status == 429
+                        }
+                        _ => true,
+                    };
+                    return Err(Failure {
+                        error: io::Error::other(error),
+                        transient,
+                    });
+                }
+            };
+            let rest = response.body().content_length();
+            match response.status().as_u16() {
+                206 => {}
+                _ if self.offset == 0 => {}
+                _ => {
+                    return Err(permanent(io::Error::other(
+                        "The server can't resume the download where it stopped",
+                    )));
+                }
+            }
+            if self.length.is_none() {
+                self.length = rest.map(|rest| self.offset + rest);
+            }
+            Ok(Some(response.into_body().into_reader()))
+        }
+
+        /// Note that all of the file has been read, moving a spooled download to its final name.
+        fn finish(&mut self) -> Result<(), Failure> {
+            self.length = Some(self.offset);
+            if let Some(spool) = self.spool.take() {
+                spool.file.sync_all().map_err(permanent)?;
+                fs::rename(&spool.partial, &spool.done).map_err(permanent)?;
+                tracing::info!("Spooled {} to {}", self.url, spool.done.display());
+            }
+            Ok(())
+        }
+    }
+
+    fn permanent(error: io::Error) -> Failure {
+        Failure {
+            error,
+            transient: false,
+        }
+    }
+}
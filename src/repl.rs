@@ -0,0 +1,271 @@
+//! An interactive prompt for exploring a saved graph, which is loaded once rather than by every
+//! command. Titles are found as by `path`, and Tab completes them from what has been typed.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::{Adjacency as _, Direction, Graph},
+    path,
+    sample::Rng,
+    workspace,
+};
+use anyhow::Context as _;
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Editor,
+};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+const HELP: &str = "\
+links <title>      pages <title> links to
+back <title>       pages linking to <title>
+path <a> -> <b>    a shortest chain of links from <a> to <b>
+random [n]         n random pages, one without n
+help               this list
+quit               leave, as does Ctrl-D";
+
+/// Most titles completed at once.
+const COMPLETIONS: usize = 100;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Most pages listed by `links` and `back`
+    #[arg(long, value_name = "N", default_value_t = 50)]
+    limit: usize,
+
+    /// Give up on each path search after this many seconds; Ctrl-C gives up on one too
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let mut editor: Editor<Titles, DefaultHistory> =
+        Editor::new().context("Failed to start the prompt").unwrap();
+    editor.set_helper(Some(Titles::new(&graph)));
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    {
+        use signal_hook::{consts::SIGINT, flag};
+        // At the prompt Ctrl-C is a key, so this only sees it during a command: the first gives
+        // up on the command, and a second one before the next command quits.
+        flag::register_conditional_shutdown(SIGINT, 130, Arc::clone(&interrupted))
+            .and_then(|_| flag::register(SIGINT, Arc::clone(&interrupted)))
+            .context("Failed to listen for Ctrl-C")
+            .unwrap();
+    }
+    let mut rng = Rng::seeded(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+            }),
+        &[],
+    );
+
+    println!(
+        "{} nodes and {} links; type `help` for commands",
+        graph.node_count(),
+        graph.edge_count()
+    );
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => panic!("Failed to read command: {error}"),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Not being able to go back to a command is no reason to stop.
+        let _ = editor.add_history_entry(line);
+        interrupted.store(false, Ordering::Relaxed);
+        let cancel =
+            Cancel::after(args.timeout.map(Duration::from_secs)).or_when(Arc::clone(&interrupted));
+
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let result = match command {
+            "links" => list(&graph, rest.trim(), args.limit, Graph::links, "links"),
+            "back" | "backlinks" => list(
+                &graph,
+                rest.trim(),
+                args.limit,
+                Graph::backlinks,
+                "backlinks",
+            ),
+            "path" => shortest_path(&graph, rest, &cancel),
+            "random" => random(&graph, rest.trim(), &mut rng),
+            "help" | "?" => {
+                println!("{HELP}");
+                Ok(())
+            }
+            "quit" | "exit" => break,
+            _ => Err(anyhow::anyhow!(
+                "Unknown command '{command}'; type `help` for commands"
+            )),
+        };
+        if let Err(error) = result {
+            println!("{error:#}");
+        }
+    }
+}
+
+/// Print the first `limit` pages of `list` for the page titled `title`.
+fn list(
+    graph: &Graph,
+    title: &str,
+    limit: usize,
+    list: fn(&Graph, u32) -> &[u32],
+    noun: &str,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(!title.is_empty(), "Which page?");
+    let node = path::find(graph, title)?;
+    let nodes = list(graph, node);
+    for &node in nodes.iter().take(limit) {
+        println!("  {}", graph.title(node));
+    }
+    if nodes.len() > limit {
+        println!("{limit} of {} {noun} of {}", nodes.len(), graph.title(node));
+    } else {
+        println!("{} {noun} of {}", nodes.len(), graph.title(node));
+    }
+    Ok(())
+}
+
+/// Print a shortest chain of links for `<a> -> <b>`.
+fn shortest_path(graph: &Graph, titles: &str, cancel: &Cancel) -> anyhow::Result<()> {
+    let (from, to) = titles
+        .split_once("->")
+        .context("Give two titles, as in `path Rust -> Finland`")?;
+    let (from, to) = (
+        path::find(graph, from.trim())?,
+        path::find(graph, to.trim())?,
+    );
+    match graph.shortest_path(from, to, Direction::Out, |_| true, cancel) {
+        Ok(Some(path)) => {
+            let titles: Vec<&str> = path.iter().map(|&node| graph.title(node)).collect();
+            println!("{}", titles.join(" → "));
+            println!(
+                "{} {}",
+                path.len() - 1,
+                if path.len() == 2 { "link" } else { "links" }
+            );
+        }
+        Ok(None) => println!(
+            "No path from '{}' to '{}'",
+            graph.title(from),
+            graph.title(to)
+        ),
+        Err(cancelled @ Cancelled::TimedOut(_)) => println!("{cancelled}"),
+        Err(Cancelled::Interrupted) => println!("Interrupted"),
+    }
+    Ok(())
+}
+
+/// Print `count` random pages that aren't redirects.
+fn random(graph: &Graph, count: &str, rng: &mut Rng) -> anyhow::Result<()> {
+    let count: usize = if count.is_empty() {
+        1
+    } else {
+        count.parse().context("Give the number of pages")?
+    };
+    let nodes = graph.node_count();
+    anyhow::ensure!(nodes > 0, "The graph is empty");
+    // A graph of only redirects has no pages to print, so stop drawing at some point.
+    let mut printed = 0;
+    for _ in 0..count.saturating_mul(100) {
+        if printed == count {
+            break;
+        }
+        let node = u32::try_from(rng.below(nodes)).unwrap();
+        if graph.redirect(node).is_none() {
+            println!("  {}", graph.title(node));
+            printed += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Completes the title being typed from the graph's titles, with underscores for spaces and
+/// either case for the first letter, as MediaWiki takes them.
+struct Titles<'a> {
+    graph: &'a Graph,
+    /// Every node, in order of title.
+    sorted: Vec<u32>,
+}
+
+impl<'a> Titles<'a> {
+    fn new(graph: &'a Graph) -> Self {
+        let mut sorted: Vec<u32> = (0..u32::try_from(graph.node_count()).unwrap()).collect();
+        sorted.sort_unstable_by_key(|&node| graph.title(node));
+        Self { graph, sorted }
+    }
+
+    /// Titles starting with `prefix`.
+    fn starting_with(&self, prefix: &str) -> Vec<String> {
+        let mut chars = prefix.chars();
+        let prefix: String = chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default();
+        let prefix = prefix.replace('_', " ");
+        let start = self
+            .sorted
+            .partition_point(|&node| self.graph.title(node) < prefix.as_str());
+        self.sorted[start..]
+            .iter()
+            .map(|&node| self.graph.title(node))
+            .take_while(|title| title.starts_with(&prefix))
+            .take(COMPLETIONS)
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl Completer for Titles<'_> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let typed = &line[..pos];
+        let Some((command, _)) = typed.split_once(' ') else {
+            return Ok((0, Vec::new()));
+        };
+        if !matches!(command, "links" | "back" | "backlinks" | "path") {
+            return Ok((0, Vec::new()));
+        }
+        // The title starts after the command, or after the arrow of a path.
+        let start = typed.rfind("->").map_or(command.len(), |arrow| arrow + 2);
+        let start = start + (typed[start..].len() - typed[start..].trim_start().len());
+        Ok((start, self.starting_with(&typed[start..])))
+    }
+}
+
+impl Hinter for Titles<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for Titles<'_> {}
+
+impl Validator for Titles<'_> {}
+
+impl rustyline::Helper for Titles<'_> {}
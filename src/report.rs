@@ -0,0 +1,461 @@
+//! Reports of how a wiki changed between two builds, such as last month's dump and this
+//! month's, as Markdown or HTML to share: how much it grew, which pages gained and lost the most
+//! links and PageRank, which pages became hubs, and the largest clusters of pages that are new.
+//! Pages are matched between the builds by title.
+
+use crate::{
+    cancel::Cancel,
+    graph::Graph,
+    stats::{self, PageRank},
+    workspace,
+};
+use anyhow::Context as _;
+use quick_xml::escape::escape;
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph of the earlier build: a file saved by `parse --graph`, or its name in the
+    /// workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    old: PathBuf,
+
+    /// Graph of the later build
+    #[arg(value_parser = workspace::graph_path)]
+    new: PathBuf,
+
+    /// Where to write the report [default: standard output]
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// [default: HTML if `--output` ends in `.html`, else Markdown]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Number of pages listed in each table
+    #[arg(long, value_name = "K", default_value_t = 10)]
+    top: usize,
+
+    /// Give up on computing PageRank after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+/// A titled table of the report, with a sentence explaining it.
+pub struct Section {
+    pub heading: &'static str,
+    pub summary: String,
+    pub header: &'static [&'static str],
+    pub rows: Vec<Vec<String>>,
+}
+
+pub fn run(args: &Args) {
+    let load = |path: &PathBuf| {
+        Graph::load(path)
+            .with_context(|| format!("Failed to load {}", path.display()))
+            .unwrap()
+    };
+    let (old, new) = (load(&args.old), load(&args.new));
+    let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+    let pagerank = |graph: &Graph| {
+        stats::stored_pagerank(graph, stats::DAMPING, stats::ITERATIONS).map_or_else(
+            || {
+                stats::pagerank_scores(graph, stats::DAMPING, stats::ITERATIONS, &cancel)
+                    .context("Failed to compute PageRank")
+                    .unwrap()
+            },
+            |pagerank: &PageRank| pagerank.scores.clone(),
+        )
+    };
+    let builds = Builds {
+        old: Build::new(&old, pagerank(&old)),
+        new: Build::new(&new, pagerank(&new)),
+    };
+
+    let title = format!(
+        "Changes from {} to {}",
+        name(&old, &args.old),
+        name(&new, &args.new)
+    );
+    let sections = [
+        builds.growth(),
+        builds.in_degree_movers(args.top),
+        builds.pagerank_movers(args.top),
+        builds.new_hubs(args.top),
+        builds.new_components(args.top),
+    ];
+    write(args.output.as_deref(), args.format, &title, &sections);
+}
+
+/// Write a document of `title` and `sections` to `output`, or standard output, as `format`:
+/// by default HTML if `output` ends in `.html`, else Markdown.
+pub fn write(output: Option<&Path>, format: Option<Format>, title: &str, sections: &[Section]) {
+    let format = format.unwrap_or_else(|| {
+        let html = output.is_some_and(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("html"))
+        });
+        if html {
+            Format::Html
+        } else {
+            Format::Markdown
+        }
+    });
+    let document = match format {
+        Format::Markdown => markdown(title, sections),
+        Format::Html => html(title, sections),
+    };
+    match output {
+        Some(path) => fs::write(path, document)
+            .with_context(|| format!("Failed to write {}", path.display()))
+            .unwrap(),
+        None => io::stdout()
+            .write_all(document.as_bytes())
+            .context("Failed to write report")
+            .unwrap(),
+    }
+}
+
+/// What a build is called in the report: its dump date, else its dump, else its file.
+pub fn name(graph: &Graph, path: &Path) -> String {
+    let metadata = graph.metadata();
+    metadata
+        .dump_date
+        .clone()
+        .or_else(|| metadata.dump.clone())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// A build with its PageRank, the rank of each node by it, and the component of each node.
+struct Build<'a> {
+    graph: &'a Graph,
+    pagerank: Vec<f64>,
+    ranks: Vec<u32>,
+    components: Vec<u32>,
+}
+
+impl<'a> Build<'a> {
+    fn new(graph: &'a Graph, pagerank: Vec<f64>) -> Self {
+        let mut order: Vec<u32> = (0..u32::try_from(graph.node_count()).unwrap()).collect();
+        order.sort_by(|&a, &b| pagerank[b as usize].total_cmp(&pagerank[a as usize]));
+        let mut ranks = vec![0; order.len()];
+        for (rank, &node) in (1..).zip(&order) {
+            ranks[node as usize] = rank;
+        }
+        let components = graph.stats().map_or_else(
+            || stats::components(graph),
+            |stats| stats.components.clone(),
+        );
+        Self {
+            graph,
+            pagerank,
+            ranks,
+            components,
+        }
+    }
+
+    /// Nodes that are pages rather than redirects.
+    fn pages(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..u32::try_from(self.graph.node_count()).unwrap())
+            .filter(|&node| self.graph.redirect(node).is_none())
+    }
+
+    /// The PageRank of `node` over that of the average node.
+    #[allow(clippy::cast_precision_loss)]
+    fn relative(&self, node: u32) -> f64 {
+        self.pagerank[node as usize] * self.graph.node_count() as f64
+    }
+
+    fn component_count(&self) -> usize {
+        self.components
+            .iter()
+            .max()
+            .map_or(0, |&max| max as usize + 1)
+    }
+
+    /// The `k` pages with the most links in, most first.
+    fn hubs(&self, k: usize) -> Vec<u32> {
+        let mut pages: Vec<u32> = self.pages().collect();
+        pages.sort_by_key(|&node| std::cmp::Reverse(self.graph.in_degree(node)));
+        pages.truncate(k);
+        pages
+    }
+}
+
+struct Builds<'a> {
+    old: Build<'a>,
+    new: Build<'a>,
+}
+
+impl Builds<'_> {
+    /// Pages of the new build with the node of the same title in the old one, if it is a page
+    /// there too.
+    fn matched(&self) -> impl Iterator<Item = (u32, Option<u32>)> + '_ {
+        self.new.pages().map(|node| {
+            let old = self
+                .old
+                .graph
+                .id(self.new.graph.title(node))
+                .filter(|&old| self.old.graph.redirect(old).is_none());
+            (node, old)
+        })
+    }
+
+    fn growth(&self) -> Section {
+        let count = |build: &Build| {
+            let pages = build.pages().count();
+            [
+                pages,
+                build.graph.node_count() - pages,
+                build.graph.edge_count(),
+                build.component_count(),
+            ]
+        };
+        let (old, new) = (count(&self.old), count(&self.new));
+        let added = self.matched().filter(|(_, old)| old.is_none()).count();
+        let removed = self
+            .old
+            .pages()
+            .filter(|&node| {
+                self.new
+                    .graph
+                    .id(self.old.graph.title(node))
+                    .is_none_or(|new| self.new.graph.redirect(new).is_some())
+            })
+            .count();
+        let rows = ["Pages", "Redirects", "Links", "Weakly connected components"]
+            .iter()
+            .zip(old.iter().zip(&new))
+            .map(|(metric, (&old, &new))| {
+                vec![
+                    String::from(*metric),
+                    old.to_string(),
+                    new.to_string(),
+                    change(old, new),
+                ]
+            })
+            .collect();
+        Section {
+            heading: "Growth",
+            summary: format!("{added} pages are new, and {removed} are gone or now redirect."),
+            header: &["", "Before", "After", "Change"],
+            rows,
+        }
+    }
+
+    fn in_degree_movers(&self, top: usize) -> Section {
+        let mut moves: Vec<(u32, u32, u32)> = self
+            .matched()
+            .filter_map(|(new, old)| {
+                Some((
+                    new,
+                    self.old.graph.in_degree(old?),
+                    self.new.graph.in_degree(new),
+                ))
+            })
+            .filter(|&(_, before, after)| before != after)
+            .collect();
+        moves.sort_by_key(|&(_, before, after)| std::cmp::Reverse(before.abs_diff(after)));
+        moves.truncate(top);
+        Section {
+            heading: "Biggest movers in links in",
+            summary: String::from("Pages whose number of links in changed the most."),
+            header: &["Page", "Before", "After", "Change"],
+            rows: moves
+                .into_iter()
+                .map(|(node, before, after)| {
+                    vec![
+                        String::from(self.new.graph.title(node)),
+                        before.to_string(),
+                        after.to_string(),
+                        change(before as usize, after as usize),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    fn pagerank_movers(&self, top: usize) -> Section {
+        let delta = |(new, old): (u32, u32)| self.new.relative(new) - self.old.relative(old);
+        let mut moves: Vec<(u32, u32)> = self
+            .matched()
+            .filter_map(|(new, old)| Some((new, old?)))
+            .filter(|&pair| delta(pair) != 0.0)
+            .collect();
+        moves.sort_by(|&a, &b| delta(b).abs().total_cmp(&delta(a).abs()));
+        moves.truncate(top);
+        Section {
+            heading: "Biggest movers in PageRank",
+            summary: format!(
+                "Pages whose PageRank (damping {}, {} iterations) changed the most. Scores are \
+                 relative to the average page, so that the wiki growing doesn't lower them all.",
+                stats::DAMPING,
+                stats::ITERATIONS
+            ),
+            header: &["Page", "Rank before", "Rank after", "Before", "After"],
+            rows: moves
+                .into_iter()
+                .map(|(new, old)| {
+                    vec![
+                        String::from(self.new.graph.title(new)),
+                        self.old.ranks[old as usize].to_string(),
+                        self.new.ranks[new as usize].to_string(),
+                        format!("{:.2}", self.old.relative(old)),
+                        format!("{:.2}", self.new.relative(new)),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    fn new_hubs(&self, top: usize) -> Section {
+        let old_hubs: Vec<&str> = self
+            .old
+            .hubs(top)
+            .into_iter()
+            .map(|node| self.old.graph.title(node))
+            .collect();
+        let rows = self
+            .new
+            .hubs(top)
+            .into_iter()
+            .filter(|&node| !old_hubs.contains(&self.new.graph.title(node)))
+            .map(|node| {
+                let title = self.new.graph.title(node);
+                let before = self.old.graph.id(title).map_or_else(
+                    || String::from("new page"),
+                    |old| self.old.graph.in_degree(old).to_string(),
+                );
+                vec![
+                    String::from(title),
+                    before,
+                    self.new.graph.in_degree(node).to_string(),
+                ]
+            })
+            .collect();
+        Section {
+            heading: "New hubs",
+            summary: format!("Pages among the {top} with the most links in that weren't before."),
+            header: &["Page", "Links in before", "Links in after"],
+            rows,
+        }
+    }
+
+    fn new_components(&self, top: usize) -> Section {
+        let graph = self.new.graph;
+        let count = self.new.component_count();
+        let mut sizes = vec![0; count];
+        let mut hubs: Vec<Option<u32>> = vec![None; count];
+        let mut known = vec![false; count];
+        for (node, &component) in (0..).zip(&self.new.components) {
+            let component = component as usize;
+            sizes[component] += 1;
+            known[component] |= self.old.graph.id(graph.title(node)).is_some();
+            if hubs[component].is_none_or(|hub| graph.in_degree(node) > graph.in_degree(hub)) {
+                hubs[component] = Some(node);
+            }
+        }
+        let mut new: Vec<(usize, u32)> = (0..count)
+            .filter(|&component| !known[component] && sizes[component] > 1)
+            .map(|component| (sizes[component], hubs[component].unwrap()))
+            .collect();
+        new.sort_by_key(|&(size, _)| std::cmp::Reverse(size));
+        new.truncate(top);
+        Section {
+            heading: "Largest new components",
+            summary: String::from(
+                "Weakly connected components of two pages or more, none of which were in the \
+                 earlier build, with their most linked-to page.",
+            ),
+            header: &["Most linked-to page", "Pages"],
+            rows: new
+                .into_iter()
+                .map(|(size, page)| vec![String::from(graph.title(page)), size.to_string()])
+                .collect(),
+        }
+    }
+}
+
+/// The change from `old` to `new`, with a sign and as a percentage.
+#[allow(clippy::cast_precision_loss)]
+fn change(old: usize, new: usize) -> String {
+    let difference = new as i128 - old as i128;
+    if old == 0 {
+        format!("{difference:+}")
+    } else {
+        let percent = difference as f64 / old as f64 * 100.0;
+        format!("{difference:+} ({percent:+.1}%)")
+    }
+}
+
+fn markdown(title: &str, sections: &[Section]) -> String {
+    let cell = |text: &str| text.replace('|', r"\|");
+    let mut out = format!("# {}\n", cell(title));
+    for section in sections {
+        let _ = write!(out, "\n## {}\n\n{}\n\n", section.heading, section.summary);
+        if section.rows.is_empty() {
+            out.push_str("None.\n");
+            continue;
+        }
+        let _ = writeln!(out, "| {} |", section.header.join(" | "));
+        // Numbers, in every column but the first, are right-aligned.
+        let align: Vec<&str> = (0..section.header.len())
+            .map(|column| if column == 0 { "---" } else { "--:" })
+            .collect();
+        let _ = writeln!(out, "|{}|", align.join("|"));
+        for row in &section.rows {
+            let row: Vec<String> = row.iter().map(|field| cell(field)).collect();
+            let _ = writeln!(out, "| {} |", row.join(" | "));
+        }
+    }
+    out
+}
+
+fn html(title: &str, sections: &[Section]) -> String {
+    let title = escape(title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>body {{ font-family: sans-serif; max-width: 60em; margin: auto; }} \
+         table {{ border-collapse: collapse; }} th, td {{ padding: 0.2em 0.8em; }} \
+         td + td {{ text-align: right; }}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+    for section in sections {
+        let _ = write!(
+            out,
+            "<h2>{}</h2>\n<p>{}</p>\n",
+            section.heading,
+            escape(&section.summary)
+        );
+        if section.rows.is_empty() {
+            out.push_str("<p>None.</p>\n");
+            continue;
+        }
+        out.push_str("<table>\n<tr>");
+        for header in section.header {
+            let _ = write!(out, "<th>{header}</th>");
+        }
+        out.push_str("</tr>\n");
+        for row in &section.rows {
+            out.push_str("<tr>");
+            for field in row {
+                let _ = write!(out, "<td>{}</td>", escape(field));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
@@ -0,0 +1,121 @@
+//! User-supplied link extraction rules, layered on top of the project profile, for wikis and
+//! experiments the built-in extractor doesn't cover. Rules are read from a TOML file:
+//!
+//! ```toml
+//! # Regexes whose `target` group (or else first group) is a link target.
+//! patterns = ['\{\{ill\|([^|}]+)']
+//!
+//! # Link-producing templates, like the profile's: the 1-based first positional argument
+//! # holding a target, and whether every following argument does too.
+//! [[templates]]
+//! name = "Redirect-distinguish"
+//! first = 2
+//! repeated = true
+//!
+//! # Targets starting with any of these are dropped.
+//! ignore_prefixes = ["List of ", "Wikipedia:"]
+//! ```
+
+use crate::template;
+use anyhow::Context as _;
+use regex::Regex;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default)]
+    patterns: Vec<String>,
+    #[serde(default)]
+    templates: Vec<TemplateRule>,
+    #[serde(default)]
+    ignore_prefixes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateRule {
+    name: String,
+    #[serde(default = "first_argument")]
+    first: usize,
+    #[serde(default)]
+    repeated: bool,
+}
+
+const fn first_argument() -> usize {
+    1
+}
+
+#[derive(Default)]
+pub struct Rules {
+    patterns: Vec<Regex>,
+    templates: Vec<TemplateRule>,
+    ignore_prefixes: Vec<String>,
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let config: Config = toml::from_str(&fs::read_to_string(path)?)?;
+        let patterns = config
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).with_context(|| format!("Invalid pattern '{pattern}'"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        anyhow::ensure!(
+            config.templates.iter().all(|rule| rule.first > 0),
+            "Template argument indices start at 1"
+        );
+        Ok(Self {
+            patterns,
+            templates: config.templates,
+            ignore_prefixes: config.ignore_prefixes,
+        })
+    }
+
+    /// Link targets matched by the extra patterns and templates.
+    pub fn links<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut links = self.pattern_links(text);
+        if !self.templates.is_empty() {
+            for template in template::templates(text) {
+                links.extend(self.template_targets(&template));
+            }
+        }
+        links
+    }
+
+    /// Link targets matched by the extra patterns.
+    pub fn pattern_links<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut links = Vec::new();
+        for pattern in &self.patterns {
+            for captures in pattern.captures_iter(text) {
+                let target = captures
+                    .name("target")
+                    .or_else(|| captures.get(1))
+                    .or_else(|| captures.get(0))
+                    .map_or("", |target| target.as_str().trim());
+                if !target.is_empty() {
+                    links.push(target);
+                }
+            }
+        }
+        links
+    }
+
+    /// Link targets among the arguments of `template`, if a rule makes it a link template.
+    pub fn template_targets<'a>(&self, template: &template::Template<'a>) -> Vec<&'a str> {
+        self.templates
+            .iter()
+            .find(|rule| template.is(&rule.name))
+            .map(|rule| template.link_args(rule.first, rule.repeated).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn ignores(&self, target: &str) -> bool {
+        self.ignore_prefixes
+            .iter()
+            .any(|prefix| target.starts_with(prefix.as_str()))
+    }
+}
@@ -0,0 +1,99 @@
+//! `parse --report`: a summary of a parse as JSON, for pipelines to check the counts of rather
+//! than scraping them from the log. Counts cover the pages parsed in this run, so after
+//! `--resume` they start from the checkpoint.
+
+use crate::graph::Metadata;
+use anyhow::Context as _;
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Write as _},
+    path::Path,
+    time::Instant,
+};
+use wikigraph::dump::Page;
+
+#[derive(Default, Serialize)]
+pub struct Report {
+    /// Pages read from the dump, in any namespace, or revisions of a full-history dump.
+    pages_read: u64,
+    /// Pages read that were parsed, rather than left out for their namespace, `--skip-list`,
+    /// their size, or `--script`.
+    pages_parsed: u64,
+    /// Pages read and left out.
+    pages_left_out: u64,
+    /// Pages the dump reader skipped as malformed, which aren't among those read.
+    malformed: u64,
+    /// Parsed pages that are redirects.
+    redirects: u64,
+    /// Links found in the parsed pages, each target counted once a page.
+    links: u64,
+    /// Links of the graph once redirects and red links are dealt with, unless pages are only
+    /// streamed out with `--format`.
+    edges: Option<u64>,
+    /// Parsed pages of each namespace.
+    namespaces: BTreeMap<i64, u64>,
+    wall_seconds: f64,
+    /// Most memory the process had resident, where the system says (Linux).
+    peak_rss_bytes: Option<u64>,
+    provenance: Option<Metadata>,
+}
+
+impl Report {
+    /// Count `page`, parsed with links to `targets`.
+    pub fn add_page(&mut self, page: &Page, targets: &[Cow<str>]) {
+        let distinct: HashSet<&str> = targets.iter().map(AsRef::as_ref).collect();
+        self.pages_parsed += 1;
+        self.redirects += u64::from(page.redirect.is_some());
+        self.links += distinct.len() as u64;
+        *self.namespaces.entry(page.namespace).or_default() += 1;
+    }
+
+    /// Count a reading of a dump in which `read` pages were read and `malformed` skipped.
+    pub fn add_reading(&mut self, read: u64, malformed: u64) {
+        self.pages_read += read;
+        self.malformed += malformed;
+    }
+
+    /// Write the report to `path`, for a parse started at `started` whose outputs have
+    /// `metadata` and whose graph has `edges`.
+    pub fn write(
+        mut self,
+        path: &Path,
+        started: Instant,
+        edges: Option<u64>,
+        metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        self.pages_left_out = self.pages_read.saturating_sub(self.pages_parsed);
+        self.edges = edges;
+        self.wall_seconds = started.elapsed().as_secs_f64();
+        self.peak_rss_bytes = peak_rss();
+        self.provenance = Some(metadata.clone());
+        let mut writer = BufWriter::new(
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+        );
+        serde_json::to_writer_pretty(&mut writer, &self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The high-water mark of the resident memory of this process, from `/proc/self/status`.
+pub fn peak_rss() -> Option<u64> {
+    memory_status("VmHWM:")
+}
+
+/// The resident memory of this process, from `/proc/self/status`.
+pub fn rss() -> Option<u64> {
+    memory_status("VmRSS:")
+}
+
+fn memory_status(field: &str) -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find_map(|line| line.strip_prefix(field))?;
+    let kibibytes: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kibibytes * 1024)
+}
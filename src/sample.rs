@@ -0,0 +1,356 @@
+//! Deterministic sampling. Decisions are derived from a hash of the seed and the titles
+//! involved, not from parse order, so the same seed picks the same pages and edges on every run.
+//!
+//! The `sample` command draws subgraphs of a saved graph by exploring it from random pages
+//! instead, which keeps much more of its local structure than dropping edges independently.
+
+use crate::{
+    graph::{Adjacency as _, Direction, Graph},
+    workspace,
+};
+use anyhow::Context as _;
+use std::{collections::VecDeque, path::PathBuf};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Where to save the sampled graph
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// How to explore the graph
+    #[arg(long, value_enum)]
+    method: Method,
+
+    /// Number of nodes to sample
+    #[arg(long, value_name = "N")]
+    size: usize,
+
+    /// Page to start exploring from [default: a random page]
+    #[arg(long, value_name = "TITLE")]
+    start: Option<String>,
+
+    /// Which links snowball sampling and random walks follow
+    #[arg(long, value_enum, default_value_t = Direction::Both)]
+    direction: Direction,
+
+    /// Seed for sampling; the same seed draws the same sample of the same graph
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Forest fire: probability of burning one more outgoing link, so each page burns
+    /// P / (1 - P) of them on average
+    #[arg(long, value_name = "P", default_value_t = 0.7, value_parser = parse_burn_probability)]
+    burn_probability: f64,
+
+    /// Forest fire: burning probability of incoming links, relative to `--burn-probability`
+    #[arg(long, value_name = "R", default_value_t = 0.2)]
+    backward_burn_ratio: f64,
+
+    /// Random walk: probability of jumping back to the start before each step
+    #[arg(long, value_name = "P", default_value_t = 0.15, value_parser = crate::parse_probability)]
+    restart_probability: f64,
+
+    /// Snowball: most neighbours of each page to add, picked at random [default: all]
+    #[arg(long, value_name = "K")]
+    snowball_neighbours: Option<usize>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Method {
+    /// Spread from the start like a fire, burning a random number of the links of each page
+    /// reached, and of the links to it
+    ForestFire,
+    /// Walk from the start along random links, jumping back to it now and then
+    RandomWalk,
+    /// Add the neighbours of the start, then theirs, and so on, breadth first
+    Snowball,
+}
+
+/// Walk steps without reaching a new page before a random walk starts over somewhere else,
+/// per sampled page.
+const STUCK_STEPS: usize = 100;
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let start = args.start.as_deref().map(|title| {
+        graph
+            .id(title)
+            .with_context(|| format!("No page titled '{title}'"))
+            .unwrap()
+    });
+
+    let mut sampler = Sampler::new(&graph, args, start);
+    match args.method {
+        Method::ForestFire => sampler.forest_fire(args.burn_probability, args.backward_burn_ratio),
+        Method::RandomWalk => sampler.random_walk(args.direction, args.restart_probability),
+        Method::Snowball => sampler.snowball(args.direction, args.snowball_neighbours),
+    }
+    let sample = graph.subgraph(&sampler.keep);
+    sample
+        .save(&args.output)
+        .context("Failed to save sampled graph")
+        .unwrap();
+
+    println!(
+        "Sampled {} of {} nodes and {} of {} links",
+        sample.node_count(),
+        graph.node_count(),
+        sample.edge_count(),
+        graph.edge_count()
+    );
+}
+
+fn parse_burn_probability(s: &str) -> Result<f64, String> {
+    let p: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..1.0).contains(&p) {
+        Ok(p)
+    } else {
+        Err(String::from("must be at least 0 and less than 1"))
+    }
+}
+
+/// The nodes sampled so far, and where to start exploring next.
+struct Sampler<'a> {
+    graph: &'a Graph,
+    rng: Rng,
+    keep: Vec<bool>,
+    kept: usize,
+    size: usize,
+    /// The `--start` page, used before any random one.
+    start: Option<u32>,
+}
+
+impl<'a> Sampler<'a> {
+    fn new(graph: &'a Graph, args: &Args, start: Option<u32>) -> Self {
+        Self {
+            graph,
+            rng: Rng::seeded(args.seed, &["sample"]),
+            keep: vec![false; graph.node_count()],
+            kept: 0,
+            size: args.size.min(graph.node_count()),
+            start,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.kept >= self.size
+    }
+
+    /// Add `node` to the sample, returning whether it is new.
+    fn add(&mut self, node: u32) -> bool {
+        let new = !self.keep[node as usize];
+        if new {
+            self.keep[node as usize] = true;
+            self.kept += 1;
+        }
+        new
+    }
+
+    /// The `--start` page the first time, and then random pages not sampled yet, added to the
+    /// sample. Exploring from more than one start lets samples grow past the start's component.
+    fn next_start(&mut self) -> u32 {
+        let node = match self.start.take() {
+            Some(node) => node,
+            None => loop {
+                let node = self.rng.below(self.keep.len());
+                if !self.keep[node] {
+                    break u32::try_from(node).unwrap();
+                }
+            },
+        };
+        self.add(node);
+        node
+    }
+
+    /// Burn from each start, spreading to a geometric number of the unburnt links of each page
+    /// and of the links to it, as in Leskovec and Faloutsos' "Sampling from Large Graphs".
+    fn forest_fire(&mut self, forward: f64, backward_ratio: f64) {
+        let backward = forward * backward_ratio;
+        let graph = self.graph;
+        while !self.is_full() {
+            let mut burning = VecDeque::from([self.next_start()]);
+            while let Some(node) = burning.pop_front() {
+                for (neighbours, p) in [
+                    (graph.links(node), forward),
+                    (graph.backlinks(node), backward),
+                ] {
+                    let mut unburnt: Vec<u32> = neighbours
+                        .iter()
+                        .copied()
+                        .filter(|&node| !self.keep[node as usize])
+                        .collect();
+                    let count = self.rng.geometric(p).min(unburnt.len());
+                    for &neighbour in self.rng.choose(&mut unburnt, count) {
+                        if self.is_full() {
+                            return;
+                        }
+                        self.add(neighbour);
+                        burning.push_back(neighbour);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walk from the start, restarting there with probability `restart` before each step,
+    /// and from a new random page when the walk stops finding new pages.
+    fn random_walk(&mut self, direction: Direction, restart: f64) {
+        while !self.is_full() {
+            let start = self.next_start();
+            let mut node = start;
+            let mut stuck = 0;
+            while !self.is_full() && stuck < STUCK_STEPS * self.size {
+                if self.rng.unit() < restart {
+                    node = start;
+                }
+                let neighbours: Vec<u32> = self.graph.neighbours(node, direction).collect();
+                if neighbours.is_empty() {
+                    node = start;
+                    stuck += 1;
+                    continue;
+                }
+                node = neighbours[self.rng.below(neighbours.len())];
+                if self.add(node) {
+                    stuck = 0;
+                } else {
+                    stuck += 1;
+                }
+            }
+        }
+    }
+
+    /// Add the neighbours of each sampled page breadth first, at most `limit` of them per page,
+    /// starting over from a random page when the start's component runs out.
+    fn snowball(&mut self, direction: Direction, limit: Option<usize>) {
+        while !self.is_full() {
+            let mut frontier = VecDeque::from([self.next_start()]);
+            while let Some(node) = frontier.pop_front() {
+                let mut neighbours: Vec<u32> = self
+                    .graph
+                    .neighbours(node, direction)
+                    .filter(|&node| !self.keep[node as usize])
+                    .collect();
+                neighbours.sort_unstable();
+                neighbours.dedup();
+                let count = limit.map_or(neighbours.len(), |limit| limit.min(neighbours.len()));
+                for &neighbour in self.rng.choose(&mut neighbours, count) {
+                    if self.is_full() {
+                        return;
+                    }
+                    self.add(neighbour);
+                    frontier.push_back(neighbour);
+                }
+            }
+        }
+    }
+}
+
+/// SplitMix64, which is plenty for picking links.
+pub struct Rng(u64);
+
+impl Rng {
+    /// A generator seeded from `seed` and `parts`, so that each of several things drawn with
+    /// one seed can have a sequence of its own.
+    pub fn seeded(seed: u64, parts: &[&str]) -> Self {
+        Self(hash(seed, parts))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        finalize(self.0)
+    }
+
+    pub fn unit(&mut self) -> f64 {
+        unit(self.next())
+    }
+
+    /// A number below `n`, nearly uniformly.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn below(&mut self, n: usize) -> usize {
+        ((u128::from(self.next()) * n as u128) >> 64) as usize
+    }
+
+    /// Successes before the first failure, where each trial succeeds with probability `p`.
+    fn geometric(&mut self, p: f64) -> usize {
+        let mut count = 0;
+        while self.unit() < p {
+            count += 1;
+        }
+        count
+    }
+
+    /// `count` random items of `items`, by a partial Fisher–Yates shuffle.
+    fn choose<'a>(&mut self, items: &'a mut [u32], count: usize) -> &'a [u32] {
+        for i in 0..count {
+            let j = i + self.below(items.len() - i);
+            items.swap(i, j);
+        }
+        &items[..count]
+    }
+}
+
+/// Whether the edge `source → target` survives sampling with keep-probability `p`.
+pub fn keep_edge(seed: u64, p: f64, source: &str, target: &str) -> bool {
+    unit(hash(seed, &[source, target])) < p
+}
+
+/// Whether the page titled `title` survives sampling with keep-probability `p`.
+pub fn keep_page(seed: u64, p: f64, title: &str) -> bool {
+    unit(hash(seed, &["page", title])) < p
+}
+
+/// `n` of the `nodes` (ID and title), picked by the smallest hashes of their titles, so the same
+/// seed picks the same pages whatever their IDs.
+pub fn pick_nodes<'a>(
+    seed: u64,
+    n: usize,
+    nodes: impl Iterator<Item = (u32, &'a str)>,
+) -> Vec<u32> {
+    let mut hashed: Vec<(u64, u32)> = nodes
+        .map(|(node, title)| (hash(seed, &[title]), node))
+        .collect();
+    if n < hashed.len() {
+        hashed.select_nth_unstable(n);
+        hashed.truncate(n);
+    }
+    hashed.sort_unstable();
+    hashed.into_iter().map(|(_, node)| node).collect()
+}
+
+/// 64-bit FNV-1a over the seed and each part (with a separator byte between parts), followed
+/// by the SplitMix64 finalizer to spread FNV's weak low bits over the whole word.
+pub fn hash(seed: u64, parts: &[&str]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let bytes = parts
+        .iter()
+        .flat_map(|part| std::iter::once(0xff).chain(part.bytes()));
+    let x = seed
+        .to_le_bytes()
+        .into_iter()
+        .chain(bytes)
+        .fold(OFFSET, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        });
+
+    finalize(x)
+}
+
+fn finalize(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Map a hash to a float in `[0, 1)` using its top 53 bits.
+#[allow(clippy::cast_precision_loss)]
+fn unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1_u64 << 53) as f64
+}
@@ -0,0 +1,83 @@
+//! A Rhai hook run on every parsed page, for custom attributes and page filters without
+//! recompiling. The script defines
+//!
+//! ```rhai
+//! fn page(title, ns, text, links) {
+//!     if title.starts_with("List of ") { return false; }        // veto the page
+//!     #{ node: #{ length: text.len() }, edges: #{ "C++": #{ first: true } } }
+//! }
+//! ```
+//!
+//! returning `false` to drop the page, `true` or nothing to keep it as is, or a map with `node`
+//! attributes for the page and `edges` attributes keyed by link target.
+
+use anyhow::Context as _;
+use rhai::{Array, Dynamic, Engine, Scope, AST};
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+
+pub type Attributes = serde_json::Map<String, serde_json::Value>;
+
+pub struct Hook {
+    engine: Engine,
+    ast: AST,
+}
+
+/// What the script made of a page it kept.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Output {
+    #[serde(default)]
+    pub node: Attributes,
+    #[serde(default)]
+    pub edges: HashMap<String, Attributes>,
+}
+
+impl Hook {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|error| anyhow::anyhow!("{error}"))?;
+        anyhow::ensure!(
+            ast.iter_functions()
+                .any(|function| function.name == "page" && function.params.len() == 4),
+            "Script must define `fn page(title, ns, text, links)`"
+        );
+        Ok(Self { engine, ast })
+    }
+
+    /// Run the hook on a page with its resolved link targets, or `None` if the script vetoes it.
+    pub fn page(
+        &self,
+        title: &str,
+        namespace: i64,
+        text: &str,
+        links: &[&str],
+    ) -> anyhow::Result<Option<Output>> {
+        let links: Array = links
+            .iter()
+            .map(|&link| Dynamic::from(link.to_owned()))
+            .collect();
+        let result: Dynamic = self
+            .engine
+            .call_fn(
+                &mut Scope::new(),
+                &self.ast,
+                "page",
+                (title.to_owned(), namespace, text.to_owned(), links),
+            )
+            .map_err(|error| anyhow::anyhow!("{error}"))?;
+
+        if result.is_unit() {
+            return Ok(Some(Output::default()));
+        }
+        if let Ok(keep) = result.as_bool() {
+            return Ok(keep.then(Output::default));
+        }
+        rhai::serde::from_dynamic(&result)
+            .map(Some)
+            .map_err(|error| anyhow::anyhow!("{error}"))
+            .context("Script must return a bool or a map of `node` and `edges` attributes")
+    }
+}
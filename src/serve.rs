@@ -0,0 +1,720 @@
+//! A small HTTP server answering queries against saved graphs. Requests are handled by a fixed
+//! pool of threads sharing the loaded graphs, which can be replaced while serving: the first by
+//! `--watch`, and any by reloading its file on SIGHUP or, for clients with an `--admin-key`, on
+//! `POST /admin/reload`. Queries already running finish on the graph they started on. Path
+//! queries give up after `--timeout`. Every response is JSON.
+//!
+//! Pages are named by a `title` parameter, or in the path as in `/links/Albert_Einstein`,
+//! percent-encoded and with underscores for spaces as in the page's URL on the wiki, and found
+//! as `path` finds them. Titles of redirects are answered for the pages they lead to, listed in
+//! `resolved`. Pages are answered with their Wikidata item if the graph was built with
+//! `parse --page-props`.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::{Adjacency as _, Direction, Graph},
+    path,
+    query::Resolution,
+    sample::Rng,
+    text_index, wikidata, workspace,
+};
+use anyhow::Context as _;
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+use tiny_http::{Header, Method, Request, Response, ResponseBox, Server};
+
+mod auth;
+mod limit;
+mod stream;
+mod watch;
+
+/// Default page size for paginated JSON responses.
+const PAGE_SIZE: usize = 1000;
+
+type Params<'a> = HashMap<Cow<'a, str>, Cow<'a, str>>;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph files saved by `parse --graph`, or their names in the workspace (see `graphs`).
+    /// Requests choose one with `graph=NAME`, and get the first without; files are named after
+    /// their stem.
+    #[arg(required = true, value_name = "GRAPH", value_parser = workspace::named_graph)]
+    graphs: Vec<(String, PathBuf)>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// Port to listen on, replacing the port of `--listen`
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Number of request handling threads
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    threads: usize,
+
+    /// File of API keys, one per line as `NAME KEY` or just `KEY`; when any keys are configured,
+    /// requests must send one as `Authorization: Bearer <KEY>` or `X-API-Key: <KEY>`
+    #[arg(long, value_name = "FILE", env = "WIKIGRAPH_API_KEYS_FILE")]
+    api_keys: Option<PathBuf>,
+
+    /// API key to accept, in addition to those in `--api-keys`
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "WIKIGRAPH_API_KEY",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    api_key: Vec<String>,
+
+    /// Key allowing `POST /admin/reload`, sent like an API key; unless one is given, graphs are
+    /// only reloaded on SIGHUP, so that clients with read-only keys can't force reloads
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "WIKIGRAPH_ADMIN_KEY",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    admin_key: Vec<String>,
+
+    /// Maximum requests per minute from each client (API key, or IP address without keys);
+    /// further requests get 429 Too Many Requests
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    rate_limit: Option<u32>,
+
+    /// Maximum queries running at once; further queries get 503 Service Unavailable
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_concurrent: Option<u32>,
+
+    /// Give up on each `/path` query after this many seconds, answering 504 Gateway Timeout
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    timeout: u64,
+
+    /// Watch this directory for new dumps, rebuilding the first graph (and its text index, if it
+    /// has one) from each dump newer than its file and switching to it without downtime
+    #[arg(long, value_name = "DIR")]
+    watch: Option<PathBuf>,
+
+    /// How often to look for new dumps
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 300,
+        requires = "watch"
+    )]
+    watch_interval: u64,
+}
+
+/// What queries are answered from; replaced as a whole when the graph is rebuilt.
+struct Data {
+    graph: Graph,
+    /// Present if the graph was saved with `--text-index`.
+    searcher: Option<text_index::Searcher>,
+}
+
+impl Data {
+    fn load(graph: &Path) -> anyhow::Result<Self> {
+        let index = text_index::path_for(graph);
+        Ok(Self {
+            graph: Graph::load(graph).context("Failed to load graph")?,
+            searcher: index
+                .exists()
+                .then(|| text_index::Searcher::open(&index).context("Failed to open text index"))
+                .transpose()?,
+        })
+    }
+}
+
+/// One of the graphs served, by name.
+struct Served {
+    name: String,
+    /// The graph file, reloaded on request.
+    path: PathBuf,
+    data: RwLock<Arc<Data>>,
+}
+
+impl Served {
+    fn data(&self) -> Arc<Data> {
+        Arc::clone(&self.data.read().unwrap())
+    }
+}
+
+struct State {
+    /// The first answers requests that don't name a graph.
+    graphs: Vec<Served>,
+    /// Held while reloading, so that requests to reload while one is running don't pile up.
+    reloading: Mutex<()>,
+    /// With no keys, every request is allowed.
+    keys: auth::Keys,
+    /// Keys allowed to reload graphs, and to make any other request; with none, nobody is.
+    admin_keys: auth::Keys,
+    rate_limiter: Option<limit::RateLimiter>,
+    concurrency: Option<limit::Concurrency>,
+    timeout: Duration,
+}
+
+impl State {
+    /// The graph named by the `graph` parameter, or the first without one.
+    fn served(&self, params: &Params) -> Option<&Served> {
+        match params.get("graph") {
+            Some(name) => self.graphs.iter().find(|served| served.name == *name),
+            None => self.graphs.first(),
+        }
+    }
+
+    /// Load the file of `served` again and serve it from now on, or `None` if a reload is
+    /// already running. While it loads, and if it fails to, the old graph is served.
+    fn reload(&self, served: &Served) -> Option<anyhow::Result<Arc<Data>>> {
+        let _reloading = self.reloading.try_lock().ok()?;
+        tracing::info!("Reloading {}", served.path.display());
+        Some(Data::load(&served.path).map(|data| {
+            let data = Arc::new(data);
+            *served.data.write().unwrap() = Arc::clone(&data);
+            tracing::info!("Reloaded {}", served.path.display());
+            data
+        }))
+    }
+}
+
+pub fn run(args: &Args) {
+    let mut graphs: Vec<Served> = Vec::new();
+    for (name, path) in &args.graphs {
+        assert!(
+            graphs.iter().all(|served| served.name != *name),
+            "Several graphs are named '{name}'"
+        );
+        let data = Data::load(path)
+            .with_context(|| format!("Failed to load {name}"))
+            .unwrap();
+        if data.searcher.is_none() {
+            tracing::warn!("No text index next to {name}, /search is disabled for it");
+        }
+        graphs.push(Served {
+            name: name.clone(),
+            path: path.clone(),
+            data: RwLock::new(Arc::new(data)),
+        });
+    }
+    let keys = auth::Keys::load(args.api_keys.as_deref(), &args.api_key)
+        .context("Failed to load API keys")
+        .unwrap();
+    if keys.is_empty() {
+        tracing::warn!("No API keys configured, every request is allowed");
+    }
+    let admin_keys = auth::Keys::load(None, &args.admin_key)
+        .context("Failed to load admin keys")
+        .unwrap();
+    let state = Arc::new(State {
+        graphs,
+        reloading: Mutex::new(()),
+        keys,
+        admin_keys,
+        rate_limiter: args.rate_limit.map(limit::RateLimiter::per_minute),
+        concurrency: args.max_concurrent.map(limit::Concurrency::new),
+        timeout: Duration::from_secs(args.timeout),
+    });
+
+    let listen = match args.port {
+        Some(port) => {
+            let host = args
+                .listen
+                .rsplit_once(':')
+                .map_or(&*args.listen, |(host, _)| host);
+            format!("{host}:{port}")
+        }
+        None => args.listen.clone(),
+    };
+    let server = Arc::new(
+        Server::http(&listen)
+            .map_err(|error| anyhow::anyhow!(error))
+            .context("Failed to start server")
+            .unwrap(),
+    );
+    tracing::info!("Listening on http://{listen}");
+
+    #[cfg(unix)]
+    {
+        let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])
+            .context("Failed to listen for SIGHUP")
+            .unwrap();
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            for _ in signals.forever() {
+                for served in &state.graphs {
+                    match state.reload(served) {
+                        Some(Ok(_)) => {}
+                        Some(Err(error)) => {
+                            tracing::error!("Failed to reload {}: {error:#}", served.name);
+                        }
+                        None => tracing::warn!("Ignoring SIGHUP, a reload is already running"),
+                    }
+                }
+            }
+        });
+    }
+
+    if let Some(dir) = &args.watch {
+        let watcher = watch::Watcher {
+            dir: dir.clone(),
+            graph: state.graphs[0].path.clone(),
+            interval: Duration::from_secs(args.watch_interval),
+        };
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            watcher.run(|data| *state.graphs[0].data.write().unwrap() = Arc::new(data));
+        });
+    }
+
+    let workers: Vec<_> = (0..args.threads.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(&state, request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+#[derive(Serialize)]
+struct Error<'a> {
+    error: &'a str,
+}
+
+#[derive(Serialize)]
+struct SearchResponse<'a> {
+    query: &'a str,
+    results: Vec<SearchResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct SearchResult<'a> {
+    title: &'a str,
+    score: f32,
+    node: u32,
+    in_degree: u32,
+    out_degree: u32,
+}
+
+fn handle(state: &Arc<State>, request: Request) {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let params: Params = form_urlencoded::parse(query.as_bytes()).collect();
+
+    let admin = state.admin_keys.check(&request).is_some();
+    let key = if admin {
+        Some("admin")
+    } else if state.keys.is_empty() {
+        Some("anonymous")
+    } else {
+        state.keys.check(&request)
+    };
+    // Without keys, clients are told apart by address.
+    let client = match (key, request.remote_addr()) {
+        (Some("anonymous"), Some(address)) => address.ip().to_string(),
+        (key, _) => String::from(key.unwrap_or("-")),
+    };
+
+    // Held until the response is fully sent, since streamed bodies are produced while sending.
+    let mut permit = None;
+    let response = if key.is_none() {
+        error(401, "Missing or invalid API key")
+            .with_header(Header::from_bytes("WWW-Authenticate", "Bearer").unwrap())
+    } else if let Some(Err(wait)) = state.rate_limiter.as_ref().map(|r| r.check(&client)) {
+        retry_later(429, "Rate limit exceeded", wait)
+    } else {
+        match state
+            .concurrency
+            .as_ref()
+            .map(limit::Concurrency::try_acquire)
+        {
+            Some(None) => retry_later(503, "Too many queries running", Duration::from_secs(1)),
+            acquired => {
+                permit = acquired.flatten();
+                route(state, request.method(), path, &params, admin)
+            }
+        }
+    };
+    tracing::info!(
+        client,
+        method = %request.method(),
+        path,
+        status = response.status_code().0,
+        "Request"
+    );
+
+    if let Err(error) = request.respond(response) {
+        tracing::warn!("Failed to send response: {error}");
+    }
+    drop(permit);
+}
+
+fn route(state: &State, method: &Method, path: &str, params: &Params, admin: bool) -> ResponseBox {
+    if (method, path) == (&Method::Get, "/graphs") {
+        return graphs(state);
+    }
+    let Some(served) = state.served(params) else {
+        return error(404, "No such graph");
+    };
+    let data = &served.data();
+    // `/links/{title}` asks the same as `/links?title={title}`.
+    let (path, title) = match path.get(1..).and_then(|path| path.split_once('/')) {
+        Some((route @ ("links" | "backlinks"), title)) => match percent_decode(title) {
+            Some(title) => (&path[..=route.len()], Some(Cow::Owned(title))),
+            None => return error(400, "Invalid percent-encoding in the title"),
+        },
+        _ => (path, params.get("title").cloned()),
+    };
+    match (method, path) {
+        (Method::Get, "/search") => search(data, params),
+        (Method::Get, "/links") => neighbours(data, title.as_deref(), params, Graph::links),
+        (Method::Get, "/backlinks") => neighbours(data, title.as_deref(), params, Graph::backlinks),
+        (Method::Get, "/path") => shortest_path(data, params, state.timeout),
+        (Method::Get, "/random") => random(data, params),
+        (Method::Post, "/admin/reload") if state.admin_keys.is_empty() => error(
+            403,
+            "Reloading over HTTP is disabled; start the server with --admin-key",
+        ),
+        (Method::Post, "/admin/reload") if !admin => error(403, "Missing or invalid admin key"),
+        (Method::Post, "/admin/reload") => reload(state, served),
+        _ => error(404, "Not found"),
+    }
+}
+
+#[derive(Serialize)]
+struct ReloadResponse {
+    nodes: usize,
+}
+
+#[derive(Serialize)]
+struct GraphsResponse<'a> {
+    graphs: Vec<GraphSummary<'a>>,
+}
+
+#[derive(Serialize)]
+struct GraphSummary<'a> {
+    name: &'a str,
+    nodes: usize,
+    links: usize,
+}
+
+/// The graphs served, the default first.
+fn graphs(state: &State) -> ResponseBox {
+    let graphs = state
+        .graphs
+        .iter()
+        .map(|served| {
+            let data = served.data();
+            GraphSummary {
+                name: &served.name,
+                nodes: data.graph.node_count(),
+                links: data.graph.edge_count(),
+            }
+        })
+        .collect();
+    json(200, &GraphsResponse { graphs })
+}
+
+/// Reload the file of `served`, answering once the new graph is being served.
+fn reload(state: &State, served: &Served) -> ResponseBox {
+    match state.reload(served) {
+        Some(Ok(data)) => json(
+            200,
+            &ReloadResponse {
+                nodes: data.graph.node_count(),
+            },
+        ),
+        Some(Err(reload_error)) => error(500, &format!("{reload_error:#}")),
+        None => error(409, "A reload is already running"),
+    }
+}
+
+fn search(data: &Data, params: &Params) -> ResponseBox {
+    let Some(searcher) = &data.searcher else {
+        return error(404, "This graph has no text index");
+    };
+    let Some(query) = params.get("q") else {
+        return error(400, "Missing query parameter 'q'");
+    };
+    let limit = match param(params, "limit", 10) {
+        Ok(limit) => limit,
+        Err(response) => return response,
+    };
+
+    let hits = match searcher.search(query, None, limit) {
+        Ok(hits) => hits,
+        Err(search_error) => return error(400, &search_error.to_string()),
+    };
+    if hits.iter().any(|hit| !hit.is_in(&data.graph)) {
+        tracing::warn!("The text index of the graph is older than it; leaving out stale hits");
+    }
+    let results = hits
+        .iter()
+        .filter(|hit| hit.is_in(&data.graph))
+        .map(|hit| SearchResult {
+            title: &hit.title,
+            score: hit.score,
+            node: hit.node,
+            in_degree: data.graph.in_degree(hit.node),
+            out_degree: data.graph.out_degree(hit.node),
+        })
+        .collect();
+    json(200, &SearchResponse { query, results })
+}
+
+#[derive(Serialize)]
+struct NeighboursResponse<'a> {
+    title: &'a str,
+    total: usize,
+    results: Vec<Node<'a>>,
+    /// Pass as `cursor` to get the next page; absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    resolved: Vec<Resolution<'a>>,
+}
+
+#[derive(Serialize)]
+#[allow(clippy::struct_field_names)]
+struct Node<'a> {
+    title: &'a str,
+    node: u32,
+    /// The page's Wikidata item, as in `Q42`, if the graph has them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wikidata: Option<String>,
+}
+
+impl<'a> Node<'a> {
+    fn new(graph: &'a Graph, node: u32) -> Self {
+        Self {
+            title: graph.title(node),
+            node,
+            wikidata: graph.wikidata_item(node).map(wikidata::name),
+        }
+    }
+}
+
+/// Pages linked from (or to, depending on `list`) the page `title` leads to, either as one page
+/// of JSON starting at `cursor`, or with `format=ndjson` as a stream of every remaining row.
+fn neighbours(
+    data: &Arc<Data>,
+    title: Option<&str>,
+    params: &Params,
+    list: fn(&Graph, u32) -> &[u32],
+) -> ResponseBox {
+    let Some(title) = title else {
+        return error(400, "Missing query parameter 'title'");
+    };
+    let mut resolved = Vec::new();
+    let id = match find(&data.graph, title, &mut resolved) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let (cursor, limit) = match (
+        param(params, "cursor", 0),
+        param(params, "limit", usize::MAX),
+    ) {
+        (Ok(cursor), Ok(limit)) => (cursor, limit),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let total = list(&data.graph, id).len();
+    let start = cursor.min(total);
+
+    match params.get("format").map(AsRef::as_ref) {
+        None | Some("json") => {
+            let end = start.saturating_add(limit.min(PAGE_SIZE)).min(total);
+            let results = list(&data.graph, id)[start..end]
+                .iter()
+                .map(|&node| Node::new(&data.graph, node))
+                .collect();
+            json(
+                200,
+                &NeighboursResponse {
+                    title: data.graph.title(id),
+                    total,
+                    results,
+                    next_cursor: (end < total).then_some(end),
+                    resolved,
+                },
+            )
+        }
+        Some("ndjson") => {
+            let end = start.saturating_add(limit).min(total);
+            // Streams keep the graph they started on, even if it is replaced meanwhile.
+            let data = Arc::clone(data);
+            let rows = (start..end).map(move |i| {
+                let node = list(&data.graph, id)[i];
+                serde_json::to_value(Node::new(&data.graph, node)).unwrap()
+            });
+            Response::new(
+                200.into(),
+                vec![Header::from_bytes("Content-Type", "application/x-ndjson").unwrap()],
+                Box::new(stream::Ndjson::new(rows)) as Box<dyn std::io::Read + Send>,
+                // Unknown length, so the body is sent chunked.
+                None,
+                None,
+            )
+        }
+        Some(_) => error(400, "Invalid 'format', expected 'json' or 'ndjson'"),
+    }
+}
+
+#[derive(Serialize)]
+struct PathResponse<'a> {
+    /// `null` if there is no path.
+    path: Option<Vec<&'a str>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    resolved: Vec<Resolution<'a>>,
+}
+
+/// A shortest chain of links from `from` to `to`, following redirects, found within `timeout`.
+fn shortest_path(data: &Data, params: &Params, timeout: Duration) -> ResponseBox {
+    let mut resolved = Vec::new();
+    let ids = ["from", "to"].map(|name| {
+        let title = params
+            .get(name)
+            .ok_or_else(|| error(400, &format!("Missing query parameter '{name}'")))?;
+        find(&data.graph, title, &mut resolved)
+    });
+    let [from, to] = match ids {
+        [Ok(from), Ok(to)] => [from, to],
+        [Err(response), _] | [_, Err(response)] => return response,
+    };
+
+    let found = data.graph.shortest_path(
+        from,
+        to,
+        Direction::Out,
+        |_| true,
+        &Cancel::after(Some(timeout)),
+    );
+    match found {
+        Ok(path) => json(
+            200,
+            &PathResponse {
+                path: path.map(|path| path.iter().map(|&node| data.graph.title(node)).collect()),
+                resolved,
+            },
+        ),
+        Err(cancelled @ Cancelled::TimedOut(_)) => error(504, &cancelled.to_string()),
+        Err(Cancelled::Interrupted) => error(503, "Interrupted"),
+    }
+}
+
+#[derive(Serialize)]
+struct RandomResponse<'a> {
+    results: Vec<Node<'a>>,
+}
+
+/// `count` different pages drawn at random, without redirects, from a `seed` if given so that
+/// games can be replayed.
+fn random(data: &Data, params: &Params) -> ResponseBox {
+    let (count, seed) = match (param(params, "count", 1), param(params, "seed", 0)) {
+        (Ok(count), Ok(seed)) => (count, seed),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let seed = if params.contains_key("seed") {
+        seed
+    } else {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+            })
+    };
+
+    let mut rng = Rng::seeded(seed, &[]);
+    let nodes = data.graph.node_count();
+    let mut results: Vec<Node> = Vec::new();
+    // A graph of only redirects has no pages to give, so stop drawing at some point.
+    for _ in 0..count.min(PAGE_SIZE).saturating_mul(100) {
+        if results.len() == count.min(PAGE_SIZE) || nodes == 0 {
+            break;
+        }
+        let node = u32::try_from(rng.below(nodes)).unwrap();
+        if data.graph.redirect(node).is_none() && results.iter().all(|page| page.node != node) {
+            results.push(Node::new(&data.graph, node));
+        }
+    }
+    json(200, &RandomResponse { results })
+}
+
+/// The page `title` leads to, found as the command line finds it (see `path::lookup`), noting a
+/// redirect followed in `resolved`, or else a 404 saying why.
+fn find<'a>(
+    graph: &'a Graph,
+    title: &str,
+    resolved: &mut Vec<Resolution<'a>>,
+) -> Result<u32, ResponseBox> {
+    let id = path::lookup(graph, title).map_err(|error| self::error(404, &error.to_string()))?;
+    let target = graph.resolve_redirect(id);
+    if target != id {
+        resolved.push(Resolution {
+            asked: String::from(title),
+            resolved: graph.title(target),
+        });
+    }
+    Ok(target)
+}
+
+/// `text` with its `%XX` escapes decoded, or `None` if they aren't valid UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        if let Some(escaped) = escaped {
+            bytes.push(escaped);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The query parameter `name` parsed, or `default` if it is absent.
+fn param<T: FromStr>(params: &Params, name: &str, default: T) -> Result<T, ResponseBox> {
+    params.get(name).map_or(Ok(default), |value| {
+        value
+            .parse()
+            .map_err(|_| error(400, &format!("Invalid '{name}'")))
+    })
+}
+
+fn json(status: u16, body: &impl Serialize) -> ResponseBox {
+    Response::from_string(serde_json::to_string(body).unwrap())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+        .boxed()
+}
+
+fn error(status: u16, message: &str) -> ResponseBox {
+    json(status, &Error { error: message })
+}
+
+fn retry_later(status: u16, message: &str, wait: Duration) -> ResponseBox {
+    let seconds = wait
+        .as_secs()
+        .saturating_add(u64::from(wait.subsec_nanos() > 0));
+    error(status, message)
+        .with_header(Header::from_bytes("Retry-After", seconds.max(1).to_string()).unwrap())
+}
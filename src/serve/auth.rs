@@ -0,0 +1,72 @@
+//! API-key authentication. Clients send a key as `Authorization: Bearer <key>` or `X-API-Key:
+//! <key>`; each key has a name, which is what gets logged instead of the key itself.
+
+use anyhow::Context as _;
+use std::{fs, path::Path};
+use tiny_http::Request;
+
+pub struct Keys {
+    keys: Vec<(String, String)>,
+}
+
+impl Keys {
+    /// Keys from `file`, one per line as `NAME KEY` or just `KEY`, plus the unnamed `extra` keys
+    /// from the command line or environment. Blank lines and lines starting with `#` are ignored.
+    pub fn load(file: Option<&Path>, extra: &[String]) -> anyhow::Result<Self> {
+        let mut keys = Vec::new();
+        if let Some(file) = file {
+            let contents = fs::read_to_string(file)
+                .with_context(|| format!("Failed to read API key file '{}'", file.display()))?;
+            for line in contents.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                match line.split_once(char::is_whitespace) {
+                    Some((name, key)) => keys.push((String::from(name), String::from(key.trim()))),
+                    None => keys.push((format!("key-{}", keys.len() + 1), String::from(line))),
+                }
+            }
+        }
+        for key in extra {
+            keys.push((format!("key-{}", keys.len() + 1), key.clone()));
+        }
+        anyhow::ensure!(
+            keys.iter().all(|(_, key)| !key.is_empty()),
+            "API keys must not be empty"
+        );
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The name of the key `request` was made with, if it is one of ours.
+    pub fn check(&self, request: &Request) -> Option<&str> {
+        let presented = request.headers().iter().find_map(|header| {
+            if header.field.equiv("X-API-Key") {
+                Some(header.value.as_str().trim())
+            } else if header.field.equiv("Authorization") {
+                header.value.as_str().trim().strip_prefix("Bearer ")
+            } else {
+                None
+            }
+        })?;
+        // Compare every key in full, so response times don't reveal how much of a key matched.
+        self.keys.iter().fold(None, |found, (name, key)| {
+            if constant_time_eq(key.as_bytes(), presented.trim().as_bytes()) {
+                Some(name.as_str())
+            } else {
+                found
+            }
+        })
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .fold(0, |difference, (x, y)| difference | (x ^ y))
+            == 0
+}
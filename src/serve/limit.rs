@@ -0,0 +1,98 @@
+//! Load shedding: a token bucket per client, and a cap on queries running at once. Both reject
+//! requests with a hint of when to retry, rather than queueing them.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Clients whose buckets are full are forgotten once this many are tracked.
+const MAX_CLIENTS: usize = 10_000;
+
+pub struct RateLimiter {
+    /// Tokens per second.
+    rate: f64,
+    /// Bucket size, i.e. how many requests a client that was idle can make at once.
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            rate: f64::from(requests) / 60.0,
+            burst: f64::from(requests),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token from `client`'s bucket, or say how long until one is available, which is
+    /// never for a rate of 0.
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_CLIENTS {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+        }
+        let bucket = buckets.entry(String::from(client)).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if self.rate > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
+}
+
+pub struct Concurrency {
+    max: usize,
+    running: AtomicUsize,
+}
+
+/// A slot for one running query, released when dropped.
+pub struct Permit<'a>(&'a AtomicUsize);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl Concurrency {
+    pub const fn new(max: u32) -> Self {
+        Self {
+            max: max as usize,
+            running: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn try_acquire(&self) -> Option<Permit<'_>> {
+        self.running
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |running| {
+                (running < self.max).then_some(running + 1)
+            })
+            .ok()
+            .map(|_| Permit(&self.running))
+    }
+}
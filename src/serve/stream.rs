@@ -0,0 +1,43 @@
+//! Chunked NDJSON bodies, produced row by row as the client reads them instead of buffered.
+
+use serde::Serialize;
+use std::io::{self, Read};
+
+pub struct Ndjson<I> {
+    rows: I,
+    buffer: Vec<u8>,
+    /// How much of `buffer` has been read already.
+    position: usize,
+}
+
+impl<I> Ndjson<I> {
+    pub const fn new(rows: I) -> Self {
+        Self {
+            rows,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<I, T> Read for Ndjson<I>
+where
+    I: Iterator<Item = T>,
+    T: Serialize,
+{
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            let Some(row) = self.rows.next() else {
+                return Ok(0);
+            };
+            self.buffer.clear();
+            self.position = 0;
+            serde_json::to_writer(&mut self.buffer, &row)?;
+            self.buffer.push(b'\n');
+        }
+        let n = out.len().min(self.buffer.len() - self.position);
+        out[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,143 @@
+//! Rebuilding the served graph when a new dump appears. Each rebuild runs `parse` in a child
+//! process, so a dump that fails to parse leaves the server running on the old graph.
+
+use super::Data;
+use crate::text_index;
+use anyhow::Context as _;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+pub struct Watcher {
+    pub dir: PathBuf,
+    /// The served graph file, replaced on every rebuild.
+    pub graph: PathBuf,
+    pub interval: Duration,
+}
+
+/// A dump as seen on one poll.
+#[derive(Clone, PartialEq, Eq)]
+struct Dump {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl Watcher {
+    /// Poll forever, passing every rebuilt graph to `swap`.
+    pub fn run(&self, swap: impl Fn(Data)) {
+        tracing::info!("Watching {} for new dumps", self.dir.display());
+        let mut previous: Option<Dump> = None;
+        let mut failed: Option<Dump> = None;
+        loop {
+            match newest_dump(&self.dir) {
+                Err(error) => tracing::warn!("Failed to look for new dumps: {error:#}"),
+                Ok(Some(dump)) if self.is_newer(&dump) && failed.as_ref() != Some(&dump) => {
+                    // Only build once the dump is the same on two polls in a row, so a dump
+                    // still being downloaded isn't parsed half-written.
+                    if previous.as_ref() == Some(&dump) {
+                        match self.rebuild(&dump.path) {
+                            Ok(data) => {
+                                swap(data);
+                                tracing::info!("Now serving the graph of {}", dump.path.display());
+                            }
+                            Err(error) => {
+                                tracing::error!(
+                                    "Failed to rebuild from {}: {error:#}",
+                                    dump.path.display()
+                                );
+                                failed = Some(dump.clone());
+                            }
+                        }
+                    }
+                    previous = Some(dump);
+                }
+                Ok(_) => previous = None,
+            }
+            thread::sleep(self.interval);
+        }
+    }
+
+    /// Whether `dump` changed after the served graph was saved.
+    fn is_newer(&self, dump: &Dump) -> bool {
+        fs::metadata(&self.graph)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|saved| dump.modified > saved)
+    }
+
+    /// Parse `dump` into a new graph next to the served one, move it into place, and load it.
+    fn rebuild(&self, dump: &Path) -> anyhow::Result<Data> {
+        tracing::info!("Rebuilding the graph from {}", dump.display());
+        let mut next = self.graph.clone().into_os_string();
+        next.push(".next");
+        let next = PathBuf::from(next);
+        let index = text_index::path_for(&self.graph);
+        let next_index = text_index::path_for(&next);
+
+        let mut parse = Command::new(env::current_exe()?);
+        parse.arg("parse").arg(dump).arg("--graph").arg(&next);
+        if index.exists() {
+            parse.arg("--text-index");
+            if next_index.exists() {
+                fs::remove_dir_all(&next_index)?;
+            }
+        }
+        let status = parse.status().context("Failed to run parse")?;
+        anyhow::ensure!(status.success(), "parse exited with {status}");
+
+        // The old graph and index stay open in memory until the swap, so they can be replaced on
+        // disk first.
+        fs::rename(&next, &self.graph)?;
+        if next_index.exists() {
+            if index.exists() {
+                fs::remove_dir_all(&index)?;
+            }
+            fs::rename(&next_index, &index)?;
+        }
+        Data::load(&self.graph)
+    }
+}
+
+/// The most recently modified dump in `dir`.
+fn newest_dump(dir: &Path) -> anyhow::Result<Option<Dump>> {
+    let mut newest: Option<Dump> = None;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !is_dump(&entry.path()) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let dump = Dump {
+            path: entry.path(),
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        };
+        if newest
+            .as_ref()
+            .is_none_or(|newest| dump.modified > newest.modified)
+        {
+            newest = Some(dump);
+        }
+    }
+    Ok(newest)
+}
+
+/// Whether `path` names an `.xml` or `.xml.bz2` file.
+fn is_dump(path: &Path) -> bool {
+    let has_extension = |path: &Path, extension: &str| {
+        path.extension()
+            .is_some_and(|found| found.eq_ignore_ascii_case(extension))
+    };
+    has_extension(path, "xml")
+        || (has_extension(path, "bz2")
+            && path
+                .file_stem()
+                .is_some_and(|stem| has_extension(Path::new(stem), "xml")))
+}
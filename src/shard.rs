@@ -0,0 +1,102 @@
+//! Splitting a multistream dump between independent workers. Shard K of N is every bzip2 stream
+//! that starts in the K-th of N equal byte ranges of the file, so each worker finds its streams
+//! from the file alone and the shards cover every stream exactly once, with no coordinator or
+//! index file.
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{Read as _, Seek as _, SeekFrom},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+};
+
+/// A stream header (`BZh` and a block size digit) is followed by this block magic, the BCD
+/// digits of pi.
+const BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+const HEADER_LEN: usize = 4 + BLOCK_MAGIC.len();
+
+/// Shard `index` of `count`, counting from 1.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    index: u64,
+    count: u64,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s.split_once('/').ok_or("expected K/N, e.g. 1/4")?;
+        let index: u64 = index.trim().parse().map_err(|e| format!("{e}"))?;
+        let count: u64 = count.trim().parse().map_err(|e| format!("{e}"))?;
+        if index == 0 || index > count {
+            return Err(String::from("K must be between 1 and N"));
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// The bytes of `path` holding this shard's streams.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read.
+    pub fn range(self, path: &Path) -> anyhow::Result<Range<u64>> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let boundary = |i: u64| {
+            let offset = u128::from(len) * u128::from(i) / u128::from(self.count);
+            // At most `len`, since `i <= count`.
+            u64::try_from(offset)
+        };
+        let start = if self.index == 1 {
+            0
+        } else {
+            next_stream(&mut file, boundary(self.index - 1)?, len)?
+        };
+        let end = if self.index == self.count {
+            len
+        } else {
+            next_stream(&mut file, boundary(self.index)?, len)?
+        };
+        Ok(start..end.max(start))
+    }
+}
+
+/// Offset of the first bzip2 stream starting at or after `from`, or `len` if there is none.
+fn next_stream(file: &mut File, from: u64, len: u64) -> anyhow::Result<u64> {
+    file.seek(SeekFrom::Start(from))
+        .context("Failed to seek in dump")?;
+    let mut offset = from;
+    let mut window = Vec::new();
+    let mut chunk = vec![0; 1 << 20];
+    loop {
+        let n = file.read(&mut chunk)?;
+        if n == 0 {
+            return Ok(len);
+        }
+        window.extend_from_slice(&chunk[..n]);
+        if let Some(i) = window.windows(HEADER_LEN).position(is_stream_header) {
+            return Ok(offset + i as u64);
+        }
+        // Keep a possible header split across chunks.
+        let skip = window.len().saturating_sub(HEADER_LEN - 1);
+        window.drain(..skip);
+        offset += skip as u64;
+    }
+}
+
+fn is_stream_header(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"BZh") && (b'1'..=b'9').contains(&bytes[3]) && bytes[4..] == BLOCK_MAGIC
+}
@@ -0,0 +1,68 @@
+//! Outputs that write the graph into databases and other external systems rather than graph
+//! files. Each one is behind a cargo feature of the same name, so that default builds don't
+//! pull in the client libraries.
+
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(any(feature = "clickhouse", feature = "postgres"))]
+use crate::Wiki;
+#[cfg(any(feature = "clickhouse", feature = "postgres"))]
+use lasso::{Key as _, Rodeo};
+#[cfg(any(feature = "clickhouse", feature = "postgres"))]
+use std::fmt::Write as _;
+
+/// Tab-separated `id, title, ns, is_redirect` rows, each ending in a newline, with a missing
+/// namespace as `\N` (NULL) and the redirect flag as 0 or 1.
+#[cfg(any(feature = "clickhouse", feature = "postgres"))]
+fn page_rows<'a>(rodeo: &'a Rodeo, wiki: &'a Wiki) -> impl Iterator<Item = String> + 'a {
+    rodeo.iter().map(|(key, title)| {
+        let mut row = String::new();
+        write!(row, "{}\t", key.into_usize()).unwrap();
+        escape_tsv(&mut row, title);
+        match wiki.namespaces.get(&key) {
+            Some(ns) => write!(row, "\t{ns}").unwrap(),
+            None => row.push_str("\t\\N"),
+        }
+        let is_redirect = u8::from(wiki.redirects.contains_key(&key));
+        writeln!(row, "\t{is_redirect}").unwrap();
+        row
+    })
+}
+
+/// Tab-separated `source, target` rows, each ending in a newline. Typed edges get an
+/// `edge_type` field too, with one row per layer and an empty type for edges without one.
+#[cfg(any(feature = "clickhouse", feature = "postgres"))]
+fn link_rows(wiki: &Wiki) -> impl Iterator<Item = String> + '_ {
+    let typed = wiki.is_typed();
+    wiki.typed_edges().map(move |(source, target, edge_type)| {
+        let mut row = format!("{}\t{}", source.into_usize(), target.into_usize());
+        if typed {
+            row.push('\t');
+            row.push_str(edge_type.map_or("", crate::edge_type::EdgeType::name));
+        }
+        row.push('\n');
+        row
+    })
+}
+
+/// Append `value` escaped for tab-separated formats (PostgreSQL's COPY text format and
+/// ClickHouse's `TabSeparated`), which share their backslash escapes.
+#[cfg(any(feature = "clickhouse", feature = "postgres"))]
+fn escape_tsv(row: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' => row.push_str("\\\\"),
+            '\t' => row.push_str("\\t"),
+            '\n' => row.push_str("\\n"),
+            '\r' => row.push_str("\\r"),
+            c => row.push(c),
+        }
+    }
+}
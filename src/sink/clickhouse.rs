@@ -0,0 +1,189 @@
+use crate::{graph::Metadata, Wiki};
+use lasso::Rodeo;
+
+#[derive(clap::Args)]
+#[group(id = "clickhouse_sink")]
+pub struct Args {
+    /// Write pages and links to the ClickHouse server with this HTTP interface URL, e.g.
+    /// `http://localhost:8123`
+    #[arg(long, value_name = "URL")]
+    clickhouse: Option<String>,
+
+    /// Database to create the `pages` and `links` tables in
+    #[arg(long, value_name = "NAME", default_value = "default")]
+    clickhouse_database: String,
+
+    /// ClickHouse user
+    #[arg(long, value_name = "NAME", env = "WIKIGRAPH_CLICKHOUSE_USER")]
+    clickhouse_user: Option<String>,
+
+    /// ClickHouse password
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "WIKIGRAPH_CLICKHOUSE_PASSWORD",
+        hide_env_values = true
+    )]
+    clickhouse_password: Option<String>,
+
+    /// Number of rows sent per INSERT
+    #[arg(long, value_name = "N", default_value_t = 1_000_000)]
+    clickhouse_batch_size: usize,
+}
+
+const PAGES: &str = "pages (id UInt64, title String, ns Nullable(Int32), is_redirect UInt8) \
+                     ENGINE = MergeTree ORDER BY id";
+const LINKS: &str =
+    "links (source UInt64, target UInt64) ENGINE = MergeTree ORDER BY (source, target)";
+/// One row per load, saying where the rows came from.
+const PROVENANCE: &str = "provenance (created Nullable(UInt64), dump Nullable(String), \
+                          dump_date Nullable(Date), wikigraph Nullable(String), \
+                          options Nullable(String), command Array(String)) \
+                          ENGINE = MergeTree ORDER BY tuple()";
+/// `LINKS` for graphs built with `--edge-types`.
+const TYPED_LINKS: &str = "links (source UInt64, target UInt64, edge_type LowCardinality(String)) \
+                           ENGINE = MergeTree ORDER BY (edge_type, source, target)";
+
+impl Args {
+    /// Insert the graph into `pages(id, title, ns, is_redirect)` and `links(source, target)`, plus
+    /// `edge_type` for typed edges, over the HTTP interface, as `TabSeparated` batches. IDs are
+    /// this run's node IDs. Each load also adds its `provenance` to the `provenance` table.
+    pub fn write(&self, rodeo: &Rodeo, wiki: &Wiki, provenance: &Metadata) -> anyhow::Result<()> {
+        let Some(url) = &self.clickhouse else {
+            return Ok(());
+        };
+        // Keep error responses, whose bodies say what went wrong.
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        let database = quote_identifier(&self.clickhouse_database);
+
+        self.execute(
+            &agent,
+            url,
+            &format!("CREATE DATABASE IF NOT EXISTS {database}"),
+            "",
+        )?;
+        let (links, link_columns) = if wiki.is_typed() {
+            (TYPED_LINKS, "source, target, edge_type")
+        } else {
+            (LINKS, "source, target")
+        };
+        for table in [PAGES, links, PROVENANCE] {
+            self.execute(
+                &agent,
+                url,
+                &format!("CREATE TABLE IF NOT EXISTS {database}.{table}"),
+                "",
+            )?;
+        }
+
+        self.insert(
+            &agent,
+            url,
+            &format!("{database}.pages (id, title, ns, is_redirect)"),
+            super::page_rows(rodeo, wiki),
+        )?;
+        self.insert(
+            &agent,
+            url,
+            &format!("{database}.links ({link_columns})"),
+            super::link_rows(wiki),
+        )?;
+        self.insert(
+            &agent,
+            url,
+            &format!(
+                "{database}.provenance (created, dump, dump_date, wikigraph, options, command)"
+            ),
+            std::iter::once(provenance_row(provenance)),
+        )?;
+
+        Ok(())
+    }
+
+    fn insert(
+        &self,
+        agent: &ureq::Agent,
+        url: &str,
+        table: &str,
+        rows: impl Iterator<Item = String>,
+    ) -> anyhow::Result<()> {
+        let query = format!("INSERT INTO {table} FORMAT TabSeparated");
+        let mut rows = rows.peekable();
+        let mut total = 0;
+        while rows.peek().is_some() {
+            let mut batch = String::new();
+            for row in rows.by_ref().take(self.clickhouse_batch_size) {
+                batch.push_str(&row);
+                total += 1;
+            }
+            self.execute(agent, url, &query, &batch)?;
+            tracing::info!("Inserted {total} rows into {table}");
+        }
+        Ok(())
+    }
+
+    /// Run `query`, with `body` as its input data.
+    fn execute(
+        &self,
+        agent: &ureq::Agent,
+        url: &str,
+        query: &str,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        let mut request = agent.post(url).query("query", query);
+        if let Some(user) = &self.clickhouse_user {
+            request = request.header("X-ClickHouse-User", user);
+        }
+        if let Some(password) = &self.clickhouse_password {
+            request = request.header("X-ClickHouse-Key", password);
+        }
+        let mut response = request.send(body)?;
+        if !response.status().is_success() {
+            let message = response.body_mut().read_to_string()?;
+            anyhow::bail!(
+                "ClickHouse returned {}: {}",
+                response.status(),
+                message.trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// `provenance` as a `TabSeparated` row, with missing fields as `\N` (NULL).
+fn provenance_row(provenance: &Metadata) -> String {
+    let mut row = String::new();
+    let mut field = |value: Option<&str>| {
+        match value {
+            Some(value) => super::escape_tsv(&mut row, value),
+            None => row.push_str("\\N"),
+        }
+        row.push('\t');
+    };
+    field(
+        provenance
+            .created
+            .map(|created| created.to_string())
+            .as_deref(),
+    );
+    field(provenance.dump.as_deref());
+    field(provenance.dump_date.as_deref());
+    field(provenance.wikigraph.as_deref());
+    field(provenance.options.as_deref());
+    // Arrays are written like `['a','b']`, with backslash escapes inside the quotes.
+    let command: Vec<String> = provenance
+        .command
+        .iter()
+        .map(|arg| format!("'{}'", arg.replace('\\', "\\\\").replace('\'', "\\'")))
+        .collect();
+    super::escape_tsv(&mut row, &format!("[{}]", command.join(",")));
+    row.push('\n');
+    row
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("`{}`", identifier.replace('\\', "\\\\").replace('`', "\\`"))
+}
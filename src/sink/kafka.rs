@@ -0,0 +1,247 @@
+//! Publishing parsed pages, or their links, to a Kafka topic while the dump is parsed. Messages
+//! are keyed by page title, so all messages about a page land in the same partition.
+//!
+//! JSON messages are `{"title", "ns", "redirect", "links"}` per page, or `{"source", "target"}`
+//! per link. Avro messages use Avro's single-object encoding (a marker, the schema's 64-bit
+//! fingerprint, then the binary record), so consumers can tell the schema without a registry;
+//! the schema is logged at startup.
+
+use kafka::producer::{Producer as KafkaProducer, Record, RequiredAcks};
+use serde::Serialize;
+use std::{sync::LazyLock, time::Duration};
+
+#[derive(clap::Args)]
+#[group(id = "kafka_sink")]
+pub struct Args {
+    /// Publish parsed pages to Kafka through these bootstrap brokers, e.g. `localhost:9092`
+    #[arg(long, value_name = "HOSTS", value_delimiter = ',')]
+    kafka: Vec<String>,
+
+    /// Topic to publish to
+    #[arg(long, value_name = "TOPIC", default_value = "wikigraph")]
+    kafka_topic: String,
+
+    /// Whether to publish one message per page or per link
+    #[arg(long, value_enum, default_value_t = Messages::Pages)]
+    kafka_messages: Messages,
+
+    /// Message serialization
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    kafka_format: Format,
+
+    /// Number of messages sent per produce request
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    kafka_batch_size: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Messages {
+    Pages,
+    Links,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Avro,
+}
+
+const PAGE_SCHEMA: &str = r#"{"type":"record","name":"Page","namespace":"wikigraph","fields":[{"name":"title","type":"string"},{"name":"ns","type":"long"},{"name":"redirect","type":["null","string"]},{"name":"links","type":{"type":"array","items":"string"}}]}"#;
+const LINK_SCHEMA: &str = r#"{"type":"record","name":"Link","namespace":"wikigraph","fields":[{"name":"source","type":"string"},{"name":"target","type":"string"}]}"#;
+
+/// The schemas in Avro's parsing canonical form, which is what fingerprints are computed over.
+const PAGE_CANONICAL: &str = r#"{"name":"wikigraph.Page","type":"record","fields":[{"name":"title","type":"string"},{"name":"ns","type":"long"},{"name":"redirect","type":["null","string"]},{"name":"links","type":{"type":"array","items":"string"}}]}"#;
+const LINK_CANONICAL: &str = r#"{"name":"wikigraph.Link","type":"record","fields":[{"name":"source","type":"string"},{"name":"target","type":"string"}]}"#;
+
+static PAGE_FINGERPRINT: LazyLock<u64> = LazyLock::new(|| fingerprint(PAGE_CANONICAL));
+static LINK_FINGERPRINT: LazyLock<u64> = LazyLock::new(|| fingerprint(LINK_CANONICAL));
+
+#[derive(Serialize)]
+struct PageMessage<'a> {
+    title: &'a str,
+    ns: i64,
+    redirect: Option<&'a str>,
+    links: &'a [&'a str],
+}
+
+#[derive(Serialize)]
+struct LinkMessage<'a> {
+    source: &'a str,
+    target: &'a str,
+}
+
+pub struct Producer {
+    inner: KafkaProducer,
+    topic: String,
+    messages: Messages,
+    format: Format,
+    batch_size: usize,
+    /// Messages not sent yet, as (key, value).
+    pending: Vec<(String, Vec<u8>)>,
+    sent: usize,
+}
+
+impl Producer {
+    /// Connect to the brokers, unless none were given.
+    pub fn connect(args: &Args) -> anyhow::Result<Option<Self>> {
+        if args.kafka.is_empty() {
+            return Ok(None);
+        }
+        let inner = KafkaProducer::from_hosts(args.kafka.clone())
+            .with_ack_timeout(Duration::from_secs(10))
+            .with_required_acks(RequiredAcks::One)
+            .with_client_id(String::from("wikigraph"))
+            .create()?;
+        if args.kafka_format == Format::Avro {
+            let schema = match args.kafka_messages {
+                Messages::Pages => PAGE_SCHEMA,
+                Messages::Links => LINK_SCHEMA,
+            };
+            tracing::info!("Publishing Avro messages with schema {schema}");
+        }
+        Ok(Some(Self {
+            inner,
+            topic: args.kafka_topic.clone(),
+            messages: args.kafka_messages,
+            format: args.kafka_format,
+            batch_size: args.kafka_batch_size.max(1),
+            pending: Vec::new(),
+            sent: 0,
+        }))
+    }
+
+    pub fn add_page(
+        &mut self,
+        title: &str,
+        ns: i64,
+        redirect: Option<&str>,
+        links: &[&str],
+    ) -> anyhow::Result<()> {
+        match self.messages {
+            Messages::Pages => {
+                let message = PageMessage {
+                    title,
+                    ns,
+                    redirect,
+                    links,
+                };
+                let value = match self.format {
+                    Format::Json => serde_json::to_vec(&message)?,
+                    Format::Avro => avro_page(&message),
+                };
+                self.pending.push((String::from(title), value));
+            }
+            Messages::Links => {
+                for &target in links {
+                    let message = LinkMessage {
+                        source: title,
+                        target,
+                    };
+                    let value = match self.format {
+                        Format::Json => serde_json::to_vec(&message)?,
+                        Format::Avro => avro_link(&message),
+                    };
+                    self.pending.push((String::from(title), value));
+                }
+            }
+        }
+        if self.pending.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        tracing::info!("Published {} messages to {}", self.sent, self.topic);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let records: Vec<_> = self
+            .pending
+            .iter()
+            .map(|(key, value)| {
+                Record::from_key_value(&self.topic, key.as_bytes(), value.as_slice())
+            })
+            .collect();
+        for confirm in self.inner.send_all(&records)? {
+            for partition in confirm.partition_confirms {
+                if let Err(code) = partition.offset {
+                    anyhow::bail!(
+                        "Kafka rejected messages for partition {} of {}: {code:?}",
+                        partition.partition,
+                        confirm.topic
+                    );
+                }
+            }
+        }
+        self.sent += self.pending.len();
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+fn avro_page(page: &PageMessage) -> Vec<u8> {
+    let mut out = single_object_header(*PAGE_FINGERPRINT);
+    avro_string(&mut out, page.title);
+    avro_long(&mut out, page.ns);
+    match page.redirect {
+        None => avro_long(&mut out, 0),
+        Some(redirect) => {
+            avro_long(&mut out, 1);
+            avro_string(&mut out, redirect);
+        }
+    }
+    // Arrays are blocks of items, ending with an empty block.
+    if !page.links.is_empty() {
+        avro_long(&mut out, i64::try_from(page.links.len()).unwrap());
+        for link in page.links {
+            avro_string(&mut out, link);
+        }
+    }
+    avro_long(&mut out, 0);
+    out
+}
+
+fn avro_link(link: &LinkMessage) -> Vec<u8> {
+    let mut out = single_object_header(*LINK_FINGERPRINT);
+    avro_string(&mut out, link.source);
+    avro_string(&mut out, link.target);
+    out
+}
+
+fn single_object_header(fingerprint: u64) -> Vec<u8> {
+    let mut out = vec![0xc3, 0x01];
+    out.extend(fingerprint.to_le_bytes());
+    out
+}
+
+/// Zigzag varint.
+fn avro_long(out: &mut Vec<u8>, n: i64) {
+    let mut n = n.cast_unsigned() << 1 ^ (n >> 63).cast_unsigned();
+    while n >= 0x80 {
+        out.push(u8::try_from(n & 0x7f).unwrap() | 0x80);
+        n >>= 7;
+    }
+    out.push(u8::try_from(n).unwrap());
+}
+
+fn avro_string(out: &mut Vec<u8>, s: &str) {
+    avro_long(out, i64::try_from(s.len()).unwrap());
+    out.extend(s.as_bytes());
+}
+
+/// Avro's CRC-64-AVRO (Rabin) fingerprint.
+fn fingerprint(schema: &str) -> u64 {
+    const EMPTY: u64 = 0xc15d_213a_a4d7_a795;
+    let table: Vec<u64> = (0..256u64)
+        .map(|i| (0..8).fold(i, |fp, _| (fp >> 1) ^ (EMPTY & (fp & 1).wrapping_neg())))
+        .collect();
+    schema.bytes().fold(EMPTY, |fp, byte| {
+        (fp >> 8) ^ table[usize::from(u8::try_from((fp ^ u64::from(byte)) & 0xff).unwrap())]
+    })
+}
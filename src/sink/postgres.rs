@@ -0,0 +1,198 @@
+use crate::{graph::Metadata, Wiki};
+use lasso::Rodeo;
+use std::io::Write as _;
+
+#[derive(clap::Args)]
+#[group(id = "postgres_sink")]
+pub struct Args {
+    /// Write pages and links to the PostgreSQL database at this connection string, e.g.
+    /// `host=localhost user=wikigraph dbname=wiki`
+    #[arg(
+        long,
+        value_name = "DSN",
+        env = "WIKIGRAPH_POSTGRES",
+        hide_env_values = true
+    )]
+    postgres: Option<String>,
+
+    /// Schema to create the `pages` and `links` tables in
+    #[arg(long, value_name = "NAME", default_value = "public")]
+    postgres_schema: String,
+
+    /// What to do with rows whose key is already in the tables
+    #[arg(long, value_enum, default_value_t = OnConflict::Error)]
+    postgres_on_conflict: OnConflict,
+
+    /// Number of rows sent per COPY
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    postgres_batch_size: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnConflict {
+    /// Fail; batches copied before the conflicting one stay
+    Error,
+    /// Keep the existing rows
+    Skip,
+    /// Overwrite the existing rows
+    Replace,
+}
+
+struct Table {
+    name: &'static str,
+    columns: &'static str,
+    definition: &'static str,
+    key: &'static str,
+    /// `SET` clause for replacing a conflicting row.
+    update: &'static str,
+}
+
+const PAGES: Table = Table {
+    name: "pages",
+    columns: "id, title, ns, is_redirect",
+    definition: "id bigint PRIMARY KEY, title text NOT NULL, ns integer, \
+                 is_redirect boolean NOT NULL",
+    key: "id",
+    update: "title = EXCLUDED.title, ns = EXCLUDED.ns, is_redirect = EXCLUDED.is_redirect",
+};
+
+const LINKS: Table = Table {
+    name: "links",
+    columns: "source, target",
+    definition: "source bigint NOT NULL, target bigint NOT NULL, PRIMARY KEY (source, target)",
+    key: "source, target",
+    update: "",
+};
+
+/// One row per load, saying where the rows came from. Rows are inserted rather than copied, so
+/// there is no key to resolve conflicts on.
+const PROVENANCE: Table = Table {
+    name: "provenance",
+    columns: "created, dump, dump_date, wikigraph, options, command",
+    definition: "created bigint, dump text, dump_date date, wikigraph text, options text, \
+                 command text[] NOT NULL",
+    key: "",
+    update: "",
+};
+
+/// `LINKS` for graphs built with `--edge-types`.
+const TYPED_LINKS: Table = Table {
+    name: "links",
+    columns: "source, target, edge_type",
+    definition: "source bigint NOT NULL, target bigint NOT NULL, edge_type text NOT NULL, \
+                 PRIMARY KEY (source, target, edge_type)",
+    key: "source, target, edge_type",
+    update: "",
+};
+
+impl Args {
+    /// Copy the graph into `pages(id, title, ns, is_redirect)` and `links(source, target)`, plus
+    /// `edge_type` for typed edges, in batches. IDs are this run's node IDs. Without conflict
+    /// handling, rows are copied straight into the tables; otherwise each batch goes through a
+    /// temporary staging table, since COPY itself can't resolve conflicts. Each load also adds its
+    /// `provenance` to the `provenance` table.
+    pub fn write(&self, rodeo: &Rodeo, wiki: &Wiki, provenance: &Metadata) -> anyhow::Result<()> {
+        let Some(dsn) = &self.postgres else {
+            return Ok(());
+        };
+        let mut client = postgres::Client::connect(dsn, postgres::NoTls)?;
+        let schema = quote_identifier(&self.postgres_schema);
+
+        client.batch_execute(&format!("CREATE SCHEMA IF NOT EXISTS {schema}"))?;
+        let links = if wiki.is_typed() {
+            &TYPED_LINKS
+        } else {
+            &LINKS
+        };
+        for table in [&PAGES, links, &PROVENANCE] {
+            client.batch_execute(&format!(
+                "CREATE TABLE IF NOT EXISTS {schema}.{} ({})",
+                table.name, table.definition
+            ))?;
+        }
+
+        self.copy(&mut client, &schema, &PAGES, super::page_rows(rodeo, wiki))?;
+        self.copy(&mut client, &schema, links, super::link_rows(wiki))?;
+        client.execute(
+            &format!(
+                "INSERT INTO {schema}.{} ({}) VALUES ($1, $2, $3::text::date, $4, $5, $6)",
+                PROVENANCE.name, PROVENANCE.columns
+            ),
+            &[
+                &provenance
+                    .created
+                    .and_then(|created| i64::try_from(created).ok()),
+                &provenance.dump,
+                &provenance.dump_date,
+                &provenance.wikigraph,
+                &provenance.options,
+                &provenance.command,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// COPY `rows`, in the text format with a trailing newline each, into `table`.
+    fn copy(
+        &self,
+        client: &mut postgres::Client,
+        schema: &str,
+        table: &Table,
+        rows: impl Iterator<Item = String>,
+    ) -> anyhow::Result<()> {
+        let target = format!("{schema}.{}", table.name);
+        let staging = format!("wikigraph_staging_{}", table.name);
+        if self.postgres_on_conflict != OnConflict::Error {
+            client.batch_execute(&format!(
+                "CREATE TEMPORARY TABLE IF NOT EXISTS {staging} (LIKE {target})"
+            ))?;
+        }
+        let conflict = match self.postgres_on_conflict {
+            OnConflict::Error => String::new(),
+            OnConflict::Skip => format!("ON CONFLICT ({}) DO NOTHING", table.key),
+            // Links have no columns besides the key, so there's nothing to replace.
+            OnConflict::Replace if table.update.is_empty() => {
+                format!("ON CONFLICT ({}) DO NOTHING", table.key)
+            }
+            OnConflict::Replace => {
+                format!("ON CONFLICT ({}) DO UPDATE SET {}", table.key, table.update)
+            }
+        };
+
+        let mut rows = rows.peekable();
+        let mut total = 0;
+        while rows.peek().is_some() {
+            let mut transaction = client.transaction()?;
+            let destination = if conflict.is_empty() {
+                &target
+            } else {
+                &staging
+            };
+            let mut writer = transaction.copy_in(&format!(
+                "COPY {destination} ({}) FROM STDIN",
+                table.columns
+            ))?;
+            for row in rows.by_ref().take(self.postgres_batch_size) {
+                writer.write_all(row.as_bytes())?;
+                total += 1;
+            }
+            writer.finish()?;
+            if !conflict.is_empty() {
+                transaction.batch_execute(&format!(
+                    "INSERT INTO {target} ({columns}) SELECT {columns} FROM {staging} {conflict}; \
+                     TRUNCATE {staging}",
+                    columns = table.columns
+                ))?;
+            }
+            transaction.commit()?;
+            tracing::info!("Copied {total} rows into {target}");
+        }
+
+        Ok(())
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
@@ -0,0 +1,228 @@
+//! The graph as an SQLite database, for querying with SQL: `pages(id, title, ns, is_redirect)`,
+//! `links(from_id, to_id)` with an `edge_type` too for typed edges, `redirects(from_id, to_id)`,
+//! and a `provenance` row. Commands that load a saved graph read such a database too.
+
+use crate::{graph::Metadata, Wiki};
+use anyhow::Context as _;
+use lasso::{Key as _, Rodeo, Spur};
+use rusqlite::{Connection, OpenFlags, OptionalExtension as _};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(clap::Args)]
+#[group(id = "sqlite_sink")]
+pub struct Args {
+    /// Write pages and links to a new SQLite database at this file, which `query`, `stats`,
+    /// and other commands taking a saved graph read as well
+    #[arg(long, value_name = "FILE")]
+    sqlite: Option<PathBuf>,
+
+    /// Number of rows inserted per transaction
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    sqlite_batch_size: usize,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE pages (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        ns INTEGER,
+        is_redirect INTEGER NOT NULL
+    );
+    CREATE TABLE redirects (from_id INTEGER PRIMARY KEY, to_id INTEGER NOT NULL);
+    CREATE TABLE provenance (
+        created INTEGER,
+        dump TEXT,
+        dump_date TEXT,
+        wikigraph TEXT,
+        options TEXT,
+        command TEXT NOT NULL
+    );
+";
+
+/// Built once the rows are in, which is much faster than keeping them up to date.
+const INDEXES: &str = "
+    CREATE UNIQUE INDEX pages_title ON pages (title);
+    CREATE INDEX links_from ON links (from_id, to_id);
+    CREATE INDEX links_to ON links (to_id, from_id);
+";
+
+impl Args {
+    /// Write the graph to the database, replacing the file only once it is complete. IDs are
+    /// this run's node IDs.
+    pub fn write(&self, rodeo: &Rodeo, wiki: &Wiki, provenance: &Metadata) -> anyhow::Result<()> {
+        let Some(path) = &self.sqlite else {
+            return Ok(());
+        };
+        let partial = path.with_extension("partial");
+        if partial.exists() {
+            fs::remove_file(&partial)?;
+        }
+        let mut connection = Connection::open(&partial)?;
+        // A half-written database is thrown away rather than recovered, so skip the journal.
+        connection.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+        connection.execute_batch(SCHEMA)?;
+        let typed = wiki.is_typed();
+        connection.execute_batch(if typed {
+            "CREATE TABLE links (from_id INTEGER NOT NULL, to_id INTEGER NOT NULL, \
+             edge_type TEXT NOT NULL)"
+        } else {
+            "CREATE TABLE links (from_id INTEGER NOT NULL, to_id INTEGER NOT NULL)"
+        })?;
+
+        self.insert(
+            &mut connection,
+            "pages",
+            "INSERT INTO pages VALUES (?1, ?2, ?3, ?4)",
+            rodeo.iter().map(|(key, title)| {
+                (
+                    id(key),
+                    title,
+                    wiki.namespaces.get(&key),
+                    wiki.redirects.contains_key(&key),
+                )
+            }),
+        )?;
+        if typed {
+            self.insert(
+                &mut connection,
+                "links",
+                "INSERT INTO links VALUES (?1, ?2, ?3)",
+                wiki.typed_edges().map(|(source, target, edge_type)| {
+                    (
+                        id(source),
+                        id(target),
+                        edge_type.map_or("", crate::edge_type::EdgeType::name),
+                    )
+                }),
+            )?;
+        } else {
+            self.insert(
+                &mut connection,
+                "links",
+                "INSERT INTO links VALUES (?1, ?2)",
+                wiki.links.iter().flat_map(|(&source, links)| {
+                    links.iter().map(move |&target| (id(source), id(target)))
+                }),
+            )?;
+        }
+        self.insert(
+            &mut connection,
+            "redirects",
+            "INSERT INTO redirects VALUES (?1, ?2)",
+            wiki.redirects
+                .iter()
+                .map(|(&page, &target)| (id(page), id(target))),
+        )?;
+        connection.execute(
+            "INSERT INTO provenance VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (
+                provenance
+                    .created
+                    .and_then(|created| i64::try_from(created).ok()),
+                &provenance.dump,
+                &provenance.dump_date,
+                &provenance.wikigraph,
+                &provenance.options,
+                serde_json::to_string(&provenance.command)?,
+            ),
+        )?;
+
+        tracing::info!("Indexing {}", partial.display());
+        connection.execute_batch(INDEXES)?;
+        connection.close().map_err(|(_, error)| error)?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    /// Insert `rows` with the statement `sql`, committing every `--sqlite-batch-size` rows.
+    fn insert<P: rusqlite::Params>(
+        &self,
+        connection: &mut Connection,
+        table: &str,
+        sql: &str,
+        rows: impl Iterator<Item = P>,
+    ) -> anyhow::Result<()> {
+        let mut rows = rows.peekable();
+        let mut total = 0;
+        while rows.peek().is_some() {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(sql)?;
+                for row in rows.by_ref().take(self.sqlite_batch_size.max(1)) {
+                    statement.execute(row)?;
+                    total += 1;
+                }
+            }
+            transaction.commit()?;
+            tracing::info!("Inserted {total} rows into {table}");
+        }
+        Ok(())
+    }
+}
+
+fn id(key: Spur) -> i64 {
+    i64::try_from(key.into_usize()).unwrap()
+}
+
+/// The pages, links, and redirects of the database at `path`, and where they came from.
+pub fn read(path: &Path) -> anyhow::Result<(Rodeo, Wiki, Metadata)> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut rodeo = Rodeo::new();
+    let mut wiki = Wiki::default();
+    let mut pages = HashMap::new();
+
+    let mut statement = connection.prepare("SELECT id, title, ns FROM pages ORDER BY id")?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        let title = rodeo.get_or_intern(row.get::<_, String>(1)?);
+        pages.insert(row.get::<_, i64>(0)?, title);
+        if let Some(ns) = row.get(2)? {
+            wiki.namespaces.insert(title, ns);
+        }
+    }
+    let page = |id: i64| {
+        pages
+            .get(&id)
+            .copied()
+            .with_context(|| format!("No page with ID {id}"))
+    };
+
+    let mut statement = connection.prepare("SELECT from_id, to_id FROM links")?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        let (source, target) = (page(row.get(0)?)?, page(row.get(1)?)?);
+        wiki.links.entry(source).or_default().insert(target);
+    }
+    let mut statement = connection.prepare("SELECT from_id, to_id FROM redirects")?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        wiki.redirects
+            .insert(page(row.get(0)?)?, page(row.get(1)?)?);
+    }
+
+    let metadata = connection
+        .query_row(
+            "SELECT created, dump, dump_date, wikigraph, options, command FROM provenance \
+             LIMIT 1",
+            [],
+            |row| {
+                Ok(Metadata {
+                    created: row
+                        .get::<_, Option<i64>>(0)?
+                        .and_then(|created| u64::try_from(created).ok()),
+                    dump: row.get(1)?,
+                    dump_date: row.get(2)?,
+                    wikigraph: row.get(3)?,
+                    options: row.get(4)?,
+                    command: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                })
+            },
+        )
+        .optional()?
+        .unwrap_or_default();
+    Ok((rodeo, wiki, metadata))
+}
@@ -0,0 +1,154 @@
+//! The `<siteinfo>` block at the head of every dump, naming the wiki's namespaces in its own
+//! language and saying whether titles are capitalized. Without it, only the English names are
+//! known, so `[[Kategorie:Vögel]]` on the German Wikipedia reads as a link to an article.
+
+use anyhow::Context as _;
+use quick_xml::events::Event;
+use std::{path::Path, sync::Arc};
+use wikigraph::{dump, remote};
+
+/// Namespace keys with meanings of their own.
+pub const FILE: i64 = 6;
+pub const CATEGORY: i64 = 14;
+pub const MEDIA: i64 = -2;
+
+#[derive(Clone, Debug, Default)]
+pub struct Siteinfo {
+    /// Whether titles may start with a lowercase letter, `<case>` being `case-sensitive`
+    /// rather than `first-letter`.
+    pub case_sensitive: bool,
+    /// Local names of the namespaces and their keys, in the order listed, leaving out the main
+    /// namespace, whose name is empty.
+    namespaces: Vec<(String, i64)>,
+}
+
+impl Siteinfo {
+    /// Read the siteinfo of the dump at `path`, which may be a URL downloaded as `remote` says.
+    /// Only the head of the dump is read.
+    ///
+    /// # Errors
+    ///
+    /// If the dump can't be read, or has no `<siteinfo>` before its first page.
+    pub fn read(path: &Path, remote: &remote::Options) -> anyhow::Result<Self> {
+        let mut xml = dump::read_xml_counted(path, None, &Arc::default(), remote)?;
+        let mut siteinfo = Self::default();
+        let mut found = false;
+        let mut buffer = Vec::new();
+        let mut key = None;
+        let mut in_case = false;
+        loop {
+            match xml
+                .read_event_into(&mut buffer)
+                .context("Failed to read XML event")?
+            {
+                Event::Start(data) if data.name().into_inner() == b"siteinfo" => found = true,
+                Event::Start(data) if data.name().into_inner() == b"case" => in_case = true,
+                Event::Start(data) if data.name().into_inner() == b"namespace" => {
+                    let value = data
+                        .try_get_attribute("key")?
+                        .context("Namespace without a key")?
+                        .unescape_value()?;
+                    key = Some(value.trim().parse().context("Invalid namespace key")?);
+                }
+                Event::Text(data) if in_case => {
+                    siteinfo.case_sensitive = data.unescape()?.trim() == "case-sensitive";
+                }
+                Event::Text(data) => {
+                    if let Some(key) = key {
+                        let name = data.unescape()?.trim().to_owned();
+                        if !name.is_empty() {
+                            siteinfo.namespaces.push((name, key));
+                        }
+                    }
+                }
+                Event::End(data) => match data.name().into_inner() {
+                    b"case" => in_case = false,
+                    b"namespace" => key = None,
+                    b"siteinfo" => break,
+                    _ => {}
+                },
+                Event::Start(data) if data.name().into_inner() == b"page" => break,
+                Event::Eof => break,
+                _ => {}
+            }
+            buffer.clear();
+        }
+        anyhow::ensure!(found, "The dump has no siteinfo");
+        Ok(siteinfo)
+    }
+
+    /// The key of the namespace named `prefix`, compared as MediaWiki does: ignoring case, and
+    /// with underscores for spaces.
+    pub fn namespace(&self, prefix: &str) -> Option<i64> {
+        let prefix = fold(prefix);
+        self.namespaces
+            .iter()
+            .find(|(name, _)| fold(name) == prefix)
+            .map(|&(_, key)| key)
+    }
+
+    /// The local name of the namespace `key`.
+    pub fn name(&self, key: i64) -> Option<&str> {
+        self.namespaces
+            .iter()
+            .find(|&&(_, k)| k == key)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// How many namespaces have names.
+    pub fn len(&self) -> usize {
+        self.namespaces.len()
+    }
+}
+
+/// Whether the namespace `key` holds no content: a talk namespace, one of those every MediaWiki
+/// site has below 16, or one of the extensions Wikimedia wikis run, such as `Module`. From 100
+/// up, namespaces are the wiki's own, and some hold content, like Wikisource's `Author`, so
+/// those are only known by the names of the project profile.
+pub fn is_meta(key: i64) -> bool {
+    key < 16 || key % 2 != 0 || matches!(key, 710 | 828 | 2300 | 2302 | 2600)
+}
+
+/// The canonical English names of the namespaces every MediaWiki site has, and of a few that
+/// Wikimedia wikis share, which dumps use where their siteinfo isn't read.
+const ENGLISH_NAMES: [(i64, &str); 19] = [
+    (1, "Talk"),
+    (2, "User"),
+    (3, "User talk"),
+    (4, "Project"),
+    (5, "Project talk"),
+    (6, "File"),
+    (7, "File talk"),
+    (8, "MediaWiki"),
+    (9, "MediaWiki talk"),
+    (10, "Template"),
+    (11, "Template talk"),
+    (12, "Help"),
+    (13, "Help talk"),
+    (14, "Category"),
+    (15, "Category talk"),
+    (100, "Portal"),
+    (101, "Portal talk"),
+    (828, "Module"),
+    (829, "Module talk"),
+];
+
+/// The canonical English name of the namespace `key`, if it is one of `ENGLISH_NAMES`.
+pub fn english_name(key: i64) -> Option<&'static str> {
+    ENGLISH_NAMES
+        .iter()
+        .find(|&&(other, _)| other == key)
+        .map(|&(_, name)| name)
+}
+
+/// The key of the namespace whose canonical English name is `name`.
+pub fn english_key(name: &str) -> Option<i64> {
+    ENGLISH_NAMES
+        .iter()
+        .find(|&&(_, other)| other == name)
+        .map(|&(key, _)| key)
+}
+
+fn fold(name: &str) -> String {
+    name.trim().replace('_', " ").to_lowercase()
+}
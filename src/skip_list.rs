@@ -0,0 +1,212 @@
+//! Pages that failed to parse, remembered across runs. A page whose link extraction panics or
+//! whose `--script` fails would otherwise end a parse of the whole dump hours in; with
+//! `parse --skip-list FILE` it is recorded in the file and left out instead, and later parses
+//! skip it without trying again. The `skip-list` command reviews and clears the file.
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Skip list written by `parse --skip-list`
+    #[arg(env = "WIKIGRAPH_SKIP_LIST")]
+    file: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// List the skipped pages, with why and when each failed
+    List,
+    /// Try pages again in the next parse
+    Remove {
+        #[arg(required = true)]
+        titles: Vec<String>,
+    },
+    /// Try every page again in the next parse
+    Clear,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Pages {
+    #[serde(default)]
+    pages: BTreeMap<String, Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// The error or panic message of the first failure.
+    reason: String,
+    /// File name of the dump it failed in.
+    dump: String,
+    /// When it first failed, in seconds since the Unix epoch.
+    failed: u64,
+}
+
+/// The skip list of a parse: which pages to leave out, and where to record new failures. It is
+/// shared by the threads extracting links.
+pub struct SkipList {
+    path: PathBuf,
+    /// File name of the dump being parsed, recorded with failures.
+    dump: String,
+    list: Mutex<Pages>,
+    /// Listed pages met so far.
+    skipped: AtomicUsize,
+    /// Pages that failed in this parse.
+    failed: AtomicUsize,
+}
+
+impl SkipList {
+    /// The skip list at `path`, which is empty if the file doesn't exist, for a parse of
+    /// `dump`.
+    pub fn load(path: &Path, dump: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            dump: dump
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            list: Mutex::new(load(path)?),
+            skipped: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        })
+    }
+
+    /// Whether the page titled `title` is listed, counting it if so.
+    pub fn skips(&self, title: &str) -> bool {
+        let listed = self.list.lock().unwrap().pages.contains_key(title);
+        if listed {
+            tracing::debug!("Skipping '{title}', which failed in an earlier parse");
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        listed
+    }
+
+    /// Run `f` on behalf of the page titled `title`, returning what it does, or `None` after
+    /// recording the page if it fails or panics. The file is saved at once, so the page stays
+    /// listed even if the parse ends some other way later.
+    pub fn attempt<T>(&self, title: &str, f: impl FnOnce() -> anyhow::Result<T>) -> Option<T> {
+        let reason = match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(Ok(value)) => return Some(value),
+            Ok(Err(error)) => format!("{error:#}"),
+            Err(payload) => payload
+                .downcast_ref::<&str>()
+                .map(|message| String::from(*message))
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .map_or_else(
+                    || String::from("Panicked"),
+                    |message| format!("Panicked: {message}"),
+                ),
+        };
+        tracing::warn!("Skipping '{title}' from now on: {reason}");
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        let failed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |age| age.as_secs());
+        let mut list = self.list.lock().unwrap();
+        list.pages.insert(
+            String::from(title),
+            Entry {
+                reason,
+                dump: self.dump.clone(),
+                failed,
+            },
+        );
+        save(&self.path, &list)
+            .with_context(|| format!("Failed to save {}", self.path.display()))
+            .unwrap();
+        None
+    }
+
+    /// Log how many pages were skipped and added.
+    pub fn report(&self) {
+        let skipped = self.skipped.load(Ordering::Relaxed);
+        let failed = self.failed.load(Ordering::Relaxed);
+        if skipped > 0 || failed > 0 {
+            tracing::info!(
+                "Skipped {skipped} listed pages; {failed} more failed and were added to {}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// `f()` for the page titled `title`, through `skip_list` if there is one. Without a skip list,
+/// failures end the parse.
+#[track_caller]
+pub fn attempt<T>(
+    skip_list: Option<&SkipList>,
+    title: &str,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> Option<T> {
+    match skip_list {
+        Some(skip_list) => skip_list.attempt(title, f),
+        None => Some(f().unwrap()),
+    }
+}
+
+pub fn run(args: &Args) {
+    let mut list = load(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))
+        .unwrap();
+    match &args.command {
+        Command::List => {
+            for (title, entry) in &list.pages {
+                println!(
+                    "{title}\t{}\t{}\t{}",
+                    entry.dump, entry.failed, entry.reason
+                );
+            }
+        }
+        Command::Remove { titles } => {
+            for title in titles {
+                list.pages
+                    .remove(title)
+                    .with_context(|| format!("'{title}' isn't listed"))
+                    .unwrap();
+            }
+            save(&args.file, &list)
+                .context("Failed to save skip list")
+                .unwrap();
+        }
+        Command::Clear => {
+            let count = list.pages.len();
+            list.pages.clear();
+            save(&args.file, &list)
+                .context("Failed to save skip list")
+                .unwrap();
+            println!("Cleared {count} pages");
+        }
+    }
+}
+
+fn load(path: &Path) -> anyhow::Result<Pages> {
+    match fs::read_to_string(path) {
+        Ok(text) => {
+            toml::from_str(&text).with_context(|| format!("Invalid skip list {}", path.display()))
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Pages::default()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Write `list` to `path`, replacing the file only once it is complete.
+fn save(path: &Path, list: &Pages) -> anyhow::Result<()> {
+    let partial = path.with_extension("partial");
+    fs::write(&partial, toml::to_string(list)?)?;
+    fs::rename(partial, path)?;
+    Ok(())
+}
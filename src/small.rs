@@ -0,0 +1,34 @@
+//! Small-graph mode, for tutorials, doctests, and CI-sized fixtures. Dumps and graphs below
+//! these sizes, or any given `--demo`, take the simple paths: one thread, so that node IDs and
+//! every output are the same on every run, and exact algorithms where the scalable ones
+//! estimate or sample, since those answer no faster on a few thousand pages.
+
+use crate::graph::Graph;
+use std::{fs, path::Path, thread};
+
+/// Largest local dump file parsed in small-graph mode without `--demo`.
+pub const DUMP_BYTES: u64 = 4 << 20;
+
+/// Most nodes of a graph analysed in small-graph mode without `--demo`.
+pub const NODES: usize = 10_000;
+
+/// Whether to parse the dump at `path` in small-graph mode.
+pub fn dump(path: &Path, demo: bool) -> bool {
+    demo || fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.len() <= DUMP_BYTES)
+}
+
+/// Whether to analyse `graph` in small-graph mode.
+pub fn graph(graph: &Graph, demo: bool) -> bool {
+    demo || graph.node_count() <= NODES
+}
+
+/// The number of threads to run: `requested`, or else one in small-graph mode and one per CPU
+/// otherwise.
+pub fn threads(requested: Option<u16>, small: bool) -> usize {
+    match requested {
+        Some(threads) => usize::from(threads),
+        None if small => 1,
+        None => thread::available_parallelism().map_or(1, usize::from),
+    }
+}
@@ -0,0 +1,186 @@
+//! Time-sliced graphs from full-history dumps.
+//!
+//! Every revision of a page is reduced to its link set and redirect target and bucketed into a
+//! period; a snapshot of period `p` sees each page as of its last revision within or before `p`,
+//! links to redirects included, so that a page that stopped being a redirect stops redirecting.
+
+use crate::Wiki;
+use anyhow::Context as _;
+use lasso::{Rodeo, Spur};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Interval {
+    Month,
+    Year,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Period {
+    year: u16,
+    month: u8,
+}
+
+impl Period {
+    /// Bucket an ISO 8601 timestamp (`2023-05-01T10:00:00Z`).
+    fn of(timestamp: &str, interval: Interval) -> Option<Self> {
+        let year = timestamp.get(0..4)?.parse().ok()?;
+        let month = match interval {
+            Interval::Month => timestamp.get(5..7)?.parse().ok()?,
+            Interval::Year => 1,
+        };
+        Some(Self { year, month })
+    }
+
+    fn next(self, interval: Interval) -> Self {
+        match interval {
+            Interval::Month if self.month < 12 => Self {
+                year: self.year,
+                month: self.month + 1,
+            },
+            Interval::Month | Interval::Year => Self {
+                year: self.year + 1,
+                month: 1,
+            },
+        }
+    }
+
+    fn name(self, interval: Interval) -> String {
+        match interval {
+            Interval::Month => format!("{:04}-{:02}", self.year, self.month),
+            Interval::Year => format!("{:04}", self.year),
+        }
+    }
+}
+
+struct Revision {
+    period: Period,
+    timestamp: String,
+    links: HashSet<Spur>,
+    redirect: Option<Spur>,
+}
+
+/// Link sets of every page over time, keeping only the last revision per period.
+pub struct History {
+    interval: Interval,
+    pages: HashMap<Spur, Vec<Revision>>,
+}
+
+impl History {
+    pub fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            pages: HashMap::new(),
+        }
+    }
+
+    /// Record a revision of `page` at `timestamp` with `links`, redirecting to `redirect` if it
+    /// is a redirect.
+    pub fn record(
+        &mut self,
+        page: Spur,
+        timestamp: &str,
+        links: HashSet<Spur>,
+        redirect: Option<Spur>,
+    ) -> anyhow::Result<()> {
+        let period = Period::of(timestamp, self.interval)
+            .with_context(|| format!("Invalid revision timestamp '{timestamp}'"))?;
+        let revisions = self.pages.entry(page).or_default();
+
+        // History dumps list revisions chronologically, but don't rely on it.
+        let index = revisions.partition_point(|r| r.period < period);
+        match revisions.get_mut(index) {
+            Some(revision) if revision.period == period => {
+                if *timestamp >= *revision.timestamp {
+                    revision.timestamp = String::from(timestamp);
+                    revision.links = links;
+                    revision.redirect = redirect;
+                }
+            }
+            _ => revisions.insert(
+                index,
+                Revision {
+                    period,
+                    timestamp: String::from(timestamp),
+                    links,
+                    redirect,
+                },
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// The links and redirects of the graph as of each page's newest revision.
+    pub fn latest(&self) -> (HashMap<Spur, HashSet<Spur>>, HashMap<Spur, Spur>) {
+        Self::graph(
+            self.pages
+                .iter()
+                .filter_map(|(&page, revisions)| Some((page, revisions.last()?))),
+        )
+    }
+
+    fn snapshot(&self, period: Period) -> (HashMap<Spur, HashSet<Spur>>, HashMap<Spur, Spur>) {
+        Self::graph(self.pages.iter().filter_map(|(&page, revisions)| {
+            let index = revisions.partition_point(|r| r.period <= period);
+            Some((page, revisions.get(index.checked_sub(1)?)?))
+        }))
+    }
+
+    fn graph<'a>(
+        revisions: impl Iterator<Item = (Spur, &'a Revision)>,
+    ) -> (HashMap<Spur, HashSet<Spur>>, HashMap<Spur, Spur>) {
+        let mut links = HashMap::new();
+        let mut redirects = HashMap::new();
+        for (page, revision) in revisions {
+            links.insert(page, revision.links.clone());
+            if let Some(target) = revision.redirect {
+                redirects.insert(page, target);
+            }
+        }
+        (links, redirects)
+    }
+
+    /// Write one Gephi export per period, from the first revision to the last, into
+    /// subdirectories of `dir` named after the period (`2023-05` or `2023`), serializing the rows
+    /// of each on up to `threads` threads. With `resolve_redirects`, links to redirects are
+    /// pointed at the pages they lead to in that period.
+    pub fn write(
+        &self,
+        dir: &Path,
+        rodeo: &Rodeo,
+        resolve_redirects: bool,
+        threads: usize,
+    ) -> anyhow::Result<()> {
+        let periods = self.pages.values().flatten().map(|r| r.period);
+        let (Some(first), Some(last)) = (periods.clone().min(), periods.max()) else {
+            return Ok(());
+        };
+
+        fs::create_dir_all(dir)?;
+
+        let mut period = first;
+        while period <= last {
+            let name = period.name(self.interval);
+            tracing::debug!("Writing snapshot {name}");
+            let (links, redirects) = self.snapshot(period);
+            let mut wiki = Wiki {
+                links,
+                redirects,
+                ..Wiki::default()
+            };
+            if resolve_redirects {
+                wiki.resolve_redirects();
+            }
+            crate::export::gephi::write(&dir.join(&name), rodeo, &wiki, threads)
+                .with_context(|| format!("Failed to write snapshot {name}"))?;
+            period = period.next(self.interval);
+        }
+
+        Ok(())
+    }
+}
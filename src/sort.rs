@@ -0,0 +1,49 @@
+//! Sort keys, so listings come out in the order MediaWiki shows them on category pages.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// The key set by the last `{{DEFAULTSORT:...}}` (or one of its aliases) in `text`; like
+/// MediaWiki, a later one overrides earlier ones.
+pub fn default_sort_key(text: &str) -> Option<&str> {
+    static REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\{\{\s*(?:DEFAULTSORT|DEFAULTSORTKEY|DEFAULTCATEGORYSORT)\s*:\s*([^{}|]*?)\s*(?:\|[^{}]*)?\}\}")
+            .unwrap()
+    });
+
+    REGEX
+        .captures_iter(text)
+        .map(|capture| capture.get(1).unwrap().as_str())
+        .filter(|key| !key.is_empty())
+        .last()
+}
+
+/// `(category, sort key)` for every `[[Category:Name]]` or `[[Category:Name|Sort key]]`
+/// membership in `text`, the prefixes of which `is_category` tells apart from other namespaces
+/// and interwikis. Linking to a category with a leading colon does not make a page a member, so
+/// `[[:Category:Name]]` is skipped.
+pub fn categories<'a>(
+    text: &'a str,
+    is_category: impl Fn(&str) -> bool + 'a,
+) -> impl Iterator<Item = (&'a str, Option<&'a str>)> {
+    static REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\[\[\s*([^\[\]|:]+?)\s*:\s*([^\[\]|]+?)\s*(?:\|([^\[\]]*))?\]\]").unwrap()
+    });
+
+    REGEX.captures_iter(text).filter_map(move |capture| {
+        if !is_category(capture.get(1).unwrap().as_str()) {
+            return None;
+        }
+        let category = capture.get(2).unwrap().as_str();
+        let key = capture
+            .get(3)
+            .map(|key| key.as_str())
+            .filter(|key| !key.is_empty());
+        Some((category, key))
+    })
+}
+
+/// The collation key ordering `key`, following MediaWiki's default "uppercase" collation.
+pub fn collation(key: &str) -> String {
+    key.to_uppercase()
+}
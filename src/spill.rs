@@ -0,0 +1,381 @@
+//! `parse --memory-limit`: links spilled to disk while parsing instead of kept in hash sets, for
+//! dumps whose links don't fit in memory. Links collect as (source, target) title IDs in a
+//! buffer of at most the limit, which is sorted and written out as a run whenever it fills.
+//! Once the dump is read, the runs are merged into one sorted stream, from which the graph and
+//! edge list are written a page at a time. The graph still takes four bytes a link, but the
+//! hash sets took a few dozen.
+
+use crate::{export::edge_list, graph, navigation, ParseArgs, Wiki};
+use anyhow::Context as _;
+use lasso::{Key as _, Rodeo, Spur};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write as _},
+    mem,
+    path::{Path, PathBuf},
+    process, vec,
+};
+
+/// Bytes of a link in the buffer and in the runs.
+const LINK_BYTES: u64 = 8;
+
+/// Where `Wiki::link_targets` says links to a title are dropped.
+pub const LEFT_OUT: u32 = u32::MAX;
+
+pub struct Spill {
+    /// Directory of the runs, removed with them when dropped.
+    dir: PathBuf,
+    buffer: Vec<(u32, u32)>,
+    /// Links the buffer holds before it is spilled.
+    capacity: usize,
+    runs: Vec<PathBuf>,
+    spilled: u64,
+    /// Where links to each title lead, by title ID, once parsing is done.
+    targets: Vec<u32>,
+}
+
+impl Spill {
+    /// Spill links into a new directory in `parent`, holding at most `limit` bytes of them in
+    /// memory.
+    pub fn create(parent: &Path, limit: u64) -> anyhow::Result<Self> {
+        let dir = parent.join(format!("wikigraph-spill-{}", process::id()));
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let capacity = usize::try_from((limit / LINK_BYTES).max(1))?;
+        Ok(Self {
+            dir,
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+            runs: Vec::new(),
+            spilled: 0,
+            targets: Vec::new(),
+        })
+    }
+
+    /// Add the links of `source` to each of `targets`, spilling the buffer if it fills.
+    pub fn add(
+        &mut self,
+        source: Spur,
+        targets: impl IntoIterator<Item = Spur>,
+    ) -> anyhow::Result<()> {
+        let source = id(source)?;
+        for target in targets {
+            if self.buffer.len() == self.capacity {
+                self.write_run()?;
+            }
+            self.buffer.push((source, id(target)?));
+        }
+        Ok(())
+    }
+
+    /// Point links to each title at `targets[title]` when merging, dropping those to titles with
+    /// `LEFT_OUT`.
+    pub fn set_targets(&mut self, targets: Vec<u32>) {
+        self.targets = targets;
+    }
+
+    fn write_run(&mut self) -> anyhow::Result<()> {
+        self.buffer.sort_unstable();
+        self.buffer.dedup();
+        let path = self.dir.join(format!("{}.run", self.runs.len()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for &(source, target) in &self.buffer {
+            writer.write_all(&source.to_le_bytes())?;
+            writer.write_all(&target.to_le_bytes())?;
+        }
+        writer.flush()?;
+        self.spilled += self.buffer.len() as u64;
+        tracing::info!(
+            "Spilled {} links to {} ({} so far)",
+            self.buffer.len(),
+            path.display(),
+            self.spilled
+        );
+        self.buffer.clear();
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// The links spilled so far and still in the buffer, merged in order.
+    fn merge(mut self) -> anyhow::Result<Merge> {
+        self.buffer.sort_unstable();
+        self.buffer.dedup();
+        let mut runs = vec![Run::Memory(mem::take(&mut self.buffer).into_iter())];
+        for path in &self.runs {
+            runs.push(Run::File(BufReader::new(File::open(path)?)));
+        }
+        tracing::info!("Merging {} runs of spilled links", runs.len());
+        let mut heap = BinaryHeap::new();
+        for (index, run) in runs.iter_mut().enumerate() {
+            if let Some(link) = run.next()? {
+                heap.push(Reverse((link, index)));
+            }
+        }
+        Ok(Merge {
+            runs,
+            heap,
+            last: None,
+            _spill: self,
+        })
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_dir_all(&self.dir) {
+            tracing::warn!("Failed to remove {}: {error}", self.dir.display());
+        }
+    }
+}
+
+/// The spilled links in order of source and then target, each once.
+struct Merge {
+    runs: Vec<Run>,
+    /// The next link of each run that has one, and its run.
+    heap: BinaryHeap<Reverse<((u32, u32), usize)>>,
+    last: Option<(u32, u32)>,
+    /// Kept until the runs are read, so their directory stays.
+    _spill: Spill,
+}
+
+impl Merge {
+    fn next(&mut self) -> io::Result<Option<(u32, u32)>> {
+        while let Some(Reverse((link, index))) = self.heap.pop() {
+            if let Some(next) = self.runs[index].next()? {
+                self.heap.push(Reverse((next, index)));
+            }
+            // Runs are each distinct, but the same link can be in several.
+            if self.last != Some(link) {
+                self.last = Some(link);
+                return Ok(Some(link));
+            }
+        }
+        Ok(None)
+    }
+}
+
+enum Run {
+    /// The links left in the buffer at the end, which aren't written out.
+    Memory(vec::IntoIter<(u32, u32)>),
+    File(BufReader<File>),
+}
+
+impl Run {
+    fn next(&mut self) -> io::Result<Option<(u32, u32)>> {
+        match self {
+            Self::Memory(links) => Ok(links.next()),
+            Self::File(reader) => {
+                let mut bytes = [0; 8];
+                match reader.read_exact(&mut bytes) {
+                    Ok(()) => {}
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(error) => return Err(error),
+                }
+                let [source, target] = [&bytes[..4], &bytes[4..]]
+                    .map(|half| u32::from_le_bytes(half.try_into().unwrap()));
+                Ok(Some((source, target)))
+            }
+        }
+    }
+}
+
+/// Merge the links of `spill` into the `--graph` file and the `--output` edge list of `args`,
+/// with the nodes and redirects of `wiki`, returning how many links they have.
+pub fn write(
+    args: &ParseArgs,
+    rodeo: &Rodeo,
+    wiki: &Wiki,
+    mut spill: Spill,
+    metadata: graph::Metadata,
+) -> anyhow::Result<u64> {
+    let mut output = Output {
+        rodeo,
+        wiki,
+        exclude_disambiguation: args.exclude_disambiguation,
+        edge_list: args
+            .output
+            .as_deref()
+            .map(|path| edge_list::Writer::create(path, args.output_format, args.shards))
+            .transpose()
+            .context("Failed to create edge list")?,
+        csr: args.graph.is_some().then(|| (vec![0], Vec::new())),
+        pages: vec![false; rodeo.len()],
+        missing: vec![false; rodeo.len()],
+        red_links: 0,
+        edges: 0,
+    };
+    for page in wiki.namespaces.keys() {
+        output.pages[page.into_usize()] = true;
+    }
+
+    let targets = mem::take(&mut spill.targets);
+    let mut merge = spill.merge()?;
+    let mut links = Vec::new();
+    let mut source = None;
+    while let Some((from, target)) = merge.next().context("Failed to read spilled links")? {
+        if let Some(source) = source.filter(|&source| source != from) {
+            output.add_page(source, &mut links, &targets)?;
+        }
+        source = Some(from);
+        links.push(target);
+    }
+    if let Some(source) = source {
+        output.add_page(source, &mut links, &targets)?;
+    }
+    output.finish(args, metadata)
+}
+
+/// Where the merged links of each page go.
+struct Output<'a> {
+    rodeo: &'a Rodeo,
+    wiki: &'a Wiki,
+    exclude_disambiguation: bool,
+    edge_list: Option<edge_list::Writer>,
+    /// Offsets and targets of the graph, if saving one.
+    csr: Option<(Vec<u64>, Vec<u32>)>,
+    /// Which titles are pages of the dump, and which are linked to without being one.
+    pages: Vec<bool>,
+    missing: Vec<bool>,
+    red_links: u64,
+    edges: u64,
+}
+
+impl Output<'_> {
+    /// Add the links of `source` to `links`, pointed at the pages `targets` says they lead to,
+    /// and clear them. Pages come in order of title ID, as the graph stores them.
+    fn add_page(
+        &mut self,
+        source: u32,
+        links: &mut Vec<u32>,
+        targets: &[u32],
+    ) -> anyhow::Result<()> {
+        if self.exclude_disambiguation
+            && self.wiki.navigation.get(&spur(source)) == Some(&navigation::Kind::Disambiguation)
+        {
+            links.clear();
+        }
+        for link in links.iter_mut() {
+            *link = targets[*link as usize];
+        }
+        links.retain(|&link| link != LEFT_OUT);
+        links.sort_unstable();
+        links.dedup();
+        self.edges += links.len() as u64;
+        for &link in links.iter() {
+            if !self.pages[link as usize] {
+                self.red_links += 1;
+                self.missing[link as usize] = true;
+            }
+        }
+        if let Some(writer) = &mut self.edge_list {
+            let titles = links.iter().map(|&link| self.rodeo.resolve(&spur(link)));
+            writer.add_links(self.rodeo.resolve(&spur(source)), titles)?;
+        }
+        if let Some((offsets, csr_targets)) = &mut self.csr {
+            while offsets.len() <= source as usize {
+                offsets.push(csr_targets.len() as u64);
+            }
+            csr_targets.extend_from_slice(links);
+        }
+        links.clear();
+        Ok(())
+    }
+
+    fn finish(self, args: &ParseArgs, metadata: graph::Metadata) -> anyhow::Result<u64> {
+        tracing::info!(
+            "{} links to {} titles without a page",
+            self.red_links,
+            self.missing.iter().filter(|&&missing| missing).count()
+        );
+        if let Some(writer) = self.edge_list {
+            writer.finish().context("Failed to write edge list")?;
+        }
+        if let (Some((mut offsets, targets)), Some(path)) = (self.csr, &args.graph) {
+            while offsets.len() <= self.rodeo.len() {
+                offsets.push(targets.len() as u64);
+            }
+            let graph = graph::Graph::from_csr(self.rodeo, self.wiki, offsets, targets, metadata)?;
+            crate::save_graph(args, graph, path).context("Failed to save graph")?;
+        }
+        Ok(self.edges)
+    }
+}
+
+/// Parse a size like `4G`, `512MiB`, or `1000000`, in bytes, with binary multiples.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("expected a number of bytes, like 4G, not '{s}'"))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let shift = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("unknown unit '{unit}'; use K, M, G, or T")),
+    };
+    number
+        .checked_mul(1 << shift)
+        .filter(|&bytes| bytes > 0)
+        .ok_or_else(|| format!("'{s}' is not a size between 1 byte and 16 EiB"))
+}
+
+fn id(title: Spur) -> anyhow::Result<u32> {
+    u32::try_from(title.into_usize()).context("Too many titles")
+}
+
+fn spur(id: u32) -> Spur {
+    Spur::try_from_usize(id as usize).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_runs_in_order_once() {
+        let mut rodeo = Rodeo::new();
+        let titles: Vec<Spur> = (0..5).map(|i| rodeo.get_or_intern(i.to_string())).collect();
+        let links = [(3, [1, 0, 4, 1]), (0, [4, 2, 2, 3]), (3, [0, 2, 4, 0])];
+        // Three links a run, so the same link is in several runs and in the buffer.
+        let mut spill = Spill::create(&std::env::temp_dir(), 3 * LINK_BYTES).unwrap();
+        for (source, targets) in links {
+            let targets = targets.map(|target| titles[target]);
+            spill.add(titles[source], targets).unwrap();
+        }
+        assert!(spill.runs.len() > 1);
+        let dir = spill.dir.clone();
+        let mut merge = spill.merge().unwrap();
+        let mut merged = Vec::new();
+        while let Some(link) = merge.next().unwrap() {
+            merged.push(link);
+        }
+        let mut expected: Vec<(u32, u32)> = links
+            .iter()
+            .flat_map(|&(source, targets)| targets.map(|target| (source, target)))
+            .map(|(source, target)| (id(titles[source]).unwrap(), id(titles[target]).unwrap()))
+            .collect();
+        expected.sort_unstable();
+        expected.dedup();
+        assert_eq!(merged, expected);
+        drop(merge);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1000000"), Ok(1_000_000));
+        assert_eq!(parse_size("4G"), Ok(4 << 30));
+        assert_eq!(parse_size("512 MiB"), Ok(512 << 20));
+        assert_eq!(parse_size("2kb"), Ok(2 << 10));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("4X").is_err());
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+}
@@ -0,0 +1,452 @@
+//! Saved graphs built from the SQL dumps Wikimedia publishes beside the XML ones: `page.sql.gz`
+//! and `pagelinks.sql.gz`, and optionally `redirect.sql.gz`, `linktarget.sql.gz`, and
+//! `page_props.sql.gz`. Their links are the ones MediaWiki itself recorded, templates expanded,
+//! so reading them is far faster than parsing wikitext, but the graph has only pages, links,
+//! redirects, and Wikidata items, and titles outside the main namespace take the canonical
+//! English name of their namespace.
+//!
+//! The files are mysqldump output: a `CREATE TABLE` statement naming the columns, then
+//! `INSERT INTO ... VALUES (...),(...);` statements of many rows each, one to a line. Since
+//! MediaWiki 1.43, `pagelinks` gives targets by a `pl_target_id` into `linktarget` instead of
+//! by namespace and title; both layouts are read.
+
+use crate::{
+    graph::{Codec, Graph, Metadata},
+    siteinfo, wikidata, Wiki,
+};
+use anyhow::Context as _;
+use lasso::{Rodeo, Spur};
+use std::{
+    collections::HashMap,
+    io::BufRead,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use wikigraph::dump;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// `page` table dump, such as `enwiki-20240601-page.sql.gz`
+    #[arg(long, value_name = "FILE")]
+    page: PathBuf,
+
+    /// `pagelinks` table dump, such as `enwiki-20240601-pagelinks.sql.gz`
+    #[arg(long, value_name = "FILE")]
+    pagelinks: PathBuf,
+
+    /// `linktarget` table dump, which `pagelinks` dumps from 2024 on give their targets by
+    #[arg(long, value_name = "FILE")]
+    linktarget: Option<PathBuf>,
+
+    /// `redirect` table dump, giving the target of every redirect [default: the only link of
+    /// each redirect page]
+    #[arg(long, value_name = "FILE")]
+    redirect: Option<PathBuf>,
+
+    /// `page_props` table dump, giving each page its Wikidata item, as with `parse --page-props`
+    #[arg(long, value_name = "FILE")]
+    page_props: Option<PathBuf>,
+
+    /// Namespace number of the pages to keep (repeatable), as with `parse --namespace`
+    #[arg(long = "namespace", value_name = "NS", default_values_t = [0])]
+    namespaces: Vec<i64>,
+
+    /// Where to save the graph
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// zstd-compress the graph file, which makes it a few times smaller
+    #[arg(long)]
+    compress_graph: bool,
+
+    /// Store the titles, links, and backlinks of the graph file in compressed blocks, as with
+    /// `parse --graph-codec`
+    #[arg(
+        long,
+        value_name = "CODEC",
+        value_enum,
+        default_value_t,
+        conflicts_with = "compress_graph"
+    )]
+    graph_codec: Codec,
+
+    /// Leave the backlinks out of the graph file, as with `parse --no-backlinks`
+    #[arg(long)]
+    no_backlinks: bool,
+}
+
+pub fn run(args: &Args) {
+    let mut rodeo = Rodeo::new();
+    let mut wiki = Wiki::default();
+    let pages = read_pages(args, &mut rodeo, &mut wiki)
+        .with_context(|| format!("Failed to read {}", args.page.display()))
+        .unwrap();
+    read_links(args, &pages, &mut rodeo, &mut wiki)
+        .with_context(|| format!("Failed to read {}", args.pagelinks.display()))
+        .unwrap();
+    match &args.redirect {
+        Some(path) => read_redirects(args, path, &pages, &mut rodeo, &mut wiki)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .unwrap(),
+        None => {
+            for &(page, redirect) in pages.values() {
+                if let (true, Some(links)) = (redirect, wiki.links.get(&page)) {
+                    if let (1, Some(&target)) = (links.len(), links.iter().next()) {
+                        wiki.redirects.insert(page, target);
+                    }
+                }
+            }
+        }
+    }
+    wiki.resolve_redirects();
+    if let Some(path) = &args.page_props {
+        wikidata::apply(path, &mut wiki)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .unwrap();
+    }
+
+    let mut graph = Graph::new(&rodeo, &wiki, Metadata::current(Some(&args.pagelinks)))
+        .context("Failed to build graph")
+        .unwrap();
+    graph.set_compressed(args.compress_graph);
+    graph.set_codec(args.graph_codec);
+    graph.set_store_backlinks(!args.no_backlinks);
+    graph
+        .save(&args.output)
+        .context("Failed to save graph")
+        .unwrap();
+
+    println!(
+        "{} nodes and {} links",
+        graph.node_count(),
+        graph.edge_count()
+    );
+}
+
+/// Intern the pages of `--namespace` in the `page` table, and return each one's node and
+/// whether it is a redirect by page ID.
+fn read_pages(
+    args: &Args,
+    rodeo: &mut Rodeo,
+    wiki: &mut Wiki,
+) -> anyhow::Result<HashMap<u64, (Spur, bool)>> {
+    let mut pages = HashMap::new();
+    let columns = [
+        "page_id",
+        "page_namespace",
+        "page_title",
+        "page_is_redirect",
+    ];
+    read_rows(&args.page, "page", &columns, |row| {
+        let namespace = number(row[1])?;
+        if !args.namespaces.contains(&namespace) {
+            return Ok(());
+        }
+        let id = number(row[0])?;
+        let page = rodeo.get_or_intern(title(namespace, text(row[2])?));
+        wiki.namespaces.insert(page, namespace);
+        wiki.page_ids.insert(page, id);
+        wiki.links.entry(page).or_default();
+        pages.insert(id, (page, number::<u8>(row[3])? != 0));
+        Ok(())
+    })?;
+    tracing::info!("Read {} pages", pages.len());
+    Ok(pages)
+}
+
+/// Add the links of the `pagelinks` table between pages of `--namespace`, reading their targets
+/// from `--linktarget` if the table gives them by ID.
+fn read_links(
+    args: &Args,
+    pages: &HashMap<u64, (Spur, bool)>,
+    rodeo: &mut Rodeo,
+    wiki: &mut Wiki,
+) -> anyhow::Result<()> {
+    let mut links = 0_u64;
+    if columns(&args.pagelinks, "pagelinks")?
+        .iter()
+        .any(|column| column == "pl_target_id")
+    {
+        let path = args.linktarget.as_deref().with_context(|| {
+            format!(
+                "{} gives link targets by `pl_target_id`, so it needs the `linktarget` table \
+                 too (see `--linktarget`)",
+                args.pagelinks.display()
+            )
+        })?;
+        let targets = read_link_targets(args, path, rodeo)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        read_rows(
+            &args.pagelinks,
+            "pagelinks",
+            &["pl_from", "pl_target_id"],
+            |row| {
+                let source = pages.get(&number(row[0])?);
+                if let (Some(&(source, _)), Some(&target)) = (source, targets.get(&number(row[1])?))
+                {
+                    wiki.links.entry(source).or_default().insert(target);
+                    links += 1;
+                }
+                Ok(())
+            },
+        )?;
+    } else {
+        let columns = ["pl_from", "pl_namespace", "pl_title"];
+        read_rows(&args.pagelinks, "pagelinks", &columns, |row| {
+            let namespace = number(row[1])?;
+            let Some(&(source, _)) = pages.get(&number(row[0])?) else {
+                return Ok(());
+            };
+            if args.namespaces.contains(&namespace) {
+                let target = rodeo.get_or_intern(title(namespace, text(row[2])?));
+                wiki.links.entry(source).or_default().insert(target);
+                links += 1;
+            }
+            Ok(())
+        })?;
+    }
+    tracing::info!("Read {links} links");
+    Ok(())
+}
+
+/// The targets of the `linktarget` table in `--namespace`, interned, by ID.
+fn read_link_targets(
+    args: &Args,
+    path: &Path,
+    rodeo: &mut Rodeo,
+) -> anyhow::Result<HashMap<u64, Spur>> {
+    let mut targets = HashMap::new();
+    let columns = ["lt_id", "lt_namespace", "lt_title"];
+    read_rows(path, "linktarget", &columns, |row| {
+        let namespace = number(row[1])?;
+        if args.namespaces.contains(&namespace) {
+            let target = rodeo.get_or_intern(title(namespace, text(row[2])?));
+            targets.insert(number(row[0])?, target);
+        }
+        Ok(())
+    })?;
+    Ok(targets)
+}
+
+/// Record the targets of the redirects of the `redirect` table, leaving out those to other
+/// wikis and outside `--namespace`.
+fn read_redirects(
+    args: &Args,
+    path: &Path,
+    pages: &HashMap<u64, (Spur, bool)>,
+    rodeo: &mut Rodeo,
+    wiki: &mut Wiki,
+) -> anyhow::Result<()> {
+    let columns = ["rd_from", "rd_namespace", "rd_title", "rd_interwiki"];
+    read_rows(path, "redirect", &columns, |row| {
+        let namespace = number(row[1])?;
+        let Some(&(page, _)) = pages.get(&number(row[0])?) else {
+            return Ok(());
+        };
+        if args.namespaces.contains(&namespace) && row[3].is_none_or(<[u8]>::is_empty) {
+            let target = rodeo.get_or_intern(title(namespace, text(row[2])?));
+            wiki.redirects.insert(page, target);
+        }
+        Ok(())
+    })?;
+    tracing::info!("Read {} redirects", wiki.redirects.len());
+    Ok(())
+}
+
+/// The title of a page as the XML dumps give it: with spaces rather than underscores, and
+/// prefixed with the canonical name of its namespace, or its number if it has none.
+fn title(namespace: i64, title: &str) -> String {
+    let title = title.replace('_', " ");
+    match (namespace, siteinfo::english_name(namespace)) {
+        (0, _) => title,
+        (_, Some(prefix)) => format!("{prefix}:{title}"),
+        (_, None) => format!("{namespace}:{title}"),
+    }
+}
+
+pub fn text(field: Option<&[u8]>) -> anyhow::Result<&str> {
+    std::str::from_utf8(field.context("Unexpected NULL")?).context("Invalid UTF-8")
+}
+
+pub fn number<T: std::str::FromStr>(field: Option<&[u8]>) -> anyhow::Result<T> {
+    let text = text(field)?;
+    text.parse()
+        .map_err(|_| anyhow::anyhow!("Invalid number '{text}'"))
+}
+
+/// The columns of `table` in the mysqldump file at `path`, reading no further than its
+/// `CREATE TABLE` statement.
+fn columns(path: &Path, table: &str) -> anyhow::Result<Vec<String>> {
+    let mut input = dump::open_decompressed(path)?;
+    let create = format!("CREATE TABLE `{table}` (");
+    let mut line = Vec::new();
+    while input.read_until(b'\n', &mut line)? > 0 {
+        if line.starts_with(create.as_bytes()) {
+            return read_columns(&mut input, &mut line);
+        }
+        line.clear();
+    }
+    anyhow::bail!("No `CREATE TABLE` statement for `{table}`")
+}
+
+/// The column names of the `CREATE TABLE` statement whose first line `input` has just read.
+fn read_columns(input: &mut impl BufRead, line: &mut Vec<u8>) -> anyhow::Result<Vec<String>> {
+    let mut columns = Vec::new();
+    loop {
+        line.clear();
+        anyhow::ensure!(
+            input.read_until(b'\n', line)? > 0,
+            "Unfinished `CREATE TABLE` statement"
+        );
+        let definition = line.trim_ascii_start();
+        if definition.starts_with(b")") {
+            return Ok(columns);
+        }
+        // Keys and constraints follow the columns, and don't start with a quoted name.
+        if let Some(rest) = definition.strip_prefix(b"`") {
+            let end = rest
+                .iter()
+                .position(|&byte| byte == b'`')
+                .context("Unterminated column name")?;
+            columns.push(String::from_utf8_lossy(&rest[..end]).into_owned());
+        }
+    }
+}
+
+/// Call `row` with the fields named `wanted` of every row of `table` in the mysqldump file at
+/// `path`, in that order, and `None` for `NULL`s.
+pub fn read_rows(
+    path: &Path,
+    table: &str,
+    wanted: &[&str],
+    mut row: impl FnMut(&[Option<&[u8]>]) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut input = dump::open_decompressed(path)?;
+    let create = format!("CREATE TABLE `{table}` (");
+    let insert = format!("INSERT INTO `{table}` VALUES ");
+    let mut positions = None;
+    let mut line = Vec::new();
+    let mut number = 0;
+    let mut tuple = Tuple::default();
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        number += 1;
+        if line.starts_with(create.as_bytes()) {
+            let columns = read_columns(&mut input, &mut line)?;
+            let found: anyhow::Result<Vec<usize>> = wanted
+                .iter()
+                .map(|&name| {
+                    columns
+                        .iter()
+                        .position(|column| column == name)
+                        .with_context(|| format!("No `{name}` column in table `{table}`"))
+                })
+                .collect();
+            positions = Some(found?);
+        } else if let Some(mut values) = line.strip_prefix(insert.as_bytes()) {
+            let positions = positions
+                .as_deref()
+                .with_context(|| format!("Rows on line {number} before `CREATE TABLE`"))?;
+            while let Some(rest) = tuple
+                .parse(values)
+                .with_context(|| format!("Invalid row on line {number}"))?
+            {
+                let fields: Vec<_> = positions
+                    .iter()
+                    .map(|&position| tuple.field(position))
+                    .collect();
+                row(&fields).with_context(|| format!("Invalid row on line {number}"))?;
+                values = rest;
+            }
+        }
+    }
+    anyhow::ensure!(
+        positions.is_some(),
+        "No `CREATE TABLE` statement for `{table}`"
+    );
+    Ok(())
+}
+
+/// The fields of one row of an `INSERT` statement, unescaped.
+#[derive(Default)]
+struct Tuple {
+    bytes: Vec<u8>,
+    fields: Vec<Option<Range<usize>>>,
+}
+
+impl Tuple {
+    /// Read the row starting `values`, and return what follows it, or `None` at the end of the
+    /// statement.
+    fn parse<'a>(&mut self, values: &'a [u8]) -> anyhow::Result<Option<&'a [u8]>> {
+        self.bytes.clear();
+        self.fields.clear();
+        let mut rest = match values.trim_ascii_start() {
+            [b'(', rest @ ..] => rest,
+            [] | [b';', ..] => return Ok(None),
+            _ => anyhow::bail!("Expected `(`"),
+        };
+        loop {
+            let start = self.bytes.len();
+            if let [b'\'', quoted @ ..] = rest {
+                rest = self.unescape(quoted)?;
+                self.fields.push(Some(start..self.bytes.len()));
+            } else {
+                let end = rest
+                    .iter()
+                    .position(|&byte| byte == b',' || byte == b')')
+                    .context("Unterminated row")?;
+                let value = rest[..end].trim_ascii();
+                if value.eq_ignore_ascii_case(b"NULL") {
+                    self.fields.push(None);
+                } else {
+                    self.bytes.extend_from_slice(value);
+                    self.fields.push(Some(start..self.bytes.len()));
+                }
+                rest = &rest[end..];
+            }
+            match rest {
+                [b',', after @ ..] => rest = after,
+                [b')', after @ ..] => {
+                    return Ok(Some(after.strip_prefix(b",").unwrap_or(after)));
+                }
+                _ => anyhow::bail!("Expected `,` or `)`"),
+            }
+        }
+    }
+
+    /// Append the string starting `quoted`, just after its opening quote, with MySQL's
+    /// backslash escapes undone, and return what follows its closing quote.
+    fn unescape<'a>(&mut self, quoted: &'a [u8]) -> anyhow::Result<&'a [u8]> {
+        let mut bytes = quoted.iter().enumerate();
+        while let Some((i, &byte)) = bytes.next() {
+            match byte {
+                b'\'' => return Ok(&quoted[i + 1..]),
+                b'\\' => {
+                    let (_, &escaped) = bytes.next().context("Unterminated string")?;
+                    self.bytes.push(match escaped {
+                        b'0' => 0,
+                        b'b' => 8,
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'Z' => 26,
+                        other => other,
+                    });
+                }
+                _ => self.bytes.push(byte),
+            }
+        }
+        anyhow::bail!("Unterminated string")
+    }
+
+    fn field(&self, position: usize) -> Option<&[u8]> {
+        self.fields
+            .get(position)
+            .cloned()
+            .flatten()
+            .map(|range| &self.bytes[range])
+    }
+}
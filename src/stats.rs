@@ -0,0 +1,882 @@
+//! Global statistics of a saved graph: degree distributions, weakly connected components, and
+//! optionally PageRank, estimated neighbourhood sizes, and the distribution of distances
+//! between pages. They are slow to compute on a full wiki, so `stats` stores them in the graph
+//! file, along with a fingerprint of the graph they describe, and later runs (and `query`, and
+//! `pagerank`) read them back instead.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::{read_u32, read_u64, Adjacency as _, Direction, Graph},
+    hyperball::{self, Neighbourhoods},
+    small, workspace,
+};
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Default PageRank damping factor and number of iterations.
+pub const DAMPING: f64 = 0.85;
+pub const ITERATIONS: u32 = 50;
+
+#[derive(clap::Args)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Also rank pages by PageRank, listing the top ones
+    #[arg(long)]
+    pagerank: bool,
+
+    /// Probability of following a link rather than jumping to a random page
+    #[arg(long, default_value_t = DAMPING)]
+    damping: f64,
+
+    /// Number of PageRank iterations
+    #[arg(long, value_name = "N", default_value_t = ITERATIONS)]
+    iterations: u32,
+
+    /// Number of top-ranked pages to list
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    top: usize,
+
+    /// Also estimate how many pages are within 1 to N (default 3) links of every page, by
+    /// HyperBall, listing the pages with the highest harmonic centrality among them
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "3"
+    )]
+    neighbourhoods: Option<u32>,
+
+    /// HyperLogLog precision of `--neighbourhoods`: counters have 2^P registers, using that many
+    /// bytes per page, and estimates are off by about 104/sqrt(2^P) percent
+    #[arg(
+        long,
+        value_name = "P",
+        default_value_t = hyperball::PRECISION,
+        value_parser = clap::value_parser!(u8).range(4..=16),
+        requires = "neighbourhoods"
+    )]
+    hll_precision: u8,
+
+    /// Also estimate how many clicks apart pages are, by breadth-first searches from a random
+    /// sample of N pages (default 1000), as in the "six degrees" studies. Only pages with links
+    /// count, as sources and as destinations.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "1000"
+    )]
+    distances: Option<usize>,
+
+    /// Seed for picking the pages to search from; the same seed picks the same pages
+    #[arg(long, default_value_t = 0, requires = "distances")]
+    seed: u64,
+
+    /// Which links the searches for `--distances` and the neighbourhoods of `--neighbourhoods`
+    /// follow. Components are weakly connected, so they don't depend on it.
+    #[arg(long, value_enum, default_value_t)]
+    direction: Direction,
+
+    /// Also find the strongly connected components, whose pages all reach each other by
+    /// following links, by Tarjan's algorithm
+    #[arg(long)]
+    strong: bool,
+
+    /// Write the weak component of every node, and its strong one with `--strong`, to this CSV
+    /// file, as `id,title,component` and `strong_component`. Components are numbered from 0 by
+    /// decreasing size.
+    #[arg(long, value_name = "FILE")]
+    components: Option<PathBuf>,
+
+    /// Recompute even if the graph file has statistics stored
+    #[arg(long)]
+    recompute: bool,
+
+    /// Don't store newly computed statistics in the graph file
+    #[arg(long)]
+    no_save: bool,
+
+    /// Count neighbourhoods exactly rather than by HyperBall, and measure `--distances` from
+    /// every page rather than a sample, as on graphs of at most 10000 pages anyway
+    #[arg(long)]
+    demo: bool,
+
+    /// Give up on computing statistics after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+pub struct Stats {
+    /// `Graph::fingerprint` of the graph they were computed from.
+    pub fingerprint: u32,
+    /// (degree, number of nodes with it), by increasing degree.
+    pub in_degrees: Vec<(u32, u64)>,
+    pub out_degrees: Vec<(u32, u64)>,
+    /// Component of each node, numbered by decreasing size.
+    pub components: Vec<u32>,
+    pub pagerank: Option<PageRank>,
+    pub neighbourhoods: Option<Neighbourhoods>,
+}
+
+pub struct PageRank {
+    pub damping: f64,
+    pub iterations: u32,
+    pub scores: Vec<f64>,
+}
+
+/// How `Stats` are stored, besides the per-node arrays.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    fingerprint: u32,
+    in_degrees: Vec<(u32, u64)>,
+    out_degrees: Vec<(u32, u64)>,
+    component_count: u32,
+    pagerank: Option<(f64, u32)>,
+    /// Absent from statistics stored before neighbourhoods were.
+    neighbourhoods: Option<hyperball::Params>,
+}
+
+impl Stats {
+    pub fn compute(
+        graph: &Graph,
+        pagerank: Option<(f64, u32)>,
+        neighbourhoods: Option<hyperball::Params>,
+        cancel: &Cancel,
+    ) -> Result<Self, Cancelled> {
+        let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+        let histogram = |degree: &dyn Fn(u32) -> u32| {
+            let mut counts = std::collections::BTreeMap::new();
+            for node in nodes.clone() {
+                *counts.entry(degree(node)).or_insert(0) += 1;
+            }
+            counts.into_iter().collect()
+        };
+        let pagerank = pagerank
+            .map(|(damping, iterations)| {
+                Ok(PageRank {
+                    damping,
+                    iterations,
+                    scores: pagerank_scores(graph, damping, iterations, cancel)?,
+                })
+            })
+            .transpose()?;
+        let neighbourhoods = neighbourhoods
+            .map(|params| Neighbourhoods::compute(graph, params, cancel))
+            .transpose()?;
+        Ok(Self {
+            fingerprint: graph.fingerprint(),
+            in_degrees: histogram(&|node| graph.in_degree(node)),
+            out_degrees: histogram(&|node| graph.out_degree(node)),
+            components: components(graph),
+            pagerank,
+            neighbourhoods,
+        })
+    }
+
+    pub fn component_count(&self) -> u32 {
+        self.components.iter().max().map_or(0, |&max| max + 1)
+    }
+
+    /// Write as a length-prefixed JSON summary, then the component of each node, then the
+    /// PageRank of each node and the neighbourhood sizes of each node if computed.
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        let header = Header {
+            fingerprint: self.fingerprint,
+            in_degrees: self.in_degrees.clone(),
+            out_degrees: self.out_degrees.clone(),
+            component_count: self.component_count(),
+            pagerank: self
+                .pagerank
+                .as_ref()
+                .map(|pagerank| (pagerank.damping, pagerank.iterations)),
+            neighbourhoods: self
+                .neighbourhoods
+                .as_ref()
+                .map(|neighbourhoods| neighbourhoods.params),
+        };
+        let header = serde_json::to_vec(&header)?;
+        writer.write_all(&u32::try_from(header.len())?.to_le_bytes())?;
+        writer.write_all(&header)?;
+        for component in &self.components {
+            writer.write_all(&component.to_le_bytes())?;
+        }
+        if let Some(pagerank) = &self.pagerank {
+            for score in &pagerank.scores {
+                writer.write_all(&score.to_bits().to_le_bytes())?;
+            }
+        }
+        if let Some(neighbourhoods) = &self.neighbourhoods {
+            for size in &neighbourhoods.sizes {
+                writer.write_all(&size.to_bits().to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(reader: &mut impl Read, node_count: usize) -> anyhow::Result<Self> {
+        let len = read_u32(reader)?;
+        let mut header = Vec::new();
+        reader.take(u64::from(len)).read_to_end(&mut header)?;
+        anyhow::ensure!(header.len() == len as usize, "Unexpected end of file");
+        let header: Header = serde_json::from_slice(&header).context("Invalid statistics")?;
+        let components = (0..node_count)
+            .map(|_| read_u32(reader))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        anyhow::ensure!(
+            components
+                .iter()
+                .all(|&component| component < header.component_count),
+            "Component IDs out of range"
+        );
+        let pagerank = header
+            .pagerank
+            .map(|(damping, iterations)| {
+                let scores = (0..node_count)
+                    .map(|_| read_u64(reader).map(f64::from_bits))
+                    .collect::<anyhow::Result<_>>()?;
+                anyhow::Ok(PageRank {
+                    damping,
+                    iterations,
+                    scores,
+                })
+            })
+            .transpose()?;
+        let neighbourhoods = header
+            .neighbourhoods
+            .map(|params| {
+                let sizes = (0..node_count * params.hops as usize)
+                    .map(|_| read_u32(reader).map(f32::from_bits))
+                    .collect::<anyhow::Result<_>>()?;
+                anyhow::Ok(Neighbourhoods { params, sizes })
+            })
+            .transpose()?;
+        Ok(Self {
+            fingerprint: header.fingerprint,
+            in_degrees: header.in_degrees,
+            out_degrees: header.out_degrees,
+            components,
+            pagerank,
+            neighbourhoods,
+        })
+    }
+}
+
+/// Weakly connected components, by union-find over the links.
+pub fn components(graph: &Graph) -> Vec<u32> {
+    fn root(parents: &mut [u32], mut node: u32) -> u32 {
+        while parents[node as usize] != node {
+            // Path halving.
+            let grandparent = parents[parents[node as usize] as usize];
+            parents[node as usize] = grandparent;
+            node = grandparent;
+        }
+        node
+    }
+
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    let mut parents: Vec<u32> = nodes.clone().collect();
+    for source in nodes.clone() {
+        for &target in graph.links(source) {
+            let (a, b) = (root(&mut parents, source), root(&mut parents, target));
+            if a != b {
+                parents[a.max(b) as usize] = a.min(b);
+            }
+        }
+    }
+
+    let roots: Vec<u32> = nodes.map(|node| root(&mut parents, node)).collect();
+    number_by_size(&roots)
+}
+
+/// Strongly connected components, by Tarjan's algorithm, with an explicit stack of the nodes
+/// being visited and how far through their links the visit is, so that long chains of links
+/// can't overflow the call stack.
+pub fn strong_components(graph: &Graph) -> Vec<u32> {
+    const UNSEEN: u32 = u32::MAX;
+    let n = graph.node_count();
+    let mut index = vec![UNSEEN; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut labels = vec![0; n];
+    let (mut next_index, mut next_label) = (0, 0);
+    let mut visits: Vec<(u32, usize)> = Vec::new();
+    for root in 0..u32::try_from(n).unwrap() {
+        if index[root as usize] != UNSEEN {
+            continue;
+        }
+        visits.push((root, 0));
+        while let Some(&(node, edge)) = visits.last() {
+            // Entering the node.
+            if index[node as usize] == UNSEEN {
+                index[node as usize] = next_index;
+                low[node as usize] = next_index;
+                next_index += 1;
+                on_stack[node as usize] = true;
+                stack.push(node);
+            }
+            if let Some(&target) = graph.links(node).get(edge) {
+                visits.last_mut().unwrap().1 += 1;
+                if index[target as usize] == UNSEEN {
+                    visits.push((target, 0));
+                } else if on_stack[target as usize] {
+                    low[node as usize] = low[node as usize].min(index[target as usize]);
+                }
+                continue;
+            }
+            visits.pop();
+            if let Some(&(parent, _)) = visits.last() {
+                low[parent as usize] = low[parent as usize].min(low[node as usize]);
+            }
+            if low[node as usize] == index[node as usize] {
+                while let Some(member) = stack.pop() {
+                    on_stack[member as usize] = false;
+                    labels[member as usize] = next_label;
+                    if member == node {
+                        break;
+                    }
+                }
+                next_label += 1;
+            }
+        }
+    }
+    number_by_size(&labels)
+}
+
+/// Renumber the components `labels` gives each node from 0, largest first, ties broken by
+/// their smallest node.
+fn number_by_size(labels: &[u32]) -> Vec<u32> {
+    let mut sizes = vec![0_u64; labels.len()];
+    let mut smallest = vec![u32::MAX; labels.len()];
+    for (node, &label) in (0..).zip(labels) {
+        sizes[label as usize] += 1;
+        smallest[label as usize] = smallest[label as usize].min(node);
+    }
+    let mut order: Vec<u32> = (0..)
+        .zip(&sizes)
+        .filter(|&(_, &size)| size > 0)
+        .map(|(label, _)| label)
+        .collect();
+    order.sort_by_key(|&label| {
+        (
+            std::cmp::Reverse(sizes[label as usize]),
+            smallest[label as usize],
+        )
+    });
+    let mut numbers = vec![0; labels.len()];
+    for (number, &label) in (0..).zip(&order) {
+        numbers[label as usize] = number;
+    }
+    labels
+        .iter()
+        .map(|&label| numbers[label as usize])
+        .collect()
+}
+
+/// Communities by label propagation, ignoring which way links point: every node starts in a
+/// community of its own, then in turn joins the community most of its neighbours are in,
+/// staying put on a tie with its own and otherwise taking the smallest. Nodes are visited in
+/// order, so the result is the same every run. Stops once a round moves no node, or after
+/// `rounds`, checking `cancel` before each round. Returns each node's community, named by one
+/// of its nodes.
+pub fn communities(graph: &Graph, rounds: u32, cancel: &Cancel) -> Result<Vec<u32>, Cancelled> {
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    let mut labels: Vec<u32> = nodes.clone().collect();
+    let mut counts: HashMap<u32, u32> = HashMap::new();
+    for _ in 0..rounds {
+        cancel.check()?;
+        let mut moved = false;
+        for node in nodes.clone() {
+            counts.clear();
+            for neighbour in graph.neighbours(node, Direction::Both) {
+                *counts.entry(labels[neighbour as usize]).or_default() += 1;
+            }
+            let current = labels[node as usize];
+            let Some(best) = counts
+                .iter()
+                .max_by_key(|&(&label, &count)| (count, label == current, std::cmp::Reverse(label)))
+                .map(|(&label, _)| label)
+            else {
+                continue;
+            };
+            if best != current {
+                labels[node as usize] = best;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    Ok(labels)
+}
+
+/// Power iteration, spreading the rank of pages without links evenly over all pages. Pages of
+/// a weighted graph pass their rank on in proportion to how many times they link to each
+/// target. Checks `cancel` before each iteration.
+#[allow(clippy::cast_precision_loss)]
+pub fn pagerank_scores(
+    graph: &Graph,
+    damping: f64,
+    iterations: u32,
+    cancel: &Cancel,
+) -> Result<Vec<f64>, Cancelled> {
+    let n = graph.node_count();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let nodes = 0..u32::try_from(n).unwrap();
+    let mut scores = vec![1.0 / n as f64; n];
+    let total_weights: Option<Vec<f64>> = graph.is_weighted().then(|| {
+        nodes
+            .clone()
+            .map(|node| {
+                graph
+                    .link_weights(node)
+                    .unwrap()
+                    .iter()
+                    .copied()
+                    .map(f64::from)
+                    .sum()
+            })
+            .collect()
+    });
+    for _ in 0..iterations {
+        cancel.check()?;
+        let dangling: f64 = nodes
+            .clone()
+            .filter(|&node| graph.out_degree(node) == 0)
+            .map(|node| scores[node as usize])
+            .sum();
+        let base = (1.0 - damping + damping * dangling) / n as f64;
+        if let Some(total_weights) = &total_weights {
+            scores = spread(graph, &scores, total_weights, base, damping);
+            continue;
+        }
+        scores = nodes
+            .clone()
+            .map(|node| {
+                let inflow: f64 = graph
+                    .backlinks(node)
+                    .iter()
+                    .map(|&source| scores[source as usize] / f64::from(graph.out_degree(source)))
+                    .sum();
+                base + damping * inflow
+            })
+            .collect();
+    }
+    Ok(scores)
+}
+
+/// One iteration of weighted PageRank, pushing each page's score along its links rather than
+/// pulling it over backlinks, which don't carry weights.
+fn spread(
+    graph: &Graph,
+    scores: &[f64],
+    total_weights: &[f64],
+    base: f64,
+    damping: f64,
+) -> Vec<f64> {
+    let mut next = vec![base; scores.len()];
+    for (source, (&score, &total)) in (0..).zip(scores.iter().zip(total_weights)) {
+        // The score of pages without links is in `base`.
+        if graph.out_degree(source) == 0 {
+            continue;
+        }
+        let weights = graph.link_weights(source).unwrap();
+        for (&target, &weight) in graph.links(source).iter().zip(weights) {
+            next[target as usize] += damping * score * f64::from(weight) / total;
+        }
+    }
+    next
+}
+
+pub fn run(args: &Args) {
+    let mut graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+
+    let wanted = args.pagerank.then_some((args.damping, args.iterations));
+    let small = small::graph(&graph, args.demo);
+    let wanted_neighbourhoods = args.neighbourhoods.map(|hops| hyperball::Params {
+        hops,
+        precision: args.hll_precision,
+        direction: args.direction,
+        exact: small,
+    });
+    let reusable = graph.stats().filter(|stats| {
+        !args.recompute
+            && wanted.is_none_or(|(damping, iterations)| {
+                stats.pagerank.as_ref().is_some_and(|pagerank| {
+                    pagerank.damping.to_bits() == damping.to_bits()
+                        && pagerank.iterations == iterations
+                })
+            })
+            && wanted_neighbourhoods.is_none_or(|params| {
+                stats
+                    .neighbourhoods
+                    .as_ref()
+                    .is_some_and(|neighbourhoods| neighbourhoods.params == params)
+            })
+    });
+    if reusable.is_some() {
+        tracing::info!("Using statistics stored in the graph file");
+    } else {
+        tracing::info!("Computing statistics");
+        // Keep a stored PageRank and neighbourhoods that weren't asked for this time.
+        let pagerank = wanted.or_else(|| {
+            graph.stats().and_then(|stats| {
+                stats
+                    .pagerank
+                    .as_ref()
+                    .map(|pagerank| (pagerank.damping, pagerank.iterations))
+            })
+        });
+        let neighbourhoods = wanted_neighbourhoods.or_else(|| {
+            graph.stats().and_then(|stats| {
+                stats
+                    .neighbourhoods
+                    .as_ref()
+                    .map(|neighbourhoods| neighbourhoods.params)
+            })
+        });
+        let stats = Stats::compute(&graph, pagerank, neighbourhoods, &cancel)
+            .context("Failed to compute statistics")
+            .unwrap();
+        graph.set_stats(stats);
+        if Graph::is_sqlite(&args.graph).unwrap_or(false) {
+            tracing::info!("Not storing statistics in an SQLite database");
+        } else if !args.no_save {
+            graph
+                .save(&args.graph)
+                .context("Failed to store statistics in graph")
+                .unwrap();
+        }
+    }
+    // Just stored, or checked to be present.
+    let stats = graph.stats().unwrap();
+
+    println!("Nodes: {}", graph.node_count());
+    println!("Links: {}", graph.edge_count());
+    print_degrees(&graph, stats);
+    let largest = stats
+        .components
+        .iter()
+        .filter(|&&component| component == 0)
+        .count();
+    println!(
+        "Weakly connected components: {} (largest has {largest} nodes)",
+        stats.component_count()
+    );
+    print_orphans(&graph);
+    let strong = args.strong.then(|| strong_components(&graph));
+    if let Some(strong) = &strong {
+        print_strong_components(&graph, strong);
+    }
+    if let Some(path) = &args.components {
+        write_components(path, &graph, &stats.components, strong.as_deref())
+            .context("Failed to write components")
+            .unwrap();
+    }
+    if let (true, Some(pagerank)) = (args.pagerank, &stats.pagerank) {
+        println!(
+            "PageRank (damping {}, {} iterations):",
+            pagerank.damping, pagerank.iterations
+        );
+        for (node, score) in top(pagerank, args.top) {
+            println!("{score:.6}\t{}", graph.title(node));
+        }
+    }
+    if let (Some(_), Some(neighbourhoods)) = (args.neighbourhoods, &stats.neighbourhoods) {
+        print_neighbourhoods(&graph, neighbourhoods, args.top);
+    }
+    if let Some(sources) = args.distances {
+        // Searching from every page is as quick as from a sample when there are few of them.
+        let sources = if small { graph.node_count() } else { sources };
+        print_distances(&graph, sources, args.seed, args.direction, &cancel);
+    }
+}
+
+/// Nodes without any links, and orphans: pages with links that no page links to. Redirects
+/// aren't orphans, since links go to their targets instead.
+fn print_orphans(graph: &Graph) {
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    let unlinked = nodes.filter(|&node| graph.in_degree(node) == 0);
+    let (singletons, orphans) = unlinked.fold((0, 0), |(singletons, orphans), node| {
+        if graph.out_degree(node) == 0 {
+            (singletons + 1, orphans)
+        } else if graph.redirect(node).is_none() {
+            (singletons, orphans + 1)
+        } else {
+            (singletons, orphans)
+        }
+    });
+    println!("Singletons: {singletons} nodes without links in or out");
+    println!("Orphans: {orphans} pages with links that no page links to");
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn print_strong_components(graph: &Graph, components: &[u32]) {
+    let count = components.iter().max().map_or(0, |&max| max + 1);
+    let mut sizes = vec![0_u64; count as usize];
+    for &component in components {
+        sizes[component as usize] += 1;
+    }
+    let largest = sizes.first().copied().unwrap_or(0);
+    println!(
+        "Strongly connected components: {count} (largest has {largest} nodes, {:.1}% of all; \
+         {} are single nodes)",
+        100.0 * largest as f64 / graph.node_count().max(1) as f64,
+        sizes.iter().filter(|&&size| size == 1).count()
+    );
+}
+
+/// Write `id,title,component` and `strong_component` if given for every node to `path`.
+fn write_components(
+    path: &Path,
+    graph: &Graph,
+    weak: &[u32],
+    strong: Option<&[u32]>,
+) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    if strong.is_some() {
+        writer.write_record(["id", "title", "component", "strong_component"])?;
+    } else {
+        writer.write_record(["id", "title", "component"])?;
+    }
+    for (node, &component) in (0..).zip(weak) {
+        let mut record = vec![
+            node.to_string(),
+            graph.title(node).to_owned(),
+            component.to_string(),
+        ];
+        if let Some(strong) = strong {
+            record.push(strong[node as usize].to_string());
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The mean, median, and maximum in- and out-degree, with a page of the maximum degree.
+fn print_degrees(graph: &Graph, stats: &Stats) {
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    for (name, histogram, degree) in [
+        (
+            "In-degree",
+            &stats.in_degrees,
+            Graph::in_degree as fn(&Graph, u32) -> u32,
+        ),
+        ("Out-degree", &stats.out_degrees, Graph::out_degree),
+    ] {
+        let summary = summarize(histogram);
+        // The first node with the maximum degree, if any node has links.
+        let most = nodes
+            .clone()
+            .find(|&node| summary.max > 0 && degree(graph, node) == summary.max)
+            .map(|node| format!(" ({})", graph.title(node)))
+            .unwrap_or_default();
+        println!(
+            "{name}: mean {:.2}, median {}, max {}{most}",
+            summary.mean, summary.median, summary.max
+        );
+    }
+}
+
+/// Number of (source, destination) pairs at each distance from breadth-first searches out of
+/// `sources` in `direction`, counting only destinations for which `is_page` holds. Index 0 is
+/// unused. Checks `cancel` before each search.
+fn distance_counts(
+    graph: &Graph,
+    sources: &[u32],
+    direction: Direction,
+    is_page: &[bool],
+    cancel: &Cancel,
+) -> Result<Vec<u64>, Cancelled> {
+    let mut counts = vec![0];
+    let mut distances = vec![u32::MAX; graph.node_count()];
+    let mut frontier = std::collections::VecDeque::new();
+    for &source in sources {
+        cancel.check()?;
+        distances.fill(u32::MAX);
+        distances[source as usize] = 0;
+        frontier.push_back(source);
+        while let Some(node) = frontier.pop_front() {
+            let distance = distances[node as usize] + 1;
+            for target in graph.neighbours(node, direction) {
+                if distances[target as usize] == u32::MAX {
+                    distances[target as usize] = distance;
+                    frontier.push_back(target);
+                    if is_page[target as usize] {
+                        let distance = distance as usize;
+                        if counts.len() <= distance {
+                            counts.resize(distance + 1, 0);
+                        }
+                        counts[distance] += 1;
+                    }
+                }
+            }
+        }
+    }
+    Ok(counts)
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn print_distances(
+    graph: &Graph,
+    sources: usize,
+    seed: u64,
+    direction: Direction,
+    cancel: &Cancel,
+) {
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    let is_page: Vec<bool> = nodes
+        .clone()
+        .map(|node| graph.out_degree(node) > 0)
+        .collect();
+    let pages = is_page.iter().filter(|&&is_page| is_page).count();
+    let sources = crate::sample::pick_nodes(
+        seed,
+        sources,
+        nodes
+            .filter(|&node| is_page[node as usize])
+            .map(|node| (node, graph.title(node))),
+    );
+    let counts = distance_counts(graph, &sources, direction, &is_page, cancel)
+        .context("Failed to compute distances")
+        .unwrap();
+
+    let pairs = (sources.len() * pages.saturating_sub(1)) as f64;
+    let reachable: u64 = counts.iter().sum();
+    let total: u64 = (0..).zip(&counts).map(|(distance, &n)| distance * n).sum();
+    println!("Distances from {} of {pages} pages:", sources.len());
+    if reachable == 0 {
+        println!("No page reaches another");
+        return;
+    }
+    let mut within = 0;
+    for (distance, &n) in counts.iter().enumerate().skip(1) {
+        within += n;
+        println!(
+            "Within {distance} click{}: {:.2}% of pairs",
+            if distance == 1 { "" } else { "s" },
+            100.0 * within as f64 / pairs
+        );
+    }
+    println!(
+        "Mean distance: {:.2} clicks; {:.2}% of pairs are connected; longest shortest path \
+         found: {} clicks",
+        total as f64 / reachable as f64,
+        100.0 * reachable as f64 / pairs,
+        counts.len() - 1
+    );
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn print_neighbourhoods(graph: &Graph, neighbourhoods: &Neighbourhoods, top: usize) {
+    let params = neighbourhoods.params;
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    if params.exact {
+        println!(
+            "Neighbourhoods (counted exactly, following {:?} links):",
+            params.direction
+        );
+    } else {
+        println!(
+            "Neighbourhoods (estimated with 2^{} registers, following {:?} links):",
+            params.precision, params.direction
+        );
+    }
+    for hops in 1..=params.hops {
+        let total: f64 = nodes
+            .clone()
+            .map(|node| neighbourhoods.size(node, hops).unwrap())
+            .sum();
+        println!(
+            "Within {hops} click{}: {:.1} pages on average",
+            if hops == 1 { "" } else { "s" },
+            total / graph.node_count().max(1) as f64
+        );
+    }
+    println!("Harmonic centrality within {} clicks:", params.hops);
+    let mut ranked: Vec<(u32, f64)> = nodes
+        .map(|node| (node, neighbourhoods.harmonic(node)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    for &(node, centrality) in ranked.iter().take(top) {
+        println!("{centrality:.1}\t{}", graph.title(node));
+    }
+}
+
+/// The PageRank that `stats --pagerank` stored in `graph`, if it was computed with `damping`
+/// and `iterations`.
+pub fn stored_pagerank(graph: &Graph, damping: f64, iterations: u32) -> Option<&PageRank> {
+    graph
+        .stats()
+        .and_then(|stats| stats.pagerank.as_ref())
+        .filter(|pagerank| {
+            pagerank.damping.to_bits() == damping.to_bits() && pagerank.iterations == iterations
+        })
+}
+
+/// The `limit` highest-ranked nodes with their scores, best first.
+pub fn top(pagerank: &PageRank, limit: usize) -> Vec<(u32, f64)> {
+    top_scores(&pagerank.scores, limit)
+}
+
+/// The `limit` nodes with the highest `scores`, best first, ties broken by the smallest node.
+/// Only those are sorted, which is quicker than sorting them all when there are few.
+pub fn top_scores(scores: &[f64], limit: usize) -> Vec<(u32, f64)> {
+    let order = |a: &(u32, f64), b: &(u32, f64)| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0));
+    let mut ranked: Vec<(u32, f64)> = (0..).zip(scores.iter().copied()).collect();
+    if limit < ranked.len() {
+        ranked.select_nth_unstable_by(limit, order);
+        ranked.truncate(limit);
+    }
+    ranked.sort_unstable_by(order);
+    ranked
+}
+
+struct Summary {
+    mean: f64,
+    median: u32,
+    max: u32,
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn summarize(histogram: &[(u32, u64)]) -> Summary {
+    let count: u64 = histogram.iter().map(|&(_, n)| n).sum();
+    let total: u64 = histogram
+        .iter()
+        .map(|&(degree, n)| u64::from(degree) * n)
+        .sum();
+    let mut seen = 0;
+    let median = histogram
+        .iter()
+        .find(|&&(_, n)| {
+            seen += n;
+            seen * 2 > count
+        })
+        .map_or(0, |&(degree, _)| degree);
+    Summary {
+        mean: if count == 0 {
+            0.0
+        } else {
+            total as f64 / count as f64
+        },
+        median,
+        max: histogram.last().map_or(0, |&(degree, _)| degree),
+    }
+}
@@ -0,0 +1,87 @@
+//! `parse --format`: the pages of a dump with their links, written as soon as each is parsed
+//! rather than from a finished graph, so that a dump of any size can be turned into a table for
+//! jq, DuckDB, or Spark, or into triples for a SPARQL store, without the graph being held in
+//! memory. Links are written as the pages
+//! have them, without following redirects, which may only be read later.
+
+pub mod jsonl;
+pub mod ntriples;
+pub mod parquet;
+
+use crate::ParseArgs;
+use anyhow::Context as _;
+use lasso::{Rodeo, Spur};
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, Write},
+};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// One JSON object per page, as in `{"title": ..., "ns": ..., "links": [...]}`, with the
+    /// target of redirects as `redirect`
+    Jsonl,
+    /// `nodes.parquet` of `id`, `title`, and `ns`, and `edges.parquet` of `source_id`,
+    /// `target_id`, and with `--edge-weights`, `weight`, in a directory
+    Parquet,
+    /// RDF triples of DBpedia's `wikiPageWikiLink` and `wikiPageRedirects` between the pages'
+    /// DBpedia resources
+    Ntriples,
+}
+
+pub enum Writer {
+    Jsonl(jsonl::Writer),
+    Parquet(Box<parquet::Writer>),
+    Ntriples(ntriples::Writer),
+}
+
+impl Writer {
+    /// Write `format` to `--output`, or to standard output without it, weighing links with
+    /// `--edge-weights`.
+    pub fn create(format: Format, args: &ParseArgs) -> anyhow::Result<Self> {
+        let output = || -> io::Result<Box<dyn Write + Send>> {
+            Ok(match &args.output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            })
+        };
+        Ok(match format {
+            Format::Jsonl => Self::Jsonl(jsonl::Writer::new(output()?)),
+            Format::Parquet => {
+                let dir = args
+                    .output
+                    .as_ref()
+                    .context("Parquet needs `--output DIR`")?;
+                Self::Parquet(Box::new(parquet::Writer::create(dir, args.edge_weights)?))
+            }
+            Format::Ntriples => Self::Ntriples(ntriples::Writer::new(output()?, &args.input)),
+        })
+    }
+
+    /// Write the page titled `title`, interned as `id`, linking to `targets`.
+    pub fn add_page(
+        &mut self,
+        rodeo: &mut Rodeo,
+        title: &str,
+        id: Spur,
+        namespace: i64,
+        redirect: Option<&str>,
+        targets: &[Cow<str>],
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Jsonl(writer) => writer.add_page(title, namespace, redirect, targets),
+            Self::Parquet(writer) => writer.add_page(rodeo, id, namespace, targets),
+            Self::Ntriples(writer) => writer.add_page(title, redirect, targets),
+        }
+    }
+
+    /// Finish the output, and return how many pages were written.
+    pub fn finish(self, rodeo: &Rodeo) -> anyhow::Result<u64> {
+        match self {
+            Self::Jsonl(writer) => writer.finish(),
+            Self::Parquet(writer) => writer.finish(rodeo),
+            Self::Ntriples(writer) => writer.finish(),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Each page as a line of JSON with its title, namespace, and link targets, to pipe into jq or
+//! DuckDB.
+
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    io::{BufWriter, Write},
+};
+
+#[derive(Serialize)]
+struct Line<'a> {
+    title: &'a str,
+    ns: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redirect: Option<&'a str>,
+    links: &'a [Cow<'a, str>],
+}
+
+pub struct Writer {
+    inner: BufWriter<Box<dyn Write + Send>>,
+    pages: u64,
+}
+
+impl Writer {
+    pub fn new(output: Box<dyn Write + Send>) -> Self {
+        Self {
+            inner: BufWriter::new(output),
+            pages: 0,
+        }
+    }
+
+    /// Write the line of a page linking to `targets`.
+    pub fn add_page(
+        &mut self,
+        title: &str,
+        namespace: i64,
+        redirect: Option<&str>,
+        targets: &[Cow<str>],
+    ) -> anyhow::Result<()> {
+        let line = Line {
+            title,
+            ns: namespace,
+            redirect,
+            links: targets,
+        };
+        serde_json::to_writer(&mut self.inner, &line)?;
+        self.inner.write_all(b"\n")?;
+        self.pages += 1;
+        Ok(())
+    }
+
+    /// Flush the lines, and return how many pages were written.
+    pub fn finish(mut self) -> anyhow::Result<u64> {
+        self.inner.flush()?;
+        Ok(self.pages)
+    }
+}
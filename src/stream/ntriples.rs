@@ -0,0 +1,119 @@
+//! Links as RDF in N-Triples, with pages named by their DBpedia resource IRIs and linked by
+//! DBpedia's ontology properties, so that the triples join with DBpedia's own in a SPARQL store.
+//! As in DBpedia's IRIs, letters outside ASCII are kept as they are, and only what an IRI can't
+//! hold, or would read as its query or fragment, is percent-encoded.
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt::Write as _,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+const WIKI_LINK: &str = "<http://dbpedia.org/ontology/wikiPageWikiLink>";
+const REDIRECTS: &str = "<http://dbpedia.org/ontology/wikiPageRedirects>";
+
+pub struct Writer {
+    inner: BufWriter<Box<dyn Write + Send>>,
+    /// Where resource IRIs start, such as `http://dbpedia.org/resource/`.
+    base: String,
+    pages: u64,
+}
+
+impl Writer {
+    /// Write to `output`, naming the resources of the pages of `dump` after its language.
+    pub fn new(output: Box<dyn Write + Send>, dump: &Path) -> Self {
+        Self {
+            inner: BufWriter::new(output),
+            base: resource_base(dump),
+            pages: 0,
+        }
+    }
+
+    /// Write a `wikiPageWikiLink` triple for each distinct link of the page titled `title`, each
+    /// in the order first linked, and a `wikiPageRedirects` one instead for its redirect target.
+    pub fn add_page(
+        &mut self,
+        title: &str,
+        redirect: Option<&str>,
+        targets: &[Cow<str>],
+    ) -> anyhow::Result<()> {
+        let subject = self.iri(title);
+        if let Some(redirect) = redirect {
+            writeln!(self.inner, "{subject} {REDIRECTS} {} .", self.iri(redirect))?;
+        }
+        let mut seen = HashSet::new();
+        for target in targets {
+            if Some(target.as_ref()) == redirect || !seen.insert(target) {
+                continue;
+            }
+            writeln!(self.inner, "{subject} {WIKI_LINK} {} .", self.iri(target))?;
+        }
+        self.pages += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<u64> {
+        self.inner.flush()?;
+        Ok(self.pages)
+    }
+
+    /// The IRI of the resource for `title`, enclosed in angle brackets.
+    fn iri(&self, title: &str) -> String {
+        let mut iri = format!("<{}", self.base);
+        for c in title.chars() {
+            match c {
+                ' ' => iri.push('_'),
+                // Not allowed in an N-Triples IRI, or meaning something else in one.
+                '\0'..='\x1f'
+                | '"'
+                | '#'
+                | '%'
+                | '<'
+                | '>'
+                | '?'
+                | '['
+                | '\\'
+                | ']'
+                | '^'
+                | '`'
+                | '{'
+                | '|'
+                | '}'
+                | '\x7f' => {
+                    let mut bytes = [0; 4];
+                    for byte in c.encode_utf8(&mut bytes).bytes() {
+                        write!(iri, "%{byte:02X}").unwrap();
+                    }
+                }
+                c => iri.push(c),
+            }
+        }
+        iri.push('>');
+        iri
+    }
+}
+
+/// `http://dbpedia.org/resource/` for English Wikipedia and dumps from elsewhere, and the
+/// resources of DBpedia's chapter for the language, such as `http://de.dbpedia.org/resource/`,
+/// for dumps named like `dewiki-20240601-pages-articles.xml.bz2`.
+fn resource_base(dump: &Path) -> String {
+    let name = dump
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match name.split_once("wiki-") {
+        Some((language, _))
+            if language != "en"
+                && !language.is_empty()
+                && language.chars().all(|c| c.is_ascii_lowercase() || c == '_') =>
+        {
+            format!(
+                "http://{}.dbpedia.org/resource/",
+                language.replace('_', "-")
+            )
+        }
+        _ => String::from("http://dbpedia.org/resource/"),
+    }
+}
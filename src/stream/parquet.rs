@@ -0,0 +1,218 @@
+//! A nodes table and an edges table as Parquet files, for data frames and query engines that
+//! read columns. Nodes are identified by the key of their interned title, so a page's ID is known
+//! as soon as it or a link to it is seen, and each table is written a row group at a time.
+//! Nodes that are only link targets, never parsed as pages, are listed at the end without a
+//! namespace.
+
+use lasso::{Key as _, Rodeo, Spur};
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::{self, File},
+    path::Path,
+    sync::Arc,
+};
+
+/// Rows buffered before they are written out as a row group.
+const ROW_GROUP: usize = 1 << 20;
+
+const NODES: &str = "message nodes {
+    REQUIRED INT64 id;
+    REQUIRED BYTE_ARRAY title (STRING);
+    OPTIONAL INT64 ns;
+}";
+const EDGES: &str = "message edges {
+    REQUIRED INT64 source_id;
+    REQUIRED INT64 target_id;
+}";
+const WEIGHTED_EDGES: &str = "message edges {
+    REQUIRED INT64 source_id;
+    REQUIRED INT64 target_id;
+    REQUIRED INT32 weight;
+}";
+
+pub struct Writer {
+    nodes: SerializedFileWriter<File>,
+    edges: SerializedFileWriter<File>,
+    /// Columns of the nodes not yet written, with just the pages' namespaces.
+    ids: Vec<i64>,
+    titles: Vec<ByteArray>,
+    namespaces: Vec<i64>,
+    sources: Vec<i64>,
+    targets: Vec<i64>,
+    /// Link counts, if links are weighed.
+    weights: Option<Vec<i32>>,
+    /// Whether each node has been written as a page.
+    written: Vec<bool>,
+    pages: u64,
+}
+
+impl Writer {
+    /// Create `nodes.parquet` and `edges.parquet` in `dir`, with a `weight` column if `weights`.
+    pub fn create(dir: &Path, weights: bool) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let properties = Arc::new(
+            WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build(),
+        );
+        let create = |name: &str, schema: &str| -> anyhow::Result<_> {
+            Ok(SerializedFileWriter::new(
+                File::create(dir.join(name))?,
+                Arc::new(parse_message_type(schema)?),
+                Arc::clone(&properties),
+            )?)
+        };
+        Ok(Self {
+            nodes: create("nodes.parquet", NODES)?,
+            edges: create(
+                "edges.parquet",
+                if weights { WEIGHTED_EDGES } else { EDGES },
+            )?,
+            ids: Vec::new(),
+            titles: Vec::new(),
+            namespaces: Vec::new(),
+            sources: Vec::new(),
+            targets: Vec::new(),
+            weights: weights.then(Vec::new),
+            written: Vec::new(),
+            pages: 0,
+        })
+    }
+
+    /// Add the page `id` in `namespace` and its links to `targets`, each distinct target once,
+    /// weighing as many links as the page has to it.
+    pub fn add_page(
+        &mut self,
+        rodeo: &mut Rodeo,
+        id: Spur,
+        namespace: i64,
+        targets: &[Cow<str>],
+    ) -> anyhow::Result<()> {
+        let index = id.into_usize();
+        if index >= self.written.len() {
+            self.written.resize(index + 1, false);
+        }
+        // A page repeated in the dump has its links written again, but is one node.
+        if !self.written[index] {
+            self.written[index] = true;
+            self.ids.push(to_i64(index));
+            self.titles.push(ByteArray::from(rodeo.resolve(&id)));
+            self.namespaces.push(namespace);
+            if self.ids.len() >= ROW_GROUP {
+                self.write_nodes(false)?;
+            }
+        }
+
+        let mut counts: Vec<(Spur, i32)> = Vec::new();
+        let mut positions: HashMap<Spur, usize> = HashMap::new();
+        for target in targets {
+            let target = rodeo.get_or_intern(target);
+            let position = *positions.entry(target).or_insert_with(|| {
+                counts.push((target, 0));
+                counts.len() - 1
+            });
+            counts[position].1 += 1;
+        }
+        for (target, count) in counts {
+            self.sources.push(to_i64(index));
+            self.targets.push(to_i64(target.into_usize()));
+            if let Some(weights) = &mut self.weights {
+                weights.push(count);
+            }
+        }
+        if self.sources.len() >= ROW_GROUP {
+            self.write_edges()?;
+        }
+        self.pages += 1;
+        Ok(())
+    }
+
+    /// Write the rows left, then the nodes that were only linked to, and return how many pages
+    /// were added.
+    pub fn finish(mut self, rodeo: &Rodeo) -> anyhow::Result<u64> {
+        self.write_edges()?;
+        self.write_nodes(false)?;
+        self.written.resize(rodeo.len(), false);
+        for (key, title) in rodeo.iter() {
+            let index = key.into_usize();
+            if !self.written[index] {
+                self.ids.push(to_i64(index));
+                self.titles.push(ByteArray::from(title));
+                if self.ids.len() >= ROW_GROUP {
+                    self.write_nodes(true)?;
+                }
+            }
+        }
+        self.write_nodes(true)?;
+        self.nodes.close()?;
+        self.edges.close()?;
+        Ok(self.pages)
+    }
+
+    /// Write the buffered nodes as a row group, the pages with their namespaces, or if
+    /// `linked_only`, without any.
+    fn write_nodes(&mut self, linked_only: bool) -> anyhow::Result<()> {
+        if self.ids.is_empty() {
+            return Ok(());
+        }
+        let levels = vec![i16::from(!linked_only); self.ids.len()];
+        let mut group = self.nodes.next_row_group()?;
+        let mut column = group.next_column()?.unwrap();
+        column
+            .typed::<Int64Type>()
+            .write_batch(&self.ids, None, None)?;
+        column.close()?;
+        let mut column = group.next_column()?.unwrap();
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&self.titles, None, None)?;
+        column.close()?;
+        let mut column = group.next_column()?.unwrap();
+        column
+            .typed::<Int64Type>()
+            .write_batch(&self.namespaces, Some(&levels), None)?;
+        column.close()?;
+        group.close()?;
+        self.ids.clear();
+        self.titles.clear();
+        self.namespaces.clear();
+        Ok(())
+    }
+
+    fn write_edges(&mut self) -> anyhow::Result<()> {
+        if self.sources.is_empty() {
+            return Ok(());
+        }
+        let mut group = self.edges.next_row_group()?;
+        for values in [&self.sources, &self.targets] {
+            let mut column = group.next_column()?.unwrap();
+            column
+                .typed::<Int64Type>()
+                .write_batch(values, None, None)?;
+            column.close()?;
+        }
+        if let Some(weights) = &mut self.weights {
+            let mut column = group.next_column()?.unwrap();
+            column
+                .typed::<Int32Type>()
+                .write_batch(weights, None, None)?;
+            column.close()?;
+            weights.clear();
+        }
+        group.close()?;
+        self.sources.clear();
+        self.targets.clear();
+        Ok(())
+    }
+}
+
+fn to_i64(index: usize) -> i64 {
+    i64::try_from(index).unwrap()
+}
@@ -0,0 +1,111 @@
+//! Slices of a saved graph, saved as graphs of their own: the pages within some links of a few
+//! pages, or the pages of a list. Every command taking a graph then works on the slice, which
+//! loads in a fraction of the time.
+
+use crate::{
+    cancel::Cancel,
+    graph::{Adjacency as _, Direction, Graph},
+    path, workspace,
+};
+use anyhow::Context as _;
+use std::{
+    fs::File,
+    io::{BufRead as _, BufReader},
+    path::{Path, PathBuf},
+};
+
+#[derive(clap::Args)]
+#[command(group(clap::ArgGroup::new("pages").required(true).args(["center", "titles_file"])))]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Where to save the subgraph
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// Keep the pages within `--radius` links of this page (repeatable)
+    #[arg(long, value_name = "TITLE")]
+    center: Vec<String>,
+
+    /// Number of links to follow from `--center`
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "center")]
+    radius: usize,
+
+    /// Which links to follow from `--center`
+    #[arg(long, value_enum, default_value_t, requires = "center")]
+    direction: Direction,
+
+    /// Keep the pages listed in this file, one title per line, such as `parse --titles`
+    /// writes; titles of redirects keep the page they redirect to
+    #[arg(long, value_name = "FILE", conflicts_with = "center")]
+    titles_file: Option<PathBuf>,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+
+    let keep = match &args.titles_file {
+        Some(path) => listed(&graph, path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .unwrap(),
+        None => around(&graph, &args.center, args.radius, args.direction),
+    };
+    let subgraph = graph.subgraph(&keep);
+    subgraph
+        .save(&args.output)
+        .context("Failed to save subgraph")
+        .unwrap();
+
+    println!(
+        "Kept {} of {} nodes and {} of {} links",
+        subgraph.node_count(),
+        graph.node_count(),
+        subgraph.edge_count(),
+        graph.edge_count()
+    );
+}
+
+/// Which nodes are within `hops` links in `direction` of the pages titled `titles`.
+pub fn around(graph: &Graph, titles: &[String], hops: usize, direction: Direction) -> Vec<bool> {
+    let mut keep = vec![false; graph.node_count()];
+    for title in titles {
+        let page = path::find_or_exit(graph, title);
+        let nearby = graph
+            .within(page, hops, direction, |_| true, &Cancel::never())
+            .unwrap();
+        for node in nearby {
+            keep[node as usize] = true;
+        }
+    }
+    keep
+}
+
+/// Which nodes are the pages listed at `path`. Titles are looked up as they are, or with
+/// underscores for spaces, rather than as `path` finds pages, which would take a pass over
+/// every title for each one missing.
+fn listed(graph: &Graph, path: &Path) -> anyhow::Result<Vec<bool>> {
+    let mut keep = vec![false; graph.node_count()];
+    let mut missing = 0_usize;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let title = line.trim();
+        if title.is_empty() {
+            continue;
+        }
+        match graph
+            .id(title)
+            .or_else(|| graph.id(&title.replace('_', " ")))
+        {
+            Some(node) => keep[graph.resolve_redirect(node) as usize] = true,
+            None => missing += 1,
+        }
+    }
+    if missing > 0 {
+        tracing::warn!("{missing} listed titles aren't in the graph");
+    }
+    Ok(keep)
+}
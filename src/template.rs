@@ -0,0 +1,157 @@
+//! Just enough template parsing to read `{{name|arg|key=value}}` invocations out of wikitext.
+
+use std::ops::Range;
+
+/// A template invocation, with its arguments split at top-level `|`s.
+pub struct Template<'a> {
+    pub name: &'a str,
+    pub args: Vec<&'a str>,
+    /// Byte range of the whole invocation, braces included, in the text it was found in.
+    pub range: Range<usize>,
+}
+
+impl<'a> Template<'a> {
+    /// Unnamed arguments, in order. `{{l|en|word}}` has positional arguments `en` and `word`.
+    pub fn positional(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.args
+            .iter()
+            .copied()
+            .filter(|arg| !is_named(arg))
+            .map(str::trim)
+    }
+
+    /// Link targets among the positional arguments: the `first` one (1-based), and with
+    /// `repeated` every one after it too.
+    pub fn link_args(&self, first: usize, repeated: bool) -> impl Iterator<Item = &'a str> + '_ {
+        self.positional()
+            .skip(first.saturating_sub(1))
+            .take(if repeated { usize::MAX } else { 1 })
+            // Arguments with markup are not plain titles; their links are found elsewhere.
+            .filter(|target| !target.is_empty() && !target.contains(['[', '{', '<']))
+    }
+
+    /// The key of the named argument whose value holds byte `offset` of `text`, the text this
+    /// invocation was found in.
+    pub fn key_at(&self, text: &'a str, offset: usize) -> Option<&'a str> {
+        let mut start = self.range.start + 2;
+        for part in split_args(&text[start..self.range.end - 2]) {
+            if (start..start + part.len()).contains(&offset) {
+                let (key, _) = part.split_once('=').filter(|_| is_named(part))?;
+                return Some(key.trim());
+            }
+            start += part.len() + 1;
+        }
+        None
+    }
+
+    /// Whether this invokes the template called `name`.
+    pub fn is(&self, name: &str) -> bool {
+        template_eq(self.name, name)
+    }
+}
+
+/// Every template invocation in `text`, including ones nested inside other templates, in order
+/// of their opening braces.
+pub fn templates(text: &str) -> impl Iterator<Item = Template<'_>> {
+    text.match_indices("{{").filter_map(|(start, _)| {
+        let len = template_len(&text[start..])?;
+        let inner = &text[start + 2..start + len - 2];
+        let mut parts = split_args(inner).into_iter();
+        let name = parts.next()?.trim();
+        // `{{{parameter}}}` inside template definitions is not an invocation.
+        if name.is_empty() || name.starts_with('{') {
+            return None;
+        }
+        Some(Template {
+            name,
+            args: parts.collect(),
+            range: start..start + len,
+        })
+    })
+}
+
+/// Length of the template invocation at the start of `text` up to and including its matching
+/// `}}`, or `None` if it is never closed.
+pub fn template_len(text: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    let mut i = 0;
+    let bytes = text.as_bytes();
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            b"{{" => {
+                depth += 1;
+                i += 2;
+            }
+            b"}}" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Split the inside of a template at `|`s that aren't nested in another template or a link.
+fn split_args(inner: &str) -> Vec<&str> {
+    let bytes = inner.as_bytes();
+    let mut parts = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' | b'[' if bytes.get(i + 1) == Some(&bytes[i]) => {
+                depth += 1;
+                i += 2;
+            }
+            b'}' | b']' if bytes.get(i + 1) == Some(&bytes[i]) => {
+                depth = depth.saturating_sub(1);
+                i += 2;
+            }
+            b'|' if depth == 0 => {
+                parts.push(&inner[start..i]);
+                i += 1;
+                start = i;
+            }
+            _ => i += 1,
+        }
+    }
+    parts.push(&inner[start..]);
+    parts
+}
+
+fn is_named(arg: &str) -> bool {
+    arg.split_once('=')
+        .is_some_and(|(key, _)| !key.contains(['[', '{']) && !key.trim().is_empty())
+}
+
+/// Whether a template name is really a magic word or parser function, like
+/// `{{DEFAULTSORT:...}}` or `{{#if:...}}`.
+pub fn is_magic_word(name: &str) -> bool {
+    name.starts_with('#')
+        || name.split_once(':').is_some_and(|(word, _)| {
+            !word.is_empty() && word.chars().all(|c| c.is_ascii_uppercase())
+        })
+}
+
+/// Infoboxes are templates named "Infobox ..." by convention on most wikis.
+pub fn is_infobox(name: &str) -> bool {
+    name.get(..7)
+        .is_some_and(|prefix| template_eq(prefix, "Infobox"))
+}
+
+/// Template names are case-insensitive only in their first letter, and underscores are
+/// interchangeable with spaces.
+pub fn template_eq(a: &str, b: &str) -> bool {
+    let mut a = a.trim().chars().map(|c| if c == '_' { ' ' } else { c });
+    let mut b = b.trim().chars().map(|c| if c == '_' { ' ' } else { c });
+    match (a.next(), b.next()) {
+        (Some(x), Some(y)) => x.to_lowercase().eq(y.to_lowercase()) && a.eq(b),
+        (None, None) => true,
+        _ => false,
+    }
+}
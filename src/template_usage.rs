@@ -0,0 +1,159 @@
+//! The bipartite graph of which pages use which templates, and its one-mode projections: pages
+//! weighted by how many templates they share, and templates by how many pages they share.
+
+use crate::{edge_type, template};
+use lasso::{Rodeo, Spur};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Projection {
+    /// Pages, linked by the templates they share
+    Pages,
+    /// Templates, linked by the pages they share
+    Templates,
+}
+
+pub struct TemplateUsage {
+    /// Template titles, interned apart from the pages so they don't become link graph nodes.
+    templates: Rodeo,
+    /// Invocation counts of each template on each page.
+    uses: HashMap<(Spur, Spur), u64>,
+}
+
+impl TemplateUsage {
+    pub fn new() -> Self {
+        Self {
+            templates: Rodeo::new(),
+            uses: HashMap::new(),
+        }
+    }
+
+    /// Count the templates invoked in `text`, the wikitext of `page`.
+    pub fn add_page(&mut self, page: Spur, text: &str) {
+        for name in template::templates(text).map(|template| template.name) {
+            if template::is_magic_word(name) {
+                continue;
+            }
+            let template = self
+                .templates
+                .get_or_intern(edge_type::template_title(name));
+            *self.uses.entry((page, template)).or_default() += 1;
+        }
+    }
+
+    /// Write `usage.csv`, the bipartite graph as a directed Gephi edge list from pages to
+    /// templates weighted by invocation count, `nodes.csv` saying which side each node is on,
+    /// and `pages.csv` and `templates.csv` for the requested `projections`, as undirected edge
+    /// lists keeping pairs that share at least `min_weight` neighbours. Templates on more than
+    /// `max_uses` pages, like citation templates, are left out of the page projection, since
+    /// they would connect almost every pair of pages. Titles are the node IDs.
+    pub fn write(
+        &self,
+        dir: &Path,
+        rodeo: &Rodeo,
+        projections: &[Projection],
+        min_weight: u64,
+        max_uses: usize,
+    ) -> anyhow::Result<()> {
+        fs::create_dir_all(dir)?;
+        let page = |spur: &Spur| rodeo.resolve(spur);
+        let template = |spur: &Spur| self.templates.resolve(spur);
+
+        let mut uses: Vec<(&str, &str, u64)> = self
+            .uses
+            .iter()
+            .map(|((p, t), &count)| (page(p), template(t), count))
+            .collect();
+        uses.sort_unstable();
+        let mut writer = csv::Writer::from_path(dir.join("usage.csv"))?;
+        writer.write_record(["Source", "Target", "Weight", "Type"])?;
+        for (page, template, count) in uses {
+            writer.write_record([page, template, count.to_string().as_str(), "Directed"])?;
+        }
+        writer.flush()?;
+
+        let pages: HashSet<Spur> = self.uses.keys().map(|&(page, _)| page).collect();
+        let mut nodes: Vec<(&str, &str)> = pages
+            .iter()
+            .map(|p| (page(p), "page"))
+            .chain(self.templates.strings().map(|t| (t, "template")))
+            .collect();
+        nodes.sort_unstable();
+        let mut writer = csv::Writer::from_path(dir.join("nodes.csv"))?;
+        writer.write_record(["Id", "Label", "part"])?;
+        for (title, part) in nodes {
+            writer.write_record([title, title, part])?;
+        }
+        writer.flush()?;
+
+        if projections.contains(&Projection::Pages) {
+            let mut by_template: HashMap<Spur, Vec<Spur>> = HashMap::new();
+            for &(page, template) in self.uses.keys() {
+                by_template.entry(template).or_default().push(page);
+            }
+            let groups = by_template
+                .into_values()
+                .filter(|pages| pages.len() <= max_uses);
+            write_projection(&dir.join("pages.csv"), groups, min_weight, page)?;
+        }
+        if projections.contains(&Projection::Templates) {
+            let mut by_page: HashMap<Spur, Vec<Spur>> = HashMap::new();
+            for &(page, template) in self.uses.keys() {
+                by_page.entry(page).or_default().push(template);
+            }
+            write_projection(
+                &dir.join("templates.csv"),
+                by_page.into_values(),
+                min_weight,
+                template,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write the undirected graph linking every pair of nodes in the same group, weighted by the
+/// number of groups they share, keeping pairs with at least `min_weight`.
+fn write_projection<'a>(
+    path: &Path,
+    groups: impl Iterator<Item = Vec<Spur>>,
+    min_weight: u64,
+    title: impl Fn(&Spur) -> &'a str,
+) -> anyhow::Result<()> {
+    let mut weights: HashMap<(Spur, Spur), u64> = HashMap::new();
+    for mut group in groups {
+        group.sort_unstable();
+        for (i, &a) in group.iter().enumerate() {
+            for &b in &group[i + 1..] {
+                *weights.entry((a, b)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut edges: Vec<(&str, &str, u64)> = weights
+        .into_iter()
+        .filter(|&(_, weight)| weight >= min_weight)
+        .map(|((a, b), weight)| {
+            let (a, b) = (title(&a), title(&b));
+            if a <= b {
+                (a, b, weight)
+            } else {
+                (b, a, weight)
+            }
+        })
+        .collect();
+    edges.sort_unstable();
+
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["Source", "Target", "Weight", "Type"])?;
+    for (a, b, weight) in edges {
+        writer.write_record([a, b, weight.to_string().as_str(), "Undirected"])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
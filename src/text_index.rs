@@ -0,0 +1,163 @@
+//! A Tantivy full-text index over page titles and plain text, stored next to the saved graph.
+//! Documents carry the page's node ID, so text hits can be fed straight into graph queries.
+
+use crate::graph::Graph;
+use anyhow::Context as _;
+use lasso::{Key as _, Spur};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use tantivy::{
+    collector::TopDocs,
+    doc,
+    query::{BooleanQuery, ConstScoreQuery, Occur, Query, QueryParser, TermSetQuery},
+    schema::{Field, Schema, Value as _, FAST, INDEXED, STORED, TEXT},
+    DocAddress, Index, IndexReader, IndexWriter, TantivyDocument, Term,
+};
+
+const WRITER_MEMORY: usize = 200_000_000;
+
+/// Where the text index of the graph saved at `graph` lives.
+pub fn path_for(graph: &Path) -> PathBuf {
+    let mut path = graph.as_os_str().to_owned();
+    path.push(".tantivy");
+    PathBuf::from(path)
+}
+
+pub struct Writer {
+    inner: IndexWriter,
+    node: Field,
+    title: Field,
+    text: Field,
+}
+
+impl Writer {
+    /// Create an empty index at `dir`, replacing a previous index there.
+    pub fn create(dir: &Path) -> anyhow::Result<Self> {
+        if dir.exists() {
+            anyhow::ensure!(
+                dir.join("meta.json").is_file(),
+                "'{}' exists and is not a text index",
+                dir.display()
+            );
+            fs::remove_dir_all(dir)?;
+        }
+        fs::create_dir_all(dir)?;
+
+        let mut schema = Schema::builder();
+        let node = schema.add_u64_field("node", INDEXED | STORED | FAST);
+        let title = schema.add_text_field("title", TEXT | STORED);
+        let text = schema.add_text_field("text", TEXT);
+        let index = Index::create_in_dir(dir, schema.build())?;
+
+        Ok(Self {
+            inner: index.writer(WRITER_MEMORY)?,
+            node,
+            title,
+            text,
+        })
+    }
+
+    pub fn add(&mut self, node: Spur, title: &str, text: &str) -> anyhow::Result<()> {
+        self.inner.add_document(doc!(
+            self.node => node.into_usize() as u64,
+            self.title => title,
+            self.text => crate::plaintext::article(text),
+        ))?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.inner.commit().context("Failed to commit text index")?;
+        self.inner.wait_merging_threads()?;
+        Ok(())
+    }
+}
+
+pub struct Hit {
+    pub node: u32,
+    pub title: String,
+    pub score: f32,
+}
+
+impl Hit {
+    /// Whether `graph` has the page of the hit under its node ID, which it may not if the graph
+    /// was saved again without its index, or swapped for another.
+    pub fn is_in(&self, graph: &Graph) -> bool {
+        (self.node as usize) < graph.node_count() && graph.title(self.node) == self.title
+    }
+}
+
+pub struct Searcher {
+    reader: IndexReader,
+    parser: QueryParser,
+    node: Field,
+    title: Field,
+}
+
+impl Searcher {
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        let index = Index::open_in_dir(dir)
+            .with_context(|| format!("Failed to open text index '{}'", dir.display()))?;
+        let schema = index.schema();
+        let node = schema.get_field("node")?;
+        let title = schema.get_field("title")?;
+        let text = schema.get_field("text")?;
+        let parser = QueryParser::for_index(&index, vec![title, text]);
+        Ok(Self {
+            reader: index.reader()?,
+            parser,
+            node,
+            title,
+        })
+    }
+
+    /// The best `limit` matches for `query`, optionally restricted to the nodes in `within`.
+    pub fn search(
+        &self,
+        query: &str,
+        within: Option<&HashSet<u32>>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Hit>> {
+        let mut query: Box<dyn Query> = Box::new(self.parser.parse_query(query)?);
+        if let Some(within) = within {
+            let nodes = TermSetQuery::new(
+                within
+                    .iter()
+                    .map(|&node| Term::from_field_u64(self.node, u64::from(node))),
+            );
+            query = Box::new(BooleanQuery::new(vec![
+                (Occur::Must, query),
+                // Only filters, without adding to the score.
+                (
+                    Occur::Must,
+                    Box::new(ConstScoreQuery::new(Box::new(nodes), 0.0)),
+                ),
+            ]));
+        }
+
+        let searcher = self.reader.searcher();
+        let top: Vec<(f32, DocAddress)> =
+            searcher.search(&query, &TopDocs::with_limit(limit).order_by_score())?;
+        top.into_iter()
+            .map(|(score, address)| {
+                let document: TantivyDocument = searcher.doc(address)?;
+                let node = document
+                    .get_first(self.node)
+                    .and_then(|value| value.as_u64())
+                    .context("Document has no node ID")?;
+                let title = document
+                    .get_first(self.title)
+                    .and_then(|value| value.as_str())
+                    .context("Document has no title")?;
+                Ok(Hit {
+                    node: u32::try_from(node)?,
+                    title: String::from(title),
+                    score,
+                })
+            })
+            .collect()
+    }
+}
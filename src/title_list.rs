@@ -0,0 +1,137 @@
+//! Every page title, written as soon as the parser meets it, so that `search` can find pages, and
+//! tell whether one exists, long before a parse of a full dump finishes. The file has one title
+//! per line in order of folded title (see `title_search::fold`), which is all the prefix search
+//! needs, and is replaced as a whole so that readers never see half of it.
+
+use crate::{graph::Adjacency, navigation::Kind, title_search};
+use anyhow::Context as _;
+use lasso::Spur;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Write as _},
+    path::{Path, PathBuf},
+};
+
+/// Fewest new pages between rewrites, which is when titles become visible to readers of the
+/// file. Rewrites get further apart as the file grows, so that the titles of a full dump are
+/// sorted a few dozen times rather than once per batch.
+const REWRITE_EVERY: usize = 10_000;
+
+pub struct Writer {
+    path: PathBuf,
+    titles: Vec<String>,
+    /// Pages already added, since history dumps have one page per revision.
+    added: HashSet<Spur>,
+    /// How many titles there are when the file is next rewritten.
+    next_rewrite: usize,
+}
+
+impl Writer {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let writer = Self {
+            path: path.to_owned(),
+            titles: Vec::new(),
+            added: HashSet::new(),
+            next_rewrite: REWRITE_EVERY,
+        };
+        // Replace whatever an earlier parse left there.
+        writer.rewrite()?;
+        Ok(writer)
+    }
+
+    /// Add `title`, the title of page `page`, unless it has been already.
+    pub fn add_page(&mut self, page: Spur, title: &str) -> anyhow::Result<()> {
+        if !self.added.insert(page) {
+            return Ok(());
+        }
+        self.titles.push(String::from(title));
+        if self.titles.len() >= self.next_rewrite {
+            self.titles
+                .sort_by_cached_key(|title| title_search::folded(title));
+            self.rewrite()?;
+            self.next_rewrite = self.titles.len() + REWRITE_EVERY.max(self.titles.len() / 4);
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.titles
+            .sort_by_cached_key(|title| title_search::folded(title));
+        self.rewrite()
+    }
+
+    /// Write the titles, already sorted, next to the file and then move them over it.
+    fn rewrite(&self) -> anyhow::Result<()> {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        let mut file = BufWriter::new(File::create(&temporary)?);
+        for title in &self.titles {
+            writeln!(file, "{title}")?;
+        }
+        file.into_inner()?.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
+
+/// A title list read back, as a graph of the pages with none of their links or redirects, which
+/// is enough for `title_search::search` and `path::find`.
+pub struct Index {
+    titles: Vec<String>,
+    ids: HashMap<String, u32>,
+    /// The node IDs in order, since the file is sorted already.
+    by_folded_title: Vec<u32>,
+}
+
+impl Index {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read title list {}", path.display()))?;
+        let titles: Vec<String> = text.lines().map(String::from).collect();
+        let count = u32::try_from(titles.len())?;
+        let ids = (0..count)
+            .map(|id| (titles[id as usize].clone(), id))
+            .collect();
+        Ok(Self {
+            titles,
+            ids,
+            by_folded_title: (0..count).collect(),
+        })
+    }
+}
+
+impl Adjacency for Index {
+    fn node_count(&self) -> usize {
+        self.titles.len()
+    }
+
+    fn id(&self, title: &str) -> Option<u32> {
+        self.ids.get(title).copied()
+    }
+
+    fn title(&self, id: u32) -> &str {
+        &self.titles[id as usize]
+    }
+
+    fn by_folded_title(&self) -> &[u32] {
+        &self.by_folded_title
+    }
+
+    fn kind(&self, _: u32) -> Option<Kind> {
+        None
+    }
+
+    fn redirect(&self, _: u32) -> Option<u32> {
+        None
+    }
+
+    fn links(&self, _: u32) -> &[u32] {
+        &[]
+    }
+
+    fn backlinks(&self, _: u32) -> &[u32] {
+        &[]
+    }
+}
@@ -0,0 +1,218 @@
+//! Finding pages by title without knowing how it is spelled: first the titles starting with
+//! what was typed, ignoring case, accents, and underscores, and then titles a typo or two away.
+//! Graph files store their node IDs in order of folded title for the prefix search, which mapped
+//! graphs run in place.
+
+use crate::{
+    graph::{mmap::MmapGraph, Adjacency, Graph},
+    path, title_list, workspace,
+};
+use anyhow::Context as _;
+use std::{cmp::Reverse, collections::HashSet, path::PathBuf};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization as _};
+
+/// Most titles starting with the query that are ranked, so that a short query doesn't rank
+/// millions.
+const PREFIX_CANDIDATES: usize = 10_000;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// The title, or its beginning, spelled however
+    query: String,
+
+    /// Maximum number of results
+    #[arg(long, default_value_t = 10)]
+    limit: usize,
+
+    /// Map the graph file into memory instead of loading it, which is much faster for one
+    /// search on a large graph; needs a graph file saved without `--compress-graph`
+    #[arg(long)]
+    mmap: bool,
+
+    /// Title list written by `parse --titles`, searched instead while the graph file doesn't
+    /// exist yet, as when that parse is still running; it knows no links or redirects, so
+    /// matches aren't ranked by backlinks
+    #[arg(long, value_name = "FILE")]
+    titles: Option<PathBuf>,
+
+    /// Only check that a page titled `query` exists, printing the page it leads to, and fail if
+    /// there is none
+    #[arg(long, conflicts_with = "limit")]
+    exists: bool,
+}
+
+pub fn run(args: &Args) {
+    if let Some(titles) = args.titles.as_ref().filter(|_| !args.graph.exists()) {
+        tracing::info!(
+            "No graph at {} yet, so searching the title list",
+            args.graph.display()
+        );
+        let index = title_list::Index::open(titles).unwrap();
+        print(&index, args);
+    } else if args.mmap {
+        let graph = MmapGraph::open(&args.graph)
+            .context("Failed to map graph")
+            .unwrap();
+        print(&graph, args);
+    } else {
+        let graph = Graph::load(&args.graph)
+            .context("Failed to load graph")
+            .unwrap();
+        print(&graph, args);
+    }
+}
+
+/// Print the title of each match, and where it redirects to if it does, or with `--exists` just
+/// the page the query leads to.
+fn print(graph: &impl Adjacency, args: &Args) {
+    if args.exists {
+        println!("{}", graph.title(path::find_or_exit(graph, &args.query)));
+        return;
+    }
+    for hit in search(graph, &args.query, args.limit) {
+        let target = graph.resolve_redirect(hit.node);
+        if target == hit.node {
+            println!("{}", graph.title(hit.node));
+        } else {
+            println!("{} → {}", graph.title(hit.node), graph.title(target));
+        }
+    }
+}
+
+/// How a title matched a query, best first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Match {
+    /// The same once folded.
+    Exact,
+    /// Starts with the query once folded.
+    Prefix,
+    /// This many edits away from the query once folded, swapping two letters being one.
+    Fuzzy(usize),
+}
+
+pub struct Hit {
+    pub node: u32,
+    pub matched: Match,
+}
+
+/// `title` as compared when searching: without accents, in lower case, and with spaces for
+/// underscores.
+pub fn fold(title: &str) -> impl Iterator<Item = char> + '_ {
+    title
+        .nfd()
+        .filter(|&c| !is_combining_mark(c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c == '_' { ' ' } else { c })
+}
+
+pub fn folded(title: &str) -> String {
+    fold(title).collect()
+}
+
+/// The IDs of `count` nodes in order of their folded titles, ties in order of ID.
+pub fn folded_order<'a>(count: usize, title: impl Fn(u32) -> &'a str) -> Vec<u32> {
+    let mut order: Vec<u32> = (0..u32::try_from(count).unwrap()).collect();
+    order.sort_by_cached_key(|&node| folded(title(node)));
+    order
+}
+
+/// Up to `limit` pages matching `query`, best first: exact matches, then titles starting with
+/// it, then titles with a typo or two for queries long enough not to match everything, each
+/// by how many pages link to them. Of a redirect and its target, only the better one is kept.
+pub fn search(graph: &impl Adjacency, query: &str, limit: usize) -> Vec<Hit> {
+    let query = folded(query);
+    let order = graph.by_folded_title();
+    let start = order.partition_point(|&node| fold(graph.title(node)).lt(query.chars()));
+    let mut hits: Vec<Hit> = order[start..]
+        .iter()
+        .map_while(|&node| {
+            let title = folded(graph.title(node));
+            title.starts_with(&query).then(|| Hit {
+                node,
+                matched: if title == query {
+                    Match::Exact
+                } else {
+                    Match::Prefix
+                },
+            })
+        })
+        .take(PREFIX_CANDIDATES)
+        .collect();
+
+    let max_distance = match query.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+    if hits.len() < limit && max_distance > 0 {
+        let query: Vec<char> = query.chars().collect();
+        for node in 0..u32::try_from(graph.node_count()).unwrap() {
+            let title = graph.title(node);
+            // Folding keeps the number of letters of nearly every title, so this skips most
+            // titles without folding them.
+            if title.chars().count().abs_diff(query.len()) > max_distance {
+                continue;
+            }
+            let title: Vec<char> = fold(title).collect();
+            if title.starts_with(&query) {
+                continue;
+            }
+            if let Some(distance) = distance(&query, &title, max_distance) {
+                hits.push(Hit {
+                    node,
+                    matched: Match::Fuzzy(distance),
+                });
+            }
+        }
+    }
+
+    let in_degree = |node: u32| graph.backlinks(graph.resolve_redirect(node)).len();
+    hits.sort_by_cached_key(|hit| (hit.matched, Reverse(in_degree(hit.node)), hit.node));
+    let mut targets = HashSet::new();
+    hits.retain(|hit| targets.insert(graph.resolve_redirect(hit.node)));
+    hits.truncate(limit);
+    hits
+}
+
+/// The end of an error message about a missing page titled `title`, suggesting up to three
+/// titles like it, or nothing if none are.
+pub fn did_you_mean(graph: &impl Adjacency, title: &str) -> String {
+    let titles: Vec<String> = search(graph, title, 3)
+        .iter()
+        .map(|hit| format!("'{}'", graph.title(hit.node)))
+        .collect();
+    match &titles[..] {
+        [] => String::new(),
+        [title] => format!("; did you mean {title}?"),
+        [first, second] => format!("; did you mean {first} or {second}?"),
+        [rest @ .., last] => format!("; did you mean {}, or {last}?", rest.join(", ")),
+    }
+}
+
+/// The number of insertions, deletions, substitutions, and swaps of adjacent letters turning
+/// `a` into `b`, if it is at most `max`.
+fn distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    // Rows of the edit distances from prefixes of `a`: two rows ago, the last, and this one.
+    let mut before: Vec<usize> = Vec::new();
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        // Later rows only build on the last two.
+        if row.iter().chain(&last).all(|&d| d > max) {
+            return None;
+        }
+        before = std::mem::replace(&mut last, row);
+    }
+    Some(last[b.len()]).filter(|&d| d <= max)
+}
@@ -0,0 +1,212 @@
+//! The pages of a saved graph that rank highest by one measure: the most linked to and most
+//! linking, by PageRank, or as hubs and authorities by HITS, where good hubs link to good
+//! authorities and good authorities are linked to from good hubs.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::Graph,
+    stats, workspace,
+};
+use anyhow::Context as _;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Measure {
+    /// Links to the page
+    InDegree,
+    /// Links from the page
+    OutDegree,
+    #[value(name = "pagerank")]
+    PageRank,
+    /// HITS hub score, for pages linking to many good authorities
+    Hub,
+    /// HITS authority score, for pages linked to from many good hubs
+    Authority,
+}
+
+impl Measure {
+    fn name(self) -> &'static str {
+        match self {
+            Self::InDegree => "in_degree",
+            Self::OutDegree => "out_degree",
+            Self::PageRank => "pagerank",
+            Self::Hub => "hub",
+            Self::Authority => "authority",
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// What to rank pages by
+    #[arg(long, value_enum, default_value_t = Measure::InDegree)]
+    by: Measure,
+
+    /// Number of pages to list
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    limit: usize,
+
+    /// Write `rank,id,title` and the score as CSV with a header, for spreadsheets, instead of
+    /// a score and a title per line
+    #[arg(long)]
+    csv: bool,
+
+    /// Write the list to this file instead of standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Number of PageRank or HITS iterations
+    #[arg(long, value_name = "N", default_value_t = stats::ITERATIONS)]
+    iterations: u32,
+
+    /// Probability of following a link rather than jumping to a random page, for PageRank
+    #[arg(long, default_value_t = stats::DAMPING)]
+    damping: f64,
+
+    /// Give up after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let cancel = Cancel::after(args.timeout.map(Duration::from_secs));
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    let scores: Vec<f64> = match args.by {
+        Measure::InDegree => nodes.map(|node| f64::from(graph.in_degree(node))).collect(),
+        Measure::OutDegree => nodes
+            .map(|node| f64::from(graph.out_degree(node)))
+            .collect(),
+        Measure::PageRank => {
+            if let Some(pagerank) = stats::stored_pagerank(&graph, args.damping, args.iterations) {
+                tracing::info!("Using PageRank stored in the graph file");
+                pagerank.scores.clone()
+            } else {
+                stats::pagerank_scores(&graph, args.damping, args.iterations, &cancel)
+                    .context("Failed to compute PageRank")
+                    .unwrap()
+            }
+        }
+        Measure::Hub | Measure::Authority => {
+            let (hubs, authorities) = hits(&graph, args.iterations, &cancel)
+                .context("Failed to compute HITS scores")
+                .unwrap();
+            if args.by == Measure::Hub {
+                hubs
+            } else {
+                authorities
+            }
+        }
+    };
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .context("Failed to create output file")
+                .unwrap(),
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    write(
+        args,
+        &graph,
+        &stats::top_scores(&scores, args.limit),
+        output,
+    )
+    .context("Failed to write the top pages")
+    .unwrap();
+}
+
+fn write(
+    args: &Args,
+    graph: &Graph,
+    ranked: &[(u32, f64)],
+    output: impl Write,
+) -> anyhow::Result<()> {
+    let degrees = matches!(args.by, Measure::InDegree | Measure::OutDegree);
+    let score = |score: f64| {
+        if degrees {
+            format!("{score}")
+        } else {
+            format!("{score:.6}")
+        }
+    };
+    if args.csv {
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(["rank", "id", "title", args.by.name()])?;
+        for (rank, &(node, value)) in (1_u32..).zip(ranked) {
+            writer.write_record([
+                &rank.to_string(),
+                &node.to_string(),
+                graph.title(node),
+                &score(value),
+            ])?;
+        }
+        writer.flush()?;
+    } else {
+        let mut writer = BufWriter::new(output);
+        for &(node, value) in ranked {
+            writeln!(writer, "{}\t{}", score(value), graph.title(node))?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Hub and authority scores by `iterations` rounds of HITS: each page's authority becomes the
+/// sum of the hub scores of the pages linking to it, and then its hub score the sum of the
+/// authorities it links to, each scaled to a unit vector. Checks `cancel` before each round.
+fn hits(
+    graph: &Graph,
+    iterations: u32,
+    cancel: &Cancel,
+) -> Result<(Vec<f64>, Vec<f64>), Cancelled> {
+    let nodes = 0..u32::try_from(graph.node_count()).unwrap();
+    let normalize = |scores: &mut Vec<f64>| {
+        let norm = scores.iter().map(|score| score * score).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            for score in scores {
+                *score /= norm;
+            }
+        }
+    };
+    let mut hubs = vec![1.0; graph.node_count()];
+    let mut authorities = vec![1.0; graph.node_count()];
+    for _ in 0..iterations {
+        cancel.check()?;
+        authorities = nodes
+            .clone()
+            .map(|node| {
+                graph
+                    .backlinks(node)
+                    .iter()
+                    .map(|&source| hubs[source as usize])
+                    .sum()
+            })
+            .collect();
+        normalize(&mut authorities);
+        hubs = nodes
+            .clone()
+            .map(|node| {
+                graph
+                    .links(node)
+                    .iter()
+                    .map(|&target| authorities[target as usize])
+                    .sum()
+            })
+            .collect();
+        normalize(&mut hubs);
+    }
+    Ok((hubs, authorities))
+}
@@ -0,0 +1,166 @@
+//! Bringing a saved graph up to date with Wikimedia's daily incremental dumps (the "adds-changes"
+//! `pages-meta-hist-incr.xml.bz2` files), instead of parsing the whole wiki again. Each page in
+//! an incremental dump replaces the links, kind, and redirect of the page in the graph, or is
+//! added with its links if it is new. The dumps don't list deleted pages, so those stay until
+//! the next full parse, as do links moved onto the target of what was a redirect: the graph only
+//! keeps where they lead, so `update` warns when a changed page stops being a redirect.
+//!
+//! The changed pages are parsed with the options of the parse that built the graph, taken from
+//! its provenance, so that links are found and normalized the same way. The updated graph keeps
+//! that command in its provenance for the next update, with the name and date of the last
+//! incremental dump applied.
+
+use crate::{
+    config,
+    graph::{Graph, Metadata},
+    normalize, workspace, Collectors, Command, ParseArgs, Wiki,
+};
+use anyhow::Context as _;
+use clap::Parser as _;
+use lasso::{Rodeo, Spur};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Incremental dumps of the pages added and changed since the graph's dump, such as
+    /// `enwiki-20240602-pages-meta-hist-incr.xml.bz2`, applied in the order given
+    #[arg(required = true, value_name = "INCR_DUMP")]
+    dumps: Vec<PathBuf>,
+
+    /// Where to save the updated graph [default: over the graph file]
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Options to parse the changed pages with instead of those of the parse that built the
+    /// graph, as in `wikigraph update enwiki.graph incr.xml.bz2 -- --namespace 0 --edge-weights`
+    #[arg(last = true, value_name = "PARSE_ARGS")]
+    parse: Vec<OsString>,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let mut rodeo = Rodeo::new();
+    // Interning the graph's titles first keeps the IDs of its nodes, and new pages follow them.
+    let mut wiki = graph.to_wiki(&mut rodeo);
+    let mut metadata = graph.metadata().clone();
+
+    for dump in &args.dumps {
+        let (parse, command) = parse_args(args, &metadata, dump)
+            .context("Failed to read the options to parse the changes with")
+            .unwrap();
+        let changes = crate::build(dump, &parse, &mut rodeo, None, &mut Collectors::default());
+        let profile = crate::link_profile(&parse, dump);
+        let (pages, unredirected) = apply(&mut wiki, changes);
+        let resolves_redirects = profile
+            .stages()
+            .contains(&normalize::Stage::ResolveRedirects);
+        if let (Some(page), true) = (unredirected.first(), resolves_redirects) {
+            tracing::warn!(
+                "{} changed pages, such as '{}', were redirects, and links to them still lead \
+                 where they redirected; parse the full dump again to point them back",
+                unredirected.len(),
+                rodeo.resolve(page)
+            );
+        }
+        wiki.resolve_targets(&rodeo, &profile);
+        tracing::info!(
+            "Applied {pages} added and changed pages of {}",
+            dump.display()
+        );
+        metadata = Metadata {
+            command,
+            options: metadata.options,
+            ..Metadata::current(Some(dump))
+        };
+    }
+
+    let mut updated = Graph::new(&rodeo, &wiki, metadata)
+        .context("Failed to build graph")
+        .unwrap();
+    updated.set_storage_of(&graph);
+    drop(graph);
+    updated
+        .save(args.output.as_ref().unwrap_or(&args.graph))
+        .context("Failed to save graph")
+        .unwrap();
+
+    println!(
+        "{} nodes and {} links",
+        updated.node_count(),
+        updated.edge_count()
+    );
+}
+
+/// The options to parse `dump` with, and the `parse` command line they come from: the
+/// `PARSE_ARGS` if given, or else those of the parse recorded in `metadata`, reading `dump`
+/// instead of its input and nothing else beside it.
+fn parse_args(
+    args: &Args,
+    metadata: &Metadata,
+    dump: &Path,
+) -> anyhow::Result<(ParseArgs, Vec<String>)> {
+    let command: Vec<String> = if args.parse.is_empty() {
+        anyhow::ensure!(
+            metadata.command.get(1).map(String::as_str) == Some("parse"),
+            "The graph wasn't built by `parse`, so give the options to parse the changes with \
+             after `--`"
+        );
+        metadata.command.clone()
+    } else {
+        ["wikigraph", "parse"]
+            .into_iter()
+            .map(OsString::from)
+            .chain([dump.as_os_str().to_owned()])
+            .chain(args.parse.iter().cloned())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    };
+    let expanded = config::expand(command.iter().map(OsString::from).collect())?;
+    let Command::Parse(mut parse) = crate::Args::try_parse_from(expanded)?.command else {
+        unreachable!("The command line is a `parse`");
+    };
+    parse.input = dump.to_path_buf();
+    // These describe the full dump, or read its every revision. Sampling picks pages by title,
+    // so it picks the same pages of the changes, but counting them from the start doesn't.
+    parse.index = None;
+    parse.shard = None;
+    parse.snapshots = None;
+    parse.skip_pages = None;
+    parse.max_pages = None;
+    parse.checkpoint = None;
+    parse.resume = false;
+    Ok((*parse, command))
+}
+
+/// Replace the links, link weights, kinds, and redirects of the pages of `changes` in `wiki`,
+/// and return how many pages that was, and which of them were redirects and aren't anymore.
+fn apply(wiki: &mut Wiki, changes: Wiki) -> (usize, Vec<Spur>) {
+    let pages: HashSet<Spur> = changes.links.keys().copied().collect();
+    if let Some(counts) = &mut wiki.link_counts {
+        counts.retain(|(source, _), _| !pages.contains(source));
+    }
+    if let Some(counts) = changes.link_counts {
+        wiki.link_counts.get_or_insert_default().extend(counts);
+    }
+    let mut unredirected = Vec::new();
+    for &page in &pages {
+        if wiki.redirects.remove(&page).is_some() && !changes.redirects.contains_key(&page) {
+            unredirected.push(page);
+        }
+        wiki.navigation.remove(&page);
+    }
+    wiki.redirects.extend(changes.redirects);
+    wiki.navigation.extend(changes.navigation);
+    wiki.links.extend(changes.links);
+    (pages.len(), unredirected)
+}
@@ -0,0 +1,191 @@
+//! Title variants of wikis written in more than one script. Chinese Wikipedia shows each page
+//! in simplified or traditional characters as the reader prefers, and Serbian Wikipedia in
+//! Cyrillic or Latin, so a link can spell its target in another script than the page's title
+//! and still reach the page. Titles are compared by converting both to one script, and such
+//! links point at the page rather than becoming a node of their own.
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashMap, fs, path::Path};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// Chinese, comparing titles in simplified characters
+    Zh,
+    /// Serbian and Serbo-Croatian, comparing titles in Cyrillic
+    Sr,
+}
+
+impl Language {
+    /// Guess the language from a dump file name like `zhwiki-20240601-pages-articles.xml.bz2`,
+    /// if its wiki converts between scripts.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let (code, _) = name.split_once("wiki")?;
+        match code {
+            "zh" => Some(Self::Zh),
+            "sr" | "sh" => Some(Self::Sr),
+            _ => None,
+        }
+    }
+
+    /// The built-in conversion table. The Chinese one only has common characters; OpenCC's
+    /// tables are complete, and can be added with `Rules::load`.
+    pub fn rules(self) -> Rules {
+        let mut rules = Rules::default();
+        match self {
+            Self::Zh => {
+                for pair in TRADITIONAL.split_whitespace() {
+                    let mut chars = pair.chars();
+                    let (Some(from), Some(to)) = (chars.next(), chars.next()) else {
+                        continue;
+                    };
+                    rules.insert(from.to_string(), to.to_string());
+                }
+            }
+            Self::Sr => {
+                for &(from, to) in SERBIAN_LATIN {
+                    rules.insert(String::from(from), String::from(to));
+                    let mut upper = from.chars();
+                    if let Some(first) = upper.next() {
+                        // `Lj` at the start of a word, and `LJ` in an all-caps one.
+                        let title: String = first.to_uppercase().chain(upper).collect();
+                        let to_upper = to.to_uppercase();
+                        rules.insert(title, to_upper.clone());
+                        rules.insert(from.to_uppercase(), to_upper);
+                    }
+                }
+            }
+        }
+        rules
+    }
+}
+
+/// What characters and phrases convert to, matched longest first.
+#[derive(Default)]
+pub struct Rules {
+    table: HashMap<String, String>,
+    /// Characters in the longest phrase of `table`.
+    longest: usize,
+}
+
+impl Rules {
+    fn insert(&mut self, from: String, to: String) {
+        self.longest = self.longest.max(from.chars().count());
+        self.table.insert(from, to);
+    }
+
+    /// Add the conversions of a table in OpenCC's format, such as its `TSCharacters.txt` and
+    /// `TSPhrases.txt`: one character or phrase per line, a tab, and what it converts to,
+    /// the first of several alternatives separated by spaces. Lines starting with `#` are
+    /// comments.
+    pub fn load(&mut self, path: &Path) -> anyhow::Result<()> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        for (number, line) in (1..).zip(text.lines()) {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (from, to) = line
+                .split_once('\t')
+                .and_then(|(from, to)| Some((from, to.split_whitespace().next()?)))
+                .with_context(|| format!("Expected a tab and a conversion on line {number}"))?;
+            self.insert(String::from(from), String::from(to));
+        }
+        Ok(())
+    }
+
+    /// `title` converted to the script titles are compared in.
+    pub fn key<'a>(&self, title: &'a str) -> Cow<'a, str> {
+        let mut converted = String::new();
+        let mut copied = 0;
+        let mut rest = title;
+        while let Some(first) = rest.chars().next() {
+            let offset = title.len() - rest.len();
+            let matched = rest
+                .char_indices()
+                .map(|(end, c)| end + c.len_utf8())
+                .take(self.longest)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .find_map(|end| Some((end, self.table.get(&rest[..end])?)));
+            match matched {
+                Some((end, to)) => {
+                    converted.push_str(&title[copied..offset]);
+                    converted.push_str(to);
+                    copied = offset + end;
+                    rest = &rest[end..];
+                }
+                None => rest = &rest[first.len_utf8()..],
+            }
+        }
+        if copied == 0 {
+            Cow::Borrowed(title)
+        } else {
+            converted.push_str(&title[copied..]);
+            Cow::Owned(converted)
+        }
+    }
+}
+
+/// Serbian Latin letters and their Cyrillic ones, digraphs included, in lowercase.
+const SERBIAN_LATIN: &[(&str, &str)] = &[
+    ("lj", "љ"),
+    ("nj", "њ"),
+    ("dž", "џ"),
+    ("a", "а"),
+    ("b", "б"),
+    ("c", "ц"),
+    ("č", "ч"),
+    ("ć", "ћ"),
+    ("d", "д"),
+    ("đ", "ђ"),
+    ("e", "е"),
+    ("f", "ф"),
+    ("g", "г"),
+    ("h", "х"),
+    ("i", "и"),
+    ("j", "ј"),
+    ("k", "к"),
+    ("l", "л"),
+    ("m", "м"),
+    ("n", "н"),
+    ("o", "о"),
+    ("p", "п"),
+    ("r", "р"),
+    ("s", "с"),
+    ("š", "ш"),
+    ("t", "т"),
+    ("u", "у"),
+    ("v", "в"),
+    ("z", "з"),
+    ("ž", "ж"),
+];
+
+/// Common traditional characters, each followed by its simplified form.
+const TRADITIONAL: &str = "
+    國国 學学 東东 語语 華华 電电 機机 點点 會会 對对 時时 個个 們们 來来 為为 這这 過过 還还
+    說说 經经 發发 開开 關关 長长 門门 問问 間间 見见 現现 實实 當当 與与 從从 應应 號号 業业
+    動动 區区 義义 車车 書书 體体 頭头 樂乐 歷历 曆历 歲岁 臺台 灣湾 廣广 場场 處处 產产 無无
+    萬万 圖图 線线 紅红 級级 組组 結结 統统 網网 總总 際际 陸陆 陽阳 隊队 隨随 雙双 雲云 題题
+    顏颜 風风 飛飞 館馆 馬马 魚鱼 鳥鸟 黃黄 龍龙 變变 讓让 議议 論论 設设 計计 話话 認认 識识
+    試试 請请 調调 讀读 軍军 輕轻 連连 運运 進进 遠远 選选 邊边 鐵铁 銀银 錢钱 錯错 陳陈 鄉乡
+    鄧邓 劉刘 張张 楊杨 趙赵 吳吴 孫孙 蘇苏 葉叶 韓韩 魯鲁 齊齐 漢汉 淺浅 濟济 術术 衛卫 觀观
+    親亲 聖圣 聯联 聲声 腦脑 藝艺 藥药 專专 將将 導导 層层 屬属 島岛 帶带 幾几 廠厂 強强 歸归
+    復复 複复 態态 戰战 戲戏 擊击 據据 數数 斷断 於于 條条 極极 樓楼 標标 權权 歐欧 氣气 濕湿
+    滿满 災灾 熱热 爾尔 獨独 獲获 環环 畫画 畢毕 異异 療疗 盡尽 監监 礎础 禮礼 種种 稱称 穩稳
+    競竞 筆笔 節节 範范 築筑 類类 紀纪 約约 紙纸 細细 終终 維维 綠绿 編编 練练 績绩 續续 興兴
+    舉举 舊旧 艦舰 蟲虫 覺觉 誌志 謝谢 證证 質质 購购 貿贸 費费 資资 賽赛 趨趋 跡迹 蹤踪 躍跃
+    農农 醫医 鍵键 鎮镇 鏡镜 陣阵 陰阴 隱隐 難难 響响 頁页 順顺 領领 頻频 顯显 飯饭 驗验 髮发
+    鬥斗 麗丽 麼么 黨党 齒齿 億亿 價价 優优 傳传 僅仅 兒儿 內内 兩两 凍冻 劃划 劇剧 勞劳 勢势
+    協协 參参 喬乔 單单 嚴严 團团 圓圆 圍围 壓压 壞坏 夠够 夢梦 奪夺 奮奋 婦妇 媽妈 寶宝 寫写
+    歡欢 漁渔 燈灯 牆墙 狀状 獎奖 瑪玛 羅罗 羣群 聞闻 職职 肅肃 腳脚 莊庄 蓋盖 蘭兰 衝冲 裝装
+    製制 詩诗 詞词 譯译 負负 貝贝 財财 貨货 賓宾 賣卖 買买 贊赞 軟软 輪轮 輸输 辦办 遲迟 適适
+    郵邮 鄭郑 釣钓 針针 鋼钢 錄录 鐘钟 閱阅 隻只 雜杂 雞鸡 離离 靈灵 韋韦 頓顿 願愿 颱台 餘余
+    騎骑 驚惊 鬱郁 鷹鹰 鹽盐 麥麦
+";
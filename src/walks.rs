@@ -0,0 +1,343 @@
+//! Corpora of random walks over a saved graph for training node embeddings, as node2vec does:
+//! each walk is a line of pages, to be fed to word2vec as a sentence.
+//!
+//! Walks are second order. A step from `v`, having come from `t`, picks the next page `x` in
+//! proportion to the weight of the link to it times `1 / p` if `x` is `t`, `1` if `x` is a
+//! neighbour of `t`, and `1 / q` otherwise, so a low `p` keeps walks near where they started
+//! and a low `q` sends them outwards. Rather than an alias table per link, which takes memory
+//! quadratic in the degree of hubs, there is one per page over the weights of its links, and a
+//! draw from it is kept with probability its bias over the largest bias, which samples the
+//! same distribution exactly with tables the size of the graph.
+//!
+//! Every walk has a generator seeded from the seed, its round, and the title it starts from, so
+//! the same seed writes the same corpus whatever the number of threads.
+
+use crate::{
+    export,
+    graph::{Adjacency as _, Direction, Graph},
+    sample::Rng,
+    workspace,
+};
+use anyhow::Context as _;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    thread,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Graph file saved by `parse --graph`, or its name in the workspace (see `graphs`)
+    #[arg(value_parser = workspace::graph_path)]
+    graph: PathBuf,
+
+    /// Write the walks to this file instead of standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Walks to start from each page
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    walks_per_node: usize,
+
+    /// Most pages in a walk, which stops early at a page it can't step on from
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 80,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    length: u32,
+
+    /// Return parameter: how unlikely a walk is to step straight back where it came from
+    #[arg(long = "p", value_name = "P", default_value_t = 1.0, value_parser = parse_bias)]
+    return_parameter: f64,
+
+    /// In-out parameter: how unlikely a walk is to step away from the page it came from
+    #[arg(long = "q", value_name = "Q", default_value_t = 1.0, value_parser = parse_bias)]
+    in_out_parameter: f64,
+
+    /// Which links walks follow
+    #[arg(long, value_enum, default_value_t = Direction::Both)]
+    direction: Direction,
+
+    /// Write titles, with underscores for spaces, instead of node IDs
+    #[arg(long)]
+    titles: bool,
+
+    /// Number of threads walking [default: the number of CPUs]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// Seed for the walks; the same seed writes the same walks of the same graph
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+pub fn run(args: &Args) {
+    let graph = Graph::load(&args.graph)
+        .context("Failed to load graph")
+        .unwrap();
+    let steps = Steps::new(&graph, args.direction);
+    let bias = Bias::new(args.return_parameter, args.in_out_parameter);
+    let threads = args.threads.map_or_else(
+        || thread::available_parallelism().map_or(1, usize::from),
+        usize::from,
+    );
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .context("Failed to create output file")
+                .unwrap(),
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut output = BufWriter::new(output);
+    let mut builder = csv::WriterBuilder::new();
+    builder
+        .delimiter(b' ')
+        .has_headers(false)
+        .flexible(true)
+        .quote_style(csv::QuoteStyle::Never);
+
+    // Pages that walks can leave, which are never redirects, since links to those lead on to
+    // the page redirected to.
+    let starts: Vec<u32> = (0..u32::try_from(graph.node_count()).unwrap())
+        .filter(|&node| !steps.targets(node).is_empty())
+        .collect();
+    for round in 0..args.walks_per_node {
+        let label = round.to_string();
+        let mut order = starts.clone();
+        shuffle(&mut order, &mut Rng::seeded(args.seed, &["walks", &label]));
+        export::write_chunked(&mut output, &builder, &order, threads, |writer, &start| {
+            let mut rng = Rng::seeded(args.seed, &["walk", &label, graph.title(start)]);
+            let walk = steps.walk(start, args.length, &bias, &mut rng);
+            if args.titles {
+                writer.write_record(walk.iter().map(|&node| graph.title(node).replace(' ', "_")))
+            } else {
+                writer.write_record(walk.iter().map(u32::to_string))
+            }
+        })
+        .context("Failed to write walks")
+        .unwrap();
+        tracing::info!("Walked round {} of {}", round + 1, args.walks_per_node);
+    }
+    output.flush().context("Failed to write walks").unwrap();
+    tracing::info!(
+        "Wrote {} walks from {} pages",
+        starts.len() * args.walks_per_node,
+        starts.len()
+    );
+}
+
+fn parse_bias(s: &str) -> Result<f64, String> {
+    let parameter: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if parameter.is_finite() && parameter > 0.0 {
+        Ok(parameter)
+    } else {
+        Err(String::from("must be a number above 0"))
+    }
+}
+
+/// How much more or less likely a step is by where it leads relative to the previous page.
+struct Bias {
+    back: f64,
+    near: f64,
+    away: f64,
+    most: f64,
+}
+
+impl Bias {
+    fn new(p: f64, q: f64) -> Self {
+        let (back, near, away) = (1.0 / p, 1.0, 1.0 / q);
+        Self {
+            back,
+            near,
+            away,
+            most: back.max(near).max(away),
+        }
+    }
+}
+
+/// The pages each page can step to, sorted, in compressed sparse rows, with an alias table per
+/// page for drawing one of them by the weight of the link in constant time: slot `i` of a page
+/// is its own target with probability `keep[i]`, and otherwise the target in slot `alias[i]`.
+struct Steps {
+    offsets: Vec<usize>,
+    targets: Vec<u32>,
+    keep: Vec<f32>,
+    alias: Vec<u32>,
+}
+
+impl Steps {
+    /// The steps along links in `direction`, with links to redirects leading to the page
+    /// redirected to and steps between the same two pages merged, weighing as much together.
+    fn new(graph: &Graph, direction: Direction) -> Self {
+        let nodes = graph.node_count();
+        let for_each_link = |f: &mut dyn FnMut(u32, u32, u32)| {
+            for source in 0..u32::try_from(nodes).unwrap() {
+                if graph.redirect(source).is_some() {
+                    continue;
+                }
+                let weights = graph.link_weights(source);
+                for (i, &target) in graph.links(source).iter().enumerate() {
+                    let target = graph.resolve_redirect(target);
+                    if target == source || graph.redirect(target).is_some() {
+                        continue;
+                    }
+                    let weight = weights.map_or(1, |weights| weights[i]);
+                    if direction != Direction::In {
+                        f(source, target, weight);
+                    }
+                    if direction != Direction::Out {
+                        f(target, source, weight);
+                    }
+                }
+            }
+        };
+
+        let mut offsets = vec![0; nodes + 1];
+        for_each_link(&mut |from, _, _| offsets[from as usize + 1] += 1);
+        for node in 0..nodes {
+            offsets[node + 1] += offsets[node];
+        }
+        let mut cursor = offsets.clone();
+        let mut targets = vec![0; offsets[nodes]];
+        let mut weights = vec![0; offsets[nodes]];
+        for_each_link(&mut |from, to, weight| {
+            let slot = &mut cursor[from as usize];
+            targets[*slot] = to;
+            weights[*slot] = weight;
+            *slot += 1;
+        });
+
+        // Sort each page's steps and merge repeats, compacting the rows as they shrink.
+        let mut len = 0;
+        let mut start = 0;
+        for node in 0..nodes {
+            let end = offsets[node + 1];
+            let mut row: Vec<(u32, u32)> = targets[start..end]
+                .iter()
+                .copied()
+                .zip(weights[start..end].iter().copied())
+                .collect();
+            row.sort_unstable_by_key(|&(target, _)| target);
+            offsets[node] = len;
+            for (target, weight) in row {
+                if len > offsets[node] && targets[len - 1] == target {
+                    weights[len - 1] = weights[len - 1].saturating_add(weight);
+                } else {
+                    targets[len] = target;
+                    weights[len] = weight;
+                    len += 1;
+                }
+            }
+            start = end;
+        }
+        offsets[nodes] = len;
+        targets.truncate(len);
+        weights.truncate(len);
+
+        let mut keep = vec![0.0; len];
+        let mut alias = vec![0; len];
+        for node in 0..nodes {
+            let row = offsets[node]..offsets[node + 1];
+            alias_table(
+                &weights[row.clone()],
+                &mut keep[row.clone()],
+                &mut alias[row],
+            );
+        }
+        Self {
+            offsets,
+            targets,
+            keep,
+            alias,
+        }
+    }
+
+    fn targets(&self, node: u32) -> &[u32] {
+        &self.targets[self.offsets[node as usize]..self.offsets[node as usize + 1]]
+    }
+
+    /// A step from `node` by the weights of its links alone.
+    fn draw(&self, node: u32, rng: &mut Rng) -> u32 {
+        let start = self.offsets[node as usize];
+        let slot = start + rng.below(self.offsets[node as usize + 1] - start);
+        if rng.unit() < f64::from(self.keep[slot]) {
+            self.targets[slot]
+        } else {
+            self.targets[start + self.alias[slot] as usize]
+        }
+    }
+
+    /// A walk of up to `length` pages from `start`.
+    fn walk(&self, start: u32, length: u32, bias: &Bias, rng: &mut Rng) -> Vec<u32> {
+        let mut walk = vec![start];
+        while walk.len() < length as usize {
+            let current = walk[walk.len() - 1];
+            if self.targets(current).is_empty() {
+                break;
+            }
+            let next = match walk.len().checked_sub(2).map(|i| walk[i]) {
+                None => self.draw(current, rng),
+                Some(previous) => loop {
+                    let next = self.draw(current, rng);
+                    let weight = if next == previous {
+                        bias.back
+                    } else if self.targets(previous).binary_search(&next).is_ok() {
+                        bias.near
+                    } else {
+                        bias.away
+                    };
+                    if rng.unit() * bias.most < weight {
+                        break next;
+                    }
+                },
+            };
+            walk.push(next);
+        }
+        walk
+    }
+}
+
+/// Fill in Vose's alias table for drawing a slot in proportion to `weights`, or uniformly if
+/// they are all 0.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn alias_table(weights: &[u32], keep: &mut [f32], alias: &mut [u32]) {
+    let total: f64 = weights.iter().map(|&weight| f64::from(weight)).sum();
+    let mut scaled: Vec<f64> = weights
+        .iter()
+        .map(|&weight| {
+            if total > 0.0 {
+                f64::from(weight) * weights.len() as f64 / total
+            } else {
+                1.0
+            }
+        })
+        .collect();
+    let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+        (0..weights.len()).partition(|&slot| scaled[slot] < 1.0);
+    while let (Some(&under), Some(&over)) = (small.last(), large.last()) {
+        small.pop();
+        keep[under] = scaled[under] as f32;
+        alias[under] = over as u32;
+        scaled[over] -= 1.0 - scaled[under];
+        if scaled[over] < 1.0 {
+            large.pop();
+            small.push(over);
+        }
+    }
+    // What is left is 1 up to rounding.
+    for slot in small.into_iter().chain(large) {
+        keep[slot] = 1.0;
+        alias[slot] = slot as u32;
+    }
+}
+
+fn shuffle(items: &mut [u32], rng: &mut Rng) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.below(i + 1));
+    }
+}
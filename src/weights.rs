@@ -0,0 +1,221 @@
+//! Edge weights from a Gephi edge list, such as `parse --cooccurrence` writes, laid over a saved
+//! graph for the queries that care how strong a connection is rather than how short.
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    graph::{Direction, Graph},
+};
+use serde::Deserialize;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    path::Path,
+};
+
+/// Weighted edges by node. Undirected edges are stored in both directions.
+pub struct Weights {
+    out: HashMap<u32, Vec<(u32, f64)>>,
+    back: HashMap<u32, Vec<(u32, f64)>>,
+}
+
+#[derive(Deserialize)]
+struct EdgeRecord {
+    #[serde(rename = "Source")]
+    source: String,
+    #[serde(rename = "Target")]
+    target: String,
+    #[serde(rename = "Weight")]
+    weight: f64,
+    #[serde(rename = "Type", default)]
+    kind: Option<String>,
+}
+
+impl Weights {
+    /// Read the edges of `path`, a CSV file with `Source` and `Target` titles, a `Weight`, and
+    /// optionally a `Type` of `Directed` or `Undirected`. Edges between titles not in `graph`
+    /// are skipped.
+    pub fn load(path: &Path, graph: &Graph) -> anyhow::Result<Self> {
+        let mut weights = Self {
+            out: HashMap::new(),
+            back: HashMap::new(),
+        };
+        for record in csv::Reader::from_path(path)?.deserialize() {
+            let record: EdgeRecord = record?;
+            let (Some(source), Some(target)) = (graph.id(&record.source), graph.id(&record.target))
+            else {
+                continue;
+            };
+            weights.add(source, target, record.weight);
+            if record
+                .kind
+                .is_some_and(|kind| kind.eq_ignore_ascii_case("undirected"))
+            {
+                weights.add(target, source, record.weight);
+            }
+        }
+        Ok(weights)
+    }
+
+    fn add(&mut self, source: u32, target: u32, weight: f64) {
+        self.out.entry(source).or_default().push((target, weight));
+        self.back.entry(target).or_default().push((source, weight));
+    }
+
+    /// The weighted edges leaving `node` in `direction`.
+    fn neighbours(&self, node: u32, direction: Direction) -> impl Iterator<Item = (u32, f64)> + '_ {
+        let out = self.out.get(&node).filter(|_| direction != Direction::In);
+        let back = self.back.get(&node).filter(|_| direction != Direction::Out);
+        out.into_iter().chain(back).flatten().copied()
+    }
+
+    /// The path from `from` to `to` whose weakest edge is strongest, with that edge's weight,
+    /// preferring fewer hops among equally wide paths. Only passes through nodes for which `keep`
+    /// holds. A path from a node to itself has no edges, and infinite width. Checks `cancel`
+    /// before settling each node.
+    pub fn widest_path(
+        &self,
+        from: u32,
+        to: u32,
+        direction: Direction,
+        keep: impl Fn(u32) -> bool,
+        cancel: &Cancel,
+    ) -> Result<Option<(Vec<u32>, f64)>, Cancelled> {
+        // Extending a path never makes it wider or shorter, so the first time a node is popped
+        // its best path is known, as in Dijkstra's algorithm.
+        let start = Candidate {
+            width: f64::INFINITY,
+            hops: 0,
+            node: from,
+        };
+        let mut best: HashMap<u32, (Candidate, u32)> = HashMap::from([(from, (start, from))]);
+        let mut frontier = BinaryHeap::from([start]);
+        while let Some(candidate) = frontier.pop() {
+            let node = candidate.node;
+            if best[&node].0 > candidate {
+                continue;
+            }
+            cancel.check()?;
+            if node == to {
+                let mut path = vec![to];
+                let mut node = to;
+                while node != from {
+                    node = best[&node].1;
+                    path.push(node);
+                }
+                path.reverse();
+                return Ok(Some((path, candidate.width)));
+            }
+            for (target, weight) in self.neighbours(node, direction) {
+                if !keep(target) && target != to {
+                    continue;
+                }
+                let next = Candidate {
+                    width: candidate.width.min(weight),
+                    hops: candidate.hops + 1,
+                    node: target,
+                };
+                if best.get(&target).is_none_or(|(known, _)| next > *known) {
+                    best.insert(target, (next, node));
+                    frontier.push(next);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The path of at most `max_hops` edges from `from` to `to`, visiting no node twice, with the
+    /// greatest total weight, and that total. Only passes through nodes for which `keep` holds.
+    /// Finding it means trying every such path, so the search space grows with the degree to
+    /// the power of `max_hops`, which is why it checks `cancel` before each step.
+    pub fn heaviest_path(
+        &self,
+        from: u32,
+        to: u32,
+        direction: Direction,
+        max_hops: usize,
+        keep: impl Fn(u32) -> bool,
+        cancel: &Cancel,
+    ) -> Result<Option<(Vec<u32>, f64)>, Cancelled> {
+        let mut search = Search {
+            weights: self,
+            to,
+            direction,
+            max_hops,
+            keep: &keep,
+            cancel,
+            path: vec![from],
+            best: None,
+        };
+        search.extend(0.0)?;
+        Ok(search.best)
+    }
+}
+
+/// A depth-first search for `Weights::heaviest_path`.
+struct Search<'a> {
+    weights: &'a Weights,
+    to: u32,
+    direction: Direction,
+    max_hops: usize,
+    keep: &'a dyn Fn(u32) -> bool,
+    cancel: &'a Cancel,
+    /// The path so far, from the start.
+    path: Vec<u32>,
+    best: Option<(Vec<u32>, f64)>,
+}
+
+impl Search<'_> {
+    fn extend(&mut self, total: f64) -> Result<(), Cancelled> {
+        self.cancel.check()?;
+        let node = *self.path.last().unwrap();
+        if node == self.to {
+            if self.best.as_ref().is_none_or(|(_, best)| total > *best) {
+                self.best = Some((self.path.clone(), total));
+            }
+            return Ok(());
+        }
+        if self.path.len() > self.max_hops {
+            return Ok(());
+        }
+        for (target, weight) in self.weights.neighbours(node, self.direction) {
+            if (!(self.keep)(target) && target != self.to) || self.path.contains(&target) {
+                continue;
+            }
+            self.path.push(target);
+            self.extend(total + weight)?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+}
+
+/// A partial path in `Weights::widest_path`, ordered so that wider and then shorter paths are
+/// greater. Which node it reaches doesn't take part in the order.
+#[derive(Clone, Copy)]
+struct Candidate {
+    width: f64,
+    hops: usize,
+    node: u32,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.width
+            .total_cmp(&other.width)
+            .then(other.hops.cmp(&self.hops))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
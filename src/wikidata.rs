@@ -0,0 +1,54 @@
+//! The Wikidata item of each page, for joining the link graph with data keyed by item. The XML
+//! dumps don't have them; the `page_props` table dump published beside them does, in rows whose
+//! `pp_propname` is `wikibase_item`, giving the item of a page by its page ID. Items are kept as
+//! their number, 42 for `Q42`.
+
+use crate::{sql_dump, Wiki};
+use std::{collections::HashMap, path::Path};
+
+/// The item number of each page ID in the `page_props` table dump at `path`.
+pub fn read(path: &Path) -> anyhow::Result<HashMap<u64, u32>> {
+    let mut items = HashMap::new();
+    let columns = ["pp_page", "pp_propname", "pp_value"];
+    sql_dump::read_rows(path, "page_props", &columns, |row| {
+        if row[1] == Some(b"wikibase_item") {
+            let item = sql_dump::text(row[2])?;
+            items.insert(sql_dump::number(row[0])?, parse(item)?);
+        }
+        Ok(())
+    })?;
+    Ok(items)
+}
+
+/// Give the pages of `wiki` their items from the `page_props` table dump at `path`, matching
+/// them up by page ID.
+pub fn apply(path: &Path, wiki: &mut Wiki) -> anyhow::Result<()> {
+    let items = read(path)?;
+    if wiki.page_ids.is_empty() {
+        tracing::warn!("The dump gives no page IDs, so no page can be given its Wikidata item");
+    }
+    wiki.wikidata_items = wiki
+        .page_ids
+        .iter()
+        .filter_map(|(&page, id)| Some((page, *items.get(id)?)))
+        .collect();
+    tracing::info!(
+        "{} of {} pages have a Wikidata item",
+        wiki.wikidata_items.len(),
+        wiki.page_ids.len()
+    );
+    Ok(())
+}
+
+/// The number of an item named as in `Q42`.
+pub fn parse(name: &str) -> anyhow::Result<u32> {
+    name.strip_prefix('Q')
+        .and_then(|number| number.parse().ok())
+        .filter(|&number| number != 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid Wikidata item '{name}'"))
+}
+
+/// The name of item `number`, as in `Q42`.
+pub fn name(number: u32) -> String {
+    format!("Q{number}")
+}
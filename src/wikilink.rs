@@ -0,0 +1,180 @@
+//! Finding `[[...]]` wikilinks in wikitext.
+
+use std::ops::Range;
+
+/// A `[[target|anchor]]` wikilink.
+pub struct Link<'a> {
+    pub target: &'a str,
+    /// The displayed text: what follows the `|`, or the target itself.
+    pub anchor: &'a str,
+    /// Byte range of the whole link, brackets included.
+    pub range: Range<usize>,
+}
+
+/// Tags whose contents MediaWiki doesn't parse as wikitext, so that links in them are text.
+const VERBATIM_TAGS: [&str; 5] = ["nowiki", "pre", "math", "syntaxhighlight", "source"];
+
+/// The wikilinks of `haystack`, in order of where they start. Links in comments and verbatim
+/// tags don't count, and links nest, as in `[[File:Cat.jpg|thumb|A [[cat]]]]`, where both the
+/// file and the cat are links.
+pub fn links(haystack: &str) -> impl Iterator<Item = Link<'_>> {
+    let bytes = haystack.as_bytes();
+    let mut links = Vec::new();
+    // Where the `[[` of each link not yet closed starts, innermost last.
+    let mut open = Vec::new();
+    let mut i = 0;
+    while let Some(offset) = bytes[i..]
+        .iter()
+        .position(|byte| matches!(byte, b'[' | b']' | b'<'))
+    {
+        i += offset;
+        let rest = &haystack[i..];
+        if rest.starts_with("<!--") {
+            i = rest.find("-->").map_or(bytes.len(), |end| i + end + 3);
+        } else if rest.starts_with('<') {
+            i += verbatim_len(rest).unwrap_or(1);
+        } else if rest.starts_with("[[[") {
+            // `[[[Cat]]]` is a link in brackets.
+            i += 1;
+        } else if rest.starts_with("[[") {
+            open.push(i);
+            i += 2;
+        } else if rest.starts_with("]]") {
+            i += 2;
+            if let Some(start) = open.pop() {
+                links.extend(link(haystack, start..i));
+            }
+        } else {
+            i += 1;
+        }
+    }
+    links.sort_unstable_by_key(|link| link.range.start);
+    links.into_iter()
+}
+
+/// The link spanning `range` of `haystack`, unless its target isn't a possible title.
+fn link(haystack: &str, range: Range<usize>) -> Option<Link<'_>> {
+    let inside = &haystack[range.start + 2..range.end - 2];
+    let (target, anchor) = match inside.split_once('|') {
+        Some((target, anchor)) => (target, Some(anchor)),
+        None => (inside, None),
+    };
+    if target.trim().is_empty() || target.contains(['[', ']', '{', '}', '<', '>', '\n']) {
+        return None;
+    }
+    Some(Link {
+        target,
+        anchor: anchor.filter(|anchor| !anchor.is_empty()).unwrap_or(target),
+        range,
+    })
+}
+
+/// The length of the verbatim element or self-closing verbatim tag at the start of `text`, if
+/// there is one there. An element that is never closed is only its opening tag.
+fn verbatim_len(text: &str) -> Option<usize> {
+    let name_len = text[1..]
+        .find(|character: char| !character.is_ascii_alphabetic())
+        .unwrap_or(text.len() - 1);
+    let name = &text[1..=name_len];
+    let tag = VERBATIM_TAGS
+        .iter()
+        .find(|tag| tag.eq_ignore_ascii_case(name))?;
+    let tag_end = text.find('>')? + 1;
+    if text[..tag_end].ends_with("/>") {
+        return Some(tag_end);
+    }
+    let after = &text[tag_end..];
+    let Some(close) = after
+        .match_indices("</")
+        .map(|(close, _)| close)
+        .find(|&close| {
+            after.as_bytes()[close + 2..]
+                .get(..tag.len())
+                .is_some_and(|name| name.eq_ignore_ascii_case(tag.as_bytes()))
+        })
+    else {
+        return Some(tag_end);
+    };
+    let close_end = after[close..]
+        .find('>')
+        .map_or(after.len(), |end| close + end + 1);
+    Some(tag_end + close_end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scans() {
+        let cases: &[(&str, &[(&str, &str)])] = &[
+            ("", &[]),
+            ("no links", &[]),
+            ("[[Cat]]", &[("Cat", "Cat")]),
+            (
+                "[[Cat|cats]] and [[Dog|]]",
+                &[("Cat", "cats"), ("Dog", "Dog")],
+            ),
+            (
+                "[[Cat#Diet|what cats eat]]",
+                &[("Cat#Diet", "what cats eat")],
+            ),
+            ("[[Cat|a|b]]", &[("Cat", "a|b")]),
+            ("[[Cat]][[Dog]]", &[("Cat", "Cat"), ("Dog", "Dog")]),
+            // Not links: no target, characters titles can't have, across lines, unclosed.
+            ("[[]] [[ |x]] [[a{b}]] [[a<b]]", &[]),
+            ("[[Cat\n]] [[Dog", &[]),
+            ("]] [[Cat]] ]]", &[("Cat", "Cat")]),
+            // Comments, closed or running to the end, and verbatim tags.
+            ("<!-- [[Cat]] --> [[Dog]]", &[("Dog", "Dog")]),
+            ("[[Dog]] <!-- [[Cat]]", &[("Dog", "Dog")]),
+            ("<nowiki>[[Cat]]</nowiki> [[Dog]]", &[("Dog", "Dog")]),
+            ("<NoWiki>[[Cat]]</NOWIKI> [[Dog]]", &[("Dog", "Dog")]),
+            ("<pre class=\"x\">[[Cat]]</pre>[[Dog]]", &[("Dog", "Dog")]),
+            ("<math>[[x]]</math><source lang=c>[[y]]</source>", &[]),
+            ("<nowiki/>[[Cat]]", &[("Cat", "Cat")]),
+            ("<nowiki>[[Cat]]", &[("Cat", "Cat")]),
+            (
+                "<ref>[[Cat]]</ref> <b>[[Dog]]</b>",
+                &[("Cat", "Cat"), ("Dog", "Dog")],
+            ),
+            ("<prefix>[[Cat]]</prefix>", &[("Cat", "Cat")]),
+            // Nested links, in order of where they start.
+            (
+                "[[File:Cat.jpg|thumb|A [[cat]] and a [[Dog|dog]]]] [[Mouse]]",
+                &[
+                    ("File:Cat.jpg", "thumb|A [[cat]] and a [[Dog|dog]]"),
+                    ("cat", "cat"),
+                    ("Dog", "dog"),
+                    ("Mouse", "Mouse"),
+                ],
+            ),
+            (
+                "[[File:A.png|[[File:B.png|[[C]]]]]]",
+                &[
+                    ("File:A.png", "[[File:B.png|[[C]]]]"),
+                    ("File:B.png", "[[C]]"),
+                    ("C", "C"),
+                ],
+            ),
+            // A link in brackets.
+            ("[[[Cat]]]", &[("Cat", "Cat")]),
+            (
+                "[[[Cat|cats]]] [[[[Dog]]]]",
+                &[("Cat", "cats"), ("Dog", "Dog")],
+            ),
+        ];
+        for &(text, expected) in cases {
+            let found: Vec<(&str, &str)> =
+                links(text).map(|link| (link.target, link.anchor)).collect();
+            assert_eq!(found, expected, "links of {text:?}");
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let text = "See [[File:Cat.jpg|A [[cat]]]].";
+        let ranges: Vec<&str> = links(text).map(|link| &text[link.range]).collect();
+        assert_eq!(ranges, ["[[File:Cat.jpg|A [[cat]]]]", "[[cat]]"]);
+    }
+}
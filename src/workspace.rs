@@ -0,0 +1,167 @@
+//! Named graphs. The workspace file remembers where the graphs of different wikis and dump
+//! dates are, so that every command taking a graph file also takes a name like
+//! `enwiki-2024-06`, and `serve` can serve several graphs by name.
+
+use crate::graph::Metadata;
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// List the named graphs, with the date of the dump each was parsed from
+    List,
+    /// Name a graph file
+    Add {
+        /// Letters, digits, `-`, `_`, and `.`, e.g. `enwiki-2024-06`
+        name: String,
+        /// Graph file saved by `parse --graph`
+        graph: PathBuf,
+        /// Point the name at this file even if it already names another
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Forget a name, keeping the graph file
+    Remove { name: String },
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Workspace {
+    #[serde(default)]
+    graphs: BTreeMap<String, Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    path: PathBuf,
+}
+
+pub fn run(args: &Args) {
+    let mut workspace = load().context("Failed to load workspace").unwrap();
+    match &args.command {
+        Command::List => {
+            for (name, entry) in &workspace.graphs {
+                let date = if entry.path.exists() {
+                    Metadata::read(&entry.path)
+                        .ok()
+                        .and_then(|metadata| metadata.dump_date)
+                        .unwrap_or_else(|| String::from("-"))
+                } else {
+                    String::from("missing")
+                };
+                println!("{name}\t{date}\t{}", entry.path.display());
+            }
+        }
+        Command::Add {
+            name,
+            graph,
+            replace,
+        } => {
+            add(&mut workspace, name, graph, *replace).unwrap();
+            save(&workspace)
+                .context("Failed to save workspace")
+                .unwrap();
+        }
+        Command::Remove { name } => {
+            workspace
+                .graphs
+                .remove(name)
+                .with_context(|| format!("No graph named '{name}'"))
+                .unwrap();
+            save(&workspace)
+                .context("Failed to save workspace")
+                .unwrap();
+        }
+    }
+}
+
+fn add(workspace: &mut Workspace, name: &str, graph: &Path, replace: bool) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')),
+        "Invalid graph name '{name}'"
+    );
+    anyhow::ensure!(
+        replace || !workspace.graphs.contains_key(name),
+        "'{name}' already names {}; pass `--replace` to change it",
+        workspace.graphs[name].path.display()
+    );
+    let path =
+        fs::canonicalize(graph).with_context(|| format!("Failed to read {}", graph.display()))?;
+    anyhow::ensure!(
+        crate::graph::Graph::is_graph_file(&path)?,
+        "{} is not a graph file",
+        graph.display()
+    );
+    workspace.graphs.insert(String::from(name), Entry { path });
+    Ok(())
+}
+
+/// The workspace file: `$WIKIGRAPH_WORKSPACE`, or else `wikigraph/graphs.toml` in the user's
+/// configuration directory.
+fn path() -> anyhow::Result<PathBuf> {
+    if let Some(path) = env::var_os("WIKIGRAPH_WORKSPACE") {
+        return Ok(PathBuf::from(path));
+    }
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .context("Neither WIKIGRAPH_WORKSPACE nor HOME is set")?;
+    Ok(config.join("wikigraph").join("graphs.toml"))
+}
+
+/// The workspace, which is empty until a graph is added.
+fn load() -> anyhow::Result<Workspace> {
+    let path = path()?;
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            toml::from_str(&text).with_context(|| format!("Invalid workspace {}", path.display()))
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Workspace::default()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn save(workspace: &Workspace) -> anyhow::Result<()> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string(workspace)?)?;
+    Ok(())
+}
+
+/// A graph argument as a name and a file: the file at `value` if there is one, else the graph
+/// named `value`, else `value` as a file anyway, for loading it to fail with a clear error.
+/// Files are named after their stem.
+pub fn named_graph(value: &str) -> Result<(String, PathBuf), String> {
+    let path = PathBuf::from(value);
+    if !path.exists() {
+        let mut workspace = load().map_err(|error| format!("{error:#}"))?;
+        if let Some(entry) = workspace.graphs.remove(value) {
+            return Ok((String::from(value), entry.path));
+        }
+    }
+    let name = path.file_stem().map_or_else(
+        || String::from(value),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+    Ok((name, path))
+}
+
+/// The file of a graph argument, which may be a name, as with `named_graph`.
+pub fn graph_path(value: &str) -> Result<PathBuf, String> {
+    named_graph(value).map(|(_, path)| path)
+}
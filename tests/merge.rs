@@ -0,0 +1,48 @@
+//! `merge` of the partial results of two shards, where a link of one leads to a redirect and a
+//! disambiguation page of the other.
+
+use std::{path::Path, process::Command};
+
+const WIKIGRAPH: &str = env!("CARGO_BIN_EXE_wikigraph");
+
+fn wikigraph(args: &[&str]) -> String {
+    let output = Command::new(WIKIGRAPH).args(args).output().unwrap();
+    assert!(
+        output.status.success(),
+        "wikigraph {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn resolves_redirects_across_shards() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/merge");
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("merge");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+
+    for shard in ["shard-0", "shard-1"] {
+        let dump = fixtures.join(format!("{shard}.xml"));
+        wikigraph(&[
+            "parse",
+            dump.to_str().unwrap(),
+            "--partial",
+            &path(&format!("{shard}.partial")),
+            "--exclude-disambiguation",
+            "--no-progress",
+        ]);
+    }
+    let merged = path("merged.graph");
+    wikigraph(&[
+        "merge",
+        &path("shard-0.partial"),
+        &path("shard-1.partial"),
+        "--output",
+        &merged,
+    ]);
+
+    // Beta, of the other shard, redirects to Gamma, and Merc to the disambiguation page Mercury.
+    let links = wikigraph(&["query", &merged, r#"{"op":"links","title":"Alpha"}"#]);
+    assert_eq!(links.trim(), r#"{"titles":["Gamma"]}"#);
+}
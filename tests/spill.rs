@@ -0,0 +1,60 @@
+//! `parse --memory-limit`, spilling links to runs on disk, against the same parse in memory,
+//! with a limit small enough that every page's links span several runs.
+
+use std::{fs, path::Path, process::Command};
+
+const WIKIGRAPH: &str = env!("CARGO_BIN_EXE_wikigraph");
+
+/// What `wikigraph` printed when run with `args`, log included.
+fn wikigraph(args: &[&str]) -> String {
+    let output = Command::new(WIKIGRAPH).args(args).output().unwrap();
+    assert!(
+        output.status.success(),
+        "wikigraph {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned() + &String::from_utf8_lossy(&output.stderr)
+}
+
+#[test]
+fn spilling_changes_nothing() {
+    let dump = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/spill/dump.xml");
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("spill");
+    fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+
+    for (name, limit) in [("memory", None), ("spilled", Some("16"))] {
+        let (edges, graph) = (path(&format!("{name}.tsv")), path(&format!("{name}.graph")));
+        let mut args = vec![
+            "parse",
+            dump.to_str().unwrap(),
+            "--output",
+            &edges,
+            "--graph",
+            &graph,
+            "--exclude-disambiguation",
+            "--no-progress",
+        ];
+        let spill_dir = path("runs");
+        if let Some(limit) = limit {
+            args.extend(["--memory-limit", limit, "--spill-dir", &spill_dir]);
+        }
+        let log = wikigraph(&args);
+        assert_eq!(log.contains("Spilled"), limit.is_some());
+        let dot = path(&format!("{name}.dot"));
+        wikigraph(&["export", &graph, "--format", "dot", "--output", &dot]);
+    }
+
+    let read = |name: &str| fs::read_to_string(path(name)).unwrap();
+    // Edge lists written from memory have pages in no particular order.
+    let lines = |name: &str| {
+        let mut lines: Vec<String> = read(name).lines().map(String::from).collect();
+        lines.sort_unstable();
+        lines
+    };
+    assert!(lines("memory.tsv").contains(&String::from("Alpha\tGamma")));
+    assert_eq!(lines("spilled.tsv"), lines("memory.tsv"));
+    // After the metadata, which has the command line.
+    let dot = |name: &str| read(name).split_once('\n').unwrap().1.to_owned();
+    assert_eq!(dot("spilled.dot"), dot("memory.dot"));
+}